@@ -1,13 +1,46 @@
 import React, { type ElementType } from 'react';
 import { jsx } from 'react/jsx-runtime';
+import { invokeCommand } from './rpc';
+import { currentPluginName } from '../state';
+
+type StorageValue = string | number | boolean;
+
+// Values are stored JSON-encoded so numbers and booleans round-trip with their type
+const decodeStorageValue = (raw: string): StorageValue => {
+	try {
+		return JSON.parse(raw) as StorageValue;
+	} catch {
+		return raw;
+	}
+};
 
 export const createLocalStorage = () => {
-	const storage = new Map<string, string>();
+	const extension = () => currentPluginName ?? 'default';
 	return {
-		getItem: async (key: string) => storage.get(key),
-		setItem: async (key: string, value: string) => storage.set(key, value),
-		removeItem: async (key: string) => storage.delete(key),
-		clear: async () => storage.clear()
+		getItem: async <T extends StorageValue>(key: string): Promise<T | undefined> => {
+			const raw = await invokeCommand<string | null>('extension_storage_get', {
+				extension: extension(),
+				key
+			});
+			return raw === null || raw === undefined ? undefined : (decodeStorageValue(raw) as T);
+		},
+		setItem: async (key: string, value: StorageValue) =>
+			invokeCommand<void>('extension_storage_set', {
+				extension: extension(),
+				key,
+				value: JSON.stringify(value)
+			}),
+		removeItem: async (key: string) =>
+			invokeCommand<void>('extension_storage_remove', { extension: extension(), key }),
+		allItems: async (): Promise<Record<string, StorageValue>> => {
+			const items = await invokeCommand<Record<string, string>>('extension_storage_all_items', {
+				extension: extension()
+			});
+			return Object.fromEntries(
+				Object.entries(items).map(([key, raw]) => [key, decodeStorageValue(raw)])
+			);
+		},
+		clear: async () => invokeCommand<void>('extension_storage_clear', { extension: extension() })
 	};
 };
 
@@ -1,6 +1,6 @@
 import { createInterface } from 'readline';
 import { writeLog, writeOutput } from './io';
-import { runPlugin } from './plugin';
+import { runPlugin, runTool } from './plugin';
 import { instances, navigationStack, toasts, browserExtensionState } from './state';
 import { batchedUpdates, updateContainer } from './reconciler';
 import { preferencesStore } from './preferences';
@@ -72,6 +72,15 @@ rl.on('line', (line) => {
 					runPlugin(pluginPath, mode, aiAccessStatus);
 					break;
 				}
+				case 'run-tool': {
+					const { callId, path, input } = command.payload as {
+						callId: string;
+						path: string;
+						input: Record<string, unknown>;
+					};
+					runTool(callId, path, input);
+					break;
+				}
 				case 'get-preferences': {
 					const { pluginName } = command.payload as { pluginName: string };
 					const preferences = preferencesStore.getAllPreferences();
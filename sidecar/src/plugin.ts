@@ -10,6 +10,7 @@ import { environment } from './api/environment';
 import { config } from './config';
 import * as ReactJsxRuntime from 'react/jsx-runtime';
 import { aiContext, setCurrentPlugin } from './state';
+import { invokeCommand } from './api/rpc';
 
 const createPluginRequire =
 	() =>
@@ -33,6 +34,18 @@ const createPluginRequire =
 		return require(moduleName);
 	};
 
+const mockConsole = {
+	log: (...args: unknown[]) => {
+		writeLog('[plugin] log: ' + args.map((arg) => inspect(arg, { depth: null })).join(' '));
+	},
+	warn: (...args: unknown[]) => {
+		writeLog('[plugin] warn: ' + args.map((arg) => inspect(arg, { depth: null })).join(' '));
+	},
+	error: (...args: unknown[]) => {
+		writeLog('[plugin] error: ' + args.map((arg) => inspect(arg, { depth: null })).join(' '));
+	}
+};
+
 export const loadPlugin = (pluginPath: string): string => {
 	try {
 		if (!fs.existsSync(pluginPath)) {
@@ -102,18 +115,6 @@ export const runPlugin = (
 		scriptText
 	);
 
-	const mockConsole = {
-		log: (...args: unknown[]) => {
-			writeLog('[plugin] log: ' + args.map((arg) => inspect(arg, { depth: null })).join(' '));
-		},
-		warn: (...args: unknown[]) => {
-			writeLog('[plugin] warn: ' + args.map((arg) => inspect(arg, { depth: null })).join(' '));
-		},
-		error: (...args: unknown[]) => {
-			writeLog('[plugin] error: ' + args.map((arg) => inspect(arg, { depth: null })).join(' '));
-		}
-	};
-
 	scriptFunction(createPluginRequire(), pluginModule, pluginModule.exports, React, mockConsole);
 
 	const PluginRoot = pluginModule.exports.default;
@@ -149,3 +150,55 @@ export const runPlugin = (
 		});
 	}
 };
+
+/**
+ * Runs an AI tool from an extension's `tools/` directory and reports its
+ * result back with `ai_extension_tool_result`.
+ */
+export const runTool = async (
+	callId: string,
+	toolPath: string,
+	input: Record<string, unknown>
+): Promise<void> => {
+	const report = (result?: unknown, error?: string) =>
+		invokeCommand('ai_extension_tool_result', { callId, result, error }).catch((e) =>
+			writeLog(`Failed to report AI tool result: ${e}`)
+		);
+
+	try {
+		const scriptText = loadPlugin(toolPath);
+		const pluginDir = path.dirname(path.dirname(toolPath));
+		let pluginName = path.basename(pluginDir);
+		const packageJsonPath = path.join(pluginDir, 'package.json');
+		if (fs.existsSync(packageJsonPath)) {
+			const packageJson = JSON.parse(fs.readFileSync(packageJsonPath, 'utf-8'));
+			pluginName = packageJson.name || pluginName;
+			setCurrentPlugin(pluginName, packageJson.preferences || []);
+		}
+		environment.assetsPath = path.join(config.pluginsDir, pluginName, 'assets');
+		environment.extensionName = pluginName;
+
+		const toolModule = {
+			exports: {} as { default?: (input: Record<string, unknown>) => unknown }
+		};
+		const scriptFunction = new Function(
+			'require',
+			'module',
+			'exports',
+			'React',
+			'console',
+			scriptText
+		);
+		scriptFunction(createPluginRequire(), toolModule, toolModule.exports, React, mockConsole);
+
+		const tool = toolModule.exports.default;
+		if (typeof tool !== 'function') {
+			throw new Error('Tool did not export a default function.');
+		}
+		const result = await tool(input);
+		await report(result === undefined ? null : result);
+	} catch (e) {
+		writeLog(`AI tool ${toolPath} failed: ${e}`);
+		await report(undefined, e instanceof Error ? e.message : String(e));
+	}
+};
@@ -32,6 +32,14 @@ class RaycastCurrencyProvider: CurrencyRateProvider {
             rates[code]
         }
 
+        /// Merges USD-based rates cached by the host app, so conversions work offline
+        func seed(rates seeded: [String: Double]) {
+            for (key, value) in seeded where value > 0 {
+                rates[key] = Decimal(value)
+            }
+            rates["USD"] = 1.0
+        }
+
         func updateRates() async {
             guard !isUpdating else { return }
             isUpdating = true
@@ -123,6 +131,13 @@ class RaycastCurrencyProvider: CurrencyRateProvider {
         }
     }
 
+    public func seed(rates: [String: Double]) {
+        let manager = self.rateManager
+        Task {
+            await manager.seed(rates: rates)
+        }
+    }
+
     func rateFor(request: CurrencyRateRequest) -> Decimal? {
         print("💰 Requesting currency rate for \(request)")
         let targetCode = request.currencyCode
@@ -16,6 +16,9 @@ struct SoulverResult: Codable {
 @MainActor
 private var globalCalculator: Calculator?
 
+@MainActor
+private var globalCurrencyProvider: RaycastCurrencyProvider?
+
 @MainActor
 @_cdecl("initialize_soulver")
 public func initialize_soulver(resourcesPath: UnsafePointer<CChar>) {
@@ -38,6 +41,7 @@ public func initialize_soulver(resourcesPath: UnsafePointer<CChar>) {
     customization.currencyRateProvider = currencyProvider
     
     currencyProvider.startUpdating()
+    globalCurrencyProvider = currencyProvider
     
     globalCalculator = Calculator(customization: customization)
     print("✅ Soulver calculator initialized and currency provider has started updating.")
@@ -93,6 +97,22 @@ public func evaluate(expression: UnsafePointer<CChar>) -> UnsafeMutablePointer<C
     return nil
 }
 
+@MainActor
+@_cdecl("set_currency_rates")
+public func set_currency_rates(ratesJson: UnsafePointer<CChar>) {
+    guard let provider = globalCurrencyProvider else {
+        print("Soulver Wrapper: set_currency_rates called before initialize_soulver().")
+        return
+    }
+
+    let data = Data(String(cString: ratesJson).utf8)
+    guard let rates = try? JSONDecoder().decode([String: Double].self, from: data) else {
+        print("❌ Soulver Wrapper: Failed to decode currency rates.")
+        return
+    }
+    provider.seed(rates: rates)
+}
+
 @_cdecl("free_string")
 public func free_string(ptr: UnsafeMutablePointer<CChar>?) {
     free(ptr)
@@ -0,0 +1,98 @@
+//! Bringing Flare's windows to the front when a hotkey or another instance
+//! asks for them, in a way focus stealing prevention accepts.
+//!
+//! On X11 the window is activated with `_NET_ACTIVE_WINDOW` and a fresh
+//! server timestamp, so the window manager treats it as the answer to the
+//! key press it just saw. On Wayland focus needs an xdg_activation_v1 token:
+//! the GlobalShortcuts portal hands one over with each activation and a
+//! launching process leaves one in `XDG_ACTIVATION_TOKEN`. GTK passes it to
+//! the compositor when the window is presented.
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::WebviewWindow;
+
+static PENDING_TOKEN: Mutex<Option<String>> = Mutex::new(None);
+
+/// Keeps an activation token for the next `present`
+pub fn set_activation_token(token: String) {
+    *PENDING_TOKEN.lock().unwrap() = Some(token);
+}
+
+/// Tokens are single use, so the environment one is only taken once
+fn take_token() -> Option<String> {
+    PENDING_TOKEN.lock().unwrap().take().or_else(|| {
+        let token = std::env::var("XDG_ACTIVATION_TOKEN").ok();
+        std::env::remove_var("XDG_ACTIVATION_TOKEN");
+        token.filter(|token| !token.is_empty())
+    })
+}
+
+/// Shows `window` above other windows and gives it keyboard focus
+pub fn present(window: &WebviewWindow) {
+    if let Err(e) = window.show() {
+        tracing::error!(error = %e, "Failed to show window");
+        return;
+    }
+    // Layer surfaces stack above everything and take the keyboard on map
+    if window.label() == "main" && crate::layer_shell::is_active() {
+        return;
+    }
+    let _ = window.set_always_on_top(true);
+
+    let window = window.clone();
+    tauri::async_runtime::spawn(async move {
+        // The window manager ignores activation requests for unmapped windows
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let activated = if std::env::var("WAYLAND_DISPLAY").is_ok() {
+            present_wayland(&window)
+        } else {
+            activate_x11(&window)
+        };
+        if let Err(e) = activated {
+            tracing::debug!(error = %e, "Window activation failed, requesting focus");
+            let _ = window.set_focus();
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn present_wayland(window: &WebviewWindow) -> Result<(), String> {
+    let token = take_token();
+    let target = window.clone();
+    window
+        .run_on_main_thread(move || {
+            use gtk::prelude::GtkWindowExt;
+            let Ok(gtk_window) = target.gtk_window() else {
+                return;
+            };
+            if let Some(token) = token {
+                gtk_window.set_startup_id(&token);
+            }
+            gtk_window.present();
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn present_wayland(_window: &WebviewWindow) -> Result<(), String> {
+    Err("Wayland activation is only supported on Linux".to_string())
+}
+
+/// The X11 id of one of our own windows
+pub(crate) fn x11_window_id(window: &WebviewWindow) -> Result<u32, String> {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+    match window.window_handle().map_err(|e| e.to_string())?.as_raw() {
+        RawWindowHandle::Xlib(handle) => Ok(handle.window as u32),
+        RawWindowHandle::Xcb(handle) => Ok(handle.window.get()),
+        _ => Err("Not an X11 window".to_string()),
+    }
+}
+
+fn activate_x11(window: &WebviewWindow) -> Result<(), String> {
+    let window_id = x11_window_id(window)?;
+    let session = crate::window_management::x11::X11Session::connect()?;
+    let timestamp = session.server_time()?;
+    session.activate(window_id, timestamp)
+}
@@ -0,0 +1,240 @@
+//! Cache for answers to one-shot prompts, so running a quick AI command like
+//! "fix spelling" on the same selection twice doesn't pay for it twice.
+//!
+//! Entries are keyed by a hash of the model, the temperature and the exact
+//! messages sent, which includes the preset's instructions. Chats and
+//! requests that use tools are never cached, since their answers depend on
+//! more than the prompt.
+
+use crate::error::AppError;
+use crate::store::Store;
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+const AI_CACHE_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS ai_response_cache (
+    key TEXT PRIMARY KEY,
+    model TEXT NOT NULL,
+    response TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+)";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseCacheSettings {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: i64,
+}
+
+impl Default for ResponseCacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            ttl_secs: default_ttl_secs(),
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_ttl_secs() -> i64 {
+    24 * 60 * 60
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub entries: u32,
+    pub bytes: u64,
+}
+
+/// The key for a request's `(model, temperature, messages)`
+pub fn cache_key(model: &str, temperature: f64, messages: &[Value]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    hasher.update([0]);
+    // Formatted so 0.7 from the settings and 0.7 from a preset hash the same
+    hasher.update(format!("{:.3}", temperature).as_bytes());
+    hasher.update([0]);
+    hasher.update(
+        serde_json::to_string(messages)
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    hex::encode(hasher.finalize())
+}
+
+pub struct AiCacheManager {
+    store: Store,
+}
+
+impl AiCacheManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "ai_cache.sqlite")?;
+        store.init_table(AI_CACHE_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(AI_CACHE_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    fn get(&self, key: &str, ttl_secs: i64, now: i64) -> Result<Option<String>, AppError> {
+        Ok(self
+            .store
+            .conn()
+            .query_row(
+                "SELECT response FROM ai_response_cache WHERE key = ?1 AND created_at > ?2",
+                params![key, now - ttl_secs],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Also drops entries that have expired
+    fn put(
+        &self,
+        key: &str,
+        model: &str,
+        response: &str,
+        ttl_secs: i64,
+        now: i64,
+    ) -> Result<(), AppError> {
+        self.store.execute(
+            "DELETE FROM ai_response_cache WHERE created_at <= ?",
+            params![now - ttl_secs],
+        )?;
+        self.store.execute(
+            "INSERT INTO ai_response_cache (key, model, response, created_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(key) DO UPDATE SET response = excluded.response, created_at = excluded.created_at",
+            params![key, model, response, now],
+        )?;
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<CacheStats, AppError> {
+        Ok(self.store.conn().query_row(
+            "SELECT COUNT(*), COALESCE(SUM(LENGTH(response)), 0) FROM ai_response_cache",
+            [],
+            |row| {
+                Ok(CacheStats {
+                    entries: row.get(0)?,
+                    bytes: row.get(1)?,
+                })
+            },
+        )?)
+    }
+
+    fn clear(&self) -> Result<(), AppError> {
+        self.store.execute("DELETE FROM ai_response_cache", [])?;
+        Ok(())
+    }
+}
+
+/// A cached answer for `key`, if caching is on and it hasn't expired
+pub fn lookup(app: &AppHandle, settings: &ResponseCacheSettings, key: &str) -> Option<String> {
+    if !settings.enabled {
+        return None;
+    }
+    let manager = app.try_state::<AiCacheManager>()?;
+    match manager.get(key, settings.ttl_secs, Utc::now().timestamp()) {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to read AI response cache");
+            None
+        }
+    }
+}
+
+pub fn store(
+    app: &AppHandle,
+    settings: &ResponseCacheSettings,
+    key: &str,
+    model: &str,
+    response: &str,
+) {
+    if !settings.enabled || response.trim().is_empty() {
+        return;
+    }
+    let Some(manager) = app.try_state::<AiCacheManager>() else {
+        return;
+    };
+    if let Err(e) = manager.put(
+        key,
+        model,
+        response,
+        settings.ttl_secs,
+        Utc::now().timestamp(),
+    ) {
+        tracing::warn!(error = %e, "Failed to write AI response cache");
+    }
+}
+
+#[tauri::command]
+pub fn get_ai_cache_stats(app: AppHandle) -> Result<CacheStats, String> {
+    app.state::<AiCacheManager>()
+        .stats()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_ai_cache(app: AppHandle) -> Result<(), String> {
+    app.state::<AiCacheManager>()
+        .clear()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cache_key() {
+        let messages = vec![json!({"role": "user", "content": "Fix: teh"})];
+        let key = cache_key("openai/gpt-4o-mini", 0.7, &messages);
+        assert_eq!(key, cache_key("openai/gpt-4o-mini", 0.7000001, &messages));
+        assert_ne!(key, cache_key("openai/gpt-4o", 0.7, &messages));
+        assert_ne!(key, cache_key("openai/gpt-4o-mini", 1.0, &messages));
+        let other = vec![json!({"role": "user", "content": "Fix: thier"})];
+        assert_ne!(key, cache_key("openai/gpt-4o-mini", 0.7, &other));
+    }
+
+    #[test]
+    fn test_get_put_and_expiry() {
+        let manager = AiCacheManager::new_for_test().unwrap();
+        manager.put("k", "m", "the", 100, 1_000).unwrap();
+        assert_eq!(
+            manager.get("k", 100, 1_050).unwrap().as_deref(),
+            Some("the")
+        );
+        assert_eq!(manager.get("k", 100, 1_100).unwrap(), None);
+
+        manager.put("k", "m", "the!", 100, 1_060).unwrap();
+        assert_eq!(
+            manager.get("k", 100, 1_100).unwrap().as_deref(),
+            Some("the!")
+        );
+        manager.put("other", "m", "x", 100, 1_200).unwrap();
+        assert_eq!(manager.stats().unwrap().entries, 1);
+
+        manager.clear().unwrap();
+        assert_eq!(
+            manager.stats().unwrap(),
+            CacheStats {
+                entries: 0,
+                bytes: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! Saved AI commands: a prompt template run against the selection or an
+//! argument, optionally through a preset, and optionally on a hotkey. A
+//! command's hotkey is registered with the `HotkeyManager` as
+//! `ai_command:<id>` when the command is saved, and saving fails if the
+//! hotkey is already taken by any other binding.
+
+use crate::error::AppError;
+use crate::hotkey_manager::types::HotkeyBinding;
+use crate::hotkey_manager::{HotkeyManager, AI_COMMAND_PREFIX};
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+const AI_COMMANDS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS ai_commands (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    icon TEXT,
+    preset_id TEXT,
+    model TEXT,
+    creativity TEXT,
+    hotkey TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+)";
+
+pub const AI_COMMAND_TRIGGERED_EVENT: &str = "ai-command-triggered";
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AiCommand {
+    pub id: String,
+    pub name: String,
+    /// Template; `{selection}` and `{argument}` are filled in by the frontend
+    pub prompt: String,
+    pub icon: Option<String>,
+    pub preset_id: Option<String>,
+    pub model: Option<String>,
+    pub creativity: Option<String>,
+    pub hotkey: Option<HotkeyBinding>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Storable for AiCommand {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let hotkey: Option<String> = row.get(7)?;
+        let created_at: i64 = row.get(8)?;
+        let updated_at: i64 = row.get(9)?;
+        Ok(AiCommand {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            prompt: row.get(2)?,
+            icon: row.get(3)?,
+            preset_id: row.get(4)?,
+            model: row.get(5)?,
+            creativity: row.get(6)?,
+            hotkey: hotkey.and_then(|json| serde_json::from_str(&json).ok()),
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_default(),
+            updated_at: DateTime::from_timestamp(updated_at, 0).unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AiCommandInput {
+    pub name: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub creativity: Option<String>,
+    #[serde(default)]
+    pub hotkey: Option<HotkeyBinding>,
+}
+
+/// The saved command, plus why its hotkey didn't register if it didn't
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedAiCommand {
+    pub command: AiCommand,
+    pub hotkey_error: Option<String>,
+}
+
+fn validate_command(input: &AiCommandInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("AI command needs a name".to_string());
+    }
+    if input.prompt.trim().is_empty() {
+        return Err("AI command needs a prompt".to_string());
+    }
+    Ok(())
+}
+
+fn hotkey_id(command_id: &str) -> String {
+    format!("{}{}", AI_COMMAND_PREFIX, command_id)
+}
+
+fn hotkey_json(hotkey: Option<&HotkeyBinding>) -> Result<Option<String>, AppError> {
+    hotkey
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| AppError::Serialization(e.to_string()))
+}
+
+const SELECT_COMMANDS: &str = "SELECT id, name, prompt, icon, preset_id, model, creativity, hotkey, created_at, updated_at FROM ai_commands";
+
+pub struct AiCommandManager {
+    store: Store,
+}
+
+impl AiCommandManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "ai_commands.sqlite")?;
+        store.init_table(AI_COMMANDS_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(AI_COMMANDS_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    pub(crate) fn list(&self) -> Result<Vec<AiCommand>, AppError> {
+        self.store.query(
+            &format!("{} ORDER BY name COLLATE NOCASE ASC", SELECT_COMMANDS),
+            [],
+        )
+    }
+
+    fn find(&self, id: &str) -> Result<Option<AiCommand>, AppError> {
+        self.store
+            .query_row(&format!("{} WHERE id = ?", SELECT_COMMANDS), params![id])
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Result<AiCommand, AppError> {
+        self.find(id)?
+            .ok_or_else(|| AppError::Rusqlite(rusqlite::Error::QueryReturnedNoRows))
+    }
+
+    fn create(&self, id: &str, input: &AiCommandInput) -> Result<AiCommand, AppError> {
+        self.store.execute(
+            "INSERT INTO ai_commands (id, name, prompt, icon, preset_id, model, creativity, hotkey, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
+            params![
+                id,
+                input.name.trim(),
+                input.prompt,
+                input.icon,
+                input.preset_id,
+                input.model,
+                input.creativity,
+                hotkey_json(input.hotkey.as_ref())?,
+                Utc::now().timestamp()
+            ],
+        )?;
+        self.get(id)
+    }
+
+    fn update(&self, id: &str, input: &AiCommandInput) -> Result<AiCommand, AppError> {
+        let changed = self.store.execute(
+            "UPDATE ai_commands SET name = ?1, prompt = ?2, icon = ?3, preset_id = ?4, model = ?5, creativity = ?6, hotkey = ?7, updated_at = ?8
+             WHERE id = ?9",
+            params![
+                input.name.trim(),
+                input.prompt,
+                input.icon,
+                input.preset_id,
+                input.model,
+                input.creativity,
+                hotkey_json(input.hotkey.as_ref())?,
+                Utc::now().timestamp(),
+                id
+            ],
+        )?;
+        if changed == 0 {
+            return Err(AppError::Rusqlite(rusqlite::Error::QueryReturnedNoRows));
+        }
+        self.get(id)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.store
+            .execute("DELETE FROM ai_commands WHERE id = ?", params![id])?;
+        Ok(())
+    }
+}
+
+/// Rejects the hotkey if another binding would fire with it
+fn check_hotkey(app: &AppHandle, id: &str, hotkey: Option<&HotkeyBinding>) -> Result<(), String> {
+    let (Some(hotkey), Some(manager)) = (hotkey, app.try_state::<HotkeyManager>()) else {
+        return Ok(());
+    };
+    match manager.find_conflict(&hotkey_id(id), hotkey) {
+        Some(other) => Err(format!("Hotkey is already used by '{}'", other)),
+        None => Ok(()),
+    }
+}
+
+/// Brings the command's hotkey registration in line with what was saved
+fn sync_hotkey(app: &AppHandle, id: &str, hotkey: Option<&HotkeyBinding>) -> Option<String> {
+    let manager = app.try_state::<HotkeyManager>()?;
+    let binding_id = hotkey_id(id);
+    let result = match hotkey {
+        Some(hotkey) => manager.set(binding_id, hotkey.clone()),
+        None if manager.list().contains_key(&binding_id) => manager.remove(&binding_id),
+        None => Ok(()),
+    };
+    result.err().inspect(|e| {
+        tracing::warn!(command_id = %id, error = %e, "Failed to register AI command hotkey");
+    })
+}
+
+/// Shows the launcher and asks the frontend to run the command
+pub fn run_from_hotkey(app: &AppHandle, id: &str) {
+    let command = match app.state::<AiCommandManager>().get(id) {
+        Ok(command) => command,
+        Err(e) => {
+            tracing::error!(command_id = %id, error = %e, "AI command for hotkey not found");
+            return;
+        }
+    };
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    if let Err(e) = app.emit(AI_COMMAND_TRIGGERED_EVENT, command) {
+        tracing::error!(command_id = %id, error = %e, "Failed to emit AI command event");
+    }
+}
+
+#[tauri::command]
+pub fn list_ai_commands(app: AppHandle) -> Result<Vec<AiCommand>, String> {
+    app.state::<AiCommandManager>()
+        .list()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_ai_command(app: AppHandle, id: String) -> Result<Option<AiCommand>, String> {
+    app.state::<AiCommandManager>()
+        .find(&id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_ai_command(
+    app: AppHandle,
+    command: AiCommandInput,
+) -> Result<SavedAiCommand, String> {
+    validate_command(&command)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    check_hotkey(&app, &id, command.hotkey.as_ref())?;
+
+    let saved = app
+        .state::<AiCommandManager>()
+        .create(&id, &command)
+        .map_err(|e| e.to_string())?;
+    let hotkey_error = sync_hotkey(&app, &id, saved.hotkey.as_ref());
+    Ok(SavedAiCommand {
+        command: saved,
+        hotkey_error,
+    })
+}
+
+#[tauri::command]
+pub fn update_ai_command(
+    app: AppHandle,
+    id: String,
+    command: AiCommandInput,
+) -> Result<SavedAiCommand, String> {
+    validate_command(&command)?;
+    check_hotkey(&app, &id, command.hotkey.as_ref())?;
+
+    let saved = app
+        .state::<AiCommandManager>()
+        .update(&id, &command)
+        .map_err(|e| e.to_string())?;
+    let hotkey_error = sync_hotkey(&app, &id, saved.hotkey.as_ref());
+    Ok(SavedAiCommand {
+        command: saved,
+        hotkey_error,
+    })
+}
+
+#[tauri::command]
+pub fn delete_ai_command(app: AppHandle, id: String) -> Result<(), String> {
+    app.state::<AiCommandManager>()
+        .delete(&id)
+        .map_err(|e| e.to_string())?;
+    if let Some(e) = sync_hotkey(&app, &id, None) {
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey_manager::types::{KeyCombo, Modifier};
+
+    fn input(name: &str, hotkey: Option<HotkeyBinding>) -> AiCommandInput {
+        AiCommandInput {
+            name: name.to_string(),
+            prompt: "Fix the grammar of {selection}".to_string(),
+            icon: None,
+            preset_id: None,
+            model: None,
+            creativity: None,
+            hotkey,
+        }
+    }
+
+    #[test]
+    fn test_crud_keeps_hotkey() {
+        let manager = AiCommandManager::new_for_test().unwrap();
+        let hotkey = HotkeyBinding::Combo {
+            combo: KeyCombo::new(vec![Modifier::Super, Modifier::Shift], "g"),
+        };
+        let created = manager
+            .create("grammar", &input("Fix Grammar", Some(hotkey.clone())))
+            .unwrap();
+        assert_eq!(created.hotkey, Some(hotkey));
+
+        let updated = manager
+            .update("grammar", &input("Fix Spelling", None))
+            .unwrap();
+        assert_eq!(updated.name, "Fix Spelling");
+        assert_eq!(updated.hotkey, None);
+        assert!(manager.update("missing", &input("x", None)).is_err());
+
+        manager.create("b", &input("Another", None)).unwrap();
+        let names: Vec<String> = manager
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, vec!["Another", "Fix Spelling"]);
+
+        manager.delete("grammar").unwrap();
+        assert!(manager.find("grammar").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_validate_command() {
+        assert!(validate_command(&input("Summarize", None)).is_ok());
+        assert!(validate_command(&input(" ", None)).is_err());
+        let mut empty_prompt = input("Summarize", None);
+        empty_prompt.prompt = "  ".to_string();
+        assert!(validate_command(&empty_prompt).is_err());
+    }
+}
@@ -0,0 +1,789 @@
+//! Retrieval over local notes and files. Chosen directories, or the notes
+//! vault, are split into chunks that are embedded with a local Ollama model
+//! or OpenAI and kept in a local SQLite index, which the `retrieve` AI tool
+//! and the search command look things up in.
+//!
+//! Vectors are stored as little-endian `f32` blobs and compared by brute
+//! force, which is plenty fast for a few thousand notes. Files are only
+//! re-embedded when they change or when the embedding model does.
+
+use super::get_ai_settings;
+use crate::error::AppError;
+use crate::integrations::notes::NotesSettings;
+use crate::secrets;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use walkdir::{DirEntry, WalkDir};
+
+pub const OPENAI_API_KEY: &str = "openai_api_key";
+const OLLAMA_DEFAULT_URL: &str = "http://localhost:11434";
+const OPENAI_DEFAULT_URL: &str = "https://api.openai.com/v1";
+const OLLAMA_DEFAULT_MODEL: &str = "nomic-embed-text";
+const OPENAI_DEFAULT_MODEL: &str = "text-embedding-3-small";
+
+const TEXT_EXTENSIONS: &[&str] = &[
+    "md", "markdown", "txt", "org", "rst", "adoc", "tex", "csv", "json", "yaml", "yml", "toml",
+    "html",
+];
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const MAX_CHUNK_CHARS: usize = 1500;
+const EMBED_BATCH: usize = 32;
+const DEFAULT_RETRIEVE_LIMIT: usize = 5;
+
+const SOURCES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS embedding_sources (
+    id TEXT PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    created_at INTEGER NOT NULL,
+    indexed_at INTEGER
+)";
+
+const CHUNKS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS embedding_chunks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    source_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    model TEXT NOT NULL,
+    vector BLOB NOT NULL,
+    modified_at INTEGER NOT NULL
+)";
+
+const CHUNKS_PATH_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS idx_embedding_chunks_path ON embedding_chunks(source_id, path)";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum EmbeddingProvider {
+    #[default]
+    Ollama,
+    OpenAi,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingSettings {
+    #[serde(default)]
+    pub provider: EmbeddingProvider,
+    /// Defaults to `nomic-embed-text` on Ollama and `text-embedding-3-small`
+    /// on OpenAI
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Server root for Ollama, API root for OpenAI-compatible services
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+impl EmbeddingSettings {
+    fn model(&self) -> &str {
+        match self.model.as_deref().map(str::trim) {
+            Some(model) if !model.is_empty() => model,
+            _ => match self.provider {
+                EmbeddingProvider::Ollama => OLLAMA_DEFAULT_MODEL,
+                EmbeddingProvider::OpenAi => OPENAI_DEFAULT_MODEL,
+            },
+        }
+    }
+
+    fn base_url(&self) -> &str {
+        match self.base_url.as_deref().map(str::trim) {
+            Some(url) if !url.is_empty() => url.trim_end_matches('/'),
+            _ => match self.provider {
+                EmbeddingProvider::Ollama => OLLAMA_DEFAULT_URL,
+                EmbeddingProvider::OpenAi => OPENAI_DEFAULT_URL,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingSource {
+    pub id: String,
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+    pub indexed_at: Option<DateTime<Utc>>,
+    pub chunk_count: u32,
+}
+
+impl Storable for EmbeddingSource {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let created_at: i64 = row.get(2)?;
+        let indexed_at: Option<i64> = row.get(3)?;
+        Ok(EmbeddingSource {
+            id: row.get(0)?,
+            path: row.get(1)?,
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_default(),
+            indexed_at: indexed_at.and_then(|at| DateTime::from_timestamp(at, 0)),
+            chunk_count: row.get(4)?,
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetrievedChunk {
+    pub path: String,
+    pub content: String,
+    /// Cosine similarity to the query
+    pub score: f32,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexReport {
+    pub files_indexed: usize,
+    pub files_unchanged: usize,
+    pub files_removed: usize,
+    pub chunks: usize,
+    pub errors: Vec<String>,
+}
+
+struct StoredChunk {
+    path: String,
+    content: String,
+    vector: Vec<f32>,
+}
+
+impl Storable for StoredChunk {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let vector: Vec<u8> = row.get(2)?;
+        Ok(StoredChunk {
+            path: row.get(0)?,
+            content: row.get(1)?,
+            vector: decode_vector(&vector),
+        })
+    }
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Packs paragraphs into chunks of up to `MAX_CHUNK_CHARS`. Markdown
+/// headings start a new chunk so chunks follow the sections of a note.
+fn chunk_text(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut flush = |current: &mut String| {
+        if !current.trim().is_empty() {
+            chunks.push(current.trim().to_string());
+        }
+        current.clear();
+    };
+
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        let length = paragraph.chars().count();
+        if paragraph.starts_with('#') || current.chars().count() + length + 2 > MAX_CHUNK_CHARS {
+            flush(&mut current);
+        }
+        if length > MAX_CHUNK_CHARS {
+            let chars: Vec<char> = paragraph.chars().collect();
+            for piece in chars.chunks(MAX_CHUNK_CHARS) {
+                current.extend(piece);
+                flush(&mut current);
+            }
+            continue;
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(paragraph);
+    }
+    flush(&mut current);
+    chunks
+}
+
+fn parse_ollama_embeddings(body: &Value) -> Result<Vec<Vec<f32>>, String> {
+    body.get("embeddings")
+        .and_then(Value::as_array)
+        .ok_or("Response has no embeddings")?
+        .iter()
+        .map(parse_vector)
+        .collect()
+}
+
+/// OpenAI tags each embedding with the index of its input
+fn parse_openai_embeddings(body: &Value) -> Result<Vec<Vec<f32>>, String> {
+    let mut data: Vec<&Value> = body
+        .get("data")
+        .and_then(Value::as_array)
+        .ok_or("Response has no embeddings")?
+        .iter()
+        .collect();
+    data.sort_by_key(|item| item.get("index").and_then(Value::as_u64).unwrap_or(0));
+    data.into_iter()
+        .map(|item| parse_vector(item.get("embedding").unwrap_or(&Value::Null)))
+        .collect()
+}
+
+fn parse_vector(value: &Value) -> Result<Vec<f32>, String> {
+    value
+        .as_array()
+        .ok_or("Embedding is not a list")?
+        .iter()
+        .map(|v| {
+            v.as_f64()
+                .map(|v| v as f32)
+                .ok_or("Embedding has a non-number")
+        })
+        .collect::<Result<_, _>>()
+        .map_err(String::from)
+}
+
+async fn embed(settings: &EmbeddingSettings, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(120))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let body = json!({ "model": settings.model(), "input": inputs });
+
+    let request = match settings.provider {
+        EmbeddingProvider::Ollama => client.post(format!("{}/api/embed", settings.base_url())),
+        EmbeddingProvider::OpenAi => {
+            let key = secrets::get(secrets::AI, OPENAI_API_KEY)
+                .map_err(|e| e.to_string())?
+                .ok_or("OpenAI API key is not set")?;
+            client
+                .post(format!("{}/embeddings", settings.base_url()))
+                .bearer_auth(key)
+        }
+    };
+    let response = request
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        let error_body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".into());
+        return Err(format!("Embedding API Error: {}", error_body));
+    }
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    let vectors = match settings.provider {
+        EmbeddingProvider::Ollama => parse_ollama_embeddings(&body)?,
+        EmbeddingProvider::OpenAi => parse_openai_embeddings(&body)?,
+    };
+    if vectors.len() != inputs.len() {
+        return Err(format!(
+            "Expected {} embeddings, got {}",
+            inputs.len(),
+            vectors.len()
+        ));
+    }
+    Ok(vectors)
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.')
+}
+
+fn is_text_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| TEXT_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Text files under `root` with their modification times
+fn collect_files(root: &Path) -> Vec<(PathBuf, i64)> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| !is_hidden(entry))
+        .flatten()
+        .filter(|entry| entry.file_type().is_file() && is_text_file(entry.path()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            if metadata.len() > MAX_FILE_BYTES {
+                return None;
+            }
+            let modified = metadata
+                .modified()
+                .ok()?
+                .duration_since(UNIX_EPOCH)
+                .ok()?
+                .as_secs() as i64;
+            Some((entry.into_path(), modified))
+        })
+        .collect()
+}
+
+pub struct EmbeddingManager {
+    store: Store,
+    indexing: AtomicBool,
+}
+
+const SELECT_SOURCES: &str = "SELECT s.id, s.path, s.created_at, s.indexed_at,
+    (SELECT COUNT(*) FROM embedding_chunks c WHERE c.source_id = s.id)
+    FROM embedding_sources s";
+
+impl EmbeddingManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "embeddings.sqlite")?;
+        Self::init(store)
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        Self::init(Store::new_in_memory()?)
+    }
+
+    fn init(store: Store) -> Result<Self, AppError> {
+        store.init_table(SOURCES_SCHEMA)?;
+        store.init_table(CHUNKS_SCHEMA)?;
+        store.init_table(CHUNKS_PATH_INDEX)?;
+        Ok(Self {
+            store,
+            indexing: AtomicBool::new(false),
+        })
+    }
+
+    fn sources(&self) -> Result<Vec<EmbeddingSource>, AppError> {
+        self.store
+            .query(&format!("{} ORDER BY s.path ASC", SELECT_SOURCES), [])
+    }
+
+    fn add_source(&self, path: &str) -> Result<EmbeddingSource, AppError> {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.store.execute(
+            "INSERT INTO embedding_sources (id, path, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(path) DO NOTHING",
+            params![id, path, Utc::now().timestamp()],
+        )?;
+        self.store
+            .query_row(
+                &format!("{} WHERE s.path = ?", SELECT_SOURCES),
+                params![path],
+            )?
+            .ok_or_else(|| AppError::Rusqlite(rusqlite::Error::QueryReturnedNoRows))
+    }
+
+    fn remove_source(&self, id: &str) -> Result<(), AppError> {
+        let mut conn = self.store.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM embedding_chunks WHERE source_id = ?",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM embedding_sources WHERE id = ?", params![id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Modification times of the files indexed with `model`
+    fn indexed_files(
+        &self,
+        source_id: &str,
+        model: &str,
+    ) -> Result<HashMap<String, i64>, AppError> {
+        let conn = self.store.conn();
+        let mut stmt = conn.prepare(
+            "SELECT path, MAX(modified_at) FROM embedding_chunks
+             WHERE source_id = ?1 AND model = ?2 GROUP BY path",
+        )?;
+        let files = stmt
+            .query_map(params![source_id, model], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(files)
+    }
+
+    fn replace_file(
+        &self,
+        source_id: &str,
+        path: &str,
+        modified_at: i64,
+        model: &str,
+        chunks: &[(String, Vec<f32>)],
+    ) -> Result<(), AppError> {
+        let mut conn = self.store.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM embedding_chunks WHERE source_id = ?1 AND path = ?2",
+            params![source_id, path],
+        )?;
+        for (index, (content, vector)) in chunks.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO embedding_chunks (source_id, path, chunk_index, content, model, vector, modified_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    source_id,
+                    path,
+                    index as i64,
+                    content,
+                    model,
+                    encode_vector(vector),
+                    modified_at
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Drops chunks of files that are gone or were embedded with another model
+    fn prune(
+        &self,
+        source_id: &str,
+        model: &str,
+        present: &HashSet<String>,
+    ) -> Result<usize, AppError> {
+        let conn = self.store.conn();
+        let mut stmt =
+            conn.prepare("SELECT DISTINCT path FROM embedding_chunks WHERE source_id = ?")?;
+        let indexed: Vec<String> = stmt
+            .query_map(params![source_id], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        let mut removed = 0;
+        for path in indexed.iter().filter(|path| !present.contains(*path)) {
+            conn.execute(
+                "DELETE FROM embedding_chunks WHERE source_id = ?1 AND path = ?2",
+                params![source_id, path],
+            )?;
+            removed += 1;
+        }
+        conn.execute(
+            "DELETE FROM embedding_chunks WHERE source_id = ?1 AND model != ?2",
+            params![source_id, model],
+        )?;
+        Ok(removed)
+    }
+
+    fn mark_indexed(&self, source_id: &str) -> Result<(), AppError> {
+        self.store.execute(
+            "UPDATE embedding_sources SET indexed_at = ?1 WHERE id = ?2",
+            params![Utc::now().timestamp(), source_id],
+        )?;
+        Ok(())
+    }
+
+    fn search(
+        &self,
+        query: &[f32],
+        model: &str,
+        limit: usize,
+    ) -> Result<Vec<RetrievedChunk>, AppError> {
+        let chunks: Vec<StoredChunk> = self.store.query(
+            "SELECT path, content, vector FROM embedding_chunks WHERE model = ?",
+            params![model],
+        )?;
+        let mut scored: Vec<RetrievedChunk> = chunks
+            .into_iter()
+            .map(|chunk| RetrievedChunk {
+                score: cosine_similarity(query, &chunk.vector),
+                path: chunk.path,
+                content: chunk.content,
+            })
+            .collect();
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+}
+
+async fn index_file(
+    manager: &EmbeddingManager,
+    settings: &EmbeddingSettings,
+    source_id: &str,
+    path: &Path,
+    modified_at: i64,
+) -> Result<usize, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let pieces = chunk_text(&text);
+    let mut chunks = Vec::with_capacity(pieces.len());
+    for batch in pieces.chunks(EMBED_BATCH) {
+        let vectors = embed(settings, batch).await?;
+        chunks.extend(batch.iter().cloned().zip(vectors));
+    }
+    manager
+        .replace_file(
+            source_id,
+            &path.to_string_lossy(),
+            modified_at,
+            settings.model(),
+            &chunks,
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(chunks.len())
+}
+
+async fn index_source(
+    manager: &EmbeddingManager,
+    settings: &EmbeddingSettings,
+    source: &EmbeddingSource,
+    report: &mut IndexReport,
+) -> Result<(), String> {
+    let root = PathBuf::from(&source.path);
+    if !root.is_dir() {
+        return Err(format!("{} does not exist", source.path));
+    }
+    let files = tauri::async_runtime::spawn_blocking(move || collect_files(&root))
+        .await
+        .map_err(|e| e.to_string())?;
+    let indexed = manager
+        .indexed_files(&source.id, settings.model())
+        .map_err(|e| e.to_string())?;
+
+    let mut present = HashSet::new();
+    for (path, modified_at) in files {
+        let key = path.to_string_lossy().to_string();
+        let unchanged = indexed.get(&key).is_some_and(|at| *at >= modified_at);
+        present.insert(key);
+        if unchanged {
+            report.files_unchanged += 1;
+            continue;
+        }
+        match index_file(manager, settings, &source.id, &path, modified_at).await {
+            Ok(chunks) => {
+                report.files_indexed += 1;
+                report.chunks += chunks;
+            }
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to embed file");
+                report.errors.push(e);
+            }
+        }
+    }
+    report.files_removed += manager
+        .prune(&source.id, settings.model(), &present)
+        .map_err(|e| e.to_string())?;
+    manager.mark_indexed(&source.id).map_err(|e| e.to_string())
+}
+
+/// Chunks most similar to `query`, for the `retrieve` tool
+pub async fn retrieve(
+    app: &AppHandle,
+    settings: &EmbeddingSettings,
+    query: &str,
+    limit: Option<usize>,
+) -> Result<Vec<RetrievedChunk>, String> {
+    let vector = embed(settings, &[query.to_string()])
+        .await?
+        .pop()
+        .ok_or("No embedding for the query")?;
+    app.state::<EmbeddingManager>()
+        .search(
+            &vector,
+            settings.model(),
+            limit.unwrap_or(DEFAULT_RETRIEVE_LIMIT),
+        )
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_embedding_sources(app: AppHandle) -> Result<Vec<EmbeddingSource>, String> {
+    app.state::<EmbeddingManager>()
+        .sources()
+        .map_err(|e| e.to_string())
+}
+
+/// Adds the configured notes vault when `path` is `None`
+#[tauri::command]
+pub fn add_embedding_source(
+    app: AppHandle,
+    path: Option<String>,
+) -> Result<EmbeddingSource, String> {
+    let root = match path {
+        Some(path) => PathBuf::from(path.trim()),
+        None => NotesSettings::load(&app)
+            .map_err(|e| e.to_string())?
+            .vault_root()?,
+    };
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", root.display(), e))?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a folder", root.display()));
+    }
+    app.state::<EmbeddingManager>()
+        .add_source(&root.to_string_lossy())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_embedding_source(app: AppHandle, id: String) -> Result<(), String> {
+    app.state::<EmbeddingManager>()
+        .remove_source(&id)
+        .map_err(|e| e.to_string())
+}
+
+/// Brings every source up to date; only one run happens at a time
+#[tauri::command]
+pub async fn index_embeddings(app: AppHandle) -> Result<IndexReport, String> {
+    let settings = get_ai_settings(app.clone())?.embeddings;
+    let manager = app.state::<EmbeddingManager>();
+    if manager.indexing.swap(true, Ordering::SeqCst) {
+        return Err("Indexing is already running".to_string());
+    }
+
+    let mut report = IndexReport::default();
+    let result = async {
+        for source in manager.sources().map_err(|e| e.to_string())? {
+            if let Err(e) = index_source(&manager, &settings, &source, &mut report).await {
+                tracing::warn!(source = %source.path, error = %e, "Failed to index source");
+                report.errors.push(e);
+            }
+        }
+        Ok::<(), String>(())
+    }
+    .await;
+    manager.indexing.store(false, Ordering::SeqCst);
+    result?;
+
+    tracing::info!(
+        indexed = report.files_indexed,
+        unchanged = report.files_unchanged,
+        removed = report.files_removed,
+        "Embedding index updated"
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn search_embeddings(
+    app: AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<RetrievedChunk>, String> {
+    let settings = get_ai_settings(app.clone())?.embeddings;
+    retrieve(&app, &settings, &query, limit).await
+}
+
+#[tauri::command]
+pub fn set_embeddings_api_key(key: String) -> Result<(), String> {
+    secrets::set(secrets::AI, OPENAI_API_KEY, &key).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_embeddings_api_key() -> Result<(), String> {
+    secrets::delete(secrets::AI, OPENAI_API_KEY).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_round_trip_and_similarity() {
+        let vector = vec![0.5, -1.25, 3.0];
+        assert_eq!(decode_vector(&encode_vector(&vector)), vector);
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 2.0]), 0.0);
+    }
+
+    #[test]
+    fn test_chunk_text() {
+        let text = "Intro line\n\nMore intro\n\n# Section\n\nBody\n\n\n\n";
+        assert_eq!(
+            chunk_text(text),
+            vec!["Intro line\n\nMore intro", "# Section\n\nBody"]
+        );
+
+        let long = "x".repeat(MAX_CHUNK_CHARS * 2 + 10);
+        let chunks = chunk_text(&format!("a\n\n{}", long));
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0], "a");
+        assert!(chunks
+            .iter()
+            .all(|chunk| chunk.chars().count() <= MAX_CHUNK_CHARS));
+    }
+
+    #[test]
+    fn test_parse_embeddings() {
+        let ollama = json!({ "embeddings": [[0.1, 0.2], [0.3, 0.4]] });
+        assert_eq!(
+            parse_ollama_embeddings(&ollama).unwrap(),
+            vec![vec![0.1, 0.2], vec![0.3, 0.4]]
+        );
+        let openai = json!({ "data": [
+            { "index": 1, "embedding": [2.0] },
+            { "index": 0, "embedding": [1.0] }
+        ] });
+        assert_eq!(
+            parse_openai_embeddings(&openai).unwrap(),
+            vec![vec![1.0], vec![2.0]]
+        );
+        assert!(parse_ollama_embeddings(&json!({ "embeddings": [["x"]] })).is_err());
+    }
+
+    #[test]
+    fn test_index_prune_and_search() {
+        let manager = EmbeddingManager::new_for_test().unwrap();
+        let source = manager.add_source("/notes").unwrap();
+        assert_eq!(manager.add_source("/notes").unwrap().id, source.id);
+
+        let chunks = |text: &str, vector: Vec<f32>| vec![(text.to_string(), vector)];
+        manager
+            .replace_file(
+                &source.id,
+                "/notes/a.md",
+                10,
+                "m",
+                &chunks("apples", vec![1.0, 0.0]),
+            )
+            .unwrap();
+        manager
+            .replace_file(
+                &source.id,
+                "/notes/b.md",
+                20,
+                "m",
+                &chunks("bananas", vec![0.0, 1.0]),
+            )
+            .unwrap();
+        assert_eq!(
+            manager.indexed_files(&source.id, "m").unwrap()["/notes/b.md"],
+            20
+        );
+        assert_eq!(manager.sources().unwrap()[0].chunk_count, 2);
+
+        let results = manager.search(&[0.9, 0.1], "m", 1).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].content, "apples");
+
+        let present = HashSet::from(["/notes/a.md".to_string()]);
+        assert_eq!(manager.prune(&source.id, "m", &present).unwrap(), 1);
+        assert_eq!(manager.search(&[0.0, 1.0], "m", 5).unwrap().len(), 1);
+
+        manager.remove_source(&source.id).unwrap();
+        assert!(manager.sources().unwrap().is_empty());
+        assert!(manager.search(&[1.0, 0.0], "m", 5).unwrap().is_empty());
+    }
+}
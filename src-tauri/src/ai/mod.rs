@@ -1,6 +1,15 @@
+pub mod cache;
+pub mod commands;
+pub mod embeddings;
+pub mod ollama;
+pub mod presets;
+pub mod sse;
+pub mod tokens;
+pub mod tools;
+
 use crate::error::AppError;
+use crate::secrets;
 use crate::store::{Storable, Store};
-use futures_util::StreamExt;
 use once_cell::sync::Lazy;
 use rusqlite::{params, Result as RusqliteResult};
 use serde::{Deserialize, Serialize};
@@ -10,8 +19,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Emitter, Manager, State};
 
-const AI_KEYRING_SERVICE: &str = "dev.byteatatime.flare.ai";
-const AI_KEYRING_USERNAME: &str = "openrouter_api_key";
+const AI_API_KEY: &str = "openrouter_api_key";
 const AI_USAGE_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS ai_generations (
     id TEXT PRIMARY KEY,
     created INTEGER NOT NULL,
@@ -37,6 +45,16 @@ const AI_CONVERSATIONS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS ai_conversatio
 pub struct AskOptions {
     pub model: Option<String>,
     pub creativity: Option<String>,
+    /// A preset to start from; the model and creativity above override it
+    pub preset_id: Option<String>,
+    /// Picks the context's default preset when `preset_id` is unset
+    pub context: Option<presets::PresetContext>,
+    /// Earlier turns of the chat, oldest first; trimmed to fit the model
+    #[serde(default)]
+    pub history: Vec<Message>,
+    /// Skips the response cache lookup; the new answer is still cached
+    #[serde(default)]
+    pub bypass_cache: bool,
 }
 
 #[derive(Serialize, Clone)]
@@ -51,6 +69,10 @@ pub struct StreamChunk {
 pub struct StreamEnd {
     request_id: String,
     full_text: String,
+    /// Sources the answer's `[n]` markers refer to, when it searched the web
+    citations: Vec<tools::Citation>,
+    /// The answer came from the response cache
+    cached: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -186,6 +208,14 @@ pub struct AiSettings {
     #[serde(default = "default_temperature")]
     temperature: f64,
     model_associations: HashMap<String, String>,
+    #[serde(default)]
+    web_search: tools::WebSearchSettings,
+    #[serde(default)]
+    embeddings: embeddings::EmbeddingSettings,
+    #[serde(default)]
+    response_cache: cache::ResponseCacheSettings,
+    #[serde(default)]
+    ollama: ollama::OllamaOptions,
 }
 
 impl Default for AiSettings {
@@ -196,6 +226,10 @@ impl Default for AiSettings {
             base_url: None,
             temperature: default_temperature(),
             model_associations: HashMap::new(),
+            web_search: tools::WebSearchSettings::default(),
+            embeddings: embeddings::EmbeddingSettings::default(),
+            response_cache: cache::ResponseCacheSettings::default(),
+            ollama: ollama::OllamaOptions::default(),
         }
     }
 }
@@ -261,6 +295,10 @@ pub fn set_ai_settings(app: tauri::AppHandle, settings: AiSettings) -> Result<()
         base_url: settings.base_url,
         temperature: settings.temperature,
         model_associations: HashMap::new(),
+        web_search: settings.web_search,
+        embeddings: settings.embeddings,
+        response_cache: settings.response_cache,
+        ollama: settings.ollama,
     };
 
     for (key, value) in settings.model_associations {
@@ -276,31 +314,31 @@ pub fn set_ai_settings(app: tauri::AppHandle, settings: AiSettings) -> Result<()
     write_settings(&path, &settings_to_save)
 }
 
-fn get_keyring_entry() -> Result<keyring::Entry, AppError> {
-    keyring::Entry::new(AI_KEYRING_SERVICE, AI_KEYRING_USERNAME).map_err(AppError::from)
+/// The provider's id for a model key like `OpenAI_GPT4o-mini`
+fn resolve_model_id(settings: &AiSettings, model_key: &str) -> String {
+    settings
+        .model_associations
+        .get(model_key)
+        .cloned()
+        .unwrap_or_else(|| match settings.provider {
+            AiProvider::OpenRouter => "mistralai/mistral-7b-instruct:free".to_string(),
+            AiProvider::Ollama => "llama3".to_string(),
+        })
 }
 
 #[tauri::command]
 pub fn set_ai_api_key(key: String) -> Result<(), String> {
-    get_keyring_entry()
-        .and_then(|entry| entry.set_password(&key).map_err(AppError::from))
-        .map_err(|e| e.to_string())
+    secrets::set(secrets::AI, AI_API_KEY, &key).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn is_ai_api_key_set() -> Result<bool, String> {
-    match get_keyring_entry().and_then(|entry| entry.get_password().map_err(AppError::from)) {
-        Ok(_) => Ok(true),
-        Err(AppError::Keyring(keyring::Error::NoEntry)) => Ok(false),
-        Err(e) => Err(e.to_string()),
-    }
+    secrets::exists(secrets::AI, AI_API_KEY).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn clear_ai_api_key() -> Result<(), String> {
-    get_keyring_entry()
-        .and_then(|entry| entry.delete_credential().map_err(AppError::from))
-        .map_err(|e| e.to_string())
+    secrets::delete(secrets::AI, AI_API_KEY).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -589,6 +627,36 @@ pub fn delete_conversation(app_handle: AppHandle, id: String) -> Result<(), Stri
         .map_err(|e| e.to_string())
 }
 
+/// Sends an answer that's already complete as a single chunk
+fn emit_answer(
+    app_handle: &AppHandle,
+    request_id: String,
+    answer: String,
+    citations: Vec<tools::Citation>,
+    cached: bool,
+) -> Result<(), String> {
+    app_handle
+        .emit(
+            "ai-stream-chunk",
+            StreamChunk {
+                request_id: request_id.clone(),
+                text: answer.clone(),
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    app_handle
+        .emit(
+            "ai-stream-end",
+            StreamEnd {
+                request_id,
+                full_text: answer,
+                citations,
+                cached,
+            },
+        )
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn ai_ask_stream(
     app_handle: AppHandle,
@@ -602,40 +670,56 @@ pub async fn ai_ask_stream(
     }
 
     let api_key = if settings.provider == AiProvider::OpenRouter {
-        match get_keyring_entry().and_then(|entry| entry.get_password().map_err(AppError::from)) {
-            Ok(key) => key,
+        match secrets::get(secrets::AI, AI_API_KEY) {
+            Ok(Some(key)) => key,
+            Ok(None) => return Err("OpenRouter API key is not set".to_string()),
             Err(e) => return Err(e.to_string()),
         }
     } else {
         String::new() // Ollama doesn't need an API key
     };
 
-    let model_key = options.model.unwrap_or_else(|| "default".to_string());
+    let preset =
+        presets::resolve_preset(&app_handle, options.preset_id.as_deref(), options.context)?;
 
-    let model_id = settings
-        .model_associations
-        .get(&model_key)
-        .cloned()
-        .unwrap_or_else(|| match settings.provider {
-            AiProvider::OpenRouter => "mistralai/mistral-7b-instruct:free".to_string(),
-            AiProvider::Ollama => "llama3".to_string(),
-        });
+    let model_key = options
+        .model
+        .or_else(|| preset.as_ref().and_then(|p| p.model.clone()))
+        .unwrap_or_else(|| "default".to_string());
+
+    let model_id = resolve_model_id(&settings, &model_key);
 
     // Use configured temperature, allow creativity parameter to override if provided
-    let temperature = match options.creativity.as_deref() {
+    let creativity = options
+        .creativity
+        .or_else(|| preset.as_ref().and_then(|p| p.creativity.clone()));
+    let temperature = match creativity.as_deref() {
         Some("none") => 0.0,
         Some("low") => 0.4,
         Some("medium") => 0.7,
         Some("high") => 1.0,
-        _ => settings.temperature,
+        _ => preset
+            .as_ref()
+            .and_then(|p| p.temperature)
+            .unwrap_or(settings.temperature),
     };
 
-    let body = serde_json::json!({
-        "model": model_id,
-        "messages": [{"role": "user", "content": prompt}],
-        "stream": true,
-        "temperature": temperature,
-    });
+    let mut messages = Vec::new();
+    if let Some(instructions) = preset
+        .as_ref()
+        .map(|p| p.instructions.trim())
+        .filter(|i| !i.is_empty())
+    {
+        messages.push(serde_json::json!({"role": "system", "content": instructions}));
+    }
+    for message in &options.history {
+        messages.push(serde_json::json!({"role": message.role, "content": message.content}));
+    }
+    messages.push(serde_json::json!({"role": "user", "content": prompt}));
+
+    tokens::ensure_tokenizer(&app_handle);
+    let context_limit = tokens::context_limit(&settings, &model_id).await;
+    tokens::trim_to_fit(&mut messages, context_limit)?;
 
     let (api_url, auth_header) = match settings.provider {
         AiProvider::OpenRouter => (
@@ -645,6 +729,7 @@ pub async fn ai_ask_stream(
         AiProvider::Ollama => {
             let base = settings
                 .base_url
+                .clone()
                 .filter(|s| !s.trim().is_empty())
                 .unwrap_or_else(|| "http://localhost:11434/v1".to_string());
             (
@@ -654,63 +739,117 @@ pub async fn ai_ask_stream(
         }
     };
 
-    let client = reqwest::Client::new();
-    let mut request = client.post(&api_url).json(&body);
+    let extension_tools = if preset.as_ref().is_some_and(|p| p.extension_tools) {
+        crate::extensions::tools::installed_tools(&app_handle)
+    } else {
+        Vec::new()
+    };
+    let toolbox = tools::Toolbox {
+        app: &app_handle,
+        web_search: preset
+            .as_ref()
+            .is_some_and(|p| p.web_search)
+            .then_some(&settings.web_search),
+        retrieval: preset
+            .as_ref()
+            .is_some_and(|p| p.retrieval)
+            .then_some(&settings.embeddings),
+        extensions: &extension_tools,
+    };
+    let mut citations = Vec::new();
+    if !toolbox.is_empty() {
+        messages.insert(
+            messages.len() - 1,
+            serde_json::json!({"role": "system", "content": toolbox.instructions()}),
+        );
+        let target = tools::ChatTarget {
+            url: &api_url,
+            auth: auth_header.as_deref(),
+            model: &model_id,
+            temperature,
+        };
+        let grounding = tools::ground(&target, &mut messages, &toolbox).await?;
+        citations = grounding.citations;
+        // The model already answered in its last tool round
+        if let Some(answer) = grounding.answer {
+            return emit_answer(&app_handle, request_id, answer, citations, false);
+        }
+    }
 
-    if let Some(auth) = auth_header {
-        request = request.header("Authorization", auth);
-        request = request.header("HTTP-Referer", "http://localhost");
+    // Only one-shot prompts are cached; chats and tool answers depend on more
+    let cache_key = (options.history.is_empty() && toolbox.is_empty())
+        .then(|| cache::cache_key(&model_id, temperature, &messages));
+    if let Some(key) = cache_key.as_deref().filter(|_| !options.bypass_cache) {
+        if let Some(answer) = cache::lookup(&app_handle, &settings.response_cache, key) {
+            return emit_answer(&app_handle, request_id, answer, Vec::new(), true);
+        }
     }
 
-    let res = request.send().await.map_err(|e| e.to_string())?;
+    let mut full_text = String::new();
+    let mut on_text = |text: &str| -> Result<(), String> {
+        full_text.push_str(text);
+        app_handle
+            .emit(
+                "ai-stream-chunk",
+                StreamChunk {
+                    request_id: request_id.clone(),
+                    text: text.to_string(),
+                },
+            )
+            .map_err(|e| e.to_string())
+    };
 
-    let open_router_request_id = res
-        .headers()
-        .get("x-request-id")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string());
+    let open_router_request_id = match settings.provider {
+        AiProvider::Ollama => {
+            let request = ollama::ChatRequest {
+                model: &model_id,
+                messages: &messages,
+                temperature,
+                options: &settings.ollama,
+            };
+            let server = ollama::server_root(settings.base_url.as_deref());
+            ollama::stream_chat(&server, &request, &mut on_text).await?;
+            None
+        }
+        AiProvider::OpenRouter => {
+            let mut body = serde_json::json!({
+                "model": model_id,
+                "messages": messages,
+                "stream": true,
+                "temperature": temperature,
+            });
+            if !toolbox.is_empty() {
+                // Out of tool rounds; the tools stay declared so the earlier
+                // calls in the messages are valid, but the model has to answer
+                body["tools"] = tools::definitions(&toolbox);
+                body["tool_choice"] = serde_json::json!("none");
+            }
 
-    if !res.status().is_success() {
-        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".into());
-        return Err(format!("API Error: {}", error_body));
-    }
+            let client = reqwest::Client::new();
+            let mut request = client.post(&api_url).json(&body);
 
-    let mut stream = res.bytes_stream();
-    let mut full_text = String::new();
+            if let Some(auth) = auth_header {
+                request = request.header("Authorization", auth);
+                request = request.header("HTTP-Referer", "http://localhost");
+            }
 
-    while let Some(item) = stream.next().await {
-        let chunk = item.map_err(|e| e.to_string())?;
-        let lines = String::from_utf8_lossy(&chunk);
+            let res = request.send().await.map_err(|e| e.to_string())?;
 
-        for line in lines.split("\n\n").filter(|s| !s.is_empty()) {
-            if line.starts_with("data: ") {
-                let json_str = &line[6..];
-                if json_str.trim() == "[DONE]" {
-                    break;
-                }
-                if let Ok(json) = serde_json::from_str::<Value>(json_str) {
-                    if let Some(delta) = json
-                        .get("choices")
-                        .and_then(|c| c.get(0))
-                        .and_then(|c0| c0.get("delta"))
-                    {
-                        if let Some(content) = delta.get("content").and_then(|c| c.as_str()) {
-                            full_text.push_str(content);
-                            app_handle
-                                .emit(
-                                    "ai-stream-chunk",
-                                    StreamChunk {
-                                        request_id: request_id.clone(),
-                                        text: content.to_string(),
-                                    },
-                                )
-                                .map_err(|e| e.to_string())?;
-                        }
-                    }
-                }
+            let open_router_request_id = res
+                .headers()
+                .get("x-request-id")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string());
+
+            if !res.status().is_success() {
+                let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".into());
+                return Err(format!("API Error: {}", error_body));
             }
+
+            sse::stream_completion(res, &mut on_text).await?;
+            open_router_request_id
         }
-    }
+    };
 
     app_handle
         .emit(
@@ -718,10 +857,22 @@ pub async fn ai_ask_stream(
             StreamEnd {
                 request_id: request_id.clone(),
                 full_text: full_text.clone(),
+                citations,
+                cached: false,
             },
         )
         .map_err(|e| e.to_string())?;
 
+    if let Some(key) = cache_key.as_deref() {
+        cache::store(
+            &app_handle,
+            &settings.response_cache,
+            key,
+            &model_id,
+            &full_text,
+        );
+    }
+
     if settings.provider == AiProvider::OpenRouter {
         if let Some(or_req_id) = open_router_request_id {
             let handle_clone = app_handle.clone();
@@ -0,0 +1,266 @@
+//! Client for Ollama's native API, which the OpenAI-compatible `/v1`
+//! endpoints don't fully cover: `keep_alive`, model options like `num_ctx`,
+//! and loading or unloading a model without asking it anything.
+//!
+//! Answers are streamed from `/api/chat` whenever the provider is Ollama.
+//! Tool rounds still go through `/v1/chat/completions`, which Ollama serves
+//! alongside, so their messages are converted before the final answer.
+
+use super::sse::LineBuffer;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const DEFAULT_SERVER: &str = "http://localhost:11434";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaOptions {
+    /// How long the model stays loaded after a request, like `5m` or `1h`;
+    /// `-1` keeps it loaded and `0` unloads it right away
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Context window in tokens; Ollama's own default is small
+    #[serde(default)]
+    pub num_ctx: Option<u32>,
+}
+
+pub struct ChatRequest<'a> {
+    pub model: &'a str,
+    pub messages: &'a [Value],
+    pub temperature: f64,
+    pub options: &'a OllamaOptions,
+}
+
+/// The server root for a configured base URL, which usually points at the
+/// OpenAI-compatible `/v1` path
+pub fn server_root(base_url: Option<&str>) -> String {
+    let base = base_url
+        .map(|url| url.trim().trim_end_matches('/'))
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_SERVER);
+    base.strip_suffix("/v1").unwrap_or(base).to_string()
+}
+
+/// Ollama takes durations as strings and plain seconds as numbers
+fn keep_alive_value(keep_alive: &str) -> Value {
+    match keep_alive.trim().parse::<i64>() {
+        Ok(seconds) => json!(seconds),
+        Err(_) => json!(keep_alive.trim()),
+    }
+}
+
+/// Native messages take tool call arguments as objects rather than strings
+fn native_messages(messages: &[Value]) -> Vec<Value> {
+    messages
+        .iter()
+        .cloned()
+        .map(|mut message| {
+            if let Some(calls) = message.get_mut("tool_calls").and_then(Value::as_array_mut) {
+                for call in calls {
+                    if let Some(arguments) = call.pointer_mut("/function/arguments") {
+                        if let Some(parsed) = arguments
+                            .as_str()
+                            .and_then(|text| serde_json::from_str::<Value>(text).ok())
+                        {
+                            *arguments = parsed;
+                        }
+                    }
+                }
+            }
+            message
+        })
+        .collect()
+}
+
+fn chat_body(request: &ChatRequest<'_>) -> Value {
+    let mut options = json!({ "temperature": request.temperature });
+    if let Some(num_ctx) = request.options.num_ctx {
+        options["num_ctx"] = json!(num_ctx);
+    }
+    let mut body = json!({
+        "model": request.model,
+        "messages": native_messages(request.messages),
+        "stream": true,
+        "options": options,
+    });
+    if let Some(keep_alive) = request.options.keep_alive.as_deref() {
+        body["keep_alive"] = keep_alive_value(keep_alive);
+    }
+    body
+}
+
+#[derive(Debug, PartialEq)]
+struct ChatLine {
+    content: String,
+    done: bool,
+}
+
+fn parse_chat_line(line: &str) -> Result<ChatLine, String> {
+    let json: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    if let Some(error) = json.get("error").and_then(Value::as_str) {
+        return Err(format!("Ollama: {}", error));
+    }
+    Ok(ChatLine {
+        content: json
+            .pointer("/message/content")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        done: json.get("done").and_then(Value::as_bool).unwrap_or(false),
+    })
+}
+
+/// Streams an answer from `/api/chat`, handing each piece of text to `on_text`
+pub async fn stream_chat(
+    server: &str,
+    request: &ChatRequest<'_>,
+    mut on_text: impl FnMut(&str) -> Result<(), String>,
+) -> Result<(), String> {
+    let res = reqwest::Client::new()
+        .post(format!("{}/api/chat", server))
+        .json(&chat_body(request))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".into());
+        return Err(format!("API Error: {}", error_body));
+    }
+
+    // One JSON object per line, which chunks can split anywhere
+    let mut stream = res.bytes_stream();
+    let mut lines = LineBuffer::default();
+    let mut handle = |line: String| -> Result<bool, String> {
+        if line.trim().is_empty() {
+            return Ok(true);
+        }
+        let line = parse_chat_line(&line)?;
+        if !line.content.is_empty() {
+            on_text(&line.content)?;
+        }
+        Ok(!line.done)
+    };
+    while let Some(item) = stream.next().await {
+        lines.push(&item.map_err(|e| e.to_string())?);
+        while let Some(line) = lines.next_line() {
+            if !handle(line)? {
+                return Ok(());
+            }
+        }
+    }
+    if let Some(line) = lines.finish() {
+        handle(line)?;
+    }
+    Ok(())
+}
+
+/// `/api/generate` with an empty prompt loads a model, or unloads it with a
+/// `keep_alive` of 0
+async fn generate_empty(server: &str, model: &str, keep_alive: Value) -> Result<(), String> {
+    let res = reqwest::Client::builder()
+        .timeout(Duration::from_secs(120))
+        .build()
+        .map_err(|e| e.to_string())?
+        .post(format!("{}/api/generate", server))
+        .json(&json!({ "model": model, "keep_alive": keep_alive, "stream": false }))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".into());
+        return Err(format!("API Error: {}", error_body));
+    }
+    Ok(())
+}
+
+/// Loads a model into memory ahead of the first request
+#[tauri::command]
+pub async fn ollama_load_model(app: tauri::AppHandle, model: String) -> Result<(), String> {
+    let settings = super::get_ai_settings(app)?;
+    let keep_alive = settings
+        .ollama
+        .keep_alive
+        .as_deref()
+        .map_or(json!("5m"), keep_alive_value);
+    generate_empty(
+        &server_root(settings.base_url.as_deref()),
+        &model,
+        keep_alive,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn ollama_unload_model(app: tauri::AppHandle, model: String) -> Result<(), String> {
+    let settings = super::get_ai_settings(app)?;
+    generate_empty(&server_root(settings.base_url.as_deref()), &model, json!(0)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_root() {
+        assert_eq!(server_root(None), "http://localhost:11434");
+        assert_eq!(
+            server_root(Some("http://box:11434/v1/")),
+            "http://box:11434"
+        );
+        assert_eq!(server_root(Some("http://box:11434")), "http://box:11434");
+        assert_eq!(server_root(Some("  ")), "http://localhost:11434");
+    }
+
+    #[test]
+    fn test_chat_body() {
+        let messages = vec![
+            json!({"role": "assistant", "content": "", "tool_calls": [
+                {"id": "1", "function": {"name": "web_search", "arguments": "{\"query\":\"rust\"}"}}
+            ]}),
+            json!({"role": "tool", "tool_call_id": "1", "content": "[1] Rust"}),
+        ];
+        let options = OllamaOptions {
+            keep_alive: Some("10m".to_string()),
+            num_ctx: Some(8192),
+        };
+        let body = chat_body(&ChatRequest {
+            model: "llama3",
+            messages: &messages,
+            temperature: 0.4,
+            options: &options,
+        });
+        assert_eq!(
+            body["options"],
+            json!({"temperature": 0.4, "num_ctx": 8192})
+        );
+        assert_eq!(body["keep_alive"], "10m");
+        assert_eq!(
+            body["messages"][0]["tool_calls"][0]["function"]["arguments"],
+            json!({"query": "rust"})
+        );
+        assert_eq!(keep_alive_value("-1"), json!(-1));
+    }
+
+    #[test]
+    fn test_parse_chat_line() {
+        assert_eq!(
+            parse_chat_line(r#"{"message":{"role":"assistant","content":"Hi"},"done":false}"#)
+                .unwrap(),
+            ChatLine {
+                content: "Hi".to_string(),
+                done: false
+            }
+        );
+        assert!(
+            parse_chat_line(r#"{"done":true,"eval_count":12}"#)
+                .unwrap()
+                .done
+        );
+        assert_eq!(
+            parse_chat_line(r#"{"error":"model 'x' not found"}"#),
+            Err("Ollama: model 'x' not found".to_string())
+        );
+    }
+}
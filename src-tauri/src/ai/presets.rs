@@ -0,0 +1,617 @@
+//! AI presets: named bundles of instructions, model and creativity that a
+//! quick AI prompt or a chat can start from. Each context can have one
+//! default preset, which is used when a request doesn't name one.
+
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
+
+const AI_PRESETS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS ai_presets (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    icon TEXT,
+    instructions TEXT NOT NULL DEFAULT '',
+    model TEXT,
+    creativity TEXT,
+    temperature REAL,
+    web_search INTEGER NOT NULL DEFAULT 0,
+    retrieval INTEGER NOT NULL DEFAULT 0,
+    extension_tools INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+)";
+
+const AI_PRESET_DEFAULTS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS ai_preset_defaults (
+    context TEXT PRIMARY KEY,
+    preset_id TEXT NOT NULL
+)";
+
+const EXPORT_VERSION: u32 = 1;
+const CREATIVITY_LEVELS: &[&str] = &["none", "low", "medium", "high"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum PresetContext {
+    QuickAi,
+    Chat,
+}
+
+impl PresetContext {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PresetContext::QuickAi => "quickAi",
+            PresetContext::Chat => "chat",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AiPreset {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    /// Sent as the system message
+    pub instructions: String,
+    /// A model key from the AI settings' model associations
+    pub model: Option<String>,
+    /// `none`, `low`, `medium` or `high`; wins over `temperature`
+    pub creativity: Option<String>,
+    pub temperature: Option<f64>,
+    /// Lets the model search the web and read pages before answering
+    pub web_search: bool,
+    /// Lets the model look things up in the indexed notes and files
+    pub retrieval: bool,
+    /// Lets the model call the AI tools installed extensions provide
+    pub extension_tools: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Storable for AiPreset {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let created_at: i64 = row.get(8)?;
+        let updated_at: i64 = row.get(9)?;
+        Ok(AiPreset {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            icon: row.get(3)?,
+            instructions: row.get(4)?,
+            model: row.get(5)?,
+            creativity: row.get(6)?,
+            temperature: row.get(7)?,
+            web_search: row.get(10)?,
+            retrieval: row.get(11)?,
+            extension_tools: row.get(12)?,
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_default(),
+            updated_at: DateTime::from_timestamp(updated_at, 0).unwrap_or_default(),
+        })
+    }
+}
+
+/// The editable part of a preset; also the shape presets are exported in
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AiPresetInput {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub instructions: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub creativity: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub web_search: bool,
+    #[serde(default)]
+    pub retrieval: bool,
+    #[serde(default)]
+    pub extension_tools: bool,
+}
+
+impl From<AiPreset> for AiPresetInput {
+    fn from(preset: AiPreset) -> Self {
+        Self {
+            name: preset.name,
+            description: preset.description,
+            icon: preset.icon,
+            instructions: preset.instructions,
+            model: preset.model,
+            creativity: preset.creativity,
+            temperature: preset.temperature,
+            web_search: preset.web_search,
+            retrieval: preset.retrieval,
+            extension_tools: preset.extension_tools,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PresetExport {
+    version: u32,
+    presets: Vec<AiPresetInput>,
+}
+
+fn validate_preset(input: &AiPresetInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("Preset needs a name".to_string());
+    }
+    if let Some(creativity) = input.creativity.as_deref() {
+        if !CREATIVITY_LEVELS.contains(&creativity) {
+            return Err(format!("Unknown creativity '{}'", creativity));
+        }
+    }
+    if let Some(temperature) = input.temperature {
+        if !(0.0..=2.0).contains(&temperature) {
+            return Err("Temperature must be between 0 and 2".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Accepts an export document or a bare array of presets
+fn parse_import(json: &str) -> Result<Vec<AiPresetInput>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let presets = match value {
+        Value::Array(_) => value,
+        Value::Object(mut object) => {
+            if let Some(version) = object.get("version").and_then(Value::as_u64) {
+                if version > EXPORT_VERSION as u64 {
+                    return Err(format!("Unsupported preset export version {}", version));
+                }
+            }
+            object
+                .remove("presets")
+                .ok_or("Expected a list of presets")?
+        }
+        _ => return Err("Expected a list of presets".to_string()),
+    };
+    let presets: Vec<AiPresetInput> =
+        serde_json::from_value(presets).map_err(|e| format!("Invalid preset: {}", e))?;
+    presets.iter().try_for_each(validate_preset)?;
+    Ok(presets)
+}
+
+const SELECT_PRESETS: &str = "SELECT id, name, description, icon, instructions, model, creativity, temperature, created_at, updated_at, web_search, retrieval, extension_tools FROM ai_presets";
+
+/// Adds the tool switches to tables created before they existed
+fn migrate_tool_columns(db: &rusqlite::Connection) -> rusqlite::Result<()> {
+    let mut stmt = db.prepare("PRAGMA table_info(ai_presets)")?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for column in ["web_search", "retrieval", "extension_tools"] {
+        if !columns.iter().any(|existing| existing == column) {
+            db.execute(
+                &format!(
+                    "ALTER TABLE ai_presets ADD COLUMN {} INTEGER NOT NULL DEFAULT 0",
+                    column
+                ),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub struct AiPresetManager {
+    store: Store,
+}
+
+impl AiPresetManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "ai_presets.sqlite")?;
+        store.init_table(AI_PRESETS_SCHEMA)?;
+        store.init_table(AI_PRESET_DEFAULTS_SCHEMA)?;
+        migrate_tool_columns(&store.conn())?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(AI_PRESETS_SCHEMA)?;
+        store.init_table(AI_PRESET_DEFAULTS_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    fn list(&self) -> Result<Vec<AiPreset>, AppError> {
+        self.store.query(
+            &format!("{} ORDER BY name COLLATE NOCASE ASC", SELECT_PRESETS),
+            [],
+        )
+    }
+
+    fn find(&self, id: &str) -> Result<Option<AiPreset>, AppError> {
+        self.store
+            .query_row(&format!("{} WHERE id = ?", SELECT_PRESETS), params![id])
+    }
+
+    fn get(&self, id: &str) -> Result<AiPreset, AppError> {
+        self.find(id)?
+            .ok_or_else(|| AppError::Rusqlite(rusqlite::Error::QueryReturnedNoRows))
+    }
+
+    fn create(&self, input: AiPresetInput) -> Result<AiPreset, AppError> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now().timestamp();
+        self.store.execute(
+            "INSERT INTO ai_presets (id, name, description, icon, instructions, model, creativity, temperature, web_search, retrieval, extension_tools, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?12)",
+            params![
+                id,
+                input.name.trim(),
+                input.description,
+                input.icon,
+                input.instructions,
+                input.model,
+                input.creativity,
+                input.temperature,
+                input.web_search,
+                input.retrieval,
+                input.extension_tools,
+                now
+            ],
+        )?;
+        self.get(&id)
+    }
+
+    fn update(&self, id: &str, input: AiPresetInput) -> Result<AiPreset, AppError> {
+        let changed = self.store.execute(
+            "UPDATE ai_presets SET name = ?1, description = ?2, icon = ?3, instructions = ?4, model = ?5, creativity = ?6, temperature = ?7, web_search = ?8, retrieval = ?9, extension_tools = ?10, updated_at = ?11
+             WHERE id = ?12",
+            params![
+                input.name.trim(),
+                input.description,
+                input.icon,
+                input.instructions,
+                input.model,
+                input.creativity,
+                input.temperature,
+                input.web_search,
+                input.retrieval,
+                input.extension_tools,
+                Utc::now().timestamp(),
+                id
+            ],
+        )?;
+        if changed == 0 {
+            return Err(AppError::Rusqlite(rusqlite::Error::QueryReturnedNoRows));
+        }
+        self.get(id)
+    }
+
+    /// Also clears the contexts it was the default for
+    fn delete(&self, id: &str) -> Result<(), AppError> {
+        let mut conn = self.store.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM ai_preset_defaults WHERE preset_id = ?",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM ai_presets WHERE id = ?", params![id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn duplicate(&self, id: &str) -> Result<AiPreset, AppError> {
+        let mut input = AiPresetInput::from(self.get(id)?);
+        input.name = format!("{} Copy", input.name);
+        self.create(input)
+    }
+
+    fn set_default(&self, context: PresetContext, id: Option<&str>) -> Result<(), AppError> {
+        match id {
+            Some(id) => {
+                self.get(id)?;
+                self.store.execute(
+                    "INSERT INTO ai_preset_defaults (context, preset_id) VALUES (?1, ?2)
+                     ON CONFLICT(context) DO UPDATE SET preset_id = excluded.preset_id",
+                    params![context.as_str(), id],
+                )?;
+            }
+            None => {
+                self.store.execute(
+                    "DELETE FROM ai_preset_defaults WHERE context = ?",
+                    params![context.as_str()],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn default_for(&self, context: PresetContext) -> Result<Option<AiPreset>, AppError> {
+        let id: Option<String> = self
+            .store
+            .conn()
+            .query_row(
+                "SELECT preset_id FROM ai_preset_defaults WHERE context = ?",
+                params![context.as_str()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match id {
+            Some(id) => self.find(&id),
+            None => Ok(None),
+        }
+    }
+
+    fn defaults(&self) -> Result<HashMap<PresetContext, String>, AppError> {
+        let mut defaults = HashMap::new();
+        for context in [PresetContext::QuickAi, PresetContext::Chat] {
+            if let Some(preset) = self.default_for(context)? {
+                defaults.insert(context, preset.id);
+            }
+        }
+        Ok(defaults)
+    }
+
+    /// Every preset when `ids` is `None`
+    fn export(&self, ids: Option<&[String]>) -> Result<String, AppError> {
+        let presets = self
+            .list()?
+            .into_iter()
+            .filter(|preset| match ids {
+                Some(ids) => ids.contains(&preset.id),
+                None => true,
+            })
+            .map(AiPresetInput::from)
+            .collect();
+        serde_json::to_string_pretty(&PresetExport {
+            version: EXPORT_VERSION,
+            presets,
+        })
+        .map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    /// Imported presets get new ids, so importing twice makes copies
+    fn import(&self, presets: Vec<AiPresetInput>) -> Result<Vec<AiPreset>, AppError> {
+        presets
+            .into_iter()
+            .map(|preset| self.create(preset))
+            .collect()
+    }
+}
+
+/// The preset a request should use: the one it names, or else the default
+/// for its context
+pub fn resolve_preset(
+    app: &AppHandle,
+    id: Option<&str>,
+    context: Option<PresetContext>,
+) -> Result<Option<AiPreset>, String> {
+    let Some(manager) = app.try_state::<AiPresetManager>() else {
+        return Ok(None);
+    };
+    match (id, context) {
+        (Some(id), _) => manager
+            .find(id)
+            .map_err(|e| e.to_string())?
+            .map(Some)
+            .ok_or_else(|| format!("AI preset '{}' not found", id)),
+        (None, Some(context)) => manager.default_for(context).map_err(|e| e.to_string()),
+        (None, None) => Ok(None),
+    }
+}
+
+#[tauri::command]
+pub fn list_ai_presets(app: AppHandle) -> Result<Vec<AiPreset>, String> {
+    app.state::<AiPresetManager>()
+        .list()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_ai_preset(app: AppHandle, id: String) -> Result<Option<AiPreset>, String> {
+    app.state::<AiPresetManager>()
+        .find(&id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_ai_preset(app: AppHandle, preset: AiPresetInput) -> Result<AiPreset, String> {
+    validate_preset(&preset)?;
+    app.state::<AiPresetManager>()
+        .create(preset)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_ai_preset(
+    app: AppHandle,
+    id: String,
+    preset: AiPresetInput,
+) -> Result<AiPreset, String> {
+    validate_preset(&preset)?;
+    app.state::<AiPresetManager>()
+        .update(&id, preset)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_ai_preset(app: AppHandle, id: String) -> Result<(), String> {
+    app.state::<AiPresetManager>()
+        .delete(&id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn duplicate_ai_preset(app: AppHandle, id: String) -> Result<AiPreset, String> {
+    app.state::<AiPresetManager>()
+        .duplicate(&id)
+        .map_err(|e| e.to_string())
+}
+
+/// `None` clears the default for the context
+#[tauri::command]
+pub fn set_default_ai_preset(
+    app: AppHandle,
+    context: PresetContext,
+    id: Option<String>,
+) -> Result<(), String> {
+    app.state::<AiPresetManager>()
+        .set_default(context, id.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_default_ai_preset(
+    app: AppHandle,
+    context: PresetContext,
+) -> Result<Option<AiPreset>, String> {
+    app.state::<AiPresetManager>()
+        .default_for(context)
+        .map_err(|e| e.to_string())
+}
+
+/// Preset ids by context, for contexts that have a default
+#[tauri::command]
+pub fn get_ai_preset_defaults(app: AppHandle) -> Result<HashMap<PresetContext, String>, String> {
+    app.state::<AiPresetManager>()
+        .defaults()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn export_ai_presets(app: AppHandle, ids: Option<Vec<String>>) -> Result<String, String> {
+    app.state::<AiPresetManager>()
+        .export(ids.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn import_ai_presets(app: AppHandle, json: String) -> Result<Vec<AiPreset>, String> {
+    let presets = parse_import(&json)?;
+    app.state::<AiPresetManager>()
+        .import(presets)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(name: &str) -> AiPresetInput {
+        AiPresetInput {
+            name: name.to_string(),
+            description: None,
+            icon: None,
+            instructions: format!("You are {}.", name),
+            model: Some("OpenAI_GPT4o-mini".to_string()),
+            creativity: Some("low".to_string()),
+            temperature: None,
+            web_search: false,
+            retrieval: false,
+            extension_tools: false,
+        }
+    }
+
+    #[test]
+    fn test_crud_and_duplicate() {
+        let manager = AiPresetManager::new_for_test().unwrap();
+        let writer = manager.create(input("Writer")).unwrap();
+        assert_eq!(writer.instructions, "You are Writer.");
+
+        let mut changed = input("Editor");
+        changed.creativity = None;
+        let editor = manager.update(&writer.id, changed).unwrap();
+        assert_eq!(editor.id, writer.id);
+        assert_eq!(editor.creativity, None);
+        assert!(manager.update("missing", input("x")).is_err());
+
+        let copy = manager.duplicate(&editor.id).unwrap();
+        assert_ne!(copy.id, editor.id);
+        assert_eq!(copy.name, "Editor Copy");
+        assert_eq!(copy.instructions, editor.instructions);
+
+        let names: Vec<String> = manager
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, vec!["Editor", "Editor Copy"]);
+
+        manager.delete(&copy.id).unwrap();
+        assert!(manager.find(&copy.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_defaults_per_context() {
+        let manager = AiPresetManager::new_for_test().unwrap();
+        let quick = manager.create(input("Quick")).unwrap();
+        let chat = manager.create(input("Chat")).unwrap();
+
+        manager
+            .set_default(PresetContext::QuickAi, Some(quick.id.as_str()))
+            .unwrap();
+        manager
+            .set_default(PresetContext::Chat, Some(chat.id.as_str()))
+            .unwrap();
+        manager
+            .set_default(PresetContext::Chat, Some(quick.id.as_str()))
+            .unwrap();
+        assert!(manager
+            .set_default(PresetContext::Chat, Some("missing"))
+            .is_err());
+        assert_eq!(
+            manager
+                .default_for(PresetContext::Chat)
+                .unwrap()
+                .unwrap()
+                .id,
+            quick.id
+        );
+
+        // Deleting the default leaves the context without one
+        manager.delete(&quick.id).unwrap();
+        assert!(manager
+            .default_for(PresetContext::QuickAi)
+            .unwrap()
+            .is_none());
+        assert!(manager.defaults().unwrap().is_empty());
+
+        manager
+            .set_default(PresetContext::Chat, Some(chat.id.as_str()))
+            .unwrap();
+        manager.set_default(PresetContext::Chat, None).unwrap();
+        assert!(manager.default_for(PresetContext::Chat).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let manager = AiPresetManager::new_for_test().unwrap();
+        let first = manager.create(input("First")).unwrap();
+        manager.create(input("Second")).unwrap();
+
+        let json = manager
+            .export(Some(std::slice::from_ref(&first.id)))
+            .unwrap();
+        let parsed = parse_import(&json).unwrap();
+        assert_eq!(parsed, vec![input("First")]);
+
+        let imported = manager.import(parsed).unwrap();
+        assert_ne!(imported[0].id, first.id);
+        assert_eq!(manager.list().unwrap().len(), 3);
+
+        let bare = r#"[{ "name": "Bare", "instructions": "Be brief." }]"#;
+        assert_eq!(parse_import(bare).unwrap()[0].name, "Bare");
+        assert!(parse_import(r#"{ "version": 99, "presets": [] }"#).is_err());
+        assert!(parse_import(r#"[{ "name": " " }]"#).is_err());
+        assert!(parse_import(r#"[{ "name": "Hot", "temperature": 3.0 }]"#).is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! Incremental parsing of streamed AI responses.
+//!
+//! Network chunks don't line up with lines or events, so bytes are buffered
+//! until a full line arrives. A line can end in `\n`, `\r\n` or a lone `\r`.
+//! Server-sent events are assembled from those lines following the
+//! EventSource rules: `:` comments are skipped, `data` fields are joined
+//! with newlines, and a blank line ends an event.
+
+use futures_util::StreamExt;
+use serde_json::Value;
+
+/// Splits a byte stream into lines, holding back a partial line until the
+/// rest of it arrives
+#[derive(Default)]
+pub struct LineBuffer {
+    buffer: Vec<u8>,
+}
+
+impl LineBuffer {
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// The next complete line, without its line ending
+    pub fn next_line(&mut self) -> Option<String> {
+        let end = self
+            .buffer
+            .iter()
+            .position(|b| *b == b'\n' || *b == b'\r')?;
+        let ending_len = match self.buffer[end] {
+            b'\n' => 1,
+            // A `\r` at the end of the chunk may be half of a `\r\n`
+            _ if end + 1 == self.buffer.len() => return None,
+            _ if self.buffer[end + 1] == b'\n' => 2,
+            _ => 1,
+        };
+        let line: Vec<u8> = self.buffer.drain(..end + ending_len).collect();
+        Some(String::from_utf8_lossy(&line[..end]).into_owned())
+    }
+
+    /// Whatever is left once the stream has ended
+    pub fn finish(&mut self) -> Option<String> {
+        if let Some(line) = self.next_line() {
+            return Some(line);
+        }
+        let rest: Vec<u8> = self.buffer.drain(..).collect();
+        let rest = rest.strip_suffix(b"\r").unwrap_or(&rest);
+        (!rest.is_empty()).then(|| String::from_utf8_lossy(rest).into_owned())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+#[derive(Default)]
+pub struct SseParser {
+    lines: LineBuffer,
+    pending: SseEvent,
+    has_data: bool,
+}
+
+impl SseParser {
+    /// The events completed by `chunk`
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.lines.push(chunk);
+        let mut events = Vec::new();
+        while let Some(line) = self.lines.next_line() {
+            events.extend(self.process_line(&line));
+        }
+        events
+    }
+
+    /// The last event, for servers that close the stream without the blank
+    /// line that should end it
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        // `feed` leaves at most one line behind, ended by a lone `\r` or nothing
+        if let Some(event) = self
+            .lines
+            .finish()
+            .and_then(|line| self.process_line(&line))
+        {
+            return Some(event);
+        }
+        self.process_line("")
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let event = std::mem::take(&mut self.pending);
+            // Events without data are dropped, like EventSource does
+            return std::mem::take(&mut self.has_data).then_some(event);
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "data" => {
+                if self.has_data {
+                    self.pending.data.push('\n');
+                }
+                self.pending.data.push_str(value);
+                self.has_data = true;
+            }
+            "event" => self.pending.event = Some(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// What one event of an OpenAI-style streamed chat completion carries
+#[derive(Debug, PartialEq)]
+pub enum CompletionEvent {
+    Text(String),
+    Done,
+    /// Role announcements, usage, keep-alives and anything unparseable
+    Other,
+}
+
+pub fn completion_event(data: &str) -> Result<CompletionEvent, String> {
+    if data.trim() == "[DONE]" {
+        return Ok(CompletionEvent::Done);
+    }
+    let Ok(json) = serde_json::from_str::<Value>(data) else {
+        return Ok(CompletionEvent::Other);
+    };
+    // Providers report failures mid-stream as an event with an error object
+    if let Some(error) = json.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string());
+        return Err(format!("API Error: {}", message));
+    }
+    Ok(json
+        .pointer("/choices/0/delta/content")
+        .and_then(Value::as_str)
+        .filter(|content| !content.is_empty())
+        .map_or(CompletionEvent::Other, |content| {
+            CompletionEvent::Text(content.to_string())
+        }))
+}
+
+/// Streams the text of an OpenAI-style chat completion to `on_text`
+pub async fn stream_completion(
+    res: reqwest::Response,
+    mut on_text: impl FnMut(&str) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut stream = res.bytes_stream();
+    let mut parser = SseParser::default();
+    let mut handle = |event: SseEvent| -> Result<bool, String> {
+        if event.event.as_deref() == Some("error") {
+            return Err(format!("API Error: {}", event.data));
+        }
+        match completion_event(&event.data)? {
+            CompletionEvent::Text(text) => on_text(&text)?,
+            CompletionEvent::Done => return Ok(false),
+            CompletionEvent::Other => {}
+        }
+        Ok(true)
+    };
+
+    while let Some(item) = stream.next().await {
+        let chunk = item.map_err(|e| e.to_string())?;
+        for event in parser.feed(&chunk) {
+            if !handle(event)? {
+                return Ok(());
+            }
+        }
+    }
+    if let Some(event) = parser.finish() {
+        handle(event)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(parser: &mut SseParser, chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut events: Vec<SseEvent> =
+            chunks.iter().flat_map(|chunk| parser.feed(chunk)).collect();
+        events.extend(parser.finish());
+        events
+    }
+
+    fn data(events: &[SseEvent]) -> Vec<&str> {
+        events.iter().map(|event| event.data.as_str()).collect()
+    }
+
+    #[test]
+    fn test_line_buffer_endings() {
+        let mut lines = LineBuffer::default();
+        lines.push(b"one\ntwo\r\nthree\rfour\r");
+        assert_eq!(lines.next_line().as_deref(), Some("one"));
+        assert_eq!(lines.next_line().as_deref(), Some("two"));
+        assert_eq!(lines.next_line().as_deref(), Some("three"));
+        // The trailing `\r` could still be followed by `\n`
+        assert_eq!(lines.next_line(), None);
+        lines.push(b"\nfive");
+        assert_eq!(lines.next_line().as_deref(), Some("four"));
+        assert_eq!(lines.next_line(), None);
+        assert_eq!(lines.finish().as_deref(), Some("five"));
+        assert_eq!(lines.finish(), None);
+    }
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let stream = b"data: {\"a\":1}\n\ndata: {\"b\":2}\n\n";
+        for split in 0..stream.len() {
+            let (first, second) = stream.split_at(split);
+            let mut parser = SseParser::default();
+            let events = feed_all(&mut parser, &[first, second]);
+            assert_eq!(
+                data(&events),
+                vec!["{\"a\":1}", "{\"b\":2}"],
+                "split at {}",
+                split
+            );
+        }
+    }
+
+    #[test]
+    fn test_crlf_split_between_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b"data: x\r").is_empty());
+        assert!(parser.feed(b"\n\r").is_empty());
+        let events = parser.feed(b"\ndata: y\r\n\r\n");
+        assert_eq!(data(&events), vec!["x", "y"]);
+    }
+
+    #[test]
+    fn test_multibyte_character_split() {
+        let stream = "data: héllo ✓\n\n".as_bytes();
+        let mut parser = SseParser::default();
+        let events = feed_all(&mut parser, &[&stream[..8], &stream[8..15], &stream[15..]]);
+        assert_eq!(data(&events), vec!["héllo ✓"]);
+    }
+
+    #[test]
+    fn test_fields_and_comments() {
+        let mut parser = SseParser::default();
+        let events = feed_all(
+            &mut parser,
+            &[b": OPENROUTER PROCESSING\n\nevent: message\nid: 7\ndata: one\ndata:two\n\n"],
+        );
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                event: Some("message".to_string()),
+                data: "one\ntwo".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_last_event() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b"data: [DONE]").is_empty());
+        assert_eq!(
+            data(&parser.finish().into_iter().collect::<Vec<_>>()),
+            vec!["[DONE]"]
+        );
+    }
+
+    #[test]
+    fn test_completion_event() {
+        assert_eq!(
+            completion_event(r#"{"choices":[{"delta":{"content":"Hi"}}]}"#),
+            Ok(CompletionEvent::Text("Hi".to_string()))
+        );
+        assert_eq!(
+            completion_event(r#"{"choices":[{"delta":{"role":"assistant"}}]}"#),
+            Ok(CompletionEvent::Other)
+        );
+        assert_eq!(completion_event(" [DONE]"), Ok(CompletionEvent::Done));
+        assert_eq!(
+            completion_event(r#"{"error":{"message":"Rate limited","code":429}}"#),
+            Err("API Error: Rate limited".to_string())
+        );
+    }
+}
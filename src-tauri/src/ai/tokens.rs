@@ -0,0 +1,542 @@
+//! Token counting for prompts and chat history, compatible with tiktoken's
+//! `cl100k_base` encoding.
+//!
+//! The merge ranks are tiktoken's own file, downloaded once into the app's
+//! data directory and checked against its published hash. Until it's there,
+//! and for models with other tokenizers, counts are a conservative estimate
+//! from the same pre-tokenization. Context limits and prices come from
+//! OpenRouter's model list, with a built-in table as the fallback.
+
+use super::{get_ai_settings, resolve_model_id, AiProvider, AiSettings, Message};
+use base64::Engine;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const RANKS_URL: &str = "https://openaipublic.blob.core.windows.net/encodings/cl100k_base.tiktoken";
+const RANKS_SHA256: &str = "223921b76ee99bde995b7ff738513eef100fb51d18c93597a113bcffe865b2a7";
+const RANKS_FILE: &str = "cl100k_base.tiktoken";
+const MODELS_URL: &str = "https://openrouter.ai/api/v1/models";
+const MODELS_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Every message is wrapped in a few formatting tokens, and the reply is
+/// primed with a few more
+const TOKENS_PER_MESSAGE: usize = 3;
+const REPLY_PRIMING_TOKENS: usize = 3;
+/// Room left for the answer when trimming history
+const RESPONSE_RESERVE: usize = 1024;
+const DEFAULT_CONTEXT_LIMIT: usize = 8192;
+
+/// Context windows by model id fragment, most specific first
+const CONTEXT_LIMITS: &[(&str, usize)] = &[
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4", 200_000),
+    ("claude", 200_000),
+    ("gemini", 1_048_576),
+    ("llama-4", 327_680),
+    ("llama-3", 131_072),
+    ("llama3", 131_072),
+    ("mistral-large", 128_000),
+    ("mistral-7b", 32_768),
+    ("mistral", 131_072),
+    ("codestral", 256_000),
+    ("deepseek", 64_000),
+    ("grok", 131_072),
+    ("sonar", 127_072),
+];
+
+type Ranks = HashMap<Vec<u8>, u32>;
+/// The model list by id, with when it was fetched
+type ModelCache = (Instant, HashMap<String, ModelInfo>);
+
+static RANKS: Lazy<Mutex<Option<Arc<Ranks>>>> = Lazy::new(|| Mutex::new(None));
+static DOWNLOADING: AtomicBool = AtomicBool::new(false);
+static MODELS: Lazy<Mutex<Option<ModelCache>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone, Debug, PartialEq)]
+struct ModelInfo {
+    context_length: Option<usize>,
+    /// USD per prompt token
+    prompt_price: Option<f64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCount {
+    pub model: String,
+    pub tokens: usize,
+    /// False while the tokenizer is still downloading
+    pub exact: bool,
+    pub context_limit: usize,
+    pub remaining: usize,
+    /// USD for the prompt, when the provider publishes prices
+    pub estimated_cost: Option<f64>,
+}
+
+/// The pieces tiktoken's cl100k pattern splits text into before merging:
+/// `'s|'t|'re|'ve|'m|'ll|'d | [^\r\n\p{L}\p{N}]?\p{L}+ | \p{N}{1,3} |
+/// ?[^\s\p{L}\p{N}]+[\r\n]* | \s*[\r\n]+ | \s+(?!\S) | \s+`. Written out by
+/// hand because the lookahead is beyond the `regex` crate.
+fn pre_tokenize(text: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let offset = |index: usize| chars.get(index).map_or(text.len(), |(offset, _)| *offset);
+    let char_at = |index: usize| chars.get(index).map(|(_, c)| *c);
+    let is_letter = |c: Option<char>| c.is_some_and(char::is_alphabetic);
+    let is_number = |c: Option<char>| c.is_some_and(char::is_numeric);
+    let is_space = |c: Option<char>| c.is_some_and(char::is_whitespace);
+    let is_newline = |c: Option<char>| matches!(c, Some('\r' | '\n'));
+    let is_other = |c: Option<char>| c.is_some() && !is_letter(c) && !is_number(c) && !is_space(c);
+
+    let mut pieces = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = char_at(i);
+        let mut end = i + 1;
+
+        let contraction = (c == Some('\'')).then(|| {
+            let rest: String = chars[i + 1..]
+                .iter()
+                .take(2)
+                .map(|(_, c)| c.to_ascii_lowercase())
+                .collect();
+            ["re", "ve", "ll"]
+                .iter()
+                .find(|suffix| rest.starts_with(**suffix))
+                .map(|suffix| suffix.len())
+                .or_else(|| rest.starts_with(['s', 't', 'm', 'd']).then_some(1))
+        });
+        if let Some(Some(length)) = contraction {
+            end = i + 1 + length;
+        } else if is_letter(c)
+            || (!is_newline(c) && !is_letter(c) && !is_number(c) && is_letter(char_at(i + 1)))
+        {
+            while is_letter(char_at(end)) {
+                end += 1;
+            }
+        } else if is_number(c) {
+            while end < i + 3 && is_number(char_at(end)) {
+                end += 1;
+            }
+        } else if is_other(c) || (c == Some(' ') && is_other(char_at(i + 1))) {
+            end = if c == Some(' ') { i + 1 } else { i };
+            while is_other(char_at(end)) {
+                end += 1;
+            }
+            while is_newline(char_at(end)) {
+                end += 1;
+            }
+        } else {
+            let mut run_end = i;
+            while is_space(char_at(run_end)) {
+                run_end += 1;
+            }
+            let last_newline = (i..run_end).rev().find(|&j| is_newline(char_at(j)));
+            end = match last_newline {
+                Some(j) => j + 1,
+                None if run_end == chars.len() || run_end - i == 1 => run_end,
+                // Leaves the last space to start the next word
+                None => run_end - 1,
+            };
+        }
+
+        pieces.push(&text[offset(i)..offset(end)]);
+        i = end;
+    }
+    pieces
+}
+
+/// tiktoken's byte pair merge, returning only how many tokens it makes
+fn byte_pair_count(piece: &[u8], ranks: &Ranks) -> usize {
+    if ranks.contains_key(piece) {
+        return 1;
+    }
+    let mut boundaries: Vec<usize> = (0..=piece.len()).collect();
+    loop {
+        let best = (0..boundaries.len().saturating_sub(2))
+            .filter_map(|i| {
+                ranks
+                    .get(&piece[boundaries[i]..boundaries[i + 2]])
+                    .map(|rank| (*rank, i))
+            })
+            .min();
+        match best {
+            Some((_, i)) => {
+                boundaries.remove(i + 1);
+            }
+            None => return boundaries.len() - 1,
+        }
+    }
+}
+
+/// Roughly four bytes per token besides the leading space, which errs on
+/// the high side for English
+fn estimate_piece(piece: &str) -> usize {
+    piece.trim_start().len().div_ceil(4).max(1)
+}
+
+fn count_with(text: &str, ranks: Option<&Ranks>) -> usize {
+    pre_tokenize(text)
+        .into_iter()
+        .map(|piece| match ranks {
+            Some(ranks) => byte_pair_count(piece.as_bytes(), ranks),
+            None => estimate_piece(piece),
+        })
+        .sum()
+}
+
+fn loaded_ranks() -> Option<Arc<Ranks>> {
+    RANKS.lock().unwrap().clone()
+}
+
+/// Tokens in a chat request's `{role, content}` messages
+pub fn count_messages(messages: &[Value]) -> usize {
+    let ranks = loaded_ranks();
+    messages
+        .iter()
+        .map(|message| {
+            let content = message
+                .get("content")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let role = message
+                .get("role")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            TOKENS_PER_MESSAGE
+                + count_with(role, ranks.as_deref())
+                + count_with(content, ranks.as_deref())
+        })
+        .sum::<usize>()
+        + REPLY_PRIMING_TOKENS
+}
+
+/// Parses a `.tiktoken` file: one base64 token and its rank per line
+fn parse_ranks(content: &str) -> Result<Ranks, String> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| -> Result<(Vec<u8>, u32), String> {
+            let (token, rank) = line.split_once(' ').ok_or("Malformed tokenizer line")?;
+            let token = base64::engine::general_purpose::STANDARD
+                .decode(token)
+                .map_err(|e| e.to_string())?;
+            let rank = rank
+                .trim()
+                .parse()
+                .map_err(|_| "Malformed tokenizer rank")?;
+            Ok((token, rank))
+        })
+        .collect()
+}
+
+fn ranks_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| "Failed to get app local data dir".to_string())?
+        .join("tokenizers");
+    Ok(dir.join(RANKS_FILE))
+}
+
+fn load_ranks(path: &Path) -> Result<Ranks, String> {
+    parse_ranks(&fs::read_to_string(path).map_err(|e| e.to_string())?)
+}
+
+async fn download_ranks(path: PathBuf) -> Result<(), String> {
+    let response = reqwest::Client::builder()
+        .timeout(Duration::from_secs(60))
+        .build()
+        .map_err(|e| e.to_string())?
+        .get(RANKS_URL)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Tokenizer download returned {}", response.status()));
+    }
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    if hex::encode(Sha256::digest(&bytes)) != RANKS_SHA256 {
+        return Err("Tokenizer download doesn't match its hash".to_string());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    fs::write(&path, &bytes).map_err(|e| e.to_string())?;
+    let ranks = load_ranks(&path)?;
+    *RANKS.lock().unwrap() = Some(Arc::new(ranks));
+    Ok(())
+}
+
+/// Loads the tokenizer, or starts downloading it in the background
+pub fn ensure_tokenizer(app: &AppHandle) {
+    if loaded_ranks().is_some() {
+        return;
+    }
+    let Ok(path) = ranks_path(app) else {
+        return;
+    };
+    if path.is_file() {
+        match load_ranks(&path) {
+            Ok(ranks) => {
+                *RANKS.lock().unwrap() = Some(Arc::new(ranks));
+                return;
+            }
+            Err(e) => tracing::warn!(error = %e, "Failed to load tokenizer, downloading again"),
+        }
+    }
+    if DOWNLOADING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = download_ranks(path).await {
+            tracing::warn!(error = %e, "Failed to download tokenizer");
+        }
+        DOWNLOADING.store(false, Ordering::SeqCst);
+    });
+}
+
+fn parse_models(body: &Value) -> HashMap<String, ModelInfo> {
+    let price = |model: &Value| {
+        model
+            .pointer("/pricing/prompt")
+            .and_then(|price| match price {
+                Value::String(price) => price.parse().ok(),
+                other => other.as_f64(),
+            })
+    };
+    body.get("data")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|model| {
+            let id = model.get("id").and_then(Value::as_str)?;
+            Some((
+                id.to_string(),
+                ModelInfo {
+                    context_length: model
+                        .get("context_length")
+                        .and_then(Value::as_u64)
+                        .map(|length| length as usize),
+                    prompt_price: price(model),
+                },
+            ))
+        })
+        .collect()
+}
+
+async fn openrouter_model(model_id: &str) -> Option<ModelInfo> {
+    let cached = MODELS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|(fetched_at, _)| fetched_at.elapsed() < MODELS_TTL)
+        .map(|(_, models)| models.get(model_id).cloned());
+    if let Some(info) = cached {
+        return info;
+    }
+
+    let fetched = async {
+        let response = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .ok()?
+            .get(MODELS_URL)
+            .send()
+            .await
+            .ok()?;
+        response.json::<Value>().await.ok()
+    }
+    .await;
+    let Some(body) = fetched else {
+        tracing::warn!("Failed to fetch OpenRouter model list");
+        return None;
+    };
+    let models = parse_models(&body);
+    let info = models.get(model_id).cloned();
+    *MODELS.lock().unwrap() = Some((Instant::now(), models));
+    info
+}
+
+fn known_context_limit(model_id: &str) -> usize {
+    let model_id = model_id.to_lowercase();
+    CONTEXT_LIMITS
+        .iter()
+        .find(|(fragment, _)| model_id.contains(fragment))
+        .map_or(DEFAULT_CONTEXT_LIMIT, |(_, limit)| *limit)
+}
+
+/// Context window and prompt price; OpenRouter is only asked about its own models
+async fn model_limits(settings: &AiSettings, model_id: &str) -> (usize, Option<f64>) {
+    let info = match settings.provider {
+        AiProvider::OpenRouter => openrouter_model(model_id).await,
+        // Local models cost nothing, and only see the context they're given
+        AiProvider::Ollama => Some(ModelInfo {
+            context_length: settings.ollama.num_ctx.map(|num_ctx| num_ctx as usize),
+            prompt_price: Some(0.0),
+        }),
+    };
+    let limit = info
+        .as_ref()
+        .and_then(|info| info.context_length)
+        .unwrap_or_else(|| known_context_limit(model_id));
+    (limit, info.and_then(|info| info.prompt_price))
+}
+
+pub async fn context_limit(settings: &AiSettings, model_id: &str) -> usize {
+    model_limits(settings, model_id).await.0
+}
+
+/// Drops the oldest history until the messages fit in `limit` with room for
+/// the answer. System messages and the last message are always kept.
+pub fn trim_to_fit(messages: &mut Vec<Value>, limit: usize) -> Result<(), String> {
+    let budget = limit.saturating_sub(RESPONSE_RESERVE.min(limit / 4));
+    let is_system = |message: &Value| message.get("role").and_then(Value::as_str) == Some("system");
+    loop {
+        let tokens = count_messages(messages);
+        if tokens <= budget {
+            return Ok(());
+        }
+        let oldest = messages
+            .iter()
+            .take(messages.len().saturating_sub(1))
+            .position(|message| !is_system(message));
+        match oldest {
+            Some(index) => {
+                messages.remove(index);
+            }
+            None => {
+                return Err(format!(
+                    "The prompt is too long for this model ({} of {} tokens)",
+                    tokens, budget
+                ))
+            }
+        }
+    }
+}
+
+/// Counts a prompt and the chat history before it for `model` (a model key
+/// from the AI settings)
+#[tauri::command]
+pub async fn count_tokens(
+    app: AppHandle,
+    messages: Vec<Message>,
+    model: Option<String>,
+) -> Result<TokenCount, String> {
+    let settings = get_ai_settings(app.clone())?;
+    ensure_tokenizer(&app);
+    let model_id = resolve_model_id(&settings, model.as_deref().unwrap_or("default"));
+
+    let messages: Vec<Value> = messages
+        .iter()
+        .map(|message| serde_json::json!({"role": message.role, "content": message.content}))
+        .collect();
+    let tokens = count_messages(&messages);
+    let (context_limit, prompt_price) = model_limits(&settings, &model_id).await;
+
+    Ok(TokenCount {
+        model: model_id,
+        tokens,
+        exact: loaded_ranks().is_some(),
+        context_limit,
+        remaining: context_limit.saturating_sub(tokens),
+        estimated_cost: prompt_price.map(|price| price * tokens as f64),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pre_tokenize() {
+        assert_eq!(pre_tokenize("Hello world"), vec!["Hello", " world"]);
+        assert_eq!(
+            pre_tokenize("I'm 12345!"),
+            vec!["I", "'m", " ", "123", "45", "!"]
+        );
+        assert_eq!(pre_tokenize("a  b"), vec!["a", " ", " b"]);
+        assert_eq!(pre_tokenize("one\n\ntwo "), vec!["one", "\n\n", "two", " "]);
+        assert_eq!(pre_tokenize("x = (y);"), vec!["x", " =", " (", "y", ");"]);
+        assert_eq!(pre_tokenize("THEY'LL"), vec!["THEY", "'LL"]);
+        assert_eq!(pre_tokenize("naïve café"), vec!["naïve", " café"]);
+    }
+
+    #[test]
+    fn test_byte_pair_count() {
+        let ranks: Ranks = [
+            ("a", 0),
+            ("b", 1),
+            ("c", 2),
+            ("ab", 3),
+            ("abc", 4),
+            ("bc", 5),
+        ]
+        .into_iter()
+        .map(|(token, rank)| (token.as_bytes().to_vec(), rank))
+        .collect();
+        assert_eq!(byte_pair_count(b"abc", &ranks), 1);
+        assert_eq!(byte_pair_count(b"abcabc", &ranks), 2);
+        // `ab` outranks `bc`, leaving `ab` + `c` + ...
+        assert_eq!(byte_pair_count(b"abcc", &ranks), 2);
+        assert_eq!(byte_pair_count(b"cab", &ranks), 2);
+    }
+
+    #[test]
+    fn test_parse_ranks() {
+        let ranks = parse_ranks("IQ== 0\nIg== 1\n\n").unwrap();
+        assert_eq!(ranks[b"!".as_slice()], 0);
+        assert_eq!(ranks[b"\"".as_slice()], 1);
+        assert!(parse_ranks("IQ==").is_err());
+    }
+
+    #[test]
+    fn test_parse_models_and_known_limits() {
+        let body = json!({ "data": [
+            { "id": "openai/gpt-4o", "context_length": 128000, "pricing": { "prompt": "0.0000025" } },
+            { "id": "x/free", "pricing": { "prompt": "0" } }
+        ] });
+        let models = parse_models(&body);
+        assert_eq!(models["openai/gpt-4o"].context_length, Some(128000));
+        assert_eq!(models["openai/gpt-4o"].prompt_price, Some(0.0000025));
+        assert_eq!(models["x/free"].context_length, None);
+
+        assert_eq!(known_context_limit("openai/gpt-4o-mini"), 128_000);
+        assert_eq!(known_context_limit("openai/gpt-4"), 8_192);
+        assert_eq!(known_context_limit("anthropic/claude-sonnet-4"), 200_000);
+        assert_eq!(known_context_limit("something-else"), DEFAULT_CONTEXT_LIMIT);
+    }
+
+    #[test]
+    fn test_trim_to_fit() {
+        let message =
+            |role: &str, words: usize| json!({ "role": role, "content": "word ".repeat(words) });
+        let mut messages = vec![
+            message("system", 10),
+            message("user", 400),
+            message("assistant", 400),
+            message("user", 10),
+        ];
+        trim_to_fit(&mut messages, 1000).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[1]["role"], "assistant");
+
+        let mut too_long = vec![message("user", 5000)];
+        assert!(trim_to_fit(&mut too_long, 1000).is_err());
+    }
+}
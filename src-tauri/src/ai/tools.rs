@@ -0,0 +1,721 @@
+//! Built-in tools the model can call before answering: `web_search` through
+//! a SearxNG instance or the Brave Search API, `fetch_url`, which reads a
+//! page and keeps only its main text, and `retrieve`, which looks things up
+//! in the local embeddings index. Installed extensions can add their own
+//! (see [`crate::extensions::tools`]).
+//!
+//! Presets turn on the web tools with `web_search`, `retrieve` with
+//! `retrieval` and the extensions' tools with `extension_tools`. The model
+//! calls them in a few non-streamed rounds, and every result it was shown is
+//! numbered, so the answer can cite `[n]` and the frontend can list the
+//! sources.
+
+use super::embeddings::{self, EmbeddingSettings};
+use crate::extensions::tools::{self as extension_tools, ExtensionTool};
+use crate::secrets;
+use crate::unfurl;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+use tauri::AppHandle;
+use url::Url;
+
+pub const BRAVE_API_KEY: &str = "brave_search_api_key";
+const BRAVE_SEARCH_URL: &str = "https://api.search.brave.com/res/v1/web/search";
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; Flare web reader)";
+
+const MAX_TOOL_ROUNDS: usize = 3;
+const DEFAULT_RESULT_COUNT: usize = 5;
+const MAX_RESULT_COUNT: usize = 10;
+const MAX_SNIPPET_CHARS: usize = 300;
+/// Pages are cut off well before they'd crowd out the rest of the context
+const MAX_PAGE_BYTES: usize = 2 * 1024 * 1024;
+const MAX_PAGE_CHARS: usize = 12_000;
+
+const WEB_SEARCH_INSTRUCTIONS: &str = "You can search the web with the web_search tool and read pages with fetch_url. Use them for anything recent or factual you aren't sure about.";
+const RETRIEVAL_INSTRUCTIONS: &str = "You can look up the user's own notes and files with the retrieve tool. Use it for questions about their notes, projects or documents.";
+const EXTENSION_TOOLS_INSTRUCTIONS: &str = "You can also use the tools the user's extensions provide. Some of them ask the user before they run; if the user declines, don't try again.";
+const CITATION_INSTRUCTIONS: &str = "Cite the sources you used with their [n] numbers.";
+
+static TITLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title\s*>").unwrap());
+static COMMENT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+/// Page chrome and markup that never holds the text of a page
+static DROPPED_BLOCK_REGEXES: Lazy<Vec<Regex>> = Lazy::new(|| {
+    [
+        "script", "style", "noscript", "template", "svg", "nav", "header", "footer", "aside",
+        "form", "iframe",
+    ]
+    .iter()
+    .map(|tag| Regex::new(&format!(r"(?is)<{0}\b[^>]*>.*?</{0}\s*>", tag)).unwrap())
+    .collect()
+});
+static ARTICLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<article\b[^>]*>(.*?)</article\s*>").unwrap());
+static MAIN_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<main\b[^>]*>(.*?)</main\s*>").unwrap());
+static BODY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<body\b[^>]*>(.*)</body\s*>").unwrap());
+static BLOCK_TAG_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)</?(p|div|br|hr|li|ul|ol|h[1-6]|tr|table|section|blockquote|pre|dd|dt)\b[^>]*>",
+    )
+    .unwrap()
+});
+static TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchBackend {
+    #[default]
+    Searxng,
+    Brave,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSearchSettings {
+    #[serde(default)]
+    pub backend: SearchBackend,
+    /// e.g. `https://searx.example.org`; the instance has to allow
+    /// `format=json`. The Brave key lives in the keyring instead.
+    #[serde(default)]
+    pub searxng_url: Option<String>,
+}
+
+/// A source the model was shown, numbered in the order it was first seen
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Citation {
+    pub title: String,
+    pub url: String,
+    pub snippet: Option<String>,
+}
+
+/// Where and how the tool rounds talk to the model
+pub struct ChatTarget<'a> {
+    pub url: &'a str,
+    pub auth: Option<&'a str>,
+    pub model: &'a str,
+    pub temperature: f64,
+}
+
+/// The tools a request may use; `None` turns a tool off
+pub struct Toolbox<'a> {
+    pub app: &'a AppHandle,
+    pub web_search: Option<&'a WebSearchSettings>,
+    pub retrieval: Option<&'a EmbeddingSettings>,
+    pub extensions: &'a [ExtensionTool],
+}
+
+impl Toolbox<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.web_search.is_none() && self.retrieval.is_none() && self.extensions.is_empty()
+    }
+
+    /// System message telling the model what it can call
+    pub fn instructions(&self) -> String {
+        let mut instructions = Vec::new();
+        if self.web_search.is_some() {
+            instructions.push(WEB_SEARCH_INSTRUCTIONS);
+        }
+        if self.retrieval.is_some() {
+            instructions.push(RETRIEVAL_INSTRUCTIONS);
+        }
+        if !self.extensions.is_empty() {
+            instructions.push(EXTENSION_TOOLS_INSTRUCTIONS);
+        }
+        for tool in self.extensions {
+            if let Some(tool_instructions) = tool.instructions.as_deref() {
+                instructions.push(tool_instructions);
+            }
+        }
+        instructions.push(CITATION_INSTRUCTIONS);
+        instructions.join(" ")
+    }
+}
+
+pub struct Grounding {
+    /// The model's answer once it stopped calling tools; `None` when it was
+    /// still calling them after the last round
+    pub answer: Option<String>,
+    pub citations: Vec<Citation>,
+}
+
+/// OpenAI-style definitions of the tools in the toolbox
+pub fn definitions(toolbox: &Toolbox<'_>) -> Value {
+    let mut tools = Vec::new();
+    if toolbox.web_search.is_some() {
+        tools.extend(web_definitions());
+    }
+    if toolbox.retrieval.is_some() {
+        tools.push(json!({
+            "type": "function",
+            "function": {
+                "name": "retrieve",
+                "description": "Search the user's indexed notes and files. Returns the most relevant passages with their file paths.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "query": { "type": "string", "description": "What to look for" },
+                        "count": {
+                            "type": "integer",
+                            "description": "How many passages to return, at most 10"
+                        }
+                    },
+                    "required": ["query"]
+                }
+            }
+        }));
+    }
+    tools.extend(toolbox.extensions.iter().map(ExtensionTool::definition));
+    Value::Array(tools)
+}
+
+fn web_definitions() -> [Value; 2] {
+    [
+        json!({
+            "type": "function",
+            "function": {
+                "name": "web_search",
+                "description": "Search the web. Returns numbered results with a title, URL and snippet.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "query": { "type": "string", "description": "The search query" },
+                        "count": {
+                            "type": "integer",
+                            "description": "How many results to return, at most 10"
+                        }
+                    },
+                    "required": ["query"]
+                }
+            }
+        }),
+        json!({
+            "type": "function",
+            "function": {
+                "name": "fetch_url",
+                "description": "Read the main text of a web page.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "url": { "type": "string", "description": "An http or https URL" }
+                    },
+                    "required": ["url"]
+                }
+            }
+        }),
+    ]
+}
+
+fn clean_text(text: &str) -> String {
+    unfurl::decode_entities(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn result_from(title: Option<&str>, url: Option<&str>, snippet: Option<&str>) -> Option<Citation> {
+    let url = url.filter(|url| !url.is_empty())?.to_string();
+    let snippet = snippet
+        .map(|snippet| clean_text(&TAG_REGEX.replace_all(snippet, "")))
+        .filter(|snippet| !snippet.is_empty())
+        .map(|snippet| unfurl::truncate(snippet, MAX_SNIPPET_CHARS));
+    Some(Citation {
+        title: title
+            .map(clean_text)
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| url.clone()),
+        url,
+        snippet,
+    })
+}
+
+fn parse_searxng(response: &Value, count: usize) -> Vec<Citation> {
+    response
+        .get("results")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|result| {
+            result_from(
+                result.get("title").and_then(Value::as_str),
+                result.get("url").and_then(Value::as_str),
+                result.get("content").and_then(Value::as_str),
+            )
+        })
+        .take(count)
+        .collect()
+}
+
+/// Brave wraps matches of the query in `<strong>`, which `result_from` strips
+fn parse_brave(response: &Value, count: usize) -> Vec<Citation> {
+    response
+        .pointer("/web/results")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|result| {
+            result_from(
+                result.get("title").and_then(Value::as_str),
+                result.get("url").and_then(Value::as_str),
+                result.get("description").and_then(Value::as_str),
+            )
+        })
+        .take(count)
+        .collect()
+}
+
+async fn search(
+    settings: &WebSearchSettings,
+    query: &str,
+    count: usize,
+) -> Result<Vec<Citation>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let count_param = count.to_string();
+
+    let request = match settings.backend {
+        SearchBackend::Searxng => {
+            let base = settings
+                .searxng_url
+                .as_deref()
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .ok_or("No SearxNG instance is configured")?;
+            client
+                .get(format!("{}/search", base.trim_end_matches('/')))
+                .query(&[("q", query), ("format", "json")])
+        }
+        SearchBackend::Brave => {
+            let key = secrets::get(secrets::AI, BRAVE_API_KEY)
+                .map_err(|e| e.to_string())?
+                .ok_or("Brave Search API key is not set")?;
+            client
+                .get(BRAVE_SEARCH_URL)
+                .query(&[("q", query), ("count", count_param.as_str())])
+                .header("X-Subscription-Token", key)
+        }
+    };
+
+    let response = request
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Search returned {}", response.status()));
+    }
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    Ok(match settings.backend {
+        SearchBackend::Searxng => parse_searxng(&body, count),
+        SearchBackend::Brave => parse_brave(&body, count),
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct Readable {
+    title: Option<String>,
+    text: String,
+}
+
+/// Keeps the text of a page's article, or its main element, or its body,
+/// with one line per block
+fn extract_readable(html: &str) -> Readable {
+    let html = COMMENT_REGEX.replace_all(html, "");
+    let title = TITLE_REGEX
+        .captures(&html)
+        .map(|caps| clean_text(&caps[1]))
+        .filter(|title| !title.is_empty());
+
+    let mut html = html.into_owned();
+    for regex in DROPPED_BLOCK_REGEXES.iter() {
+        html = regex.replace_all(&html, "").into_owned();
+    }
+
+    // Pages with several articles are usually feeds; the longest one is the
+    // best guess at what the link was for
+    let content = ARTICLE_REGEX
+        .captures_iter(&html)
+        .filter_map(|caps| caps.get(1))
+        .max_by_key(|article| article.len())
+        .or_else(|| MAIN_REGEX.captures(&html).and_then(|caps| caps.get(1)))
+        .or_else(|| BODY_REGEX.captures(&html).and_then(|caps| caps.get(1)))
+        .map_or(html.as_str(), |content| content.as_str());
+
+    let content = BLOCK_TAG_REGEX.replace_all(content, "\n");
+    let content = TAG_REGEX.replace_all(&content, "");
+    let text = unfurl::decode_entities(&content)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Readable { title, text }
+}
+
+async fn fetch_page(url: &str) -> Result<(Url, Readable), String> {
+    let url = Url::parse(url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    let (final_url, response) = unfurl::fetch_public(
+        url,
+        "text/html,application/xhtml+xml,text/plain",
+        USER_AGENT,
+    )
+    .await?;
+
+    let content_type = unfurl::content_type(&response);
+    let is_plain = content_type.starts_with("text/plain");
+    if !(is_plain || content_type.is_empty() || content_type.contains("html")) {
+        return Err(format!("Can't read {} content", content_type));
+    }
+    let body = unfurl::read_limited(response, MAX_PAGE_BYTES).await?;
+    let mut page = if is_plain {
+        Readable {
+            title: None,
+            text: body.trim().to_string(),
+        }
+    } else {
+        extract_readable(&body)
+    };
+    page.text = unfurl::truncate(page.text, MAX_PAGE_CHARS);
+    Ok((final_url, page))
+}
+
+/// The number a source is cited by, adding it if it's new
+fn cite(citations: &mut Vec<Citation>, citation: Citation) -> usize {
+    match citations.iter().position(|c| c.url == citation.url) {
+        Some(index) => index + 1,
+        None => {
+            citations.push(citation);
+            citations.len()
+        }
+    }
+}
+
+fn format_results(results: Vec<Citation>, citations: &mut Vec<Citation>) -> String {
+    if results.is_empty() {
+        return "No results.".to_string();
+    }
+    results
+        .into_iter()
+        .map(|result| {
+            let entry = format!(
+                "{}\n{}\n{}",
+                result.title,
+                result.url,
+                result.snippet.as_deref().unwrap_or_default()
+            );
+            format!("[{}] {}", cite(citations, result), entry.trim_end())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Passages as numbered sources; passages from the same file share a number
+fn format_passages(
+    passages: Vec<embeddings::RetrievedChunk>,
+    citations: &mut Vec<Citation>,
+) -> String {
+    if passages.is_empty() {
+        return "Nothing relevant in the indexed notes.".to_string();
+    }
+    passages
+        .into_iter()
+        .map(|passage| {
+            let path = Path::new(&passage.path);
+            let title = path.file_name().map_or(passage.path.clone(), |name| {
+                name.to_string_lossy().to_string()
+            });
+            let url = Url::from_file_path(path)
+                .map(String::from)
+                .unwrap_or_else(|_| passage.path.clone());
+            let number = cite(
+                citations,
+                Citation {
+                    title: title.clone(),
+                    url,
+                    snippet: passage
+                        .content
+                        .lines()
+                        .next()
+                        .map(|line| unfurl::truncate(line.to_string(), MAX_SNIPPET_CHARS)),
+                },
+            );
+            format!(
+                "[{}] {}\n{}\n\n{}",
+                number, title, passage.path, passage.content
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn requested_count(arguments: &Value) -> usize {
+    arguments
+        .get("count")
+        .and_then(Value::as_u64)
+        .map_or(DEFAULT_RESULT_COUNT, |count| count as usize)
+        .clamp(1, MAX_RESULT_COUNT)
+}
+
+/// Runs one tool call. Failures are reported to the model as the result, so
+/// it can try something else rather than abort the answer.
+async fn execute(
+    name: &str,
+    arguments: &str,
+    toolbox: &Toolbox<'_>,
+    citations: &mut Vec<Citation>,
+) -> String {
+    let arguments: Value = serde_json::from_str(arguments).unwrap_or(Value::Null);
+    if let Some(tool) = toolbox
+        .extensions
+        .iter()
+        .find(|tool| tool.function_name() == name)
+    {
+        return extension_tools::run(toolbox.app, tool, arguments)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(tool = name, error = %e, "Extension AI tool call failed");
+                format!("Error: {}", e)
+            });
+    }
+    let query = arguments.get("query").and_then(Value::as_str);
+    let result = match (name, toolbox.web_search, toolbox.retrieval) {
+        ("web_search", Some(settings), _) => match query {
+            Some(query) => search(settings, query, requested_count(&arguments))
+                .await
+                .map(|results| format_results(results, citations)),
+            None => Err("Missing query".to_string()),
+        },
+        ("retrieve", _, Some(settings)) => match query {
+            Some(query) => embeddings::retrieve(
+                toolbox.app,
+                settings,
+                query,
+                Some(requested_count(&arguments)),
+            )
+            .await
+            .map(|passages| format_passages(passages, citations)),
+            None => Err("Missing query".to_string()),
+        },
+        ("fetch_url", Some(_), _) => match arguments.get("url").and_then(Value::as_str) {
+            Some(url) => fetch_page(url).await.map(|(url, page)| {
+                let url = url.to_string();
+                let title = page.title.unwrap_or_else(|| url.clone());
+                let number = cite(
+                    citations,
+                    Citation {
+                        title: title.clone(),
+                        url: url.clone(),
+                        snippet: page
+                            .text
+                            .lines()
+                            .next()
+                            .map(|line| unfurl::truncate(line.to_string(), MAX_SNIPPET_CHARS)),
+                    },
+                );
+                format!("[{}] {}\n{}\n\n{}", number, title, url, page.text)
+            }),
+            None => Err("Missing url".to_string()),
+        },
+        _ => Err(format!("Unknown tool '{}'", name)),
+    };
+    result.unwrap_or_else(|e| {
+        tracing::warn!(tool = name, error = %e, "AI tool call failed");
+        format!("Error: {}", e)
+    })
+}
+
+/// Lets the model call the tools for up to `MAX_TOOL_ROUNDS` rounds, adding
+/// its calls and their results to `messages`
+pub async fn ground(
+    target: &ChatTarget<'_>,
+    messages: &mut Vec<Value>,
+    toolbox: &Toolbox<'_>,
+) -> Result<Grounding, String> {
+    let client = reqwest::Client::new();
+    let tools = definitions(toolbox);
+    let mut citations = Vec::new();
+
+    for _ in 0..MAX_TOOL_ROUNDS {
+        let body = json!({
+            "model": target.model,
+            "messages": messages,
+            "tools": tools,
+            "temperature": target.temperature,
+            "stream": false,
+        });
+        let mut request = client.post(target.url).json(&body);
+        if let Some(auth) = target.auth {
+            request = request.header("Authorization", auth);
+            request = request.header("HTTP-Referer", "http://localhost");
+        }
+        let res = request.send().await.map_err(|e| e.to_string())?;
+        if !res.status().is_success() {
+            let error_body = res.text().await.unwrap_or_else(|_| "Unknown error".into());
+            return Err(format!("API Error: {}", error_body));
+        }
+        let json: Value = res.json().await.map_err(|e| e.to_string())?;
+        let message = json
+            .pointer("/choices/0/message")
+            .cloned()
+            .ok_or("API response has no message")?;
+
+        let calls = message
+            .get("tool_calls")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        if calls.is_empty() {
+            let answer = message
+                .get("content")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            return Ok(Grounding {
+                answer: Some(answer),
+                citations,
+            });
+        }
+
+        messages.push(message);
+        for call in calls {
+            let name = call
+                .pointer("/function/name")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let arguments = call
+                .pointer("/function/arguments")
+                .and_then(Value::as_str)
+                .unwrap_or("{}");
+            let content = execute(name, arguments, toolbox, &mut citations).await;
+            messages.push(json!({
+                "role": "tool",
+                "tool_call_id": call.get("id").cloned().unwrap_or(Value::Null),
+                "content": content,
+            }));
+        }
+    }
+
+    Ok(Grounding {
+        answer: None,
+        citations,
+    })
+}
+
+#[tauri::command]
+pub fn set_web_search_api_key(key: String) -> Result<(), String> {
+    secrets::set(secrets::AI, BRAVE_API_KEY, &key).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn is_web_search_api_key_set() -> Result<bool, String> {
+    secrets::exists(secrets::AI, BRAVE_API_KEY).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_web_search_api_key() -> Result<(), String> {
+    secrets::delete(secrets::AI, BRAVE_API_KEY).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_searxng() {
+        let response = json!({
+            "results": [
+                { "title": "Rust &amp; Cargo", "url": "https://doc.rust-lang.org/cargo", "content": "The  Rust package\nmanager" },
+                { "title": "No URL" },
+                { "url": "https://example.com" }
+            ]
+        });
+        assert_eq!(
+            parse_searxng(&response, 5),
+            vec![
+                Citation {
+                    title: "Rust & Cargo".to_string(),
+                    url: "https://doc.rust-lang.org/cargo".to_string(),
+                    snippet: Some("The Rust package manager".to_string()),
+                },
+                Citation {
+                    title: "https://example.com".to_string(),
+                    url: "https://example.com".to_string(),
+                    snippet: None,
+                },
+            ]
+        );
+        assert_eq!(parse_searxng(&response, 1).len(), 1);
+        assert!(parse_searxng(&json!({}), 5).is_empty());
+    }
+
+    #[test]
+    fn test_parse_brave() {
+        let response = json!({
+            "web": { "results": [
+                { "title": "Tauri", "url": "https://tauri.app", "description": "Build <strong>apps</strong>" }
+            ] }
+        });
+        let results = parse_brave(&response, 5);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].snippet.as_deref(), Some("Build apps"));
+    }
+
+    #[test]
+    fn test_extract_readable_prefers_article() {
+        let html = r#"<html><head><title>A &quot;Post&quot;</title>
+            <style>p { color: red }</style></head>
+            <body><header><h1>Site</h1></header><nav><a href="/">Home</a></nav>
+            <article><h2>Intro</h2><p>First   paragraph.</p><!-- ad --><p>Second<br>line &amp; more</p>
+            <script>track()</script></article>
+            <article><p>Short</p></article>
+            <footer>Copyright</footer></body></html>"#;
+        assert_eq!(
+            extract_readable(html),
+            Readable {
+                title: Some("A \"Post\"".to_string()),
+                text: "Intro\nFirst paragraph.\nSecond\nline & more".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_extract_readable_falls_back_to_body() {
+        let html = "<body><div>One</div><aside>Related</aside><div>Two</div></body>";
+        let page = extract_readable(html);
+        assert_eq!(page.title, None);
+        assert_eq!(page.text, "One\nTwo");
+        assert_eq!(extract_readable("plain <b>text</b>").text, "plain text");
+    }
+
+    #[test]
+    fn test_citations_are_numbered_once_per_url() {
+        let result = |url: &str| Citation {
+            title: url.to_string(),
+            url: url.to_string(),
+            snippet: None,
+        };
+        let mut citations = Vec::new();
+        let first = format_results(
+            vec![result("https://a"), result("https://b")],
+            &mut citations,
+        );
+        assert!(first.starts_with("[1] https://a"));
+        let second = format_results(
+            vec![result("https://b"), result("https://c")],
+            &mut citations,
+        );
+        assert!(second.starts_with("[2] https://b"));
+        assert!(second.contains("[3] https://c"));
+        assert_eq!(citations.len(), 3);
+        assert_eq!(format_results(Vec::new(), &mut citations), "No results.");
+    }
+}
@@ -0,0 +1,393 @@
+//! User-defined keywords for anything launchable. Typing an alias's keyword
+//! as the first word of a query picks its target directly, ahead of fuzzy
+//! matching, and the rest of the query is passed on as the argument
+//! (`gh tauri` runs the GitHub search quicklink with `tauri`).
+
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+use tauri::{AppHandle, Manager};
+
+const ALIASES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS aliases (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    keyword TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    target_kind TEXT NOT NULL,
+    target TEXT NOT NULL,
+    description TEXT,
+    use_count INTEGER NOT NULL DEFAULT 0,
+    last_used_at INTEGER,
+    created_at INTEGER NOT NULL
+)";
+
+const MAX_KEYWORD_LENGTH: usize = 32;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AliasTargetKind {
+    /// A builtin command, by command id
+    Command,
+    /// An extension command, as `<extension>/<command>`
+    Extension,
+    /// A quicklink, by id
+    Quicklink,
+    /// A shell command; arguments are passed as `$1`, `$2`, ... and `$@`
+    Script,
+}
+
+impl AliasTargetKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AliasTargetKind::Command => "command",
+            AliasTargetKind::Extension => "extension",
+            AliasTargetKind::Quicklink => "quicklink",
+            AliasTargetKind::Script => "script",
+        }
+    }
+
+    fn from_db(value: &str) -> Self {
+        match value {
+            "extension" => AliasTargetKind::Extension,
+            "quicklink" => AliasTargetKind::Quicklink,
+            "script" => AliasTargetKind::Script,
+            _ => AliasTargetKind::Command,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Alias {
+    pub id: i64,
+    pub keyword: String,
+    pub target_kind: AliasTargetKind,
+    pub target: String,
+    pub description: Option<String>,
+    pub use_count: i64,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Storable for Alias {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let last_used_at: Option<i64> = row.get(6)?;
+        let created_at: i64 = row.get(7)?;
+        Ok(Alias {
+            id: row.get(0)?,
+            keyword: row.get(1)?,
+            target_kind: AliasTargetKind::from_db(&row.get::<_, String>(2)?),
+            target: row.get(3)?,
+            description: row.get(4)?,
+            use_count: row.get(5)?,
+            last_used_at: last_used_at.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_default(),
+        })
+    }
+}
+
+/// An alias a palette query starts with, plus whatever followed the keyword
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedAlias {
+    pub alias: Alias,
+    pub argument: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AliasInput {
+    pub keyword: String,
+    pub target_kind: AliasTargetKind,
+    pub target: String,
+    pub description: Option<String>,
+}
+
+fn validate_alias(input: &AliasInput) -> Result<(), String> {
+    let keyword = input.keyword.trim();
+    if keyword.is_empty() || keyword.contains(char::is_whitespace) {
+        return Err("Keyword must be a single word".to_string());
+    }
+    if keyword.chars().count() > MAX_KEYWORD_LENGTH {
+        return Err(format!(
+            "Keyword can be at most {} characters",
+            MAX_KEYWORD_LENGTH
+        ));
+    }
+    if input.target.trim().is_empty() {
+        return Err("Alias needs a target".to_string());
+    }
+    match input.target_kind {
+        AliasTargetKind::Quicklink if input.target.trim().parse::<i64>().is_err() => {
+            Err("Quicklink target must be a quicklink id".to_string())
+        }
+        AliasTargetKind::Extension if !input.target.contains('/') => {
+            Err("Extension target must be <extension>/<command>".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+const SELECT_ALIASES: &str = "SELECT id, keyword, target_kind, target, description, use_count, last_used_at, created_at FROM aliases";
+
+pub struct AliasManager {
+    store: Store,
+}
+
+impl AliasManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "aliases.sqlite")?;
+        store.init_table(ALIASES_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(ALIASES_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    fn list_aliases(&self) -> Result<Vec<Alias>, AppError> {
+        self.store.query(
+            &format!("{} ORDER BY keyword COLLATE NOCASE ASC", SELECT_ALIASES),
+            [],
+        )
+    }
+
+    fn get_alias(&self, id: i64) -> Result<Alias, AppError> {
+        self.store
+            .query_row(&format!("{} WHERE id = ?", SELECT_ALIASES), params![id])?
+            .ok_or_else(|| AppError::Rusqlite(rusqlite::Error::QueryReturnedNoRows))
+    }
+
+    fn find_by_keyword(&self, keyword: &str) -> Result<Option<Alias>, AppError> {
+        self.store.query_row(
+            &format!("{} WHERE keyword = ?", SELECT_ALIASES),
+            params![keyword],
+        )
+    }
+
+    fn create_alias(&self, input: AliasInput) -> Result<i64, AppError> {
+        self.store.execute(
+            "INSERT INTO aliases (keyword, target_kind, target, description, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+            params![
+                input.keyword.trim(),
+                input.target_kind.as_str(),
+                input.target.trim(),
+                input.description,
+                Utc::now().timestamp()
+            ],
+        )?;
+        Ok(self.store.last_insert_rowid())
+    }
+
+    fn update_alias(&self, id: i64, input: AliasInput) -> Result<(), AppError> {
+        self.store.execute(
+            "UPDATE aliases SET keyword = ?, target_kind = ?, target = ?, description = ?
+             WHERE id = ?",
+            params![
+                input.keyword.trim(),
+                input.target_kind.as_str(),
+                input.target.trim(),
+                input.description,
+                id
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete_alias(&self, id: i64) -> Result<(), AppError> {
+        self.store
+            .execute("DELETE FROM aliases WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    fn record_usage(&self, id: i64) -> Result<(), AppError> {
+        self.store.execute(
+            "UPDATE aliases SET use_count = use_count + 1, last_used_at = ? WHERE id = ?",
+            params![Utc::now().timestamp(), id],
+        )?;
+        Ok(())
+    }
+
+    /// `gh tauri` resolves to the `gh` alias with argument `tauri`; a bare
+    /// `gh` resolves without an argument
+    fn resolve(&self, input: &str) -> Result<Option<ResolvedAlias>, AppError> {
+        let input = input.trim_start();
+        let (keyword, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        if keyword.is_empty() {
+            return Ok(None);
+        }
+        let argument = Some(rest.trim()).filter(|rest| !rest.is_empty());
+        Ok(self.find_by_keyword(keyword)?.map(|alias| ResolvedAlias {
+            alias,
+            argument: argument.map(String::from),
+        }))
+    }
+}
+
+fn script_command(script: &str, argument: Option<&str>) -> Command {
+    let mut command = Command::new("sh");
+    // `$0` is the name shown in process listings, then the arguments
+    command.arg("-c").arg(script).arg("flare-alias");
+    if let Some(argument) = argument {
+        command.args(argument.split_whitespace());
+    }
+    command
+}
+
+#[tauri::command]
+pub fn list_aliases(app: AppHandle) -> Result<Vec<Alias>, String> {
+    app.state::<AliasManager>()
+        .list_aliases()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_alias(app: AppHandle, alias: AliasInput) -> Result<i64, String> {
+    validate_alias(&alias)?;
+    app.state::<AliasManager>()
+        .create_alias(alias)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_alias(app: AppHandle, id: i64, alias: AliasInput) -> Result<(), String> {
+    validate_alias(&alias)?;
+    app.state::<AliasManager>()
+        .update_alias(id, alias)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_alias(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<AliasManager>()
+        .delete_alias(id)
+        .map_err(|e| e.to_string())
+}
+
+/// Checked by the palette before fuzzy matching
+#[tauri::command]
+pub fn resolve_alias(app: AppHandle, query: String) -> Result<Option<ResolvedAlias>, String> {
+    app.state::<AliasManager>()
+        .resolve(&query)
+        .map_err(|e| e.to_string())
+}
+
+/// Called when the frontend runs a command, extension or quicklink alias
+#[tauri::command]
+pub fn record_alias_usage(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<AliasManager>()
+        .record_usage(id)
+        .map_err(|e| e.to_string())
+}
+
+/// Starts a script alias detached, with the argument split into words
+#[tauri::command]
+pub fn run_alias_script(app: AppHandle, id: i64, argument: Option<String>) -> Result<(), String> {
+    let manager = app.state::<AliasManager>();
+    let alias = manager.get_alias(id).map_err(|e| e.to_string())?;
+    if alias.target_kind != AliasTargetKind::Script {
+        return Err(format!("'{}' is not a script alias", alias.keyword));
+    }
+
+    let mut child = script_command(&alias.target, argument.as_deref())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run '{}': {}", alias.keyword, e))?;
+    // Reap the child so finished scripts don't linger as zombies
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    manager.record_usage(id).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(keyword: &str, target_kind: AliasTargetKind, target: &str) -> AliasInput {
+        AliasInput {
+            keyword: keyword.to_string(),
+            target_kind,
+            target: target.to_string(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_with_argument_passthrough() {
+        let manager = AliasManager::new_for_test().unwrap();
+        manager
+            .create_alias(input("gh", AliasTargetKind::Quicklink, "3"))
+            .unwrap();
+
+        let resolved = manager.resolve("GH  tauri plugins ").unwrap().unwrap();
+        assert_eq!(resolved.alias.keyword, "gh");
+        assert_eq!(resolved.alias.target_kind, AliasTargetKind::Quicklink);
+        assert_eq!(resolved.argument.as_deref(), Some("tauri plugins"));
+
+        let bare = manager.resolve("gh").unwrap().unwrap();
+        assert_eq!(bare.argument, None);
+
+        assert!(manager.resolve("ghost").unwrap().is_none());
+        assert!(manager.resolve("  ").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_keywords_are_unique_ignoring_case() {
+        let manager = AliasManager::new_for_test().unwrap();
+        manager
+            .create_alias(input("cal", AliasTargetKind::Command, "calendar"))
+            .unwrap();
+        assert!(manager
+            .create_alias(input("CAL", AliasTargetKind::Command, "calculator"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_update_delete_and_usage() {
+        let manager = AliasManager::new_for_test().unwrap();
+        let id = manager
+            .create_alias(input("t", AliasTargetKind::Command, "timers"))
+            .unwrap();
+        manager
+            .update_alias(id, input("tm", AliasTargetKind::Extension, "timer/start"))
+            .unwrap();
+        manager.record_usage(id).unwrap();
+
+        let alias = manager.get_alias(id).unwrap();
+        assert_eq!(alias.keyword, "tm");
+        assert_eq!(alias.target_kind, AliasTargetKind::Extension);
+        assert_eq!(alias.use_count, 1);
+        assert!(alias.last_used_at.is_some());
+
+        manager.delete_alias(id).unwrap();
+        assert!(manager.list_aliases().unwrap().is_empty());
+        assert!(manager.get_alias(id).is_err());
+    }
+
+    #[test]
+    fn test_validate_alias() {
+        assert!(validate_alias(&input("gh", AliasTargetKind::Quicklink, "4")).is_ok());
+        assert!(validate_alias(&input("g h", AliasTargetKind::Command, "x")).is_err());
+        assert!(validate_alias(&input("gh", AliasTargetKind::Quicklink, "github")).is_err());
+        assert!(validate_alias(&input("x", AliasTargetKind::Extension, "spotify")).is_err());
+        assert!(validate_alias(&input("x", AliasTargetKind::Script, " ")).is_err());
+    }
+
+    #[test]
+    fn test_script_arguments_are_positional() {
+        let command = script_command("echo \"$1\"", Some("a; rm -rf b"));
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            vec!["-c", "echo \"$1\"", "flare-alias", "a;", "rm", "-rf", "b"]
+        );
+    }
+}
@@ -0,0 +1,680 @@
+//! Quick markup for screenshots before they're copied: crop, rectangles,
+//! arrows, blurred regions and text, composited onto the image in order.
+//!
+//! Coordinates are in image pixels. A crop changes the origin, so
+//! annotations after it are relative to the cropped image.
+
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use image::{imageops, ImageFormat, Pixel, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_COLOR: &str = "#ff3b30";
+/// Tried when fontconfig isn't available
+const FALLBACK_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/dejavu-sans-fonts/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Bold.ttf",
+    "/usr/share/fonts/noto/NotoSans-Bold.ttf",
+];
+const TEXT_PADDING: f32 = 6.0;
+
+fn default_color() -> String {
+    DEFAULT_COLOR.to_string()
+}
+
+fn default_thickness() -> u32 {
+    4
+}
+
+fn default_strength() -> f32 {
+    8.0
+}
+
+fn default_text_size() -> f32 {
+    24.0
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Annotation {
+    Crop {
+        region: Region,
+    },
+    Rectangle {
+        region: Region,
+        #[serde(default = "default_color")]
+        color: String,
+        #[serde(default = "default_thickness")]
+        thickness: u32,
+        #[serde(default)]
+        filled: bool,
+    },
+    Arrow {
+        from: Position,
+        to: Position,
+        #[serde(default = "default_color")]
+        color: String,
+        #[serde(default = "default_thickness")]
+        thickness: u32,
+    },
+    /// Pixelating hides text reliably; a gaussian blur can sometimes be undone
+    Blur {
+        region: Region,
+        #[serde(default = "default_strength")]
+        strength: f32,
+        #[serde(default)]
+        pixelate: bool,
+    },
+    Text {
+        position: Position,
+        text: String,
+        #[serde(default = "default_color")]
+        color: String,
+        #[serde(default = "default_text_size")]
+        size: f32,
+        background: Option<String>,
+    },
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotatableImage {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// `#rgb`, `#rrggbb` or `#rrggbbaa`
+fn parse_color(color: &str) -> Result<Rgba<u8>, String> {
+    let hex = color.trim().trim_start_matches('#');
+    let invalid = || format!("Invalid color '{}'", color);
+    let channel = |i: usize, len: usize| {
+        u8::from_str_radix(hex.get(i..i + len).ok_or_else(invalid)?, 16).map_err(|_| invalid())
+    };
+    match hex.len() {
+        3 => {
+            let [r, g, b] = [0, 1, 2].map(|i| channel(i, 1).map(|v| v * 17));
+            Ok(Rgba([r?, g?, b?, 255]))
+        }
+        6 | 8 => {
+            let alpha = if hex.len() == 8 { channel(6, 2)? } else { 255 };
+            Ok(Rgba([
+                channel(0, 2)?,
+                channel(2, 2)?,
+                channel(4, 2)?,
+                alpha,
+            ]))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn blend_pixel(image: &mut RgbaImage, x: i64, y: i64, color: Rgba<u8>, coverage: f32) {
+    if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+        return;
+    }
+    let mut color = color;
+    color[3] = (color[3] as f32 * coverage.clamp(0.0, 1.0)).round() as u8;
+    image.get_pixel_mut(x as u32, y as u32).blend(&color);
+}
+
+fn fill_rect(image: &mut RgbaImage, x: i64, y: i64, width: i64, height: i64, color: Rgba<u8>) {
+    for py in y.max(0)..(y + height).min(image.height() as i64) {
+        for px in x.max(0)..(x + width).min(image.width() as i64) {
+            blend_pixel(image, px, py, color, 1.0);
+        }
+    }
+}
+
+/// Border drawn inside `region`, without overlapping corners so translucent
+/// colors stay even
+fn stroke_rect(image: &mut RgbaImage, region: &Region, thickness: u32, color: Rgba<u8>) {
+    let (x, y) = (region.x as i64, region.y as i64);
+    let (w, h) = (region.width as i64, region.height as i64);
+    let t = (thickness as i64).min(w / 2).min(h / 2).max(1);
+    fill_rect(image, x, y, w, t, color);
+    fill_rect(image, x, y + h - t, w, t, color);
+    fill_rect(image, x, y + t, t, h - 2 * t, color);
+    fill_rect(image, x + w - t, y + t, t, h - 2 * t, color);
+}
+
+/// Even-odd scanline fill, sampling at pixel centers
+fn fill_polygon(image: &mut RgbaImage, points: &[(f32, f32)], color: Rgba<u8>) {
+    if points.len() < 3 {
+        return;
+    }
+    let min_y = points
+        .iter()
+        .map(|p| p.1)
+        .fold(f32::MAX, f32::min)
+        .floor()
+        .max(0.0) as i64;
+    let max_y = points
+        .iter()
+        .map(|p| p.1)
+        .fold(f32::MIN, f32::max)
+        .ceil()
+        .min(image.height() as f32) as i64;
+
+    for y in min_y..max_y {
+        let center = y as f32 + 0.5;
+        let mut crossings: Vec<f32> = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .filter(|(a, b)| (a.1 <= center) != (b.1 <= center))
+            .map(|(a, b)| a.0 + (center - a.1) / (b.1 - a.1) * (b.0 - a.0))
+            .collect();
+        crossings.sort_by(f32::total_cmp);
+        for span in crossings.chunks_exact(2) {
+            let start = (span[0] - 0.5).ceil() as i64;
+            let end = (span[1] - 0.5).floor() as i64;
+            for x in start..=end {
+                blend_pixel(image, x, y, color, 1.0);
+            }
+        }
+    }
+}
+
+/// Outline of an arrow as a single polygon, so the shaft and head don't
+/// blend twice where they meet
+fn arrow_polygon(from: Position, to: Position, thickness: u32) -> Vec<(f32, f32)> {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < 1.0 {
+        return Vec::new();
+    }
+    let (ux, uy) = (dx / length, dy / length);
+    // Perpendicular unit vector
+    let (nx, ny) = (-uy, ux);
+
+    let half_shaft = thickness.max(1) as f32 / 2.0;
+    let head_length = (thickness as f32 * 4.0).max(12.0).min(length);
+    let half_head = head_length * 0.5;
+    let base = (to.x - ux * head_length, to.y - uy * head_length);
+
+    vec![
+        (from.x + nx * half_shaft, from.y + ny * half_shaft),
+        (base.0 + nx * half_shaft, base.1 + ny * half_shaft),
+        (base.0 + nx * half_head, base.1 + ny * half_head),
+        (to.x, to.y),
+        (base.0 - nx * half_head, base.1 - ny * half_head),
+        (base.0 - nx * half_shaft, base.1 - ny * half_shaft),
+        (from.x - nx * half_shaft, from.y - ny * half_shaft),
+    ]
+}
+
+/// `region` limited to the image, or `None` when nothing of it is inside
+fn clamp_region(image: &RgbaImage, region: &Region) -> Option<Region> {
+    let x = region.x.min(image.width());
+    let y = region.y.min(image.height());
+    let width = region.width.min(image.width() - x);
+    let height = region.height.min(image.height() - y);
+    (width > 0 && height > 0).then_some(Region {
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+fn blur_region(image: &mut RgbaImage, region: &Region, strength: f32, pixelate: bool) {
+    let Some(region) = clamp_region(image, region) else {
+        return;
+    };
+    let area =
+        imageops::crop_imm(&*image, region.x, region.y, region.width, region.height).to_image();
+    let strength = strength.max(1.0);
+    let processed = if pixelate {
+        let block = strength.round() as u32;
+        let small = imageops::resize(
+            &area,
+            (region.width / block).max(1),
+            (region.height / block).max(1),
+            imageops::FilterType::Triangle,
+        );
+        imageops::resize(
+            &small,
+            region.width,
+            region.height,
+            imageops::FilterType::Nearest,
+        )
+    } else {
+        imageops::blur(&area, strength)
+    };
+    imageops::replace(image, &processed, region.x as i64, region.y as i64);
+}
+
+fn draw_text(image: &mut RgbaImage, font: &FontVec, text: &str, options: TextOptions) {
+    let scale = PxScale::from(options.size.max(1.0));
+    let scaled = font.as_scaled(scale);
+    let line_height = scaled.height() + scaled.line_gap();
+    let lines: Vec<&str> = text.lines().collect();
+
+    let line_width = |line: &str| {
+        let mut width = 0.0;
+        let mut previous = None;
+        for c in line.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                width += scaled.kern(previous, id);
+            }
+            width += scaled.h_advance(id);
+            previous = Some(id);
+        }
+        width
+    };
+
+    if let Some(background) = options.background {
+        let width = lines
+            .iter()
+            .map(|line| line_width(line))
+            .fold(0.0, f32::max);
+        let height = line_height * lines.len() as f32;
+        fill_rect(
+            image,
+            (options.position.x - TEXT_PADDING).round() as i64,
+            (options.position.y - TEXT_PADDING).round() as i64,
+            (width + TEXT_PADDING * 2.0).round() as i64,
+            (height + TEXT_PADDING * 2.0).round() as i64,
+            background,
+        );
+    }
+
+    for (index, line) in lines.iter().enumerate() {
+        let baseline = options.position.y + scaled.ascent() + line_height * index as f32;
+        let mut caret = options.position.x;
+        let mut previous = None;
+        for c in line.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            let glyph = id.with_scale_and_position(scale, point(caret, baseline));
+            caret += scaled.h_advance(id);
+            previous = Some(id);
+
+            if let Some(outlined) = font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|x, y, coverage| {
+                    blend_pixel(
+                        image,
+                        bounds.min.x as i64 + x as i64,
+                        bounds.min.y as i64 + y as i64,
+                        options.color,
+                        coverage,
+                    )
+                });
+            }
+        }
+    }
+}
+
+struct TextOptions {
+    position: Position,
+    size: f32,
+    color: Rgba<u8>,
+    background: Option<Rgba<u8>>,
+}
+
+/// A bold sans-serif system font for text annotations
+fn load_font() -> Result<FontVec, String> {
+    let from_fontconfig = Command::new("fc-match")
+        .args(["-f", "%{file}", "sans-serif:bold"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|path| !path.is_empty());
+
+    from_fontconfig
+        .into_iter()
+        .chain(FALLBACK_FONTS.iter().map(|path| path.to_string()))
+        .filter_map(|path| fs::read(path).ok())
+        .find_map(|bytes| FontVec::try_from_vec(bytes).ok())
+        .ok_or_else(|| "No font found for text annotations (install fontconfig)".to_string())
+}
+
+/// Applies `annotations` in order. `font` is only needed for text.
+pub fn apply(
+    mut image: RgbaImage,
+    annotations: &[Annotation],
+    font: Option<&FontVec>,
+) -> Result<RgbaImage, String> {
+    for annotation in annotations {
+        match annotation {
+            Annotation::Crop { region } => {
+                let region = clamp_region(&image, region).ok_or("Crop is outside the image")?;
+                image = imageops::crop_imm(&image, region.x, region.y, region.width, region.height)
+                    .to_image();
+            }
+            Annotation::Rectangle {
+                region,
+                color,
+                thickness,
+                filled,
+            } => {
+                let color = parse_color(color)?;
+                if *filled {
+                    fill_rect(
+                        &mut image,
+                        region.x as i64,
+                        region.y as i64,
+                        region.width as i64,
+                        region.height as i64,
+                        color,
+                    );
+                } else {
+                    stroke_rect(&mut image, region, *thickness, color);
+                }
+            }
+            Annotation::Arrow {
+                from,
+                to,
+                color,
+                thickness,
+            } => {
+                let color = parse_color(color)?;
+                fill_polygon(&mut image, &arrow_polygon(*from, *to, *thickness), color);
+            }
+            Annotation::Blur {
+                region,
+                strength,
+                pixelate,
+            } => blur_region(&mut image, region, *strength, *pixelate),
+            Annotation::Text {
+                position,
+                text,
+                color,
+                size,
+                background,
+            } => {
+                let font = font.ok_or("No font loaded for text annotations")?;
+                let options = TextOptions {
+                    position: *position,
+                    size: *size,
+                    color: parse_color(color)?,
+                    background: background.as_deref().map(parse_color).transpose()?,
+                };
+                draw_text(&mut image, font, text, options);
+            }
+        }
+    }
+    Ok(image)
+}
+
+const CAPTURE_PREFIX: &str = "flare_capture";
+
+fn temp_image_path(prefix: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}_{}.png", prefix, uuid::Uuid::new_v4()))
+}
+
+/// Whether `path` is a capture `save_for_markup` left in the temp directory
+fn is_temp_capture(path: &Path) -> bool {
+    path.parent() == Some(std::env::temp_dir().as_path())
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(&format!("{}_", CAPTURE_PREFIX)))
+}
+
+fn save_png(image: &RgbaImage, path: &Path) -> Result<AnnotatableImage, String> {
+    image
+        .save_with_format(path, ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(AnnotatableImage {
+        path: path.to_string_lossy().to_string(),
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+fn save_for_markup(png: &[u8]) -> Result<AnnotatableImage, String> {
+    let image = image::load_from_memory(png)
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+    save_png(&image, &temp_image_path(CAPTURE_PREFIX))
+}
+
+fn copy_image(image: &RgbaImage) -> Result<(), String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    clipboard
+        .set_image(arboard::ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: Cow::Borrowed(image.as_raw()),
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// Hides the launcher, lets the user select a region and brings the launcher
+/// back with the capture ready for markup
+#[tauri::command]
+pub async fn annotate_capture(app: AppHandle) -> Result<AnnotatableImage, String> {
+    let window = app.get_webview_window("main");
+    if let Some(window) = &window {
+        let _ = window.hide();
+        // Give the compositor a moment to unmap the window before capturing
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    let captured = tauri::async_runtime::spawn_blocking(|| {
+        let png = crate::ocr::capture_region(&temp_image_path("flare_region"))?;
+        save_for_markup(&png)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if let Some(window) = &window {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    captured
+}
+
+#[tauri::command]
+pub async fn annotate_clipboard_image() -> Result<AnnotatableImage, String> {
+    tauri::async_runtime::spawn_blocking(|| save_for_markup(&crate::ocr::clipboard_image_png()?))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Renders the annotations onto the image at `path`, then copies the result
+/// (unless `copy` is false) and optionally saves it to `save_to`, returning
+/// the saved image. A temporary capture is removed once it has been used.
+#[tauri::command]
+pub async fn annotate_apply(
+    path: String,
+    annotations: Vec<Annotation>,
+    copy: Option<bool>,
+    save_to: Option<String>,
+) -> Result<Option<AnnotatableImage>, String> {
+    let copy = copy.unwrap_or(true);
+    if !copy && save_to.is_none() {
+        return Err("Nothing to do: neither copying nor saving".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let image = image::open(&path).map_err(|e| e.to_string())?.to_rgba8();
+        let needs_font = annotations
+            .iter()
+            .any(|annotation| matches!(annotation, Annotation::Text { .. }));
+        let font = if needs_font { Some(load_font()?) } else { None };
+        let annotated = apply(image, &annotations, font.as_ref())?;
+
+        if copy {
+            copy_image(&annotated)?;
+        }
+        let saved = save_to
+            .map(|save_to| save_png(&annotated, Path::new(&save_to)))
+            .transpose()?;
+        if is_temp_capture(Path::new(&path)) {
+            let _ = fs::remove_file(&path);
+        }
+        Ok(saved)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    fn canvas(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, WHITE)
+    }
+
+    fn region(x: u32, y: u32, width: u32, height: u32) -> Region {
+        Region {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_only_temp_captures_are_removed() {
+        assert!(is_temp_capture(&temp_image_path(CAPTURE_PREFIX)));
+        assert!(!is_temp_capture(&temp_image_path("flare_region")));
+        assert!(!is_temp_capture(Path::new("/home/me/flare_capture_1.png")));
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#f00").unwrap(), RED);
+        assert_eq!(parse_color("ff0000").unwrap(), RED);
+        assert_eq!(parse_color("#00ff0080").unwrap(), Rgba([0, 255, 0, 128]));
+        assert!(parse_color("#ff00").is_err());
+        assert!(parse_color("#gg0000").is_err());
+    }
+
+    #[test]
+    fn test_crop_then_rectangle() {
+        let annotations = [
+            Annotation::Crop {
+                region: region(10, 10, 50, 500),
+            },
+            Annotation::Rectangle {
+                region: region(0, 0, 20, 20),
+                color: "#f00".into(),
+                thickness: 2,
+                filled: false,
+            },
+        ];
+        let image = apply(canvas(100, 100), &annotations, None).unwrap();
+        assert_eq!(image.dimensions(), (50, 90));
+        assert_eq!(*image.get_pixel(0, 0), RED);
+        assert_eq!(*image.get_pixel(19, 10), RED);
+        assert_eq!(*image.get_pixel(10, 10), WHITE);
+        assert_eq!(*image.get_pixel(25, 25), WHITE);
+
+        let outside = [Annotation::Crop {
+            region: region(200, 0, 10, 10),
+        }];
+        assert!(apply(canvas(100, 100), &outside, None).is_err());
+    }
+
+    #[test]
+    fn test_translucent_fill_blends_once() {
+        let annotations = [Annotation::Rectangle {
+            region: region(0, 0, 10, 10),
+            color: "#00000080".into(),
+            thickness: 3,
+            filled: false,
+        }];
+        let image = apply(canvas(10, 10), &annotations, None).unwrap();
+        // Corners and edges must have exactly the same shade
+        assert_eq!(image.get_pixel(0, 0), image.get_pixel(1, 5));
+        assert_ne!(*image.get_pixel(0, 0), WHITE);
+    }
+
+    #[test]
+    fn test_arrow() {
+        let annotations = [Annotation::Arrow {
+            from: Position { x: 5.0, y: 50.0 },
+            to: Position { x: 90.0, y: 50.0 },
+            color: "#f00".into(),
+            thickness: 4,
+        }];
+        let image = apply(canvas(100, 100), &annotations, None).unwrap();
+        assert_eq!(*image.get_pixel(30, 50), RED);
+        // Head is wider than the shaft
+        assert_eq!(*image.get_pixel(78, 54), RED);
+        assert_eq!(*image.get_pixel(30, 55), WHITE);
+        assert_eq!(*image.get_pixel(95, 50), WHITE);
+    }
+
+    #[test]
+    fn test_blur_only_touches_region() {
+        let mut image = canvas(40, 40);
+        for y in 0..40 {
+            for x in (0..40).step_by(2) {
+                image.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+        let original = image.clone();
+        let annotations = [Annotation::Blur {
+            region: region(0, 0, 20, 20),
+            strength: 4.0,
+            pixelate: true,
+        }];
+        let blurred = apply(image, &annotations, None).unwrap();
+        assert_ne!(blurred.get_pixel(4, 4), original.get_pixel(4, 4));
+        assert_eq!(blurred.get_pixel(30, 30), original.get_pixel(30, 30));
+        assert_eq!(blurred.get_pixel(20, 4), original.get_pixel(20, 4));
+    }
+
+    #[test]
+    fn test_text_needs_font() {
+        let annotations = [Annotation::Text {
+            position: Position { x: 0.0, y: 0.0 },
+            text: "hi".into(),
+            color: default_color(),
+            size: default_text_size(),
+            background: None,
+        }];
+        assert!(apply(canvas(10, 10), &annotations, None).is_err());
+    }
+
+    #[test]
+    fn test_annotation_deserialize() {
+        let annotation: Annotation = serde_json::from_str(
+            r#"{"type":"rectangle","region":{"x":1,"y":2,"width":3,"height":4}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            annotation,
+            Annotation::Rectangle {
+                region: region(1, 2, 3, 4),
+                color: DEFAULT_COLOR.into(),
+                thickness: 4,
+                filled: false,
+            }
+        );
+    }
+}
@@ -1,23 +1,76 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// How an app was installed; native entries win when deduplicating
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AppSource {
+    #[default]
+    Native,
+    Flatpak,
+    Snap,
+    AppImage,
+}
+
+/// A `[Desktop Action]` entry, e.g. "New Private Window"
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppAction {
+    pub id: String,
+    pub name: String,
+    pub exec: String,
+}
+
+/// Desktop entry keys that affect how an app is started
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct LaunchOptions {
+    /// Path of the `.desktop` file, used for `%k` and the `gio launch` fallback
+    pub desktop_file: Option<String>,
+    pub terminal: bool,
+    pub startup_notify: bool,
+    pub startup_wm_class: Option<String>,
+    /// The entry's `Path` key
+    pub working_dir: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct App {
+    /// Desktop file id (`firefox.desktop`), or the file path for AppImages
+    #[serde(default)]
+    pub id: Option<String>,
     pub name: String,
     pub comment: Option<String>,
     pub exec: Option<String>,
     pub icon_path: Option<String>,
+    #[serde(default)]
+    pub source: AppSource,
+    #[serde(default)]
+    pub actions: Vec<AppAction>,
+    #[serde(default)]
+    pub launch: LaunchOptions,
+    /// The `MimeType` list, for "Open with…"
+    #[serde(default)]
+    pub mime_types: Vec<String>,
 }
 
 impl App {
     pub fn new(name: String) -> Self {
         Self {
+            id: None,
             name,
             comment: None,
             exec: None,
             icon_path: None,
+            source: AppSource::Native,
+            actions: Vec::new(),
+            launch: LaunchOptions::default(),
+            mime_types: Vec::new(),
         }
     }
 
+    pub fn with_id(mut self, id: Option<String>) -> Self {
+        self.id = id;
+        self
+    }
+
     pub fn with_comment(mut self, comment: Option<String>) -> Self {
         self.comment = comment;
         self
@@ -32,4 +85,24 @@ impl App {
         self.icon_path = icon_path;
         self
     }
+
+    pub fn with_source(mut self, source: AppSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn with_actions(mut self, actions: Vec<AppAction>) -> Self {
+        self.actions = actions;
+        self
+    }
+
+    pub fn with_launch_options(mut self, launch: LaunchOptions) -> Self {
+        self.launch = launch;
+        self
+    }
+
+    pub fn with_mime_types(mut self, mime_types: Vec<String>) -> Self {
+        self.mime_types = mime_types;
+        self
+    }
 }
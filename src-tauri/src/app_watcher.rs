@@ -0,0 +1,343 @@
+//! Keeps the app list current by watching the desktop entry and AppImage
+//! directories with inotify. Changed files are re-parsed one at a time and
+//! merged with the rest, the on-disk cache is rewritten, and `apps-changed`
+//! tells the frontend what was added, updated or removed.
+
+use crate::{
+    app::{App, AppSource},
+    cache::AppCache,
+    desktop::{AppScanOptions, DesktopFileManager, ScannedApp},
+    power_awareness,
+};
+use notify::{RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+use tauri::{AppHandle, Emitter};
+
+pub const APPS_CHANGED_EVENT: &str = "apps-changed";
+
+const DEBOUNCE: Duration = Duration::from_millis(500);
+/// How often directories that don't exist yet (e.g. Flatpak exports before
+/// the first Flatpak install) are checked for
+const MISSING_DIR_CHECK: Duration = Duration::from_secs(30);
+
+/// Bumped on every restart; older watcher threads stop when they notice
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppsChanged {
+    pub added: Vec<App>,
+    pub updated: Vec<App>,
+    /// Names of apps that are gone
+    pub removed: Vec<String>,
+}
+
+impl AppsChanged {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Apps are keyed by lowercased name, which is what deduplication uses
+fn diff(old: &[App], new: &[App]) -> AppsChanged {
+    let old_by_name: HashMap<String, &App> = old
+        .iter()
+        .map(|app| (app.name.to_lowercase(), app))
+        .collect();
+    let new_names: HashSet<String> = new.iter().map(|app| app.name.to_lowercase()).collect();
+
+    let mut changes = AppsChanged::default();
+    for app in new {
+        match old_by_name.get(&app.name.to_lowercase()) {
+            None => changes.added.push(app.clone()),
+            Some(previous) if *previous != app => changes.updated.push(app.clone()),
+            Some(_) => {}
+        }
+    }
+    changes.removed = old
+        .iter()
+        .filter(|app| !new_names.contains(&app.name.to_lowercase()))
+        .map(|app| app.name.clone())
+        .collect();
+    changes
+}
+
+/// Every parsed entry by file, so a single change can be merged without
+/// rescanning the rest
+struct AppIndex {
+    roots: Vec<(PathBuf, AppSource)>,
+    options: AppScanOptions,
+    entries: HashMap<PathBuf, ScannedApp>,
+    apps: Vec<App>,
+}
+
+impl AppIndex {
+    fn build(roots: Vec<(PathBuf, AppSource)>, options: AppScanOptions) -> Self {
+        let entries: HashMap<PathBuf, ScannedApp> =
+            DesktopFileManager::scan_roots(&roots, &options)
+                .into_iter()
+                .map(|scanned| (scanned.path.clone(), scanned))
+                .collect();
+        let apps = DesktopFileManager::merge_scanned(entries.values());
+        Self {
+            roots,
+            options,
+            entries,
+            apps,
+        }
+    }
+
+    fn update_file(&mut self, path: &Path) {
+        match DesktopFileManager::scan_path(&self.roots, &self.options, path) {
+            Some(scanned) => {
+                self.entries.insert(path.to_path_buf(), scanned);
+            }
+            None => {
+                self.entries.remove(path);
+            }
+        }
+    }
+
+    /// Re-reads `paths` (files or whole directories) and returns what changed
+    /// in the merged list
+    fn apply(&mut self, paths: &HashSet<PathBuf>) -> AppsChanged {
+        for path in paths {
+            // Covers deleted files as well as deleted or moved-away directories
+            self.entries
+                .retain(|entry, _| !entry.starts_with(path) || entry.exists());
+            if path.is_dir() {
+                let files: Vec<PathBuf> = DesktopFileManager::find_desktop_files(path)
+                    .into_iter()
+                    .chain(
+                        std::fs::read_dir(path)
+                            .into_iter()
+                            .flatten()
+                            .flatten()
+                            .map(|entry| entry.path())
+                            .filter(|file| file.is_file()),
+                    )
+                    .collect();
+                for file in files {
+                    self.update_file(&file);
+                }
+            } else if path.exists() {
+                self.update_file(path);
+            }
+        }
+
+        let apps = DesktopFileManager::merge_scanned(self.entries.values());
+        let changes = diff(&self.apps, &apps);
+        self.apps = apps;
+        changes
+    }
+}
+
+/// (Re)starts watching with the current app source settings, replacing any
+/// running watcher
+pub fn start(app: AppHandle) {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    thread::spawn(move || {
+        if let Err(e) = watch(&app, generation) {
+            tracing::error!(error = %e, "App directory watcher stopped");
+        }
+    });
+}
+
+fn is_current(generation: u64) -> bool {
+    GENERATION.load(Ordering::SeqCst) == generation
+}
+
+fn publish(app: &AppHandle, index: &AppIndex, changes: AppsChanged) {
+    if changes.is_empty() {
+        return;
+    }
+    tracing::info!(
+        added = changes.added.len(),
+        updated = changes.updated.len(),
+        removed = changes.removed.len(),
+        "Installed apps changed"
+    );
+    AppCache::store_apps(app, index.apps.clone(), &index.options);
+    let _ = app.emit(APPS_CHANGED_EVENT, &changes);
+}
+
+fn watch(app: &AppHandle, generation: u64) -> Result<(), String> {
+    let options = AppCache::scan_options(app);
+    let roots = DesktopFileManager::get_scan_roots(&options);
+
+    // The full scan also catches whatever changed while Flare wasn't running
+    let previous = AppCache::cached_apps(app);
+    let mut index = AppIndex::build(roots.clone(), options.clone());
+    if !is_current(generation) {
+        return Ok(());
+    }
+    let changes = diff(&previous, &index.apps);
+    publish(app, &index, changes);
+
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer = new_debouncer(DEBOUNCE, None, move |result: DebounceEventResult| {
+        let _ = tx.send(result);
+    })
+    .map_err(|e| e.to_string())?;
+
+    let mut watched: HashSet<PathBuf> = HashSet::new();
+    let mut first_pass = true;
+    loop {
+        if !is_current(generation) {
+            return Ok(());
+        }
+
+        // Watches are dropped with their directory, so removed directories
+        // are watched again once they come back
+        watched.retain(|dir| dir.is_dir());
+        let mut appeared = HashSet::new();
+        for (dir, source) in &roots {
+            if watched.contains(dir) || !dir.is_dir() {
+                continue;
+            }
+            let mode = if *source == AppSource::AppImage {
+                RecursiveMode::NonRecursive
+            } else {
+                RecursiveMode::Recursive
+            };
+            match debouncer.watcher().watch(dir, mode) {
+                Ok(()) => {
+                    debouncer.cache().add_root(dir, mode);
+                    watched.insert(dir.clone());
+                    if !first_pass {
+                        appeared.insert(dir.clone());
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = ?e, path = %dir.display(), "Failed to watch app directory")
+                }
+            }
+        }
+        if first_pass {
+            tracing::info!(count = watched.len(), "Watching app directories");
+            first_pass = false;
+        }
+        if !appeared.is_empty() {
+            let changes = index.apply(&appeared);
+            publish(app, &index, changes);
+        }
+
+        let events = match rx.recv_timeout(power_awareness::scaled(MISSING_DIR_CHECK)) {
+            Ok(Ok(events)) => events,
+            Ok(Err(errors)) => {
+                for error in errors {
+                    tracing::warn!(error = ?error, "App directory watch error");
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        if !is_current(generation) {
+            return Ok(());
+        }
+        let paths: HashSet<PathBuf> = events
+            .into_iter()
+            .flat_map(|event| event.event.paths)
+            .collect();
+        let changes = index.apply(&paths);
+        publish(app, &index, changes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn desktop_entry(name: &str, extra: &str) -> String {
+        format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={}\n{}",
+            name,
+            name.to_lowercase(),
+            extra
+        )
+    }
+
+    fn names(apps: &[App]) -> Vec<&str> {
+        apps.iter().map(|app| app.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_incremental_updates() {
+        let root = std::env::temp_dir().join(format!("flare_apps_{}", uuid::Uuid::new_v4()));
+        let native = root.join("native");
+        let flatpak = root.join("flatpak");
+        fs::create_dir_all(&native).unwrap();
+        fs::create_dir_all(&flatpak).unwrap();
+        fs::write(native.join("editor.desktop"), desktop_entry("Editor", "")).unwrap();
+
+        let mut index = AppIndex::build(
+            vec![
+                (native.clone(), AppSource::Native),
+                (flatpak.clone(), AppSource::Flatpak),
+            ],
+            AppScanOptions {
+                entry_cache_path: Some(root.join("entries.bincode")),
+                ..Default::default()
+            },
+        );
+        assert_eq!(names(&index.apps), vec!["Editor"]);
+
+        // A new install shows up as added
+        let browser = native.join("browser.desktop");
+        fs::write(&browser, desktop_entry("Browser", "")).unwrap();
+        let changes = index.apply(&HashSet::from([browser.clone()]));
+        assert_eq!(names(&changes.added), vec!["Browser"]);
+        assert_eq!(names(&index.apps), vec!["Browser", "Editor"]);
+
+        // A Flatpak duplicate of a native app stays hidden
+        let flatpak_editor = flatpak.join("org.editor.desktop");
+        fs::write(&flatpak_editor, desktop_entry("Editor", "")).unwrap();
+        assert!(index
+            .apply(&HashSet::from([flatpak_editor.clone()]))
+            .is_empty());
+
+        // ...until the native one is removed
+        fs::remove_file(native.join("editor.desktop")).unwrap();
+        let changes = index.apply(&HashSet::from([native.join("editor.desktop")]));
+        assert_eq!(names(&changes.updated), vec!["Editor"]);
+        assert_eq!(changes.updated[0].source, AppSource::Flatpak);
+
+        // Hiding an entry removes it
+        fs::write(&browser, desktop_entry("Browser", "NoDisplay=true\n")).unwrap();
+        let changes = index.apply(&HashSet::from([browser]));
+        assert_eq!(changes.removed, vec!["Browser"]);
+
+        // Removing a whole directory drops everything in it
+        fs::remove_dir_all(&flatpak).unwrap();
+        let changes = index.apply(&HashSet::from([flatpak]));
+        assert_eq!(changes.removed, vec!["Editor"]);
+        assert!(index.apps.is_empty());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = vec![App::new("A".to_string()), App::new("B".to_string())];
+        let new = vec![
+            App::new("a".to_string()).with_comment(Some("changed".to_string())),
+            App::new("C".to_string()),
+        ];
+        let changes = diff(&old, &new);
+        assert_eq!(names(&changes.added), vec!["C"]);
+        assert_eq!(names(&changes.updated), vec!["a"]);
+        assert_eq!(changes.removed, vec!["B"]);
+        assert!(diff(&new, &new).is_empty());
+    }
+}
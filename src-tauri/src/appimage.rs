@@ -0,0 +1,276 @@
+use crate::app::{App, AppSource};
+use freedesktop_file_parser::parse;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+/// Type 2 AppImages carry `AI\x02` at offset 8 of the ELF header
+const TYPE2_MAGIC: &[u8; 3] = b"AI\x02";
+const EXTRACT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Name, comment and icon pulled out of an AppImage, cached per file version
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct AppImageMetadata {
+    name: Option<String>,
+    comment: Option<String>,
+    icon_path: Option<String>,
+}
+
+pub fn is_appimage(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("appimage"))
+}
+
+fn is_type2(path: &Path) -> bool {
+    let mut header = [0u8; 11];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok()
+        && &header[8..11] == TYPE2_MAGIC
+}
+
+/// `Obsidian-1.5.3-x86_64.AppImage` -> `Obsidian`
+fn name_from_file(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let starts_version = |rest: &str| {
+        let rest = rest.strip_prefix('v').unwrap_or(rest);
+        rest.chars().next().is_some_and(|c| c.is_ascii_digit())
+    };
+    let end = stem
+        .char_indices()
+        .find(|(i, c)| (*c == '-' || *c == '_') && starts_version(&stem[i + 1..]))
+        .map_or(stem.len(), |(i, _)| i);
+    let name = stem[..end].replace(['-', '_'], " ");
+    if name.trim().is_empty() {
+        stem
+    } else {
+        name.trim().to_string()
+    }
+}
+
+/// Quotes an Exec argument as required by the desktop entry spec
+pub fn quote_exec_arg(arg: &str) -> String {
+    let needs_quoting = arg.chars().any(|c| {
+        c.is_whitespace()
+            || matches!(
+                c,
+                '"' | '\''
+                    | '\\'
+                    | '>'
+                    | '<'
+                    | '~'
+                    | '|'
+                    | '&'
+                    | ';'
+                    | '$'
+                    | '*'
+                    | '?'
+                    | '#'
+                    | '('
+                    | ')'
+                    | '`'
+            )
+    });
+    if !needs_quoting {
+        return arg.to_string();
+    }
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Cache key that changes whenever the AppImage is replaced or updated
+fn cache_key(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    let mut hasher = Sha256::new();
+    hasher.update(path.to_string_lossy().as_bytes());
+    hasher.update(modified.to_le_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    Some(hex::encode(&hasher.finalize()[..8]))
+}
+
+/// Runs the AppImage runtime's `--appimage-extract <pattern>` in `workdir`
+fn extract(appimage: &Path, pattern: &str, workdir: &Path) -> bool {
+    let Ok(mut child) = Command::new(appimage)
+        .arg("--appimage-extract")
+        .arg(pattern)
+        .current_dir(workdir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) if started.elapsed() < EXTRACT_TIMEOUT => {
+                thread::sleep(Duration::from_millis(50))
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+        }
+    }
+}
+
+/// Extracts the embedded desktop entry and `.DirIcon` into the cache
+fn extract_metadata(appimage: &Path, cache_dir: &Path, key: &str) -> AppImageMetadata {
+    let mut metadata = AppImageMetadata::default();
+    if !is_type2(appimage) {
+        return metadata;
+    }
+
+    let workdir = std::env::temp_dir().join(format!("flare_appimage_{}", key));
+    if fs::create_dir_all(&workdir).is_err() {
+        return metadata;
+    }
+    let root = workdir.join("squashfs-root");
+
+    if extract(appimage, "*.desktop", &workdir) {
+        let desktop_file = fs::read_dir(&root).ok().and_then(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .find(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+        });
+        if let Some(entry) = desktop_file
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| parse(&content).ok())
+        {
+            metadata.name = Some(entry.entry.name.default).filter(|name| !name.is_empty());
+            metadata.comment = entry.entry.comment.map(|lc| lc.default);
+        }
+    }
+
+    if extract(appimage, ".DirIcon", &workdir) {
+        let mut icon = root.join(".DirIcon");
+        // `.DirIcon` is usually a symlink to the real icon, which isn't
+        // extracted along with it
+        if let Ok(target) = fs::read_link(&icon) {
+            let target = target.to_string_lossy().trim_start_matches('/').to_string();
+            if extract(appimage, &target, &workdir) {
+                icon = root.join(target);
+            }
+        }
+        if let Ok(bytes) = fs::read(&icon) {
+            let extension = if bytes.starts_with(b"<") {
+                "svg"
+            } else {
+                "png"
+            };
+            let dest = cache_dir.join(format!("{}.{}", key, extension));
+            if fs::write(&dest, bytes).is_ok() {
+                metadata.icon_path = dest.to_str().map(String::from);
+            }
+        }
+    }
+
+    let _ = fs::remove_dir_all(&workdir);
+    metadata
+}
+
+fn load_metadata(appimage: &Path, cache_dir: Option<&Path>) -> AppImageMetadata {
+    let Some(cache_dir) = cache_dir else {
+        return AppImageMetadata::default();
+    };
+    let Some(key) = cache_key(appimage) else {
+        return AppImageMetadata::default();
+    };
+
+    let metadata_path = cache_dir.join(format!("{}.json", key));
+    if let Some(cached) = fs::read_to_string(&metadata_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+    {
+        return cached;
+    }
+
+    if fs::create_dir_all(cache_dir).is_err() {
+        return AppImageMetadata::default();
+    }
+    let metadata = extract_metadata(appimage, cache_dir, &key);
+    // Cached even when extraction failed, so broken AppImages aren't re-run on every scan
+    if let Ok(content) = serde_json::to_string(&metadata) {
+        let _ = fs::write(metadata_path, content);
+    }
+    metadata
+}
+
+pub fn find_appimages(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    dirs.iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+        .filter(|path| is_appimage(path))
+        .collect()
+}
+
+pub fn parse_appimage(path: &Path, cache_dir: Option<&Path>) -> App {
+    let metadata = load_metadata(path, cache_dir);
+    App::new(metadata.name.unwrap_or_else(|| name_from_file(path)))
+        .with_id(path.to_str().map(String::from))
+        .with_comment(metadata.comment)
+        .with_exec(Some(quote_exec_arg(&path.to_string_lossy())))
+        .with_icon_path(metadata.icon_path)
+        .with_source(AppSource::AppImage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_from_file() {
+        assert_eq!(
+            name_from_file(Path::new("/opt/Obsidian-1.5.3-x86_64.AppImage")),
+            "Obsidian"
+        );
+        assert_eq!(
+            name_from_file(Path::new("Cool_Retro_Term-v1.2.AppImage")),
+            "Cool Retro Term"
+        );
+        assert_eq!(name_from_file(Path::new("krita.appimage")), "krita");
+    }
+
+    #[test]
+    fn test_quote_exec_arg() {
+        assert_eq!(quote_exec_arg("/opt/app.AppImage"), "/opt/app.AppImage");
+        assert_eq!(
+            quote_exec_arg("/home/me/My Apps/app.AppImage"),
+            "\"/home/me/My Apps/app.AppImage\""
+        );
+        assert_eq!(quote_exec_arg("/tmp/$x"), "\"/tmp/\\$x\"");
+    }
+}
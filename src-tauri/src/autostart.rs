@@ -0,0 +1,287 @@
+//! Starting Flare at login, either through an XDG autostart entry (read by
+//! every desktop's session manager) or a systemd user service bound to the
+//! graphical session. Both launch with `--hidden`, so logging in doesn't
+//! pop the launcher up.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Passed by both autostart methods; see `main.rs`
+pub const HIDDEN_FLAG: &str = "--hidden";
+const DESKTOP_FILE: &str = "flare.desktop";
+const SERVICE: &str = "flare.service";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AutostartMethod {
+    DesktopEntry,
+    SystemdUser,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AutostartStatus {
+    pub enabled: bool,
+    pub method: Option<AutostartMethod>,
+    /// What the entry launches, to spot one left behind by a moved install
+    pub command: Option<String>,
+    pub systemd_available: bool,
+}
+
+fn config_dir() -> Result<PathBuf, String> {
+    dirs::config_dir().ok_or_else(|| "No config directory".to_string())
+}
+
+fn desktop_entry_path() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join("autostart").join(DESKTOP_FILE))
+}
+
+fn service_path() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join("systemd").join("user").join(SERVICE))
+}
+
+/// The binary to start. An AppImage's `current_exe` is inside its temporary
+/// mount, so the image itself is used instead.
+fn executable() -> Result<String, String> {
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Ok(appimage.to_string_lossy().into_owned());
+    }
+    std::env::current_exe()
+        .map(|exe| exe.to_string_lossy().into_owned())
+        .map_err(|e| format!("Failed to locate the Flare binary: {}", e))
+}
+
+/// Quotes an argument for a desktop entry `Exec` key
+fn desktop_quote(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    // String values unescape backslashes once more before the Exec quoting
+    // is read, and field codes start with % even inside quotes
+    quoted.replace('\\', "\\\\").replace('%', "%%")
+}
+
+/// Quotes an argument for a systemd `ExecStart` line
+fn systemd_quote(arg: &str) -> String {
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+fn desktop_entry(exe: &str) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Flare\n\
+         Comment=Start Flare in the background at login\n\
+         Exec={} {}\n\
+         Icon=flare\n\
+         Terminal=false\n\
+         NoDisplay=true\n\
+         X-GNOME-Autostart-enabled=true\n",
+        desktop_quote(exe),
+        HIDDEN_FLAG
+    )
+}
+
+fn service_unit(exe: &str) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Flare launcher\n\
+         PartOf=graphical-session.target\n\
+         After=graphical-session.target\n\
+         \n\
+         [Service]\n\
+         ExecStart={} {}\n\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=graphical-session.target\n",
+        systemd_quote(exe),
+        HIDDEN_FLAG
+    )
+}
+
+/// `key=value` from the group-less or first group of an ini-style file
+fn entry_value<'a>(contents: &'a str, key: &str) -> Option<&'a str> {
+    contents.lines().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        (name.trim() == key).then(|| value.trim())
+    })
+}
+
+/// A desktop entry the session manager will start. Desktop settings panels
+/// turn entries off with `Hidden` or the GNOME key rather than deleting them.
+fn desktop_entry_enabled(contents: &str) -> bool {
+    entry_value(contents, "Hidden") != Some("true")
+        && entry_value(contents, "X-GNOME-Autostart-enabled") != Some("false")
+}
+
+fn systemctl(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run systemctl: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(if stderr.is_empty() { stdout } else { stderr })
+    }
+}
+
+/// A user manager is running for this session
+fn systemd_available() -> bool {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(|dir| Path::new(&dir).join("systemd").join("private").exists())
+        .unwrap_or(false)
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn remove_file(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to remove {}: {}", path.display(), e))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn disable_service() -> Result<(), String> {
+    let path = service_path()?;
+    if !path.exists() {
+        return Ok(());
+    }
+    if systemd_available() {
+        systemctl(&["disable", SERVICE])?;
+    }
+    remove_file(&path)?;
+    if systemd_available() {
+        systemctl(&["daemon-reload"])?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn autostart_status() -> Result<AutostartStatus, String> {
+    let systemd_available = systemd_available();
+    let service = service_path()?;
+    if systemd_available && service.exists() && systemctl(&["is-enabled", SERVICE]).is_ok() {
+        let contents = fs::read_to_string(&service).unwrap_or_default();
+        return Ok(AutostartStatus {
+            enabled: true,
+            method: Some(AutostartMethod::SystemdUser),
+            command: entry_value(&contents, "ExecStart").map(str::to_string),
+            systemd_available,
+        });
+    }
+
+    let contents = fs::read_to_string(desktop_entry_path()?).ok();
+    let enabled = contents.as_deref().is_some_and(desktop_entry_enabled);
+    Ok(AutostartStatus {
+        enabled,
+        method: enabled.then_some(AutostartMethod::DesktopEntry),
+        command: contents
+            .as_deref()
+            .filter(|_| enabled)
+            .and_then(|contents| entry_value(contents, "Exec"))
+            .map(str::to_string),
+        systemd_available,
+    })
+}
+
+/// Turns autostart on with `method`, replacing the other method's entry so
+/// Flare isn't started twice
+#[tauri::command]
+pub fn enable_autostart(method: AutostartMethod) -> Result<AutostartStatus, String> {
+    let exe = executable()?;
+    match method {
+        AutostartMethod::DesktopEntry => {
+            disable_service()?;
+            write_file(&desktop_entry_path()?, &desktop_entry(&exe))?;
+        }
+        AutostartMethod::SystemdUser => {
+            if !systemd_available() {
+                return Err("No systemd user session is running".to_string());
+            }
+            remove_file(&desktop_entry_path()?)?;
+            write_file(&service_path()?, &service_unit(&exe))?;
+            systemctl(&["daemon-reload"])?;
+            systemctl(&["enable", SERVICE])?;
+        }
+    }
+    autostart_status()
+}
+
+#[tauri::command]
+pub fn disable_autostart() -> Result<AutostartStatus, String> {
+    disable_service()?;
+    remove_file(&desktop_entry_path()?)?;
+    autostart_status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(desktop_quote("/opt/Flare/flare"), "\"/opt/Flare/flare\"");
+        assert_eq!(
+            desktop_quote("/home/a b/$x\\100%.AppImage"),
+            "\"/home/a b/\\\\$x\\\\\\\\100%%.AppImage\""
+        );
+        assert_eq!(
+            systemd_quote("/home/a \"b\"/$flare"),
+            "\"/home/a \\\"b\\\"/$$flare\""
+        );
+    }
+
+    #[test]
+    fn test_generated_entries_start_hidden() {
+        let entry = desktop_entry("/usr/bin/flare");
+        assert_eq!(
+            entry_value(&entry, "Exec"),
+            Some("\"/usr/bin/flare\" --hidden")
+        );
+        assert!(desktop_entry_enabled(&entry));
+
+        let unit = service_unit("/usr/bin/flare");
+        assert_eq!(
+            entry_value(&unit, "ExecStart"),
+            Some("\"/usr/bin/flare\" --hidden")
+        );
+        assert_eq!(
+            entry_value(&unit, "WantedBy"),
+            Some("graphical-session.target")
+        );
+    }
+
+    #[test]
+    fn test_desktop_entry_disabled_by_settings_panels() {
+        let entry = desktop_entry("/usr/bin/flare");
+        assert!(!desktop_entry_enabled(&entry.replace(
+            "X-GNOME-Autostart-enabled=true",
+            "X-GNOME-Autostart-enabled=false"
+        )));
+        assert!(!desktop_entry_enabled(&format!("{}Hidden=true\n", entry)));
+    }
+}
@@ -0,0 +1,393 @@
+//! `flare bench`: starts the app with its window hidden, times the startup
+//! path and the work behind a palette query, prints one JSON object to
+//! stdout and exits. Logs go to stderr, so the output can be piped straight
+//! into a regression tracker.
+//!
+//! ```text
+//! flare bench [--iterations N] [--query TEXT]
+//! ```
+//!
+//! Snippet keyword matching is timed against a small and a large synthetic
+//! library; its per-keystroke cost should stay flat between the two.
+
+use crate::aliases;
+use crate::cache::AppCache;
+use crate::file_search::manager::FileSearchManager;
+use crate::matcher::{self, MatchCandidate};
+use crate::snippets::triggers::{TriggerIndex, TypedKeys};
+use crate::snippets::types::Snippet;
+use serde::Serialize;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_ITERATIONS: usize = 20;
+const DEFAULT_QUERY: &str = "term";
+/// Size of the synthetic item set the fuzzy matcher is timed against
+const MATCHER_CANDIDATES: usize = 10_000;
+const RESULT_LIMIT: usize = 100;
+/// Snippet library sizes the keyword matcher is timed with
+const TRIGGER_LIBRARIES: [usize; 2] = [100, 10_000];
+const TRIGGER_KEYSTROKES: usize = 100_000;
+
+static PROCESS_START: OnceLock<Instant> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchOptions {
+    pub iterations: usize,
+    pub query: String,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            iterations: DEFAULT_ITERATIONS,
+            query: DEFAULT_QUERY.to_string(),
+        }
+    }
+}
+
+/// `None` unless the first argument is `bench`
+pub fn options_from_args(args: impl IntoIterator<Item = String>) -> Option<BenchOptions> {
+    let mut args = args.into_iter();
+    if args.next().as_deref() != Some("bench") {
+        return None;
+    }
+    let mut options = BenchOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--iterations" | "-n" => {
+                if let Some(n) = args.next().and_then(|n| n.parse().ok()) {
+                    options.iterations = n;
+                }
+            }
+            "--query" | "-q" => {
+                if let Some(query) = args.next() {
+                    options.query = query;
+                }
+            }
+            other => eprintln!("flare bench: ignoring unknown argument '{}'", other),
+        }
+    }
+    options.iterations = options.iterations.max(1);
+    Some(options)
+}
+
+/// Called first thing in `run()`; cold start is measured from here
+pub fn mark_process_start() {
+    let _ = PROCESS_START.set(Instant::now());
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryTimings {
+    pub query: String,
+    pub results: usize,
+    pub first_ms: f64,
+    pub median_ms: f64,
+    pub p95_ms: f64,
+    pub iterations: usize,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerTimings {
+    pub keywords: usize,
+    pub build_ms: f64,
+    /// Mean over `TRIGGER_KEYSTROKES` simulated key presses
+    pub per_keystroke_ns: f64,
+    pub matches: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchReport {
+    pub version: &'static str,
+    /// From `run()` to the end of app setup, including webview creation
+    pub cold_start_ms: f64,
+    /// Reading and validating the on-disk app cache
+    pub app_cache_load_ms: f64,
+    /// Whether the cache was fresh; a stale cache means startup pays for a scan
+    pub app_cache_fresh: bool,
+    /// A full rescan of desktop entries, Flatpaks, Snaps and AppImages
+    pub app_scan_ms: f64,
+    pub app_count: usize,
+    pub plugin_discovery_ms: f64,
+    pub plugin_count: usize,
+    pub first_query: QueryTimings,
+    /// Fuzzy ranking alone, over a synthetic set of `MATCHER_CANDIDATES` items
+    pub matcher: QueryTimings,
+    pub snippet_triggers: Vec<TriggerTimings>,
+}
+
+fn millis(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1000.0 * 1000.0).round() / 1000.0
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, f64) {
+    let started = Instant::now();
+    let value = f();
+    (value, millis(started.elapsed()))
+}
+
+/// Percentile by nearest rank over sorted samples
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn query_timings(query: &str, samples: &[f64], results: usize) -> QueryTimings {
+    let first_ms = samples.first().copied().unwrap_or_default();
+    // The first run is reported on its own; warm runs make up the percentiles
+    let mut warm: Vec<f64> = samples.iter().skip(1).copied().collect();
+    if warm.is_empty() {
+        warm.push(first_ms);
+    }
+    warm.sort_by(|a, b| a.total_cmp(b));
+    QueryTimings {
+        query: query.to_string(),
+        results,
+        first_ms,
+        median_ms: percentile(&warm, 50.0),
+        p95_ms: percentile(&warm, 95.0),
+        iterations: samples.len(),
+    }
+}
+
+/// The backend work behind one palette query: apps, frecency, alias
+/// resolution and the file index
+fn run_query(app: &AppHandle, query: &str) -> usize {
+    let apps = crate::get_installed_apps(app.clone());
+    let _ = crate::get_frecency_data(app.clone());
+    let _ = aliases::resolve_alias(app.clone(), query.to_string());
+
+    let candidates: Vec<MatchCandidate> = apps
+        .iter()
+        .map(|app| MatchCandidate {
+            id: app.id.clone().unwrap_or_else(|| app.name.clone()),
+            text: app.name.clone(),
+            keywords: app.comment.iter().cloned().collect(),
+        })
+        .collect();
+    let mut results = matcher::rank(query, &candidates, RESULT_LIMIT).len();
+    if let Some(files) = app.try_state::<FileSearchManager>() {
+        results += files
+            .search_files(query, u32::try_from(RESULT_LIMIT).unwrap_or(u32::MAX))
+            .map(|f| f.len())
+            .unwrap_or(0);
+    }
+    results
+}
+
+/// Names shaped like launcher items: several words, mixed case, paths
+fn synthetic_candidates(count: usize) -> Vec<MatchCandidate> {
+    const WORDS: &[&str] = &[
+        "terminal",
+        "Settings",
+        "browser",
+        "files",
+        "Text",
+        "editor",
+        "Music",
+        "player",
+        "system",
+        "monitor",
+        "clipboard",
+        "history",
+        "window",
+        "Manager",
+        "notes",
+        "calendar",
+    ];
+    (0..count)
+        .map(|i| {
+            let words: Vec<&str> = (0..3)
+                .map(|n| WORDS[(i * 7 + n * 5) % WORDS.len()])
+                .collect();
+            MatchCandidate {
+                id: i.to_string(),
+                text: format!(
+                    "{} {}{} ~/projects/{}-{}",
+                    words[0], words[1], words[2], words[0], i
+                ),
+                keywords: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+fn measure_matcher(options: &BenchOptions) -> QueryTimings {
+    let candidates = synthetic_candidates(MATCHER_CANDIDATES);
+    let mut samples = Vec::with_capacity(options.iterations);
+    let mut results = 0;
+    for _ in 0..options.iterations {
+        let (count, elapsed) =
+            timed(|| matcher::rank(&options.query, &candidates, RESULT_LIMIT).len());
+        results = count;
+        samples.push(elapsed);
+    }
+    query_timings(&options.query, &samples, results)
+}
+
+fn synthetic_snippets(count: usize) -> Vec<Snippet> {
+    let now = chrono::Utc::now();
+    (0..count)
+        .map(|i| Snippet {
+            id: i as i64,
+            name: format!("Snippet {}", i),
+            keyword: format!(";s{}x", i),
+            content: format!("Expanded text {}", i),
+            created_at: now,
+            updated_at: now,
+            times_used: 0,
+            last_used_at: now,
+            folder: None,
+            tags: Vec::new(),
+        })
+        .collect()
+}
+
+fn measure_triggers(keywords: usize, keystrokes: usize) -> TriggerTimings {
+    let snippets = synthetic_snippets(keywords);
+    let (index, build_ms) = timed(|| TriggerIndex::new(&snippets));
+    // Prose with a keyword from the middle of the library now and then
+    let text: Vec<char> = format!("typing some ordinary words ;s{}x and more ", keywords / 2)
+        .chars()
+        .collect();
+    let mut typed = TypedKeys::new(30);
+    let mut matches = 0;
+    let started = Instant::now();
+    for ch in text.iter().cycle().take(keystrokes) {
+        let state = typed.push(&index, *ch);
+        if index.matched(state).is_some() {
+            matches += 1;
+            typed.clear();
+        }
+    }
+    let per_keystroke_ns = started.elapsed().as_nanos() as f64 / keystrokes.max(1) as f64;
+    TriggerTimings {
+        keywords: index.keyword_count(),
+        build_ms,
+        per_keystroke_ns: (per_keystroke_ns * 10.0).round() / 10.0,
+        matches,
+    }
+}
+
+fn measure(app: &AppHandle, options: &BenchOptions, cold_start_ms: f64) -> BenchReport {
+    let (cache, app_cache_load_ms) = timed(|| {
+        AppCache::get_cache_path(app)
+            .and_then(|path| AppCache::read_from_file(&path))
+            .ok()
+            .map(|cache| !cache.is_stale(&AppCache::scan_options(app)))
+    });
+    let (apps, app_scan_ms) = timed(|| AppCache::refresh_and_get_apps(app).unwrap_or_default());
+    let (plugins, plugin_discovery_ms) =
+        timed(|| crate::extensions::discover_plugins(app).unwrap_or_default());
+
+    let mut samples = Vec::with_capacity(options.iterations);
+    let mut results = 0;
+    for _ in 0..options.iterations {
+        let (count, elapsed) = timed(|| run_query(app, &options.query));
+        results = count;
+        samples.push(elapsed);
+    }
+
+    BenchReport {
+        version: env!("CARGO_PKG_VERSION"),
+        cold_start_ms,
+        app_cache_load_ms,
+        app_cache_fresh: cache.unwrap_or(false),
+        app_scan_ms,
+        app_count: apps.len(),
+        plugin_discovery_ms,
+        plugin_count: plugins.len(),
+        first_query: query_timings(&options.query, &samples, results),
+        matcher: measure_matcher(options),
+        snippet_triggers: TRIGGER_LIBRARIES
+            .iter()
+            .map(|&keywords| measure_triggers(keywords, TRIGGER_KEYSTROKES))
+            .collect(),
+    }
+}
+
+/// Called at the end of setup in bench mode
+pub fn start(app: AppHandle, options: BenchOptions) {
+    let cold_start_ms = PROCESS_START
+        .get()
+        .map(|start| millis(start.elapsed()))
+        .unwrap_or_default();
+    for window in app.webview_windows().values() {
+        let _ = window.hide();
+    }
+
+    std::thread::spawn(move || {
+        let report = measure(&app, &options, cold_start_ms);
+        match serde_json::to_string(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("flare bench: failed to serialize results: {}", e),
+        }
+        app.exit(0);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_options_from_args() {
+        assert_eq!(options_from_args(args(&[])), None);
+        assert_eq!(options_from_args(args(&["raycast://x"])), None);
+        assert_eq!(
+            options_from_args(args(&["bench"])),
+            Some(BenchOptions::default())
+        );
+        assert_eq!(
+            options_from_args(args(&["bench", "-n", "5", "--query", "fire"])),
+            Some(BenchOptions {
+                iterations: 5,
+                query: "fire".to_string()
+            })
+        );
+        assert_eq!(
+            options_from_args(args(&["bench", "--iterations", "0"]))
+                .unwrap()
+                .iterations,
+            1
+        );
+    }
+
+    #[test]
+    fn test_query_timings() {
+        let timings = query_timings("q", &[40.0, 3.0, 1.0, 2.0, 10.0], 7);
+        assert_eq!(timings.first_ms, 40.0);
+        assert_eq!(timings.median_ms, 2.0);
+        assert_eq!(timings.p95_ms, 10.0);
+        assert_eq!(timings.iterations, 5);
+        assert_eq!(timings.results, 7);
+
+        let single = query_timings("q", &[4.0], 0);
+        assert_eq!(single.median_ms, 4.0);
+    }
+
+    #[test]
+    fn test_synthetic_candidates_match_default_query() {
+        let candidates = synthetic_candidates(500);
+        assert_eq!(candidates.len(), 500);
+        assert!(!matcher::rank(DEFAULT_QUERY, &candidates, RESULT_LIMIT).is_empty());
+    }
+
+    #[test]
+    fn test_measure_triggers_finds_keywords() {
+        let timings = measure_triggers(1000, 2000);
+        assert_eq!(timings.keywords, 1000);
+        assert!(timings.matches > 0);
+    }
+}
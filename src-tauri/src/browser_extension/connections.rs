@@ -0,0 +1,152 @@
+//! Which connected browser a request goes to. Each browser's extension holds
+//! its own connection; requests go to the one the user was in last unless a
+//! caller picks another.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionInfo {
+    pub id: u64,
+    pub origin: String,
+    /// `firefox`, `chrome`, ... as the extension says, or guessed from its
+    /// origin
+    pub browser: String,
+    pub version: u32,
+    /// Milliseconds since the epoch
+    pub connected_at: i64,
+    /// When the browser last reported a tab being activated, in the same unit
+    pub last_active_at: i64,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum BrowserTarget {
+    /// The browser the user was in most recently
+    #[default]
+    Active,
+    Connection {
+        id: u64,
+    },
+    /// The most recently active connection from a browser, by name
+    Browser {
+        name: String,
+    },
+}
+
+/// The browser's name from the extension's `hello`, or from the scheme of
+/// its origin
+pub fn browser_name(reported: Option<&str>, origin: &str) -> String {
+    if let Some(name) = reported.map(str::trim).filter(|name| !name.is_empty()) {
+        return name.to_lowercase();
+    }
+    let scheme = origin.split("://").next().unwrap_or_default();
+    match scheme {
+        "moz-extension" => "firefox",
+        "safari-web-extension" => "safari",
+        "chrome-extension" => "chromium",
+        _ => "unknown",
+    }
+    .to_string()
+}
+
+pub fn select(connections: &[ConnectionInfo], target: &BrowserTarget) -> Option<u64> {
+    let most_recent = |candidates: &mut dyn Iterator<Item = &ConnectionInfo>| {
+        candidates
+            .max_by_key(|c| (c.last_active_at, c.connected_at, c.id))
+            .map(|c| c.id)
+    };
+    match target {
+        BrowserTarget::Active => most_recent(&mut connections.iter()),
+        BrowserTarget::Connection { id } => connections.iter().find(|c| c.id == *id).map(|c| c.id),
+        BrowserTarget::Browser { name } => most_recent(
+            &mut connections
+                .iter()
+                .filter(|c| c.browser.eq_ignore_ascii_case(name)),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(
+        id: u64,
+        browser: &str,
+        connected_at: i64,
+        last_active_at: i64,
+    ) -> ConnectionInfo {
+        ConnectionInfo {
+            id,
+            origin: format!("ext://{}", id),
+            browser: browser.to_string(),
+            version: 2,
+            connected_at,
+            last_active_at,
+        }
+    }
+
+    #[test]
+    fn test_browser_name() {
+        assert_eq!(
+            browser_name(Some("Firefox"), "moz-extension://x"),
+            "firefox"
+        );
+        assert_eq!(browser_name(None, "moz-extension://x"), "firefox");
+        assert_eq!(browser_name(Some(" "), "chrome-extension://x"), "chromium");
+        assert_eq!(browser_name(None, "https://x"), "unknown");
+    }
+
+    #[test]
+    fn test_select() {
+        let connections = vec![
+            connection(1, "chrome", 100, 500),
+            connection(2, "firefox", 200, 300),
+            connection(3, "firefox", 300, 400),
+        ];
+        assert_eq!(select(&connections, &BrowserTarget::Active), Some(1));
+        assert_eq!(
+            select(
+                &connections,
+                &BrowserTarget::Browser {
+                    name: "Firefox".to_string()
+                }
+            ),
+            Some(3)
+        );
+        assert_eq!(
+            select(&connections, &BrowserTarget::Connection { id: 2 }),
+            Some(2)
+        );
+        assert_eq!(
+            select(&connections, &BrowserTarget::Connection { id: 9 }),
+            None
+        );
+        assert_eq!(
+            select(
+                &connections,
+                &BrowserTarget::Browser {
+                    name: "safari".to_string()
+                }
+            ),
+            None
+        );
+        assert_eq!(select(&[], &BrowserTarget::Active), None);
+    }
+
+    #[test]
+    fn test_target_deserializes() {
+        assert_eq!(
+            serde_json::from_str::<BrowserTarget>(r#"{"kind":"browser","name":"firefox"}"#)
+                .unwrap(),
+            BrowserTarget::Browser {
+                name: "firefox".to_string()
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<BrowserTarget>(r#"{"kind":"active"}"#).unwrap(),
+            BrowserTarget::Active
+        );
+    }
+}
@@ -0,0 +1,571 @@
+//! WebSocket server the browser extension connects to on `127.0.0.1:7265`.
+//!
+//! Any local page could open a socket to that port, so the upgrade is only
+//! accepted from a browser extension origin, and the connection only becomes
+//! usable once the extension proves it holds the pairing token (see
+//! [`protocol`]). Several browsers can be paired and connected at once; see
+//! [`connections`] for which one a request goes to.
+
+pub mod connections;
+pub mod protocol;
+
+use crate::secrets;
+use chrono::Utc;
+use connections::{BrowserTarget, ConnectionInfo};
+use futures_util::{stream::StreamExt, SinkExt};
+use protocol::{BrowserEvent, BrowserRequest, Hello, Incoming, RpcError, RECONNECT_POLICY};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::handshake::server::{
+    Callback, ErrorResponse, Request, Response,
+};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Message;
+
+const SERVER_ADDR: &str = "127.0.0.1:7265";
+const PAIRING_TOKEN_KEY: &str = "pairing_token";
+const PAIRED_ORIGINS_KEY: &str = "paired_origins";
+/// How long a new connection has to send `hello`
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Requests made while the extension is reconnecting wait this long for it
+const RECONNECT_GRACE: Duration = Duration::from_secs(3);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(20);
+/// Connections silent for longer than this are dropped as dead
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+struct Connection {
+    info: ConnectionInfo,
+    sender: mpsc::Sender<String>,
+}
+
+/// Requests waiting for an answer, with the connection they were sent on
+type PendingRequest = (u64, oneshot::Sender<Result<Value, String>>);
+
+#[derive(Default)]
+pub struct WsState {
+    connections: Mutex<HashMap<u64, Connection>>,
+    pending_requests: Mutex<HashMap<u64, PendingRequest>>,
+    request_id_counter: AtomicU64,
+    connection_id_counter: AtomicU64,
+}
+
+impl WsState {
+    fn infos(&self) -> Vec<ConnectionInfo> {
+        let mut infos: Vec<ConnectionInfo> = self
+            .connections
+            .lock()
+            .unwrap()
+            .values()
+            .map(|c| c.info.clone())
+            .collect();
+        infos.sort_by_key(|info| info.id);
+        infos
+    }
+
+    fn sender(&self, id: u64) -> Option<mpsc::Sender<String>> {
+        self.connections
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|c| c.sender.clone())
+    }
+
+    fn mark_active(&self, id: u64) {
+        if let Some(connection) = self.connections.lock().unwrap().get_mut(&id) {
+            connection.info.last_active_at = Utc::now().timestamp_millis();
+        }
+    }
+
+    /// Forgets connection `id`, failing whatever was still waiting on it
+    fn disconnect(&self, id: u64) -> bool {
+        if self.connections.lock().unwrap().remove(&id).is_none() {
+            return false;
+        }
+        self.pending_requests
+            .lock()
+            .unwrap()
+            .retain(|_, (connection_id, _)| *connection_id != id);
+        true
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserExtensionStatus {
+    pub connected: bool,
+    pub paired_origins: Vec<String>,
+    pub connections: Vec<ConnectionInfo>,
+}
+
+/// `browser-extension-event` payload
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct EventPayload {
+    connection_id: u64,
+    browser: String,
+    event: BrowserEvent,
+}
+
+/// One browser's answer to a broadcast
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BroadcastResult {
+    pub connection_id: u64,
+    pub browser: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn pairing_token() -> Result<Option<String>, String> {
+    secrets::get(secrets::BROWSER, PAIRING_TOKEN_KEY).map_err(|e| e.to_string())
+}
+
+/// Origins of every extension that has paired, for display
+fn paired_origins() -> Vec<String> {
+    secrets::get(secrets::BROWSER, PAIRED_ORIGINS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn remember_origin(origin: &str) {
+    let mut origins = paired_origins();
+    if origins.iter().any(|o| o == origin) {
+        return;
+    }
+    origins.push(origin.to_string());
+    let json = serde_json::to_string(&origins).unwrap_or_default();
+    if let Err(e) = secrets::set(secrets::BROWSER, PAIRED_ORIGINS_KEY, &json) {
+        tracing::warn!(error = %e, "Failed to remember the paired browser extension");
+    }
+}
+
+fn forbidden(reason: &str) -> Box<ErrorResponse> {
+    let mut response = ErrorResponse::new(Some(reason.to_string()));
+    *response.status_mut() = StatusCode::FORBIDDEN;
+    Box::new(response)
+}
+
+/// Accepts the upgrade only from a browser extension; the token in its
+/// `hello` decides whether it's ours
+fn check_origin(request: &Request, origin: &mut Option<String>) -> Result<(), Box<ErrorResponse>> {
+    let value = request
+        .headers()
+        .get("origin")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !protocol::is_extension_origin(value) {
+        return Err(forbidden("Only the Flare browser extension may connect"));
+    }
+    *origin = Some(value.to_string());
+    Ok(())
+}
+
+/// The upgrade callback, noting the origin `check_origin` accepted
+struct OriginCheck<'a>(&'a mut Option<String>);
+
+impl Callback for OriginCheck<'_> {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        check_origin(request, self.0)
+            .map(|_| response)
+            .map_err(|error| *error)
+    }
+}
+
+/// Answers the `hello` that has to open every connection
+fn handshake(text: &str, origin: &str) -> (u64, Result<Hello, RpcError>) {
+    let (id, params) = match protocol::parse_frame(text) {
+        Ok(Incoming::Request { id, method, params }) if method == "hello" => (id, params),
+        Ok(Incoming::Request { id, .. }) => {
+            return (
+                id,
+                Err(RpcError::new(
+                    protocol::UNAUTHORIZED,
+                    "Say hello before anything else",
+                )),
+            )
+        }
+        _ => {
+            return (
+                0,
+                Err(RpcError::new(protocol::UNAUTHORIZED, "Expected hello")),
+            )
+        }
+    };
+    let hello: Hello = match serde_json::from_value(params) {
+        Ok(hello) => hello,
+        Err(e) => {
+            return (
+                id,
+                Err(RpcError::new(protocol::INVALID_PARAMS, e.to_string())),
+            )
+        }
+    };
+    let token = match pairing_token() {
+        Ok(token) => token.unwrap_or_default(),
+        Err(e) => return (id, Err(RpcError::new(protocol::UNAUTHORIZED, e))),
+    };
+    if let Err(error) = protocol::check_hello(&hello, &token) {
+        return (id, Err(error));
+    }
+    remember_origin(origin);
+    (id, Ok(hello))
+}
+
+async fn handle_connection(stream: TcpStream, app_handle: AppHandle) {
+    let mut origin = None;
+    let ws_stream =
+        match tokio_tungstenite::accept_hdr_async(stream, OriginCheck(&mut origin)).await {
+            Ok(ws) => ws,
+            Err(e) => {
+                tracing::debug!(error = %e, "Browser extension handshake refused");
+                return;
+            }
+        };
+    let origin = origin.unwrap_or_default();
+    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+
+    let hello = match tokio::time::timeout(HELLO_TIMEOUT, ws_receiver.next()).await {
+        Ok(Some(Ok(Message::Text(text)))) => text,
+        _ => {
+            tracing::debug!(origin = %origin, "Browser extension didn't say hello");
+            return;
+        }
+    };
+    let (hello_id, hello) = handshake(&hello, &origin);
+    let hello = match hello {
+        Ok(hello) => {
+            let welcome =
+                json!({ "version": protocol::PROTOCOL_VERSION, "reconnect": RECONNECT_POLICY });
+            let frame = protocol::response_frame(hello_id, Ok(welcome));
+            if ws_sender.send(Message::Text(frame.into())).await.is_err() {
+                return;
+            }
+            hello
+        }
+        Err(error) => {
+            tracing::warn!(origin = %origin, error = %error.message, "Browser extension refused");
+            let frame = protocol::response_frame(hello_id, Err(error));
+            let _ = ws_sender.send(Message::Text(frame.into())).await;
+            let _ = ws_sender.close().await;
+            return;
+        }
+    };
+
+    let state: State<WsState> = app_handle.state();
+    let connection_id = state.connection_id_counter.fetch_add(1, Ordering::Relaxed) + 1;
+    let (tx, mut rx) = mpsc::channel::<String>(100);
+    let now = Utc::now().timestamp_millis();
+    let info = ConnectionInfo {
+        id: connection_id,
+        browser: connections::browser_name(hello.browser.as_deref(), &origin),
+        origin: origin.clone(),
+        version: hello.version,
+        connected_at: now,
+        last_active_at: now,
+    };
+    tracing::info!(origin = %origin, browser = %info.browser, "Browser extension connected");
+    let browser = info.browser.clone();
+    // Only the stored sender is strong, so dropping the connection (say on
+    // unpairing) ends the sender task
+    let responder = tx.downgrade();
+    // A reloaded extension reconnects before its old socket times out
+    for stale in state.infos().iter().filter(|c| c.origin == origin) {
+        state.disconnect(stale.id);
+    }
+    state
+        .connections
+        .lock()
+        .unwrap()
+        .insert(connection_id, Connection { info, sender: tx });
+    let _ = app_handle.emit("browser-extension-connection", state.infos());
+
+    let last_seen = std::sync::Arc::new(Mutex::new(Instant::now()));
+    let last_seen_by_sender = last_seen.clone();
+    let sender_task = tokio::spawn(async move {
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            let message = tokio::select! {
+                message = rx.recv() => match message {
+                    Some(text) => Message::Binary(text.into()),
+                    None => break,
+                },
+                _ = heartbeat.tick() => {
+                    if last_seen_by_sender.lock().unwrap().elapsed() > IDLE_TIMEOUT {
+                        tracing::info!("Browser extension stopped answering heartbeats");
+                        break;
+                    }
+                    Message::Ping(Default::default())
+                }
+            };
+            if ws_sender.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let app_clone_for_receiver = app_handle.clone();
+    let receiver_task = tokio::spawn(async move {
+        while let Some(msg) = ws_receiver.next().await {
+            let msg = match msg {
+                Ok(m) => m,
+                Err(_) => break,
+            };
+            *last_seen.lock().unwrap() = Instant::now();
+
+            let Message::Text(text) = msg else {
+                continue;
+            };
+            match protocol::parse_frame(&text) {
+                Ok(Incoming::Request { id, method, .. }) => {
+                    let result = match method.as_str() {
+                        "ping" => Ok(Value::Null),
+                        _ => Err(RpcError::new(
+                            protocol::METHOD_NOT_FOUND,
+                            format!("Unknown method '{}'", method),
+                        )),
+                    };
+                    if let Some(tx) = responder.upgrade() {
+                        let _ = tx.send(protocol::response_frame(id, result)).await;
+                    }
+                }
+                Ok(Incoming::Response { id, result }) => {
+                    let pending = app_clone_for_receiver
+                        .state::<WsState>()
+                        .pending_requests
+                        .lock()
+                        .unwrap()
+                        .remove(&id);
+                    if let Some((_, sender)) = pending {
+                        let _ = sender.send(result.map_err(|error| error.message));
+                    }
+                }
+                Ok(Incoming::Event(event)) => {
+                    if matches!(event, BrowserEvent::TabActivated { .. }) {
+                        app_clone_for_receiver
+                            .state::<WsState>()
+                            .mark_active(connection_id);
+                    }
+                    let payload = EventPayload {
+                        connection_id,
+                        browser: browser.clone(),
+                        event,
+                    };
+                    let _ = app_clone_for_receiver.emit("browser-extension-event", payload);
+                }
+                Ok(Incoming::UnknownEvent(method)) => {
+                    tracing::debug!(method = %method, "Ignoring unknown browser extension notification");
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to parse message from browser extension");
+                }
+            }
+        }
+    });
+
+    tokio::select! {
+        _ = sender_task => {},
+        _ = receiver_task => {},
+    }
+
+    if state.disconnect(connection_id) {
+        let _ = app_handle.emit("browser-extension-connection", state.infos());
+    }
+    tracing::info!(origin = %origin, "Browser extension disconnected");
+}
+
+pub async fn run_server(app_handle: AppHandle) {
+    // The port can still be held by a previous instance that's shutting down
+    let mut attempt = 0;
+    let listener = loop {
+        match TcpListener::bind(SERVER_ADDR).await {
+            Ok(listener) => break listener,
+            Err(e) => {
+                let delay = RECONNECT_POLICY.delay(attempt);
+                tracing::warn!(error = %e, retry_in = ?delay, "Browser extension server failed to bind");
+                tokio::time::sleep(delay).await;
+                attempt = attempt.saturating_add(1);
+            }
+        }
+    };
+    tracing::info!("Browser extension server listening on ws://{}", SERVER_ADDR);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_connection(stream, app_handle.clone()));
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Browser extension server failed to accept");
+                tokio::time::sleep(RECONNECT_POLICY.delay(0)).await;
+            }
+        }
+    }
+}
+
+/// The connection `target` picks, giving an extension that's reconnecting a
+/// moment to come back
+async fn resolve(state: &WsState, target: &BrowserTarget) -> Result<u64, String> {
+    let waited = Instant::now();
+    loop {
+        if let Some(id) = connections::select(&state.infos(), target) {
+            return Ok(id);
+        }
+        if waited.elapsed() >= RECONNECT_GRACE {
+            return Err(match target {
+                BrowserTarget::Active => "Browser extension not connected".to_string(),
+                BrowserTarget::Connection { id } => {
+                    format!("Browser connection {} is gone", id)
+                }
+                BrowserTarget::Browser { name } => format!("{} is not connected", name),
+            });
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Sends `request` on connection `connection_id` and waits for its checked
+/// result
+async fn send_request(
+    state: &WsState,
+    connection_id: u64,
+    request: &BrowserRequest,
+) -> Result<Value, String> {
+    let tx = state
+        .sender(connection_id)
+        .ok_or("Browser extension not connected")?;
+    let request_id = state.request_id_counter.fetch_add(1, Ordering::Relaxed) + 1;
+    let (response_tx, response_rx) = oneshot::channel();
+    state
+        .pending_requests
+        .lock()
+        .unwrap()
+        .insert(request_id, (connection_id, response_tx));
+
+    if tx.send(request.to_frame(request_id)).await.is_err() {
+        state.pending_requests.lock().unwrap().remove(&request_id);
+        return Err("Failed to send message to browser extension".into());
+    }
+
+    let result = match tokio::time::timeout(REQUEST_TIMEOUT, response_rx).await {
+        Ok(Ok(result)) => result,
+        // Dropped when the connection went away
+        Ok(Err(_)) => Err("Browser extension disconnected".into()),
+        Err(_) => {
+            state.pending_requests.lock().unwrap().remove(&request_id);
+            Err("Request timed out".into())
+        }
+    };
+    request.decode_result(result?)
+}
+
+pub async fn request(
+    state: &WsState,
+    target: &BrowserTarget,
+    request: BrowserRequest,
+) -> Result<Value, String> {
+    let connection_id = resolve(state, target).await?;
+    send_request(state, connection_id, &request).await
+}
+
+/// Sends `request` to every connected browser at once
+pub async fn broadcast(state: &WsState, request: BrowserRequest) -> Vec<BroadcastResult> {
+    let requests = state.infos().into_iter().map(|info| {
+        let request = &request;
+        async move {
+            let outcome = send_request(state, info.id, request).await;
+            BroadcastResult {
+                connection_id: info.id,
+                browser: info.browser,
+                error: outcome.as_ref().err().cloned(),
+                result: outcome.ok(),
+            }
+        }
+    });
+    futures_util::future::join_all(requests).await
+}
+
+#[tauri::command]
+pub async fn browser_extension_check_connection(
+    state: tauri::State<'_, WsState>,
+) -> Result<bool, String> {
+    Ok(!state.connections.lock().unwrap().is_empty())
+}
+
+#[tauri::command]
+pub fn browser_extension_status(
+    state: tauri::State<'_, WsState>,
+) -> Result<BrowserExtensionStatus, String> {
+    let connections = state.infos();
+    Ok(BrowserExtensionStatus {
+        connected: !connections.is_empty(),
+        paired_origins: paired_origins(),
+        connections,
+    })
+}
+
+#[tauri::command]
+pub fn browser_list_connections(
+    state: tauri::State<'_, WsState>,
+) -> Result<Vec<ConnectionInfo>, String> {
+    Ok(state.infos())
+}
+
+/// `target` defaults to the browser the user was in last
+#[tauri::command]
+pub async fn browser_extension_request(
+    method: String,
+    params: Value,
+    target: Option<BrowserTarget>,
+    state: tauri::State<'_, WsState>,
+) -> Result<Value, String> {
+    let request = BrowserRequest::from_parts(&method, params)?;
+    self::request(&state, &target.unwrap_or_default(), request).await
+}
+
+#[tauri::command]
+pub async fn browser_extension_broadcast(
+    method: String,
+    params: Value,
+    state: tauri::State<'_, WsState>,
+) -> Result<Vec<BroadcastResult>, String> {
+    let request = BrowserRequest::from_parts(&method, params)?;
+    Ok(broadcast(&state, request).await)
+}
+
+/// The token to enter in the extension to pair it, created on first use
+#[tauri::command]
+pub fn browser_extension_pairing_token() -> Result<String, String> {
+    if let Some(token) = pairing_token()? {
+        return Ok(token);
+    }
+    let token = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    secrets::set(secrets::BROWSER, PAIRING_TOKEN_KEY, &token).map_err(|e| e.to_string())?;
+    Ok(token)
+}
+
+/// Forgets every paired extension and the token, and drops their connections
+#[tauri::command]
+pub fn browser_extension_unpair(state: tauri::State<'_, WsState>) -> Result<(), String> {
+    secrets::delete(secrets::BROWSER, PAIRING_TOKEN_KEY).map_err(|e| e.to_string())?;
+    secrets::delete(secrets::BROWSER, PAIRED_ORIGINS_KEY).map_err(|e| e.to_string())?;
+    for info in state.infos() {
+        state.disconnect(info.id);
+    }
+    Ok(())
+}
@@ -0,0 +1,497 @@
+//! Version 2 of the protocol spoken with the browser extension.
+//!
+//! Frames are JSON-RPC 2.0. The extension opens every connection with a
+//! `hello` request carrying the protocol version and the pairing token shown
+//! in Flare's settings; anything else first is refused. After that Flare
+//! sends typed [`BrowserRequest`]s, the extension answers them with results
+//! wrapped as `{ "value": ... }`, and pushes [`BrowserEvent`]s as
+//! notifications.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub const PROTOCOL_VERSION: u32 = 2;
+
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+pub const UNSUPPORTED_VERSION: i32 = -32001;
+pub const UNAUTHORIZED: i32 = -32002;
+
+/// Origin schemes browsers give their extensions' pages
+const EXTENSION_SCHEMES: &[&str] = &[
+    "chrome-extension://",
+    "moz-extension://",
+    "safari-web-extension://",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentFormat {
+    Html,
+    Text,
+    #[default]
+    Markdown,
+}
+
+/// Everything Flare can ask the extension for
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(
+    tag = "method",
+    content = "params",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum BrowserRequest {
+    Ping {},
+    // Tabs
+    GetTabs {},
+    /// Content of a tab, the active one when `tab_id` is unset
+    GetTab {
+        #[serde(default)]
+        tab_id: Option<u64>,
+        #[serde(default)]
+        field: ContentFormat,
+        #[serde(default)]
+        selector: Option<String>,
+    },
+    OpenTab {
+        url: String,
+        #[serde(default)]
+        background: bool,
+    },
+    ActivateTab {
+        tab_id: u64,
+    },
+    CloseTab {
+        tab_id: u64,
+    },
+    // History
+    SearchHistory {
+        query: String,
+        #[serde(default)]
+        limit: Option<u32>,
+    },
+    // Bookmarks
+    SearchBookmarks {
+        query: String,
+        #[serde(default)]
+        limit: Option<u32>,
+    },
+    CreateBookmark {
+        url: String,
+        title: String,
+        #[serde(default)]
+        folder_id: Option<String>,
+    },
+    // Downloads
+    ListDownloads {
+        #[serde(default)]
+        limit: Option<u32>,
+    },
+    OpenDownload {
+        download_id: u64,
+    },
+    ShowDownload {
+        download_id: u64,
+    },
+    // Reading list
+    GetReadingList {},
+    AddToReadingList {
+        url: String,
+        #[serde(default)]
+        title: Option<String>,
+    },
+    RemoveFromReadingList {
+        url: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Tab {
+    pub tab_id: u64,
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub favicon: Option<String>,
+    pub active: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Milliseconds since the epoch
+    #[serde(default)]
+    pub last_visit_time: Option<f64>,
+    #[serde(default)]
+    pub visit_count: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    pub id: String,
+    pub title: String,
+    /// Unset for folders
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Download {
+    pub id: u64,
+    pub url: String,
+    pub filename: String,
+    /// `in_progress`, `interrupted` or `complete`
+    pub state: String,
+    #[serde(default)]
+    pub bytes_received: Option<u64>,
+    #[serde(default)]
+    pub total_bytes: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingListItem {
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub has_been_read: bool,
+}
+
+/// Notifications the extension pushes as things change in the browser
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(
+    tag = "method",
+    content = "params",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum BrowserEvent {
+    TabActivated { tab_id: u64 },
+    TabUpdated { tab: Tab },
+    TabRemoved { tab_id: u64 },
+    DownloadChanged { download: Download },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Hello {
+    pub version: u32,
+    pub token: String,
+    /// e.g. `chrome` or `firefox`, for display
+    #[serde(default)]
+    pub browser: Option<String>,
+}
+
+/// How the extension should back off when the connection drops: wait
+/// `initial_delay_ms`, then multiply by `multiplier` up to `max_delay_ms`
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconnectPolicy {
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub multiplier: u32,
+}
+
+pub const RECONNECT_POLICY: ReconnectPolicy = ReconnectPolicy {
+    initial_delay_ms: 1_000,
+    max_delay_ms: 30_000,
+    multiplier: 2,
+};
+
+impl ReconnectPolicy {
+    /// The wait before retry number `attempt`, counting from 0
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = u64::from(self.multiplier).saturating_pow(attempt);
+        Duration::from_millis(
+            self.initial_delay_ms
+                .saturating_mul(factor)
+                .min(self.max_delay_ms),
+        )
+    }
+}
+
+/// One frame from the extension
+#[derive(Debug, PartialEq)]
+pub enum Incoming {
+    Request {
+        id: u64,
+        method: String,
+        params: Value,
+    },
+    Response {
+        id: u64,
+        result: Result<Value, RpcError>,
+    },
+    Event(BrowserEvent),
+    /// A notification this version doesn't know about
+    UnknownEvent(String),
+}
+
+#[derive(Deserialize)]
+struct Frame {
+    #[serde(default)]
+    id: Option<u64>,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    params: Option<Value>,
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+pub fn parse_frame(text: &str) -> Result<Incoming, String> {
+    let frame: Frame = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    match (frame.id, frame.method) {
+        (Some(id), Some(method)) => Ok(Incoming::Request {
+            id,
+            method,
+            params: frame.params.unwrap_or(Value::Null),
+        }),
+        (Some(id), None) => Ok(Incoming::Response {
+            id,
+            result: match frame.error {
+                Some(error) => Err(error),
+                None => Ok(frame.result.unwrap_or(Value::Null)),
+            },
+        }),
+        (None, Some(method)) => {
+            let event = json!({ "method": method, "params": frame.params.unwrap_or(json!({})) });
+            Ok(serde_json::from_value(event)
+                .map_or(Incoming::UnknownEvent(method), Incoming::Event))
+        }
+        (None, None) => Err("Frame has neither an id nor a method".to_string()),
+    }
+}
+
+pub fn response_frame(id: u64, result: Result<Value, RpcError>) -> String {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+    .to_string()
+}
+
+pub fn is_extension_origin(origin: &str) -> bool {
+    EXTENSION_SCHEMES
+        .iter()
+        .any(|scheme| origin.len() > scheme.len() && origin.starts_with(scheme))
+}
+
+/// Compares without stopping at the first difference, so response times
+/// don't leak how much of a guessed token was right
+pub fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+pub fn check_hello(hello: &Hello, pairing_token: &str) -> Result<(), RpcError> {
+    if hello.version != PROTOCOL_VERSION {
+        return Err(RpcError::new(
+            UNSUPPORTED_VERSION,
+            format!(
+                "Protocol version {} is not supported; Flare speaks version {}",
+                hello.version, PROTOCOL_VERSION
+            ),
+        ));
+    }
+    if pairing_token.is_empty() || !tokens_match(&hello.token, pairing_token) {
+        return Err(RpcError::new(UNAUTHORIZED, "Invalid pairing token"));
+    }
+    Ok(())
+}
+
+fn decode<T: DeserializeOwned + Serialize>(value: Value) -> Result<Value, String> {
+    #[derive(Deserialize, Serialize)]
+    struct Envelope<T> {
+        value: T,
+    }
+    let envelope: Envelope<T> = serde_json::from_value(value)
+        .map_err(|e| format!("Unexpected response from the browser extension: {}", e))?;
+    serde_json::to_value(envelope).map_err(|e| e.to_string())
+}
+
+impl BrowserRequest {
+    /// A request from its JSON-RPC method and params, as the sidecar sends them
+    pub fn from_parts(method: &str, params: Value) -> Result<Self, String> {
+        let params = match params {
+            Value::Null => json!({}),
+            params => params,
+        };
+        serde_json::from_value(json!({ "method": method, "params": params }))
+            .map_err(|e| format!("Invalid browser extension request '{}': {}", method, e))
+    }
+
+    pub fn to_frame(&self, id: u64) -> String {
+        let mut frame = serde_json::to_value(self).unwrap_or_else(|_| json!({}));
+        frame["jsonrpc"] = json!("2.0");
+        frame["id"] = json!(id);
+        frame.to_string()
+    }
+
+    /// Checks a result has the shape this request promises
+    pub fn decode_result(&self, value: Value) -> Result<Value, String> {
+        match self {
+            BrowserRequest::GetTabs {} => decode::<Vec<Tab>>(value),
+            BrowserRequest::GetTab { .. } => decode::<String>(value),
+            BrowserRequest::OpenTab { .. } => decode::<Tab>(value),
+            BrowserRequest::SearchHistory { .. } => decode::<Vec<HistoryEntry>>(value),
+            BrowserRequest::SearchBookmarks { .. } => decode::<Vec<Bookmark>>(value),
+            BrowserRequest::CreateBookmark { .. } => decode::<Bookmark>(value),
+            BrowserRequest::ListDownloads { .. } => decode::<Vec<Download>>(value),
+            BrowserRequest::GetReadingList {} => decode::<Vec<ReadingListItem>>(value),
+            // Acknowledgements carry nothing worth checking
+            BrowserRequest::Ping {}
+            | BrowserRequest::ActivateTab { .. }
+            | BrowserRequest::CloseTab { .. }
+            | BrowserRequest::OpenDownload { .. }
+            | BrowserRequest::ShowDownload { .. }
+            | BrowserRequest::AddToReadingList { .. }
+            | BrowserRequest::RemoveFromReadingList { .. } => Ok(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_round_trip() {
+        let request =
+            BrowserRequest::from_parts("getTab", json!({"field": "text", "tabId": 7})).unwrap();
+        assert_eq!(
+            request,
+            BrowserRequest::GetTab {
+                tab_id: Some(7),
+                field: ContentFormat::Text,
+                selector: None
+            }
+        );
+        let frame: Value = serde_json::from_str(&request.to_frame(3)).unwrap();
+        assert_eq!(frame["jsonrpc"], "2.0");
+        assert_eq!(frame["id"], 3);
+        assert_eq!(frame["method"], "getTab");
+        assert_eq!(frame["params"]["tabId"], 7);
+
+        assert_eq!(
+            BrowserRequest::from_parts("getTabs", Value::Null).unwrap(),
+            BrowserRequest::GetTabs {}
+        );
+        assert!(BrowserRequest::from_parts("formatDisk", json!({})).is_err());
+        assert!(BrowserRequest::from_parts("activateTab", json!({})).is_err());
+    }
+
+    #[test]
+    fn test_decode_result() {
+        let tabs = json!({"value": [{"tabId": 1, "url": "https://a.com", "active": true}]});
+        assert_eq!(
+            BrowserRequest::GetTabs {}.decode_result(tabs).unwrap()["value"][0]["tabId"],
+            1
+        );
+        assert!(BrowserRequest::GetTabs {}
+            .decode_result(json!({"value": "nope"}))
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_frame() {
+        assert_eq!(
+            parse_frame(r#"{"jsonrpc":"2.0","id":4,"result":{"value":"ok"}}"#).unwrap(),
+            Incoming::Response {
+                id: 4,
+                result: Ok(json!({"value": "ok"}))
+            }
+        );
+        assert_eq!(
+            parse_frame(r#"{"id":5,"error":{"code":-1,"message":"no tab"}}"#).unwrap(),
+            Incoming::Response {
+                id: 5,
+                result: Err(RpcError::new(-1, "no tab"))
+            }
+        );
+        assert_eq!(
+            parse_frame(r#"{"method":"tabRemoved","params":{"tabId":9}}"#).unwrap(),
+            Incoming::Event(BrowserEvent::TabRemoved { tab_id: 9 })
+        );
+        assert_eq!(
+            parse_frame(r#"{"method":"somethingNew","params":{}}"#).unwrap(),
+            Incoming::UnknownEvent("somethingNew".to_string())
+        );
+        assert!(matches!(
+            parse_frame(r#"{"id":1,"method":"hello","params":{"version":2,"token":"t"}}"#).unwrap(),
+            Incoming::Request { id: 1, .. }
+        ));
+        assert!(parse_frame("{}").is_err());
+    }
+
+    #[test]
+    fn test_check_hello() {
+        let hello = |version, token: &str| Hello {
+            version,
+            token: token.to_string(),
+            browser: None,
+        };
+        assert!(check_hello(&hello(2, "secret"), "secret").is_ok());
+        assert_eq!(
+            check_hello(&hello(2, "secreT"), "secret").unwrap_err().code,
+            UNAUTHORIZED
+        );
+        assert_eq!(
+            check_hello(&hello(2, ""), "").unwrap_err().code,
+            UNAUTHORIZED
+        );
+        assert_eq!(
+            check_hello(&hello(1, "secret"), "secret").unwrap_err().code,
+            UNSUPPORTED_VERSION
+        );
+    }
+
+    #[test]
+    fn test_is_extension_origin() {
+        assert!(is_extension_origin("chrome-extension://abcdefghijklmnop"));
+        assert!(is_extension_origin("moz-extension://1234-5678"));
+        assert!(!is_extension_origin("chrome-extension://"));
+        assert!(!is_extension_origin("https://evil.example"));
+        assert!(!is_extension_origin("null"));
+    }
+
+    #[test]
+    fn test_reconnect_delay() {
+        assert_eq!(RECONNECT_POLICY.delay(0), Duration::from_secs(1));
+        assert_eq!(RECONNECT_POLICY.delay(3), Duration::from_secs(8));
+        assert_eq!(RECONNECT_POLICY.delay(10), Duration::from_secs(30));
+        assert_eq!(RECONNECT_POLICY.delay(100), Duration::from_secs(30));
+    }
+}
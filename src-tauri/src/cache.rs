@@ -1,13 +1,113 @@
-use crate::{app::App, desktop::DesktopFileManager, error::AppError};
+use crate::{
+    app::App,
+    app_watcher,
+    desktop::{AppScanOptions, DesktopFileManager},
+    error::AppError,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
-    fs,
+    env, fs,
     path::{Path, PathBuf},
     time::SystemTime,
 };
 use tauri::{AppHandle, Manager};
 
+/// Which app formats get indexed, stored in `app_sources.json`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSourceSettings {
+    #[serde(default = "default_true")]
+    pub include_flatpak: bool,
+    #[serde(default = "default_true")]
+    pub include_snap: bool,
+    /// Directories scanned (non-recursively) for `*.AppImage` files; `~/` is expanded
+    #[serde(default = "default_appimage_directories")]
+    pub appimage_directories: Vec<String>,
+}
+
+impl Default for AppSourceSettings {
+    fn default() -> Self {
+        Self {
+            include_flatpak: true,
+            include_snap: true,
+            appimage_directories: default_appimage_directories(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_appimage_directories() -> Vec<String> {
+    vec![
+        "~/Applications".to_string(),
+        "~/AppImages".to_string(),
+        "~/.local/bin".to_string(),
+    ]
+}
+
+fn expand_home(dir: &str) -> PathBuf {
+    match (dir.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(dir),
+    }
+}
+
+impl AppSourceSettings {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("app_sources.json"))
+    }
+
+    pub fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+
+    fn scan_options(&self, app: &AppHandle) -> AppScanOptions {
+        AppScanOptions {
+            include_flatpak: self.include_flatpak,
+            include_snap: self.include_snap,
+            appimage_dirs: self
+                .appimage_directories
+                .iter()
+                .map(|dir| expand_home(dir))
+                .collect(),
+            appimage_cache_dir: app
+                .path()
+                .app_cache_dir()
+                .ok()
+                .map(|dir| dir.join("appimages")),
+            entry_cache_path: app
+                .path()
+                .app_cache_dir()
+                .ok()
+                .map(|dir| dir.join("desktop_entries.bincode")),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct AppCache {
     apps: Vec<App>,
@@ -38,8 +138,8 @@ impl AppCache {
         Ok(())
     }
 
-    pub fn is_stale(&self) -> bool {
-        DesktopFileManager::get_app_directories()
+    pub fn is_stale(&self, options: &AppScanOptions) -> bool {
+        DesktopFileManager::get_watched_directories(options)
             .into_iter()
             .any(|dir| {
                 let current_mod_time = fs::metadata(&dir).ok().and_then(|m| m.modified().ok());
@@ -54,9 +154,10 @@ impl AppCache {
 
     pub fn get_apps(app: &AppHandle) -> Result<Vec<App>, AppError> {
         let cache_path = Self::get_cache_path(app)?;
+        let options = Self::scan_options(app);
 
         if let Ok(cached_data) = Self::read_from_file(&cache_path) {
-            if !cached_data.is_stale() {
+            if !cached_data.is_stale(&options) {
                 return Ok(cached_data.apps);
             }
         }
@@ -64,29 +165,66 @@ impl AppCache {
         Self::refresh_and_get_apps(app)
     }
 
+    pub(crate) fn scan_options(app: &AppHandle) -> AppScanOptions {
+        let settings = AppSourceSettings::load(app).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to read app source settings");
+            AppSourceSettings::default()
+        });
+        settings.scan_options(app)
+    }
+
     pub fn refresh_and_get_apps(app: &AppHandle) -> Result<Vec<App>, AppError> {
-        let (apps, dir_mod_times) = DesktopFileManager::scan_and_parse_apps()?;
+        let options = Self::scan_options(app);
+        let (apps, dir_mod_times) = DesktopFileManager::scan_and_parse_apps(&options)?;
+        Self::write_apps(app, apps.clone(), dir_mod_times);
+        Ok(apps)
+    }
+
+    /// Replaces the cached list after an incremental update
+    pub fn store_apps(app: &AppHandle, apps: Vec<App>, options: &AppScanOptions) {
+        match DesktopFileManager::get_directory_modification_times(
+            DesktopFileManager::get_watched_directories(options),
+        ) {
+            Ok(dir_mod_times) => Self::write_apps(app, apps, dir_mod_times),
+            Err(e) => tracing::warn!(error = ?e, "Failed to read app directory times"),
+        }
+    }
+
+    fn write_apps(app: &AppHandle, apps: Vec<App>, dir_mod_times: HashMap<PathBuf, SystemTime>) {
         let cache_data = AppCache {
-            apps: apps.clone(),
+            apps,
             dir_mod_times,
         };
-
         if let Ok(cache_path) = Self::get_cache_path(app) {
             if let Err(e) = cache_data.write_to_file(&cache_path) {
                 eprintln!("Failed to write to app cache: {:?}", e);
             }
         }
-
-        Ok(apps)
     }
 
-    pub fn refresh_background(app: AppHandle) {
-        if let Err(e) = Self::refresh_and_get_apps(&app) {
-            eprintln!("Error refreshing app cache in background: {:?}", e);
-        }
+    /// The cached list as last written, without checking whether it is stale
+    pub fn cached_apps(app: &AppHandle) -> Vec<App> {
+        Self::get_cache_path(app)
+            .and_then(|path| Self::read_from_file(&path))
+            .map(|cache| cache.apps)
+            .unwrap_or_default()
     }
 }
 
+#[tauri::command]
+pub fn get_app_source_settings(app: AppHandle) -> Result<AppSourceSettings, String> {
+    AppSourceSettings::load(&app).map_err(|e| e.to_string())
+}
+
+/// Saves the settings and restarts the app watcher, which rescans in the
+/// background since AppImage icon extraction can take a while
+#[tauri::command]
+pub fn set_app_source_settings(app: AppHandle, settings: AppSourceSettings) -> Result<(), String> {
+    settings.save(&app).map_err(|e| e.to_string())?;
+    app_watcher::start(app);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
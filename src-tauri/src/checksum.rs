@@ -0,0 +1,329 @@
+//! File checksums for verifying downloads. Every requested algorithm is fed
+//! from a single read of the file, and the result can be checked against a
+//! checksum on the clipboard, whatever form `sha256sum` or a release page
+//! left it in.
+
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+const BUFFER_SIZE: usize = 1024 * 1024;
+/// Smaller files finish before a progress bar would be worth showing
+const PROGRESS_THRESHOLD: u64 = 64 * 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Length of the digest in hex
+    fn hex_len(self) -> usize {
+        match self {
+            HashAlgorithm::Md5 => 32,
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => 64,
+        }
+    }
+
+    fn hasher(self) -> Hasher {
+        match self {
+            HashAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+            HashAlgorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+}
+
+enum Hasher {
+    Md5(Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(hasher) => hasher.update(data),
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        match self {
+            Hasher::Md5(hasher) => hex::encode(hasher.finalize()),
+            Hasher::Sha1(hasher) => hex::encode(hasher.finalize()),
+            Hasher::Sha256(hasher) => hex::encode(hasher.finalize()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileHash {
+    pub algorithm: HashAlgorithm,
+    /// Lowercase hex
+    pub digest: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HashResult {
+    pub path: String,
+    pub size: u64,
+    pub hashes: Vec<FileHash>,
+    /// The checksum found on the clipboard, when asked to compare
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clipboard_checksum: Option<String>,
+    /// Which digest the clipboard checksum matched; `None` when it matched
+    /// none of them or there was nothing to compare
+    pub matched: Option<HashAlgorithm>,
+}
+
+/// `hash-progress` payload
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct HashProgress<'a> {
+    path: &'a str,
+    bytes_hashed: u64,
+    total_bytes: u64,
+}
+
+/// Hashes everything `reader` yields with each of `algorithms`, calling
+/// `on_progress` with the running byte count after every chunk
+fn hash_reader(
+    mut reader: impl Read,
+    algorithms: &[HashAlgorithm],
+    mut on_progress: impl FnMut(u64),
+) -> io::Result<Vec<FileHash>> {
+    let mut hashers: Vec<(HashAlgorithm, Hasher)> = algorithms
+        .iter()
+        .map(|&algorithm| (algorithm, algorithm.hasher()))
+        .collect();
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut hashed = 0u64;
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        for (_, hasher) in &mut hashers {
+            hasher.update(&buffer[..read]);
+        }
+        hashed += read as u64;
+        on_progress(hashed);
+    }
+    Ok(hashers
+        .into_iter()
+        .map(|(algorithm, hasher)| FileHash {
+            algorithm,
+            digest: hasher.finalize_hex(),
+        })
+        .collect())
+}
+
+/// The checksum in `text`: the first run of hex as long as some digest, so
+/// `sha256sum` output, `sha256:<hex>` and uppercase all work
+fn find_checksum(text: &str) -> Option<String> {
+    text.split(|c: char| !c.is_ascii_hexdigit())
+        .find(|word| [32, 40, 64].contains(&word.len()))
+        .map(str::to_ascii_lowercase)
+}
+
+/// The algorithm whose digest is `checksum`
+fn matching(hashes: &[FileHash], checksum: &str) -> Option<HashAlgorithm> {
+    hashes
+        .iter()
+        .find(|hash| hash.digest.eq_ignore_ascii_case(checksum))
+        .map(|hash| hash.algorithm)
+}
+
+/// `requested` plus whatever `checksum` needs to be compared at all: a
+/// 64-digit checksum may be either SHA-256 or BLAKE3, so SHA-256 is added
+/// unless one of them was asked for
+fn with_algorithm_for(requested: &[HashAlgorithm], checksum: &str) -> Vec<HashAlgorithm> {
+    let mut algorithms = requested.to_vec();
+    if algorithms.iter().any(|a| a.hex_len() == checksum.len()) {
+        return algorithms;
+    }
+    let needed = match checksum.len() {
+        32 => HashAlgorithm::Md5,
+        40 => HashAlgorithm::Sha1,
+        _ => HashAlgorithm::Sha256,
+    };
+    algorithms.push(needed);
+    algorithms
+}
+
+fn dedup(algorithms: Vec<HashAlgorithm>) -> Vec<HashAlgorithm> {
+    let mut unique = Vec::new();
+    for algorithm in algorithms {
+        if !unique.contains(&algorithm) {
+            unique.push(algorithm);
+        }
+    }
+    unique
+}
+
+/// Hashes the file at `path` (SHA-256 unless `algorithms` says otherwise)
+/// and, with `compare_clipboard`, checks the result against the checksum on
+/// the clipboard. Large files emit `hash-progress` as they go.
+#[tauri::command]
+pub async fn hash_file(
+    app: AppHandle,
+    path: String,
+    algorithms: Option<Vec<HashAlgorithm>>,
+    compare_clipboard: Option<bool>,
+) -> Result<HashResult, String> {
+    let clipboard_checksum = if compare_clipboard.unwrap_or(false) {
+        let text = app.clipboard().read_text().unwrap_or_default();
+        Some(find_checksum(&text).ok_or("The clipboard doesn't hold a checksum")?)
+    } else {
+        None
+    };
+    let mut algorithms = dedup(algorithms.unwrap_or_else(|| vec![HashAlgorithm::Sha256]));
+    if algorithms.is_empty() {
+        return Err("Pick at least one algorithm".to_string());
+    }
+    if let Some(checksum) = &clipboard_checksum {
+        algorithms = with_algorithm_for(&algorithms, checksum);
+    }
+
+    let file = File::open(Path::new(&path)).map_err(|e| format!("Can't open {}: {}", path, e))?;
+    let metadata = file.metadata().map_err(|e| e.to_string())?;
+    if !metadata.is_file() {
+        return Err(format!("{} is not a file", path));
+    }
+    let size = metadata.len();
+
+    let progress_path = path.clone();
+    let hashes = tauri::async_runtime::spawn_blocking(move || {
+        let mut last_emit = Instant::now();
+        hash_reader(file, &algorithms, |hashed| {
+            let done = hashed == size;
+            if size < PROGRESS_THRESHOLD || (!done && last_emit.elapsed() < PROGRESS_INTERVAL) {
+                return;
+            }
+            last_emit = Instant::now();
+            let _ = app.emit(
+                "hash-progress",
+                HashProgress {
+                    path: &progress_path,
+                    bytes_hashed: hashed,
+                    total_bytes: size,
+                },
+            );
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+    let matched = clipboard_checksum
+        .as_deref()
+        .and_then(|checksum| matching(&hashes, checksum));
+    Ok(HashResult {
+        path,
+        size,
+        hashes,
+        clipboard_checksum,
+        matched,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [HashAlgorithm; 4] = [
+        HashAlgorithm::Md5,
+        HashAlgorithm::Sha1,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Blake3,
+    ];
+
+    #[test]
+    fn test_hash_reader() {
+        let mut progress = Vec::new();
+        let hashes = hash_reader(&b"abc"[..], &ALL, |hashed| progress.push(hashed)).unwrap();
+        let digests: Vec<_> = hashes.iter().map(|hash| hash.digest.as_str()).collect();
+        assert_eq!(
+            digests,
+            vec![
+                "900150983cd24fb0d6963f7d28e17f72",
+                "a9993e364706816aba3e25717850c26c9cd0d89d",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ]
+        );
+        assert_eq!(progress, vec![3]);
+        for (hash, algorithm) in hashes.iter().zip(ALL) {
+            assert_eq!(hash.digest.len(), algorithm.hex_len());
+        }
+    }
+
+    #[test]
+    fn test_find_checksum() {
+        let sha = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(
+            find_checksum(&format!("{}  flare.AppImage\n", sha)).as_deref(),
+            Some(sha)
+        );
+        assert_eq!(
+            find_checksum(&format!("sha256:{}", sha.to_uppercase())).as_deref(),
+            Some(sha)
+        );
+        assert_eq!(
+            find_checksum("MD5 (x) = 900150983cd24fb0d6963f7d28e17f72").as_deref(),
+            Some("900150983cd24fb0d6963f7d28e17f72")
+        );
+        assert_eq!(find_checksum("deadbeef"), None);
+        assert_eq!(find_checksum(""), None);
+    }
+
+    #[test]
+    fn test_compare() {
+        let hashes = hash_reader(&b"abc"[..], &ALL, |_| {}).unwrap();
+        assert_eq!(
+            matching(&hashes, "A9993E364706816ABA3E25717850C26C9CD0D89D"),
+            Some(HashAlgorithm::Sha1)
+        );
+        assert_eq!(matching(&hashes, "00"), None);
+
+        let sha256 = [HashAlgorithm::Sha256];
+        assert_eq!(with_algorithm_for(&sha256, &"0".repeat(64)), sha256);
+        assert_eq!(
+            with_algorithm_for(&sha256, &"0".repeat(32)),
+            vec![HashAlgorithm::Sha256, HashAlgorithm::Md5]
+        );
+        assert_eq!(
+            with_algorithm_for(&[HashAlgorithm::Md5], &"0".repeat(64)),
+            vec![HashAlgorithm::Md5, HashAlgorithm::Sha256]
+        );
+        assert_eq!(
+            dedup(vec![HashAlgorithm::Md5, HashAlgorithm::Md5]),
+            vec![HashAlgorithm::Md5]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Actions for window manager keybindings and shell scripts:
+//!
+//! ```text
+//! flare toggle
+//! flare copy <text>
+//! flare snippet <name or keyword>
+//! flare run <command-id>
+//! ```
+//!
+//! With Flare already running, the single-instance plugin hands the
+//! arguments over and the new process exits. Otherwise the app starts
+//! hidden and runs the action once setup is done.
+
+use crate::snippets::input_manager::InputManager;
+use crate::snippets::manager::SnippetManager;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+const USAGE: &str = "usage: flare [toggle | copy <text> | snippet <name> | run <command-id>]";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CliAction {
+    Toggle,
+    Copy(String),
+    Snippet(String),
+    /// A hotkey binding id: a command, quicklink or extension command id
+    Run(String),
+}
+
+/// `None` unless the first argument names an action; `Some(Err)` with a
+/// usage message when its argument is missing
+pub fn action_from_args(
+    args: impl IntoIterator<Item = String>,
+) -> Option<Result<CliAction, String>> {
+    let mut args = args.into_iter();
+    let action = args.next()?;
+    let rest = args.collect::<Vec<_>>().join(" ");
+    let argument = |make: fn(String) -> CliAction| {
+        if rest.is_empty() {
+            Err(format!("flare {}: missing argument\n{}", action, USAGE))
+        } else {
+            Ok(make(rest.clone()))
+        }
+    };
+    match action.as_str() {
+        "toggle" => Some(Ok(CliAction::Toggle)),
+        "copy" => Some(argument(CliAction::Copy)),
+        "snippet" => Some(argument(CliAction::Snippet)),
+        "run" => Some(argument(CliAction::Run)),
+        _ => None,
+    }
+}
+
+fn paste_snippet(app: &AppHandle, name: &str) -> Result<(), String> {
+    let manager = app.state::<SnippetManager>();
+    let snippet = match manager
+        .find_snippet_by_keyword(name)
+        .map_err(|e| e.to_string())?
+    {
+        Some(snippet) => snippet,
+        None => manager
+            .find_snippet_by_name(name)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("No snippet named {}", name))?,
+    };
+    if app.try_state::<Arc<dyn InputManager>>().is_none() {
+        return Err("Text input isn't available in this session".to_string());
+    }
+    crate::snippets::paste_snippet_content(app.clone(), snippet.content)?;
+    manager
+        .snippet_was_used(snippet.id)
+        .map_err(|e| e.to_string())
+}
+
+pub fn perform(app: &AppHandle, action: CliAction) {
+    tracing::debug!(action = ?action, "Running CLI action");
+    let result = match &action {
+        CliAction::Toggle => {
+            crate::hotkey_manager::toggle_main_window(app);
+            Ok(())
+        }
+        CliAction::Copy(text) => app
+            .clipboard()
+            .write_text(text.clone())
+            .map_err(|e| e.to_string()),
+        CliAction::Snippet(name) => paste_snippet(app, name),
+        CliAction::Run(id) => {
+            crate::hotkey_manager::trigger(app, id);
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        tracing::error!(action = ?action, error = %e, "CLI action failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_action_from_args() {
+        assert_eq!(action_from_args(args(&[])), None);
+        assert_eq!(action_from_args(args(&["bench"])), None);
+        assert_eq!(action_from_args(args(&["--hidden"])), None);
+        assert_eq!(
+            action_from_args(args(&["toggle"])),
+            Some(Ok(CliAction::Toggle))
+        );
+        assert_eq!(
+            action_from_args(args(&["copy", "hello", "world"])),
+            Some(Ok(CliAction::Copy("hello world".to_string())))
+        );
+        assert_eq!(
+            action_from_args(args(&["run", "snap_layout:2"])),
+            Some(Ok(CliAction::Run("snap_layout:2".to_string())))
+        );
+        assert!(matches!(
+            action_from_args(args(&["snippet"])),
+            Some(Err(message)) if message.contains("missing argument")
+        ));
+    }
+}
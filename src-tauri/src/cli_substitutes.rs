@@ -1,44 +1,263 @@
+//! CLI binary substitution rules: which Linux build replaces a macOS binary
+//! an extension bundles. The built-in rules live in `cli_substitutes.toml`
+//! next to this file; users can add their own in the app config directory
+//! without recompiling.
+
 use flate2::read::GzDecoder;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
-use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tar::Archive;
+use tauri::Manager;
+
+const BUILTIN_RULES: &str = include_str!("cli_substitutes.toml");
+const USER_RULES_FILE: &str = "cli_substitutes";
+const KNOWN_ARCHES: &[&str] = &["x86_64", "aarch64", "armhf"];
+const POST_INSTALL_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveKind {
+    /// tar.gz when the URL ends in `.tgz` or `.tar.gz`, a bare binary otherwise
+    #[default]
+    Auto,
+    TarGz,
+    Binary,
+}
+
+/// One checksum for every arch, or one per arch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Checksum {
+    Any(String),
+    PerArch(HashMap<String, String>),
+}
 
-/// CLI binary substitution registry
-/// Maps macOS binary names to their Linux download URLs and extraction paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RuleSource {
+    #[default]
+    Builtin,
+    User,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CliSubstitute {
-    /// Name of the binary file to substitute
-    pub binary_name: String,
-    /// URL template for downloading Linux version (use {arch} placeholder)
-    pub download_url_template: String,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SubstitutionRule {
+    /// Name of the binary file to substitute; `*` matches any run of characters
+    pub pattern: String,
+    /// URL template for downloading the Linux version (use {arch} placeholder)
+    pub url: String,
+    #[serde(default)]
+    pub archive: ArchiveKind,
     /// Path within the archive to the binary (if in a subdirectory)
-    pub binary_path_in_archive: Option<String>,
-    /// Whether the download is a tar.gz archive
-    pub is_tar_gz: bool,
+    pub archive_path: Option<String>,
+    /// SHA-256 of the download
+    pub sha256: Option<Checksum>,
+    /// Shell script run in the binary's directory once it's in place
+    pub post_install: Option<String>,
+    #[serde(default, skip_deserializing)]
+    pub source: RuleSource,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RulesFile {
+    #[serde(default, rename = "rule", alias = "rules")]
+    rules: Vec<SubstitutionRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleError {
+    /// Position of the rule in its file, when the file itself parsed
+    pub index: Option<usize>,
+    pub pattern: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleSet {
+    /// User rules first, so they win over built-in ones
+    pub rules: Vec<SubstitutionRule>,
+    pub errors: Vec<RuleError>,
+    pub user_rules_path: Option<String>,
+}
+
+impl SubstitutionRule {
+    pub fn matches(&self, binary_name: &str) -> bool {
+        wildcard_match(&self.pattern, binary_name)
+    }
+
+    fn is_tar_gz(&self) -> bool {
+        match self.archive {
+            ArchiveKind::TarGz => true,
+            ArchiveKind::Binary => false,
+            ArchiveKind::Auto => self.url.ends_with(".tgz") || self.url.ends_with(".tar.gz"),
+        }
+    }
+
+    /// The checksum to verify the `arch` download against; `None` when the
+    /// rule has none. A per-arch table without `arch` is an error, not a
+    /// reason to skip verification.
+    fn checksum_for(&self, arch: &str) -> Result<Option<&str>, String> {
+        match &self.sha256 {
+            None => Ok(None),
+            Some(Checksum::Any(sum)) => Ok(Some(sum)),
+            Some(Checksum::PerArch(sums)) => sums
+                .get(arch)
+                .map(|sum| Some(sum.as_str()))
+                .ok_or_else(|| format!("No sha256 for {} in the '{}' rule", arch, self.pattern)),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.pattern.trim().is_empty() || self.pattern.contains('/') {
+            return Err("pattern must be a file name".to_string());
+        }
+        let url = url::Url::parse(&self.url.replace("{arch}", KNOWN_ARCHES[0]))
+            .map_err(|e| format!("invalid url: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("url must be http or https".to_string());
+        }
+        if let Some(path) = &self.archive_path {
+            if Path::new(path).is_absolute() || path.split('/').any(|part| part == "..") {
+                return Err("archive_path must be relative to the archive".to_string());
+            }
+            if !self.is_tar_gz() {
+                return Err("archive_path only applies to tar_gz archives".to_string());
+            }
+        }
+        let sums: Vec<(Option<&String>, &String)> = match &self.sha256 {
+            None => Vec::new(),
+            Some(Checksum::Any(sum)) => vec![(None, sum)],
+            Some(Checksum::PerArch(sums)) => sums.iter().map(|(a, s)| (Some(a), s)).collect(),
+        };
+        for (arch, sum) in sums {
+            if let Some(arch) = arch.filter(|arch| !KNOWN_ARCHES.contains(&arch.as_str())) {
+                return Err(format!("unknown arch '{}' in sha256", arch));
+            }
+            if sum.len() != 64 || !sum.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err("sha256 must be 64 hex characters".to_string());
+            }
+        }
+        if let Some(script) = &self.post_install {
+            if script.trim().is_empty() {
+                return Err("post_install is empty".to_string());
+            }
+            if self.sha256.is_none() {
+                return Err("post_install needs a sha256 to verify the download".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `*` matches any run of characters, everything else itself
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Parses a rules file as TOML, or as JSON when `json` is set, keeping the
+/// rules that validate
+fn parse_rules(
+    contents: &str,
+    json: bool,
+    source: RuleSource,
+) -> (Vec<SubstitutionRule>, Vec<RuleError>) {
+    let parsed: Result<RulesFile, String> = if json {
+        serde_json::from_str(contents).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(contents).map_err(|e| e.to_string())
+    };
+    let file = match parsed {
+        Ok(file) => file,
+        Err(message) => {
+            return (
+                Vec::new(),
+                vec![RuleError {
+                    index: None,
+                    pattern: None,
+                    message,
+                }],
+            )
+        }
+    };
+
+    let mut rules = Vec::new();
+    let mut errors = Vec::new();
+    for (index, mut rule) in file.rules.into_iter().enumerate() {
+        match rule.validate() {
+            Ok(()) => {
+                rule.source = source;
+                rules.push(rule);
+            }
+            Err(message) => errors.push(RuleError {
+                index: Some(index),
+                pattern: Some(rule.pattern),
+                message,
+            }),
+        }
+    }
+    (rules, errors)
 }
 
-/// Built-in registry of known CLI substitutes
-pub fn get_builtin_registry() -> HashMap<String, CliSubstitute> {
-    let mut registry = HashMap::new();
+fn builtin_rules() -> Vec<SubstitutionRule> {
+    parse_rules(BUILTIN_RULES, false, RuleSource::Builtin).0
+}
 
-    // Speedtest CLI by Ookla
-    registry.insert(
-        "speedtest".to_string(),
-        CliSubstitute {
-            binary_name: "speedtest".to_string(),
-            download_url_template:
-                "https://install.speedtest.net/app/cli/ookla-speedtest-1.2.0-linux-{arch}.tgz"
-                    .to_string(),
-            binary_path_in_archive: Some("speedtest".to_string()),
-            is_tar_gz: true,
-        },
-    );
+/// `cli_substitutes.toml` in the config directory, or `.json` if that's the
+/// one that exists
+fn user_rules_path(app: &tauri::AppHandle) -> Option<PathBuf> {
+    let config_dir = app.path().app_config_dir().ok()?;
+    let toml_path = config_dir.join(format!("{}.toml", USER_RULES_FILE));
+    let json_path = config_dir.join(format!("{}.json", USER_RULES_FILE));
+    Some(if !toml_path.exists() && json_path.exists() {
+        json_path
+    } else {
+        toml_path
+    })
+}
 
-    registry
+/// The user's rules followed by the built-in ones, read fresh each time
+pub fn load_rules(app: &tauri::AppHandle) -> RuleSet {
+    let mut set = RuleSet::default();
+    if let Some(path) = user_rules_path(app) {
+        if let Ok(contents) = fs::read_to_string(&path) {
+            let json = path.extension().is_some_and(|ext| ext == "json");
+            let (rules, errors) = parse_rules(&contents, json, RuleSource::User);
+            for error in &errors {
+                eprintln!(
+                    "⚠️ Skipping CLI substitution rule in {}: {}",
+                    path.display(),
+                    error.message
+                );
+            }
+            set.rules = rules;
+            set.errors = errors;
+        }
+        set.user_rules_path = Some(path.to_string_lossy().into_owned());
+    }
+    set.rules.extend(builtin_rules());
+    set
 }
 
 /// Get the current architecture string for download URLs
@@ -61,13 +280,48 @@ fn get_arch_string() -> &'static str {
     }
 }
 
-/// Download and extract a Linux CLI binary substitute
+fn make_executable(path: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to set permissions: {}", e))?;
+    }
+    Ok(())
+}
+
+async fn run_post_install(script: &str, binary: &Path, arch: &str) -> Result<(), String> {
+    let mut command = tokio::process::Command::new("sh");
+    command
+        .arg("-c")
+        .arg(script)
+        .env("FLARE_BINARY", binary)
+        .env("FLARE_ARCH", arch)
+        .kill_on_drop(true);
+    if let Some(dir) = binary.parent() {
+        command.current_dir(dir);
+    }
+    let output = tokio::time::timeout(POST_INSTALL_TIMEOUT, command.output())
+        .await
+        .map_err(|_| "Post-install script timed out".to_string())?
+        .map_err(|e| format!("Failed to run post-install script: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Post-install script failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Download, verify and extract a Linux CLI binary substitute
 pub async fn download_substitute(
-    substitute: &CliSubstitute,
+    rule: &SubstitutionRule,
+    binary_name: &str,
     target_dir: &Path,
 ) -> Result<PathBuf, String> {
     let arch = get_arch_string();
-    let url = substitute.download_url_template.replace("{arch}", arch);
+    let url = rule.url.replace("{arch}", arch);
 
     // Download the archive
     let response = reqwest::get(&url)
@@ -86,25 +340,33 @@ pub async fn download_substitute(
         .await
         .map_err(|e| format!("Failed to read response: {}", e))?;
 
+    let expected = rule.checksum_for(arch)?;
+    if let Some(expected) = expected {
+        let actual = hex::encode(Sha256::digest(&bytes));
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                url, expected, actual
+            ));
+        }
+    }
+
     // Ensure target directory exists
     fs::create_dir_all(target_dir)
         .map_err(|e| format!("Failed to create target directory: {}", e))?;
 
-    let target_binary_path = target_dir.join(&substitute.binary_name);
+    let target_binary_path = target_dir.join(binary_name);
 
-    if substitute.is_tar_gz {
+    if rule.is_tar_gz() {
         // Extract from tar.gz
         let cursor = std::io::Cursor::new(bytes.as_ref());
         let tar = GzDecoder::new(cursor);
         let mut archive = Archive::new(tar);
 
-        let binary_path_in_archive = substitute
-            .binary_path_in_archive
-            .as_ref()
-            .map(|s| s.as_str())
-            .unwrap_or(&substitute.binary_name);
+        let binary_path_in_archive = rule.archive_path.as_deref().unwrap_or(binary_name);
 
         let entries = archive.entries().map_err(|e| e.to_string())?;
+        let mut found = false;
         for entry_result in entries {
             let mut entry = entry_result.map_err(|e| e.to_string())?;
             let entry_path = entry.path().map_err(|e| e.to_string())?;
@@ -116,48 +378,49 @@ pub async fn download_substitute(
                     .map_err(|e| format!("Failed to create binary file: {}", e))?;
                 std::io::copy(&mut entry, &mut file)
                     .map_err(|e| format!("Failed to write binary: {}", e))?;
-
-                // Make executable on Unix
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::PermissionsExt;
-                    fs::set_permissions(&target_binary_path, fs::Permissions::from_mode(0o755))
-                        .map_err(|e| format!("Failed to set permissions: {}", e))?;
-                }
-
-                return Ok(target_binary_path);
+                found = true;
+                break;
             }
         }
-
-        Err(format!(
-            "Binary '{}' not found in archive",
-            binary_path_in_archive
-        ))
+        if !found {
+            return Err(format!(
+                "Binary '{}' not found in archive",
+                binary_path_in_archive
+            ));
+        }
     } else {
         // Direct binary download
         fs::write(&target_binary_path, &bytes)
             .map_err(|e| format!("Failed to write binary: {}", e))?;
+    }
+    make_executable(&target_binary_path)?;
 
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            fs::set_permissions(&target_binary_path, fs::Permissions::from_mode(0o755))
-                .map_err(|e| format!("Failed to set permissions: {}", e))?;
+    if let Some(script) = &rule.post_install {
+        if expected.is_none() {
+            return Err(format!(
+                "Not running the post-install script for unverified {}",
+                url
+            ));
         }
-
-        Ok(target_binary_path)
+        run_post_install(script, &target_binary_path, arch).await?;
     }
+
+    Ok(target_binary_path)
 }
 
-/// Check if a substitute exists for a given binary name
-pub fn find_substitute(binary_name: &str) -> Option<CliSubstitute> {
-    get_builtin_registry().get(binary_name).cloned()
+/// The first rule that matches a given binary name
+pub fn find_substitute<'a>(
+    rules: &'a [SubstitutionRule],
+    binary_name: &str,
+) -> Option<&'a SubstitutionRule> {
+    rules.iter().find(|rule| rule.matches(binary_name))
 }
 
 /// Substitute macOS binaries with Linux equivalents in an extension
 pub async fn substitute_macos_binaries(
     extension_dir: &Path,
     macho_binaries: &[String],
+    rules: &[SubstitutionRule],
 ) -> Result<Vec<String>, String> {
     let support_cli_dir = extension_dir.join("support").join("cli");
     let assets_dir = extension_dir.join("assets");
@@ -165,9 +428,9 @@ pub async fn substitute_macos_binaries(
     let mut substituted = Vec::new();
 
     for binary_name in macho_binaries {
-        if let Some(substitute) = find_substitute(binary_name) {
+        if let Some(rule) = find_substitute(rules, binary_name) {
             // Download and install the Linux substitute
-            match download_substitute(&substitute, &support_cli_dir).await {
+            match download_substitute(rule, binary_name, &support_cli_dir).await {
                 Ok(path) => {
                     // Also check if there's a binary in assets that needs replacing
                     let asset_binary = assets_dir.join(binary_name);
@@ -193,20 +456,100 @@ pub async fn substitute_macos_binaries(
     Ok(substituted)
 }
 
+/// The rules installs use right now, with any that failed validation
+#[tauri::command]
+pub fn list_substitution_rules(app: tauri::AppHandle) -> RuleSet {
+    load_rules(&app)
+}
+
+/// Checks a rules file before it's saved; `format` is `toml` (the default)
+/// or `json`
+#[tauri::command]
+pub fn validate_substitution_rules(contents: String, format: Option<String>) -> Vec<RuleError> {
+    let json = format.as_deref() == Some("json");
+    parse_rules(&contents, json, RuleSource::User).1
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_registry_has_speedtest() {
-        let registry = get_builtin_registry();
-        assert!(registry.contains_key("speedtest"));
+    fn test_builtin_rules_have_speedtest() {
+        let (rules, errors) = parse_rules(BUILTIN_RULES, false, RuleSource::Builtin);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let rule = find_substitute(&rules, "speedtest").unwrap();
+        assert!(rule.is_tar_gz());
+        assert_eq!(rule.source, RuleSource::Builtin);
     }
 
     #[test]
     fn test_find_substitute() {
-        assert!(find_substitute("speedtest").is_some());
-        assert!(find_substitute("nonexistent").is_none());
+        let rules = builtin_rules();
+        assert!(find_substitute(&rules, "speedtest").is_some());
+        assert!(find_substitute(&rules, "nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("speedtest", "speedtest"));
+        assert!(!wildcard_match("speedtest", "speedtest2"));
+        assert!(wildcard_match("ffmpeg*", "ffmpeg-arm64"));
+        assert!(wildcard_match("*-cli", "gh-cli"));
+        assert!(wildcard_match("a*b*c", "a-b-b-c"));
+        assert!(!wildcard_match("a*b*c", "a-c"));
+        assert!(!wildcard_match("ab*ba", "aba"));
+        assert!(wildcard_match("*", "anything"));
+    }
+
+    #[test]
+    fn test_user_rules_are_validated() {
+        let toml = r#"
+            [[rule]]
+            pattern = "yt-dlp*"
+            url = "https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp_linux"
+            sha256 = { x86_64 = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa" }
+            post_install = "echo ok"
+
+            [[rule]]
+            pattern = "bad"
+            url = "ftp://example.com/bad"
+
+            [[rule]]
+            pattern = "loose"
+            url = "https://example.com/loose"
+            archive_path = "bin/loose"
+
+            [[rule]]
+            pattern = "unverified"
+            url = "https://example.com/unverified"
+            post_install = "echo ok"
+        "#;
+        let (rules, errors) = parse_rules(toml, false, RuleSource::User);
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].source, RuleSource::User);
+        assert!(!rules[0].is_tar_gz());
+        assert_eq!(
+            rules[0].checksum_for("x86_64").unwrap().map(str::len),
+            Some(64)
+        );
+        assert!(rules[0].checksum_for("aarch64").is_err());
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].index, Some(1));
+        assert_eq!(errors[1].pattern.as_deref(), Some("loose"));
+        assert_eq!(
+            errors[2].message,
+            "post_install needs a sha256 to verify the download"
+        );
+
+        let json =
+            r#"{"rules": [{"pattern": "x", "url": "https://e.com/x.tgz", "sha256": "nothex"}]}"#;
+        let (rules, errors) = parse_rules(json, true, RuleSource::User);
+        assert!(rules.is_empty());
+        assert_eq!(errors[0].message, "sha256 must be 64 hex characters");
+
+        let (_, errors) = parse_rules("not toml [", false, RuleSource::User);
+        assert_eq!(errors[0].index, None);
     }
 
     #[test]
@@ -1,7 +1,10 @@
-use enigo::{Enigo, Key, Keyboard, Settings};
-use std::{thread, time::Duration};
+use std::io::Write;
+use std::process::{Command, Stdio};
 use tauri_plugin_clipboard_manager::ClipboardExt;
 
+pub const HTML_MIME: &str = "text/html";
+pub const RTF_MIME: &str = "text/rtf";
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ReadResult {
@@ -15,9 +18,106 @@ pub struct ReadResult {
 pub struct ClipboardContent {
     text: Option<String>,
     html: Option<String>,
+    /// Only written when there is no HTML, which more apps paste
+    #[serde(default)]
+    rtf: Option<String>,
     file: Option<String>,
 }
 
+impl ClipboardContent {
+    pub fn from_text(text: String) -> Self {
+        Self {
+            text: Some(text),
+            html: None,
+            rtf: None,
+            file: None,
+        }
+    }
+
+    pub fn from_html(html: String, text: String) -> Self {
+        Self {
+            html: Some(html),
+            ..Self::from_text(text)
+        }
+    }
+
+    pub fn from_rtf(rtf: String, text: String) -> Self {
+        Self {
+            rtf: Some(rtf),
+            ..Self::from_text(text)
+        }
+    }
+}
+
+/// The clipboard's contents as `mime`, read with wl-paste or xclip since
+/// the clipboard plugin only reads plain text
+pub fn read_format(mime: &str) -> Option<String> {
+    let output = if std::env::var("WAYLAND_DISPLAY").is_ok() {
+        Command::new("wl-paste")
+            .args(["--no-newline", "--type", mime])
+            .output()
+    } else {
+        Command::new("xclip")
+            .args(["-selection", "clipboard", "-t", mime, "-o"])
+            .output()
+    };
+    output
+        .ok()
+        .filter(|output| output.status.success() && !output.stdout.is_empty())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Puts `content` on the clipboard as `mime` only. Both tools keep running
+/// in the background to serve it.
+pub fn write_format(mime: &str, content: &str) -> Result<(), String> {
+    let mut command = if std::env::var("WAYLAND_DISPLAY").is_ok() {
+        let mut command = Command::new("wl-copy");
+        command.args(["--type", mime]);
+        command
+    } else {
+        let mut command = Command::new("xclip");
+        command.args(["-selection", "clipboard", "-t", mime, "-i"]);
+        command
+    };
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to write {} to the clipboard: {}", mime, e))?;
+    child
+        .stdin
+        .take()
+        .ok_or("No stdin for the clipboard tool")?
+        .write_all(content.as_bytes())
+        .map_err(|e| e.to_string())?;
+    // Both fork once the selection is theirs, so this returns right away
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Failed to write {} to the clipboard", mime))
+    }
+}
+
+/// Puts `content` on the clipboard through arboard, for callers without an
+/// app handle. Takes the same formats as `clipboard_copy`.
+pub fn write(clipboard: &mut arboard::Clipboard, content: &ClipboardContent) -> Result<(), String> {
+    if let Some(file_path) = &content.file {
+        clipboard.set_text(file_path).map_err(|e| e.to_string())
+    } else if let Some(html) = &content.html {
+        clipboard
+            .set_html(html, content.text.as_ref())
+            .map_err(|e| e.to_string())
+    } else if let Some(rtf) = &content.rtf {
+        write_format(RTF_MIME, rtf)
+    } else {
+        clipboard
+            .set_text(content.text.as_deref().unwrap_or_default())
+            .map_err(|e| e.to_string())
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CopyOptions {
@@ -39,7 +139,8 @@ pub async fn clipboard_read_text(app: tauri::AppHandle) -> Result<ReadResult, St
 pub async fn clipboard_read(app: tauri::AppHandle) -> Result<ReadResult, String> {
     let clipboard = app.clipboard();
     let text = clipboard.read_text().ok();
-    let html = None; // read_html is not supported by the plugin
+    // read_html is not supported by the plugin
+    let html = read_format(HTML_MIME);
 
     let file = if let Some(ref text_content) = text {
         if text_content.lines().count() == 1
@@ -72,6 +173,8 @@ pub async fn clipboard_copy(
         clipboard
             .write_html(html.clone(), content.text)
             .map_err(|e| e.to_string())?;
+    } else if let Some(rtf) = &content.rtf {
+        write_format(RTF_MIME, rtf)?;
     } else if let Some(text) = &content.text {
         clipboard
             .write_text(text.clone())
@@ -81,42 +184,13 @@ pub async fn clipboard_copy(
     Ok(())
 }
 
+/// Pastes into the frontmost app, restoring the clipboard afterwards
 #[tauri::command]
 pub async fn clipboard_paste(
     app: tauri::AppHandle,
     content: ClipboardContent,
 ) -> Result<(), String> {
-    let clipboard = app.clipboard();
-    let original_text = clipboard.read_text().ok();
-
-    clipboard_copy(app.clone(), content, None).await?;
-
-    thread::sleep(Duration::from_millis(100));
-
-    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
-
-    #[cfg(target_os = "macos")]
-    {
-        enigo.key(Key::Meta, enigo::Direction::Press).ok();
-        enigo.key(Key::Unicode('v'), enigo::Direction::Click).ok();
-        enigo.key(Key::Meta, enigo::Direction::Release).ok();
-    }
-    #[cfg(not(target_os = "macos"))]
-    {
-        enigo.key(Key::Control, enigo::Direction::Press).ok();
-        enigo.key(Key::Unicode('v'), enigo::Direction::Click).ok();
-        enigo.key(Key::Control, enigo::Direction::Release).ok();
-    }
-
-    thread::sleep(Duration::from_millis(100));
-
-    if let Some(text) = original_text {
-        clipboard.write_text(text).map_err(|e| e.to_string())?;
-    } else {
-        clipboard.clear().map_err(|e| e.to_string())?;
-    }
-
-    Ok(())
+    crate::paste::paste(&app, content).await
 }
 
 #[tauri::command]
@@ -1,13 +1,21 @@
 use super::{
     encryption::{decrypt, encrypt, get_encryption_key},
     monitor::start_monitoring,
-    types::{ClipboardItem, ContentType, INLINE_CONTENT_THRESHOLD_BYTES, PREVIEW_LENGTH_CHARS},
+    rich_text::{RichFormat, RichText},
+    sync::{
+        self,
+        log::{materialize, open, Change, SyncOp},
+    },
+    types::{
+        ClipboardItem, ContentType, HistoryCursor, INLINE_CONTENT_THRESHOLD_BYTES,
+        PREVIEW_LENGTH_CHARS,
+    },
 };
 use crate::error::AppError;
 use crate::store::Store;
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
-use rusqlite::{params, Result as RusqliteResult};
+use rusqlite::{params, OptionalExtension, Result as RusqliteResult};
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::Mutex;
@@ -27,12 +35,104 @@ const CLIPBOARD_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS clipboard_history (
     is_pinned INTEGER NOT NULL DEFAULT 0
 )";
 
+/// Operations for other devices, sealed with the sync key; see `sync::log`
+const SYNC_LOG_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS sync_log (
+    op_id TEXT PRIMARY KEY,
+    device TEXT NOT NULL,
+    at INTEGER NOT NULL,
+    payload TEXT NOT NULL
+)";
+
+/// Deleted rows kept for undo until purged; same columns as
+/// `clipboard_history`
+const TOMBSTONE_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS clipboard_tombstones (
+    id INTEGER PRIMARY KEY,
+    hash TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    encrypted_content TEXT NOT NULL,
+    encrypted_preview TEXT,
+    content_size_bytes INTEGER,
+    source_app_name TEXT,
+    first_copied_at INTEGER NOT NULL,
+    last_copied_at INTEGER NOT NULL,
+    times_copied INTEGER NOT NULL,
+    is_pinned INTEGER NOT NULL,
+    rich_format TEXT,
+    encrypted_rich_content TEXT,
+    rich_metadata TEXT,
+    deleted_at INTEGER NOT NULL
+)";
+
+const TOMBSTONE_COLUMNS: &str = "id, hash, content_type, encrypted_content, encrypted_preview, content_size_bytes, source_app_name, first_copied_at, last_copied_at, times_copied, is_pinned, rich_format, encrypted_rich_content, rich_metadata";
+
+/// Rows decrypted per query while filtering a page by search term
+const PAGE_SCAN_BATCH: u32 = 500;
+
+const ITEM_COLUMNS: &str = "id, hash, content_type, source_app_name, first_copied_at, last_copied_at, times_copied, is_pinned, content_size_bytes, encrypted_preview, CASE WHEN content_size_bytes <= ?1 THEN encrypted_content ELSE NULL END as conditional_encrypted_content, rich_metadata";
+
 pub struct ClipboardHistoryManager {
     store: Store,
     key: [u8; 32],
     pub image_dir: PathBuf,
 }
 
+fn filter_clause(filter: &str) -> Option<&'static str> {
+    match filter {
+        "pinned" => Some("is_pinned = 1"),
+        "text" => Some("content_type = 'text'"),
+        "image" => Some("content_type = 'image'"),
+        "link" => Some("content_type = 'link'"),
+        "color" => Some("content_type = 'color'"),
+        _ => None,
+    }
+}
+
+/// Search is done on decrypted text, so it can't happen in SQL
+fn matches_search(item: &ClipboardItem, lower_term: &str) -> bool {
+    if let Some(preview) = &item.preview {
+        preview.to_lowercase().contains(lower_term)
+    } else if let Some(value) = &item.content_value {
+        value.to_lowercase().contains(lower_term)
+    } else {
+        false
+    }
+}
+
+/// Adds the columns for formatted copies; safe to run on every start
+fn migrate_rich_content(db: &rusqlite::Connection) -> RusqliteResult<()> {
+    let mut stmt = db.prepare("PRAGMA table_info(clipboard_history)")?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (column, column_type) in [
+        ("rich_format", "TEXT"),
+        ("encrypted_rich_content", "TEXT"),
+        ("rich_metadata", "TEXT"),
+    ] {
+        if !columns.iter().any(|existing| existing == column) {
+            db.execute(
+                &format!(
+                    "ALTER TABLE clipboard_history ADD COLUMN {} {}",
+                    column, column_type
+                ),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn preview_of(content_value: &str) -> String {
+    let mut preview_text = content_value
+        .chars()
+        .take(PREVIEW_LENGTH_CHARS)
+        .collect::<String>();
+    if content_value.chars().count() > PREVIEW_LENGTH_CHARS {
+        preview_text.push_str("...");
+    }
+    preview_text
+}
+
 fn row_to_clipboard_item(row: &rusqlite::Row, key: &[u8; 32]) -> RusqliteResult<ClipboardItem> {
     let conditional_encrypted_content: Option<String> = row.get(10)?;
     let content_value = conditional_encrypted_content.and_then(|cec| decrypt(&cec, key).ok());
@@ -55,6 +155,9 @@ fn row_to_clipboard_item(row: &rusqlite::Row, key: &[u8; 32]) -> RusqliteResult<
         last_copied_at: DateTime::from_timestamp_nanos(last_ts),
         times_copied: row.get(6)?,
         is_pinned: row.get::<_, i32>(7)? == 1,
+        rich: row
+            .get::<_, Option<String>>(11)?
+            .and_then(|metadata| serde_json::from_str(&metadata).ok()),
     })
 }
 
@@ -69,6 +172,9 @@ impl ClipboardHistoryManager {
 
         let store = Store::new(app_handle, "clipboard_history.sqlite")?;
         store.init_table(CLIPBOARD_SCHEMA)?;
+        migrate_rich_content(&store.conn())?;
+        store.init_table(SYNC_LOG_SCHEMA)?;
+        store.init_table(TOMBSTONE_SCHEMA)?;
 
         // Add indices for performance
         store.conn().execute(
@@ -100,6 +206,9 @@ impl ClipboardHistoryManager {
 
         let store = Store::new_in_memory()?;
         store.init_table(CLIPBOARD_SCHEMA)?;
+        migrate_rich_content(&store.conn())?;
+        store.init_table(SYNC_LOG_SCHEMA)?;
+        store.init_table(TOMBSTONE_SCHEMA)?;
 
         let key: [u8; 32] = [0; 32];
 
@@ -131,23 +240,29 @@ impl ClipboardHistoryManager {
                 "UPDATE clipboard_history SET last_copied_at = ?, times_copied = times_copied + 1 WHERE hash = ?",
                 params![now_nanos, &hash],
             )?;
+            drop(db);
+            self.record_change(now_nanos, Change::Copy { hash });
         } else {
             let content_size_bytes = content_value.len() as i64;
-            let mut preview_text = content_value
-                .chars()
-                .take(PREVIEW_LENGTH_CHARS)
-                .collect::<String>();
-            if content_value.chars().count() > PREVIEW_LENGTH_CHARS {
-                preview_text.push_str("...");
-            }
-
-            let encrypted_preview = encrypt(&preview_text, &self.key)?;
+            let encrypted_preview = encrypt(&preview_of(&content_value), &self.key)?;
             let encrypted_content = encrypt(&content_value, &self.key)?;
             db.execute(
                 "INSERT INTO clipboard_history (hash, content_type, encrypted_content, encrypted_preview, content_size_bytes, source_app_name, first_copied_at, last_copied_at)
                  VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
                 params![hash, content_type.as_str(), encrypted_content, encrypted_preview, content_size_bytes, source_app_name, now_nanos, now_nanos],
             )?;
+            drop(db);
+            if sync::is_synced(&content_type, &content_value) {
+                self.record_change(
+                    now_nanos,
+                    Change::Add {
+                        hash,
+                        content_type,
+                        content: content_value,
+                        source_app_name,
+                    },
+                );
+            }
         }
         Ok(())
     }
@@ -160,55 +275,104 @@ impl ClipboardHistoryManager {
         offset: u32,
     ) -> Result<Vec<ClipboardItem>, AppError> {
         let db = self.store.conn();
-        let mut query = "SELECT id, hash, content_type, source_app_name, first_copied_at, last_copied_at, times_copied, is_pinned, content_size_bytes, encrypted_preview, CASE WHEN content_size_bytes <= ? THEN encrypted_content ELSE NULL END as conditional_encrypted_content FROM clipboard_history".to_string();
-        let mut where_clauses: Vec<String> = Vec::new();
-        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> =
-            vec![Box::new(INLINE_CONTENT_THRESHOLD_BYTES)];
-
-        match filter.as_str() {
-            "pinned" => where_clauses.push("is_pinned = 1".to_string()),
-            "text" => where_clauses.push("content_type = 'text'".to_string()),
-            "image" => where_clauses.push("content_type = 'image'".to_string()),
-            "link" => where_clauses.push("content_type = 'link'".to_string()),
-            "color" => where_clauses.push("content_type = 'color'".to_string()),
-            _ => {}
-        }
-
-        if !where_clauses.is_empty() {
+        let mut query = format!("SELECT {} FROM clipboard_history", ITEM_COLUMNS);
+        if let Some(clause) = filter_clause(&filter) {
             query.push_str(" WHERE ");
-            query.push_str(&where_clauses.join(" AND "));
+            query.push_str(clause);
         }
-
-        query.push_str(" ORDER BY last_copied_at DESC LIMIT ? OFFSET ?");
-        params_vec.push(Box::new(limit));
-        params_vec.push(Box::new(offset));
-
-        let params_ref: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|b| b.as_ref()).collect();
+        query.push_str(" ORDER BY last_copied_at DESC LIMIT ?2 OFFSET ?3");
 
         let mut stmt = db.prepare(&query)?;
         let key = self.key;
-        let items_iter = stmt.query_map(&params_ref[..], |row| row_to_clipboard_item(row, &key))?;
+        let items_iter = stmt.query_map(
+            params![INLINE_CONTENT_THRESHOLD_BYTES, limit, offset],
+            |row| row_to_clipboard_item(row, &key),
+        )?;
 
         let mut all_items = items_iter.collect::<Result<Vec<_>, _>>()?;
 
         if let Some(term) = search_term {
             if !term.is_empty() {
                 let lower_term = term.to_lowercase();
-                all_items.retain(|item| {
-                    if let Some(preview) = &item.preview {
-                        preview.to_lowercase().contains(&lower_term)
-                    } else if let Some(value) = &item.content_value {
-                        value.to_lowercase().contains(&lower_term)
-                    } else {
-                        false
-                    }
-                });
+                all_items.retain(|item| matches_search(item, &lower_term));
             }
         }
 
         Ok(all_items)
     }
 
+    /// Up to `limit` items following `after`, with the position to continue
+    /// from, or `None` when the history has no more matches
+    pub fn get_items_after(
+        &self,
+        filter: &str,
+        search_term: Option<&str>,
+        after: Option<&HistoryCursor>,
+        limit: u32,
+    ) -> Result<(Vec<ClipboardItem>, Option<HistoryCursor>), AppError> {
+        let lower_term = search_term
+            .filter(|term| !term.is_empty())
+            .map(str::to_lowercase);
+        let mut query = format!(
+            "SELECT {} FROM clipboard_history WHERE (?2 IS NULL OR last_copied_at < ?2 OR (last_copied_at = ?2 AND id < ?3))",
+            ITEM_COLUMNS
+        );
+        if let Some(clause) = filter_clause(filter) {
+            query.push_str(" AND ");
+            query.push_str(clause);
+        }
+        query.push_str(" ORDER BY last_copied_at DESC, id DESC LIMIT ?4");
+
+        let db = self.store.conn();
+        let mut stmt = db.prepare(&query)?;
+        let key = self.key;
+        let mut position = after.cloned();
+        let mut items = Vec::new();
+        loop {
+            // Without a search every row matches, so one batch of `limit` rows is enough
+            let batch_size = if lower_term.is_some() {
+                PAGE_SCAN_BATCH
+            } else {
+                limit
+            };
+            let rows = stmt
+                .query_map(
+                    params![
+                        INLINE_CONTENT_THRESHOLD_BYTES,
+                        position.as_ref().map(|p| p.last_copied_at),
+                        position.as_ref().map(|p| p.id),
+                        batch_size
+                    ],
+                    |row| row_to_clipboard_item(row, &key),
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+            let exhausted = (rows.len() as u32) < batch_size;
+
+            for item in rows {
+                position = Some(HistoryCursor {
+                    last_copied_at: item
+                        .last_copied_at
+                        .timestamp_nanos_opt()
+                        .unwrap_or_default(),
+                    id: item.id,
+                });
+                let matches = match &lower_term {
+                    Some(term) => matches_search(&item, term),
+                    None => true,
+                };
+                if matches {
+                    items.push(item);
+                    if items.len() as u32 == limit {
+                        return Ok((items, position));
+                    }
+                }
+            }
+            if exhausted {
+                return Ok((items, None));
+            }
+        }
+    }
+
     pub fn get_content_by_offset(&self, offset: u32) -> Result<Option<String>, AppError> {
         let db = self.store.conn();
         let res: rusqlite::Result<String> = db.query_row(
@@ -234,30 +398,352 @@ impl ClipboardHistoryManager {
         decrypt(&encrypted_content, &self.key)
     }
 
-    pub fn item_was_copied(&self, id: i64) -> RusqliteResult<usize> {
+    pub fn get_item_with_type(&self, id: i64) -> Result<(ContentType, String), AppError> {
+        let db = self.store.conn();
+        let (content_type, encrypted_content): (String, String) = db.query_row(
+            "SELECT content_type, encrypted_content FROM clipboard_history WHERE id = ?",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((
+            ContentType::from_str(&content_type).unwrap_or(ContentType::Text),
+            decrypt(&encrypted_content, &self.key)?,
+        ))
+    }
+
+    /// Keeps the formatted version of the item with `hash`, replacing an
+    /// older one; the plain text stays the item's content
+    pub fn set_rich_content(&self, hash: &str, rich: &RichText) -> Result<(), AppError> {
+        let metadata = serde_json::to_string(&rich.metadata())
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
         self.store.conn().execute(
+            "UPDATE clipboard_history SET rich_format = ?, encrypted_rich_content = ?, rich_metadata = ? WHERE hash = ?",
+            params![rich.format.as_str(), encrypt(&rich.content, &self.key)?, metadata, hash],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_rich_content(&self, id: i64) -> Result<Option<RichText>, AppError> {
+        let row: (Option<String>, Option<String>) = self.store.conn().query_row(
+            "SELECT rich_format, encrypted_rich_content FROM clipboard_history WHERE id = ?",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        match row {
+            (Some(format), Some(encrypted)) => Ok(RichFormat::parse(&format)
+                .map(|format| -> Result<RichText, AppError> {
+                    Ok(RichText {
+                        format,
+                        content: decrypt(&encrypted, &self.key)?,
+                    })
+                })
+                .transpose()?),
+            _ => Ok(None),
+        }
+    }
+
+    fn hash_of(&self, id: i64) -> RusqliteResult<Option<String>> {
+        self.store
+            .conn()
+            .query_row(
+                "SELECT hash FROM clipboard_history WHERE id = ?",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    pub fn item_was_copied(&self, id: i64) -> RusqliteResult<usize> {
+        let now_nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        let updated = self.store.conn().execute(
             "UPDATE clipboard_history SET last_copied_at = ?, times_copied = times_copied + 1 WHERE id = ?",
+            params![now_nanos, id],
+        )?;
+        if let Some(hash) = self.hash_of(id)? {
+            self.record_change(now_nanos, Change::Copy { hash });
+        }
+        Ok(updated)
+    }
+
+    /// Deletes the item but keeps its row aside, so `restore_item` can
+    /// bring it back until `purge_tombstone`. Other devices only hear of
+    /// the deletion once it's purged.
+    pub fn tombstone_item(&self, id: i64) -> RusqliteResult<usize> {
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        tx.execute(
+            &format!(
+                "INSERT OR REPLACE INTO clipboard_tombstones ({0}, deleted_at)
+                 SELECT {0}, ? FROM clipboard_history WHERE id = ?",
+                TOMBSTONE_COLUMNS
+            ),
             params![Utc::now().timestamp_nanos_opt().unwrap_or_default(), id],
-        )
+        )?;
+        let deleted = tx.execute("DELETE FROM clipboard_history WHERE id = ?", params![id])?;
+        tx.commit()?;
+        Ok(deleted)
     }
 
-    pub fn delete_item(&self, id: i64) -> RusqliteResult<usize> {
+    /// Puts a tombstoned item back; false when it's gone or the same
+    /// content was copied again meanwhile
+    pub fn restore_item(&self, id: i64) -> RusqliteResult<bool> {
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        let restored = tx.execute(
+            &format!(
+                "INSERT OR IGNORE INTO clipboard_history ({0})
+                 SELECT {0} FROM clipboard_tombstones WHERE id = ?",
+                TOMBSTONE_COLUMNS
+            ),
+            params![id],
+        )?;
+        tx.execute("DELETE FROM clipboard_tombstones WHERE id = ?", params![id])?;
+        tx.commit()?;
+        Ok(restored > 0)
+    }
+
+    /// Drops a tombstoned item for good
+    pub fn purge_tombstone(&self, id: i64) -> RusqliteResult<()> {
+        let hash: Option<String> = self
+            .store
+            .conn()
+            .query_row(
+                "SELECT hash FROM clipboard_tombstones WHERE id = ?",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
         self.store
             .conn()
-            .execute("DELETE FROM clipboard_history WHERE id = ?", params![id])
+            .execute("DELETE FROM clipboard_tombstones WHERE id = ?", params![id])?;
+        if let Some(hash) = hash {
+            self.record_change(
+                Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+                Change::Delete { hash },
+            );
+        }
+        Ok(())
+    }
+
+    /// Purges what's left from a previous run, whose undo entries are gone
+    fn purge_tombstones(&self) -> RusqliteResult<()> {
+        let ids: Vec<i64> = {
+            let db = self.store.conn();
+            let mut stmt = db.prepare("SELECT id FROM clipboard_tombstones")?;
+            let ids = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            ids
+        };
+        for id in ids {
+            self.purge_tombstone(id)?;
+        }
+        Ok(())
     }
 
     pub fn toggle_pin(&self, id: i64) -> RusqliteResult<usize> {
-        self.store.conn().execute(
+        let updated = self.store.conn().execute(
             "UPDATE clipboard_history SET is_pinned = 1 - is_pinned WHERE id = ?",
             params![id],
-        )
+        )?;
+        let pin: Option<(String, bool)> = self
+            .store
+            .conn()
+            .query_row(
+                "SELECT hash, is_pinned FROM clipboard_history WHERE id = ?",
+                params![id],
+                |row| Ok((row.get(0)?, row.get::<_, i32>(1)? == 1)),
+            )
+            .optional()?;
+        if let Some((hash, pinned)) = pin {
+            self.record_change(
+                Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+                Change::Pin { hash, pinned },
+            );
+        }
+        Ok(updated)
     }
 
     pub fn clear_all(&self) -> RusqliteResult<usize> {
-        self.store
+        let hashes: Vec<String> = {
+            let db = self.store.conn();
+            let mut stmt = db.prepare("SELECT hash FROM clipboard_history WHERE is_pinned = 0")?;
+            let hashes = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            hashes
+        };
+        let deleted = self
+            .store
             .conn()
-            .execute("DELETE FROM clipboard_history WHERE is_pinned = 0", [])
+            .execute("DELETE FROM clipboard_history WHERE is_pinned = 0", [])?;
+        let now_nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        for hash in hashes {
+            self.record_change(now_nanos, Change::Delete { hash });
+        }
+        Ok(deleted)
+    }
+
+    /// Appends a local change to the sync log while sync is set up
+    fn record_change(&self, at: i64, change: Change) {
+        let Some(context) = sync::context() else {
+            return;
+        };
+        let op = SyncOp::new(&context.device, at, change);
+        if let Err(e) = self.insert_sync_op(&op, &context.key) {
+            tracing::warn!(error = %e, "Failed to record clipboard change for sync");
+        }
+        sync::changed();
+    }
+
+    fn insert_sync_op(&self, op: &SyncOp, sync_key: &[u8; 32]) -> Result<bool, AppError> {
+        let payload = sync::log::seal(op, sync_key)?;
+        let inserted = self.store.conn().execute(
+            "INSERT OR IGNORE INTO sync_log (op_id, device, at, payload) VALUES (?, ?, ?, ?)",
+            params![op.id, op.device, op.at, payload],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Sealed operations in the log, all of them or one device's
+    pub fn sync_payloads(&self, device: Option<&str>) -> Result<Vec<String>, AppError> {
+        let db = self.store.conn();
+        let mut stmt = db.prepare(
+            "SELECT payload FROM sync_log WHERE ?1 IS NULL OR device = ?1 ORDER BY at, op_id",
+        )?;
+        let payloads = stmt
+            .query_map(params![device], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(payloads)
+    }
+
+    /// Starts the log with the history recorded before sync was set up
+    pub fn seed_sync_log(&self, device: &str, sync_key: &[u8; 32]) -> Result<usize, AppError> {
+        let has_own: bool = self.store.conn().query_row(
+            "SELECT EXISTS(SELECT 1 FROM sync_log WHERE device = ?)",
+            params![device],
+            |row| row.get(0),
+        )?;
+        if has_own {
+            return Ok(0);
+        }
+        let rows: Vec<(i64, String, String, Option<String>, i64, bool)> = {
+            let db = self.store.conn();
+            let mut stmt = db.prepare(
+                "SELECT id, hash, content_type, source_app_name, last_copied_at, is_pinned FROM clipboard_history ORDER BY last_copied_at",
+            )?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get::<_, i32>(5)? == 1,
+                    ))
+                })?
+                .collect::<Result<_, _>>()?;
+            rows
+        };
+        let mut seeded = 0;
+        for (id, hash, content_type, source_app_name, at, pinned) in rows {
+            let content_type = ContentType::from_str(&content_type).unwrap_or(ContentType::Text);
+            let content = self.get_item_content(id)?;
+            if !sync::is_synced(&content_type, &content) {
+                continue;
+            }
+            let add = Change::Add {
+                hash: hash.clone(),
+                content_type,
+                content,
+                source_app_name,
+            };
+            self.insert_sync_op(&SyncOp::new(device, at, add), sync_key)?;
+            if pinned {
+                let pin = Change::Pin { hash, pinned };
+                self.insert_sync_op(&SyncOp::new(device, at, pin), sync_key)?;
+            }
+            seeded += 1;
+        }
+        Ok(seeded)
+    }
+
+    /// Empties the log, once the ops in it can't be opened anymore
+    pub fn reset_sync_log(&self) -> Result<(), AppError> {
+        self.store.conn().execute("DELETE FROM sync_log", [])?;
+        Ok(())
+    }
+
+    /// Adds other devices' operations to the log. Returns how many were new
+    /// and how many couldn't be opened with the sync key.
+    pub fn ingest_sync_payloads(
+        &self,
+        payloads: &[String],
+        sync_key: &[u8; 32],
+    ) -> Result<(usize, usize), AppError> {
+        let (mut added, mut rejected) = (0, 0);
+        for payload in payloads.iter().filter(|p| !p.trim().is_empty()) {
+            match open(payload, sync_key) {
+                // The greeting LAN peers send has no hash, and no place in the log
+                Ok(op) if op.change.hash().is_empty() => rejected += 1,
+                Ok(op) => {
+                    let inserted = self.store.conn().execute(
+                        "INSERT OR IGNORE INTO sync_log (op_id, device, at, payload) VALUES (?, ?, ?, ?)",
+                        params![op.id, op.device, op.at, payload.trim()],
+                    )?;
+                    added += inserted;
+                }
+                Err(_) => rejected += 1,
+            }
+        }
+        Ok((added, rejected))
+    }
+
+    /// Brings the history in line with the log. Items the log has never
+    /// heard of, such as images, are left alone.
+    pub fn apply_sync_log(&self, sync_key: &[u8; 32]) -> Result<(), AppError> {
+        let ops = self
+            .sync_payloads(None)?
+            .iter()
+            .filter_map(|payload| open(payload, sync_key).ok())
+            .collect();
+        let merged = materialize(ops);
+
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        for hash in &merged.deleted {
+            tx.execute(
+                "DELETE FROM clipboard_history WHERE hash = ?",
+                params![hash],
+            )?;
+        }
+        for (hash, item) in &merged.items {
+            let updated = tx.execute(
+                "UPDATE clipboard_history SET first_copied_at = ?, last_copied_at = ?, times_copied = ?, is_pinned = ? WHERE hash = ?",
+                params![item.first_copied_at, item.last_copied_at, item.times_copied, item.is_pinned as i32, hash],
+            )?;
+            if updated == 0 {
+                tx.execute(
+                    "INSERT INTO clipboard_history (hash, content_type, encrypted_content, encrypted_preview, content_size_bytes, source_app_name, first_copied_at, last_copied_at, times_copied, is_pinned)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    params![
+                        hash,
+                        item.content_type.as_str(),
+                        encrypt(&item.content, &self.key)?,
+                        encrypt(&preview_of(&item.content), &self.key)?,
+                        item.content.len() as i64,
+                        item.source_app_name,
+                        item.first_copied_at,
+                        item.last_copied_at,
+                        item.times_copied,
+                        item.is_pinned as i32
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
     }
 }
 
@@ -271,9 +757,151 @@ pub fn init(app_handle: AppHandle) {
             Ok(manager) => {
                 *manager_guard = Some(manager);
                 drop(manager_guard);
+                super::ignore_rules::load(&app_handle);
+                sync::init(&app_handle);
+                if let Some(manager) = MANAGER.lock().unwrap().as_ref() {
+                    if let Err(e) = manager.purge_tombstones() {
+                        tracing::warn!(error = %e, "Failed to purge deleted clipboard items");
+                    }
+                }
                 start_monitoring(app_handle);
             }
             Err(e) => tracing::error!(error = ?e, "Failed to create ClipboardHistoryManager"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager_with(texts: &[&str]) -> ClipboardHistoryManager {
+        let manager = ClipboardHistoryManager::new_for_test().unwrap();
+        for text in texts {
+            manager
+                .add_item(text.to_string(), ContentType::Text, text.to_string(), None)
+                .unwrap();
+        }
+        manager
+    }
+
+    fn page_all(
+        manager: &ClipboardHistoryManager,
+        filter: &str,
+        search: Option<&str>,
+        limit: u32,
+    ) -> Vec<Vec<String>> {
+        let mut pages = Vec::new();
+        let mut after = None;
+        loop {
+            let (items, next) = manager
+                .get_items_after(filter, search, after.as_ref(), limit)
+                .unwrap();
+            pages.push(items.into_iter().filter_map(|i| i.content_value).collect());
+            match next {
+                Some(next) => after = Some(next),
+                None => return pages,
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_items_after_pages_newest_first() {
+        let manager = manager_with(&["one", "two", "three", "four", "five"]);
+        assert_eq!(
+            page_all(&manager, "all", None, 2),
+            vec![vec!["five", "four"], vec!["three", "two"], vec!["one"]]
+        );
+    }
+
+    #[test]
+    fn test_get_items_after_filters_by_search() {
+        let manager = manager_with(&["apple pie", "banana", "Apple juice", "cherry", "apple"]);
+        assert_eq!(
+            page_all(&manager, "all", Some("APPLE"), 2),
+            vec![vec!["apple", "Apple juice"], vec!["apple pie"]]
+        );
+        assert_eq!(
+            page_all(&manager, "pinned", None, 2),
+            vec![Vec::<String>::new()]
+        );
+    }
+
+    #[test]
+    fn test_apply_sync_log_merges_other_devices() {
+        let manager = manager_with(&["local"]);
+        let key: [u8; 32] = rand::random();
+        let add = Change::Add {
+            hash: "h1".to_string(),
+            content_type: ContentType::Text,
+            content: "from the laptop".to_string(),
+            source_app_name: None,
+        };
+        let pin = Change::Pin {
+            hash: "h1".to_string(),
+            pinned: true,
+        };
+        let payloads = vec![
+            sync::log::seal(&SyncOp::new("laptop", 1, add), &key).unwrap(),
+            sync::log::seal(&SyncOp::new("laptop", 2, pin), &key).unwrap(),
+            sync::log::seal(
+                &SyncOp::new("stranger", 3, Change::Copy { hash: "h1".into() }),
+                &rand::random(),
+            )
+            .unwrap(),
+        ];
+
+        assert_eq!(
+            manager.ingest_sync_payloads(&payloads, &key).unwrap(),
+            (2, 1)
+        );
+        assert_eq!(
+            manager.ingest_sync_payloads(&payloads, &key).unwrap(),
+            (0, 1)
+        );
+        manager.apply_sync_log(&key).unwrap();
+
+        assert_eq!(
+            page_all(&manager, "pinned", None, 10),
+            vec![vec!["from the laptop"]]
+        );
+        // Items the log doesn't know about are kept
+        assert_eq!(page_all(&manager, "all", None, 10)[0].len(), 2);
+    }
+
+    #[test]
+    fn test_rich_content_is_kept_next_to_plain_text() {
+        let manager = manager_with(&["Hello bold"]);
+        let rich = RichText {
+            format: RichFormat::Html,
+            content: "<p>Hello <b>bold</b></p>".to_string(),
+        };
+        manager.set_rich_content("Hello bold", &rich).unwrap();
+
+        let (items, _) = manager.get_items_after("all", None, None, 10).unwrap();
+        assert_eq!(items[0].content_value.as_deref(), Some("Hello bold"));
+        let metadata = items[0].rich.as_ref().unwrap();
+        assert_eq!(metadata.format, RichFormat::Html);
+        assert_eq!(manager.get_rich_content(items[0].id).unwrap(), Some(rich));
+    }
+
+    #[test]
+    fn test_tombstoned_items_can_be_restored_until_purged() {
+        let manager = manager_with(&["keep", "oops"]);
+        let (items, _) = manager.get_items_after("all", None, None, 10).unwrap();
+        let oops = items[0].id;
+
+        manager.tombstone_item(oops).unwrap();
+        assert_eq!(page_all(&manager, "all", None, 10), vec![vec!["keep"]]);
+        assert!(manager.restore_item(oops).unwrap());
+        assert_eq!(
+            page_all(&manager, "all", None, 10),
+            vec![vec!["oops", "keep"]]
+        );
+
+        manager.tombstone_item(oops).unwrap();
+        manager.purge_tombstone(oops).unwrap();
+        assert!(!manager.restore_item(oops).unwrap());
+        assert_eq!(page_all(&manager, "all", None, 10), vec![vec!["keep"]]);
+    }
+}
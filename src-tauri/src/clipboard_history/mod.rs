@@ -1,11 +1,23 @@
 mod encryption;
+mod ignore_rules;
 pub mod manager;
 mod monitor;
+pub mod paste_stack;
+mod plain_text;
+pub mod rich_text;
+pub mod sync;
 pub mod types;
 
+use crate::clipboard::ClipboardContent;
+use crate::streaming::{self, StreamOptions, StreamPage, StreamRegistry, StreamSummary};
+use ignore_rules::{IgnoreRules, PauseState};
 pub use manager::init;
 use manager::MANAGER;
-use types::ClipboardItem;
+use paste_stack::{PasteStackState, PASTE_STACK, PASTE_STACK_CHANGED_EVENT};
+use rich_text::{RichContent, RichFormat};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter, State};
+use types::{ClipboardItem, ContentType, HistoryCursor};
 
 #[tauri::command]
 pub fn history_get_items(
@@ -23,6 +35,39 @@ pub fn history_get_items(
     }
 }
 
+/// Streams the history over `on_page` a page at a time, like
+/// `search_files_stream`; a new history stream cancels the running one
+#[tauri::command]
+pub async fn history_get_items_stream(
+    streams: State<'_, StreamRegistry>,
+    filter: String,
+    search_term: Option<String>,
+    options: StreamOptions,
+    on_page: Channel<StreamPage<ClipboardItem>>,
+) -> Result<StreamSummary, String> {
+    let streams = streams.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        streaming::run(
+            &streams,
+            "clipboard_history",
+            options,
+            |after: Option<&HistoryCursor>, limit| {
+                // Locked per page so the monitor can keep recording meanwhile
+                let guard = MANAGER.lock().unwrap();
+                let manager = guard
+                    .as_ref()
+                    .ok_or("Clipboard history manager not initialized")?;
+                manager
+                    .get_items_after(&filter, search_term.as_deref(), after, limit)
+                    .map_err(|e| e.to_string())
+            },
+            |page| on_page.send(page).is_ok(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 pub fn history_get_item_content(id: i64) -> Result<String, String> {
     if let Some(manager) = MANAGER.lock().unwrap().as_ref() {
@@ -43,13 +88,10 @@ pub fn history_item_was_copied(id: i64) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn history_delete_item(id: i64) -> Result<(), String> {
-    if let Some(manager) = MANAGER.lock().unwrap().as_ref() {
-        manager.delete_item(id).map_err(|e| e.to_string())?;
-        Ok(())
-    } else {
-        Err("Clipboard history manager not initialized".to_string())
-    }
+pub fn history_delete_item(app: AppHandle, id: i64) -> Result<(), String> {
+    crate::undo::delete_history_item(&app, id)?;
+    PASTE_STACK.lock().unwrap().remove(id);
+    Ok(())
 }
 
 #[tauri::command]
@@ -71,3 +113,171 @@ pub fn history_clear_all() -> Result<(), String> {
         Err("Clipboard history manager not initialized".to_string())
     }
 }
+
+#[tauri::command]
+pub fn history_get_ignore_rules(app: AppHandle) -> Result<IgnoreRules, String> {
+    ignore_rules::read_rules(&app)
+}
+
+#[tauri::command]
+pub fn history_set_ignore_rules(app: AppHandle, rules: IgnoreRules) -> Result<(), String> {
+    ignore_rules::save_rules(&app, rules)
+}
+
+/// Stops recording for `minutes`, or until resumed when no duration is given
+#[tauri::command]
+pub fn history_pause(app: AppHandle, minutes: Option<u32>) -> PauseState {
+    ignore_rules::pause(&app, minutes)
+}
+
+#[tauri::command]
+pub fn history_resume(app: AppHandle) -> PauseState {
+    ignore_rules::resume(&app)
+}
+
+#[tauri::command]
+pub fn history_get_pause_state() -> PauseState {
+    ignore_rules::pause_state()
+}
+
+/// Looks up a text item for pasting and bumps its copy count
+fn content_for_paste(id: i64, plain_text: bool) -> Result<String, String> {
+    let guard = MANAGER.lock().unwrap();
+    let manager = guard
+        .as_ref()
+        .ok_or("Clipboard history manager not initialized")?;
+    let (content_type, content) = manager.get_item_with_type(id).map_err(|e| e.to_string())?;
+    if content_type == ContentType::Image {
+        return Err("Image items can't be pasted as text".to_string());
+    }
+    manager.item_was_copied(id).map_err(|e| e.to_string())?;
+    Ok(if plain_text {
+        plain_text::strip_formatting(&content)
+    } else {
+        content
+    })
+}
+
+/// The paste module keeps the temporary clipboard contents out of the history
+async fn paste_content(app: AppHandle, content: ClipboardContent) -> Result<(), String> {
+    crate::paste::paste(&app, content).await
+}
+
+async fn paste_text(app: AppHandle, text: String) -> Result<(), String> {
+    paste_content(app, ClipboardContent::from_text(text)).await
+}
+
+fn emit_paste_stack_changed(app: &AppHandle, state: &PasteStackState) {
+    if let Err(e) = app.emit(PASTE_STACK_CHANGED_EVENT, state) {
+        tracing::error!(error = %e, "Failed to emit paste stack event");
+    }
+}
+
+#[tauri::command]
+pub async fn history_paste_as_plain_text(app: AppHandle, id: i64) -> Result<(), String> {
+    let text = content_for_paste(id, true)?;
+    paste_text(app, text).await
+}
+
+/// Pastes the HTML or RTF the item was copied with, and its plain text
+/// where it has none
+#[tauri::command]
+pub async fn history_paste_with_formatting(app: AppHandle, id: i64) -> Result<(), String> {
+    let text = content_for_paste(id, false)?;
+    let rich = {
+        let guard = MANAGER.lock().unwrap();
+        let manager = guard
+            .as_ref()
+            .ok_or("Clipboard history manager not initialized")?;
+        manager.get_rich_content(id).map_err(|e| e.to_string())?
+    };
+    let content = match rich {
+        Some(rich) if rich.format == RichFormat::Html => {
+            ClipboardContent::from_html(rich.content, text)
+        }
+        Some(rich) => ClipboardContent::from_rtf(rich.content, text),
+        None => ClipboardContent::from_text(text),
+    };
+    paste_content(app, content).await
+}
+
+/// The item's formatted version for previewing, if it was copied with one
+#[tauri::command]
+pub fn history_get_item_rich_content(id: i64) -> Result<Option<RichContent>, String> {
+    if let Some(manager) = MANAGER.lock().unwrap().as_ref() {
+        manager
+            .get_rich_content(id)
+            .map(|rich| rich.map(|rich| rich.preview()))
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Clipboard history manager not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+pub fn history_paste_stack_start(
+    app: AppHandle,
+    ids: Vec<i64>,
+    plain_text: Option<bool>,
+) -> Result<PasteStackState, String> {
+    let state = {
+        let mut stack = PASTE_STACK.lock().unwrap();
+        stack.start(ids, plain_text.unwrap_or(false));
+        stack.state()
+    };
+    emit_paste_stack_changed(&app, &state);
+    Ok(state)
+}
+
+/// Pastes the next queued item. Items that can't be pasted anymore (deleted,
+/// or images) are skipped.
+#[tauri::command]
+pub async fn history_paste_stack_next(app: AppHandle) -> Result<PasteStackState, String> {
+    loop {
+        let next = {
+            let mut stack = PASTE_STACK.lock().unwrap();
+            stack.take_next().map(|id| (id, stack.plain_text()))
+        };
+        let Some((id, plain_text)) = next else {
+            break;
+        };
+        match content_for_paste(id, plain_text) {
+            Ok(text) => {
+                let result = paste_text(app.clone(), text).await;
+                let state = PASTE_STACK.lock().unwrap().state();
+                emit_paste_stack_changed(&app, &state);
+                return result.map(|()| state);
+            }
+            Err(e) => tracing::warn!(id, error = %e, "Skipping paste stack item"),
+        }
+    }
+
+    let state = PASTE_STACK.lock().unwrap().state();
+    emit_paste_stack_changed(&app, &state);
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn history_paste_stack_get() -> PasteStackState {
+    PASTE_STACK.lock().unwrap().state()
+}
+
+#[tauri::command]
+pub fn history_paste_stack_clear(app: AppHandle) {
+    let state = {
+        let mut stack = PASTE_STACK.lock().unwrap();
+        stack.clear();
+        stack.state()
+    };
+    emit_paste_stack_changed(&app, &state);
+}
+
+/// Handler for the paste-stack hotkey
+pub fn paste_stack_hotkey(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = history_paste_stack_next(app).await {
+            tracing::error!(error = %e, "Failed to paste from paste stack");
+        }
+    });
+}
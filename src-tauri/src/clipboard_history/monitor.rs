@@ -1,18 +1,49 @@
 use super::{
+    ignore_rules,
     manager::MANAGER,
+    rich_text,
     types::{ContentType, COLOR_REGEX, URL_REGEX},
 };
 use sha2::{Digest, Sha256};
 use std::time::Duration;
 use tauri::AppHandle;
 
-pub fn start_monitoring(_app_handle: AppHandle) {
+/// Checks a new clipboard value against the ignore rules. Returns the app
+/// that owns it when it may be recorded.
+fn check_ignore_rules(text: Option<&str>) -> Result<Option<String>, String> {
+    if ignore_rules::is_paused() {
+        return Err("recording paused".to_string());
+    }
+    let source_app = ignore_rules::active_app_class();
+    let mime_types = ignore_rules::clipboard_mime_types();
+    let reason = ignore_rules::with_rules(|rules| {
+        if source_app
+            .as_deref()
+            .is_some_and(|app| rules.is_ignored_app(app))
+        {
+            Some("ignored app".to_string())
+        } else if rules.is_sensitive(&mime_types) {
+            Some("marked as sensitive".to_string())
+        } else {
+            text.and_then(|text| rules.excluded_by(text))
+                .map(|rule| format!("matches '{}'", rule))
+        }
+    })
+    .flatten();
+    match reason {
+        Some(reason) => Err(reason),
+        None => Ok(source_app),
+    }
+}
+
+pub fn start_monitoring(app_handle: AppHandle) {
     std::thread::spawn(move || {
         let mut last_text_hash = String::new();
         let mut last_image_hash = String::new();
         let mut clipboard = arboard::Clipboard::new().unwrap();
 
         loop {
+            ignore_rules::check_auto_resume(&app_handle);
             if super::manager::INTERNAL_CLIPBOARD_CHANGE.load(std::sync::atomic::Ordering::SeqCst) {
                 std::thread::sleep(Duration::from_millis(50));
                 continue;
@@ -31,14 +62,32 @@ pub fn start_monitoring(_app_handle: AppHandle) {
                             (ContentType::Text, text.to_string())
                         };
 
-                        if let Some(manager) = MANAGER.lock().unwrap().as_ref() {
-                            if let Err(e) = manager.add_item(
-                                current_hash.clone(),
-                                content_type,
-                                content_value,
-                                None,
-                            ) {
-                                tracing::error!(error = ?e, "Error adding clipboard text item");
+                        match check_ignore_rules(Some(text)) {
+                            Ok(source_app) => {
+                                let rich = (content_type == ContentType::Text)
+                                    .then(|| {
+                                        rich_text::capture(&ignore_rules::clipboard_mime_types())
+                                    })
+                                    .flatten();
+                                if let Some(manager) = MANAGER.lock().unwrap().as_ref() {
+                                    if let Err(e) = manager.add_item(
+                                        current_hash.clone(),
+                                        content_type,
+                                        content_value,
+                                        source_app,
+                                    ) {
+                                        tracing::error!(error = ?e, "Error adding clipboard text item");
+                                    } else if let Some(rich) = rich {
+                                        if let Err(e) =
+                                            manager.set_rich_content(&current_hash, &rich)
+                                        {
+                                            tracing::error!(error = ?e, "Error adding formatted clipboard text");
+                                        }
+                                    }
+                                }
+                            }
+                            Err(reason) => {
+                                tracing::debug!(reason = %reason, "Not recording clipboard text")
                             }
                         }
                         last_text_hash = current_hash;
@@ -50,7 +99,16 @@ pub fn start_monitoring(_app_handle: AppHandle) {
             if let Ok(image_data) = clipboard.get_image() {
                 let current_hash = hex::encode(Sha256::digest(&image_data.bytes));
                 if current_hash != last_image_hash {
-                    if let Some(manager) = MANAGER.lock().unwrap().as_ref() {
+                    let source_app = match check_ignore_rules(None) {
+                        Ok(source_app) => Some(source_app),
+                        Err(reason) => {
+                            tracing::debug!(reason = %reason, "Not recording clipboard image");
+                            None
+                        }
+                    };
+                    if let (Some(source_app), Some(manager)) =
+                        (source_app, MANAGER.lock().unwrap().as_ref())
+                    {
                         let image_path = manager.image_dir.join(format!("{}.png", current_hash));
                         match image::save_buffer(
                             &image_path,
@@ -65,7 +123,7 @@ pub fn start_monitoring(_app_handle: AppHandle) {
                                     current_hash.clone(),
                                     ContentType::Image,
                                     content_value,
-                                    None,
+                                    source_app,
                                 ) {
                                     tracing::error!(error = ?e, "Error adding clipboard image item");
                                 }
@@ -0,0 +1,103 @@
+//! Paste stack: history items selected together are queued and pasted one
+//! per press of the paste-stack hotkey, in the order they were selected.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+pub const PASTE_STACK_CHANGED_EVENT: &str = "paste-stack-changed";
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteStackState {
+    pub active: bool,
+    /// Ids still to be pasted, next one first
+    pub queued: Vec<i64>,
+    pub pasted: usize,
+    pub total: usize,
+    pub plain_text: bool,
+}
+
+#[derive(Default)]
+pub struct PasteStack {
+    queue: VecDeque<i64>,
+    pasted: usize,
+    total: usize,
+    plain_text: bool,
+}
+
+impl PasteStack {
+    /// Replaces whatever was queued; duplicate ids are only pasted once
+    pub fn start(&mut self, ids: Vec<i64>, plain_text: bool) {
+        self.queue.clear();
+        for id in ids {
+            if !self.queue.contains(&id) {
+                self.queue.push_back(id);
+            }
+        }
+        self.pasted = 0;
+        self.total = self.queue.len();
+        self.plain_text = plain_text;
+    }
+
+    pub fn take_next(&mut self) -> Option<i64> {
+        let id = self.queue.pop_front()?;
+        self.pasted += 1;
+        Some(id)
+    }
+
+    /// Drops an item, e.g. one deleted from history while queued
+    pub fn remove(&mut self, id: i64) {
+        if let Some(index) = self.queue.iter().position(|queued| *queued == id) {
+            self.queue.remove(index);
+            self.total -= 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn plain_text(&self) -> bool {
+        self.plain_text
+    }
+
+    pub fn state(&self) -> PasteStackState {
+        PasteStackState {
+            active: !self.queue.is_empty(),
+            queued: self.queue.iter().copied().collect(),
+            pasted: self.pasted,
+            total: self.total,
+            plain_text: self.plain_text,
+        }
+    }
+}
+
+pub static PASTE_STACK: Lazy<Mutex<PasteStack>> = Lazy::new(|| Mutex::new(PasteStack::default()));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paste_stack_order() {
+        let mut stack = PasteStack::default();
+        stack.start(vec![3, 1, 3, 2], true);
+        assert_eq!(stack.state().queued, vec![3, 1, 2]);
+        assert_eq!(stack.state().total, 3);
+
+        assert_eq!(stack.take_next(), Some(3));
+        stack.remove(2);
+        assert_eq!(stack.take_next(), Some(1));
+
+        let state = stack.state();
+        assert!(!state.active);
+        assert_eq!((state.pasted, state.total), (2, 2));
+        assert!(state.plain_text);
+        assert_eq!(stack.take_next(), None);
+
+        stack.clear();
+        assert_eq!(stack.state(), PasteStackState::default());
+    }
+}
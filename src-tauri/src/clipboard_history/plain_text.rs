@@ -0,0 +1,236 @@
+//! Turns copied rich text (HTML or RTF markup) back into plain text for
+//! "paste as plain text".
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static HTML_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)<(html|body|div|p|span|br|b|i|u|em|strong|a|table|tr|td|ul|ol|li|h[1-6])[\s>/]",
+    )
+    .unwrap()
+});
+static HTML_SKIPPED_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<(script|style|head)[\s>].*?</(script|style|head)\s*>").unwrap()
+});
+static HTML_BREAK_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)<(br|/p|/div|/li|/tr|/h[1-6]|/blockquote|/pre)(\s[^>]*)?/?>").unwrap()
+});
+static HTML_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+static HTML_ENTITY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap());
+
+/// RTF groups whose contents are metadata rather than document text
+const RTF_SKIPPED_DESTINATIONS: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "header",
+    "footer",
+    "listtable",
+    "listoverridetable",
+    "generator",
+];
+
+/// Strips HTML or RTF markup and invisible formatting characters
+pub fn strip_formatting(content: &str) -> String {
+    let text = if content.trim_start().starts_with("{\\rtf") {
+        strip_rtf(content)
+    } else if HTML_REGEX.is_match(content) {
+        strip_html(content)
+    } else {
+        content.to_string()
+    };
+
+    let text: String = text
+        .chars()
+        .filter(|c| !matches!(c, '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{FEFF}'))
+        .map(|c| if c == '\u{00A0}' { ' ' } else { c })
+        .collect();
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    lines.join("\n").trim().to_string()
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(hex) = entity
+        .strip_prefix("#x")
+        .or_else(|| entity.strip_prefix("#X"))
+    {
+        return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+    }
+    if let Some(decimal) = entity.strip_prefix('#') {
+        return decimal.parse().ok().and_then(char::from_u32);
+    }
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        "ndash" => Some('–'),
+        "mdash" => Some('—'),
+        "hellip" => Some('…'),
+        "copy" => Some('©'),
+        _ => None,
+    }
+}
+
+fn strip_html(html: &str) -> String {
+    let text = HTML_SKIPPED_REGEX.replace_all(html, "");
+    // Markup newlines are insignificant; block elements decide where lines end
+    let text = text.replace(['\r', '\n'], " ");
+    let text = HTML_BREAK_REGEX.replace_all(&text, "\n");
+    let text = HTML_TAG_REGEX.replace_all(&text, "");
+    let text = HTML_ENTITY_REGEX.replace_all(&text, |caps: &regex::Captures| {
+        decode_entity(&caps[1])
+            .map(String::from)
+            .unwrap_or_else(|| caps[0].to_string())
+    });
+
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn strip_rtf(rtf: &str) -> String {
+    let mut text = String::new();
+    let mut chars = rtf.chars().peekable();
+    // Depth of each open group and whether its contents are skipped
+    let mut skipped_stack: Vec<bool> = Vec::new();
+    let mut skipping = false;
+    // Fallback characters still to drop after a `\uN` escape
+    let mut fallback_to_skip = 0usize;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                skipped_stack.push(skipping);
+                let mut lookahead = chars.clone();
+                if lookahead.next() == Some('\\') && lookahead.next() == Some('*') {
+                    skipping = true;
+                }
+            }
+            '}' => skipping = skipped_stack.pop().unwrap_or(false),
+            '\\' => {
+                let Some(&next) = chars.peek() else { break };
+                if !next.is_ascii_alphabetic() {
+                    chars.next();
+                    let literal = match next {
+                        '\\' | '{' | '}' => Some(next),
+                        '~' => Some(' '),
+                        '_' => Some('-'),
+                        '\'' => {
+                            let hex: String = chars.by_ref().take(2).collect();
+                            u8::from_str_radix(&hex, 16).ok().map(char::from)
+                        }
+                        _ => None,
+                    };
+                    if let Some(literal) = literal {
+                        if skipping {
+                            continue;
+                        }
+                        if fallback_to_skip > 0 {
+                            fallback_to_skip -= 1;
+                        } else {
+                            text.push(literal);
+                        }
+                    }
+                    continue;
+                }
+
+                let mut word = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                    word.push(c);
+                    chars.next();
+                }
+                let mut param = String::new();
+                if chars.peek() == Some(&'-') {
+                    param.push('-');
+                    chars.next();
+                }
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    param.push(c);
+                    chars.next();
+                }
+                // A single space delimits the control word and isn't text
+                if chars.peek() == Some(&' ') {
+                    chars.next();
+                }
+
+                if RTF_SKIPPED_DESTINATIONS.contains(&word.as_str()) {
+                    skipping = true;
+                }
+                if skipping {
+                    continue;
+                }
+                match word.as_str() {
+                    "par" | "line" | "row" => text.push('\n'),
+                    "tab" | "cell" => text.push('\t'),
+                    "u" => {
+                        // Negative values encode code points above 32767
+                        if let Some(c) = param
+                            .parse::<i32>()
+                            .ok()
+                            .map(|n| if n < 0 { n + 65536 } else { n } as u32)
+                            .and_then(char::from_u32)
+                        {
+                            text.push(c);
+                        }
+                        fallback_to_skip = 1;
+                    }
+                    _ => {}
+                }
+            }
+            '\r' | '\n' => {}
+            _ if skipping => {}
+            _ => {
+                if fallback_to_skip > 0 {
+                    fallback_to_skip -= 1;
+                } else {
+                    text.push(c);
+                }
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_html() {
+        let html = "<html><head><style>p { color: red }</style></head><body>\n\
+                    <p>Hello <b>bold</b>&nbsp;&amp; <a href=\"#\">link</a></p>\n\
+                    <div>Second&#33;<br/>Third &#x263A;</div></body></html>";
+        assert_eq!(
+            strip_formatting(html),
+            "Hello bold & link\nSecond!\nThird ☺"
+        );
+    }
+
+    #[test]
+    fn test_strip_rtf() {
+        let rtf = r"{\rtf1\ansi{\fonttbl\f0\fswiss Helvetica;}{\colortbl;\red255\green0\blue0;}
+{\*\expandedcolortbl;;}\f0\pard Hello \b bold\b0  \'e9t\'e9\par
+Caf\u233e and \{braces\}\tab done}";
+        assert_eq!(
+            strip_formatting(rtf),
+            "Hello bold été\nCafé and {braces}\tdone"
+        );
+    }
+
+    #[test]
+    fn test_plain_text_is_kept() {
+        assert_eq!(
+            strip_formatting("a < b\u{200B} and\u{00A0}c  \n"),
+            "a < b and c"
+        );
+        assert_eq!(strip_formatting("if x<y && y>z"), "if x<y && y>z");
+    }
+}
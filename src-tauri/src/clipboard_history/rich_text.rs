@@ -0,0 +1,186 @@
+//! The formatted version of a copy. Browsers and office apps offer HTML or
+//! RTF next to the plain text; the history keeps it so an item can be pasted
+//! with its formatting, and describes it so the UI can preview it.
+
+use crate::clipboard::{self, HTML_MIME, RTF_MIME};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Larger formatted copies (whole pages with inlined images) only keep
+/// their plain text
+const MAX_RICH_BYTES: usize = 2 * 1024 * 1024;
+
+static UNSAFE_ELEMENT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"(?is)<(script|style|iframe|object|embed|head)[\s>].*?",
+        r"</(script|style|iframe|object|embed|head)\s*>|<(meta|link|base)[^>]*>"
+    ))
+    .unwrap()
+});
+static EVENT_HANDLER_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\s+on[a-z]+\s*=\s*("[^"]*"|'[^']*'|[^\s>]+)"#).unwrap());
+static SCRIPT_URL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(href|src)\s*=\s*("\s*javascript:[^"]*"|'\s*javascript:[^']*')"#).unwrap()
+});
+/// Chromium wraps the copied part in these
+static FRAGMENT_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<!--StartFragment-->(.*)<!--EndFragment-->").unwrap());
+static LINK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<a\s[^>]*href").unwrap());
+static IMAGE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<img[\s>]").unwrap());
+static TABLE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<table[\s>]").unwrap());
+static LIST_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<(ul|ol)[\s>]").unwrap());
+static CODE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<(pre|code)[\s>]").unwrap());
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RichFormat {
+    Html,
+    Rtf,
+}
+
+impl RichFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "html" => Some(RichFormat::Html),
+            "rtf" => Some(RichFormat::Rtf),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RichFormat::Html => "html",
+            RichFormat::Rtf => "rtf",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RichText {
+    pub format: RichFormat,
+    pub content: String,
+}
+
+/// What the history list needs to show a formatted item without loading it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RichMetadata {
+    pub format: RichFormat,
+    pub size_bytes: usize,
+    pub has_links: bool,
+    pub has_images: bool,
+    pub has_tables: bool,
+    pub has_lists: bool,
+    pub has_code: bool,
+}
+
+/// A formatted item for the preview pane. `html` is sanitized and only set
+/// for HTML; RTF is previewed from `text`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RichContent {
+    pub format: RichFormat,
+    pub html: Option<String>,
+    pub text: String,
+}
+
+/// The formatted version on the clipboard, if it offers one
+pub fn capture(mime_types: &[String]) -> Option<RichText> {
+    let offers = |mimes: &[&str]| {
+        mime_types
+            .iter()
+            .find(|offered| mimes.iter().any(|mime| offered.starts_with(mime)))
+            .cloned()
+    };
+    let (format, mime) = if let Some(mime) = offers(&[HTML_MIME]) {
+        (RichFormat::Html, mime)
+    } else if let Some(mime) = offers(&[RTF_MIME, "application/rtf"]) {
+        (RichFormat::Rtf, mime)
+    } else {
+        return None;
+    };
+    let content = clipboard::read_format(&mime)?;
+    if content.trim().is_empty() || content.len() > MAX_RICH_BYTES {
+        return None;
+    }
+    Some(RichText { format, content })
+}
+
+impl RichText {
+    pub fn metadata(&self) -> RichMetadata {
+        let markup = &self.content;
+        let matches = |html: &Regex, rtf: &str| match self.format {
+            RichFormat::Html => html.is_match(markup),
+            RichFormat::Rtf => markup.contains(rtf),
+        };
+        RichMetadata {
+            format: self.format,
+            size_bytes: markup.len(),
+            has_links: matches(&LINK_REGEX, "HYPERLINK"),
+            has_images: matches(&IMAGE_REGEX, "\\pict"),
+            has_tables: matches(&TABLE_REGEX, "\\trowd"),
+            has_lists: matches(&LIST_REGEX, "\\pntext"),
+            has_code: matches(&CODE_REGEX, "\\fmodern"),
+        }
+    }
+
+    pub fn preview(&self) -> RichContent {
+        RichContent {
+            format: self.format,
+            html: match self.format {
+                RichFormat::Html => Some(sanitize_html(&self.content)),
+                RichFormat::Rtf => None,
+            },
+            text: super::plain_text::strip_formatting(&self.content),
+        }
+    }
+}
+
+/// The copied fragment without anything that runs or loads when shown
+pub fn sanitize_html(html: &str) -> String {
+    let html = FRAGMENT_REGEX
+        .captures(html)
+        .and_then(|captures| captures.get(1))
+        .map_or(html, |fragment| fragment.as_str());
+    let html = UNSAFE_ELEMENT_REGEX.replace_all(html, "");
+    let html = EVENT_HANDLER_REGEX.replace_all(&html, "");
+    let html = SCRIPT_URL_REGEX.replace_all(&html, "$1=\"#\"");
+    html.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_html() {
+        let html = "<html><head><meta charset=\"utf-8\"><style>b{}</style></head><body>\
+                    <!--StartFragment--><p onclick=\"steal()\">Hi <a href=\"javascript:x()\">a</a>\
+                    <img src=x onerror=alert(1)></p><script>bad()</script><!--EndFragment-->\
+                    </body></html>";
+        assert_eq!(
+            sanitize_html(html),
+            "<p>Hi <a href=\"#\">a</a><img src=x></p>"
+        );
+    }
+
+    #[test]
+    fn test_metadata() {
+        let html = RichText {
+            format: RichFormat::Html,
+            content: "<table><tr><td><a href=\"https://example.com\">x</a></td></tr></table>"
+                .to_string(),
+        };
+        let metadata = html.metadata();
+        assert!(metadata.has_links && metadata.has_tables);
+        assert!(!metadata.has_images && !metadata.has_lists);
+
+        let rtf = RichText {
+            format: RichFormat::Rtf,
+            content: r"{\rtf1\ansi\trowd\cellx1000 cell\cell\row}".to_string(),
+        };
+        assert!(rtf.metadata().has_tables);
+        assert_eq!(rtf.preview().html, None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Direct sync between Flare instances on the local network, without any
+//! storage in between. A device connects to each configured peer and both
+//! sides swap every sealed operation they hold, as one line of JSON each
+//! way, so changes also travel through peers that are in reach of both.
+//!
+//! Peers prove they hold the shared key with a sealed greeting before they
+//! get an answer; the operations themselves are sealed as well, so a
+//! stranger on the network never sees anything but ciphertext.
+
+use super::log::{open, seal, Change, SyncOp};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const DEFAULT_PORT: u16 = 53319;
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Upper bound on one side of an exchange
+const MAX_MESSAGE_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Exchange {
+    /// A sealed operation naming the sender, only readable with the key
+    greeting: String,
+    payloads: Vec<String>,
+}
+
+/// Sealed like any operation. It's a tombstone for a hash nothing has, so
+/// it would change nothing even if it ended up in a log.
+fn greeting(device: &str, key: &[u8; 32]) -> Result<String, String> {
+    let op = SyncOp::new(
+        device,
+        0,
+        Change::Delete {
+            hash: String::new(),
+        },
+    );
+    seal(&op, key).map_err(|e| e.to_string())
+}
+
+fn greeted_by(exchange: &Exchange, key: &[u8; 32]) -> Option<String> {
+    open(&exchange.greeting, key).ok().map(|op| op.device)
+}
+
+async fn read_exchange(stream: &mut BufReader<TcpStream>) -> Result<Exchange, String> {
+    let mut line = String::new();
+    (&mut *stream)
+        .take(MAX_MESSAGE_BYTES)
+        .read_line(&mut line)
+        .await
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&line).map_err(|e| format!("Invalid sync message: {}", e))
+}
+
+async fn write_exchange(
+    stream: &mut BufReader<TcpStream>,
+    exchange: &Exchange,
+) -> Result<(), String> {
+    let mut line = serde_json::to_string(exchange).map_err(|e| e.to_string())?;
+    line.push('\n');
+    let stream = stream.get_mut();
+    stream
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    stream.flush().await.map_err(|e| e.to_string())
+}
+
+/// Sends everything to `peer` (`host` or `host:port`) and returns what it
+/// sent back
+pub async fn exchange(
+    peer: &str,
+    device: &str,
+    key: &[u8; 32],
+    payloads: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let address = if peer.contains(':') {
+        peer.to_string()
+    } else {
+        format!("{}:{}", peer, DEFAULT_PORT)
+    };
+    let request = Exchange {
+        greeting: greeting(device, key)?,
+        payloads,
+    };
+    tokio::time::timeout(TIMEOUT, async {
+        let stream = TcpStream::connect(&address)
+            .await
+            .map_err(|e| format!("Can't reach {}: {}", address, e))?;
+        let mut stream = BufReader::new(stream);
+        write_exchange(&mut stream, &request).await?;
+        let reply = read_exchange(&mut stream).await?;
+        if greeted_by(&reply, key).is_none() {
+            return Err(format!("{} doesn't have this sync passphrase", address));
+        }
+        Ok(reply.payloads)
+    })
+    .await
+    .map_err(|_| format!("Timed out syncing with {}", address))?
+}
+
+/// Answers peers on `port` until the task is dropped. `handle` gets a
+/// peer's operations and returns ours.
+pub async fn serve<F>(port: u16, device: String, key: [u8; 32], handle: F) -> Result<(), String>
+where
+    F: Fn(Vec<String>) -> Result<Vec<String>, String> + Send + Sync + Clone + 'static,
+{
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .map_err(|e| format!("Can't listen for sync peers on port {}: {}", port, e))?;
+    loop {
+        let (stream, address) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to accept sync peer");
+                continue;
+            }
+        };
+        let (device, handle) = (device.clone(), handle.clone());
+        tokio::spawn(async move {
+            let result = tokio::time::timeout(TIMEOUT, async {
+                let mut stream = BufReader::new(stream);
+                let request = read_exchange(&mut stream).await?;
+                let peer = greeted_by(&request, &key).ok_or("peer doesn't have the passphrase")?;
+                tracing::debug!(peer = %peer, %address, "Syncing clipboard history with peer");
+                let payloads = handle(request.payloads)?;
+                let reply = Exchange {
+                    greeting: greeting(&device, &key)?,
+                    payloads,
+                };
+                write_exchange(&mut stream, &reply).await
+            })
+            .await;
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::debug!(%address, error = %e, "Sync peer turned away"),
+                Err(_) => tracing::debug!(%address, "Sync peer timed out"),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_exchange_between_instances() {
+        let key: [u8; 32] = rand::random();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        tokio::spawn(serve(port, "server".to_string(), key, |received| {
+            assert_eq!(received, vec!["from client".to_string()]);
+            Ok(vec!["from server".to_string()])
+        }));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let peer = format!("127.0.0.1:{}", port);
+        let reply = exchange(&peer, "client", &key, vec!["from client".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(reply, vec!["from server".to_string()]);
+
+        let wrong_key: [u8; 32] = rand::random();
+        assert!(exchange(&peer, "stranger", &wrong_key, Vec::new())
+            .await
+            .is_err());
+    }
+}
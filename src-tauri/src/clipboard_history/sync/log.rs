@@ -0,0 +1,253 @@
+//! The sync log. Every device appends its own history changes as operations
+//! and never edits them; deletions are tombstones rather than removals.
+//! Replaying the union of all devices' operations in timestamp order gives
+//! the same history everywhere, whichever order they arrived in.
+
+use crate::clipboard_history::encryption::{decrypt, encrypt};
+use crate::clipboard_history::types::ContentType;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+
+/// Shared by every device, so the same passphrase gives the same key
+const KEY_SALT: &[u8] = b"dev.byteatatime.flare.clipboard-sync";
+const KEY_ROUNDS: u32 = 600_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum Change {
+    Add {
+        hash: String,
+        content_type: ContentType,
+        content: String,
+        source_app_name: Option<String>,
+    },
+    Copy {
+        hash: String,
+    },
+    Pin {
+        hash: String,
+        pinned: bool,
+    },
+    Delete {
+        hash: String,
+    },
+}
+
+impl Change {
+    pub fn hash(&self) -> &str {
+        match self {
+            Change::Add { hash, .. }
+            | Change::Copy { hash }
+            | Change::Pin { hash, .. }
+            | Change::Delete { hash } => hash,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncOp {
+    pub id: String,
+    pub device: String,
+    /// Nanoseconds since the epoch, like the history's timestamps
+    pub at: i64,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+impl SyncOp {
+    pub fn new(device: &str, at: i64, change: Change) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            device: device.to_string(),
+            at,
+            change,
+        }
+    }
+}
+
+/// A history item as the log has it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ItemState {
+    pub content_type: ContentType,
+    pub content: String,
+    pub source_app_name: Option<String>,
+    pub first_copied_at: i64,
+    pub last_copied_at: i64,
+    pub times_copied: i32,
+    pub is_pinned: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Merged {
+    pub items: HashMap<String, ItemState>,
+    /// Hashes the log knows about whose last word was a deletion
+    pub deleted: HashSet<String>,
+}
+
+/// Replays `ops`. Ties on the timestamp are broken by device and operation
+/// id, so every device picks the same order.
+pub fn materialize(mut ops: Vec<SyncOp>) -> Merged {
+    ops.sort_by(|a, b| (a.at, &a.device, &a.id).cmp(&(b.at, &b.device, &b.id)));
+    let mut merged = Merged::default();
+    for op in ops {
+        let at = op.at;
+        match op.change {
+            Change::Add {
+                hash,
+                content_type,
+                content,
+                source_app_name,
+            } => {
+                merged.deleted.remove(&hash);
+                merged
+                    .items
+                    .entry(hash)
+                    .and_modify(|item| {
+                        item.last_copied_at = at;
+                        item.times_copied += 1;
+                    })
+                    .or_insert(ItemState {
+                        content_type,
+                        content,
+                        source_app_name,
+                        first_copied_at: at,
+                        last_copied_at: at,
+                        times_copied: 1,
+                        is_pinned: false,
+                    });
+            }
+            Change::Copy { hash } => {
+                if let Some(item) = merged.items.get_mut(&hash) {
+                    item.last_copied_at = at;
+                    item.times_copied += 1;
+                }
+            }
+            Change::Pin { hash, pinned } => {
+                if let Some(item) = merged.items.get_mut(&hash) {
+                    item.is_pinned = pinned;
+                }
+            }
+            Change::Delete { hash } => {
+                if merged.items.remove(&hash).is_some() {
+                    merged.deleted.insert(hash);
+                }
+            }
+        }
+    }
+    merged
+}
+
+/// The shared key for a sync passphrase
+pub fn derive_key(passphrase: &str) -> [u8; 32] {
+    let mut key = [0; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), KEY_SALT, KEY_ROUNDS, &mut key);
+    key
+}
+
+/// Encrypts an operation for the log; this is all that leaves the device
+pub fn seal(op: &SyncOp, key: &[u8; 32]) -> Result<String, AppError> {
+    let json = serde_json::to_string(op).map_err(|e| AppError::Serialization(e.to_string()))?;
+    encrypt(&json, key)
+}
+
+pub fn open(payload: &str, key: &[u8; 32]) -> Result<SyncOp, AppError> {
+    let json = decrypt(payload.trim(), key)?;
+    serde_json::from_str(&json).map_err(|e| AppError::Serialization(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(device: &str, at: i64, change: Change) -> SyncOp {
+        SyncOp::new(device, at, change)
+    }
+
+    fn add(hash: &str) -> Change {
+        Change::Add {
+            hash: hash.to_string(),
+            content_type: ContentType::Text,
+            content: format!("text {}", hash),
+            source_app_name: None,
+        }
+    }
+
+    fn delete(hash: &str) -> Change {
+        Change::Delete {
+            hash: hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_materialize_is_order_independent() {
+        let ops = vec![
+            op("a", 1, add("x")),
+            op("b", 2, add("y")),
+            op(
+                "b",
+                3,
+                Change::Pin {
+                    hash: "x".to_string(),
+                    pinned: true,
+                },
+            ),
+            op("a", 4, delete("y")),
+            op(
+                "a",
+                5,
+                Change::Copy {
+                    hash: "x".to_string(),
+                },
+            ),
+        ];
+        let mut reversed = ops.clone();
+        reversed.reverse();
+
+        for merged in [materialize(ops), materialize(reversed)] {
+            let x = &merged.items["x"];
+            assert!(x.is_pinned);
+            assert_eq!((x.first_copied_at, x.last_copied_at), (1, 5));
+            assert_eq!(x.times_copied, 2);
+            assert!(!merged.items.contains_key("y"));
+            assert_eq!(merged.deleted, HashSet::from(["y".to_string()]));
+        }
+    }
+
+    #[test]
+    fn test_add_after_tombstone_restores_item() {
+        let merged = materialize(vec![
+            op("a", 1, add("x")),
+            op("b", 2, delete("x")),
+            op("a", 3, add("x")),
+        ]);
+        assert_eq!(merged.items["x"].first_copied_at, 3);
+        assert!(merged.deleted.is_empty());
+
+        // A pin for an item that is gone doesn't bring it back
+        let merged = materialize(vec![
+            op("a", 1, add("x")),
+            op("b", 2, delete("x")),
+            op(
+                "a",
+                3,
+                Change::Pin {
+                    hash: "x".to_string(),
+                    pinned: true,
+                },
+            ),
+        ]);
+        assert!(merged.items.is_empty());
+    }
+
+    #[test]
+    fn test_sealed_ops_need_the_shared_key() {
+        let key: [u8; 32] = rand::random();
+        let sealed = seal(&op("a", 1, add("x")), &key).unwrap();
+        assert_eq!(open(&sealed, &key).unwrap().change, add("x"));
+        assert!(open(&sealed, &rand::random()).is_err());
+        assert!(!sealed.contains("text x"));
+    }
+}
@@ -0,0 +1,435 @@
+//! Clipboard history sync between machines, through a WebDAV folder or S3
+//! bucket the user provides, or directly between Flare instances on the
+//! local network.
+//!
+//! Every text item, copy, pin and deletion becomes an operation in the sync
+//! log (see [`log`]), sealed with a key derived from a passphrase the user
+//! sets on each machine. Only sealed operations leave the device. Images
+//! and files stay local, since their content is a path on this machine.
+
+mod lan;
+pub mod log;
+mod remote;
+
+use super::manager::{ClipboardHistoryManager, MANAGER};
+use super::types::ContentType;
+use crate::error::AppError;
+use crate::power_awareness;
+use crate::secrets;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use remote::{Remote, WebDav, S3};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+pub const SYNCED_EVENT: &str = "clipboard-history-synced";
+const KEY_SECRET: &str = "key";
+/// The WebDAV password or the S3 secret access key
+const BACKEND_SECRET: &str = "backend_secret";
+const DEFAULT_INTERVAL_MINUTES: u32 = 5;
+/// Local changes go out once there has been none for this long
+const CHANGE_DEBOUNCE: Duration = Duration::from_secs(5);
+/// Larger items stay on the machine they were copied on
+const MAX_SYNCED_BYTES: usize = 1024 * 1024;
+
+#[derive(Clone)]
+pub struct SyncContext {
+    pub device: String,
+    pub key: [u8; 32],
+}
+
+/// Set while sync is on and a passphrase is stored
+static CONTEXT: Lazy<Mutex<Option<SyncContext>>> = Lazy::new(|| Mutex::new(None));
+static CHANGED: Lazy<Notify> = Lazy::new(Notify::new);
+static LAST_REPORT: Lazy<Mutex<Option<SyncReport>>> = Lazy::new(|| Mutex::new(None));
+static LAN_SERVER: Lazy<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SyncBackend {
+    WebDav {
+        url: String,
+        username: String,
+    },
+    S3 {
+        endpoint: String,
+        region: String,
+        bucket: String,
+        #[serde(default)]
+        prefix: String,
+        access_key_id: String,
+    },
+    Lan {
+        #[serde(default = "default_lan_port")]
+        port: u16,
+        /// `host` or `host:port` of the other instances
+        peers: Vec<String>,
+    },
+}
+
+fn default_lan_port() -> u16 {
+    lan::DEFAULT_PORT
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncSettings {
+    pub enabled: bool,
+    /// Names this machine's log; generated on first use
+    pub device_id: String,
+    pub interval_minutes: u32,
+    pub backend: Option<SyncBackend>,
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device_id: String::new(),
+            interval_minutes: DEFAULT_INTERVAL_MINUTES,
+            backend: None,
+        }
+    }
+}
+
+impl SyncSettings {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("clipboard_sync.json"))
+    }
+
+    pub fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        let mut settings = if path.exists() {
+            let content = fs::read_to_string(path)?;
+            if content.trim().is_empty() {
+                Self::default()
+            } else {
+                serde_json::from_str(&content)
+                    .map_err(|e| AppError::Serialization(e.to_string()))?
+            }
+        } else {
+            Self::default()
+        };
+        if settings.device_id.is_empty() {
+            settings.device_id = uuid::Uuid::new_v4().simple().to_string();
+            settings.save(app)?;
+        }
+        Ok(settings)
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub at: DateTime<Utc>,
+    /// Operations from other devices that were new here
+    pub received: usize,
+    /// Operations that didn't open with our key, from another passphrase
+    pub rejected: usize,
+    pub sent: usize,
+    /// Problems that didn't stop the rest, like an unreachable LAN peer
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub settings: SyncSettings,
+    pub has_passphrase: bool,
+    pub has_backend_secret: bool,
+    pub last_sync: Option<SyncReport>,
+}
+
+pub fn context() -> Option<SyncContext> {
+    CONTEXT.lock().unwrap().clone()
+}
+
+/// Called when a local change was logged, to send it out soon
+pub fn changed() {
+    CHANGED.notify_one();
+}
+
+pub fn is_synced(content_type: &ContentType, content: &str) -> bool {
+    !matches!(content_type, ContentType::Image | ContentType::File)
+        && content.len() <= MAX_SYNCED_BYTES
+}
+
+fn stored_key() -> Result<Option<[u8; 32]>, AppError> {
+    let Some(hex_key) = secrets::get(secrets::CLIPBOARD_SYNC, KEY_SECRET)? else {
+        return Ok(None);
+    };
+    let key = hex::decode(hex_key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| AppError::ClipboardHistory("Invalid sync key".into()))?;
+    Ok(Some(key))
+}
+
+fn with_manager<T>(
+    f: impl FnOnce(&ClipboardHistoryManager) -> Result<T, AppError>,
+) -> Result<T, String> {
+    let guard = MANAGER.lock().unwrap();
+    let manager = guard
+        .as_ref()
+        .ok_or("Clipboard history manager not initialized")?;
+    f(manager).map_err(|e| e.to_string())
+}
+
+/// Adds operations from elsewhere and applies them to the history.
+/// Returns how many were new and how many were rejected.
+fn take_in(
+    app: &AppHandle,
+    context: &SyncContext,
+    payloads: &[String],
+) -> Result<(usize, usize), String> {
+    let (received, rejected) = with_manager(|manager| {
+        let counts = manager.ingest_sync_payloads(payloads, &context.key)?;
+        if counts.0 > 0 {
+            manager.apply_sync_log(&context.key)?;
+        }
+        Ok(counts)
+    })?;
+    if received > 0 {
+        let _ = app.emit(SYNCED_EVENT, received);
+    }
+    Ok((received, rejected))
+}
+
+fn remote_for(backend: &SyncBackend) -> Result<Option<Remote<'_>>, String> {
+    let secret =
+        || secrets::get(secrets::CLIPBOARD_SYNC, BACKEND_SECRET).map_err(|e| e.to_string());
+    Ok(match backend {
+        SyncBackend::WebDav { url, username } => Some(Remote::WebDav(WebDav {
+            url,
+            username,
+            password: secret()?,
+        })),
+        SyncBackend::S3 {
+            endpoint,
+            region,
+            bucket,
+            prefix,
+            access_key_id,
+        } => Some(Remote::S3(S3 {
+            endpoint,
+            region,
+            bucket,
+            prefix,
+            access_key_id,
+            secret_access_key: secret()?.ok_or("No S3 secret access key set")?,
+        })),
+        SyncBackend::Lan { .. } => None,
+    })
+}
+
+async fn sync_now(app: &AppHandle) -> Result<SyncReport, String> {
+    let settings = SyncSettings::load(app).map_err(|e| e.to_string())?;
+    if !settings.enabled {
+        return Err("Clipboard sync is turned off".to_string());
+    }
+    let context = context().ok_or("Set a sync passphrase first")?;
+    let backend = settings.backend.ok_or("No sync backend configured")?;
+    with_manager(|manager| manager.seed_sync_log(&context.device, &context.key))?;
+
+    let mut report = SyncReport {
+        at: Utc::now(),
+        received: 0,
+        rejected: 0,
+        sent: 0,
+        errors: Vec::new(),
+    };
+    if let SyncBackend::Lan { peers, .. } = &backend {
+        for peer in peers {
+            let payloads = with_manager(|manager| manager.sync_payloads(None))?;
+            let count = payloads.len();
+            match lan::exchange(peer, &context.device, &context.key, payloads).await {
+                Ok(received) => {
+                    let (new, rejected) = take_in(app, &context, &received)?;
+                    report.received += new;
+                    report.rejected += rejected;
+                    report.sent += count;
+                }
+                Err(e) => report.errors.push(e),
+            }
+        }
+    } else if let Some(remote) = remote_for(&backend)? {
+        let own_log = format!("{}{}", context.device, remote::LOG_EXTENSION);
+        let payloads: Vec<String> = remote
+            .pull(&own_log)
+            .await?
+            .iter()
+            .flat_map(|(_, body)| body.lines().map(str::to_string).collect::<Vec<_>>())
+            .collect();
+        let (received, rejected) = take_in(app, &context, &payloads)?;
+        report.received = received;
+        report.rejected = rejected;
+
+        let own = with_manager(|manager| manager.sync_payloads(Some(&context.device)))?;
+        report.sent = own.len();
+        remote.push(&own_log, own.join("\n")).await?;
+    }
+    Ok(report)
+}
+
+/// Syncs and keeps the outcome for `history_sync_status`
+async fn run(app: &AppHandle) -> Result<SyncReport, String> {
+    let result = sync_now(app).await;
+    let report = match &result {
+        Ok(report) => report.clone(),
+        Err(e) => SyncReport {
+            at: Utc::now(),
+            received: 0,
+            rejected: 0,
+            sent: 0,
+            errors: vec![e.clone()],
+        },
+    };
+    for error in &report.errors {
+        tracing::warn!(error = %error, "Clipboard sync problem");
+    }
+    *LAST_REPORT.lock().unwrap() = Some(report);
+    result
+}
+
+/// Re-reads the settings and key, and starts or stops answering LAN peers
+fn refresh(app: &AppHandle) -> Result<SyncSettings, String> {
+    let settings = SyncSettings::load(app).map_err(|e| e.to_string())?;
+    let key = stored_key().map_err(|e| e.to_string())?;
+    let context = key.filter(|_| settings.enabled).map(|key| SyncContext {
+        device: settings.device_id.clone(),
+        key,
+    });
+    *CONTEXT.lock().unwrap() = context.clone();
+
+    let mut server = LAN_SERVER.lock().unwrap();
+    if let Some(server) = server.take() {
+        server.abort();
+    }
+    if let (Some(context), Some(SyncBackend::Lan { port, .. })) = (context, &settings.backend) {
+        let (app, port) = (app.clone(), *port);
+        *server = Some(tauri::async_runtime::spawn(async move {
+            let handler_context = context.clone();
+            let handle = move |payloads: Vec<String>| {
+                take_in(&app, &handler_context, &payloads)?;
+                with_manager(|manager| manager.sync_payloads(None))
+            };
+            if let Err(e) = lan::serve(port, context.device, context.key, handle).await {
+                tracing::error!(error = %e, "Clipboard sync stopped listening for peers");
+            }
+        }));
+    }
+    Ok(settings)
+}
+
+fn status(settings: SyncSettings) -> Result<SyncStatus, String> {
+    Ok(SyncStatus {
+        settings,
+        has_passphrase: secrets::exists(secrets::CLIPBOARD_SYNC, KEY_SECRET)
+            .map_err(|e| e.to_string())?,
+        has_backend_secret: secrets::exists(secrets::CLIPBOARD_SYNC, BACKEND_SECRET)
+            .map_err(|e| e.to_string())?,
+        last_sync: LAST_REPORT.lock().unwrap().clone(),
+    })
+}
+
+/// Syncs every few minutes, and shortly after local changes
+pub fn init(app: &AppHandle) {
+    if let Err(e) = refresh(app) {
+        tracing::warn!(error = %e, "Failed to set up clipboard sync");
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let interval = SyncSettings::load(&app)
+                .map(|settings| settings.interval_minutes.max(1))
+                .unwrap_or(DEFAULT_INTERVAL_MINUTES);
+            tokio::select! {
+                _ = power_awareness::sleep(Duration::from_secs(interval as u64 * 60)) => {}
+                _ = CHANGED.notified() => tokio::time::sleep(CHANGE_DEBOUNCE).await,
+            }
+            if context().is_some() {
+                let _ = run(&app).await;
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn history_sync_status(app: AppHandle) -> Result<SyncStatus, String> {
+    status(SyncSettings::load(&app).map_err(|e| e.to_string())?)
+}
+
+#[tauri::command]
+pub fn history_sync_set_settings(
+    app: AppHandle,
+    settings: SyncSettings,
+) -> Result<SyncStatus, String> {
+    let stored = SyncSettings::load(&app).map_err(|e| e.to_string())?;
+    let settings = SyncSettings {
+        device_id: stored.device_id,
+        interval_minutes: settings.interval_minutes.max(1),
+        ..settings
+    };
+    settings.save(&app).map_err(|e| e.to_string())?;
+    status(refresh(&app)?)
+}
+
+/// Sets the passphrase every synced machine shares, or forgets it. The log
+/// is started over, since what was sealed with the old key can't be read
+/// with the new one.
+#[tauri::command]
+pub async fn history_sync_set_passphrase(
+    app: AppHandle,
+    passphrase: Option<String>,
+) -> Result<SyncStatus, String> {
+    match passphrase.filter(|passphrase| !passphrase.is_empty()) {
+        Some(passphrase) => {
+            let key = tauri::async_runtime::spawn_blocking(move || log::derive_key(&passphrase))
+                .await
+                .map_err(|e| e.to_string())?;
+            secrets::set(secrets::CLIPBOARD_SYNC, KEY_SECRET, &hex::encode(key))
+        }
+        None => secrets::delete(secrets::CLIPBOARD_SYNC, KEY_SECRET),
+    }
+    .map_err(|e| e.to_string())?;
+    with_manager(|manager| manager.reset_sync_log())?;
+    status(refresh(&app)?)
+}
+
+/// The WebDAV password or S3 secret access key
+#[tauri::command]
+pub fn history_sync_set_backend_secret(secret: Option<String>) -> Result<(), String> {
+    match secret.filter(|secret| !secret.is_empty()) {
+        Some(secret) => secrets::set(secrets::CLIPBOARD_SYNC, BACKEND_SECRET, &secret),
+        None => secrets::delete(secrets::CLIPBOARD_SYNC, BACKEND_SECRET),
+    }
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn history_sync_now(app: AppHandle) -> Result<SyncReport, String> {
+    run(&app).await
+}
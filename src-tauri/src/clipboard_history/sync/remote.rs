@@ -0,0 +1,399 @@
+//! Storage the user provides: a WebDAV folder or an S3 bucket (AWS or any
+//! compatible service). Each device writes `<device>.log` there with one
+//! sealed operation per line, and only ever its own file, so two devices
+//! never race on a write.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+pub const LOG_EXTENSION: &str = ".log";
+const TIMEOUT: Duration = Duration::from_secs(30);
+const PROPFIND_BODY: &str = r#"<?xml version="1.0"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/></d:prop></d:propfind>"#;
+
+static WEBDAV_HREF: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<(?:[a-z0-9]+:)?href>([^<]+)</(?:[a-z0-9]+:)?href>").unwrap());
+static S3_KEY: Lazy<Regex> = Lazy::new(|| Regex::new(r"<Key>([^<]+)</Key>").unwrap());
+
+pub struct WebDav<'a> {
+    /// The folder holding the logs
+    pub url: &'a str,
+    pub username: &'a str,
+    pub password: Option<String>,
+}
+
+pub struct S3<'a> {
+    /// `https://s3.<region>.amazonaws.com`, or the compatible service's root
+    pub endpoint: &'a str,
+    pub region: &'a str,
+    pub bucket: &'a str,
+    pub prefix: &'a str,
+    pub access_key_id: &'a str,
+    pub secret_access_key: String,
+}
+
+pub enum Remote<'a> {
+    WebDav(WebDav<'a>),
+    S3(S3<'a>),
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+async fn checked(response: reqwest::Response) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        let body = response.text().await.unwrap_or_default();
+        Err(format!(
+            "{} {}",
+            status,
+            body.chars().take(200).collect::<String>()
+        ))
+    }
+}
+
+/// Log file names in a listing, from the last path segment of each entry
+fn log_names<'t>(names: impl Iterator<Item = &'t str>) -> Vec<String> {
+    let mut logs: Vec<String> = names
+        .filter_map(|name| name.trim_end_matches('/').rsplit('/').next())
+        .map(|name| {
+            percent_encoding::percent_decode_str(name)
+                .decode_utf8_lossy()
+                .into_owned()
+        })
+        .filter(|name| name.ends_with(LOG_EXTENSION))
+        .collect();
+    logs.sort();
+    logs.dedup();
+    logs
+}
+
+impl WebDav<'_> {
+    fn folder(&self) -> String {
+        format!("{}/", self.url.trim_end_matches('/'))
+    }
+
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        method: reqwest::Method,
+        url: &str,
+    ) -> reqwest::RequestBuilder {
+        client
+            .request(method, url)
+            .basic_auth(self.username, self.password.as_deref())
+    }
+
+    async fn list(&self, client: &reqwest::Client) -> Result<Vec<String>, String> {
+        let propfind = reqwest::Method::from_bytes(b"PROPFIND").unwrap();
+        let response = self
+            .request(client, propfind, &self.folder())
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml")
+            .body(PROPFIND_BODY)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        let body = checked(response)
+            .await?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(log_names(
+            WEBDAV_HREF
+                .captures_iter(&body)
+                .filter_map(|captures| captures.get(1))
+                .map(|href| href.as_str()),
+        ))
+    }
+
+    async fn get(&self, client: &reqwest::Client, name: &str) -> Result<String, String> {
+        let url = format!("{}{}", self.folder(), name);
+        checked(
+            self.request(client, reqwest::Method::GET, &url)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?,
+        )
+        .await?
+        .text()
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    async fn put(&self, client: &reqwest::Client, name: &str, body: String) -> Result<(), String> {
+        // 405 when the folder is already there
+        let mkcol = reqwest::Method::from_bytes(b"MKCOL").unwrap();
+        let _ = self.request(client, mkcol, &self.folder()).send().await;
+        let url = format!("{}{}", self.folder(), name);
+        checked(
+            self.request(client, reqwest::Method::PUT, &url)
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?,
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{}", secret).as_bytes(), date);
+    let key = hmac(&key, region);
+    let key = hmac(&key, service);
+    hmac(&key, "aws4_request")
+}
+
+/// RFC 3986 encoding as SigV4 wants it
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn canonical_query(url: &url::Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (uri_encode(&name), uri_encode(&value)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+impl S3<'_> {
+    fn url(&self, key: &str, query: &[(&str, &str)]) -> Result<url::Url, String> {
+        let mut url = url::Url::parse(&format!(
+            "{}/{}/{}",
+            self.endpoint.trim_end_matches('/'),
+            self.bucket,
+            key
+        ))
+        .map_err(|e| format!("Invalid S3 endpoint: {}", e))?;
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        Ok(url)
+    }
+
+    fn key(&self, name: &str) -> String {
+        let prefix = self.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", prefix, name)
+        }
+    }
+
+    /// A SigV4-signed request
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        method: reqwest::Method,
+        url: url::Url,
+        body: String,
+        now: DateTime<Utc>,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err("Invalid S3 endpoint".to_string()),
+        };
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(body.as_bytes()));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method,
+            url.path(),
+            canonical_query(&url),
+            host,
+            payload_hash,
+            amz_date,
+            signed_headers,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signature = hex::encode(hmac(
+            &signing_key(&self.secret_access_key, &date, self.region, "s3"),
+            &string_to_sign,
+        ));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, scope, signed_headers, signature
+        );
+
+        Ok(client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("Authorization", authorization)
+            .body(body))
+    }
+
+    async fn list(&self, client: &reqwest::Client) -> Result<Vec<String>, String> {
+        let prefix = self.key("");
+        let url = self.url("", &[("list-type", "2"), ("prefix", &prefix)])?;
+        let body = checked(
+            self.request(client, reqwest::Method::GET, url, String::new(), Utc::now())?
+                .send()
+                .await
+                .map_err(|e| e.to_string())?,
+        )
+        .await?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+        Ok(log_names(
+            S3_KEY
+                .captures_iter(&body)
+                .filter_map(|captures| captures.get(1))
+                .map(|key| key.as_str()),
+        ))
+    }
+
+    async fn get(&self, client: &reqwest::Client, name: &str) -> Result<String, String> {
+        let url = self.url(&self.key(name), &[])?;
+        checked(
+            self.request(client, reqwest::Method::GET, url, String::new(), Utc::now())?
+                .send()
+                .await
+                .map_err(|e| e.to_string())?,
+        )
+        .await?
+        .text()
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    async fn put(&self, client: &reqwest::Client, name: &str, body: String) -> Result<(), String> {
+        let url = self.url(&self.key(name), &[])?;
+        checked(
+            self.request(client, reqwest::Method::PUT, url, body, Utc::now())?
+                .send()
+                .await
+                .map_err(|e| e.to_string())?,
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+impl Remote<'_> {
+    /// Every other device's log, by file name
+    pub async fn pull(&self, own: &str) -> Result<Vec<(String, String)>, String> {
+        let client = client()?;
+        let names = match self {
+            Remote::WebDav(webdav) => webdav.list(&client).await?,
+            Remote::S3(s3) => s3.list(&client).await?,
+        };
+        let mut logs = Vec::new();
+        for name in names.into_iter().filter(|name| name != own) {
+            let body = match self {
+                Remote::WebDav(webdav) => webdav.get(&client, &name).await,
+                Remote::S3(s3) => s3.get(&client, &name).await,
+            };
+            match body {
+                Ok(body) => logs.push((name, body)),
+                Err(e) => tracing::warn!(log = %name, error = %e, "Failed to download sync log"),
+            }
+        }
+        Ok(logs)
+    }
+
+    pub async fn push(&self, own: &str, body: String) -> Result<(), String> {
+        let client = client()?;
+        match self {
+            Remote::WebDav(webdav) => webdav.put(&client, own, body).await,
+            Remote::S3(s3) => s3.put(&client, own, body).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key_matches_aws_example() {
+        // From the AWS SigV4 documentation
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex::encode(key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn test_canonical_query_is_sorted_and_encoded() {
+        let url = url::Url::parse("https://s3.example.com/bucket/?prefix=flare sync/&list-type=2")
+            .unwrap();
+        assert_eq!(canonical_query(&url), "list-type=2&prefix=flare%20sync%2F");
+    }
+
+    #[test]
+    fn test_log_names_from_listings() {
+        let body = r#"<d:multistatus xmlns:d="DAV:">
+            <d:response><d:href>/dav/flare/</d:href></d:response>
+            <d:response><d:href>/dav/flare/abc.log</d:href></d:response>
+            <D:response><D:href>/dav/flare/my%20notes.txt</D:href></D:response>
+            <d:response><d:href>https://dav.example.com/dav/flare/def.log</d:href></d:response>
+        </d:multistatus>"#;
+        let hrefs: Vec<&str> = WEBDAV_HREF
+            .captures_iter(body)
+            .filter_map(|captures| captures.get(1))
+            .map(|href| href.as_str())
+            .collect();
+        assert_eq!(hrefs.len(), 4);
+        assert_eq!(log_names(hrefs.into_iter()), vec!["abc.log", "def.log"]);
+
+        assert_eq!(
+            log_names(["flare/abc.log", "flare/abc.log", "flare/readme"].into_iter()),
+            vec!["abc.log"]
+        );
+    }
+}
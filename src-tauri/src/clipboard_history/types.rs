@@ -1,3 +1,4 @@
+use super::rich_text::RichMetadata;
 use crate::error::AppError;
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
@@ -26,6 +27,17 @@ pub struct ClipboardItem {
     pub last_copied_at: DateTime<Utc>,
     pub times_copied: i32,
     pub is_pinned: bool,
+    /// Set when the copy also had HTML or RTF
+    pub rich: Option<RichMetadata>,
+}
+
+/// Keyset position in the history, which is ordered by last copy, newest
+/// first, then by id
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryCursor {
+    pub last_copied_at: i64,
+    pub id: i64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
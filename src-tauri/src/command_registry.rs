@@ -0,0 +1,591 @@
+//! User overrides for launchable items: builtin commands, extension commands
+//! and quicklinks. Items are keyed by the ids the palette already uses for
+//! frecency, and only items the user changed get a row.
+//!
+//! Search results are passed through `apply_command_overrides`, which drops
+//! disabled items, swaps in custom titles and icons and floats pinned items
+//! to the top in their saved order, then applies the active `profiles`.
+
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
+
+const COMMAND_OVERRIDES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS command_overrides (
+    item_id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    disabled INTEGER NOT NULL DEFAULT 0,
+    custom_title TEXT,
+    custom_icon TEXT,
+    pinned INTEGER NOT NULL DEFAULT 0,
+    favorite INTEGER NOT NULL DEFAULT 0,
+    position INTEGER,
+    updated_at INTEGER NOT NULL
+)";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CommandKind {
+    Builtin,
+    Extension,
+    Quicklink,
+}
+
+impl CommandKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CommandKind::Builtin => "builtin",
+            CommandKind::Extension => "extension",
+            CommandKind::Quicklink => "quicklink",
+        }
+    }
+
+    fn from_db(value: &str) -> Self {
+        match value {
+            "extension" => CommandKind::Extension,
+            "quicklink" => CommandKind::Quicklink,
+            _ => CommandKind::Builtin,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandOverride {
+    pub item_id: String,
+    pub kind: CommandKind,
+    pub disabled: bool,
+    pub custom_title: Option<String>,
+    pub custom_icon: Option<String>,
+    pub pinned: bool,
+    pub favorite: bool,
+    /// Order among pinned items, lowest first
+    pub position: Option<i64>,
+}
+
+impl Storable for CommandOverride {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        Ok(CommandOverride {
+            item_id: row.get(0)?,
+            kind: CommandKind::from_db(&row.get::<_, String>(1)?),
+            disabled: row.get(2)?,
+            custom_title: row.get(3)?,
+            custom_icon: row.get(4)?,
+            pinned: row.get(5)?,
+            favorite: row.get(6)?,
+            position: row.get(7)?,
+        })
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandOverrideInput {
+    #[serde(default)]
+    pub disabled: bool,
+    pub custom_title: Option<String>,
+    pub custom_icon: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+/// A search result as the palette sees it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchableItem {
+    pub id: String,
+    pub kind: CommandKind,
+    pub title: String,
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+fn non_blank(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+const SELECT_OVERRIDES: &str = "SELECT item_id, kind, disabled, custom_title, custom_icon, pinned, favorite, position FROM command_overrides";
+
+pub struct CommandRegistry {
+    store: Store,
+}
+
+impl CommandRegistry {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "command_registry.sqlite")?;
+        store.init_table(COMMAND_OVERRIDES_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(COMMAND_OVERRIDES_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    fn list_overrides(&self) -> Result<Vec<CommandOverride>, AppError> {
+        self.store.query(
+            &format!(
+                "{} ORDER BY position IS NULL, position, item_id",
+                SELECT_OVERRIDES
+            ),
+            [],
+        )
+    }
+
+    fn get_override(&self, item_id: &str) -> Result<Option<CommandOverride>, AppError> {
+        self.store.query_row(
+            &format!("{} WHERE item_id = ?", SELECT_OVERRIDES),
+            params![item_id],
+        )
+    }
+
+    /// Replaces the item's overrides. Newly pinned items go to the end of the
+    /// pinned list; unpinning clears the position.
+    fn set_override(
+        &self,
+        item_id: &str,
+        kind: CommandKind,
+        input: CommandOverrideInput,
+    ) -> Result<(), AppError> {
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        let existing: Option<Option<i64>> = tx
+            .query_row(
+                "SELECT position FROM command_overrides WHERE item_id = ?",
+                params![item_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let position = match (input.pinned, existing.flatten()) {
+            (false, _) => None,
+            (true, Some(position)) => Some(position),
+            (true, None) => Some(tx.query_row(
+                "SELECT COALESCE(MAX(position), -1) + 1 FROM command_overrides",
+                [],
+                |row| row.get::<_, i64>(0),
+            )?),
+        };
+        tx.execute(
+            "INSERT INTO command_overrides
+                (item_id, kind, disabled, custom_title, custom_icon, pinned, favorite, position, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(item_id) DO UPDATE SET
+                kind = excluded.kind, disabled = excluded.disabled,
+                custom_title = excluded.custom_title, custom_icon = excluded.custom_icon,
+                pinned = excluded.pinned, favorite = excluded.favorite,
+                position = excluded.position, updated_at = excluded.updated_at",
+            params![
+                item_id,
+                kind.as_str(),
+                input.disabled,
+                non_blank(input.custom_title),
+                non_blank(input.custom_icon),
+                input.pinned,
+                input.favorite,
+                position,
+                Utc::now().timestamp()
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn reset_override(&self, item_id: &str) -> Result<(), AppError> {
+        self.store.execute(
+            "DELETE FROM command_overrides WHERE item_id = ?",
+            params![item_id],
+        )?;
+        Ok(())
+    }
+
+    /// Pins or unpins an item without touching its other overrides; rows that
+    /// end up overriding nothing are removed
+    fn set_pinned(&self, item_id: &str, kind: CommandKind, pinned: bool) -> Result<(), AppError> {
+        let existing = self.get_override(item_id)?;
+        let input = match existing {
+            Some(o) => CommandOverrideInput {
+                disabled: o.disabled,
+                custom_title: o.custom_title,
+                custom_icon: o.custom_icon,
+                pinned,
+                favorite: o.favorite,
+            },
+            None => CommandOverrideInput {
+                pinned,
+                ..Default::default()
+            },
+        };
+        let is_default = !input.disabled
+            && !input.pinned
+            && !input.favorite
+            && input.custom_title.is_none()
+            && input.custom_icon.is_none();
+        if is_default {
+            return self.reset_override(item_id);
+        }
+        self.set_override(item_id, kind, input)
+    }
+
+    fn pinned(&self) -> Result<Vec<CommandOverride>, AppError> {
+        self.store.query(
+            &format!(
+                "{} WHERE pinned = 1 AND disabled = 0 ORDER BY position, item_id",
+                SELECT_OVERRIDES
+            ),
+            [],
+        )
+    }
+
+    /// Renumbers pinned items in the given order; pinned items missing from
+    /// `item_ids` keep their relative order after the listed ones
+    fn reorder_pinned(&self, item_ids: &[String]) -> Result<(), AppError> {
+        let pinned: Vec<String> = self
+            .list_overrides()?
+            .into_iter()
+            .filter(|o| o.pinned)
+            .map(|o| o.item_id)
+            .collect();
+        let ordered: Vec<&String> = item_ids
+            .iter()
+            .filter(|id| pinned.contains(id))
+            .chain(pinned.iter().filter(|id| !item_ids.contains(id)))
+            .collect();
+
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        for (position, item_id) in ordered.iter().enumerate() {
+            tx.execute(
+                "UPDATE command_overrides SET position = ? WHERE item_id = ?",
+                params![position as i64, item_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn apply(&self, items: Vec<LaunchableItem>) -> Result<Vec<LaunchableItem>, AppError> {
+        let overrides: HashMap<String, CommandOverride> = self
+            .list_overrides()?
+            .into_iter()
+            .map(|o| (o.item_id.clone(), o))
+            .collect();
+        Ok(apply_overrides(items, &overrides))
+    }
+}
+
+/// Keeps the incoming (ranked) order apart from pinned items, which move to
+/// the front sorted by their position
+fn apply_overrides(
+    items: Vec<LaunchableItem>,
+    overrides: &HashMap<String, CommandOverride>,
+) -> Vec<LaunchableItem> {
+    let mut pinned = Vec::new();
+    let mut rest = Vec::new();
+    for mut item in items {
+        let Some(o) = overrides.get(&item.id) else {
+            rest.push(item);
+            continue;
+        };
+        if o.disabled {
+            continue;
+        }
+        if let Some(title) = &o.custom_title {
+            item.title = title.clone();
+        }
+        if let Some(icon) = &o.custom_icon {
+            item.icon = Some(icon.clone());
+        }
+        item.pinned = o.pinned;
+        item.favorite = o.favorite;
+        if o.pinned {
+            pinned.push((o.position.unwrap_or(i64::MAX), item));
+        } else {
+            rest.push(item);
+        }
+    }
+    pinned.sort_by_key(|(position, _)| *position);
+    pinned
+        .into_iter()
+        .map(|(_, item)| item)
+        .chain(rest)
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_command_overrides(app: AppHandle) -> Result<Vec<CommandOverride>, String> {
+    app.state::<CommandRegistry>()
+        .list_overrides()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_command_override(
+    app: AppHandle,
+    item_id: String,
+) -> Result<Option<CommandOverride>, String> {
+    app.state::<CommandRegistry>()
+        .get_override(&item_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_command_override(
+    app: AppHandle,
+    item_id: String,
+    kind: CommandKind,
+    settings: CommandOverrideInput,
+) -> Result<(), String> {
+    if item_id.trim().is_empty() {
+        return Err("Item id cannot be empty".to_string());
+    }
+    app.state::<CommandRegistry>()
+        .set_override(&item_id, kind, settings)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn reset_command_override(app: AppHandle, item_id: String) -> Result<(), String> {
+    app.state::<CommandRegistry>()
+        .reset_override(&item_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn reorder_pinned_commands(app: AppHandle, item_ids: Vec<String>) -> Result<(), String> {
+    app.state::<CommandRegistry>()
+        .reorder_pinned(&item_ids)
+        .map_err(|e| e.to_string())
+}
+
+/// Pins an item to the top of the empty-query view, regardless of frecency
+#[tauri::command]
+pub fn pin_item(app: AppHandle, item_id: String, kind: CommandKind) -> Result<(), String> {
+    if item_id.trim().is_empty() {
+        return Err("Item id cannot be empty".to_string());
+    }
+    app.state::<CommandRegistry>()
+        .set_pinned(&item_id, kind, true)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn unpin_item(app: AppHandle, item_id: String) -> Result<(), String> {
+    let registry = app.state::<CommandRegistry>();
+    let kind = match registry.get_override(&item_id).map_err(|e| e.to_string())? {
+        Some(o) => o.kind,
+        None => return Ok(()),
+    };
+    registry
+        .set_pinned(&item_id, kind, false)
+        .map_err(|e| e.to_string())
+}
+
+/// Pinned items in display order
+#[tauri::command]
+pub fn get_pinned(app: AppHandle) -> Result<Vec<CommandOverride>, String> {
+    app.state::<CommandRegistry>()
+        .pinned()
+        .map_err(|e| e.to_string())
+}
+
+/// Run by the palette over its ranked results before display. The active
+/// profiles get the last word, after the per-item overrides.
+#[tauri::command]
+pub fn apply_command_overrides(
+    app: AppHandle,
+    items: Vec<LaunchableItem>,
+) -> Result<Vec<LaunchableItem>, String> {
+    let items = app
+        .state::<CommandRegistry>()
+        .apply(items)
+        .map_err(|e| e.to_string())?;
+    crate::profiles::apply_active_profiles(&app, items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, kind: CommandKind) -> LaunchableItem {
+        LaunchableItem {
+            id: id.to_string(),
+            kind,
+            title: id.to_string(),
+            icon: None,
+            pinned: false,
+            favorite: false,
+        }
+    }
+
+    fn pin() -> CommandOverrideInput {
+        CommandOverrideInput {
+            pinned: true,
+            ..Default::default()
+        }
+    }
+
+    fn ids(items: &[LaunchableItem]) -> Vec<&str> {
+        items.iter().map(|i| i.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_apply_disables_renames_and_pins() {
+        let registry = CommandRegistry::new_for_test().unwrap();
+        registry
+            .set_override(
+                "builtin:clipboard",
+                CommandKind::Builtin,
+                CommandOverrideInput {
+                    disabled: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        registry
+            .set_override(
+                "quicklink:3",
+                CommandKind::Quicklink,
+                CommandOverrideInput {
+                    custom_title: Some(" GitHub ".to_string()),
+                    custom_icon: Some("github".to_string()),
+                    favorite: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        registry
+            .set_override("extension:spotify/play", CommandKind::Extension, pin())
+            .unwrap();
+
+        let items = vec![
+            item("builtin:clipboard", CommandKind::Builtin),
+            item("quicklink:3", CommandKind::Quicklink),
+            item("builtin:calculator", CommandKind::Builtin),
+            item("extension:spotify/play", CommandKind::Extension),
+        ];
+        let applied = registry.apply(items).unwrap();
+        assert_eq!(
+            ids(&applied),
+            vec![
+                "extension:spotify/play",
+                "quicklink:3",
+                "builtin:calculator"
+            ]
+        );
+        assert!(applied[0].pinned);
+        assert_eq!(applied[1].title, "GitHub");
+        assert_eq!(applied[1].icon.as_deref(), Some("github"));
+        assert!(applied[1].favorite);
+    }
+
+    #[test]
+    fn test_pin_order_and_reorder() {
+        let registry = CommandRegistry::new_for_test().unwrap();
+        for id in ["a", "b", "c"] {
+            registry
+                .set_override(id, CommandKind::Builtin, pin())
+                .unwrap();
+        }
+        let items = || {
+            ["c", "b", "a", "d"]
+                .iter()
+                .map(|id| item(id, CommandKind::Builtin))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(&registry.apply(items()).unwrap()),
+            vec!["a", "b", "c", "d"]
+        );
+
+        registry
+            .reorder_pinned(&["c".to_string(), "a".to_string(), "zzz".to_string()])
+            .unwrap();
+        assert_eq!(
+            ids(&registry.apply(items()).unwrap()),
+            vec!["c", "a", "b", "d"]
+        );
+
+        // Re-saving a pinned item keeps its place; unpinning drops it
+        registry
+            .set_override("c", CommandKind::Builtin, pin())
+            .unwrap();
+        registry
+            .set_override("a", CommandKind::Builtin, CommandOverrideInput::default())
+            .unwrap();
+        assert_eq!(
+            ids(&registry.apply(items()).unwrap()),
+            vec!["c", "b", "a", "d"]
+        );
+        assert_eq!(registry.get_override("a").unwrap().unwrap().position, None);
+    }
+
+    #[test]
+    fn test_pin_and_unpin_keep_other_overrides() {
+        let registry = CommandRegistry::new_for_test().unwrap();
+        registry
+            .set_override(
+                "quicklink:1",
+                CommandKind::Quicklink,
+                CommandOverrideInput {
+                    custom_title: Some("Docs".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        registry
+            .set_pinned("quicklink:1", CommandKind::Quicklink, true)
+            .unwrap();
+        registry
+            .set_pinned("builtin:emoji", CommandKind::Builtin, true)
+            .unwrap();
+
+        let pinned = registry.pinned().unwrap();
+        let pinned_ids: Vec<&str> = pinned.iter().map(|o| o.item_id.as_str()).collect();
+        assert_eq!(pinned_ids, vec!["quicklink:1", "builtin:emoji"]);
+        assert_eq!(pinned[0].custom_title.as_deref(), Some("Docs"));
+
+        registry
+            .set_pinned("quicklink:1", CommandKind::Quicklink, false)
+            .unwrap();
+        registry
+            .set_pinned("builtin:emoji", CommandKind::Builtin, false)
+            .unwrap();
+        assert!(registry.pinned().unwrap().is_empty());
+        // The custom title survives unpinning; the bare pin is removed
+        assert_eq!(
+            registry
+                .get_override("quicklink:1")
+                .unwrap()
+                .unwrap()
+                .custom_title
+                .as_deref(),
+            Some("Docs")
+        );
+        assert!(registry.get_override("builtin:emoji").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_reset_override() {
+        let registry = CommandRegistry::new_for_test().unwrap();
+        registry
+            .set_override("builtin:x", CommandKind::Builtin, pin())
+            .unwrap();
+        assert_eq!(registry.list_overrides().unwrap().len(), 1);
+
+        registry.reset_override("builtin:x").unwrap();
+        assert!(registry.get_override("builtin:x").unwrap().is_none());
+        assert!(registry.list_overrides().unwrap().is_empty());
+    }
+}
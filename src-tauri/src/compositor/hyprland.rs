@@ -0,0 +1,288 @@
+//! Hyprland over the request socket `hyprctl` uses: a request is written,
+//! the JSON (`j/` prefix) or `ok` reply is read until the socket closes.
+
+use super::{Compositor, CompositorWindow, Workspace};
+use crate::window_management::geometry::Rect;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Client {
+    address: String,
+    #[serde(default = "default_true")]
+    mapped: bool,
+    #[serde(default)]
+    hidden: bool,
+    at: [i32; 2],
+    size: [i32; 2],
+    workspace: ClientWorkspace,
+    #[serde(default)]
+    floating: bool,
+    #[serde(default)]
+    monitor: i64,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    pid: i64,
+    /// 0 for the focused window, counting up into the past
+    #[serde(rename = "focusHistoryID", default)]
+    focus_history_id: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ClientWorkspace {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct HyprWorkspace {
+    id: i64,
+    name: String,
+    #[serde(default)]
+    monitor: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Monitor {
+    id: i64,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    #[serde(default = "default_scale")]
+    scale: f64,
+    /// Left, top, right, bottom space taken by bars, in logical pixels
+    #[serde(default)]
+    reserved: [u32; 4],
+    #[serde(default)]
+    focused: bool,
+    #[serde(rename = "activeWorkspace")]
+    active_workspace: Option<ClientWorkspace>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+pub struct Hyprland {
+    socket: PathBuf,
+}
+
+impl Hyprland {
+    pub fn from_env() -> Option<Self> {
+        let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").ok()?;
+        // Hyprland 0.40 moved the sockets from /tmp into the runtime dir
+        let runtime_dir = std::env::var("XDG_RUNTIME_DIR").ok().map(PathBuf::from);
+        let socket = runtime_dir
+            .map(|dir| dir.join("hypr").join(&signature).join(".socket.sock"))
+            .filter(|socket| socket.exists())
+            .unwrap_or_else(|| {
+                PathBuf::from("/tmp/hypr")
+                    .join(&signature)
+                    .join(".socket.sock")
+            });
+        Some(Self { socket })
+    }
+
+    fn request(&self, request: &str) -> Result<String, String> {
+        let mut stream = UnixStream::connect(&self.socket)
+            .map_err(|e| format!("Failed to connect to Hyprland: {}", e))?;
+        stream
+            .set_read_timeout(Some(SOCKET_TIMEOUT))
+            .map_err(|e| e.to_string())?;
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| e.to_string())?;
+        let mut reply = String::new();
+        stream
+            .read_to_string(&mut reply)
+            .map_err(|e| e.to_string())?;
+        Ok(reply)
+    }
+
+    fn query<T: serde::de::DeserializeOwned>(&self, what: &str) -> Result<T, String> {
+        let reply = self.request(&format!("j/{}", what))?;
+        serde_json::from_str(&reply).map_err(|e| format!("Unexpected hyprctl {}: {}", what, e))
+    }
+
+    /// Runs dispatchers in one batch, so a float-move-resize happens at once
+    fn dispatch(&self, commands: &[String]) -> Result<(), String> {
+        let batch = commands
+            .iter()
+            .map(|command| format!("dispatch {}", command))
+            .collect::<Vec<_>>()
+            .join(";");
+        let reply = self.request(&format!("[[BATCH]]{}", batch))?;
+        match reply.split("\n\n").find(|part| part.trim() != "ok") {
+            Some(error) if !error.trim().is_empty() => Err(error.trim().to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    fn client(&self, address: &str) -> Result<Client, String> {
+        self.query::<Vec<Client>>("clients")?
+            .into_iter()
+            .find(|client| client.address == address)
+            .ok_or_else(|| format!("No window {}", address))
+    }
+}
+
+fn windows_from(mut clients: Vec<Client>) -> Vec<CompositorWindow> {
+    clients.retain(|client| client.mapped && !client.hidden);
+    clients.sort_by_key(|client| Reverse(client.focus_history_id));
+    clients
+        .into_iter()
+        .map(|client| CompositorWindow {
+            focused: client.focus_history_id == 0,
+            rect: Rect::new(
+                client.at[0],
+                client.at[1],
+                client.size[0].max(0) as u32,
+                client.size[1].max(0) as u32,
+            ),
+            workspace: Some(client.workspace.name),
+            pid: u32::try_from(client.pid).ok().filter(|&pid| pid > 0),
+            app_id: client.class,
+            title: client.title,
+            id: client.address,
+        })
+        .collect()
+}
+
+/// Monitor size in logical pixels, less the reserved space
+fn monitor_work_area(monitor: &Monitor) -> Rect {
+    let [left, top, right, bottom] = monitor.reserved;
+    let width = (monitor.width as f64 / monitor.scale).round() as u32;
+    let height = (monitor.height as f64 / monitor.scale).round() as u32;
+    Rect::new(
+        monitor.x + left as i32,
+        monitor.y + top as i32,
+        width.saturating_sub(left + right),
+        height.saturating_sub(top + bottom),
+    )
+}
+
+impl Compositor for Hyprland {
+    fn name(&self) -> &'static str {
+        "hyprland"
+    }
+
+    fn windows(&self) -> Result<Vec<CompositorWindow>, String> {
+        Ok(windows_from(self.query("clients")?))
+    }
+
+    fn workspaces(&self) -> Result<Vec<Workspace>, String> {
+        let monitors: Vec<Monitor> = self.query("monitors")?;
+        let focused = monitors
+            .iter()
+            .find(|monitor| monitor.focused)
+            .and_then(|monitor| monitor.active_workspace.as_ref())
+            .map(|workspace| workspace.name.clone());
+        let mut workspaces: Vec<HyprWorkspace> = self.query("workspaces")?;
+        workspaces.sort_by_key(|workspace| workspace.id);
+        Ok(workspaces
+            .into_iter()
+            .map(|workspace| Workspace {
+                focused: focused.as_deref() == Some(workspace.name.as_str()),
+                id: workspace.id.to_string(),
+                output: Some(workspace.monitor).filter(|monitor| !monitor.is_empty()),
+                name: workspace.name,
+            })
+            .collect())
+    }
+
+    fn focus(&self, id: &str) -> Result<(), String> {
+        self.dispatch(&[format!("focuswindow address:{}", id)])
+    }
+
+    fn move_resize(&self, id: &str, rect: &Rect) -> Result<(), String> {
+        let mut commands = Vec::new();
+        if !self.client(id)?.floating {
+            commands.push(format!("setfloating address:{}", id));
+        }
+        commands.push(format!(
+            "movewindowpixel exact {} {},address:{}",
+            rect.x, rect.y, id
+        ));
+        commands.push(format!(
+            "resizewindowpixel exact {} {},address:{}",
+            rect.width, rect.height, id
+        ));
+        self.dispatch(&commands)
+    }
+
+    fn move_to_workspace(&self, id: &str, workspace: &str) -> Result<(), String> {
+        let target = if workspace.parse::<i64>().is_ok() {
+            workspace.to_string()
+        } else {
+            format!("name:{}", workspace)
+        };
+        self.dispatch(&[format!("movetoworkspacesilent {},address:{}", target, id)])
+    }
+
+    fn work_area(&self, window: &CompositorWindow) -> Result<Rect, String> {
+        let client = self.client(&window.id)?;
+        let monitors: Vec<Monitor> = self.query("monitors")?;
+        monitors
+            .iter()
+            .find(|monitor| monitor.id == client.monitor)
+            .or_else(|| monitors.iter().find(|monitor| monitor.focused))
+            .map(monitor_work_area)
+            .ok_or_else(|| "No monitors found".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_from_clients() {
+        let clients: Vec<Client> = serde_json::from_str(
+            r#"[
+                {"address": "0x1", "mapped": true, "hidden": false, "at": [0, 30],
+                 "size": [960, 1050], "workspace": {"id": 1, "name": "1"},
+                 "floating": false, "monitor": 0, "class": "kitty", "title": "~",
+                 "pid": 42, "focusHistoryID": 1},
+                {"address": "0x2", "mapped": true, "hidden": false, "at": [960, 30],
+                 "size": [960, 1050], "workspace": {"id": 1, "name": "1"},
+                 "floating": false, "monitor": 0, "class": "firefox", "title": "Docs",
+                 "pid": 43, "focusHistoryID": 0},
+                {"address": "0x3", "mapped": false, "hidden": false, "at": [0, 0],
+                 "size": [0, 0], "workspace": {"id": -1, "name": ""}, "class": "",
+                 "title": "", "pid": -1, "focusHistoryID": 2}
+            ]"#,
+        )
+        .unwrap();
+        let windows = windows_from(clients);
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].app_id, "kitty");
+        assert!(windows[1].focused);
+        assert_eq!(windows[1].rect, Rect::new(960, 30, 960, 1050));
+        assert_eq!(windows[1].pid, Some(43));
+    }
+
+    #[test]
+    fn test_monitor_work_area_is_logical() {
+        let monitor: Monitor = serde_json::from_str(
+            r#"{"id": 0, "name": "DP-1", "x": 0, "y": 0, "width": 3840, "height": 2160,
+                "scale": 2.0, "reserved": [0, 30, 0, 0], "focused": true,
+                "activeWorkspace": {"id": 1, "name": "1"}}"#,
+        )
+        .unwrap();
+        assert_eq!(monitor_work_area(&monitor), Rect::new(0, 30, 1920, 1050));
+    }
+}
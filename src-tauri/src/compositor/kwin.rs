@@ -0,0 +1,374 @@
+//! KWin through its scripting DBus API. KWin has no query interface for
+//! windows, so each call loads a short script that does the work inside
+//! KWin and sends the result back with `callDBus` to an object served on
+//! our own connection. Handles the KWin 6 API with fallbacks for KWin 5.
+
+use super::{Compositor, CompositorWindow, Workspace};
+use crate::window_management::geometry::Rect;
+use serde::Deserialize;
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+
+const KWIN_SERVICE: &str = "org.kde.KWin";
+const REPLY_PATH: &str = "/KWinReply";
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Shared by every script: window lookup and the reply call. Scripts set
+/// `SERVICE`, `TOKEN` and `ARGS` before it.
+const PRELUDE: &str = r#"
+const windowList = workspace.windowList ? workspace.windowList() : workspace.clientList();
+const findWindow = (id) => windowList.find((w) => String(w.internalId) === id);
+const reply = (value) =>
+    callDBus(SERVICE, "/KWinReply", "dev.byteatatime.flare.KWinReply", "reply", TOKEN, JSON.stringify(value));
+"#;
+
+const WINDOWS_SCRIPT: &str = r#"
+const active = workspace.activeWindow || workspace.activeClient;
+reply({ ok: windowList
+    .filter((w) => w.normalWindow && !w.skipTaskbar && !w.minimized)
+    .map((w) => ({
+        id: String(w.internalId),
+        appId: String(w.resourceClass),
+        title: w.caption,
+        x: w.frameGeometry.x,
+        y: w.frameGeometry.y,
+        width: w.frameGeometry.width,
+        height: w.frameGeometry.height,
+        workspace: w.desktops ? (w.desktops.length ? w.desktops[0].name : null) : String(w.desktop),
+        focused: w === active,
+        pid: w.pid,
+    })) });
+"#;
+
+const WORKSPACES_SCRIPT: &str = r#"
+if (Array.isArray(workspace.desktops)) {
+    reply({ ok: workspace.desktops.map((d) => ({
+        id: String(d.id), name: d.name, focused: d.id === workspace.currentDesktop.id,
+    })) });
+} else {
+    const desktops = [];
+    for (let i = 1; i <= workspace.desktops; i++) {
+        desktops.push({ id: String(i), name: workspace.desktopName(i), focused: i === workspace.currentDesktop });
+    }
+    reply({ ok: desktops });
+}
+"#;
+
+const FOCUS_SCRIPT: &str = r#"
+const w = findWindow(ARGS.id);
+if (!w) throw new Error("No window " + ARGS.id);
+if (workspace.activeWindow !== undefined) workspace.activeWindow = w; else workspace.activeClient = w;
+reply({ ok: null });
+"#;
+
+const MOVE_RESIZE_SCRIPT: &str = r#"
+const w = findWindow(ARGS.id);
+if (!w) throw new Error("No window " + ARGS.id);
+if (w.setMaximize) w.setMaximize(false, false);
+w.frameGeometry = { x: ARGS.x, y: ARGS.y, width: ARGS.width, height: ARGS.height };
+reply({ ok: null });
+"#;
+
+const MOVE_TO_WORKSPACE_SCRIPT: &str = r#"
+const w = findWindow(ARGS.id);
+if (!w) throw new Error("No window " + ARGS.id);
+if (Array.isArray(workspace.desktops)) {
+    const desktop = workspace.desktops.find((d) => String(d.id) === ARGS.workspace || d.name === ARGS.workspace);
+    if (!desktop) throw new Error("No workspace " + ARGS.workspace);
+    w.desktops = [desktop];
+} else {
+    w.desktop = Number(ARGS.workspace);
+}
+reply({ ok: null });
+"#;
+
+const WORK_AREA_SCRIPT: &str = r#"
+const w = findWindow(ARGS.id);
+if (!w) throw new Error("No window " + ARGS.id);
+const area = workspace.clientArea(KWin.MaximizeArea, w);
+reply({ ok: { x: area.x, y: area.y, width: area.width, height: area.height } });
+"#;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct KWinWindow {
+    id: String,
+    #[serde(default)]
+    app_id: String,
+    #[serde(default)]
+    title: String,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    workspace: Option<String>,
+    #[serde(default)]
+    focused: bool,
+    pid: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct KWinWorkspace {
+    id: String,
+    name: String,
+    #[serde(default)]
+    focused: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct KWinRect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl From<KWinRect> for Rect {
+    fn from(rect: KWinRect) -> Self {
+        Rect::new(
+            rect.x.round() as i32,
+            rect.y.round() as i32,
+            rect.width.round().max(0.0) as u32,
+            rect.height.round().max(0.0) as u32,
+        )
+    }
+}
+
+pub fn is_kde_session() -> bool {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .map(|desktop| desktop.split(':').any(|part| part == "KDE"))
+        .unwrap_or(false)
+}
+
+struct ReplyReceiver {
+    sender: Mutex<mpsc::Sender<(String, String)>>,
+}
+
+#[zbus::interface(name = "dev.byteatatime.flare.KWinReply")]
+impl ReplyReceiver {
+    fn reply(&self, token: String, payload: String) {
+        let _ = self.sender.lock().unwrap().send((token, payload));
+    }
+}
+
+fn dbus_err(e: zbus::Error) -> String {
+    format!("KWin scripting failed: {}", e)
+}
+
+/// The script source with its constants filled in as JSON literals
+fn build_script(service: &str, token: &str, args: &serde_json::Value, body: &str) -> String {
+    let literal = |value: &str| serde_json::Value::from(value).to_string();
+    format!(
+        "const SERVICE = {};\nconst TOKEN = {};\nconst ARGS = {};\n{}\ntry {{\n{}\n}} catch (e) {{\n    reply({{ error: String(e) }});\n}}\n",
+        literal(service),
+        literal(token),
+        args,
+        PRELUDE,
+        body
+    )
+}
+
+/// What a script passed to `reply`: `{ok: value}` or `{error: message}`
+fn parse_reply(payload: &str) -> Result<serde_json::Value, String> {
+    let mut reply: serde_json::Value =
+        serde_json::from_str(payload).map_err(|e| format!("Unexpected KWin reply: {}", e))?;
+    if let Some(error) = reply.get("error").and_then(|error| error.as_str()) {
+        return Err(error.to_string());
+    }
+    Ok(reply
+        .get_mut("ok")
+        .map(serde_json::Value::take)
+        .unwrap_or_default())
+}
+
+fn run_script(body: &str, args: serde_json::Value) -> Result<serde_json::Value, String> {
+    let (sender, receiver) = mpsc::channel();
+    let connection = zbus::blocking::connection::Builder::session()
+        .and_then(|builder| {
+            builder.serve_at(
+                REPLY_PATH,
+                ReplyReceiver {
+                    sender: Mutex::new(sender),
+                },
+            )
+        })
+        .and_then(|builder| builder.build())
+        .map_err(dbus_err)?;
+    let service = connection
+        .unique_name()
+        .ok_or("Not connected to the session bus")?
+        .to_string();
+
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    let plugin = format!("flare-{}", token);
+    let path = std::env::temp_dir().join(format!("{}.js", plugin));
+    std::fs::write(&path, build_script(&service, &token, &args, body))
+        .map_err(|e| e.to_string())?;
+
+    let result = (|| {
+        let reply = connection
+            .call_method(
+                Some(KWIN_SERVICE),
+                "/Scripting",
+                Some("org.kde.kwin.Scripting"),
+                "loadScript",
+                &(path.to_string_lossy().as_ref(), plugin.as_str()),
+            )
+            .map_err(dbus_err)?;
+        let id: i32 = reply.body().deserialize().map_err(dbus_err)?;
+        // KWin 6 moved script objects under /Scripting
+        let run = |script_path: String| {
+            connection.call_method(
+                Some(KWIN_SERVICE),
+                script_path.as_str(),
+                Some("org.kde.kwin.Script"),
+                "run",
+                &(),
+            )
+        };
+        run(format!("/Scripting/Script{}", id))
+            .or_else(|_| run(format!("/{}", id)))
+            .map_err(dbus_err)?;
+
+        loop {
+            let (reply_token, payload) = receiver
+                .recv_timeout(SCRIPT_TIMEOUT)
+                .map_err(|_| "KWin didn't answer".to_string())?;
+            if reply_token == token {
+                return parse_reply(&payload);
+            }
+        }
+    })();
+
+    let _ = connection.call_method(
+        Some(KWIN_SERVICE),
+        "/Scripting",
+        Some("org.kde.kwin.Scripting"),
+        "unloadScript",
+        &(plugin.as_str(),),
+    );
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+fn from_value<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Result<T, String> {
+    serde_json::from_value(value).map_err(|e| format!("Unexpected KWin reply: {}", e))
+}
+
+fn windows_from(windows: Vec<KWinWindow>) -> Vec<CompositorWindow> {
+    let mut windows: Vec<CompositorWindow> = windows
+        .into_iter()
+        .map(|window| CompositorWindow {
+            rect: KWinRect {
+                x: window.x,
+                y: window.y,
+                width: window.width,
+                height: window.height,
+            }
+            .into(),
+            id: window.id,
+            app_id: window.app_id,
+            title: window.title,
+            workspace: window.workspace,
+            focused: window.focused,
+            pid: window.pid.and_then(|pid| u32::try_from(pid).ok()),
+        })
+        .collect();
+    windows.sort_by_key(|window| window.focused);
+    windows
+}
+
+pub struct KWin;
+
+impl Compositor for KWin {
+    fn name(&self) -> &'static str {
+        "kwin"
+    }
+
+    fn windows(&self) -> Result<Vec<CompositorWindow>, String> {
+        let windows = from_value(run_script(WINDOWS_SCRIPT, serde_json::json!({}))?)?;
+        Ok(windows_from(windows))
+    }
+
+    fn workspaces(&self) -> Result<Vec<Workspace>, String> {
+        let workspaces: Vec<KWinWorkspace> =
+            from_value(run_script(WORKSPACES_SCRIPT, serde_json::json!({}))?)?;
+        Ok(workspaces
+            .into_iter()
+            .map(|workspace| Workspace {
+                id: workspace.id,
+                name: workspace.name,
+                output: None,
+                focused: workspace.focused,
+            })
+            .collect())
+    }
+
+    fn focus(&self, id: &str) -> Result<(), String> {
+        run_script(FOCUS_SCRIPT, serde_json::json!({ "id": id })).map(|_| ())
+    }
+
+    fn move_resize(&self, id: &str, rect: &Rect) -> Result<(), String> {
+        let args = serde_json::json!({
+            "id": id,
+            "x": rect.x,
+            "y": rect.y,
+            "width": rect.width,
+            "height": rect.height,
+        });
+        run_script(MOVE_RESIZE_SCRIPT, args).map(|_| ())
+    }
+
+    fn move_to_workspace(&self, id: &str, workspace: &str) -> Result<(), String> {
+        let args = serde_json::json!({ "id": id, "workspace": workspace });
+        run_script(MOVE_TO_WORKSPACE_SCRIPT, args).map(|_| ())
+    }
+
+    fn work_area(&self, window: &CompositorWindow) -> Result<Rect, String> {
+        let area: KWinRect = from_value(run_script(
+            WORK_AREA_SCRIPT,
+            serde_json::json!({ "id": window.id }),
+        )?)?;
+        Ok(area.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_script_quotes_constants() {
+        let script = build_script(
+            ":1.42",
+            "abc",
+            &serde_json::json!({ "id": "x\"y" }),
+            "reply(1);",
+        );
+        assert!(script.starts_with("const SERVICE = \":1.42\";\nconst TOKEN = \"abc\";\n"));
+        assert!(script.contains(r#"const ARGS = {"id":"x\"y"};"#));
+        assert!(script.contains("try {\nreply(1);\n}"));
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(
+            parse_reply(r#"{"ok": [1, 2]}"#).unwrap(),
+            serde_json::json!([1, 2])
+        );
+        assert_eq!(
+            parse_reply(r#"{"error": "Error: No window 7"}"#).unwrap_err(),
+            "Error: No window 7"
+        );
+        let windows: Vec<KWinWindow> = from_value(serde_json::json!([
+            {"id": "{a}", "appId": "org.kde.dolphin", "title": "Home", "x": 0, "y": 0,
+             "width": 800.5, "height": 600, "workspace": "Desktop 1", "focused": true, "pid": 7},
+            {"id": "{b}", "appId": "firefox", "title": "Docs", "x": 10, "y": 10,
+             "width": 100, "height": 100, "workspace": null, "focused": false, "pid": 8}
+        ]))
+        .unwrap();
+        let windows = windows_from(windows);
+        assert_eq!(windows[0].id, "{b}");
+        assert_eq!(windows[1].rect, Rect::new(0, 0, 801, 600));
+    }
+}
@@ -0,0 +1,101 @@
+//! Window and workspace queries in each compositor's own terms.
+//!
+//! Wayland has no protocol for listing or moving other clients' windows, so
+//! every compositor that allows it does so over its own IPC: Hyprland's
+//! `hyprctl` socket, Sway's i3-compatible socket and KWin's scripting DBus
+//! API. X11 sessions keep using EWMH. `detect` picks the adapter for the
+//! running session.
+
+pub mod hyprland;
+pub mod kwin;
+pub mod sway;
+pub mod x11;
+
+use crate::window_management::geometry::Rect;
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompositorWindow {
+    /// Backend-specific: a Hyprland address, a Sway con_id, a KWin uuid or
+    /// an X11 window id
+    pub id: String,
+    /// Wayland app_id or the X11 WM_CLASS
+    pub app_id: String,
+    pub title: String,
+    pub rect: Rect,
+    pub workspace: Option<String>,
+    pub focused: bool,
+    pub pid: Option<u32>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub output: Option<String>,
+    pub focused: bool,
+}
+
+pub trait Compositor: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Every mapped window in focus or stacking order, most recent last
+    fn windows(&self) -> Result<Vec<CompositorWindow>, String>;
+    fn workspaces(&self) -> Result<Vec<Workspace>, String>;
+    fn focus(&self, id: &str) -> Result<(), String>;
+    /// Tiling compositors float the window first
+    fn move_resize(&self, id: &str, rect: &Rect) -> Result<(), String>;
+    fn move_to_workspace(&self, id: &str, workspace: &str) -> Result<(), String>;
+    /// The part of the window's output not taken by panels and bars
+    fn work_area(&self, window: &CompositorWindow) -> Result<Rect, String>;
+
+    fn active_window(&self) -> Result<CompositorWindow, String> {
+        self.windows()?
+            .into_iter()
+            .find(|window| window.focused)
+            .ok_or_else(|| "No active window".to_string())
+    }
+}
+
+/// The adapter for the running session, falling back to EWMH
+pub fn detect() -> Box<dyn Compositor> {
+    if let Some(hyprland) = hyprland::Hyprland::from_env() {
+        return Box::new(hyprland);
+    }
+    if let Some(sway) = sway::Sway::from_env() {
+        return Box::new(sway);
+    }
+    if std::env::var("WAYLAND_DISPLAY").is_ok() && kwin::is_kde_session() {
+        return Box::new(kwin::KWin);
+    }
+    Box::new(x11::Ewmh)
+}
+
+/// Open windows across all workspaces, for the window switcher. The focused
+/// window comes last.
+#[tauri::command]
+pub fn list_open_windows() -> Result<Vec<CompositorWindow>, String> {
+    detect().windows()
+}
+
+#[tauri::command]
+pub fn focus_open_window(id: String) -> Result<(), String> {
+    detect().focus(&id)
+}
+
+#[tauri::command]
+pub fn list_workspaces() -> Result<Vec<Workspace>, String> {
+    detect().workspaces()
+}
+
+#[tauri::command]
+pub fn move_window_to_workspace(id: String, workspace: String) -> Result<(), String> {
+    detect().move_to_workspace(&id, &workspace)
+}
+
+/// Which adapter window management goes through, for diagnostics
+#[tauri::command]
+pub fn compositor_name() -> &'static str {
+    detect().name()
+}
@@ -0,0 +1,290 @@
+//! Sway (and i3) over the IPC socket in `SWAYSOCK`/`I3SOCK`. Messages are
+//! `i3-ipc`, then the payload length and message type as native-endian
+//! u32s, then the payload; replies are framed the same way.
+
+use super::{Compositor, CompositorWindow, Workspace};
+use crate::window_management::geometry::Rect;
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const MAGIC: &[u8] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const GET_WORKSPACES: u32 = 1;
+const GET_TREE: u32 = 4;
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Debug)]
+struct Node {
+    id: i64,
+    #[serde(rename = "type")]
+    kind: String,
+    name: Option<String>,
+    #[serde(default)]
+    focused: bool,
+    rect: NodeRect,
+    app_id: Option<String>,
+    window_properties: Option<WindowProperties>,
+    pid: Option<u32>,
+    /// X11 window id, the only client marker i3 has
+    window: Option<u64>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    floating_nodes: Vec<Node>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+struct NodeRect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct WindowProperties {
+    class: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SwayWorkspace {
+    #[serde(default)]
+    id: i64,
+    name: String,
+    #[serde(default)]
+    output: String,
+    #[serde(default)]
+    focused: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct CommandResult {
+    success: bool,
+    error: Option<String>,
+}
+
+impl From<NodeRect> for Rect {
+    fn from(rect: NodeRect) -> Self {
+        Rect::new(rect.x, rect.y, rect.width, rect.height)
+    }
+}
+
+pub struct Sway {
+    socket: PathBuf,
+}
+
+fn encode(kind: u32, payload: &str) -> Vec<u8> {
+    let mut message = MAGIC.to_vec();
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    message
+}
+
+/// Criteria selecting window `id` in a command
+fn con(id: &str) -> Result<String, String> {
+    id.parse::<i64>()
+        .map(|id| format!("[con_id={}]", id))
+        .map_err(|_| format!("Not a Sway window: {}", id))
+}
+
+impl Sway {
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("SWAYSOCK")
+            .or_else(|| std::env::var_os("I3SOCK"))
+            .map(|socket| Self {
+                socket: PathBuf::from(socket),
+            })
+    }
+
+    fn message<T: serde::de::DeserializeOwned>(
+        &self,
+        kind: u32,
+        payload: &str,
+    ) -> Result<T, String> {
+        let mut stream = UnixStream::connect(&self.socket)
+            .map_err(|e| format!("Failed to connect to Sway: {}", e))?;
+        stream
+            .set_read_timeout(Some(SOCKET_TIMEOUT))
+            .map_err(|e| e.to_string())?;
+        stream
+            .write_all(&encode(kind, payload))
+            .map_err(|e| e.to_string())?;
+
+        let mut header = [0u8; 14];
+        stream.read_exact(&mut header).map_err(|e| e.to_string())?;
+        if &header[..6] != MAGIC {
+            return Err("Unexpected reply from Sway".to_string());
+        }
+        let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+        let mut body = vec![0u8; length];
+        stream.read_exact(&mut body).map_err(|e| e.to_string())?;
+        serde_json::from_slice(&body).map_err(|e| format!("Unexpected reply from Sway: {}", e))
+    }
+
+    fn run(&self, command: &str) -> Result<(), String> {
+        let results: Vec<CommandResult> = self.message(RUN_COMMAND, command)?;
+        match results.into_iter().find(|result| !result.success) {
+            Some(failed) => Err(failed
+                .error
+                .unwrap_or_else(|| format!("Sway refused '{}'", command))),
+            None => Ok(()),
+        }
+    }
+
+    fn tree(&self) -> Result<Node, String> {
+        self.message(GET_TREE, "")
+    }
+}
+
+/// Leaf containers that hold a client, with the workspace they're on
+fn collect_windows<'a>(
+    node: &'a Node,
+    workspace: Option<&'a Node>,
+    windows: &mut Vec<(&'a Node, Option<&'a Node>)>,
+) {
+    let workspace = if node.kind == "workspace" {
+        Some(node)
+    } else {
+        workspace
+    };
+    let is_client = node.pid.is_some() || node.window.is_some();
+    if is_client && node.nodes.is_empty() && node.floating_nodes.is_empty() {
+        windows.push((node, workspace));
+    }
+    for child in node.nodes.iter().chain(&node.floating_nodes) {
+        collect_windows(child, workspace, windows);
+    }
+}
+
+fn windows_from(tree: &Node) -> Vec<CompositorWindow> {
+    let mut nodes = Vec::new();
+    collect_windows(tree, None, &mut nodes);
+    let mut windows: Vec<CompositorWindow> = nodes
+        .into_iter()
+        // The scratchpad workspace holds hidden windows
+        .filter(|(_, workspace)| workspace.and_then(|w| w.name.as_deref()) != Some("__i3_scratch"))
+        .map(|(node, workspace)| CompositorWindow {
+            id: node.id.to_string(),
+            app_id: node
+                .app_id
+                .clone()
+                .or_else(|| node.window_properties.as_ref()?.class.clone())
+                .unwrap_or_default(),
+            title: node.name.clone().unwrap_or_default(),
+            rect: node.rect.into(),
+            workspace: workspace.and_then(|workspace| workspace.name.clone()),
+            focused: node.focused,
+            pid: node.pid,
+        })
+        .collect();
+    // The tree has no focus history, so only the focused window moves last
+    windows.sort_by_key(|window| window.focused);
+    windows
+}
+
+impl Compositor for Sway {
+    fn name(&self) -> &'static str {
+        "sway"
+    }
+
+    fn windows(&self) -> Result<Vec<CompositorWindow>, String> {
+        Ok(windows_from(&self.tree()?))
+    }
+
+    fn workspaces(&self) -> Result<Vec<Workspace>, String> {
+        let workspaces: Vec<SwayWorkspace> = self.message(GET_WORKSPACES, "")?;
+        Ok(workspaces
+            .into_iter()
+            .map(|workspace| Workspace {
+                id: workspace.id.to_string(),
+                name: workspace.name,
+                output: Some(workspace.output).filter(|output| !output.is_empty()),
+                focused: workspace.focused,
+            })
+            .collect())
+    }
+
+    fn focus(&self, id: &str) -> Result<(), String> {
+        self.run(&format!("{} focus", con(id)?))
+    }
+
+    fn move_resize(&self, id: &str, rect: &Rect) -> Result<(), String> {
+        self.run(&format!(
+            "{} floating enable, move absolute position {} {}, resize set {} {}",
+            con(id)?,
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height
+        ))
+    }
+
+    fn move_to_workspace(&self, id: &str, workspace: &str) -> Result<(), String> {
+        let workspace = workspace.replace('"', "\\\"");
+        self.run(&format!(
+            "{} move container to workspace \"{}\"",
+            con(id)?,
+            workspace
+        ))
+    }
+
+    /// A workspace's rect is its output less the bars
+    fn work_area(&self, window: &CompositorWindow) -> Result<Rect, String> {
+        let tree = self.tree()?;
+        let mut nodes = Vec::new();
+        collect_windows(&tree, None, &mut nodes);
+        let id: i64 = window.id.parse().map_err(|_| "Not a Sway window")?;
+        nodes
+            .into_iter()
+            .find(|(node, _)| node.id == id)
+            .and_then(|(_, workspace)| workspace)
+            .map(|workspace| workspace.rect.into())
+            .ok_or_else(|| format!("No window {}", window.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_message() {
+        let message = encode(GET_TREE, "");
+        assert_eq!(&message[..6], b"i3-ipc");
+        assert_eq!(message.len(), 14);
+        assert_eq!(u32::from_ne_bytes(message[10..14].try_into().unwrap()), 4);
+    }
+
+    #[test]
+    fn test_windows_from_tree() {
+        let tree: Node = serde_json::from_str(
+            r#"{"id": 1, "type": "root", "name": "root", "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+                "nodes": [{"id": 2, "type": "output", "name": "DP-1",
+                    "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+                    "nodes": [{"id": 3, "type": "workspace", "name": "1",
+                        "rect": {"x": 0, "y": 30, "width": 1920, "height": 1050},
+                        "nodes": [
+                            {"id": 4, "type": "con", "name": "Docs", "focused": true, "app_id": "firefox",
+                             "pid": 10, "rect": {"x": 0, "y": 30, "width": 960, "height": 1050}},
+                            {"id": 5, "type": "con", "name": "xterm", "app_id": null, "pid": 11,
+                             "window_properties": {"class": "XTerm"},
+                             "rect": {"x": 960, "y": 30, "width": 960, "height": 1050}}
+                        ]}]}]}"#,
+        )
+        .unwrap();
+        let windows = windows_from(&tree);
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].app_id, "XTerm");
+        assert_eq!(windows[1].id, "4");
+        assert!(windows[1].focused);
+        assert_eq!(windows[1].workspace.as_deref(), Some("1"));
+
+        let mut nodes = Vec::new();
+        collect_windows(&tree, None, &mut nodes);
+        assert_eq!(nodes[0].1.unwrap().rect.y, 30);
+    }
+}
@@ -0,0 +1,101 @@
+//! X11 window managers through EWMH, which every adapter falls back to and
+//! which XWayland windows still answer on sessions without IPC.
+
+use super::{Compositor, CompositorWindow, Workspace};
+use crate::window_management::geometry::Rect;
+use crate::window_management::x11::X11Session;
+
+fn window_id(id: &str) -> Result<u32, String> {
+    id.parse().map_err(|_| format!("Not an X11 window: {}", id))
+}
+
+pub struct Ewmh;
+
+impl Compositor for Ewmh {
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+
+    fn windows(&self) -> Result<Vec<CompositorWindow>, String> {
+        let session = X11Session::connect()?;
+        let active = session.active_window().ok();
+        let (desktops, _) = session.desktops()?;
+        session
+            .list_windows()?
+            .into_iter()
+            .map(|window| {
+                Ok(CompositorWindow {
+                    id: window.id.to_string(),
+                    pid: session.pid(window.id)?,
+                    focused: Some(window.id) == active,
+                    workspace: window.desktop.map(|desktop| {
+                        desktops
+                            .get(desktop as usize)
+                            .cloned()
+                            .unwrap_or_else(|| desktop.to_string())
+                    }),
+                    app_id: window.wm_class,
+                    title: window.title,
+                    rect: window.rect,
+                })
+            })
+            .collect()
+    }
+
+    fn workspaces(&self) -> Result<Vec<Workspace>, String> {
+        let (desktops, current) = X11Session::connect()?.desktops()?;
+        Ok(desktops
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| Workspace {
+                id: index.to_string(),
+                name,
+                output: None,
+                focused: current == Some(index as u32),
+            })
+            .collect())
+    }
+
+    fn focus(&self, id: &str) -> Result<(), String> {
+        let session = X11Session::connect()?;
+        let timestamp = session.server_time()?;
+        session.activate(window_id(id)?, timestamp)
+    }
+
+    fn move_resize(&self, id: &str, rect: &Rect) -> Result<(), String> {
+        X11Session::connect()?.move_resize(window_id(id)?, rect)
+    }
+
+    /// Takes a desktop index or name
+    fn move_to_workspace(&self, id: &str, workspace: &str) -> Result<(), String> {
+        let session = X11Session::connect()?;
+        let desktop = match workspace.parse::<u32>() {
+            Ok(index) => index,
+            Err(_) => session
+                .desktops()?
+                .0
+                .iter()
+                .position(|name| name == workspace)
+                .ok_or_else(|| format!("No workspace {}", workspace))? as u32,
+        };
+        session.move_to_desktop(window_id(id)?, desktop)
+    }
+
+    fn work_area(&self, window: &CompositorWindow) -> Result<Rect, String> {
+        X11Session::connect()?.work_area_for(window_id(&window.id)?)
+    }
+
+    fn active_window(&self) -> Result<CompositorWindow, String> {
+        let session = X11Session::connect()?;
+        let id = session.active_window()?;
+        Ok(CompositorWindow {
+            id: id.to_string(),
+            app_id: session.wm_class(id)?,
+            title: session.title(id)?,
+            rect: session.frame_rect(id)?,
+            workspace: None,
+            focused: true,
+            pid: session.pid(id)?,
+        })
+    }
+}
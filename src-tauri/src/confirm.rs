@@ -0,0 +1,232 @@
+//! Asking the user before something dangerous happens, in a small
+//! always-on-top window of its own so the question shows even while the
+//! launcher is hidden. `request` resolves to the user's choice; closing the
+//! window, or not answering within `CONFIRM_TIMEOUT`, is a no.
+//!
+//! Requests arriving while one is open queue behind it. The window asks for
+//! the request it should show with `get_pending_confirmation` when it loads,
+//! then follows `CONFIRMATION_REQUEST_EVENT` for the next ones.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+use tokio::sync::oneshot;
+
+pub const CONFIRMATION_REQUEST_EVENT: &str = "confirmation-request";
+
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(120);
+const WINDOW_LABEL: &str = "confirm";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum DangerLevel {
+    #[default]
+    Low,
+    Medium,
+    /// Can't be undone; the window doesn't default to confirming
+    High,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmationRequest {
+    pub id: String,
+    pub message: String,
+    pub details: Option<String>,
+    pub danger_level: DangerLevel,
+    pub confirm_label: String,
+}
+
+impl ConfirmationRequest {
+    pub fn new(message: impl Into<String>, danger_level: DangerLevel) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            message: message.into(),
+            details: None,
+            danger_level,
+            confirm_label: "Continue".to_string(),
+        }
+    }
+
+    pub fn details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into()).filter(|details| !details.is_empty());
+        self
+    }
+
+    pub fn confirm_label(mut self, label: impl Into<String>) -> Self {
+        self.confirm_label = label.into();
+        self
+    }
+}
+
+struct Pending {
+    request: ConfirmationRequest,
+    answer: oneshot::Sender<bool>,
+}
+
+static QUEUE: Lazy<Mutex<VecDeque<Pending>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+fn window(app: &AppHandle) -> Result<WebviewWindow, String> {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        return Ok(window);
+    }
+    tauri::WebviewWindowBuilder::new(app, WINDOW_LABEL, tauri::WebviewUrl::App("/confirm".into()))
+        .title("Confirm")
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(false)
+        .center()
+        .inner_size(420.0, 200.0)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Shows the request at the front of the queue, or hides the window when
+/// none is left
+fn show_front(app: &AppHandle) -> Result<(), String> {
+    let front = QUEUE
+        .lock()
+        .unwrap()
+        .front()
+        .map(|pending| pending.request.clone());
+    match front {
+        Some(request) => {
+            let window = window(app)?;
+            window.show().map_err(|e| e.to_string())?;
+            window.set_focus().map_err(|e| e.to_string())?;
+            window
+                .emit(CONFIRMATION_REQUEST_EVENT, request)
+                .map_err(|e| e.to_string())
+        }
+        None => {
+            if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+                let _ = window.hide();
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Takes request `id` out of the queue, moving on to the next one if it was
+/// being shown
+fn take(app: &AppHandle, id: &str) -> Option<Pending> {
+    let (pending, was_shown) = {
+        let mut queue = QUEUE.lock().unwrap();
+        let index = queue.iter().position(|pending| pending.request.id == id)?;
+        (queue.remove(index)?, index == 0)
+    };
+    if was_shown {
+        if let Err(e) = show_front(app) {
+            tracing::warn!(error = %e, "Failed to show the next confirmation");
+        }
+    }
+    Some(pending)
+}
+
+/// Asks the user and waits for the answer
+pub async fn request(app: &AppHandle, request: ConfirmationRequest) -> bool {
+    let id = request.id.clone();
+    let (answer, receiver) = oneshot::channel();
+    let first = {
+        let mut queue = QUEUE.lock().unwrap();
+        queue.push_back(Pending { request, answer });
+        queue.len() == 1
+    };
+    if first {
+        if let Err(e) = show_front(app) {
+            tracing::warn!(error = %e, "Failed to show the confirmation window");
+            take(app, &id);
+            return false;
+        }
+    }
+    let confirmed = matches!(
+        tokio::time::timeout(CONFIRM_TIMEOUT, receiver).await,
+        Ok(Ok(true))
+    );
+    // Still queued when it timed out
+    take(app, &id);
+    confirmed
+}
+
+/// For the frontend and extensions: asks `message` in the confirmation
+/// window and resolves to the user's choice
+#[tauri::command]
+pub async fn request_confirmation(
+    app: AppHandle,
+    message: String,
+    details: Option<String>,
+    danger_level: Option<DangerLevel>,
+    confirm_label: Option<String>,
+) -> bool {
+    let mut confirmation = ConfirmationRequest::new(message, danger_level.unwrap_or_default());
+    if let Some(details) = details {
+        confirmation = confirmation.details(details);
+    }
+    if let Some(label) = confirm_label {
+        confirmation = confirmation.confirm_label(label);
+    }
+    request(&app, confirmation).await
+}
+
+/// Only the confirmation window itself gets to see and answer requests;
+/// otherwise an extension's webview could confirm its own
+fn check_caller(window: &WebviewWindow) -> Result<(), String> {
+    if window.label() == WINDOW_LABEL {
+        Ok(())
+    } else {
+        Err("Only the confirmation window can answer confirmations".to_string())
+    }
+}
+
+/// The request the window should show, for when it has just loaded
+#[tauri::command]
+pub fn get_pending_confirmation(
+    window: WebviewWindow,
+) -> Result<Option<ConfirmationRequest>, String> {
+    check_caller(&window)?;
+    Ok(QUEUE
+        .lock()
+        .unwrap()
+        .front()
+        .map(|pending| pending.request.clone()))
+}
+
+/// The window's answer to request `id`
+#[tauri::command]
+pub fn confirmation_respond(
+    app: AppHandle,
+    window: WebviewWindow,
+    id: String,
+    confirmed: bool,
+) -> Result<(), String> {
+    check_caller(&window)?;
+    let pending = take(&app, &id).ok_or("No confirmation is waiting for that answer")?;
+    let _ = pending.answer.send(confirmed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let request = ConfirmationRequest::new("Shred “notes.txt”?", DangerLevel::High)
+            .details("")
+            .confirm_label("Shred");
+        assert_eq!(request.details, None);
+        assert_eq!(request.confirm_label, "Shred");
+        assert_ne!(
+            request.id,
+            ConfirmationRequest::new("Again", DangerLevel::Low).id
+        );
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["dangerLevel"], "high");
+        assert_eq!(json["confirmLabel"], "Shred");
+    }
+}
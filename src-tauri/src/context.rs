@@ -0,0 +1,170 @@
+//! The application the user is working in. Per-app snippets, the `{app}`
+//! placeholder in AI commands and the window actions all want the window
+//! that had focus before the launcher opened, so Flare's own windows are
+//! skipped: the answer is the last focused window of another process.
+//!
+//! Lookups go through the compositor adapters and are cached briefly, since
+//! several features ask at once when the launcher opens. A watcher polls
+//! for focus changes and emits `active-window-changed` with the new window.
+
+use crate::compositor::{self, CompositorWindow};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+pub const ACTIVE_WINDOW_CHANGED_EVENT: &str = "active-window-changed";
+
+const CACHE_TTL: Duration = Duration::from_millis(250);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Every KWin query loads a script, so it is asked less often
+const KWIN_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveWindowInfo {
+    /// In the terms of `compositor`, e.g. an X11 window id or a Sway con_id
+    pub window_id: String,
+    /// Wayland app_id or the X11 WM_CLASS
+    pub app_id: String,
+    pub title: String,
+    pub pid: Option<u32>,
+    /// WM_CLASS, set only on X11 sessions
+    pub window_class: Option<String>,
+    /// Which compositor adapter answered
+    pub backend: &'static str,
+}
+
+impl ActiveWindowInfo {
+    fn from_window(window: CompositorWindow, backend: &'static str) -> Self {
+        Self {
+            window_class: (backend == "x11").then(|| window.app_id.clone()),
+            window_id: window.id,
+            app_id: window.app_id,
+            title: window.title,
+            pid: window.pid,
+            backend,
+        }
+    }
+
+    fn is_own(&self) -> bool {
+        self.pid == Some(std::process::id())
+    }
+
+    /// Same window with the same title; a retitled window is a change too,
+    /// since browsers switch tabs without switching windows
+    fn same_as(&self, other: &ActiveWindowInfo) -> bool {
+        self.window_id == other.window_id && self.title == other.title
+    }
+}
+
+struct Cache {
+    checked_at: Option<Instant>,
+    window: Option<ActiveWindowInfo>,
+}
+
+static CACHE: Lazy<Mutex<Cache>> = Lazy::new(|| {
+    Mutex::new(Cache {
+        checked_at: None,
+        window: None,
+    })
+});
+
+/// Asks the compositor, keeping the previous answer while Flare itself has
+/// focus or the query fails
+fn refresh() -> Option<ActiveWindowInfo> {
+    let compositor = compositor::detect();
+    let focused = compositor
+        .active_window()
+        .map(|window| ActiveWindowInfo::from_window(window, compositor.name()));
+
+    let mut cache = CACHE.lock().unwrap();
+    cache.checked_at = Some(Instant::now());
+    match focused {
+        Ok(window) if !window.is_own() => cache.window = Some(window),
+        Ok(_) => {}
+        Err(e) => tracing::debug!(error = %e, "Couldn't get the active window"),
+    }
+    cache.window.clone()
+}
+
+/// The focused window of another app, at most `CACHE_TTL` old
+pub fn active_window_info() -> Option<ActiveWindowInfo> {
+    {
+        let cache = CACHE.lock().unwrap();
+        if cache
+            .checked_at
+            .is_some_and(|checked_at| checked_at.elapsed() < CACHE_TTL)
+        {
+            return cache.window.clone();
+        }
+    }
+    refresh()
+}
+
+/// Starts the focus watcher
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    let interval = match compositor::detect().name() {
+        "kwin" => KWIN_POLL_INTERVAL,
+        _ => POLL_INTERVAL,
+    };
+    thread::spawn(move || {
+        let mut last: Option<ActiveWindowInfo> = None;
+        loop {
+            if let Some(window) = refresh() {
+                if !last.as_ref().is_some_and(|last| last.same_as(&window)) {
+                    if let Err(e) = app.emit(ACTIVE_WINDOW_CHANGED_EVENT, &window) {
+                        tracing::error!(error = %e, "Failed to emit active window event");
+                    }
+                    last = Some(window);
+                }
+            }
+            thread::sleep(interval);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_active_window_info() -> Option<ActiveWindowInfo> {
+    active_window_info()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window_management::geometry::Rect;
+
+    fn window(pid: u32, title: &str) -> CompositorWindow {
+        CompositorWindow {
+            id: "42".to_string(),
+            app_id: "firefox".to_string(),
+            title: title.to_string(),
+            rect: Rect::new(0, 0, 800, 600),
+            workspace: None,
+            focused: true,
+            pid: Some(pid),
+        }
+    }
+
+    #[test]
+    fn test_from_window() {
+        let x11 = ActiveWindowInfo::from_window(window(1, "Docs"), "x11");
+        assert_eq!(x11.window_class.as_deref(), Some("firefox"));
+        assert!(!x11.is_own());
+
+        let sway = ActiveWindowInfo::from_window(window(std::process::id(), "Docs"), "sway");
+        assert_eq!(sway.window_class, None);
+        assert!(sway.is_own());
+    }
+
+    #[test]
+    fn test_retitled_window_is_a_change() {
+        let docs = ActiveWindowInfo::from_window(window(1, "Docs"), "x11");
+        let mail = ActiveWindowInfo::from_window(window(1, "Mail"), "x11");
+        assert!(docs.same_as(&docs.clone()));
+        assert!(!docs.same_as(&mail));
+    }
+}
@@ -0,0 +1,374 @@
+//! Currency exchange rates for the calculator.
+//!
+//! Rates are fetched from exchangerate (open.er-api.com) with the ECB daily
+//! reference rates as a fallback, cached in `currency_rates.json` and refreshed
+//! in the background. They are pushed into the Soulver wrapper so currency
+//! math keeps working offline, and back the simple `<amount> <from> to <to>`
+//! fallback when Soulver can't evaluate an expression.
+
+use crate::power_awareness;
+use crate::soulver;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const EXCHANGERATE_URL: &str = "https://open.er-api.com/v6/latest/USD";
+const ECB_URL: &str = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml";
+
+/// How often the background task checks whether the cache is stale
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Cached rates older than this are refetched
+const MAX_AGE_SECS: i64 = 6 * 60 * 60;
+
+const BASE_CURRENCY: &str = "USD";
+
+static RATES: Lazy<Mutex<Option<CurrencyRates>>> = Lazy::new(|| Mutex::new(None));
+
+static CONVERSION_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^\s*([$€£¥]?)\s*([\d.,]+)\s*([a-z]{3})?\s+(?:to|in|as)\s+([a-z]{3}|[$€£¥])\s*$",
+    )
+    .unwrap()
+});
+
+static ECB_CUBE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"currency=['"]([A-Z]{3})['"]\s+rate=['"]([\d.]+)['"]"#).unwrap());
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyRates {
+    /// Units of each currency per one unit of `base`
+    pub rates: HashMap<String, f64>,
+    pub base: String,
+    /// `exchangerate` or `ecb`
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl CurrencyRates {
+    fn rate_for(&self, code: &str) -> Option<f64> {
+        let code = code.to_uppercase();
+        if code == self.base {
+            return Some(1.0);
+        }
+        self.rates.get(&code).copied().filter(|rate| *rate > 0.0)
+    }
+
+    pub fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        Some(self.rate_for(to)? / self.rate_for(from)?)
+    }
+
+    fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        (now - self.fetched_at).num_seconds() > MAX_AGE_SECS
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyConversion {
+    pub amount: f64,
+    pub from: String,
+    pub to: String,
+    pub rate: f64,
+    pub result: f64,
+    pub fetched_at: DateTime<Utc>,
+}
+
+fn symbol_to_code(symbol: &str) -> Option<&'static str> {
+    match symbol {
+        "$" => Some("USD"),
+        "€" => Some("EUR"),
+        "£" => Some("GBP"),
+        "¥" => Some("JPY"),
+        _ => None,
+    }
+}
+
+fn parse_amount(raw: &str) -> Option<f64> {
+    raw.replace(',', "").parse().ok()
+}
+
+/// Parses `100 usd to eur`, `$20 in gbp` and similar
+fn parse_conversion(expression: &str) -> Option<(f64, String, String)> {
+    let captures = CONVERSION_RE.captures(expression)?;
+    let amount = parse_amount(&captures[2])?;
+    let from = match (captures.get(3), symbol_to_code(&captures[1])) {
+        (Some(code), _) => code.as_str().to_uppercase(),
+        (None, Some(code)) => code.to_string(),
+        (None, None) => return None,
+    };
+    let to = symbol_to_code(&captures[4])
+        .map(str::to_string)
+        .unwrap_or_else(|| captures[4].to_uppercase());
+    Some((amount, from, to))
+}
+
+fn parse_exchangerate(body: &Value) -> Result<HashMap<String, f64>, String> {
+    if body.get("result").and_then(Value::as_str) != Some("success") {
+        return Err("exchangerate returned an error".to_string());
+    }
+    if body.get("base_code").and_then(Value::as_str) != Some(BASE_CURRENCY) {
+        return Err("exchangerate returned an unexpected base currency".to_string());
+    }
+    let rates = body
+        .get("rates")
+        .and_then(Value::as_object)
+        .ok_or("exchangerate response has no rates")?;
+    Ok(rates
+        .iter()
+        .filter_map(|(code, rate)| Some((code.clone(), rate.as_f64()?)))
+        .collect())
+}
+
+/// ECB rates are quoted against EUR; they're rebased onto USD to match the
+/// other source and the Soulver wrapper
+fn parse_ecb(xml: &str) -> Result<HashMap<String, f64>, String> {
+    let mut eur_rates: HashMap<String, f64> = ECB_CUBE_RE
+        .captures_iter(xml)
+        .filter_map(|c| Some((c[1].to_string(), c[2].parse().ok()?)))
+        .collect();
+    eur_rates.insert("EUR".to_string(), 1.0);
+
+    let usd = *eur_rates
+        .get(BASE_CURRENCY)
+        .ok_or("ECB response has no USD rate")?;
+    Ok(eur_rates
+        .into_iter()
+        .map(|(code, rate)| (code, rate / usd))
+        .collect())
+}
+
+async fn fetch_rates() -> Result<CurrencyRates, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let exchangerate = async {
+        let response = client
+            .get(EXCHANGERATE_URL)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let body: Value = response.json().await.map_err(|e| e.to_string())?;
+        parse_exchangerate(&body)
+    };
+    let (rates, source) = match exchangerate.await {
+        Ok(rates) => (rates, "exchangerate"),
+        Err(e) => {
+            tracing::warn!(error = %e, "exchangerate fetch failed, falling back to ECB");
+            let xml = client
+                .get(ECB_URL)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?
+                .text()
+                .await
+                .map_err(|e| e.to_string())?;
+            (parse_ecb(&xml)?, "ecb")
+        }
+    };
+
+    Ok(CurrencyRates {
+        rates,
+        base: BASE_CURRENCY.to_string(),
+        source: source.to_string(),
+        fetched_at: Utc::now(),
+    })
+}
+
+fn get_cache_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| "Failed to get app local data dir".to_string())?;
+
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(data_dir.join("currency_rates.json"))
+}
+
+fn read_cache(path: &Path) -> Result<Option<CurrencyRates>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+fn install(rates: CurrencyRates) {
+    soulver::set_currency_rates(&rates.rates);
+    *RATES.lock().unwrap() = Some(rates);
+}
+
+pub fn current_rates() -> Option<CurrencyRates> {
+    RATES.lock().unwrap().clone()
+}
+
+async fn refresh(app: &AppHandle) -> Result<CurrencyRates, String> {
+    let rates = fetch_rates().await?;
+    let content = serde_json::to_string_pretty(&rates).map_err(|e| e.to_string())?;
+    fs::write(get_cache_path(app)?, content).map_err(|e| e.to_string())?;
+    install(rates.clone());
+    Ok(rates)
+}
+
+/// Loads cached rates and keeps them fresh in the background
+pub fn init(app: &AppHandle) {
+    match get_cache_path(app).and_then(|path| read_cache(&path)) {
+        Ok(Some(rates)) => install(rates),
+        Ok(None) => {}
+        Err(e) => tracing::warn!(error = %e, "Failed to read cached currency rates"),
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let stale = match current_rates() {
+                Some(rates) => rates.is_stale(Utc::now()),
+                None => true,
+            };
+            if stale {
+                if let Err(e) = refresh(&app).await {
+                    tracing::warn!(error = %e, "Failed to refresh currency rates");
+                }
+            }
+            power_awareness::sleep(REFRESH_INTERVAL).await;
+        }
+    });
+}
+
+fn convert(amount: f64, from: &str, to: &str) -> Result<CurrencyConversion, String> {
+    let rates = current_rates().ok_or("Currency rates are not available yet")?;
+    let rate = rates
+        .rate(from, to)
+        .ok_or_else(|| format!("Unknown currency: {} or {}", from, to))?;
+    Ok(CurrencyConversion {
+        amount,
+        from: from.to_uppercase(),
+        to: to.to_uppercase(),
+        rate,
+        result: amount * rate,
+        fetched_at: rates.fetched_at,
+    })
+}
+
+/// Evaluates plain currency conversions when Soulver returns nothing, in the
+/// same JSON shape as the Soulver wrapper
+pub fn calculator_fallback(expression: &str) -> Option<String> {
+    let (amount, from, to) = parse_conversion(expression)?;
+    let conversion = convert(amount, &from, &to).ok()?;
+    let result = serde_json::json!({
+        "value": format!("{:.2} {}", conversion.result, conversion.to),
+        "type": "Currency",
+        "error": null,
+    });
+    Some(result.to_string())
+}
+
+#[tauri::command]
+pub fn get_currency_rates() -> Option<CurrencyRates> {
+    current_rates()
+}
+
+#[tauri::command]
+pub fn get_rate(from: String, to: String) -> Result<f64, String> {
+    convert(1.0, &from, &to).map(|conversion| conversion.rate)
+}
+
+#[tauri::command]
+pub fn convert_currency(
+    amount: f64,
+    from: String,
+    to: String,
+) -> Result<CurrencyConversion, String> {
+    convert(amount, &from, &to)
+}
+
+#[tauri::command]
+pub async fn refresh_currency_rates(app: AppHandle) -> Result<CurrencyRates, String> {
+    refresh(&app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_rates() -> CurrencyRates {
+        CurrencyRates {
+            rates: HashMap::from([
+                ("EUR".to_string(), 0.5),
+                ("GBP".to_string(), 0.25),
+                ("XXX".to_string(), 0.0),
+            ]),
+            base: "USD".to_string(),
+            source: "exchangerate".to_string(),
+            fetched_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_cross_rates() {
+        let rates = sample_rates();
+        assert_eq!(rates.rate("USD", "EUR"), Some(0.5));
+        assert_eq!(rates.rate("eur", "usd"), Some(2.0));
+        assert_eq!(rates.rate("EUR", "GBP"), Some(0.5));
+        assert_eq!(rates.rate("USD", "XXX"), None);
+        assert_eq!(rates.rate("USD", "ABC"), None);
+    }
+
+    #[test]
+    fn test_parse_conversion() {
+        assert_eq!(
+            parse_conversion("100 usd to eur"),
+            Some((100.0, "USD".to_string(), "EUR".to_string()))
+        );
+        assert_eq!(
+            parse_conversion("$1,250.50 in £"),
+            Some((1250.5, "USD".to_string(), "GBP".to_string()))
+        );
+        assert_eq!(parse_conversion("100 to eur"), None);
+        assert_eq!(parse_conversion("2 + 2"), None);
+    }
+
+    #[test]
+    fn test_parse_ecb() {
+        let xml = "<Cube time='2024-05-01'>\
+                   <Cube currency='USD' rate='2.0'/>\
+                   <Cube currency='GBP' rate='0.5'/>\
+                   </Cube>";
+        let rates = parse_ecb(xml).unwrap();
+        assert_eq!(rates["USD"], 1.0);
+        assert_eq!(rates["EUR"], 0.5);
+        assert_eq!(rates["GBP"], 0.25);
+        assert!(parse_ecb("<Cube/>").is_err());
+    }
+
+    #[test]
+    fn test_parse_exchangerate() {
+        let body = serde_json::json!({
+            "result": "success",
+            "base_code": "USD",
+            "rates": { "USD": 1, "EUR": 0.92 }
+        });
+        let rates = parse_exchangerate(&body).unwrap();
+        assert_eq!(rates["EUR"], 0.92);
+
+        let error = serde_json::json!({ "result": "error" });
+        assert!(parse_exchangerate(&error).is_err());
+    }
+}
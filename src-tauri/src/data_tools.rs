@@ -0,0 +1,519 @@
+//! JSON, YAML and TOML tools: validate, pretty-print, minify, convert between
+//! the formats and run jq filters (through jaq) or simple JSONPath queries.
+//!
+//! Every command works on the given text or, when none is given, on the
+//! clipboard. Errors carry the line and column they were found at.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::ops::Range;
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DataFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl DataFormat {
+    /// Cheap guess used when no format is given; anything that isn't obviously
+    /// JSON or TOML is treated as YAML, which accepts the most
+    pub fn detect(text: &str) -> Self {
+        // `[section]` starts both a TOML table and a JSON array
+        if looks_like_toml(text) {
+            return DataFormat::Toml;
+        }
+        if text.trim_start().starts_with(['{', '[']) {
+            return DataFormat::Json;
+        }
+        DataFormat::Yaml
+    }
+}
+
+/// A `[table]` header or a `key = value` line
+fn looks_like_toml(text: &str) -> bool {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .is_some_and(|line| {
+            let is_header = line
+                .strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+                .is_some_and(|name| {
+                    let name = name.trim_matches(['[', ']']).trim();
+                    name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                        && !name.contains([',', ':'])
+                });
+            is_header
+                || line.split_once('=').is_some_and(|(key, _)| {
+                    let key = key.trim();
+                    !key.is_empty()
+                        && key.chars().all(|c| {
+                            c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '"' | ' ')
+                        })
+                })
+        })
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DataError {
+    pub message: String,
+    /// 1-based; in the query for query errors, in the input otherwise
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// Whether the error is in the query rather than the input
+    pub in_query: bool,
+}
+
+impl DataError {
+    fn new(message: impl Into<String>) -> Self {
+        DataError {
+            message: message.into(),
+            line: None,
+            column: None,
+            in_query: false,
+        }
+    }
+
+    fn at(message: impl Into<String>, line: usize, column: usize) -> Self {
+        DataError {
+            line: Some(line),
+            column: Some(column),
+            ..DataError::new(message)
+        }
+    }
+
+    fn at_offset(message: impl Into<String>, text: &str, offset: usize) -> Self {
+        let (line, column) = line_column(text, offset);
+        DataError::at(message, line, column)
+    }
+
+    fn in_query(self) -> Self {
+        DataError {
+            in_query: true,
+            ..self
+        }
+    }
+}
+
+impl From<String> for DataError {
+    fn from(message: String) -> Self {
+        DataError::new(message)
+    }
+}
+
+/// 1-based (line, column) of a byte offset
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(text.len());
+    let before = text.get(..offset).unwrap_or(text);
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+    (line, column)
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DataOutput {
+    pub format: DataFormat,
+    pub output: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Validation {
+    pub valid: bool,
+    pub format: DataFormat,
+    pub error: Option<DataError>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryOutput {
+    pub format: DataFormat,
+    pub results: Vec<Value>,
+    /// Results as jq prints them: one JSON value per line
+    pub output: String,
+}
+
+pub fn parse(text: &str, format: DataFormat) -> Result<Value, DataError> {
+    match format {
+        DataFormat::Json => serde_json::from_str(text)
+            .map_err(|e| DataError::at(e.to_string(), e.line(), e.column())),
+        DataFormat::Yaml => serde_yaml::from_str(text).map_err(|e| match e.location() {
+            Some(location) => DataError::at(e.to_string(), location.line(), location.column()),
+            None => DataError::new(e.to_string()),
+        }),
+        DataFormat::Toml => toml::from_str(text).map_err(|e| {
+            let message = e.message().to_string();
+            match e.span() {
+                Some(span) => DataError::at_offset(message, text, span.start),
+                None => DataError::new(message),
+            }
+        }),
+    }
+}
+
+pub fn serialize(value: &Value, format: DataFormat, pretty: bool) -> Result<String, DataError> {
+    let json = |value: &Value| {
+        if pretty {
+            serde_json::to_string_pretty(value)
+        } else {
+            serde_json::to_string(value)
+        }
+        .map_err(|e| DataError::new(e.to_string()))
+    };
+    match format {
+        DataFormat::Json => json(value),
+        DataFormat::Yaml if pretty => {
+            serde_yaml::to_string(value).map_err(|e| DataError::new(e.to_string()))
+        }
+        // JSON is valid YAML and the most compact way to write it
+        DataFormat::Yaml => json(value),
+        DataFormat::Toml => {
+            if !value.is_object() {
+                return Err(DataError::new(
+                    "TOML documents must be a table at the top level",
+                ));
+            }
+            let result = if pretty {
+                toml::to_string_pretty(value)
+            } else {
+                toml::to_string(value)
+            };
+            result.map_err(|e| DataError::new(format!("Can't write as TOML: {}", e)))
+        }
+    }
+}
+
+/// Rewrites a JSONPath expression (`$.store.book[0].title`, `$..author`,
+/// `$.items[*]`) as the equivalent jq filter
+pub fn jsonpath_to_jq(path: &str) -> Result<String, DataError> {
+    let rest = path
+        .trim()
+        .strip_prefix('$')
+        .ok_or_else(|| DataError::new("JSONPath must start with $"))?;
+    let mut filter = String::from(".");
+    let mut chars = rest.char_indices().peekable();
+
+    let key = |name: &str| format!("[{}]", Value::String(name.to_string()));
+    let read_name = |chars: &mut std::iter::Peekable<std::str::CharIndices>| {
+        let mut name = String::new();
+        while let Some(&(_, c)) = chars.peek() {
+            if c == '.' || c == '[' {
+                break;
+            }
+            name.push(c);
+            chars.next();
+        }
+        name
+    };
+
+    while let Some((offset, c)) = chars.next() {
+        let error = |message: &str| Err(DataError::at_offset(message, path, offset + 1));
+        match c {
+            '.' if chars.peek().is_some_and(|(_, c)| *c == '.') => {
+                chars.next();
+                let name = read_name(&mut chars);
+                if name.is_empty() || name == "*" {
+                    // Leaves a `.` for following segments to attach to
+                    filter.push_str(" | .. | .");
+                } else {
+                    filter.push_str(&format!(
+                        " | .. | objects | select(has({})) | .{}",
+                        Value::String(name.clone()),
+                        key(&name)
+                    ));
+                }
+            }
+            '.' => match read_name(&mut chars).as_str() {
+                "" if chars.peek().is_some_and(|(_, c)| *c == '[') => {}
+                "" => return error("Expected a name after ."),
+                "*" => filter.push_str("[]"),
+                name => filter.push_str(&key(name)),
+            },
+            '[' => {
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, ']')) => break,
+                        Some((_, c)) => inner.push(c),
+                        None => return error("Unclosed ["),
+                    }
+                }
+                let inner = inner.trim();
+                if inner == "*" {
+                    filter.push_str("[]");
+                } else if let Some(name) = inner
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')))
+                {
+                    filter.push_str(&key(name));
+                } else if inner.parse::<i64>().is_ok() {
+                    filter.push_str(&format!("[{}]", inner));
+                } else if let Some((start, end)) = inner.split_once(':') {
+                    filter.push_str(&format!("[{}:{}]", start.trim(), end.trim()));
+                } else {
+                    return error("Unsupported JSONPath selector");
+                }
+            }
+            _ => return error("Expected . or ["),
+        }
+    }
+    Ok(filter)
+}
+
+/// Runs a jq filter over `input` and collects every output
+pub fn run_jq(filter: &str, input: Value) -> Result<Vec<Value>, DataError> {
+    use jaq_interpret::{Ctx, FilterT, ParseCtx, RcIter, Val};
+
+    let span_error = |message: String, span: Range<usize>| {
+        DataError::at_offset(message, filter, span.start).in_query()
+    };
+
+    let (parsed, errors) = jaq_parse::parse(filter, jaq_parse::main());
+    if let Some(error) = errors.into_iter().next() {
+        return Err(span_error(
+            format!("Invalid query: {}", error),
+            error.span(),
+        ));
+    }
+    let parsed = parsed.ok_or_else(|| DataError::new("Empty query").in_query())?;
+
+    let mut defs = ParseCtx::new(Vec::new());
+    defs.insert_natives(jaq_core::core());
+    defs.insert_defs(jaq_std::std());
+    let compiled = defs.compile(parsed);
+    if let Some((_, span)) = defs.errs.into_iter().next() {
+        let name = filter.get(span.clone()).unwrap_or_default();
+        return Err(span_error(
+            format!("Unknown filter or variable `{}`", name),
+            span,
+        ));
+    }
+
+    let inputs = RcIter::new(core::iter::empty());
+    compiled
+        .run((Ctx::new([], &inputs), Val::from(input)))
+        .map(|result| {
+            result
+                .map(Value::from)
+                .map_err(|e| DataError::new(e.to_string()).in_query())
+        })
+        .collect()
+}
+
+/// JSONPath when the query starts with `$`, jq otherwise
+pub fn query(value: Value, query: &str) -> Result<Vec<Value>, DataError> {
+    let query = query.trim();
+    if query.starts_with('$') {
+        let filter = jsonpath_to_jq(query).map_err(DataError::in_query)?;
+        run_jq(&filter, value)
+    } else {
+        run_jq(if query.is_empty() { "." } else { query }, value)
+    }
+}
+
+fn read_input(app: &AppHandle, text: Option<String>) -> Result<String, DataError> {
+    let text = match text {
+        Some(text) => text,
+        None => app.clipboard().read_text().unwrap_or_default(),
+    };
+    if text.trim().is_empty() {
+        return Err(DataError::new("Nothing to read: the clipboard is empty"));
+    }
+    Ok(text)
+}
+
+#[tauri::command]
+pub fn data_validate(
+    app: AppHandle,
+    text: Option<String>,
+    format: Option<DataFormat>,
+) -> Result<Validation, DataError> {
+    let text = read_input(&app, text)?;
+    let format = format.unwrap_or_else(|| DataFormat::detect(&text));
+    let error = parse(&text, format).err();
+    Ok(Validation {
+        valid: error.is_none(),
+        format,
+        error,
+    })
+}
+
+#[tauri::command]
+pub fn data_pretty(
+    app: AppHandle,
+    text: Option<String>,
+    format: Option<DataFormat>,
+) -> Result<DataOutput, DataError> {
+    let text = read_input(&app, text)?;
+    let format = format.unwrap_or_else(|| DataFormat::detect(&text));
+    let output = serialize(&parse(&text, format)?, format, true)?;
+    Ok(DataOutput { format, output })
+}
+
+#[tauri::command]
+pub fn data_minify(
+    app: AppHandle,
+    text: Option<String>,
+    format: Option<DataFormat>,
+) -> Result<DataOutput, DataError> {
+    let text = read_input(&app, text)?;
+    let format = format.unwrap_or_else(|| DataFormat::detect(&text));
+    let output = serialize(&parse(&text, format)?, format, false)?;
+    Ok(DataOutput { format, output })
+}
+
+#[tauri::command]
+pub fn data_convert(
+    app: AppHandle,
+    text: Option<String>,
+    from: Option<DataFormat>,
+    to: DataFormat,
+    pretty: Option<bool>,
+) -> Result<DataOutput, DataError> {
+    let text = read_input(&app, text)?;
+    let from = from.unwrap_or_else(|| DataFormat::detect(&text));
+    let output = serialize(&parse(&text, from)?, to, pretty.unwrap_or(true))?;
+    Ok(DataOutput { format: to, output })
+}
+
+/// Runs a jq filter, or a JSONPath expression when it starts with `$`
+#[tauri::command]
+pub fn data_query(
+    app: AppHandle,
+    text: Option<String>,
+    format: Option<DataFormat>,
+    query: String,
+) -> Result<QueryOutput, DataError> {
+    let text = read_input(&app, text)?;
+    let format = format.unwrap_or_else(|| DataFormat::detect(&text));
+    let results = self::query(parse(&text, format)?, &query)?;
+    let output = results
+        .iter()
+        .map(|value| serialize(value, DataFormat::Json, true))
+        .collect::<Result<Vec<_>, _>>()?
+        .join("\n");
+    Ok(QueryOutput {
+        format,
+        results,
+        output,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(DataFormat::detect(r#"{"a": 1}"#), DataFormat::Json);
+        assert_eq!(DataFormat::detect("[1, 2]"), DataFormat::Json);
+        assert_eq!(
+            DataFormat::detect("[package]\nname = \"flare\""),
+            DataFormat::Toml
+        );
+        assert_eq!(
+            DataFormat::detect("# config\nport = 8080"),
+            DataFormat::Toml
+        );
+        assert_eq!(
+            DataFormat::detect("name: flare\nport: 8080"),
+            DataFormat::Yaml
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_have_positions() {
+        let error = parse("{\n  \"a\": 1,\n  \"b\": }", DataFormat::Json).unwrap_err();
+        assert_eq!(error.line, Some(3));
+        assert!(!error.in_query);
+
+        let error = parse("a = 1\nb = \n", DataFormat::Toml).unwrap_err();
+        assert_eq!(error.line, Some(2));
+
+        let error = parse("a: 1\n  b: 2\n", DataFormat::Yaml).unwrap_err();
+        assert_eq!(error.line, Some(2));
+    }
+
+    #[test]
+    fn test_convert() {
+        let value = parse(
+            "name = \"flare\"\n[window]\nwidth = 800\n",
+            DataFormat::Toml,
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            json!({ "name": "flare", "window": { "width": 800 } })
+        );
+        assert_eq!(
+            serialize(&value, DataFormat::Json, false).unwrap(),
+            r#"{"name":"flare","window":{"width":800}}"#
+        );
+        assert_eq!(
+            serialize(&value, DataFormat::Yaml, true).unwrap(),
+            "name: flare\nwindow:\n  width: 800\n"
+        );
+        assert!(serialize(&json!([1, 2]), DataFormat::Toml, true).is_err());
+    }
+
+    #[test]
+    fn test_line_column() {
+        assert_eq!(line_column("ab\ncd", 0), (1, 1));
+        assert_eq!(line_column("ab\ncd", 4), (2, 2));
+        assert_eq!(line_column("ab", 99), (1, 3));
+    }
+
+    #[test]
+    fn test_jsonpath_to_jq() {
+        assert_eq!(
+            jsonpath_to_jq("$.store.book[0].title").unwrap(),
+            r#".["store"]["book"][0]["title"]"#
+        );
+        assert_eq!(
+            jsonpath_to_jq("$.items[*]['id']").unwrap(),
+            r#".["items"][]["id"]"#
+        );
+        assert_eq!(
+            jsonpath_to_jq("$..author").unwrap(),
+            r#". | .. | objects | select(has("author")) | .["author"]"#
+        );
+        assert_eq!(jsonpath_to_jq("$").unwrap(), ".");
+        let error = jsonpath_to_jq("$.a[").unwrap_err();
+        assert_eq!(error.column, Some(4));
+        assert!(jsonpath_to_jq("store").is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        let value = json!({ "items": [{ "id": 1, "tags": ["a"] }, { "id": 2 }] });
+        assert_eq!(
+            query(value.clone(), ".items[] | .id").unwrap(),
+            vec![json!(1), json!(2)]
+        );
+        assert_eq!(
+            query(value.clone(), "$.items[*].id").unwrap(),
+            vec![json!(1), json!(2)]
+        );
+        assert_eq!(
+            query(value.clone(), "[.items[].id] | add").unwrap(),
+            vec![json!(3)]
+        );
+
+        let error = query(value.clone(), ".items[").unwrap_err();
+        assert!(error.in_query);
+        let error = query(value, "nosuchfilter").unwrap_err();
+        assert!(error.message.contains("nosuchfilter"));
+    }
+}
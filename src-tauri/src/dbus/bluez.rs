@@ -0,0 +1,57 @@
+//! BlueZ adapters. Powering an adapter on fails while rfkill blocks it, so
+//! callers still need rfkill for that case.
+
+use super::system_bus;
+use std::collections::HashMap;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+const SERVICE: &str = "org.bluez";
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+
+type ManagedObjects = HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
+
+async fn adapters(connection: &zbus::Connection) -> zbus::Result<Vec<OwnedObjectPath>> {
+    let proxy = zbus::Proxy::new(
+        connection,
+        SERVICE,
+        "/",
+        "org.freedesktop.DBus.ObjectManager",
+    )
+    .await?;
+    let response = proxy.call_method("GetManagedObjects", &()).await?;
+    let body = response.body();
+    let objects: ManagedObjects = body.deserialize()?;
+    let mut adapters: Vec<OwnedObjectPath> = objects
+        .into_iter()
+        .filter(|(_, interfaces)| interfaces.contains_key(ADAPTER_INTERFACE))
+        .map(|(path, _)| path)
+        .collect();
+    adapters.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    Ok(adapters)
+}
+
+/// Whether any adapter is powered; None when there is no adapter
+pub async fn powered() -> zbus::Result<Option<bool>> {
+    let connection = system_bus().await?;
+    let mut any = None;
+    for path in adapters(&connection).await? {
+        let adapter = zbus::Proxy::new(&connection, SERVICE, path, ADAPTER_INTERFACE).await?;
+        let powered: bool = adapter.get_property("Powered").await?;
+        any = Some(any.unwrap_or(false) || powered);
+    }
+    Ok(any)
+}
+
+/// Powers every adapter on or off, returning how many there were
+pub async fn set_powered(enabled: bool) -> zbus::Result<usize> {
+    let connection = system_bus().await?;
+    let adapters = adapters(&connection).await?;
+    for path in &adapters {
+        zbus::Proxy::new(&connection, SERVICE, path, ADAPTER_INTERFACE)
+            .await?
+            .set_property("Powered", enabled)
+            .await
+            .map_err(zbus::Error::from)?;
+    }
+    Ok(adapters.len())
+}
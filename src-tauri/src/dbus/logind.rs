@@ -0,0 +1,42 @@
+//! The logind session Flare runs in
+
+use super::system_bus;
+
+const SERVICE: &str = "org.freedesktop.login1";
+/// logind resolves `auto` to the caller's session
+const SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+const MANAGER_PATH: &str = "/org/freedesktop/login1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.login1.Manager";
+
+/// Sets a backlight's raw brightness. logind lets the active session do
+/// this without write access to sysfs.
+pub async fn set_brightness(subsystem: &str, device: &str, brightness: u32) -> zbus::Result<()> {
+    let connection = system_bus().await?;
+    zbus::Proxy::new(&connection, SERVICE, SESSION_PATH, SESSION_INTERFACE)
+        .await?
+        .call_method("SetBrightness", &(subsystem, device, brightness))
+        .await?;
+    Ok(())
+}
+
+/// Calls `PowerOff`, `Reboot` or `Suspend`. They're interactive, so polkit
+/// can ask for a password when the system policy wants one.
+pub async fn power(method: &str) -> zbus::Result<()> {
+    let connection = system_bus().await?;
+    zbus::Proxy::new(&connection, SERVICE, MANAGER_PATH, MANAGER_INTERFACE)
+        .await?
+        .call_method(method, &(true,))
+        .await?;
+    Ok(())
+}
+
+/// Ends this session, logging the user out
+pub async fn terminate_session() -> zbus::Result<()> {
+    let connection = system_bus().await?;
+    zbus::Proxy::new(&connection, SERVICE, SESSION_PATH, SESSION_INTERFACE)
+        .await?
+        .call_method("Terminate", &())
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,30 @@
+//! Clients for the desktop's system services, asked over DBus instead of
+//! through their command-line tools: NetworkManager, BlueZ, UPower, logind
+//! and the settings portal. Each is a few `zbus::Proxy` calls; callers fall
+//! back to the CLI tools (through `exec`) when a service isn't running.
+
+pub mod bluez;
+pub mod logind;
+pub mod network_manager;
+pub mod settings_portal;
+pub mod upower;
+
+use tokio::sync::OnceCell;
+
+static SYSTEM_BUS: OnceCell<zbus::Connection> = OnceCell::const_new();
+static SESSION_BUS: OnceCell<zbus::Connection> = OnceCell::const_new();
+
+/// One connection to the system bus, opened on first use
+pub async fn system_bus() -> zbus::Result<zbus::Connection> {
+    SYSTEM_BUS
+        .get_or_try_init(zbus::Connection::system)
+        .await
+        .cloned()
+}
+
+pub async fn session_bus() -> zbus::Result<zbus::Connection> {
+    SESSION_BUS
+        .get_or_try_init(zbus::Connection::session)
+        .await
+        .cloned()
+}
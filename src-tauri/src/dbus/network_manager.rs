@@ -0,0 +1,23 @@
+//! NetworkManager's radio switches
+
+use super::system_bus;
+
+const SERVICE: &str = "org.freedesktop.NetworkManager";
+const PATH: &str = "/org/freedesktop/NetworkManager";
+
+async fn proxy() -> zbus::Result<zbus::Proxy<'static>> {
+    zbus::Proxy::new(&system_bus().await?, SERVICE, PATH, SERVICE).await
+}
+
+pub async fn wireless_enabled() -> zbus::Result<bool> {
+    proxy().await?.get_property("WirelessEnabled").await
+}
+
+/// Needs the polkit permission `nmcli radio` needs too
+pub async fn set_wireless_enabled(enabled: bool) -> zbus::Result<()> {
+    proxy()
+        .await?
+        .set_property("WirelessEnabled", enabled)
+        .await
+        .map_err(zbus::Error::from)
+}
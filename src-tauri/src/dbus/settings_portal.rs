@@ -0,0 +1,40 @@
+//! The desktop's appearance settings, read through the settings portal so
+//! every desktop with a portal answers the same way
+
+use super::session_bus;
+use zbus::zvariant::Value;
+
+const SERVICE: &str = "org.freedesktop.portal.Desktop";
+const PATH: &str = "/org/freedesktop/portal/desktop";
+const INTERFACE: &str = "org.freedesktop.portal.Settings";
+
+const APPEARANCE: &str = "org.freedesktop.appearance";
+/// `color-scheme` values besides 0, no preference
+const PREFER_DARK: u32 = 1;
+const PREFER_LIGHT: u32 = 2;
+
+async fn read_u32(namespace: &str, key: &str) -> zbus::Result<u32> {
+    let connection = session_bus().await?;
+    let proxy = zbus::Proxy::new(&connection, SERVICE, PATH, INTERFACE).await?;
+    let response = match proxy.call_method("ReadOne", &(namespace, key)).await {
+        Ok(response) => response,
+        // ReadOne is new in version 2; Read has the value in a second variant
+        Err(_) => proxy.call_method("Read", &(namespace, key)).await?,
+    };
+    let body = response.body();
+    let value = match body.deserialize::<Value>()? {
+        Value::Value(inner) => *inner,
+        value => value,
+    };
+    Ok(u32::try_from(value)?)
+}
+
+/// Whether the desktop asks for dark or light, or `None` when it has no
+/// preference
+pub async fn prefers_dark() -> zbus::Result<Option<bool>> {
+    Ok(match read_u32(APPEARANCE, "color-scheme").await? {
+        PREFER_DARK => Some(true),
+        PREFER_LIGHT => Some(false),
+        _ => None,
+    })
+}
@@ -0,0 +1,138 @@
+//! UPower's battery. The display device combines all batteries the way
+//! panels show one; health and cycle counts come from the batteries
+//! themselves.
+
+use super::system_bus;
+use futures_util::StreamExt;
+use zbus::zvariant::OwnedObjectPath;
+
+const SERVICE: &str = "org.freedesktop.UPower";
+const PATH: &str = "/org/freedesktop/UPower";
+const DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+/// `Type` of a battery that powers the machine, not a mouse or phone
+const BATTERY_TYPE: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryState {
+    Unknown,
+    Charging,
+    Discharging,
+    Empty,
+    FullyCharged,
+    PendingCharge,
+    PendingDischarge,
+}
+
+impl From<u32> for BatteryState {
+    fn from(state: u32) -> Self {
+        match state {
+            1 => BatteryState::Charging,
+            2 => BatteryState::Discharging,
+            3 => BatteryState::Empty,
+            4 => BatteryState::FullyCharged,
+            5 => BatteryState::PendingCharge,
+            6 => BatteryState::PendingDischarge,
+            _ => BatteryState::Unknown,
+        }
+    }
+}
+
+impl BatteryState {
+    /// Plugged in, whether or not charge is going in right now
+    pub fn is_charging(self) -> bool {
+        matches!(
+            self,
+            BatteryState::Charging | BatteryState::FullyCharged | BatteryState::PendingCharge
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Battery {
+    pub percentage: f64,
+    pub state: BatteryState,
+    pub is_present: bool,
+    /// 0 while UPower can't tell
+    pub time_to_empty_secs: i64,
+    /// Full capacity as a percentage of the design capacity
+    pub capacity: Option<f64>,
+    pub charge_cycles: Option<u32>,
+}
+
+async fn device(connection: &zbus::Connection, path: &str) -> zbus::Result<zbus::Proxy<'static>> {
+    zbus::Proxy::new(connection, SERVICE, path.to_string(), DEVICE_INTERFACE).await
+}
+
+/// Capacity and charge cycles of the first system battery. ChargeCycles is
+/// newer than most UPower installs and reads -1 when unknown.
+async fn battery_health(connection: &zbus::Connection) -> zbus::Result<(Option<f64>, Option<u32>)> {
+    let upower = zbus::Proxy::new(connection, SERVICE, PATH, SERVICE).await?;
+    let response = upower.call_method("EnumerateDevices", &()).await?;
+    let body = response.body();
+    let devices: Vec<OwnedObjectPath> = body.deserialize()?;
+    for path in devices {
+        let device = device(connection, path.as_str()).await?;
+        let is_battery = device.get_property::<u32>("Type").await? == BATTERY_TYPE
+            && device
+                .get_property::<bool>("PowerSupply")
+                .await
+                .unwrap_or(true);
+        if !is_battery {
+            continue;
+        }
+        let capacity = device
+            .get_property::<f64>("Capacity")
+            .await
+            .ok()
+            .filter(|capacity| *capacity > 0.0);
+        let cycles = device
+            .get_property::<i32>("ChargeCycles")
+            .await
+            .ok()
+            .and_then(|cycles| u32::try_from(cycles).ok());
+        return Ok((capacity, cycles));
+    }
+    Ok((None, None))
+}
+
+pub async fn display_battery() -> zbus::Result<Battery> {
+    let connection = system_bus().await?;
+    let display = device(&connection, DISPLAY_DEVICE).await?;
+    let (capacity, charge_cycles) = battery_health(&connection).await.unwrap_or_default();
+    Ok(Battery {
+        percentage: display.get_property("Percentage").await?,
+        state: display.get_property::<u32>("State").await?.into(),
+        is_present: display.get_property("IsPresent").await?,
+        time_to_empty_secs: display.get_property("TimeToEmpty").await?,
+        capacity,
+        charge_cycles,
+    })
+}
+
+/// Calls `on_change` with the battery each time UPower reports a change,
+/// until the bus connection goes away
+pub async fn watch<F>(mut on_change: F) -> zbus::Result<()>
+where
+    F: FnMut(Battery),
+{
+    let connection = system_bus().await?;
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .sender(SERVICE)?
+        .path(DISPLAY_DEVICE)?
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .build();
+    let mut stream = zbus::MessageStream::for_match_rule(rule, &connection, None).await?;
+    while let Some(message) = stream.next().await {
+        if message.is_err() {
+            continue;
+        }
+        match display_battery().await {
+            Ok(battery) => on_change(battery),
+            Err(e) => tracing::debug!(error = %e, "Failed to read the battery after a change"),
+        }
+    }
+    Ok(())
+}
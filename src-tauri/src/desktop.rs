@@ -1,13 +1,100 @@
-use crate::{app::App, error::AppError};
+use crate::{
+    app::{App, AppAction, AppSource, LaunchOptions},
+    appimage,
+    error::AppError,
+};
 use freedesktop_file_parser::{parse, EntryType};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
     env, fs,
     path::{Path, PathBuf},
+    sync::Mutex,
     time::SystemTime,
 };
 
+/// Which app formats to index besides native `.desktop` entries
+#[derive(Debug, Clone)]
+pub struct AppScanOptions {
+    pub include_flatpak: bool,
+    pub include_snap: bool,
+    pub appimage_dirs: Vec<PathBuf>,
+    /// Where extracted AppImage icons and metadata are kept
+    pub appimage_cache_dir: Option<PathBuf>,
+    /// Where parsed desktop entries are kept between scans (see `EntryCache`)
+    pub entry_cache_path: Option<PathBuf>,
+}
+
+impl Default for AppScanOptions {
+    fn default() -> Self {
+        Self {
+            include_flatpak: true,
+            include_snap: true,
+            appimage_dirs: Vec::new(),
+            appimage_cache_dir: None,
+            entry_cache_path: None,
+        }
+    }
+}
+
+/// Parsed desktop entries from the previous scan, reused while a file's mtime
+/// is unchanged so startup doesn't re-parse (and re-resolve icons for) every
+/// entry on the system
+#[derive(Serialize, Deserialize, Default)]
+pub struct EntryCache {
+    entries: HashMap<PathBuf, CachedEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedEntry {
+    modified: SystemTime,
+    /// `None` for files that aren't shown, e.g. `NoDisplay=true`
+    app: Option<App>,
+}
+
+impl EntryCache {
+    pub fn load(path: &Path) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| {
+                bincode::serde::decode_from_slice(&bytes, bincode::config::standard()).ok()
+            })
+            .map(|(cache, _)| cache)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        let encoded = bincode::serde::encode_to_vec(self, bincode::config::standard())?;
+        fs::write(path, encoded)?;
+        Ok(())
+    }
+
+    /// The cached parse result, unless the file changed or its resolved icon
+    /// has since disappeared
+    fn get(&self, path: &Path, modified: SystemTime) -> Option<Option<App>> {
+        let entry = self.entries.get(path).filter(|e| e.modified == modified)?;
+        let icon_exists = match entry.app.as_ref().and_then(|app| app.icon_path.as_deref()) {
+            Some(icon) => Path::new(icon).exists(),
+            None => true,
+        };
+        icon_exists.then(|| entry.app.clone())
+    }
+}
+
+/// Icon theme lookups by `Icon=` value, shared across one scan; many entries
+/// use the same icon and each lookup walks the theme directories
+type IconLookups = Mutex<HashMap<String, Option<String>>>;
+
+/// A parsed entry and the file it came from, before deduplication
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannedApp {
+    pub path: PathBuf,
+    /// Index of the scan root it was found in; lower wins when names collide
+    pub priority: usize,
+    pub app: App,
+}
+
 pub struct DesktopFileManager;
 
 impl DesktopFileManager {
@@ -23,6 +110,67 @@ impl DesktopFileManager {
         app_dirs
     }
 
+    pub fn get_flatpak_directories() -> Vec<PathBuf> {
+        let mut app_dirs = vec![PathBuf::from("/var/lib/flatpak/exports/share/applications")];
+
+        if let Ok(home_dir) = env::var("HOME") {
+            app_dirs.push(
+                PathBuf::from(home_dir).join(".local/share/flatpak/exports/share/applications"),
+            );
+        }
+        app_dirs
+    }
+
+    pub fn get_snap_directories() -> Vec<PathBuf> {
+        vec![PathBuf::from("/var/lib/snapd/desktop/applications")]
+    }
+
+    /// Desktop entry directories in priority order: native first, so packaged
+    /// duplicates of a native app are dropped
+    fn get_source_directories(options: &AppScanOptions) -> Vec<(PathBuf, AppSource)> {
+        let mut dirs: Vec<(PathBuf, AppSource)> = Self::get_app_directories()
+            .into_iter()
+            .map(|dir| (dir, AppSource::Native))
+            .collect();
+        if options.include_flatpak {
+            dirs.extend(
+                Self::get_flatpak_directories()
+                    .into_iter()
+                    .map(|dir| (dir, AppSource::Flatpak)),
+            );
+        }
+        if options.include_snap {
+            dirs.extend(
+                Self::get_snap_directories()
+                    .into_iter()
+                    .map(|dir| (dir, AppSource::Snap)),
+            );
+        }
+        dirs
+    }
+
+    /// Every directory whose modification invalidates the app cache
+    pub fn get_watched_directories(options: &AppScanOptions) -> Vec<PathBuf> {
+        Self::get_scan_roots(options)
+            .into_iter()
+            .map(|(dir, _)| dir)
+            .collect()
+    }
+
+    /// Directories to scan in priority order, with the source their entries
+    /// come from; `AppSource::AppImage` roots hold AppImages, not desktop files
+    pub fn get_scan_roots(options: &AppScanOptions) -> Vec<(PathBuf, AppSource)> {
+        Self::get_source_directories(options)
+            .into_iter()
+            .chain(
+                options
+                    .appimage_dirs
+                    .iter()
+                    .map(|dir| (dir.clone(), AppSource::AppImage)),
+            )
+            .collect()
+    }
+
     pub fn find_desktop_files(path: &Path) -> Vec<PathBuf> {
         let mut desktop_files = Vec::new();
         if let Ok(entries) = fs::read_dir(path) {
@@ -38,27 +186,174 @@ impl DesktopFileManager {
         desktop_files
     }
 
-    pub fn scan_and_parse_apps() -> Result<(Vec<App>, HashMap<PathBuf, SystemTime>), AppError> {
-        let app_dirs = Self::get_app_directories();
-        let desktop_files: Vec<PathBuf> = app_dirs
+    pub fn scan_roots(roots: &[(PathBuf, AppSource)], options: &AppScanOptions) -> Vec<ScannedApp> {
+        let desktop_files: Vec<(PathBuf, usize, AppSource)> = roots
             .iter()
-            .filter(|dir| dir.exists())
-            .flat_map(|dir| Self::find_desktop_files(dir))
+            .enumerate()
+            .filter(|(_, (dir, source))| *source != AppSource::AppImage && dir.exists())
+            .flat_map(|(priority, (dir, source))| {
+                Self::find_desktop_files(dir)
+                    .into_iter()
+                    .map(move |file| (file, priority, *source))
+            })
             .collect();
 
-        let apps: Vec<App> = desktop_files
-            .par_iter()
-            .filter_map(|file_path| Self::parse_desktop_file(file_path))
+        let previous = options
+            .entry_cache_path
+            .as_deref()
+            .map(EntryCache::load)
+            .unwrap_or_default();
+        let icons = IconLookups::default();
+        let parsed: Vec<(PathBuf, usize, Option<SystemTime>, Option<App>)> = desktop_files
+            .into_par_iter()
+            .map(|(path, priority, source)| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+                let app = match modified.and_then(|m| previous.get(&path, m)) {
+                    Some(cached) => cached,
+                    None => Self::parse_desktop_file(&path, source, &icons),
+                };
+                (path, priority, modified, app)
+            })
             .collect();
 
-        let unique_apps = Self::deduplicate_and_sort_apps(apps);
+        let mut next = EntryCache::default();
+        let mut scanned = Vec::with_capacity(parsed.len());
+        for (path, priority, modified, app) in parsed {
+            if let Some(modified) = modified {
+                next.entries.insert(
+                    path.clone(),
+                    CachedEntry {
+                        modified,
+                        app: app.clone(),
+                    },
+                );
+            }
+            if let Some(app) = app {
+                scanned.push(ScannedApp {
+                    path,
+                    priority,
+                    app,
+                });
+            }
+        }
+        if let Some(cache_path) = options.entry_cache_path.as_deref() {
+            if let Err(e) = next.save(cache_path) {
+                tracing::warn!(error = ?e, "Failed to write the desktop entry cache");
+            }
+        }
 
-        let dir_mod_times = Self::get_directory_modification_times(app_dirs)?;
+        // AppImage metadata has its own per-file cache; extracting uncached
+        // ones runs the AppImage, so they're done in parallel too
+        let appimages: Vec<(PathBuf, usize)> = roots
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, source))| *source == AppSource::AppImage)
+            .flat_map(|(priority, (dir, _))| {
+                appimage::find_appimages(std::slice::from_ref(dir))
+                    .into_iter()
+                    .map(move |path| (path, priority))
+            })
+            .collect();
+        scanned.par_extend(
+            appimages
+                .into_par_iter()
+                .map(|(path, priority)| ScannedApp {
+                    app: appimage::parse_appimage(&path, options.appimage_cache_dir.as_deref()),
+                    path,
+                    priority,
+                }),
+        );
+        scanned
+    }
+
+    /// Parses a single file under one of `roots`; `None` when the file is not
+    /// an app (anymore), e.g. it was hidden or isn't a desktop entry
+    pub fn scan_path(
+        roots: &[(PathBuf, AppSource)],
+        options: &AppScanOptions,
+        path: &Path,
+    ) -> Option<ScannedApp> {
+        let (priority, (dir, source)) = roots
+            .iter()
+            .enumerate()
+            .find(|(_, (dir, _))| path.starts_with(dir))?;
+        let app = if *source == AppSource::AppImage {
+            // AppImage directories aren't scanned recursively
+            if path.parent() != Some(dir.as_path()) || !appimage::is_appimage(path) {
+                return None;
+            }
+            appimage::parse_appimage(path, options.appimage_cache_dir.as_deref())
+        } else {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("desktop") {
+                return None;
+            }
+            Self::parse_desktop_file(path, *source, &IconLookups::default())?
+        };
+        Some(ScannedApp {
+            path: path.to_path_buf(),
+            priority,
+            app,
+        })
+    }
+
+    /// The deduplicated, sorted list shown to users
+    pub fn merge_scanned<'a>(scanned: impl IntoIterator<Item = &'a ScannedApp>) -> Vec<App> {
+        let mut scanned: Vec<&ScannedApp> = scanned.into_iter().collect();
+        scanned.sort_by(|a, b| (a.priority, &a.path).cmp(&(b.priority, &b.path)));
+        Self::deduplicate_and_sort_apps(scanned.into_iter().map(|s| s.app.clone()).collect())
+    }
+
+    pub fn scan_and_parse_apps(
+        options: &AppScanOptions,
+    ) -> Result<(Vec<App>, HashMap<PathBuf, SystemTime>), AppError> {
+        let scanned = Self::scan_roots(&Self::get_scan_roots(options), options);
+        let unique_apps = Self::merge_scanned(&scanned);
+
+        let dir_mod_times =
+            Self::get_directory_modification_times(Self::get_watched_directories(options))?;
 
         Ok((unique_apps, dir_mod_times))
     }
 
-    fn parse_desktop_file(file_path: &Path) -> Option<App> {
+    /// Strips packaging wrappers from Exec lines so every source launches the
+    /// same way as a native entry
+    pub fn normalize_exec(exec: &str, source: AppSource) -> String {
+        match source {
+            // Flatpak exports wrap file arguments in `@@u %U @@` markers
+            AppSource::Flatpak => exec
+                .split(' ')
+                .filter(|token| !token.is_empty() && *token != "@@u" && *token != "@@")
+                .collect::<Vec<_>>()
+                .join(" "),
+            // Snap entries go through `env BAMF_DESKTOP_FILE_HINT=<path> /snap/bin/<app>`
+            AppSource::Snap => match exec.strip_prefix("env BAMF_DESKTOP_FILE_HINT=") {
+                Some(rest) => rest
+                    .split_once(' ')
+                    .map(|(_, command)| command.trim().to_string())
+                    .unwrap_or_else(|| exec.to_string()),
+                None => exec.to_string(),
+            },
+            AppSource::Native | AppSource::AppImage => exec.to_string(),
+        }
+    }
+
+    fn resolve_icon(
+        icon: &str,
+        icons: &IconLookups,
+        lookup: impl FnOnce() -> Option<String>,
+    ) -> Option<String> {
+        if let Some(resolved) = icons.lock().unwrap().get(icon) {
+            return resolved.clone();
+        }
+        let resolved = lookup();
+        icons
+            .lock()
+            .unwrap()
+            .insert(icon.to_string(), resolved.clone());
+        resolved
+    }
+
+    fn parse_desktop_file(file_path: &Path, source: AppSource, icons: &IconLookups) -> Option<App> {
         let content = fs::read_to_string(file_path).ok()?;
         let desktop_file = parse(&content).ok()?;
 
@@ -72,27 +367,146 @@ impl DesktopFileManager {
             if app_fields.exec.is_some() && !desktop_file.entry.name.default.is_empty() {
                 return Some(
                     App::new(desktop_file.entry.name.default)
+                        .with_id(
+                            file_path
+                                .file_name()
+                                .and_then(|name| name.to_str())
+                                .map(String::from),
+                        )
                         .with_comment(desktop_file.entry.comment.map(|lc| lc.default))
-                        .with_exec(app_fields.exec)
-                        .with_icon_path(
-                            desktop_file
-                                .entry
-                                .icon
-                                .and_then(|ic| ic.get_icon_path())
-                                .and_then(|p| p.to_str().map(String::from)),
-                        ),
+                        .with_exec(
+                            app_fields
+                                .exec
+                                .map(|exec| Self::normalize_exec(&exec, source)),
+                        )
+                        .with_source(source)
+                        .with_actions(Self::parse_actions(&content, source))
+                        .with_launch_options(Self::parse_launch_options(&content, file_path))
+                        .with_mime_types(Self::parse_mime_types(&content))
+                        .with_icon_path(Self::entry_value(&content, "Icon").and_then(|icon| {
+                            Self::resolve_icon(icon, icons, || {
+                                desktop_file
+                                    .entry
+                                    .icon
+                                    .and_then(|ic| ic.get_icon_path())
+                                    .and_then(|p| p.to_str().map(String::from))
+                            })
+                        })),
                 );
             }
         }
         None
     }
 
+    /// Looks up a key in the `[Desktop Entry]` group, ignoring localized variants
+    fn entry_value<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+        let mut in_entry = false;
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+                continue;
+            }
+            if !in_entry {
+                continue;
+            }
+            if let Some((k, value)) = line.split_once('=') {
+                if k.trim() == key {
+                    return Some(value.trim());
+                }
+            }
+        }
+        None
+    }
+
+    fn parse_mime_types(content: &str) -> Vec<String> {
+        Self::entry_value(content, "MimeType")
+            .map(|types| {
+                types
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|mime| !mime.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn parse_launch_options(content: &str, file_path: &Path) -> LaunchOptions {
+        let flag = |key: &str| Self::entry_value(content, key).is_some_and(|v| v == "true");
+        let text = |key: &str| {
+            Self::entry_value(content, key)
+                .filter(|v| !v.is_empty())
+                .map(String::from)
+        };
+        LaunchOptions {
+            desktop_file: file_path.to_str().map(String::from),
+            terminal: flag("Terminal"),
+            startup_notify: flag("StartupNotify"),
+            startup_wm_class: text("StartupWMClass"),
+            working_dir: text("Path"),
+        }
+    }
+
+    /// Reads the `[Desktop Action <id>]` groups listed in the entry's `Actions` key
+    fn parse_actions(content: &str, source: AppSource) -> Vec<AppAction> {
+        let mut declared: Vec<String> = Vec::new();
+        let mut groups: HashMap<String, (Option<String>, Option<String>)> = HashMap::new();
+        let mut current_group: Option<String> = None;
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(group) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                current_group = Some(group.to_string());
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim().to_string());
+
+            match current_group.as_deref() {
+                Some("Desktop Entry") if key == "Actions" => {
+                    declared = value
+                        .split(';')
+                        .filter(|id| !id.is_empty())
+                        .map(String::from)
+                        .collect();
+                }
+                Some(group) => {
+                    if let Some(id) = group.strip_prefix("Desktop Action ") {
+                        let entry = groups.entry(id.to_string()).or_default();
+                        match key {
+                            "Name" => entry.0 = Some(value),
+                            "Exec" => entry.1 = Some(value),
+                            _ => {}
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+
+        declared
+            .into_iter()
+            .filter_map(|id| {
+                let (name, exec) = groups.remove(&id)?;
+                Some(AppAction {
+                    exec: Self::normalize_exec(&exec?, source),
+                    name: name?,
+                    id,
+                })
+            })
+            .collect()
+    }
+
     fn deduplicate_and_sort_apps(apps: Vec<App>) -> Vec<App> {
         let mut unique_apps = Vec::new();
         let mut seen_app_names = HashSet::new();
 
         for app in apps {
-            if seen_app_names.insert(app.name.clone()) {
+            if seen_app_names.insert(app.name.to_lowercase()) {
                 unique_apps.push(app);
             }
         }
@@ -101,7 +515,7 @@ impl DesktopFileManager {
         unique_apps
     }
 
-    fn get_directory_modification_times(
+    pub fn get_directory_modification_times(
         app_dirs: Vec<PathBuf>,
     ) -> Result<HashMap<PathBuf, SystemTime>, AppError> {
         Ok(app_dirs
@@ -115,3 +529,147 @@ impl DesktopFileManager {
             .collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_flatpak_exec() {
+        let exec = "/usr/bin/flatpak run --branch=stable --arch=x86_64 --command=obsidian.sh --file-forwarding md.obsidian.Obsidian @@u %U @@";
+        assert_eq!(
+            DesktopFileManager::normalize_exec(exec, AppSource::Flatpak),
+            "/usr/bin/flatpak run --branch=stable --arch=x86_64 --command=obsidian.sh --file-forwarding md.obsidian.Obsidian %U"
+        );
+    }
+
+    #[test]
+    fn test_normalize_snap_exec() {
+        let exec = "env BAMF_DESKTOP_FILE_HINT=/var/lib/snapd/desktop/applications/firefox_firefox.desktop /snap/bin/firefox %u";
+        assert_eq!(
+            DesktopFileManager::normalize_exec(exec, AppSource::Snap),
+            "/snap/bin/firefox %u"
+        );
+        assert_eq!(
+            DesktopFileManager::normalize_exec("/snap/bin/code", AppSource::Snap),
+            "/snap/bin/code"
+        );
+    }
+
+    #[test]
+    fn test_parse_actions() {
+        let content = "[Desktop Entry]\n\
+                       Name=Firefox\n\
+                       Exec=firefox %u\n\
+                       Actions=new-window;new-private-window;missing;\n\
+                       \n\
+                       [Desktop Action new-window]\n\
+                       Name=New Window\n\
+                       Name[de]=Neues Fenster\n\
+                       Exec=firefox --new-window %u\n\
+                       \n\
+                       [Desktop Action new-private-window]\n\
+                       Name=New Private Window\n\
+                       Exec=firefox --private-window %u\n\
+                       \n\
+                       [Desktop Action undeclared]\n\
+                       Name=Undeclared\n\
+                       Exec=firefox\n";
+        let actions = DesktopFileManager::parse_actions(content, AppSource::Native);
+        assert_eq!(
+            actions,
+            vec![
+                AppAction {
+                    id: "new-window".to_string(),
+                    name: "New Window".to_string(),
+                    exec: "firefox --new-window %u".to_string(),
+                },
+                AppAction {
+                    id: "new-private-window".to_string(),
+                    name: "New Private Window".to_string(),
+                    exec: "firefox --private-window %u".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_launch_options() {
+        let content = "[Desktop Entry]\n\
+                       Name=htop\n\
+                       Terminal=true\n\
+                       StartupWMClass=htop\n\
+                       \n\
+                       [Desktop Action other]\n\
+                       StartupNotify=true\n";
+        let options =
+            DesktopFileManager::parse_launch_options(content, Path::new("/usr/share/htop.desktop"));
+        assert!(options.terminal);
+        assert!(!options.startup_notify);
+        assert_eq!(options.startup_wm_class.as_deref(), Some("htop"));
+        assert_eq!(options.working_dir, None);
+        assert_eq!(
+            options.desktop_file.as_deref(),
+            Some("/usr/share/htop.desktop")
+        );
+    }
+
+    #[test]
+    fn test_unchanged_entries_come_from_the_entry_cache() {
+        let dir = env::temp_dir().join(format!("flare_entries_{}", uuid::Uuid::new_v4()));
+        let apps_dir = dir.join("applications");
+        fs::create_dir_all(&apps_dir).unwrap();
+        let entry = apps_dir.join("tool.desktop");
+        let write_entry = |name: &str| {
+            fs::write(
+                &entry,
+                format!(
+                    "[Desktop Entry]\nType=Application\nName={}\nExec=tool\n",
+                    name
+                ),
+            )
+            .unwrap();
+        };
+        let roots = vec![(apps_dir.clone(), AppSource::Native)];
+        let options = AppScanOptions {
+            entry_cache_path: Some(dir.join("entries.bincode")),
+            ..Default::default()
+        };
+        let names = || -> Vec<String> {
+            DesktopFileManager::scan_roots(&roots, &options)
+                .into_iter()
+                .map(|scanned| scanned.app.name)
+                .collect()
+        };
+
+        write_entry("Alpha");
+        assert_eq!(names(), vec!["Alpha"]);
+
+        // Same mtime: the cached parse is used even though the content changed
+        let modified = fs::metadata(&entry).unwrap().modified().unwrap();
+        write_entry("Beta");
+        let file = fs::File::options().write(true).open(&entry).unwrap();
+        file.set_modified(modified).unwrap();
+        assert_eq!(names(), vec!["Alpha"]);
+
+        file.set_modified(modified + std::time::Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(names(), vec!["Beta"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_native_apps_win_deduplication() {
+        let apps = vec![
+            App::new("Firefox".to_string()).with_source(AppSource::Native),
+            App::new("firefox".to_string()).with_source(AppSource::Snap),
+            App::new("Obsidian".to_string()).with_source(AppSource::AppImage),
+        ];
+        let unique = DesktopFileManager::deduplicate_and_sort_apps(apps);
+        assert_eq!(unique.len(), 2);
+        assert_eq!(unique[0].name, "Firefox");
+        assert_eq!(unique[0].source, AppSource::Native);
+        assert_eq!(unique[1].source, AppSource::AppImage);
+    }
+}
@@ -0,0 +1,482 @@
+//! `run_doctor`: checks the system for the tools and permissions Flare's
+//! features rely on, for the onboarding screen and bug reports. Every check
+//! reports a status and, when something is missing, how to fix it.
+
+use crate::launcher::find_in_path;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Pass,
+    /// Works, but some features are degraded or unavailable
+    Warn,
+    Fail,
+    /// Doesn't apply to this system, e.g. brightness on a desktop
+    Skipped,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorCheck {
+    pub id: String,
+    pub title: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl DoctorCheck {
+    fn new(id: &str, title: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            id: id.to_string(),
+            title: title.to_string(),
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn with_fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SessionType {
+    Wayland,
+    X11,
+    Unknown,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Compositor {
+    Gnome,
+    Kde,
+    Hyprland,
+    Sway,
+    Cosmic,
+    Other,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorReport {
+    pub session_type: SessionType,
+    pub compositor: Compositor,
+    pub desktop: Option<String>,
+    pub checks: Vec<DoctorCheck>,
+    /// No check failed
+    pub healthy: bool,
+}
+
+struct Environment {
+    session_type: SessionType,
+    compositor: Compositor,
+    desktop: Option<String>,
+}
+
+impl Environment {
+    fn detect() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let session_type = if var("WAYLAND_DISPLAY").is_some() {
+            SessionType::Wayland
+        } else if var("DISPLAY").is_some() {
+            SessionType::X11
+        } else {
+            match var("XDG_SESSION_TYPE").as_deref() {
+                Some("wayland") => SessionType::Wayland,
+                Some("x11") => SessionType::X11,
+                _ => SessionType::Unknown,
+            }
+        };
+        let desktop = var("XDG_CURRENT_DESKTOP").or_else(|| var("DESKTOP_SESSION"));
+        let lowered = desktop.as_deref().unwrap_or_default().to_lowercase();
+        let compositor = if var("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Compositor::Hyprland
+        } else if var("SWAYSOCK").is_some() || lowered.contains("sway") {
+            Compositor::Sway
+        } else if lowered.contains("kde") || lowered.contains("plasma") {
+            Compositor::Kde
+        } else if lowered.contains("cosmic") {
+            Compositor::Cosmic
+        } else if ["gnome", "ubuntu", "unity", "pop"]
+            .iter()
+            .any(|name| lowered.contains(name))
+        {
+            Compositor::Gnome
+        } else {
+            Compositor::Other
+        };
+        Self {
+            session_type,
+            compositor,
+            desktop,
+        }
+    }
+
+    fn wayland(&self) -> bool {
+        self.session_type == SessionType::Wayland
+    }
+}
+
+fn has_tool(name: &str) -> bool {
+    find_in_path(name).is_some()
+}
+
+fn input_devices() -> Vec<std::path::PathBuf> {
+    fs::read_dir("/dev/input")
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with("event"))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn check_input_devices(env: &Environment) -> DoctorCheck {
+    const ID: &str = "input_devices";
+    const TITLE: &str = "Keyboard input access";
+    let devices = input_devices();
+    let readable = devices.iter().any(|device| fs::File::open(device).is_ok());
+    if readable {
+        return DoctorCheck::new(ID, TITLE, CheckStatus::Pass, "Can read /dev/input devices");
+    }
+    // X11 gets key events from the server; only chord hotkeys need evdev
+    let status = if env.wayland() {
+        CheckStatus::Fail
+    } else {
+        CheckStatus::Warn
+    };
+    let detail = if devices.is_empty() {
+        "No devices found under /dev/input".to_string()
+    } else {
+        "No read access to /dev/input; snippet expansion on Wayland and double-tap hotkeys \
+         won't work"
+            .to_string()
+    };
+    DoctorCheck::new(ID, TITLE, status, detail)
+        .with_fix("Run `sudo usermod -aG input $USER`, then log out and back in")
+}
+
+fn check_input_injection(env: &Environment) -> DoctorCheck {
+    const ID: &str = "input_injection";
+    const TITLE: &str = "Typing into other apps";
+    if env.wayland() {
+        let uinput = OpenOptions::new().write(true).open("/dev/uinput").is_ok();
+        if uinput {
+            return DoctorCheck::new(ID, TITLE, CheckStatus::Pass, "Can write to /dev/uinput");
+        }
+        if has_tool("ydotool") {
+            return DoctorCheck::new(
+                ID,
+                TITLE,
+                CheckStatus::Warn,
+                "/dev/uinput isn't writable; ydotool is installed but needs ydotoold running",
+            )
+            .with_fix("Start ydotoold, or add a udev rule granting the input group /dev/uinput");
+        }
+        return DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Fail,
+            "Neither /dev/uinput nor ydotool is available, so snippets and paste actions can't \
+             type",
+        )
+        .with_fix(
+            "Add `KERNEL==\"uinput\", GROUP=\"input\", MODE=\"0660\"` to \
+             /etc/udev/rules.d/99-uinput.rules, or install ydotool",
+        );
+    }
+    if has_tool("xdotool") {
+        DoctorCheck::new(ID, TITLE, CheckStatus::Pass, "xdotool is installed")
+    } else {
+        DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Warn,
+            "xdotool not found; typing falls back to XTest, which some apps ignore",
+        )
+        .with_fix("Install xdotool")
+    }
+}
+
+fn check_clipboard_tools(env: &Environment) -> DoctorCheck {
+    const ID: &str = "clipboard_tools";
+    const TITLE: &str = "Clipboard tools";
+    if env.wayland() {
+        return if has_tool("wl-copy") && has_tool("wl-paste") {
+            DoctorCheck::new(ID, TITLE, CheckStatus::Pass, "wl-clipboard is installed")
+        } else {
+            DoctorCheck::new(
+                ID,
+                TITLE,
+                CheckStatus::Fail,
+                "wl-copy/wl-paste not found; clipboard history can't see other apps' copies",
+            )
+            .with_fix("Install wl-clipboard")
+        };
+    }
+    match ["xclip", "xsel"].into_iter().find(|tool| has_tool(tool)) {
+        Some(tool) => DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Pass,
+            format!("{} is installed", tool),
+        ),
+        None => DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Warn,
+            "xclip not found; ignore rules can't read clipboard formats",
+        )
+        .with_fix("Install xclip"),
+    }
+}
+
+fn check_network_manager() -> DoctorCheck {
+    const ID: &str = "network_manager";
+    const TITLE: &str = "Wi-Fi and network toggles";
+    if has_tool("nmcli") {
+        DoctorCheck::new(ID, TITLE, CheckStatus::Pass, "nmcli is installed")
+    } else {
+        DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Warn,
+            "nmcli not found; Wi-Fi toggles and network info are unavailable",
+        )
+        .with_fix("Install NetworkManager")
+    }
+}
+
+fn check_brightness() -> DoctorCheck {
+    const ID: &str = "brightness";
+    const TITLE: &str = "Screen brightness";
+    let backlights: Vec<_> = fs::read_dir("/sys/class/backlight")
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    if backlights.is_empty() {
+        return DoctorCheck::new(ID, TITLE, CheckStatus::Skipped, "No backlight devices");
+    }
+    let writable = backlights.iter().any(|backlight| {
+        OpenOptions::new()
+            .write(true)
+            .open(backlight.join("brightness"))
+            .is_ok()
+    });
+    match (has_tool("brightnessctl"), writable) {
+        (true, true) => DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Pass,
+            "brightnessctl can change the backlight",
+        ),
+        (true, false) => DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Warn,
+            "The backlight isn't writable; brightnessctl only works through logind",
+        )
+        .with_fix("Run `sudo usermod -aG video $USER`, then log out and back in"),
+        (false, _) => DoctorCheck::new(ID, TITLE, CheckStatus::Warn, "brightnessctl not found")
+            .with_fix("Install brightnessctl"),
+    }
+}
+
+fn check_window_management(env: &Environment) -> DoctorCheck {
+    const ID: &str = "window_management";
+    const TITLE: &str = "Window management";
+    match env.session_type {
+        SessionType::X11 => DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Pass,
+            "Snapping and layouts work through X11",
+        ),
+        SessionType::Wayland => DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Warn,
+            "Wayland apps can't be moved by other clients; only XWayland windows can be snapped",
+        )
+        .with_fix("Use your compositor's own tiling shortcuts for native Wayland windows"),
+        SessionType::Unknown => {
+            DoctorCheck::new(ID, TITLE, CheckStatus::Fail, "No display server detected")
+        }
+    }
+}
+
+/// Global shortcuts on Wayland need either the GlobalShortcuts portal or a
+/// compositor keybinding that runs `flare`, which toggles the
+/// running instance
+async fn check_global_shortcuts(env: &Environment) -> DoctorCheck {
+    const ID: &str = "global_shortcuts";
+    const TITLE: &str = "Global shortcuts";
+    if !env.wayland() {
+        return DoctorCheck::new(ID, TITLE, CheckStatus::Pass, "X11 allows global key grabs");
+    }
+    match global_shortcuts_portal_version().await {
+        Some(version) => DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Pass,
+            format!("GlobalShortcuts portal available (version {})", version),
+        ),
+        None => DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Warn,
+            "The GlobalShortcuts portal isn't available, so the launcher hotkey may not fire \
+             outside Flare",
+        )
+        .with_fix(match env.compositor {
+            Compositor::Hyprland => "Add `bind = SUPER, SPACE, exec, flare` to hyprland.conf",
+            Compositor::Sway => "Add `bindsym Mod4+space exec flare` to your sway config",
+            Compositor::Gnome => "Add a custom shortcut running `flare` in Settings > Keyboard",
+            _ => "Bind a shortcut to `flare` in your desktop's keyboard settings",
+        }),
+    }
+}
+
+async fn global_shortcuts_portal_version() -> Option<u32> {
+    let connection = zbus::Connection::session().await.ok()?;
+    let reply = connection
+        .call_method(
+            Some("org.freedesktop.portal.Desktop"),
+            "/org/freedesktop/portal/desktop",
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &("org.freedesktop.portal.GlobalShortcuts", "version"),
+        )
+        .await
+        .ok()?;
+    let value: zbus::zvariant::OwnedValue = reply.body().deserialize().ok()?;
+    u32::try_from(value).ok()
+}
+
+fn check_keyring() -> DoctorCheck {
+    const ID: &str = "keyring";
+    const TITLE: &str = "Secret storage";
+    match crate::secrets::probe() {
+        Ok(()) => DoctorCheck::new(ID, TITLE, CheckStatus::Pass, "The keyring is reachable"),
+        Err(e) => DoctorCheck::new(
+            ID,
+            TITLE,
+            CheckStatus::Fail,
+            format!("Can't use the keyring: {}", e),
+        )
+        .with_fix("Make sure a keyring daemon is running and unlocked for this session"),
+    }
+}
+
+fn check_ocr() -> DoctorCheck {
+    const ID: &str = "ocr";
+    const TITLE: &str = "Text recognition";
+    if has_tool("tesseract") {
+        DoctorCheck::new(ID, TITLE, CheckStatus::Pass, "tesseract is installed")
+    } else {
+        DoctorCheck::new(ID, TITLE, CheckStatus::Warn, "tesseract not found")
+            .with_fix("Install tesseract and the language packs you need")
+    }
+}
+
+fn run_local_checks(env: &Environment) -> Vec<DoctorCheck> {
+    vec![
+        check_input_devices(env),
+        check_input_injection(env),
+        check_clipboard_tools(env),
+        check_window_management(env),
+        check_network_manager(),
+        check_brightness(),
+        check_keyring(),
+        check_ocr(),
+    ]
+}
+
+fn healthy(checks: &[DoctorCheck]) -> bool {
+    checks.iter().all(|check| check.status != CheckStatus::Fail)
+}
+
+#[tauri::command]
+pub async fn run_doctor() -> Result<DoctorReport, String> {
+    let env = Environment::detect();
+    let shortcuts = check_global_shortcuts(&env).await;
+    let (env, mut checks) = tauri::async_runtime::spawn_blocking(move || {
+        let checks = run_local_checks(&env);
+        (env, checks)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    checks.insert(3, shortcuts);
+
+    Ok(DoctorReport {
+        session_type: env.session_type,
+        compositor: env.compositor,
+        desktop: env.desktop,
+        healthy: healthy(&checks),
+        checks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> Environment {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Environment::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_environment_detection() {
+        let hyprland = env(&[
+            ("WAYLAND_DISPLAY", "wayland-1"),
+            ("HYPRLAND_INSTANCE_SIGNATURE", "abc"),
+            ("XDG_CURRENT_DESKTOP", "Hyprland"),
+        ]);
+        assert_eq!(hyprland.session_type, SessionType::Wayland);
+        assert_eq!(hyprland.compositor, Compositor::Hyprland);
+
+        let plasma = env(&[("DISPLAY", ":0"), ("XDG_CURRENT_DESKTOP", "KDE")]);
+        assert_eq!(plasma.session_type, SessionType::X11);
+        assert_eq!(plasma.compositor, Compositor::Kde);
+
+        let gnome = env(&[
+            ("XDG_SESSION_TYPE", "wayland"),
+            ("XDG_CURRENT_DESKTOP", "ubuntu:GNOME"),
+        ]);
+        assert_eq!(gnome.session_type, SessionType::Wayland);
+        assert_eq!(gnome.compositor, Compositor::Gnome);
+
+        let headless = env(&[]);
+        assert_eq!(headless.session_type, SessionType::Unknown);
+        assert_eq!(headless.compositor, Compositor::Other);
+        assert_eq!(check_window_management(&headless).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_healthy_ignores_warnings() {
+        let warn = DoctorCheck::new("a", "A", CheckStatus::Warn, "").with_fix("x");
+        let fail = DoctorCheck::new("b", "B", CheckStatus::Fail, "");
+        assert!(healthy(std::slice::from_ref(&warn)));
+        assert!(!healthy(&[warn, fail]));
+    }
+}
@@ -0,0 +1,407 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+
+static HTML_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]+>").unwrap());
+static WN_SENSE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\d+\.\s+(?:\(\d+\)\s+)?(.+?) -- \((.*)\)$").unwrap());
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Sense {
+    pub part_of_speech: String,
+    pub definition: String,
+    pub examples: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WordDefinition {
+    pub word: String,
+    pub language: String,
+    /// Backend that answered: `wiktextract`, `wordnet`, `dictionaryapi` or `wiktionary`
+    pub source: String,
+    pub phonetic: Option<String>,
+    pub senses: Vec<Sense>,
+    pub synonyms: Vec<String>,
+}
+
+impl WordDefinition {
+    fn new(word: &str, language: &str, source: &str) -> Self {
+        Self {
+            word: word.to_string(),
+            language: language.to_string(),
+            source: source.to_string(),
+            phonetic: None,
+            senses: Vec::new(),
+            synonyms: Vec::new(),
+        }
+    }
+
+    fn add_synonym(&mut self, synonym: &str) {
+        let synonym = synonym.trim();
+        if !synonym.is_empty()
+            && !synonym.eq_ignore_ascii_case(&self.word)
+            && !self.synonyms.iter().any(|s| s == synonym)
+        {
+            self.synonyms.push(synonym.to_string());
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.senses.is_empty() && self.synonyms.is_empty()
+    }
+}
+
+/// Wiktextract JSONL dumps go in `<app data>/dictionaries/<lang>.jsonl`
+fn wiktextract_path(app: &AppHandle, language: &str) -> Option<PathBuf> {
+    let path = app
+        .path()
+        .app_local_data_dir()
+        .ok()?
+        .join("dictionaries")
+        .join(format!("{}.jsonl", language));
+    path.exists().then_some(path)
+}
+
+fn lookup_wiktextract(path: &Path, word: &str, language: &str) -> Option<WordDefinition> {
+    let file = File::open(path).ok()?;
+    let needle = format!("\"word\": \"{}\"", word);
+    let compact_needle = format!("\"word\":\"{}\"", word);
+
+    let mut result = WordDefinition::new(word, language, "wiktextract");
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        // Cheap pre-filter before parsing; dumps are hundreds of MB
+        if !line.contains(&needle) && !line.contains(&compact_needle) {
+            continue;
+        }
+        parse_wiktextract_entry(&line, &mut result);
+    }
+    (!result.is_empty()).then_some(result)
+}
+
+fn parse_wiktextract_entry(line: &str, result: &mut WordDefinition) {
+    let Ok(entry) = serde_json::from_str::<Value>(line) else {
+        return;
+    };
+    if !entry["word"]
+        .as_str()
+        .is_some_and(|w| w.eq_ignore_ascii_case(&result.word))
+    {
+        return;
+    }
+
+    let part_of_speech = entry["pos"].as_str().unwrap_or_default().to_string();
+    if result.phonetic.is_none() {
+        result.phonetic = entry["sounds"]
+            .as_array()
+            .and_then(|sounds| sounds.iter().find_map(|s| s["ipa"].as_str()))
+            .map(|s| s.to_string());
+    }
+
+    let word_synonyms = entry["synonyms"].as_array().cloned().unwrap_or_default();
+    for sense in entry["senses"].as_array().into_iter().flatten() {
+        let Some(definition) = sense["glosses"]
+            .as_array()
+            .and_then(|glosses| glosses.last())
+            .and_then(|g| g.as_str())
+        else {
+            continue;
+        };
+        result.senses.push(Sense {
+            part_of_speech: part_of_speech.clone(),
+            definition: definition.to_string(),
+            examples: sense["examples"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e["text"].as_str().map(|s| s.to_string()))
+                .collect(),
+        });
+        for synonym in sense["synonyms"].as_array().into_iter().flatten() {
+            if let Some(word) = synonym["word"].as_str() {
+                result.add_synonym(word);
+            }
+        }
+    }
+    for synonym in &word_synonyms {
+        if let Some(word) = synonym["word"].as_str() {
+            result.add_synonym(word);
+        }
+    }
+}
+
+/// Queries the WordNet `wn` CLI (English only)
+fn lookup_wordnet(word: &str) -> Option<WordDefinition> {
+    let output = Command::new("wn").args([word, "-over"]).output().ok()?;
+    // wn exits with the number of senses found, so don't check the status
+    let text = String::from_utf8_lossy(&output.stdout);
+    let result = parse_wordnet_overview(word, &text);
+    (!result.is_empty()).then_some(result)
+}
+
+fn parse_wordnet_overview(word: &str, output: &str) -> WordDefinition {
+    let mut result = WordDefinition::new(word, "en", "wordnet");
+    let mut part_of_speech = String::new();
+
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Overview of ") {
+            part_of_speech = rest
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            continue;
+        }
+        let Some(captures) = WN_SENSE.captures(line) else {
+            continue;
+        };
+
+        for synonym in captures[1].split(", ") {
+            result.add_synonym(synonym);
+        }
+
+        // Gloss format: definition; "example one"; "example two"
+        let mut parts = captures[2].split("; \"");
+        let definition = parts.next().unwrap_or_default().trim().to_string();
+        let examples = parts
+            .map(|example| example.trim_end_matches('"').to_string())
+            .collect();
+        result.senses.push(Sense {
+            part_of_speech: part_of_speech.clone(),
+            definition,
+            examples,
+        });
+    }
+    result
+}
+
+fn parse_dictionaryapi(word: &str, body: &Value) -> WordDefinition {
+    let mut result = WordDefinition::new(word, "en", "dictionaryapi");
+    for entry in body.as_array().into_iter().flatten() {
+        if result.phonetic.is_none() {
+            result.phonetic = entry["phonetic"].as_str().map(|s| s.to_string());
+        }
+        for meaning in entry["meanings"].as_array().into_iter().flatten() {
+            let part_of_speech = meaning["partOfSpeech"].as_str().unwrap_or_default();
+            for definition in meaning["definitions"].as_array().into_iter().flatten() {
+                let Some(text) = definition["definition"].as_str() else {
+                    continue;
+                };
+                result.senses.push(Sense {
+                    part_of_speech: part_of_speech.to_string(),
+                    definition: text.to_string(),
+                    examples: definition["example"]
+                        .as_str()
+                        .map(|e| vec![e.to_string()])
+                        .unwrap_or_default(),
+                });
+                for synonym in definition["synonyms"].as_array().into_iter().flatten() {
+                    result.add_synonym(synonym.as_str().unwrap_or_default());
+                }
+            }
+            for synonym in meaning["synonyms"].as_array().into_iter().flatten() {
+                result.add_synonym(synonym.as_str().unwrap_or_default());
+            }
+        }
+    }
+    result
+}
+
+fn strip_html(html: &str) -> String {
+    HTML_TAG.replace_all(html, "").trim().to_string()
+}
+
+/// Wiktionary's REST API groups entries by language code
+fn parse_wiktionary(word: &str, language: &str, body: &Value) -> WordDefinition {
+    let mut result = WordDefinition::new(word, language, "wiktionary");
+    for entry in body[language].as_array().into_iter().flatten() {
+        let part_of_speech = entry["partOfSpeech"]
+            .as_str()
+            .unwrap_or_default()
+            .to_lowercase();
+        for definition in entry["definitions"].as_array().into_iter().flatten() {
+            let text = strip_html(definition["definition"].as_str().unwrap_or_default());
+            if text.is_empty() {
+                continue;
+            }
+            result.senses.push(Sense {
+                part_of_speech: part_of_speech.clone(),
+                definition: text,
+                examples: definition["examples"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|e| e.as_str().map(strip_html))
+                    .collect(),
+            });
+        }
+    }
+    result
+}
+
+async fn lookup_online(word: &str, language: &str) -> Result<WordDefinition, String> {
+    let client = reqwest::Client::new();
+
+    if language == "en" {
+        let url = format!(
+            "https://api.dictionaryapi.dev/api/v2/entries/en/{}",
+            urlencoding::encode(word)
+        );
+        let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+        if response.status().is_success() {
+            let body: Value = response.json().await.map_err(|e| e.to_string())?;
+            let result = parse_dictionaryapi(word, &body);
+            if !result.is_empty() {
+                return Ok(result);
+            }
+        }
+    }
+
+    let url = format!(
+        "https://en.wiktionary.org/api/rest_v1/page/definition/{}",
+        urlencoding::encode(word)
+    );
+    let response = client
+        .get(&url)
+        .header("User-Agent", "Flare launcher")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("No definition found for '{}'", word));
+    }
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    let result = parse_wiktionary(word, language, &body);
+    if result.is_empty() {
+        return Err(format!("No definition found for '{}'", word));
+    }
+    Ok(result)
+}
+
+/// Offline sources first (wiktextract dump, then WordNet for English), online as fallback
+async fn lookup(
+    app: &AppHandle,
+    word: Option<String>,
+    language: Option<String>,
+) -> Result<WordDefinition, String> {
+    // Called from the selected-text action menu without an explicit word
+    let word = match word {
+        Some(word) => word,
+        None => tauri::async_runtime::spawn_blocking(selection::get_text)
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+    let word = word.trim().to_string();
+    if word.is_empty() {
+        return Err("No word given".to_string());
+    }
+    let language = language.unwrap_or_else(|| "en".to_string());
+
+    if let Some(path) = wiktextract_path(app, &language) {
+        let (w, l) = (word.clone(), language.clone());
+        let result =
+            tauri::async_runtime::spawn_blocking(move || lookup_wiktextract(&path, &w, &l))
+                .await
+                .map_err(|e| e.to_string())?;
+        if let Some(result) = result {
+            return Ok(result);
+        }
+    }
+
+    if language == "en" {
+        let w = word.clone();
+        let result = tauri::async_runtime::spawn_blocking(move || lookup_wordnet(&w))
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(result) = result {
+            return Ok(result);
+        }
+    }
+
+    lookup_online(&word, &language).await
+}
+
+#[tauri::command]
+pub async fn define_word(
+    app: AppHandle,
+    word: Option<String>,
+    language: Option<String>,
+) -> Result<WordDefinition, String> {
+    lookup(&app, word, language).await
+}
+
+#[tauri::command]
+pub async fn synonyms(
+    app: AppHandle,
+    word: Option<String>,
+    language: Option<String>,
+) -> Result<Vec<String>, String> {
+    Ok(lookup(&app, word, language).await?.synonyms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wordnet_overview() {
+        let output = r#"
+Overview of noun dog
+
+The noun dog has 2 senses (first 1 from tagged texts)
+
+1. (42) dog, domestic dog, Canis familiaris -- (a member of the genus Canis; "the dog barked all night")
+2. frump, dog -- (a dull unattractive unpleasant girl or woman; "she got a reputation as a frump"; "she's a real dog")
+
+Overview of verb dog
+
+The verb dog has 1 sense (no senses from tagged texts)
+
+1. chase, chase after, trail, tail, tag, give chase, dog, go after, track -- (go after with the intent to catch)
+"#;
+        let result = parse_wordnet_overview("dog", output);
+        assert_eq!(result.senses.len(), 3);
+        assert_eq!(result.senses[0].part_of_speech, "noun");
+        assert_eq!(result.senses[0].definition, "a member of the genus Canis");
+        assert_eq!(result.senses[1].examples.len(), 2);
+        assert_eq!(result.senses[2].part_of_speech, "verb");
+        assert!(result.synonyms.contains(&"domestic dog".to_string()));
+        assert!(result.synonyms.contains(&"chase".to_string()));
+        assert!(!result.synonyms.contains(&"dog".to_string()));
+    }
+
+    #[test]
+    fn test_parse_wiktextract_entry() {
+        let line = r#"{"word": "chien", "pos": "noun", "lang_code": "fr", "sounds": [{"ipa": "/ʃjɛ̃/"}], "senses": [{"glosses": ["dog"], "examples": [{"text": "Le chien aboie."}]}], "synonyms": [{"word": "toutou"}]}"#;
+        let mut result = WordDefinition::new("chien", "fr", "wiktextract");
+        parse_wiktextract_entry(line, &mut result);
+        parse_wiktextract_entry(r#"{"word": "chienne", "senses": []}"#, &mut result);
+
+        assert_eq!(result.phonetic.as_deref(), Some("/ʃjɛ̃/"));
+        assert_eq!(result.senses.len(), 1);
+        assert_eq!(result.senses[0].examples, vec!["Le chien aboie."]);
+        assert_eq!(result.synonyms, vec!["toutou"]);
+    }
+
+    #[test]
+    fn test_parse_wiktionary_strips_html() {
+        let body: Value = serde_json::json!({
+            "fr": [{
+                "partOfSpeech": "Noun",
+                "definitions": [{"definition": "<a href=\"/wiki/dog\">dog</a>", "examples": ["<i>le chien</i>"]}]
+            }]
+        });
+        let result = parse_wiktionary("chien", "fr", &body);
+        assert_eq!(result.senses[0].definition, "dog");
+        assert_eq!(result.senses[0].part_of_speech, "noun");
+        assert_eq!(result.senses[0].examples, vec!["le chien"]);
+    }
+}
@@ -0,0 +1,600 @@
+//! Offline API documentation from DevDocs.
+//!
+//! A docset's `index.json` (entry names and paths) and `db.json` (the HTML of
+//! every page) are downloaded once and stored in `docs.sqlite`, so searching
+//! and reading works without a connection. Entry paths may carry an anchor
+//! (`array#method-map`) pointing inside the page.
+
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Result as RusqliteResult};
+use serde::de::{Deserializer as _, Error as _, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+const DOCS_LIST_URL: &str = "https://devdocs.io/docs.json";
+const DOCUMENTS_URL: &str = "https://documents.devdocs.io";
+const DEVDOCS_URL: &str = "https://devdocs.io";
+
+const DOCSETS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS docsets (
+    slug TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    version TEXT,
+    release TEXT,
+    mtime INTEGER NOT NULL,
+    entry_count INTEGER NOT NULL,
+    installed_at INTEGER NOT NULL
+)";
+const ENTRIES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS doc_entries (
+    docset TEXT NOT NULL,
+    name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    entry_type TEXT
+)";
+const ENTRIES_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS idx_doc_entries_docset ON doc_entries (docset)";
+const PAGES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS doc_pages (
+    docset TEXT NOT NULL,
+    path TEXT NOT NULL,
+    html TEXT NOT NULL,
+    PRIMARY KEY (docset, path)
+)";
+
+const DOCS_LIST_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+/// Candidates pulled from SQLite before ranking
+const CANDIDATE_LIMIT: i64 = 2000;
+
+/// The DevDocs list, with when it was fetched
+type DocsList = (Instant, Vec<DocsetInfo>);
+
+static DOCS_LIST: Lazy<Mutex<Option<DocsList>>> = Lazy::new(|| Mutex::new(None));
+
+/// A docset as listed in DevDocs' `docs.json`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DocsetInfo {
+    pub slug: String,
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub release: Option<String>,
+    /// DevDocs' build timestamp, also used to bust its document cache
+    #[serde(default)]
+    pub mtime: i64,
+    #[serde(default)]
+    pub db_size: Option<u64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledDocset {
+    pub slug: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub release: Option<String>,
+    pub mtime: i64,
+    pub entry_count: i64,
+    pub installed_at: DateTime<Utc>,
+}
+
+impl Storable for InstalledDocset {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let installed_at_ts: i64 = row.get(6)?;
+        Ok(InstalledDocset {
+            slug: row.get(0)?,
+            name: row.get(1)?,
+            version: row.get(2)?,
+            release: row.get(3)?,
+            mtime: row.get(4)?,
+            entry_count: row.get(5)?,
+            installed_at: DateTime::from_timestamp(installed_at_ts, 0).unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DocMatch {
+    pub docset: String,
+    pub name: String,
+    /// Page path without the anchor, as passed to `docs_get_page`
+    pub path: String,
+    pub anchor: Option<String>,
+    pub entry_type: Option<String>,
+    /// The same entry on devdocs.io
+    pub url: String,
+}
+
+struct EntryRow {
+    name: String,
+    path: String,
+    entry_type: Option<String>,
+}
+
+impl Storable for EntryRow {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        Ok(EntryRow {
+            name: row.get(0)?,
+            path: row.get(1)?,
+            entry_type: row.get(2)?,
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct InstallProgress<'a> {
+    slug: &'a str,
+    stage: &'a str,
+}
+
+/// `array#method-map` -> (`array`, `Some("method-map")`)
+fn split_anchor(path: &str) -> (&str, Option<&str>) {
+    match path.split_once('#') {
+        Some((page, anchor)) if !anchor.is_empty() => (page, Some(anchor)),
+        Some((page, _)) => (page, None),
+        None => (path, None),
+    }
+}
+
+/// Lower is better: exact, prefix, word start, then anywhere in the name
+fn rank(name: &str, query: &str) -> Option<u8> {
+    let name = name.to_lowercase();
+    if name == query {
+        return Some(0);
+    }
+    if name.starts_with(query) {
+        return Some(1);
+    }
+    let position = name.find(query)?;
+    let word_start = name[..position]
+        .chars()
+        .next_back()
+        .is_some_and(|c| !c.is_alphanumeric());
+    Some(if word_start { 2 } else { 3 })
+}
+
+fn escape_like(query: &str) -> String {
+    query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// A docset's `index.json`
+#[derive(Deserialize, Debug, Default)]
+pub struct DocIndex {
+    #[serde(default)]
+    entries: Vec<IndexEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct IndexEntry {
+    name: Option<String>,
+    path: Option<String>,
+    #[serde(rename = "type")]
+    entry_type: Option<String>,
+}
+
+/// Inserts each `path: html` pair of `db.json` as it is parsed, so the pages
+/// of large docsets are never all held at once
+struct PageInserter<'a> {
+    slug: &'a str,
+    insert: rusqlite::Statement<'a>,
+}
+
+impl<'de> Visitor<'de> for PageInserter<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object of page paths to HTML")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        while let Some((path, html)) = map.next_entry::<String, serde_json::Value>()? {
+            if let Some(html) = html.as_str() {
+                self.insert
+                    .execute(params![self.slug, path, html])
+                    .map_err(A::Error::custom)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct DocsManager {
+    store: Store,
+}
+
+impl DocsManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        Self::with_store(Store::new(app_handle, "docs.sqlite")?)
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        Self::with_store(Store::new_in_memory()?)
+    }
+
+    fn with_store(store: Store) -> Result<Self, AppError> {
+        store.init_table(DOCSETS_SCHEMA)?;
+        store.init_table(ENTRIES_SCHEMA)?;
+        store.init_table(ENTRIES_INDEX)?;
+        store.init_table(PAGES_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    /// Replaces any installed copy of the docset with `index` and the pages
+    /// in `db`, which is read page by page rather than parsed up front
+    pub fn install(&self, info: &DocsetInfo, index: &DocIndex, db: &[u8]) -> Result<(), AppError> {
+        let mut conn = self.store.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM doc_entries WHERE docset = ?",
+            params![info.slug],
+        )?;
+        tx.execute("DELETE FROM doc_pages WHERE docset = ?", params![info.slug])?;
+
+        let mut entry_count = 0i64;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO doc_entries (docset, name, path, entry_type) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for entry in &index.entries {
+                let (Some(name), Some(path)) = (&entry.name, &entry.path) else {
+                    continue;
+                };
+                insert.execute(params![info.slug, name, path, entry.entry_type])?;
+                entry_count += 1;
+            }
+        }
+        {
+            let insert = tx.prepare(
+                "INSERT OR REPLACE INTO doc_pages (docset, path, html) VALUES (?1, ?2, ?3)",
+            )?;
+            let mut deserializer = serde_json::Deserializer::from_slice(db);
+            deserializer
+                .deserialize_map(PageInserter {
+                    slug: &info.slug,
+                    insert,
+                })
+                .map_err(|e| AppError::Serialization(format!("Docset db: {}", e)))?;
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO docsets (slug, name, version, release, mtime, entry_count, installed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                info.slug,
+                info.name,
+                info.version,
+                info.release,
+                info.mtime,
+                entry_count,
+                Utc::now().timestamp()
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn uninstall(&self, slug: &str) -> Result<(), AppError> {
+        let mut conn = self.store.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM doc_entries WHERE docset = ?", params![slug])?;
+        tx.execute("DELETE FROM doc_pages WHERE docset = ?", params![slug])?;
+        tx.execute("DELETE FROM docsets WHERE slug = ?", params![slug])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn installed(&self) -> Result<Vec<InstalledDocset>, AppError> {
+        self.store.query(
+            "SELECT slug, name, version, release, mtime, entry_count, installed_at
+             FROM docsets ORDER BY name",
+            [],
+        )
+    }
+
+    pub fn search(
+        &self,
+        docset: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<DocMatch>, AppError> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let candidates: Vec<EntryRow> = self.store.query(
+            "SELECT name, path, entry_type FROM doc_entries
+             WHERE docset = ?1 AND name LIKE ?2 ESCAPE '\\' LIMIT ?3",
+            params![
+                docset,
+                format!("%{}%", escape_like(&query)),
+                CANDIDATE_LIMIT
+            ],
+        )?;
+
+        let mut ranked: Vec<(u8, EntryRow)> = candidates
+            .into_iter()
+            .filter_map(|entry| Some((rank(&entry.name, &query)?, entry)))
+            .collect();
+        ranked.sort_by(|(a_rank, a), (b_rank, b)| {
+            a_rank
+                .cmp(b_rank)
+                .then(a.name.len().cmp(&b.name.len()))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        Ok(ranked
+            .into_iter()
+            .take(limit)
+            .map(|(_, entry)| {
+                let (page, anchor) = split_anchor(&entry.path);
+                DocMatch {
+                    docset: docset.to_string(),
+                    name: entry.name.clone(),
+                    path: page.to_string(),
+                    anchor: anchor.map(String::from),
+                    entry_type: entry.entry_type.clone(),
+                    url: format!("{}/{}/{}", DEVDOCS_URL, docset, entry.path),
+                }
+            })
+            .collect())
+    }
+
+    pub fn page(&self, docset: &str, path: &str) -> Result<Option<String>, AppError> {
+        let (page, _) = split_anchor(path);
+        let conn = self.store.conn();
+        let mut stmt =
+            conn.prepare("SELECT html FROM doc_pages WHERE docset = ?1 AND path = ?2")?;
+        let mut rows = stmt.query(params![docset, page])?;
+        Ok(match rows.next()? {
+            Some(row) => Some(row.get(0)?),
+            None => None,
+        })
+    }
+}
+
+fn http_client(timeout: Duration) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .user_agent("Flare launcher")
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+async fn get_json(client: &reqwest::Client, url: &str) -> Result<Value, String> {
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("DevDocs error: {} for {}", response.status(), url));
+    }
+    response.json().await.map_err(|e| e.to_string())
+}
+
+async fn get_bytes(client: &reqwest::Client, url: &str) -> Result<bytes::Bytes, String> {
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("DevDocs error: {} for {}", response.status(), url));
+    }
+    response.bytes().await.map_err(|e| e.to_string())
+}
+
+async fn available_docsets() -> Result<Vec<DocsetInfo>, String> {
+    if let Some((fetched_at, docsets)) = DOCS_LIST.lock().unwrap().as_ref() {
+        if fetched_at.elapsed() < DOCS_LIST_TTL {
+            return Ok(docsets.clone());
+        }
+    }
+    let client = http_client(Duration::from_secs(15))?;
+    let docsets: Vec<DocsetInfo> = serde_json::from_value(get_json(&client, DOCS_LIST_URL).await?)
+        .map_err(|e| e.to_string())?;
+    *DOCS_LIST.lock().unwrap() = Some((Instant::now(), docsets.clone()));
+    Ok(docsets)
+}
+
+#[tauri::command]
+pub async fn docs_list_available() -> Result<Vec<DocsetInfo>, String> {
+    available_docsets().await
+}
+
+#[tauri::command]
+pub fn docs_list_installed(
+    manager: State<'_, DocsManager>,
+) -> Result<Vec<InstalledDocset>, String> {
+    manager.installed().map_err(|e| e.to_string())
+}
+
+/// Downloads (or updates) a docset, emitting `docs-install-progress` along the way
+#[tauri::command]
+pub async fn docs_install(
+    app: AppHandle,
+    manager: State<'_, DocsManager>,
+    slug: String,
+) -> Result<InstalledDocset, String> {
+    let info = available_docsets()
+        .await?
+        .into_iter()
+        .find(|docset| docset.slug == slug)
+        .ok_or_else(|| format!("Unknown docset: {}", slug))?;
+
+    let progress = |stage: &str| {
+        let _ = app.emit(
+            "docs-install-progress",
+            InstallProgress {
+                slug: &info.slug,
+                stage,
+            },
+        );
+    };
+
+    let client = http_client(DOWNLOAD_TIMEOUT)?;
+    progress("index");
+    let index: DocIndex = serde_json::from_slice(
+        &get_bytes(
+            &client,
+            &format!("{}/{}/index.json?{}", DOCUMENTS_URL, info.slug, info.mtime),
+        )
+        .await?,
+    )
+    .map_err(|e| e.to_string())?;
+    progress("pages");
+    let db = get_bytes(
+        &client,
+        &format!("{}/{}/db.json?{}", DOCUMENTS_URL, info.slug, info.mtime),
+    )
+    .await?;
+
+    progress("storing");
+    let handle = app.clone();
+    let docset = info.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        handle.state::<DocsManager>().install(&docset, &index, &db)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    progress("done");
+
+    manager
+        .installed()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|docset| docset.slug == info.slug)
+        .ok_or_else(|| "Docset was not saved".to_string())
+}
+
+#[tauri::command]
+pub fn docs_uninstall(manager: State<'_, DocsManager>, slug: String) -> Result<(), String> {
+    manager.uninstall(&slug).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn search_docs(
+    manager: State<'_, DocsManager>,
+    docset: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<DocMatch>, String> {
+    manager
+        .search(&docset, &query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .map_err(|e| e.to_string())
+}
+
+/// HTML of an installed page; an anchor in `path` is ignored
+#[tauri::command]
+pub fn docs_get_page(
+    manager: State<'_, DocsManager>,
+    docset: String,
+    path: String,
+) -> Result<String, String> {
+    manager
+        .page(&docset, &path)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Page not found: {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn install_rust(manager: &DocsManager) {
+        let info = DocsetInfo {
+            slug: "rust".to_string(),
+            name: "Rust".to_string(),
+            version: None,
+            release: Some("1.84.0".to_string()),
+            mtime: 1736960400,
+            db_size: None,
+        };
+        let index: DocIndex = serde_json::from_value(json!({ "entries": [
+            { "name": "std::vec::Vec", "path": "std/vec/struct.vec", "type": "std::vec" },
+            { "name": "Vec::push", "path": "std/vec/struct.vec#method.push", "type": "std::vec" },
+            { "name": "std::vec", "path": "std/vec/index", "type": "std::vec" },
+            { "name": "VecDeque", "path": "std/collections/struct.vecdeque", "type": "collections" },
+            { "name": "broken" }
+        ]}))
+        .unwrap();
+        let db = json!({
+            "std/vec/struct.vec": "<h1>Struct std::vec::Vec</h1>",
+            "std/vec/index": "<h1>Module std::vec</h1>"
+        });
+        manager
+            .install(&info, &index, db.to_string().as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_split_anchor() {
+        assert_eq!(split_anchor("a/b#c"), ("a/b", Some("c")));
+        assert_eq!(split_anchor("a/b#"), ("a/b", None));
+        assert_eq!(split_anchor("a/b"), ("a/b", None));
+    }
+
+    #[test]
+    fn test_rank() {
+        assert_eq!(rank("Vec", "vec"), Some(0));
+        assert_eq!(rank("VecDeque", "vec"), Some(1));
+        assert_eq!(rank("std::vec", "vec"), Some(2));
+        assert_eq!(rank("SmallVec", "vec"), Some(3));
+        assert_eq!(rank("String", "vec"), None);
+    }
+
+    #[test]
+    fn test_install_and_search() {
+        let manager = DocsManager::new_for_test().unwrap();
+        install_rust(&manager);
+
+        let installed = manager.installed().unwrap();
+        assert_eq!(installed.len(), 1);
+        assert_eq!(installed[0].entry_count, 4);
+
+        let matches = manager.search("rust", "push", 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, "std/vec/struct.vec");
+        assert_eq!(matches[0].anchor.as_deref(), Some("method.push"));
+        assert_eq!(
+            matches[0].url,
+            "https://devdocs.io/rust/std/vec/struct.vec#method.push"
+        );
+
+        let matches = manager.search("rust", "vec", 10).unwrap();
+        assert_eq!(matches[0].name, "VecDeque");
+        assert_eq!(matches[1].name, "Vec::push");
+        assert!(manager.search("rust", "100%", 10).unwrap().is_empty());
+        assert!(manager.search("python", "vec", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pages_and_uninstall() {
+        let manager = DocsManager::new_for_test().unwrap();
+        install_rust(&manager);
+        // Reinstalling replaces rather than duplicates
+        install_rust(&manager);
+        assert_eq!(manager.search("rust", "vecdeque", 10).unwrap().len(), 1);
+
+        assert_eq!(
+            manager
+                .page("rust", "std/vec/struct.vec#method.push")
+                .unwrap()
+                .as_deref(),
+            Some("<h1>Struct std::vec::Vec</h1>")
+        );
+        assert_eq!(manager.page("rust", "missing").unwrap(), None);
+
+        manager.uninstall("rust").unwrap();
+        assert!(manager.installed().unwrap().is_empty());
+        assert_eq!(manager.page("rust", "std/vec/index").unwrap(), None);
+    }
+}
@@ -0,0 +1,305 @@
+//! Dragging results out of the launcher into other apps. The frontend calls
+//! `start_drag` once the pointer has moved far enough with the button held,
+//! and GTK takes the drag over from there as a native drag source offering
+//! a file (`text/uri-list`), text, or both.
+//!
+//! Text items dragged as files are written first to a directory of their
+//! own under `$XDG_RUNTIME_DIR/flare-drag`, or the app cache directory
+//! without one, readable only by the user. It's removed once the drag ends.
+
+use crate::clipboard_history::manager::MANAGER;
+use crate::clipboard_history::types::ContentType;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tauri::{Manager, WebviewWindow};
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum DragItem {
+    /// Images and files always go as files; text only when `as_file` is set
+    HistoryItem {
+        id: i64,
+        #[serde(default)]
+        as_file: bool,
+    },
+    /// A file search result
+    File { path: String },
+}
+
+/// What the drop target gets offered
+#[derive(Clone, Debug, Default, PartialEq)]
+struct DragPayload {
+    text: Option<String>,
+    uri: Option<String>,
+    /// Directory written for this drag, removed when it ends
+    export: Option<PathBuf>,
+}
+
+fn remove_export(export: Option<&Path>) {
+    if let Some(dir) = export {
+        if let Err(e) = std::fs::remove_dir_all(dir) {
+            tracing::debug!(error = %e, path = %dir.display(), "Failed to remove drag export");
+        }
+    }
+}
+
+fn file_uri(path: &Path) -> Result<String, String> {
+    let path =
+        std::fs::canonicalize(path).map_err(|e| format!("Can't drag {}: {}", path.display(), e))?;
+    url::Url::from_file_path(&path)
+        .map(String::from)
+        .map_err(|_| format!("Can't drag {}", path.display()))
+}
+
+fn export_root(window: &WebviewWindow) -> Result<PathBuf, String> {
+    let base = match dirs::runtime_dir() {
+        Some(dir) => dir,
+        None => window
+            .app_handle()
+            .path()
+            .app_cache_dir()
+            .map_err(|e| e.to_string())?,
+    };
+    Ok(base.join("flare-drag"))
+}
+
+/// Writes `content` to a new file named after history item `id`, in a fresh
+/// directory under `root`. Returns the directory and the file.
+fn export_text(root: &Path, id: i64, content: &str) -> Result<(PathBuf, PathBuf), String> {
+    use std::io::Write;
+    #[cfg(unix)]
+    use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+
+    let mut dirs = std::fs::DirBuilder::new();
+    dirs.recursive(true);
+    #[cfg(unix)]
+    dirs.mode(0o700);
+    dirs.create(root).map_err(|e| e.to_string())?;
+    let dir = root.join(uuid::Uuid::new_v4().simple().to_string());
+    dirs.recursive(false);
+    dirs.create(&dir).map_err(|e| e.to_string())?;
+
+    let path = dir.join(format!("clipboard-item-{}.txt", id));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let written = options
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()));
+    if let Err(e) = written {
+        let _ = std::fs::remove_dir_all(&dir);
+        return Err(e.to_string());
+    }
+    Ok((dir, path))
+}
+
+/// `export_root` is where text dragged as a file is written
+fn history_payload(
+    export_root: &Path,
+    id: i64,
+    content_type: ContentType,
+    content: String,
+    as_file: bool,
+) -> Result<DragPayload, String> {
+    Ok(match content_type {
+        ContentType::Image | ContentType::File => DragPayload {
+            uri: Some(file_uri(Path::new(&content))?),
+            ..Default::default()
+        },
+        // Browsers and file managers take a dropped link as a URI
+        ContentType::Link if !as_file => DragPayload {
+            uri: Some(content.clone()),
+            text: Some(content),
+            export: None,
+        },
+        ContentType::Text | ContentType::Color | ContentType::Link if as_file => {
+            let (dir, path) = export_text(export_root, id, &content)?;
+            let uri = file_uri(&path).inspect_err(|_| {
+                let _ = std::fs::remove_dir_all(&dir);
+            })?;
+            DragPayload {
+                uri: Some(uri),
+                text: Some(content),
+                export: Some(dir),
+            }
+        }
+        ContentType::Text | ContentType::Color | ContentType::Link => DragPayload {
+            text: Some(content),
+            ..Default::default()
+        },
+    })
+}
+
+fn payload_for(window: &WebviewWindow, item: &DragItem) -> Result<DragPayload, String> {
+    match item {
+        DragItem::HistoryItem { id, as_file } => {
+            let (content_type, content) = {
+                let guard = MANAGER.lock().unwrap();
+                let manager = guard
+                    .as_ref()
+                    .ok_or("Clipboard history manager not initialized")?;
+                manager.get_item_with_type(*id).map_err(|e| e.to_string())?
+            };
+            history_payload(&export_root(window)?, *id, content_type, content, *as_file)
+        }
+        DragItem::File { path } => Ok(DragPayload {
+            uri: Some(file_uri(Path::new(path))?),
+            text: Some(path.clone()),
+            export: None,
+        }),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn begin(window: &WebviewWindow, payload: DragPayload) -> Result<(), String> {
+    use gtk::glib::ObjectExt;
+    use gtk::prelude::WidgetExt;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const INFO_URIS: u32 = 1;
+    const INFO_TEXT: u32 = 2;
+
+    let target = window.clone();
+    window
+        .run_on_main_thread(move || {
+            let Ok(gtk_window) = target.gtk_window() else {
+                return;
+            };
+            let targets = gtk::TargetList::new(&[]);
+            if payload.uri.is_some() {
+                targets.add_uri_targets(INFO_URIS);
+            }
+            if payload.text.is_some() {
+                targets.add_text_targets(INFO_TEXT);
+            }
+
+            // Connected for this drag only, and dropped when it ends
+            let payload = Rc::new(payload);
+            let handlers = Rc::new(RefCell::new(Vec::new()));
+            let data = payload.clone();
+            handlers.borrow_mut().push(gtk_window.connect_drag_data_get(
+                move |_, _, selection, info, _| match info {
+                    INFO_URIS => {
+                        if let Some(uri) = &data.uri {
+                            selection.set_uris(&[uri.as_str()]);
+                        }
+                    }
+                    _ => {
+                        if let Some(text) = &data.text {
+                            selection.set_text(text);
+                        }
+                    }
+                },
+            ));
+            let ended = handlers.clone();
+            let finished = payload.clone();
+            handlers
+                .borrow_mut()
+                .push(gtk_window.connect_drag_end(move |window, _| {
+                    for handler in ended.borrow_mut().drain(..) {
+                        window.disconnect(handler);
+                    }
+                    remove_export(finished.export.as_deref());
+                }));
+
+            let event = gtk::current_event();
+            if gtk_window
+                .drag_begin_with_coordinates(
+                    &targets,
+                    gtk::gdk::DragAction::COPY,
+                    1,
+                    event.as_ref(),
+                    -1,
+                    -1,
+                )
+                .is_none()
+            {
+                tracing::warn!("GTK refused to start the drag");
+                for handler in handlers.borrow_mut().drain(..) {
+                    gtk_window.disconnect(handler);
+                }
+                remove_export(payload.export.as_deref());
+            }
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn begin(_window: &WebviewWindow, _payload: DragPayload) -> Result<(), String> {
+    Err("Dragging out of the launcher is only supported on Linux".to_string())
+}
+
+#[tauri::command]
+pub fn start_drag(window: WebviewWindow, item: DragItem) -> Result<(), String> {
+    let payload = payload_for(&window, &item)?;
+    let export = payload.export.clone();
+    begin(&window, payload).inspect_err(|_| remove_export(export.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drag_item_from_frontend() {
+        let item: DragItem = serde_json::from_str(r#"{"kind": "historyItem", "id": 4}"#).unwrap();
+        assert_eq!(
+            item,
+            DragItem::HistoryItem {
+                id: 4,
+                as_file: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_history_payload() {
+        let root = std::env::temp_dir().join(format!("flare-drag-{}", rand::random::<u32>()));
+        let link = history_payload(
+            &root,
+            1,
+            ContentType::Link,
+            "https://example.com".to_string(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(link.uri.as_deref(), Some("https://example.com"));
+
+        let text =
+            history_payload(&root, 2, ContentType::Text, "hello".to_string(), false).unwrap();
+        assert_eq!(
+            text,
+            DragPayload {
+                text: Some("hello".to_string()),
+                ..Default::default()
+            }
+        );
+
+        let file = history_payload(&root, 3, ContentType::Text, "hello".to_string(), true).unwrap();
+        let again = history_payload(&root, 3, ContentType::Text, "bye".to_string(), true).unwrap();
+        let path = url::Url::parse(file.uri.as_deref().unwrap())
+            .unwrap()
+            .to_file_path()
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello");
+        assert_eq!(path.file_name().unwrap(), "clipboard-item-3.txt");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert_ne!(file.export, again.export);
+
+        remove_export(file.export.as_deref());
+        assert!(!path.exists());
+        assert!(again.export.as_ref().unwrap().exists());
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
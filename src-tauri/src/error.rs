@@ -11,6 +11,10 @@ pub enum AppError {
     Frecency(String),
     FileSearch(String),
     Ai(String),
+    WindowManagement(String),
+    Translate(String),
+    ExtensionStorage(String),
+    Secrets(String),
 }
 
 impl From<io::Error> for AppError {
@@ -55,6 +59,10 @@ impl std::fmt::Display for AppError {
             AppError::Frecency(msg) => write!(f, "Frecency error: {}", msg),
             AppError::FileSearch(msg) => write!(f, "File search error: {}", msg),
             AppError::Ai(msg) => write!(f, "AI error: {}", msg),
+            AppError::WindowManagement(msg) => write!(f, "Window management error: {}", msg),
+            AppError::Translate(msg) => write!(f, "Translation error: {}", msg),
+            AppError::ExtensionStorage(msg) => write!(f, "Extension storage error: {}", msg),
+            AppError::Secrets(msg) => write!(f, "Secrets error: {}", msg),
         }
     }
 }
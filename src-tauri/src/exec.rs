@@ -0,0 +1,210 @@
+//! Running the external tools that state is read from (nmcli, rfkill,
+//! gsettings, nvidia-smi, ...). The UI polls a lot of that state, so these
+//! calls share a few limits: every run has a timeout and is killed past it,
+//! only `MAX_CONCURRENT` run at once, and reads go through `cached`, which
+//! answers from the last result while it is fresh. Callers asking for the
+//! same command while it runs wait for that run instead of starting another.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_CONCURRENT: usize = 8;
+const WAIT_STEP: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Output {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+type Key = Vec<String>;
+type Slot = Arc<Mutex<Option<(Instant, Result<Output, String>)>>>;
+
+static RUNNING: Lazy<(Mutex<usize>, Condvar)> = Lazy::new(|| (Mutex::new(0), Condvar::new()));
+static CACHE: Lazy<Mutex<HashMap<Key, Slot>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A place among the `MAX_CONCURRENT` running commands, given back on drop
+struct Permit;
+
+impl Permit {
+    fn acquire() -> Self {
+        let (running, freed) = &*RUNNING;
+        let mut running = freed
+            .wait_while(running.lock().unwrap(), |running| {
+                *running >= MAX_CONCURRENT
+            })
+            .unwrap();
+        *running += 1;
+        Permit
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let (running, freed) = &*RUNNING;
+        *running.lock().unwrap() -= 1;
+        freed.notify_one();
+    }
+}
+
+fn key(program: &str, args: &[&str]) -> Key {
+    std::iter::once(program)
+        .chain(args.iter().copied())
+        .map(str::to_string)
+        .collect()
+}
+
+fn read_all(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        String::from_utf8_lossy(&buffer).into_owned()
+    })
+}
+
+/// Runs `program`, killing it after `timeout`. Blocks, also while waiting
+/// for a free place.
+pub fn run_with_timeout(program: &str, args: &[&str], timeout: Duration) -> Result<Output, String> {
+    let _permit = Permit::acquire();
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    // Read while waiting, so a chatty command can't fill the pipe and stall
+    let stdout = read_all(child.stdout.take());
+    let stderr = read_all(child.stderr.take());
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() < timeout => thread::sleep(WAIT_STEP),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{} took longer than {:?}", program, timeout));
+            }
+            Err(e) => return Err(format!("Failed to wait for {}: {}", program, e)),
+        }
+    };
+    Ok(Output {
+        success: status.success(),
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+pub fn run(program: &str, args: &[&str]) -> Result<Output, String> {
+    run_with_timeout(program, args, DEFAULT_TIMEOUT)
+}
+
+/// The last result of the command if it is younger than `ttl`, otherwise a
+/// new run. Failures to start are kept too, so a missing tool is not looked
+/// for on every poll.
+pub fn cached(program: &str, args: &[&str], ttl: Duration) -> Result<Output, String> {
+    let slot = CACHE
+        .lock()
+        .unwrap()
+        .entry(key(program, args))
+        .or_default()
+        .clone();
+    // Held while running, which makes concurrent callers wait for this run
+    let mut slot = slot.lock().unwrap();
+    if let Some((ran_at, result)) = slot.as_ref() {
+        if ran_at.elapsed() < ttl {
+            return result.clone();
+        }
+    }
+    let result = run(program, args);
+    *slot = Some((Instant::now(), result.clone()));
+    result
+}
+
+/// Forgets the cached results of `program`, e.g. after changing what it
+/// reports
+pub fn invalidate(program: &str) {
+    CACHE
+        .lock()
+        .unwrap()
+        .retain(|key, _| key.first().is_none_or(|cached| cached != program));
+}
+
+/// Forgets every cached result, e.g. when clearing caches from settings
+pub fn clear() {
+    CACHE.lock().unwrap().clear();
+}
+
+fn owned(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// `run`, off the async runtime's threads
+pub async fn run_async(program: &str, args: &[&str]) -> Result<Output, String> {
+    let (program, args) = (program.to_string(), owned(args));
+    tauri::async_runtime::spawn_blocking(move || {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        run(&program, &args)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// `cached`, off the async runtime's threads
+pub async fn cached_async(program: &str, args: &[&str], ttl: Duration) -> Result<Output, String> {
+    let (program, args) = (program.to_string(), owned(args));
+    tauri::async_runtime::spawn_blocking(move || {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        cached(&program, &args, ttl)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_captures_output() {
+        let output = run("sh", &["-c", "echo out; echo err >&2; exit 3"]).unwrap();
+        assert!(!output.success);
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+    }
+
+    #[test]
+    fn test_timeout_kills() {
+        let started = Instant::now();
+        let result = run_with_timeout("sleep", &["5"], Duration::from_millis(100));
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_cached_reuses_fresh_results() {
+        // Prints a new value on every run
+        let args = ["-c", "date +%s%N"];
+        let first = cached("sh", &args, Duration::from_secs(60)).unwrap();
+        assert_eq!(cached("sh", &args, Duration::from_secs(60)).unwrap(), first);
+        assert_ne!(cached("sh", &args, Duration::ZERO).unwrap(), first);
+
+        let latest = cached("sh", &args, Duration::from_secs(60)).unwrap();
+        invalidate("sh");
+        assert_ne!(
+            cached("sh", &args, Duration::from_secs(60)).unwrap(),
+            latest
+        );
+    }
+}
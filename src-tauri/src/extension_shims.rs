@@ -1,7 +1,17 @@
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, WebviewWindow};
+use tokio::sync::oneshot;
+
+use crate::exec;
+use crate::json_settings::JsonSettings;
+use crate::process;
+use crate::window_management::{self, geometry::FrameChange};
 
 /// Provides Linux equivalents for macOS-specific APIs used in Raycast extensions
 /// This module helps bridge the gap between macOS and Linux for extension compatibility
@@ -74,45 +84,173 @@ impl PathShim {
     }
 }
 
+/// An AppleScript command with a Linux equivalent
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppCommand {
+    Activate(String),
+    Quit(String),
+    Notify { title: String, message: String },
+    SetVolume(i32),
+    /// The items selected in the Finder, as POSIX paths
+    GetSelection,
+    /// Showing a file selected in its folder
+    Reveal(PathBuf),
+    /// Moving or resizing a window of a process, or the front window
+    SetWindowFrame {
+        process: Option<String>,
+        change: FrameChange,
+    },
+    /// `name of every process`, optionally only those with windows
+    ListProcesses { foreground_only: bool },
+    FrontmostProcess,
+    /// `do shell script "..."`, run with `sh -c`
+    ShellScript(String),
+}
+
+impl AppCommand {
+    pub fn parse(script: &str) -> Option<Self> {
+        if let Some(command) = AppleScriptShim::extract_shell_script(script) {
+            return Some(AppCommand::ShellScript(command));
+        }
+        // Finder scripts usually activate it too, so these come first
+        if let Some(path) = AppleScriptShim::extract_reveal(script) {
+            return Some(AppCommand::Reveal(path));
+        }
+        if AppleScriptShim::is_finder_selection(script) {
+            return Some(AppCommand::GetSelection);
+        }
+        if let Some(command) = AppleScriptShim::extract_system_events(script) {
+            return Some(command);
+        }
+        if let Some(app_name) = AppleScriptShim::extract_activate_app(script) {
+            return Some(AppCommand::Activate(app_name));
+        }
+        if let Some(app_name) = AppleScriptShim::extract_quit_app(script) {
+            return Some(AppCommand::Quit(app_name));
+        }
+        if let Some((title, message)) = AppleScriptShim::extract_notification(script) {
+            return Some(AppCommand::Notify { title, message });
+        }
+        AppleScriptShim::extract_set_volume(script).map(AppCommand::SetVolume)
+    }
+}
+
 /// Provides shims for AppleScript functionality
 pub struct AppleScriptShim;
 
 impl AppleScriptShim {
     /// Attempts to translate and execute common AppleScript commands
     pub fn run_apple_script(script: &str) -> ShimResult {
-        // Parse common AppleScript patterns and translate to Linux equivalents
-        
-        // Pattern: tell application "AppName" to activate
-        if let Some(app_name) = Self::extract_activate_app(script) {
-            return Self::activate_application(&app_name);
-        }
-        
-        // Pattern: tell application "AppName" to quit
-        if let Some(app_name) = Self::extract_quit_app(script) {
-            return Self::quit_application(&app_name);
-        }
-        
-        // Pattern: display notification
-        if let Some((title, message)) = Self::extract_notification(script) {
-            return Self::show_notification(&title, &message);
+        match AppCommand::parse(script) {
+            Some(AppCommand::Activate(app_name)) => Self::activate_application(&app_name),
+            Some(AppCommand::Quit(app_name)) => Self::quit_application(&app_name),
+            Some(AppCommand::Notify { title, message }) => {
+                Self::show_notification(&title, &message)
+            }
+            Some(AppCommand::SetVolume(volume)) => Self::set_system_volume(volume),
+            Some(AppCommand::GetSelection) => Self::get_finder_selection(),
+            Some(AppCommand::Reveal(path)) => Self::reveal(&path),
+            Some(AppCommand::SetWindowFrame { process, change }) => {
+                Self::set_window_frame(process.as_deref(), change)
+            }
+            Some(AppCommand::ListProcesses { foreground_only }) => {
+                Self::list_process_names(foreground_only)
+            }
+            Some(AppCommand::FrontmostProcess) => {
+                Self::shim_output(window_management::frontmost_app_name())
+            }
+            Some(AppCommand::ShellScript(command)) => Self::run_shell_script(&command),
+            // If we can't translate, return an error
+            None => ShimResult {
+                success: false,
+                output: None,
+                error: Some(format!(
+                    "AppleScript not supported on Linux. Script: {}",
+                    script
+                )),
+            },
         }
-        
-        // Pattern: set volume
-        if let Some(volume) = Self::extract_set_volume(script) {
-            return Self::set_system_volume(volume);
+    }
+
+    fn extract_shell_script(script: &str) -> Option<String> {
+        // Match a script that is only: do shell script "..."
+        let pattern = r#"^\s*do shell script\s+"((?:[^"\\]|\\.)*)"\s*$"#;
+        let command = regex::Regex::new(pattern)
+            .ok()?
+            .captures(script)?
+            .get(1)?
+            .as_str()
+            .replace("\\\"", "\"")
+            .replace("\\\\", "\\");
+        Some(command)
+    }
+
+    fn is_finder_selection(script: &str) -> bool {
+        // Match: tell application "Finder" ... selection
+        regex::Regex::new(r#"(?s)tell application "Finder".*\bselection\b"#)
+            .map(|re| re.is_match(script))
+            .unwrap_or(false)
+    }
+
+    fn extract_reveal(script: &str) -> Option<PathBuf> {
+        // Match: reveal POSIX file "/path" or reveal (POSIX file "/path")
+        let pattern = r#"reveal\s+\(?\s*POSIX file "([^"]+)""#;
+        let path = regex::Regex::new(pattern)
+            .ok()?
+            .captures(script)?
+            .get(1)?
+            .as_str()
+            .to_string();
+        Some(PathShim::expand_home(&PathShim::translate_path(&path)))
+    }
+
+    fn extract_system_events(script: &str) -> Option<AppCommand> {
+        let capture = |pattern: &str| regex::Regex::new(pattern).ok()?.captures(script);
+        let number = |caps: &regex::Captures, i: usize| caps.get(i)?.as_str().parse::<i32>().ok();
+
+        // Match: name of first (application) process whose frontmost is true
+        if capture(r"name of (?:the )?first (?:application )?process whose frontmost is true")
+            .is_some()
+        {
+            return Some(AppCommand::FrontmostProcess);
         }
-        
-        // If we can't translate, return an error
-        ShimResult {
-            success: false,
-            output: None,
-            error: Some(format!(
-                "AppleScript not supported on Linux. Script: {}",
-                script
-            )),
+        // Match: name of every (application) process
+        if capture(r"name of every (?:application )?process").is_some() {
+            return Some(AppCommand::ListProcesses {
+                foreground_only: script.contains("background only is false"),
+            });
         }
+
+        // Match: set bounds|position|size of window 1 to {...}
+        let window = r"set (bounds|position|size) of [^{]*?\bwindow\b[^{]*? to \{";
+        let int = r"\s*(-?\d+)\s*";
+        let bounds = capture(&format!("{window}{int},{int},{int},{int}\\}}"))
+            .filter(|caps| &caps[1] == "bounds");
+        let change = if let Some(caps) = bounds {
+            FrameChange::Bounds(
+                number(&caps, 2)?,
+                number(&caps, 3)?,
+                number(&caps, 4)?,
+                number(&caps, 5)?,
+            )
+        } else {
+            let caps = capture(&format!("{window}{int},{int}\\}}"))?;
+            let (a, b) = (number(&caps, 2)?, number(&caps, 3)?);
+            match &caps[1] {
+                "position" => FrameChange::Position(a, b),
+                "size" => FrameChange::Size(a.max(1) as u32, b.max(1) as u32),
+                _ => return None,
+            }
+        };
+
+        // The process is named in System Events, or it's the app told directly
+        let process = capture(r#"process "([^"]+)""#)
+            .or_else(|| capture(r#"tell application "([^"]+)""#))
+            .map(|caps| caps[1].to_string())
+            .filter(|name| name != "System Events");
+        Some(AppCommand::SetWindowFrame { process, change })
     }
-    
+
     fn extract_activate_app(script: &str) -> Option<String> {
         // Match: tell application "AppName" to activate
         let patterns = [
@@ -250,28 +388,21 @@ impl AppleScriptShim {
         let vol = volume.clamp(0, 100);
         
         // Try using pactl (PulseAudio/PipeWire)
-        let output = Command::new("pactl")
-            .arg("set-sink-volume")
-            .arg("@DEFAULT_SINK@")
-            .arg(format!("{}%", vol))
-            .output();
+        let level = format!("{}%", vol);
+        let output = exec::run("pactl", &["set-sink-volume", "@DEFAULT_SINK@", &level]);
         
         match output {
-            Ok(out) if out.status.success() => ShimResult {
+            Ok(out) if out.success => ShimResult {
                 success: true,
                 output: Some(format!("Set volume to {}%", vol)),
                 error: None,
             },
             _ => {
                 // Fallback: try amixer (ALSA)
-                let fallback = Command::new("amixer")
-                    .arg("set")
-                    .arg("Master")
-                    .arg(format!("{}%", vol))
-                    .output();
+                let fallback = exec::run("amixer", &["set", "Master", &level]);
                 
                 match fallback {
-                    Ok(out) if out.status.success() => ShimResult {
+                    Ok(out) if out.success => ShimResult {
                         success: true,
                         output: Some(format!("Set volume to {}%", vol)),
                         error: None,
@@ -285,6 +416,333 @@ impl AppleScriptShim {
             }
         }
     }
+    fn shim_output(result: Result<String, String>) -> ShimResult {
+        match result {
+            Ok(output) => ShimResult {
+                success: true,
+                output: Some(output),
+                error: None,
+            },
+            Err(e) => ShimResult {
+                success: false,
+                output: None,
+                error: Some(e),
+            },
+        }
+    }
+
+    /// Like osascript, the output loses its trailing newline
+    fn run_shell_script(command: &str) -> ShimResult {
+        match Command::new("sh").arg("-c").arg(command).output() {
+            Ok(output) if output.status.success() => ShimResult {
+                success: true,
+                output: Some(
+                    String::from_utf8_lossy(&output.stdout)
+                        .trim_end_matches('\n')
+                        .to_string(),
+                ),
+                error: None,
+            },
+            Ok(output) => ShimResult {
+                success: false,
+                output: None,
+                error: Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+            },
+            Err(e) => ShimResult {
+                success: false,
+                output: None,
+                error: Some(format!("Failed to run the shell script: {}", e)),
+            },
+        }
+    }
+
+    fn set_window_frame(process: Option<&str>, change: FrameChange) -> ShimResult {
+        Self::shim_output(
+            window_management::change_window_frame(process, change)
+                .map(|()| "Window moved".to_string()),
+        )
+    }
+
+    /// AppleScript lists come back comma-separated from osascript
+    fn list_process_names(foreground_only: bool) -> ShimResult {
+        let names = if foreground_only {
+            window_management::window_app_names()
+        } else {
+            Ok(process::process_names(&process::list_processes()))
+        };
+        Self::shim_output(names.map(|names| names.join(", ")))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn get_finder_selection() -> ShimResult {
+        match tauri::async_runtime::block_on(crate::filesystem::file_manager_selection()) {
+            Ok(paths) => ShimResult {
+                success: true,
+                output: Some(
+                    paths
+                        .iter()
+                        .map(|path| path.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                ),
+                error: None,
+            },
+            Err(e) => ShimResult {
+                success: false,
+                output: None,
+                error: Some(format!("Failed to get the file manager selection: {}", e)),
+            },
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn reveal(path: &std::path::Path) -> ShimResult {
+        match tauri::async_runtime::block_on(crate::filesystem::reveal_in_file_manager(path)) {
+            Ok(()) => ShimResult {
+                success: true,
+                output: Some(format!("Revealed {}", path.display())),
+                error: None,
+            },
+            Err(e) => ShimResult {
+                success: false,
+                output: None,
+                error: Some(e),
+            },
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn get_finder_selection() -> ShimResult {
+        ShimResult {
+            success: false,
+            output: None,
+            error: Some("Finder selection is only shimmed on Linux".to_string()),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn reveal(path: &std::path::Path) -> ShimResult {
+        ShimResult {
+            success: false,
+            output: None,
+            error: Some(format!("Can't reveal {} outside Linux", path.display())),
+        }
+    }
+}
+
+/// What a shim call can do on the user's behalf. Each extension is asked
+/// once per capability, unless it was told to ask every time.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ShimCapability {
+    AppleScript,
+    Shell,
+}
+
+impl ShimCapability {
+    pub fn of_script(script: &str) -> Self {
+        match AppCommand::parse(script) {
+            Some(AppCommand::ShellScript(_)) => ShimCapability::Shell,
+            _ => ShimCapability::AppleScript,
+        }
+    }
+}
+
+/// The remembered answers, by extension name
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShimPermissions {
+    #[serde(default)]
+    pub grants: BTreeMap<String, BTreeMap<ShimCapability, bool>>,
+}
+
+impl JsonSettings for ShimPermissions {
+    const FILE_NAME: &'static str = "shim_permissions.json";
+}
+
+impl ShimPermissions {
+    fn decision(&self, extension: &str, capability: ShimCapability) -> Option<bool> {
+        self.grants.get(extension)?.get(&capability).copied()
+    }
+}
+
+const PERMISSION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The window that relays sidecar calls and shows the permission prompts
+const PROMPT_WINDOW: &str = "main";
+
+/// The extension the main window is running, which shim calls act for
+static RUNNING_EXTENSION: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy)]
+struct PermissionAnswer {
+    allow: bool,
+    remember: bool,
+}
+
+static PENDING_PERMISSIONS: Lazy<Mutex<HashMap<String, oneshot::Sender<PermissionAnswer>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PermissionRequest<'a> {
+    request_id: String,
+    /// None for calls that didn't come from a running extension; their
+    /// answer can't be remembered
+    extension: Option<&'a str>,
+    capability: ShimCapability,
+    /// The script, so the user sees what would run
+    detail: &'a str,
+}
+
+/// Asks the frontend; no answer is a no
+async fn ask_permission(
+    app: &AppHandle,
+    extension: Option<&str>,
+    capability: ShimCapability,
+    detail: &str,
+) -> PermissionAnswer {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let (sender, receiver) = oneshot::channel();
+    PENDING_PERMISSIONS
+        .lock()
+        .unwrap()
+        .insert(request_id.clone(), sender);
+
+    let request = PermissionRequest {
+        request_id: request_id.clone(),
+        extension,
+        capability,
+        detail,
+    };
+    if let Err(e) = app.emit_to(PROMPT_WINDOW, "shim-permission-request", request) {
+        tracing::warn!(error = %e, "Failed to ask for shim permission");
+    }
+    let answer = match tokio::time::timeout(PERMISSION_TIMEOUT, receiver).await {
+        Ok(Ok(answer)) => answer,
+        _ => PermissionAnswer {
+            allow: false,
+            remember: false,
+        },
+    };
+    PENDING_PERMISSIONS.lock().unwrap().remove(&request_id);
+    answer
+}
+
+/// Lets the call through if `extension` holds `capability`, asking the user
+/// the first time
+pub async fn authorize(
+    app: &AppHandle,
+    extension: Option<&str>,
+    capability: ShimCapability,
+    detail: &str,
+) -> Result<(), String> {
+    if let Some(extension) = extension {
+        let permissions = ShimPermissions::load(app).map_err(|e| e.to_string())?;
+        match permissions.decision(extension, capability) {
+            Some(true) => return Ok(()),
+            Some(false) => return Err(format!("{} is not allowed to run this script", extension)),
+            None => {}
+        }
+    }
+
+    let answer = ask_permission(app, extension, capability, detail).await;
+    if let (Some(extension), true) = (extension, answer.remember) {
+        let mut permissions = ShimPermissions::load(app).map_err(|e| e.to_string())?;
+        permissions
+            .grants
+            .entry(extension.to_string())
+            .or_default()
+            .insert(capability, answer.allow);
+        permissions.save(app).map_err(|e| e.to_string())?;
+    }
+    tracing::info!(
+        extension = extension.unwrap_or("unknown"),
+        ?capability,
+        allowed = answer.allow,
+        "Shim permission answered"
+    );
+    if answer.allow {
+        Ok(())
+    } else {
+        Err("The user declined to run this script".to_string())
+    }
+}
+
+/// Only the main window runs extensions and answers their prompts;
+/// otherwise another webview could grant itself a capability
+pub fn check_caller(window: &WebviewWindow) -> Result<(), String> {
+    if window.label() == PROMPT_WINDOW {
+        Ok(())
+    } else {
+        Err("Only the main window can act for extensions".to_string())
+    }
+}
+
+/// The extension shim calls are attributed to, if one is running
+pub fn running_extension() -> Option<String> {
+    RUNNING_EXTENSION.lock().unwrap().clone()
+}
+
+/// Records which extension the main window has started, or `None` once it
+/// has closed
+#[tauri::command]
+pub fn shim_set_running_extension(
+    window: WebviewWindow,
+    extension: Option<String>,
+) -> Result<(), String> {
+    check_caller(&window)?;
+    *RUNNING_EXTENSION.lock().unwrap() = extension;
+    Ok(())
+}
+
+/// The frontend's answer to a `shim-permission-request`
+#[tauri::command]
+pub fn shim_permission_respond(
+    window: WebviewWindow,
+    request_id: String,
+    allow: bool,
+    remember: bool,
+) -> Result<(), String> {
+    check_caller(&window)?;
+    let sender = PENDING_PERMISSIONS
+        .lock()
+        .unwrap()
+        .remove(&request_id)
+        .ok_or("No shim call is waiting for that permission")?;
+    let _ = sender.send(PermissionAnswer { allow, remember });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_shim_permissions(app: AppHandle) -> Result<ShimPermissions, String> {
+    ShimPermissions::load(&app).map_err(|e| e.to_string())
+}
+
+/// Forgets the remembered answers for `extension`, or only the one for
+/// `capability`, so it is asked again
+#[tauri::command]
+pub fn revoke_shim_permission(
+    app: AppHandle,
+    extension: String,
+    capability: Option<ShimCapability>,
+) -> Result<ShimPermissions, String> {
+    let mut permissions = ShimPermissions::load(&app).map_err(|e| e.to_string())?;
+    match capability {
+        Some(capability) => {
+            if let Some(grants) = permissions.grants.get_mut(&extension) {
+                grants.remove(&capability);
+                if grants.is_empty() {
+                    permissions.grants.remove(&extension);
+                }
+            }
+        }
+        None => {
+            permissions.grants.remove(&extension);
+        }
+    }
+    permissions.save(&app).map_err(|e| e.to_string())?;
+    Ok(permissions)
 }
 
 /// System API shims for common macOS system operations
@@ -374,4 +832,97 @@ mod tests {
             Some(("Test".to_string(), "Hello World".to_string()))
         );
     }
+
+    #[test]
+    fn test_parse_finder_commands() {
+        let script = r#"tell application "Finder"
+            activate
+            reveal POSIX file "/tmp/report.pdf"
+        end tell"#;
+        assert_eq!(
+            AppCommand::parse(script),
+            Some(AppCommand::Reveal(PathBuf::from("/tmp/report.pdf")))
+        );
+        let script = r#"tell application "Finder" to set theItems to selection as alias list"#;
+        assert_eq!(AppCommand::parse(script), Some(AppCommand::GetSelection));
+        assert_eq!(
+            AppCommand::parse(r#"tell application "Finder" to activate"#),
+            Some(AppCommand::Activate("Finder".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_system_events() {
+        let script = r#"tell application "System Events" to tell process "firefox"
+            set position of window 1 to {0, 25}
+        end tell"#;
+        assert_eq!(
+            AppCommand::parse(script),
+            Some(AppCommand::SetWindowFrame {
+                process: Some("firefox".to_string()),
+                change: FrameChange::Position(0, 25),
+            })
+        );
+        let script = r#"tell application "Safari" to set bounds of front window to {0, 0, 1280, 800}"#;
+        assert_eq!(
+            AppCommand::parse(script),
+            Some(AppCommand::SetWindowFrame {
+                process: Some("Safari".to_string()),
+                change: FrameChange::Bounds(0, 0, 1280, 800),
+            })
+        );
+        let script = r#"tell application "System Events"
+            set size of window 1 of (first application process whose frontmost is true) to {800, 600}
+        end tell"#;
+        assert_eq!(
+            AppCommand::parse(script),
+            Some(AppCommand::SetWindowFrame {
+                process: None,
+                change: FrameChange::Size(800, 600),
+            })
+        );
+        let script = r#"tell application "System Events" to get name of every process whose background only is false"#;
+        assert_eq!(
+            AppCommand::parse(script),
+            Some(AppCommand::ListProcesses {
+                foreground_only: true
+            })
+        );
+        let script = r#"tell application "System Events" to get name of first application process whose frontmost is true"#;
+        assert_eq!(AppCommand::parse(script), Some(AppCommand::FrontmostProcess));
+    }
+
+    #[test]
+    fn test_parse_shell_script() {
+        let script = r#"do shell script "echo \"hi\" | tr a-z A-Z""#;
+        assert_eq!(
+            AppCommand::parse(script),
+            Some(AppCommand::ShellScript(r#"echo "hi" | tr a-z A-Z"#.to_string()))
+        );
+        assert_eq!(ShimCapability::of_script(script), ShimCapability::Shell);
+        assert_eq!(
+            ShimCapability::of_script(r#"tell application "Finder" to activate"#),
+            ShimCapability::AppleScript
+        );
+    }
+
+    #[test]
+    fn test_permission_decision() {
+        let mut permissions = ShimPermissions::default();
+        permissions
+            .grants
+            .entry("todo-list".to_string())
+            .or_default()
+            .insert(ShimCapability::AppleScript, true);
+        assert_eq!(
+            permissions.decision("todo-list", ShimCapability::AppleScript),
+            Some(true)
+        );
+        assert_eq!(permissions.decision("todo-list", ShimCapability::Shell), None);
+        assert_eq!(permissions.decision("other", ShimCapability::AppleScript), None);
+
+        let json = serde_json::to_string(&permissions).unwrap();
+        assert_eq!(json, r#"{"grants":{"todo-list":{"appleScript":true}}}"#);
+        assert_eq!(serde_json::from_str::<ShimPermissions>(&json).unwrap(), permissions);
+    }
 }
@@ -0,0 +1,881 @@
+//! Typed view of the Raycast extension surface Flare implements.
+//!
+//! Manifests are validated against the command modes and preference types the
+//! frontend can render, and each command's bundled JS is scanned for the
+//! `@raycast/api` exports it touches so unsupported calls can be reported per
+//! command before anything is run.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use super::{get_extension_dir, HeuristicViolation};
+
+const RAYCAST_API_MODULE: &str = "@raycast/api";
+
+/// Exports provided by the sidecar's `getRaycastApi()` (sidecar/src/api/index.ts)
+const SUPPORTED_APIS: &[&str] = &[
+    "AI",
+    "Action",
+    "ActionPanel",
+    "BrowserExtension",
+    "Cache",
+    "Clipboard",
+    "Color",
+    "Detail",
+    "Form",
+    "Grid",
+    "Icon",
+    "Image",
+    "Keyboard",
+    "LaunchType",
+    "List",
+    "LocalStorage",
+    "OAuth",
+    "Toast",
+    "closeMainWindow",
+    "environment",
+    "getApplications",
+    "getDefaultApplication",
+    "getFrontmostApplication",
+    "getPreferenceValues",
+    "getSelectedText",
+    "open",
+    "showHUD",
+    "showInFinder",
+    "showToast",
+    "trash",
+    "useNavigation",
+];
+
+/// Exports that exist but only approximate Raycast's behaviour on Linux
+const PARTIAL_APIS: &[(&str, &str)] = &[
+    (
+        "runAppleScript",
+        "Only common AppleScript patterns are translated to Linux equivalents",
+    ),
+    (
+        "getSelectedFinderItems",
+        "Falls back to file paths on the clipboard when the file manager selection is unavailable",
+    ),
+    (
+        "popToRoot",
+        "No-op; the command returns to the root when it finishes",
+    ),
+    (
+        "usePersistentState",
+        "State is kept in memory and not persisted between launches",
+    ),
+];
+
+const COMMAND_MODES: &[&str] = &["view", "no-view", "menu-bar"];
+const PREFERENCE_TYPES: &[&str] = &[
+    "textfield",
+    "password",
+    "checkbox",
+    "dropdown",
+    "appPicker",
+    "file",
+    "directory",
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestIssue {
+    /// `None` for extension-level fields
+    pub command_name: Option<String>,
+    pub field: String,
+    pub message: String,
+    pub severity: IssueSeverity,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialApi {
+    pub name: String,
+    pub note: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandApiReport {
+    pub command_name: String,
+    pub command_title: String,
+    pub mode: Option<String>,
+    /// Every `@raycast/api` export the bundle references
+    pub used_apis: Vec<String>,
+    pub partially_supported: Vec<PartialApi>,
+    pub unsupported: Vec<String>,
+    /// False when the command's bundle could not be found
+    pub analyzed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionApiReport {
+    pub extension_name: Option<String>,
+    pub manifest_issues: Vec<ManifestIssue>,
+    pub commands: Vec<CommandApiReport>,
+}
+
+impl ExtensionApiReport {
+    /// Manifest errors and unsupported API calls, in the shape the install
+    /// confirmation flow already understands
+    pub fn to_violations(&self) -> Vec<HeuristicViolation> {
+        let command_title = |name: &str| {
+            self.commands
+                .iter()
+                .find(|c| c.command_name == name)
+                .map_or_else(|| name.to_string(), |c| c.command_title.clone())
+        };
+
+        let mut violations: Vec<HeuristicViolation> = self
+            .manifest_issues
+            .iter()
+            .filter(|issue| issue.severity == IssueSeverity::Error)
+            .map(|issue| match issue.command_name.as_deref() {
+                Some(name) => HeuristicViolation {
+                    command_name: name.to_string(),
+                    command_title: command_title(name),
+                    reason: issue.message.clone(),
+                },
+                None => HeuristicViolation {
+                    command_name: "_extension".to_string(),
+                    command_title: "Extension Manifest".to_string(),
+                    reason: issue.message.clone(),
+                },
+            })
+            .collect();
+
+        violations.extend(
+            self.commands
+                .iter()
+                .filter(|c| !c.unsupported.is_empty())
+                .map(|c| HeuristicViolation {
+                    command_name: c.command_name.clone(),
+                    command_title: c.command_title.clone(),
+                    reason: format!(
+                        "Uses Raycast APIs that are not supported yet: {}",
+                        c.unsupported.join(", ")
+                    ),
+                }),
+        );
+        violations
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ManifestPreference {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    label: Option<String>,
+    data: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ManifestCommand {
+    name: Option<String>,
+    title: Option<String>,
+    mode: Option<String>,
+    icon: Option<String>,
+    #[serde(default)]
+    preferences: Vec<ManifestPreference>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Manifest {
+    name: Option<String>,
+    icon: Option<String>,
+    #[serde(default)]
+    commands: Vec<ManifestCommand>,
+    #[serde(default)]
+    preferences: Vec<ManifestPreference>,
+}
+
+/// Read access to extension files, either still in the downloaded archive or
+/// already extracted. Paths are relative to the extension root.
+pub trait ExtensionSource {
+    fn read_to_string(&mut self, path: &str) -> Option<String>;
+    fn exists(&mut self, path: &str) -> bool;
+}
+
+pub struct DirSource {
+    root: PathBuf,
+}
+
+impl DirSource {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+}
+
+impl ExtensionSource for DirSource {
+    fn read_to_string(&mut self, path: &str) -> Option<String> {
+        fs::read_to_string(self.root.join(path)).ok()
+    }
+
+    fn exists(&mut self, path: &str) -> bool {
+        self.root.join(path).exists()
+    }
+}
+
+pub struct ArchiveSource<'a> {
+    archive: &'a mut ZipArchive<Cursor<bytes::Bytes>>,
+    prefix: Option<PathBuf>,
+}
+
+impl<'a> ArchiveSource<'a> {
+    pub fn new(archive: &'a mut ZipArchive<Cursor<bytes::Bytes>>, prefix: Option<PathBuf>) -> Self {
+        Self { archive, prefix }
+    }
+
+    fn full_path(&self, path: &str) -> String {
+        match &self.prefix {
+            Some(prefix) => prefix.join(path).to_string_lossy().into_owned(),
+            None => path.to_string(),
+        }
+    }
+}
+
+impl ExtensionSource for ArchiveSource<'_> {
+    fn read_to_string(&mut self, path: &str) -> Option<String> {
+        let full_path = self.full_path(path);
+        let mut file = self.archive.by_name(&full_path).ok()?;
+        let mut content = String::new();
+        file.read_to_string(&mut content).ok()?;
+        Some(content)
+    }
+
+    fn exists(&mut self, path: &str) -> bool {
+        let full_path = self.full_path(path);
+        self.archive.index_for_name(&full_path).is_some()
+    }
+}
+
+fn issue(
+    command_name: Option<&str>,
+    field: &str,
+    message: String,
+    severity: IssueSeverity,
+) -> ManifestIssue {
+    ManifestIssue {
+        command_name: command_name.map(str::to_string),
+        field: field.to_string(),
+        message,
+        severity,
+    }
+}
+
+fn validate_icon(
+    source: &mut dyn ExtensionSource,
+    command_name: Option<&str>,
+    icon: Option<&str>,
+    issues: &mut Vec<ManifestIssue>,
+) {
+    let Some(icon) = icon.filter(|icon| !icon.is_empty()) else {
+        return;
+    };
+    // Raycast resolves manifest icons relative to the assets folder
+    if !source.exists(&format!("assets/{}", icon)) && !source.exists(icon) {
+        issues.push(issue(
+            command_name,
+            "icon",
+            format!("Icon '{}' is not bundled in assets/", icon),
+            IssueSeverity::Warning,
+        ));
+    }
+}
+
+fn validate_preferences(
+    command_name: Option<&str>,
+    preferences: &[ManifestPreference],
+    issues: &mut Vec<ManifestIssue>,
+) {
+    for preference in preferences {
+        let name = preference.name.as_deref().unwrap_or("<unnamed>");
+        if preference.name.is_none() {
+            issues.push(issue(
+                command_name,
+                "preferences",
+                "Preference is missing a name".to_string(),
+                IssueSeverity::Error,
+            ));
+        }
+        match preference.kind.as_deref() {
+            None => issues.push(issue(
+                command_name,
+                "preferences",
+                format!("Preference '{}' has no type", name),
+                IssueSeverity::Error,
+            )),
+            Some(kind) if !PREFERENCE_TYPES.contains(&kind) => issues.push(issue(
+                command_name,
+                "preferences",
+                format!("Preference '{}' has unsupported type '{}'", name, kind),
+                IssueSeverity::Error,
+            )),
+            Some("dropdown") if preference.data.as_deref().unwrap_or_default().is_empty() => issues
+                .push(issue(
+                    command_name,
+                    "preferences",
+                    format!("Dropdown preference '{}' has no options", name),
+                    IssueSeverity::Error,
+                )),
+            Some("checkbox") if preference.label.is_none() => issues.push(issue(
+                command_name,
+                "preferences",
+                format!("Checkbox preference '{}' has no label", name),
+                IssueSeverity::Warning,
+            )),
+            _ => {}
+        }
+    }
+}
+
+fn validate_manifest(source: &mut dyn ExtensionSource, manifest: &Manifest) -> Vec<ManifestIssue> {
+    let mut issues = Vec::new();
+
+    if manifest.commands.is_empty() {
+        issues.push(issue(
+            None,
+            "commands",
+            "The extension declares no commands".to_string(),
+            IssueSeverity::Error,
+        ));
+    }
+    validate_icon(source, None, manifest.icon.as_deref(), &mut issues);
+    validate_preferences(None, &manifest.preferences, &mut issues);
+
+    for command in &manifest.commands {
+        let Some(name) = command.name.as_deref() else {
+            issues.push(issue(
+                None,
+                "commands",
+                "A command is missing its name".to_string(),
+                IssueSeverity::Error,
+            ));
+            continue;
+        };
+        match command.mode.as_deref() {
+            None => issues.push(issue(
+                Some(name),
+                "mode",
+                "No mode given; the command will open as a view".to_string(),
+                IssueSeverity::Warning,
+            )),
+            Some(mode) if !COMMAND_MODES.contains(&mode) => issues.push(issue(
+                Some(name),
+                "mode",
+                format!("Unknown command mode '{}'", mode),
+                IssueSeverity::Error,
+            )),
+            Some("menu-bar") => issues.push(issue(
+                Some(name),
+                "mode",
+                "Menu bar commands are not supported yet".to_string(),
+                IssueSeverity::Warning,
+            )),
+            _ => {}
+        }
+        validate_icon(source, Some(name), command.icon.as_deref(), &mut issues);
+        validate_preferences(Some(name), &command.preferences, &mut issues);
+    }
+
+    issues
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Punct(char),
+    /// Numbers, regex and template literal text; kept only so neighbours stay correct
+    Other,
+}
+
+const REGEX_PRECEDING_KEYWORDS: &[&str] = &[
+    "return",
+    "typeof",
+    "case",
+    "do",
+    "else",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "yield",
+    "await",
+    "instanceof",
+];
+
+fn regex_allowed_after(previous: Option<&Token>) -> bool {
+    match previous {
+        None => true,
+        Some(Token::Punct(c)) => !matches!(c, ')' | ']' | '}'),
+        Some(Token::Ident(word)) => REGEX_PRECEDING_KEYWORDS.contains(&word.as_str()),
+        Some(_) => false,
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Skips a quoted literal starting after the opening quote and returns its contents
+fn read_quoted(chars: &[char], i: &mut usize, quote: char) -> String {
+    let mut value = String::new();
+    while *i < chars.len() {
+        let c = chars[*i];
+        *i += 1;
+        if c == '\\' {
+            if let Some(&escaped) = chars.get(*i) {
+                value.push(escaped);
+                *i += 1;
+            }
+        } else if c == quote || c == '\n' {
+            break;
+        } else {
+            value.push(c);
+        }
+    }
+    value
+}
+
+/// Scans template literal text up to the closing backtick or the next `${`.
+/// Returns true when an interpolation was entered.
+fn read_template_text(chars: &[char], i: &mut usize) -> bool {
+    while *i < chars.len() {
+        match chars[*i] {
+            '\\' => *i += 2,
+            '`' => {
+                *i += 1;
+                return false;
+            }
+            '$' if chars.get(*i + 1) == Some(&'{') => {
+                *i += 2;
+                return true;
+            }
+            _ => *i += 1,
+        }
+    }
+    false
+}
+
+/// A small JavaScript tokenizer: enough to skip comments, strings, templates
+/// and regex literals so identifiers in prose or data are never mistaken for
+/// API usage.
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut brace_depth = 0usize;
+    // Brace depths at which currently open `${ ... }` interpolations started
+    let mut template_stack: Vec<usize> = Vec::new();
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '\'' || c == '"' {
+            i += 1;
+            tokens.push(Token::Str(read_quoted(&chars, &mut i, c)));
+        } else if c == '`' {
+            i += 1;
+            tokens.push(Token::Other);
+            if read_template_text(&chars, &mut i) {
+                template_stack.push(brace_depth);
+            }
+        } else if c == '/' && regex_allowed_after(tokens.last()) {
+            i += 1;
+            let mut in_class = false;
+            while i < chars.len() && chars[i] != '\n' {
+                match chars[i] {
+                    '\\' => i += 1,
+                    '[' => in_class = true,
+                    ']' => in_class = false,
+                    '/' if !in_class => break,
+                    _ => {}
+                }
+                i += 1;
+            }
+            i += 1;
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            tokens.push(Token::Other);
+        } else if is_ident_start(c) {
+            let start = i;
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (is_ident_char(chars[i]) || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Other);
+        } else {
+            i += 1;
+            match c {
+                '{' => brace_depth += 1,
+                '}' if template_stack.last() == Some(&brace_depth) => {
+                    // End of a `${ ... }` interpolation: back to template text
+                    template_stack.pop();
+                    if read_template_text(&chars, &mut i) {
+                        template_stack.push(brace_depth);
+                    }
+                    tokens.push(Token::Other);
+                    continue;
+                }
+                '}' => brace_depth = brace_depth.saturating_sub(1),
+                _ => {}
+            }
+            tokens.push(Token::Punct(c));
+        }
+    }
+    tokens
+}
+
+fn is_ident(token: Option<&Token>, name: &str) -> bool {
+    matches!(token, Some(Token::Ident(s)) if s == name)
+}
+
+fn is_punct(token: Option<&Token>, c: char) -> bool {
+    matches!(token, Some(Token::Punct(p)) if *p == c)
+}
+
+fn is_api_module(token: Option<&Token>) -> bool {
+    matches!(token, Some(Token::Str(s)) if s == RAYCAST_API_MODULE)
+}
+
+/// Collects `{ a, b: c, d as e }` keys between braces, starting at the `{`
+fn collect_braced_names(tokens: &[Token], open: usize, close: usize) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut expect_name = true;
+    for token in &tokens[open + 1..close] {
+        match token {
+            Token::Ident(name) if expect_name => {
+                // `type X` specifiers are erased at runtime
+                if name != "type" {
+                    names.push(name.clone());
+                }
+                expect_name = false;
+            }
+            Token::Punct(',') => expect_name = true,
+            _ => {}
+        }
+    }
+    names
+}
+
+/// Finds every `@raycast/api` export referenced by a bundled command, whether
+/// through esbuild's `var import_api = __toESM(require("@raycast/api"))`
+/// namespace objects, destructured requires or ES module imports.
+fn find_api_usage(tokens: &[Token]) -> BTreeSet<String> {
+    let mut namespaces: HashSet<String> = HashSet::new();
+    let mut used = BTreeSet::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if is_ident(Some(token), "require")
+            && is_punct(tokens.get(i + 1), '(')
+            && is_api_module(tokens.get(i + 2))
+        {
+            let mut k = i;
+            // Unwrap interop helpers such as `__toESM(require(...))`
+            if k >= 2 && is_punct(tokens.get(k - 1), '(') {
+                if let Some(Token::Ident(helper)) = tokens.get(k - 2) {
+                    if helper.starts_with("__") {
+                        k -= 2;
+                    }
+                }
+            }
+            if k < 2 || !is_punct(tokens.get(k - 1), '=') {
+                continue;
+            }
+            match &tokens[k - 2] {
+                Token::Ident(alias) => {
+                    namespaces.insert(alias.clone());
+                }
+                Token::Punct('}') => {
+                    let close = k - 2;
+                    if let Some(open) = tokens[..close]
+                        .iter()
+                        .rposition(|t| *t == Token::Punct('{'))
+                    {
+                        used.extend(collect_braced_names(tokens, open, close));
+                    }
+                }
+                _ => {}
+            }
+        } else if is_ident(Some(token), "import") {
+            let Some(from) = tokens[i..]
+                .iter()
+                .take(64)
+                .position(|t| is_ident(Some(t), "from"))
+                .map(|offset| i + offset)
+            else {
+                continue;
+            };
+            if !is_api_module(tokens.get(from + 1)) {
+                continue;
+            }
+            let clause = &tokens[i + 1..from];
+            if let Some(star) = clause.iter().position(|t| *t == Token::Punct('*')) {
+                if let Some(Token::Ident(alias)) = clause.get(star + 2) {
+                    namespaces.insert(alias.clone());
+                }
+            }
+            if let (Some(open), Some(close)) = (
+                clause.iter().position(|t| *t == Token::Punct('{')),
+                clause.iter().position(|t| *t == Token::Punct('}')),
+            ) {
+                used.extend(collect_braced_names(clause, open, close));
+            }
+        }
+    }
+
+    for window in tokens.windows(3) {
+        let [Token::Ident(object), access, property] = window else {
+            continue;
+        };
+        if !namespaces.contains(object) {
+            continue;
+        }
+        let name = match (access, property) {
+            (Token::Punct('.'), Token::Ident(name)) => name,
+            (Token::Punct('['), Token::Str(name)) => name,
+            _ => continue,
+        };
+        if name != "default" && name != "__esModule" {
+            used.insert(name.clone());
+        }
+    }
+
+    used
+}
+
+fn analyze_command(
+    source: &mut dyn ExtensionSource,
+    command: &ManifestCommand,
+    name: &str,
+) -> CommandApiReport {
+    let mut report = CommandApiReport {
+        command_name: name.to_string(),
+        command_title: command.title.clone().unwrap_or_else(|| name.to_string()),
+        mode: command.mode.clone(),
+        used_apis: Vec::new(),
+        partially_supported: Vec::new(),
+        unsupported: Vec::new(),
+        analyzed: false,
+    };
+    let Some(bundle) = source.read_to_string(&format!("{}.js", name)) else {
+        return report;
+    };
+
+    report.analyzed = true;
+    for api in find_api_usage(&tokenize(&bundle)) {
+        if let Some((_, note)) = PARTIAL_APIS.iter().find(|(partial, _)| *partial == api) {
+            report.partially_supported.push(PartialApi {
+                name: api.clone(),
+                note: note.to_string(),
+            });
+        } else if !SUPPORTED_APIS.contains(&api.as_str()) {
+            report.unsupported.push(api.clone());
+        }
+        report.used_apis.push(api);
+    }
+    report
+}
+
+/// Validates the manifest and reports API coverage for every command
+pub fn analyze_extension(source: &mut dyn ExtensionSource) -> Result<ExtensionApiReport, String> {
+    let manifest_str = source
+        .read_to_string("package.json")
+        .ok_or_else(|| "Extension has no package.json".to_string())?;
+    let manifest: Manifest = serde_json::from_str(&manifest_str)
+        .map_err(|e| format!("Failed to parse package.json: {}", e))?;
+
+    let manifest_issues = validate_manifest(source, &manifest);
+    let commands = manifest
+        .commands
+        .iter()
+        .filter_map(|command| {
+            let name = command.name.as_deref()?;
+            Some(analyze_command(source, command, name))
+        })
+        .collect();
+
+    Ok(ExtensionApiReport {
+        extension_name: manifest.name,
+        manifest_issues,
+        commands,
+    })
+}
+
+#[tauri::command]
+pub fn get_extension_api_report(
+    app: tauri::AppHandle,
+    slug: String,
+) -> Result<ExtensionApiReport, String> {
+    let extension_dir = get_extension_dir(&app, &slug)?;
+    if !extension_dir.exists() {
+        return Err(format!("Extension '{}' is not installed", slug));
+    }
+    analyze_extension(&mut DirSource::new(&extension_dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct MemorySource(HashMap<String, String>);
+
+    impl ExtensionSource for MemorySource {
+        fn read_to_string(&mut self, path: &str) -> Option<String> {
+            self.0.get(path).cloned()
+        }
+
+        fn exists(&mut self, path: &str) -> bool {
+            self.0.contains_key(path)
+        }
+    }
+
+    fn usage(source: &str) -> Vec<String> {
+        find_api_usage(&tokenize(source)).into_iter().collect()
+    }
+
+    #[test]
+    fn test_esbuild_namespace_usage() {
+        let bundle = r#"
+            var import_api = __toESM(require("@raycast/api"), 1);
+            var import_react = require("react");
+            // import_api.MenuBarExtra is mentioned in a comment only
+            const label = "import_api.launchCommand";
+            async function run() {
+                await (0, import_api.showToast)({ title: `Hi ${import_api.environment.extensionName}` });
+                (0, import_api.updateCommandMetadata)({ subtitle: "x" });
+                import_react.useState();
+            }
+        "#;
+        assert_eq!(
+            usage(bundle),
+            vec!["environment", "showToast", "updateCommandMetadata"]
+        );
+    }
+
+    #[test]
+    fn test_destructured_and_esm_imports() {
+        assert_eq!(
+            usage(r#"const { List, confirmAlert: ask } = require("@raycast/api");"#),
+            vec!["List", "confirmAlert"]
+        );
+        assert_eq!(
+            usage(r#"import { Detail, type LaunchProps } from "@raycast/api";"#),
+            vec!["Detail"]
+        );
+        assert_eq!(
+            usage(r#"import * as api from "@raycast/api"; api.showHUD("x");"#),
+            vec!["showHUD"]
+        );
+    }
+
+    #[test]
+    fn test_regex_literals_do_not_confuse_tokenizer() {
+        let bundle = r#"
+            var import_api = require("@raycast/api");
+            const re = /"import_api.launchCommand/g;
+            const half = 10 / 2;
+            import_api.open("x");
+        "#;
+        assert_eq!(usage(bundle), vec!["open"]);
+    }
+
+    #[test]
+    fn test_analyze_extension() {
+        let manifest = r#"{
+            "name": "demo",
+            "icon": "icon.png",
+            "commands": [
+                { "name": "search", "title": "Search", "mode": "view", "icon": "missing.png" },
+                { "name": "bar", "mode": "menu-bar" },
+                { "name": "broken", "mode": "popup",
+                  "preferences": [{ "name": "kind", "type": "dropdown" }] }
+            ]
+        }"#;
+        let search_js = r#"var import_api = require("@raycast/api");
+            (0, import_api.showToast)({});
+            (0, import_api.runAppleScript)("x");
+            (0, import_api.launchCommand)({});"#;
+        let mut source = MemorySource(HashMap::from([
+            ("package.json".to_string(), manifest.to_string()),
+            ("assets/icon.png".to_string(), String::new()),
+            ("search.js".to_string(), search_js.to_string()),
+        ]));
+
+        let report = analyze_extension(&mut source).unwrap();
+        assert_eq!(report.extension_name.as_deref(), Some("demo"));
+        assert!(report
+            .manifest_issues
+            .iter()
+            .any(|issue| issue.severity == IssueSeverity::Error));
+
+        let messages: Vec<(Option<&str>, &str)> = report
+            .manifest_issues
+            .iter()
+            .map(|i| (i.command_name.as_deref(), i.message.as_str()))
+            .collect();
+        assert!(messages.contains(&(
+            Some("search"),
+            "Icon 'missing.png' is not bundled in assets/"
+        )));
+        assert!(messages.contains(&(Some("bar"), "Menu bar commands are not supported yet")));
+        assert!(messages.contains(&(Some("broken"), "Unknown command mode 'popup'")));
+        assert!(messages.contains(&(Some("broken"), "Dropdown preference 'kind' has no options")));
+
+        let search = &report.commands[0];
+        assert!(search.analyzed);
+        assert_eq!(
+            search.used_apis,
+            vec!["launchCommand", "runAppleScript", "showToast"]
+        );
+        assert_eq!(search.unsupported, vec!["launchCommand"]);
+        assert_eq!(search.partially_supported[0].name, "runAppleScript");
+        assert!(!report.commands[1].analyzed);
+
+        let violations = report.to_violations();
+        assert!(violations
+            .iter()
+            .any(|v| v.command_title == "Search" && v.reason.contains("launchCommand")));
+        assert!(violations.iter().all(|v| !v.reason.contains("Menu bar")));
+    }
+}
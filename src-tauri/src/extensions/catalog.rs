@@ -0,0 +1,700 @@
+//! A local copy of the extension store's index, so the store can be searched
+//! offline and extensions installed by slug instead of by download URL.
+//!
+//! `sync_store_catalog` pages through the Raycast store listing (or a mirror
+//! serving the same shape) and replaces the table in one go. Every listing
+//! gets a compatibility estimate from its metadata alone, since the source
+//! isn't available until install time, where the real heuristic checks run.
+
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::Utc;
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::InstallResult;
+
+pub const DEFAULT_INDEX_URL: &str = "https://backend.raycast.com/api/v1/store_listings";
+const PAGE_SIZE: usize = 100;
+/// Well past the size of the store, in case a mirror never stops paging
+const MAX_PAGES: usize = 100;
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+const MAX_SEARCH_LIMIT: usize = 500;
+
+const STORE_EXTENSIONS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS store_extensions (
+    id INTEGER PRIMARY KEY,
+    store_id TEXT NOT NULL,
+    slug TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    author_name TEXT NOT NULL DEFAULT '',
+    author_handle TEXT NOT NULL DEFAULT '',
+    categories TEXT NOT NULL DEFAULT '',
+    platforms TEXT NOT NULL DEFAULT '',
+    download_count INTEGER NOT NULL DEFAULT 0,
+    download_url TEXT NOT NULL,
+    store_url TEXT,
+    icon TEXT,
+    command_count INTEGER NOT NULL DEFAULT 0,
+    tool_count INTEGER NOT NULL DEFAULT 0,
+    compatibility_score INTEGER NOT NULL,
+    compatibility_notes TEXT NOT NULL DEFAULT '',
+    updated_at INTEGER,
+    UNIQUE(author_handle, slug)
+)";
+const STORE_FTS_SCHEMA: &str = "CREATE VIRTUAL TABLE IF NOT EXISTS store_extensions_fts
+    USING fts5(slug, title, description, author, categories, keywords, tokenize = 'unicode61')";
+const CATALOG_META_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS store_catalog_meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+)";
+
+const SELECT_STORE_EXTENSIONS: &str = "SELECT e.slug, e.title, e.description, e.author_name, e.author_handle, e.categories, e.platforms, e.download_count, e.store_url, e.icon, e.command_count, e.tool_count, e.compatibility_score, e.compatibility_notes, e.updated_at
+    FROM store_extensions e";
+/// Joins list columns; can't appear in a category or a note
+const LIST_SEPARATOR: char = '\u{1f}';
+
+/// Words in a listing that point at macOS-only apps or APIs, with what they
+/// cost the estimate; the same things the install-time heuristics look for
+const MACOS_HINTS: &[(&str, u8, &str)] = &[
+    ("applescript", 35, "Mentions AppleScript"),
+    ("jxa", 35, "Mentions JavaScript for Automation"),
+    ("finder", 25, "Works with Finder"),
+    ("safari", 25, "Works with Safari"),
+    ("apple music", 25, "Works with Apple Music"),
+    ("apple notes", 25, "Works with Apple Notes"),
+    ("imessage", 25, "Works with Messages"),
+    ("keychain", 20, "Uses the macOS Keychain"),
+    ("shortcuts", 20, "Mentions Shortcuts"),
+    ("xcode", 20, "Works with Xcode"),
+    ("homebrew", 10, "Mentions Homebrew"),
+    ("menu bar", 10, "Has a menu bar command"),
+    ("macos", 10, "Mentions macOS"),
+];
+const MACOS_ONLY_PENALTY: u8 = 10;
+const DEPRECATED_PENALTY: u8 = 30;
+
+/// A store listing as the index serves it; only what we keep
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct Listing {
+    id: String,
+    name: String,
+    title: String,
+    description: String,
+    author: ListingAuthor,
+    categories: Vec<String>,
+    platforms: Option<Vec<String>>,
+    download_count: i64,
+    download_url: String,
+    store_url: Option<String>,
+    status: Option<String>,
+    kill_listed_at: Option<i64>,
+    updated_at: Option<i64>,
+    icons: ListingIcons,
+    commands: Vec<ListingCommand>,
+    tools: Vec<ListingCommand>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct ListingAuthor {
+    name: String,
+    handle: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct ListingIcons {
+    light: Option<String>,
+    dark: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct ListingCommand {
+    title: String,
+    description: String,
+    mode: Option<String>,
+    keywords: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Compatibility {
+    /// 0 to 100; how likely the extension is to work here
+    pub score: u8,
+    pub notes: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreExtension {
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub author_name: String,
+    pub author_handle: String,
+    pub categories: Vec<String>,
+    pub platforms: Vec<String>,
+    pub download_count: i64,
+    pub store_url: Option<String>,
+    pub icon: Option<String>,
+    pub command_count: i64,
+    pub tool_count: i64,
+    pub compatibility: Compatibility,
+    pub updated_at: Option<i64>,
+}
+
+fn split_list(joined: Option<String>) -> Vec<String> {
+    joined
+        .map(|joined| {
+            joined
+                .split(LIST_SEPARATOR)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn join_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| item.replace(LIST_SEPARATOR, ""))
+        .collect::<Vec<_>>()
+        .join(&LIST_SEPARATOR.to_string())
+}
+
+impl Storable for StoreExtension {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let score: i64 = row.get(12)?;
+        Ok(StoreExtension {
+            slug: row.get(0)?,
+            title: row.get(1)?,
+            description: row.get(2)?,
+            author_name: row.get(3)?,
+            author_handle: row.get(4)?,
+            categories: split_list(row.get(5)?),
+            platforms: split_list(row.get(6)?),
+            download_count: row.get(7)?,
+            store_url: row.get(8)?,
+            icon: row.get(9)?,
+            command_count: row.get(10)?,
+            tool_count: row.get(11)?,
+            compatibility: Compatibility {
+                score: score.clamp(0, 100) as u8,
+                notes: split_list(row.get(13)?),
+            },
+            updated_at: row.get(14)?,
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogStatus {
+    pub count: i64,
+    pub index_url: Option<String>,
+    pub synced_at: Option<i64>,
+}
+
+/// Guesses how well a listing will work on Linux from what it says about
+/// itself
+fn estimate_compatibility(listing: &Listing) -> Compatibility {
+    let mut text = format!("{} {}", listing.title, listing.description);
+    for command in listing.commands.iter().chain(&listing.tools) {
+        text.push(' ');
+        text.push_str(&command.title);
+        text.push(' ');
+        text.push_str(&command.description);
+        for keyword in &command.keywords {
+            text.push(' ');
+            text.push_str(keyword);
+        }
+        if command.mode.as_deref() == Some("menu-bar") {
+            text.push_str(" menu bar");
+        }
+    }
+    let text = text.to_lowercase();
+
+    let mut penalty: u32 = 0;
+    let mut notes = Vec::new();
+    for (hint, cost, note) in MACOS_HINTS {
+        if text.contains(hint) {
+            penalty += *cost as u32;
+            notes.push(note.to_string());
+        }
+    }
+    let platforms = listing.platforms.as_deref().unwrap_or_default();
+    if !platforms.is_empty() && platforms.iter().all(|p| p == "macOS") {
+        penalty += MACOS_ONLY_PENALTY as u32;
+        notes.push("Only published for macOS".to_string());
+    }
+    if listing.kill_listed_at.is_some() || listing.status.as_deref() == Some("deprecated") {
+        penalty += DEPRECATED_PENALTY as u32;
+        notes.push("Deprecated in the store".to_string());
+    }
+
+    Compatibility {
+        score: 100u32.saturating_sub(penalty) as u8,
+        notes,
+    }
+}
+
+/// A page of the index: `{ "data": [...] }` from the store API, or a bare
+/// array from a mirror that serves the whole index as one file
+fn parse_page(body: &str) -> Result<(Vec<Listing>, bool), String> {
+    let value: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| format!("Invalid store index: {}", e))?;
+    let (items, paged) = match value {
+        serde_json::Value::Array(items) => (items, false),
+        serde_json::Value::Object(mut object) => match object.remove("data") {
+            Some(serde_json::Value::Array(items)) => (items, true),
+            _ => return Err("Store index has no listings".to_string()),
+        },
+        _ => return Err("Store index has no listings".to_string()),
+    };
+    let listings = items
+        .into_iter()
+        .filter_map(|item| serde_json::from_value::<Listing>(item).ok())
+        .filter(|listing| super::is_valid_slug(&listing.name) && !listing.download_url.is_empty())
+        .collect();
+    Ok((listings, paged))
+}
+
+async fn fetch_index(index_url: &str) -> Result<Vec<Listing>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut listings = Vec::new();
+    for page in 1..=MAX_PAGES {
+        let res = client
+            .get(index_url)
+            .query(&[
+                ("page", page.to_string()),
+                ("per_page", PAGE_SIZE.to_string()),
+            ])
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !res.status().is_success() {
+            return Err(format!("Store index returned {}", res.status()));
+        }
+        let body = res.text().await.map_err(|e| e.to_string())?;
+        let (page_listings, paged) = parse_page(&body)?;
+        let last = !paged || page_listings.len() < PAGE_SIZE;
+        listings.extend(page_listings);
+        if last {
+            break;
+        }
+    }
+    Ok(listings)
+}
+
+pub struct CatalogManager {
+    store: Store,
+}
+
+impl CatalogManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        Self::init(Store::new(app_handle, "store_catalog.sqlite")?)
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        Self::init(Store::new_in_memory()?)
+    }
+
+    fn init(store: Store) -> Result<Self, AppError> {
+        store.init_table(STORE_EXTENSIONS_SCHEMA)?;
+        store.init_table(STORE_FTS_SCHEMA)?;
+        store.init_table(CATALOG_META_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    /// Swaps the whole catalog for `listings`; a slug listed twice by the same
+    /// author keeps the first
+    fn replace_all(&self, listings: &[Listing], index_url: &str) -> Result<usize, AppError> {
+        let mut conn = self.store.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM store_extensions", [])?;
+        tx.execute("DELETE FROM store_extensions_fts", [])?;
+        let mut count = 0;
+        for listing in listings {
+            let compatibility = estimate_compatibility(listing);
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO store_extensions (store_id, slug, title, description, author_name, author_handle, categories, platforms, download_count, download_url, store_url, icon, command_count, tool_count, compatibility_score, compatibility_notes, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+                params![
+                    listing.id,
+                    listing.name,
+                    if listing.title.is_empty() { &listing.name } else { &listing.title },
+                    listing.description,
+                    listing.author.name,
+                    listing.author.handle,
+                    join_list(&listing.categories),
+                    join_list(listing.platforms.as_deref().unwrap_or_default()),
+                    listing.download_count,
+                    listing.download_url,
+                    listing.store_url,
+                    listing.icons.light.as_ref().or(listing.icons.dark.as_ref()),
+                    listing.commands.len() as i64,
+                    listing.tools.len() as i64,
+                    compatibility.score,
+                    join_list(&compatibility.notes),
+                    listing.updated_at,
+                ],
+            )?;
+            if inserted == 0 {
+                continue;
+            }
+            let keywords: Vec<String> = listing
+                .commands
+                .iter()
+                .chain(&listing.tools)
+                .flat_map(|command| {
+                    std::iter::once(command.title.clone()).chain(command.keywords.clone())
+                })
+                .collect();
+            tx.execute(
+                "INSERT INTO store_extensions_fts (rowid, slug, title, description, author, categories, keywords)
+                 VALUES (last_insert_rowid(), ?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    listing.name,
+                    listing.title,
+                    listing.description,
+                    format!("{} {}", listing.author.name, listing.author.handle),
+                    listing.categories.join(" "),
+                    keywords.join(" "),
+                ],
+            )?;
+            count += 1;
+        }
+        let now = Utc::now().timestamp().to_string();
+        for (key, value) in [("index_url", index_url), ("synced_at", now.as_str())] {
+            tx.execute(
+                "INSERT OR REPLACE INTO store_catalog_meta (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )?;
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>, AppError> {
+        let db = self.store.conn();
+        match db.query_row(
+            "SELECT value FROM store_catalog_meta WHERE key = ?",
+            params![key],
+            |row| row.get(0),
+        ) {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn status(&self) -> Result<CatalogStatus, AppError> {
+        let count: i64 =
+            self.store
+                .conn()
+                .query_row("SELECT COUNT(*) FROM store_extensions", [], |row| {
+                    row.get(0)
+                })?;
+        Ok(CatalogStatus {
+            count,
+            index_url: self.meta("index_url")?,
+            synced_at: self.meta("synced_at")?.and_then(|value| value.parse().ok()),
+        })
+    }
+
+    /// Listings matching every word of `query` as a prefix, best match first
+    /// and then by downloads; an empty query lists the most downloaded
+    fn search(
+        &self,
+        query: &str,
+        category: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<StoreExtension>, AppError> {
+        let category = category.map(|category| format!("%{}%", category));
+        let limit = limit as i64;
+        match fts_query(query) {
+            Some(fts) => self.store.query(
+                &format!(
+                    "{} JOIN store_extensions_fts ON store_extensions_fts.rowid = e.id
+                     WHERE store_extensions_fts MATCH ?1 AND (?2 IS NULL OR e.categories LIKE ?2)
+                     ORDER BY bm25(store_extensions_fts, 10.0, 8.0, 1.0, 2.0, 2.0, 3.0), e.download_count DESC
+                     LIMIT ?3",
+                    SELECT_STORE_EXTENSIONS
+                ),
+                params![fts, category, limit],
+            ),
+            None => self.store.query(
+                &format!(
+                    "{} WHERE (?1 IS NULL OR e.categories LIKE ?1)
+                     ORDER BY e.download_count DESC LIMIT ?2",
+                    SELECT_STORE_EXTENSIONS
+                ),
+                params![category, limit],
+            ),
+        }
+    }
+
+    /// The slug and download URL of every listing matching `slug`, or
+    /// `author/slug` as in store URLs. More than one means authors share
+    /// the slug.
+    fn download_urls(&self, slug: &str) -> Result<Vec<(String, String)>, AppError> {
+        let (handle, name) = match slug.split_once('/') {
+            Some((handle, name)) => (Some(handle), name),
+            None => (None, slug),
+        };
+        let db = self.store.conn();
+        let mut stmt = db.prepare(
+            "SELECT slug, download_url FROM store_extensions
+             WHERE slug = ?1 AND (?2 IS NULL OR author_handle = ?2)
+             ORDER BY download_count DESC",
+        )?;
+        let found = stmt
+            .query_map(params![name, handle], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<RusqliteResult<Vec<_>>>()?;
+        Ok(found)
+    }
+}
+
+/// Each word becomes a quoted prefix query
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| term.replace('"', ""))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"*", term))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+async fn sync(app: &AppHandle, index_url: &str) -> Result<usize, String> {
+    let listings = fetch_index(index_url).await?;
+    if listings.is_empty() {
+        return Err("The store index is empty".to_string());
+    }
+    let count = app
+        .state::<CatalogManager>()
+        .replace_all(&listings, index_url)
+        .map_err(|e| e.to_string())?;
+    tracing::info!(count, index_url, "Synced extension store catalog");
+    Ok(count)
+}
+
+/// Downloads the store index into the local catalog and returns how many
+/// extensions it holds. `index_url` points at a mirror; by default it's the
+/// last one used, or the Raycast store.
+#[tauri::command]
+pub async fn sync_store_catalog(
+    app: AppHandle,
+    index_url: Option<String>,
+) -> Result<usize, String> {
+    let index_url = match index_url.filter(|url| !url.trim().is_empty()) {
+        Some(url) => url,
+        None => app
+            .state::<CatalogManager>()
+            .meta("index_url")
+            .map_err(|e| e.to_string())?
+            .unwrap_or_else(|| DEFAULT_INDEX_URL.to_string()),
+    };
+    sync(&app, &index_url).await
+}
+
+#[tauri::command]
+pub fn store_catalog_status(app: AppHandle) -> Result<CatalogStatus, String> {
+    app.state::<CatalogManager>()
+        .status()
+        .map_err(|e| e.to_string())
+}
+
+/// Searches the local catalog, syncing it first if it's never been synced
+#[tauri::command]
+pub async fn search_store(
+    app: AppHandle,
+    query: String,
+    category: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<StoreExtension>, String> {
+    let never_synced = app
+        .state::<CatalogManager>()
+        .meta("synced_at")
+        .map_err(|e| e.to_string())?
+        .is_none();
+    if never_synced {
+        sync(&app, DEFAULT_INDEX_URL).await?;
+    }
+    let limit = limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    app.state::<CatalogManager>()
+        .search(&query, category.as_deref(), limit)
+        .map_err(|e| e.to_string())
+}
+
+/// Installs a catalog extension by its slug (or `author/slug`)
+#[tauri::command]
+pub async fn install_store_extension(
+    app: AppHandle,
+    slug: String,
+    force: bool,
+) -> Result<InstallResult, String> {
+    let mut found = app
+        .state::<CatalogManager>()
+        .download_urls(slug.trim())
+        .map_err(|e| e.to_string())?;
+    if found.len() > 1 {
+        return Err(format!(
+            "Several authors publish '{}'; use author/slug",
+            slug
+        ));
+    }
+    let Some((slug, download_url)) = found.pop() else {
+        return Err(format!("No extension '{}' in the store catalog", slug));
+    };
+    super::install_extension(app, download_url, slug, force).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(name: &str, description: &str, downloads: i64) -> Listing {
+        Listing {
+            id: format!("id-{}", name),
+            name: name.to_string(),
+            title: name.to_uppercase(),
+            description: description.to_string(),
+            author: ListingAuthor {
+                name: "Jane".to_string(),
+                handle: "jane".to_string(),
+            },
+            categories: vec!["Developer Tools".to_string()],
+            platforms: Some(vec!["macOS".to_string(), "Windows".to_string()]),
+            download_count: downloads,
+            download_url: format!("https://example.com/{}.zip", name),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_page_shapes() {
+        let body = r#"{"data": [
+            {"id": "1", "name": "github", "title": "GitHub", "download_url": "https://x/1.zip",
+             "author": {"name": "Raycast", "handle": "raycast"}, "unknown": true},
+            {"id": "2", "name": "no-download"},
+            {"id": "3", "name": "../escape", "download_url": "https://x/3.zip"}
+        ]}"#;
+        let (listings, paged) = parse_page(body).unwrap();
+        assert!(paged);
+        assert_eq!(listings.len(), 1);
+        assert_eq!(listings[0].author.handle, "raycast");
+
+        let (listings, paged) =
+            parse_page(r#"[{"name": "a", "download_url": "https://x/a.zip"}]"#).unwrap();
+        assert!(!paged);
+        assert_eq!(listings.len(), 1);
+        assert!(parse_page(r#"{"error": "nope"}"#).is_err());
+    }
+
+    #[test]
+    fn test_estimate_compatibility() {
+        let portable = listing("todo", "Manage your todos", 10);
+        assert_eq!(estimate_compatibility(&portable).score, 100);
+
+        let mut mac = listing("music", "Control Apple Music with AppleScript", 10);
+        mac.platforms = Some(vec!["macOS".to_string()]);
+        let estimate = estimate_compatibility(&mac);
+        assert_eq!(estimate.score, 100 - 35 - 25 - 10);
+        assert_eq!(estimate.notes.len(), 3);
+
+        let mut dead = listing("gone", "Finder Safari Xcode AppleScript", 1);
+        dead.kill_listed_at = Some(1);
+        assert_eq!(estimate_compatibility(&dead).score, 0);
+    }
+
+    #[test]
+    fn test_sync_search_and_lookup() {
+        let manager = CatalogManager::new_for_test().unwrap();
+        assert_eq!(manager.status().unwrap().count, 0);
+
+        let mut brew = listing("brew", "Search packages", 500);
+        brew.commands = vec![ListingCommand {
+            title: "Search Formulae".to_string(),
+            keywords: vec!["homebrew".to_string()],
+            ..Default::default()
+        }];
+        let listings = vec![
+            listing("github", "Work with pull requests", 1000),
+            listing("gitlab", "Work with merge requests", 200),
+            brew,
+            listing("github", "Duplicate", 1),
+        ];
+        assert_eq!(
+            manager.replace_all(&listings, DEFAULT_INDEX_URL).unwrap(),
+            3
+        );
+        let status = manager.status().unwrap();
+        assert_eq!(status.count, 3);
+        assert_eq!(status.index_url.as_deref(), Some(DEFAULT_INDEX_URL));
+        assert!(status.synced_at.is_some());
+
+        let slugs =
+            |results: Vec<StoreExtension>| results.into_iter().map(|e| e.slug).collect::<Vec<_>>();
+        assert_eq!(
+            slugs(manager.search("git", None, 10).unwrap()),
+            vec!["github", "gitlab"]
+        );
+        assert_eq!(
+            slugs(manager.search("merge", None, 10).unwrap()),
+            vec!["gitlab"]
+        );
+        assert_eq!(
+            slugs(manager.search("formul", None, 10).unwrap()),
+            vec!["brew"]
+        );
+        assert_eq!(
+            slugs(manager.search("", None, 2).unwrap()),
+            vec!["github", "brew"]
+        );
+        assert!(manager.search("", Some("Media"), 10).unwrap().is_empty());
+
+        let found = manager.search("brew", None, 1).unwrap().remove(0);
+        assert_eq!(found.categories, vec!["Developer Tools"]);
+        assert_eq!(found.platforms, vec!["macOS", "Windows"]);
+        assert_eq!(found.command_count, 1);
+        assert_eq!(found.compatibility.notes, vec!["Mentions Homebrew"]);
+
+        assert_eq!(
+            manager.download_urls("jane/gitlab").unwrap(),
+            vec![(
+                "gitlab".to_string(),
+                "https://example.com/gitlab.zip".to_string()
+            )]
+        );
+        assert!(manager.download_urls("someone/gitlab").unwrap().is_empty());
+
+        // The same slug from another author is a different extension
+        let mut fork = listing("gitlab", "A fork", 5);
+        fork.author.handle = "someone".to_string();
+        manager
+            .replace_all(&[listing("gitlab", "", 200), fork], DEFAULT_INDEX_URL)
+            .unwrap();
+        assert_eq!(manager.download_urls("gitlab").unwrap().len(), 2);
+        assert_eq!(manager.download_urls("someone/gitlab").unwrap().len(), 1);
+
+        // A sync replaces everything
+        manager
+            .replace_all(&[listing("notion", "", 5)], DEFAULT_INDEX_URL)
+            .unwrap();
+        assert!(manager.search("git", None, 10).unwrap().is_empty());
+        assert_eq!(manager.status().unwrap().count, 1);
+    }
+}
@@ -0,0 +1,279 @@
+//! Shared storage for files that several extensions ship identical copies
+//! of, mostly the vendored chunks their bundlers emit.
+//!
+//! After an install every file is hashed, and the cache next to the plugins
+//! directory keeps one hard link per content hash. A file whose content is
+//! already cached is replaced with a link to the cached copy, so the bytes
+//! are stored once however many extensions carry them. Cache entries no
+//! extension links to any more are pruned on the next install.
+
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use walkdir::WalkDir;
+
+/// Small files aren't worth an inode lookup
+const MIN_DEDUPE_SIZE: u64 = 1024;
+const CACHE_DIR_NAME: &str = "plugin-cache";
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupeReport {
+    pub linked_files: usize,
+    pub saved_bytes: u64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionSize {
+    pub slug: String,
+    /// What the extension's files add up to
+    pub total_bytes: u64,
+    /// The part of that no other extension shares, i.e. what uninstalling it
+    /// would free
+    pub exclusive_bytes: u64,
+    pub shared_bytes: u64,
+    pub file_count: usize,
+}
+
+/// Sits next to the plugins directory so hard links between them work
+fn cache_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| "Failed to get app local data dir".to_string())?;
+    Ok(data_dir.join(CACHE_DIR_NAME))
+}
+
+fn content_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(fs::File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn regular_files(dir: &Path) -> impl Iterator<Item = (PathBuf, fs::Metadata)> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((entry.into_path(), metadata))
+        })
+}
+
+/// Points `path` at `cached` without a moment where `path` is missing
+fn replace_with_link(cached: &Path, path: &Path) -> io::Result<()> {
+    let temp = path.with_file_name(format!(".{}.flare-link", uuid::Uuid::new_v4().simple()));
+    fs::hard_link(cached, &temp)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+/// Links the files in `extension_dir` with identical ones already in the
+/// cache, and adds the rest to it
+fn dedupe_into_cache(extension_dir: &Path, cache: &Path) -> io::Result<DedupeReport> {
+    let mut report = DedupeReport::default();
+    for (path, metadata) in regular_files(extension_dir) {
+        if metadata.len() < MIN_DEDUPE_SIZE {
+            continue;
+        }
+        let hash = match content_hash(&path) {
+            Ok(hash) => hash,
+            Err(e) => {
+                tracing::debug!(path = %path.display(), error = %e, "Can't hash extension file");
+                continue;
+            }
+        };
+        let cached = cache.join(&hash[..2]).join(&hash);
+
+        match fs::metadata(&cached) {
+            Ok(existing) => {
+                let same_file =
+                    existing.dev() == metadata.dev() && existing.ino() == metadata.ino();
+                // Links share permissions, so only files that agree on them
+                // can share an inode
+                if same_file
+                    || existing.mode() != metadata.mode()
+                    || existing.len() != metadata.len()
+                {
+                    continue;
+                }
+                match replace_with_link(&cached, &path) {
+                    Ok(()) => {
+                        report.linked_files += 1;
+                        report.saved_bytes += metadata.len();
+                    }
+                    Err(e) => tracing::debug!(error = %e, "Failed to link extension file"),
+                }
+            }
+            Err(_) => {
+                fs::create_dir_all(cached.parent().unwrap_or(cache))?;
+                // A different filesystem just means no sharing
+                if let Err(e) = fs::hard_link(&path, &cached) {
+                    tracing::debug!(error = %e, "Failed to add extension file to the cache");
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Removes cache entries no extension links to any more and returns the
+/// bytes freed
+fn prune_cache(cache: &Path) -> io::Result<u64> {
+    let mut freed = 0;
+    for (path, metadata) in regular_files(cache) {
+        if metadata.nlink() <= 1 {
+            fs::remove_file(&path)?;
+            freed += metadata.len();
+        }
+    }
+    Ok(freed)
+}
+
+/// A file's device and inode number, which hard links share
+type Inode = (u64, u64);
+
+/// Disk usage of every extension directory in `plugins_dir`, counting each
+/// shared file once per extension and splitting out what's shared
+fn extension_sizes(plugins_dir: &Path) -> io::Result<Vec<ExtensionSize>> {
+    let mut per_extension: Vec<(String, Vec<(Inode, u64)>)> = Vec::new();
+    let mut users: HashMap<Inode, usize> = HashMap::new();
+
+    for entry in fs::read_dir(plugins_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let mut files = Vec::new();
+        let mut seen = HashSet::new();
+        for (_, metadata) in regular_files(&entry.path()) {
+            let inode = (metadata.dev(), metadata.ino());
+            if seen.insert(inode) {
+                *users.entry(inode).or_default() += 1;
+            }
+            files.push((inode, metadata.len()));
+        }
+        per_extension.push((entry.file_name().to_string_lossy().into_owned(), files));
+    }
+
+    let mut sizes: Vec<ExtensionSize> = per_extension
+        .into_iter()
+        .map(|(slug, files)| {
+            let total_bytes: u64 = files.iter().map(|(_, len)| len).sum();
+            let shared_bytes: u64 = files
+                .iter()
+                .filter(|(inode, _)| users.get(inode).copied().unwrap_or(0) > 1)
+                .map(|(_, len)| len)
+                .sum();
+            ExtensionSize {
+                slug,
+                total_bytes,
+                exclusive_bytes: total_bytes - shared_bytes,
+                shared_bytes,
+                file_count: files.len(),
+            }
+        })
+        .collect();
+    sizes.sort_by_key(|size| Reverse(size.total_bytes));
+    Ok(sizes)
+}
+
+/// Shares a freshly installed extension's files through the cache and drops
+/// entries left over from what it replaced. Failing only costs disk space.
+pub(super) fn share_files(app: &tauri::AppHandle, extension_dir: &Path) {
+    let cache = match cache_dir(app) {
+        Ok(cache) => cache,
+        Err(e) => {
+            eprintln!("⚠️ Failed to dedupe extension files: {}", e);
+            return;
+        }
+    };
+    match dedupe_into_cache(extension_dir, &cache) {
+        Ok(report) if report.linked_files > 0 => eprintln!(
+            "Shared {} files ({} bytes) with other extensions",
+            report.linked_files, report.saved_bytes
+        ),
+        Ok(_) => {}
+        Err(e) => eprintln!("⚠️ Failed to dedupe extension files: {}", e),
+    }
+    if let Err(e) = prune_cache(&cache) {
+        eprintln!("⚠️ Failed to prune the extension file cache: {}", e);
+    }
+}
+
+/// Per-extension disk usage, biggest first
+#[tauri::command]
+pub fn get_extension_sizes(app: tauri::AppHandle) -> Result<Vec<ExtensionSize>, String> {
+    let plugins_dir = super::get_extension_dir(&app, "")?;
+    if !plugins_dir.exists() {
+        return Ok(Vec::new());
+    }
+    extension_sizes(&plugins_dir).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("flare_dedupe_{}", rand::random::<u32>()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_identical_files_share_storage() {
+        let root = temp_dir();
+        let plugins = root.join("plugins");
+        let cache = root.join(CACHE_DIR_NAME);
+        let chunk = vec![b'x'; 4096];
+        for slug in ["one", "two"] {
+            let dir = plugins.join(slug);
+            fs::create_dir_all(dir.join("chunks")).unwrap();
+            fs::write(dir.join("chunks/vendor.js"), &chunk).unwrap();
+            fs::write(
+                dir.join("index.js"),
+                format!("{}{}", slug, "y".repeat(2000)),
+            )
+            .unwrap();
+            fs::write(dir.join("package.json"), "{}").unwrap();
+        }
+
+        let first = dedupe_into_cache(&plugins.join("one"), &cache).unwrap();
+        assert_eq!(first, DedupeReport::default());
+        let second = dedupe_into_cache(&plugins.join("two"), &cache).unwrap();
+        assert_eq!(second.linked_files, 1);
+        assert_eq!(second.saved_bytes, 4096);
+        assert_eq!(
+            fs::read(plugins.join("two/chunks/vendor.js")).unwrap(),
+            chunk
+        );
+        // Running again finds nothing new to link
+        assert_eq!(
+            dedupe_into_cache(&plugins.join("two"), &cache).unwrap(),
+            DedupeReport::default()
+        );
+
+        let sizes = extension_sizes(&plugins).unwrap();
+        assert_eq!(sizes.len(), 2);
+        let one = sizes.iter().find(|size| size.slug == "one").unwrap();
+        assert_eq!(one.file_count, 3);
+        assert_eq!(one.shared_bytes, 4096);
+        assert_eq!(one.total_bytes, 4096 + 2003 + 2);
+        assert_eq!(one.exclusive_bytes, 2003 + 2);
+
+        // Nothing is freed while the extensions still link to the cache
+        assert_eq!(prune_cache(&cache).unwrap(), 0);
+        fs::remove_dir_all(&plugins).unwrap();
+        assert_eq!(prune_cache(&cache).unwrap(), 4096 + 2003 * 2);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -8,6 +8,13 @@ use zip::result::ZipError;
 use zip::ZipArchive;
 
 use crate::cli_substitutes;
+use crate::confirm::{self, ConfirmationRequest, DangerLevel};
+
+pub mod api;
+pub mod catalog;
+pub mod dedupe;
+pub mod storage;
+pub mod tools;
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -100,7 +107,20 @@ fn is_macho_binary(data: &[u8]) -> bool {
     MACH_O_MAGIC_BYTES.contains(&header)
 }
 
+/// Store slugs are lowercase words joined by hyphens; anything else could
+/// escape the plugins directory
+pub(crate) fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+/// The directory for `slug`, or the plugins directory itself for ""
 fn get_extension_dir(app: &tauri::AppHandle, slug: &str) -> Result<PathBuf, String> {
+    if !slug.is_empty() && !is_valid_slug(slug) {
+        return Err(format!("Invalid extension slug '{}'", slug));
+    }
     let data_dir = app
         .path()
         .app_local_data_dir()
@@ -303,6 +323,13 @@ fn run_heuristic_checks(archive_data: &bytes::Bytes) -> Result<HeuristicResult,
             }
         }
     }
+
+    // Validate the manifest and look for unsupported @raycast/api calls
+    match api::analyze_extension(&mut api::ArchiveSource::new(&mut archive, prefix)) {
+        Ok(report) => violations.extend(report.to_violations()),
+        Err(e) => eprintln!("Skipping extension API analysis: {}", e),
+    }
+
     Ok(HeuristicResult {
         violations,
         macho_binaries: macho_binaries_found,
@@ -605,9 +632,11 @@ pub async fn install_extension(
 
     // Attempt to substitute macOS binaries with Linux equivalents
     if !heuristic_result.macho_binaries.is_empty() {
+        let rules = cli_substitutes::load_rules(&app).rules;
         match cli_substitutes::substitute_macos_binaries(
             &extension_dir,
             &heuristic_result.macho_binaries,
+            &rules,
         )
         .await
         {
@@ -625,7 +654,39 @@ pub async fn install_extension(
         }
     }
 
+    dedupe::share_files(&app, &extension_dir);
+
     save_compatibility_metadata(&extension_dir, &heuristic_result.violations)?;
 
+    let ai_tools = tools::save_tools(&extension_dir, &slug)?;
+    if !ai_tools.is_empty() {
+        eprintln!("Registered {} AI tools for {}", ai_tools.len(), slug);
+    }
+
     Ok(InstallResult::Success)
 }
+
+/// Removes an installed extension with its commands and AI tools, once the
+/// user confirms
+#[tauri::command]
+pub async fn uninstall_extension(
+    app: tauri::AppHandle,
+    slug: String,
+    title: Option<String>,
+) -> Result<(), String> {
+    if slug.is_empty() {
+        return Err("No extension given".to_string());
+    }
+    let extension_dir = get_extension_dir(&app, &slug)?;
+    if !extension_dir.exists() {
+        return Err(format!("Extension '{}' is not installed", slug));
+    }
+    let name = title.unwrap_or_else(|| slug.clone());
+    let request = ConfirmationRequest::new(format!("Uninstall “{}”?", name), DangerLevel::Medium)
+        .details("Its commands and AI tools will be removed.")
+        .confirm_label("Uninstall");
+    if !confirm::request(&app, request).await {
+        return Err("Uninstalling was cancelled".to_string());
+    }
+    fs::remove_dir_all(&extension_dir).map_err(|e| e.to_string())
+}
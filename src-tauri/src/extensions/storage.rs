@@ -0,0 +1,213 @@
+//! Backing store for the Raycast `LocalStorage` API, namespaced per extension
+//! so one extension can never read another's data. `Cache` stays in the
+//! sidecar: its reads are synchronous, which a round trip here can't be.
+
+use crate::error::AppError;
+use crate::store::Store;
+use chrono::Utc;
+use rusqlite::params;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
+
+const LOCAL_STORAGE_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS extension_local_storage (
+    extension TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (extension, key)
+)";
+
+/// Per-extension LocalStorage quota, counting both keys and values
+pub const LOCAL_STORAGE_QUOTA_BYTES: i64 = 5 * 1024 * 1024;
+pub struct ExtensionStorageManager {
+    store: Store,
+}
+
+impl ExtensionStorageManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "extension_storage.sqlite")?;
+        Self::init(store)
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        Self::init(Store::new_in_memory()?)
+    }
+
+    fn init(store: Store) -> Result<Self, AppError> {
+        store.init_table(LOCAL_STORAGE_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    fn check_extension(extension: &str) -> Result<(), AppError> {
+        if extension.trim().is_empty() {
+            return Err(AppError::ExtensionStorage(
+                "An extension name is required".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn storage_get(&self, extension: &str, key: &str) -> Result<Option<String>, AppError> {
+        Self::check_extension(extension)?;
+        let db = self.store.conn();
+        let res: rusqlite::Result<String> = db.query_row(
+            "SELECT value FROM extension_local_storage WHERE extension = ? AND key = ?",
+            params![extension, key],
+            |row| row.get(0),
+        );
+        match res {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn storage_all_items(&self, extension: &str) -> Result<HashMap<String, String>, AppError> {
+        Self::check_extension(extension)?;
+        let db = self.store.conn();
+        let mut stmt =
+            db.prepare("SELECT key, value FROM extension_local_storage WHERE extension = ?")?;
+        let items = stmt
+            .query_map(params![extension], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<HashMap<String, String>>>()?;
+        Ok(items)
+    }
+
+    pub fn storage_set(&self, extension: &str, key: &str, value: &str) -> Result<(), AppError> {
+        Self::check_extension(extension)?;
+        let db = self.store.conn();
+        let used: i64 = db.query_row(
+            "SELECT COALESCE(SUM(LENGTH(CAST(key AS BLOB)) + LENGTH(CAST(value AS BLOB))), 0)
+             FROM extension_local_storage WHERE extension = ? AND key != ?",
+            params![extension, key],
+            |row| row.get(0),
+        )?;
+        let needed = (key.len() + value.len()) as i64;
+        if used + needed > LOCAL_STORAGE_QUOTA_BYTES {
+            return Err(AppError::ExtensionStorage(format!(
+                "LocalStorage quota of {} bytes exceeded for '{}'",
+                LOCAL_STORAGE_QUOTA_BYTES, extension
+            )));
+        }
+
+        db.execute(
+            "INSERT INTO extension_local_storage (extension, key, value, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(extension, key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+            params![extension, key, value, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    pub fn storage_remove(&self, extension: &str, key: &str) -> Result<(), AppError> {
+        Self::check_extension(extension)?;
+        self.store.execute(
+            "DELETE FROM extension_local_storage WHERE extension = ? AND key = ?",
+            params![extension, key],
+        )?;
+        Ok(())
+    }
+
+    pub fn storage_clear(&self, extension: &str) -> Result<(), AppError> {
+        Self::check_extension(extension)?;
+        self.store.execute(
+            "DELETE FROM extension_local_storage WHERE extension = ?",
+            params![extension],
+        )?;
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub fn extension_storage_get(
+    app: AppHandle,
+    extension: String,
+    key: String,
+) -> Result<Option<String>, String> {
+    app.state::<ExtensionStorageManager>()
+        .storage_get(&extension, &key)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn extension_storage_all_items(
+    app: AppHandle,
+    extension: String,
+) -> Result<HashMap<String, String>, String> {
+    app.state::<ExtensionStorageManager>()
+        .storage_all_items(&extension)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn extension_storage_set(
+    app: AppHandle,
+    extension: String,
+    key: String,
+    value: String,
+) -> Result<(), String> {
+    app.state::<ExtensionStorageManager>()
+        .storage_set(&extension, &key, &value)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn extension_storage_remove(
+    app: AppHandle,
+    extension: String,
+    key: String,
+) -> Result<(), String> {
+    app.state::<ExtensionStorageManager>()
+        .storage_remove(&extension, &key)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn extension_storage_clear(app: AppHandle, extension: String) -> Result<(), String> {
+    app.state::<ExtensionStorageManager>()
+        .storage_clear(&extension)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_storage_is_namespaced() {
+        let manager = ExtensionStorageManager::new_for_test().unwrap();
+        manager.storage_set("a", "token", "1").unwrap();
+        manager.storage_set("b", "token", "2").unwrap();
+        manager.storage_set("a", "token", "3").unwrap();
+
+        assert_eq!(
+            manager.storage_get("a", "token").unwrap().as_deref(),
+            Some("3")
+        );
+        assert_eq!(
+            manager.storage_get("b", "token").unwrap().as_deref(),
+            Some("2")
+        );
+        assert_eq!(manager.storage_all_items("a").unwrap().len(), 1);
+
+        manager.storage_clear("a").unwrap();
+        assert_eq!(manager.storage_get("a", "token").unwrap(), None);
+        assert_eq!(
+            manager.storage_get("b", "token").unwrap().as_deref(),
+            Some("2")
+        );
+        assert!(manager.storage_get("", "token").is_err());
+    }
+
+    #[test]
+    fn test_local_storage_quota() {
+        let manager = ExtensionStorageManager::new_for_test().unwrap();
+        let half = "x".repeat((LOCAL_STORAGE_QUOTA_BYTES / 2) as usize);
+        manager.storage_set("ext", "one", &half).unwrap();
+        assert!(manager.storage_set("ext", "two", &half).is_err());
+        // Overwriting a key only counts the new value
+        manager.storage_set("ext", "one", &half).unwrap();
+        // Other extensions have their own quota
+        manager.storage_set("other", "two", &half).unwrap();
+    }
+}
@@ -0,0 +1,407 @@
+//! AI tools that extensions provide, in the shape of Raycast's AI Extensions.
+//! An extension lists them under `tools` in its package.json; each one is a
+//! module at `tools/<name>.js` whose default export takes the model's input.
+//!
+//! The list is read when the extension is installed and saved next to it.
+//! Presets with `extension_tools` hand them to the model as functions, and a
+//! call runs in the sidecar: we emit `ai-extension-tool-run`, the frontend
+//! passes it on, and the sidecar answers with `ai_extension_tool_result`.
+//! Tools that aren't read-only wait for the user to approve each call first,
+//! in the confirmation window.
+
+use crate::confirm::{self, ConfirmationRequest, DangerLevel};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+
+const TOOLS_FILE_NAME: &str = "ai-tools.json";
+/// OpenAI-style function names are limited to 64 characters
+const MAX_FUNCTION_NAME_LEN: usize = 64;
+const TOOL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Tool names starting with these change nothing
+const READ_ONLY_VERBS: &[&str] = &[
+    "get", "list", "search", "find", "fetch", "read", "query", "lookup", "show", "check", "count",
+];
+const DESTRUCTIVE_VERBS: &[&str] = &[
+    "delete",
+    "remove",
+    "destroy",
+    "drop",
+    "erase",
+    "purge",
+    "clear",
+    "kill",
+    "trash",
+    "uninstall",
+];
+
+type RunSender = oneshot::Sender<Result<Value, String>>;
+
+static PENDING_RUNS: Lazy<Mutex<HashMap<String, RunSender>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// What a tool can do to the user's data, which decides whether calls need
+/// approval
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ToolSafety {
+    ReadOnly,
+    Write,
+    Destructive,
+}
+
+impl ToolSafety {
+    pub fn needs_approval(self) -> bool {
+        self != ToolSafety::ReadOnly
+    }
+
+    /// A guess from the tool's name for manifests that don't say; anything
+    /// unrecognised counts as writing
+    fn infer(name: &str) -> Self {
+        let verb: String = name
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic() && !c.is_ascii_uppercase())
+            .collect();
+        if DESTRUCTIVE_VERBS.contains(&verb.as_str()) {
+            ToolSafety::Destructive
+        } else if READ_ONLY_VERBS.contains(&verb.as_str()) {
+            ToolSafety::ReadOnly
+        } else {
+            ToolSafety::Write
+        }
+    }
+}
+
+/// A `tools` entry as written in package.json
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ToolManifest {
+    name: String,
+    title: Option<String>,
+    description: Option<String>,
+    instructions: Option<String>,
+    /// JSON schema of the input object
+    input: Option<Value>,
+    /// Raycast's flag for tools that ask before they run
+    confirmation: Option<bool>,
+    safety: Option<ToolSafety>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionTool {
+    /// The extension's directory name
+    pub extension: String,
+    pub extension_title: String,
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub instructions: Option<String>,
+    pub input_schema: Value,
+    pub safety: ToolSafety,
+    /// The tool's module
+    pub path: String,
+}
+
+impl ExtensionTool {
+    /// The name the model calls it by, `<extension>__<tool>` cut down to the
+    /// characters function names allow
+    pub fn function_name(&self) -> String {
+        let clean = |s: &str| -> String {
+            s.chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect()
+        };
+        let mut name = format!("{}__{}", clean(&self.extension), clean(&self.name));
+        name.truncate(MAX_FUNCTION_NAME_LEN);
+        name
+    }
+
+    /// OpenAI-style function definition
+    pub fn definition(&self) -> Value {
+        let description = format!(
+            "{} ({}): {}",
+            self.title, self.extension_title, self.description
+        );
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.function_name(),
+                "description": description,
+                "parameters": self.input_schema,
+            }
+        })
+    }
+}
+
+/// The tools a package.json declares whose modules are in `extension_dir`
+fn parse_tools(package_json: &Value, extension_dir: &Path, slug: &str) -> Vec<ExtensionTool> {
+    let Some(entries) = package_json.get("tools").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    let extension_title = package_json
+        .get("title")
+        .and_then(Value::as_str)
+        .unwrap_or(slug)
+        .to_string();
+
+    entries
+        .iter()
+        .filter_map(
+            |entry| match serde_json::from_value::<ToolManifest>(entry.clone()) {
+                Ok(manifest) => Some(manifest),
+                Err(e) => {
+                    tracing::warn!(extension = slug, error = %e, "Skipping invalid AI tool entry");
+                    None
+                }
+            },
+        )
+        .filter_map(|manifest| {
+            let path = extension_dir
+                .join("tools")
+                .join(format!("{}.js", manifest.name));
+            if !path.is_file() {
+                tracing::warn!(extension = slug, tool = %manifest.name, "AI tool module not found");
+                return None;
+            }
+            let mut safety = manifest
+                .safety
+                .unwrap_or_else(|| ToolSafety::infer(&manifest.name));
+            if manifest.confirmation == Some(true) && safety == ToolSafety::ReadOnly {
+                safety = ToolSafety::Write;
+            }
+            let input_schema = manifest
+                .input
+                .filter(Value::is_object)
+                .unwrap_or_else(|| json!({ "type": "object", "properties": {} }));
+            Some(ExtensionTool {
+                extension: slug.to_string(),
+                extension_title: extension_title.clone(),
+                title: manifest.title.unwrap_or_else(|| manifest.name.clone()),
+                description: manifest.description.unwrap_or_default(),
+                name: manifest.name,
+                instructions: manifest.instructions,
+                input_schema,
+                safety,
+                path: path.to_string_lossy().into_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Reads the tools out of an extracted extension's package.json and saves
+/// them next to it
+pub(super) fn save_tools(extension_dir: &Path, slug: &str) -> Result<Vec<ExtensionTool>, String> {
+    let package_json = match fs::read_to_string(extension_dir.join("package.json")) {
+        Ok(data) => serde_json::from_str(&data).map_err(|_| "Failed to parse package.json")?,
+        Err(_) => Value::Null,
+    };
+    let tools = parse_tools(&package_json, extension_dir, slug);
+    let data = serde_json::to_string_pretty(&tools).map_err(|e| e.to_string())?;
+    fs::write(extension_dir.join(TOOLS_FILE_NAME), data).map_err(|e| e.to_string())?;
+    Ok(tools)
+}
+
+fn load_tools(extension_dir: &Path) -> Vec<ExtensionTool> {
+    fs::read_to_string(extension_dir.join(TOOLS_FILE_NAME))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// The tools of every installed extension
+pub fn installed_tools(app: &AppHandle) -> Vec<ExtensionTool> {
+    let Ok(plugins_dir) = super::get_extension_dir(app, "") else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(plugins_dir) else {
+        return Vec::new();
+    };
+    let mut tools: Vec<ExtensionTool> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .flat_map(|entry| load_tools(&entry.path()))
+        .collect();
+    tools.sort_by_key(ExtensionTool::function_name);
+    tools
+}
+
+/// `ai-extension-tool-run` payload
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ToolRun<'a> {
+    call_id: String,
+    extension: &'a str,
+    tool: &'a str,
+    path: &'a str,
+    input: &'a Value,
+}
+
+/// Asks the user whether the call may go ahead; no answer is a no
+async fn approve(app: &AppHandle, tool: &ExtensionTool, input: &Value) -> bool {
+    let (safety, danger_level) = match tool.safety {
+        ToolSafety::Destructive => ("This tool can delete data.", DangerLevel::High),
+        _ => ("This tool can create or modify data.", DangerLevel::Medium),
+    };
+    let description = Some(tool.description.as_str()).filter(|d| !d.is_empty());
+    let input = serde_json::to_string_pretty(input).unwrap_or_default();
+    let details = [description.unwrap_or(&tool.title), safety, &input].join("\n\n");
+    let request = ConfirmationRequest::new(
+        format!("Allow {} to run “{}”?", tool.extension_title, tool.title),
+        danger_level,
+    )
+    .details(details)
+    .confirm_label("Run");
+    confirm::request(app, request).await
+}
+
+/// Runs a tool call in the sidecar, after approval if the tool needs it, and
+/// returns its result as text for the model
+pub async fn run(app: &AppHandle, tool: &ExtensionTool, input: Value) -> Result<String, String> {
+    let input = if input.is_object() { input } else { json!({}) };
+    if tool.safety.needs_approval() && !approve(app, tool, &input).await {
+        tracing::info!(extension = %tool.extension, tool = %tool.name, "AI tool call declined");
+        return Err("The user declined to run this tool".to_string());
+    }
+
+    let call_id = uuid::Uuid::new_v4().to_string();
+    let (sender, receiver) = oneshot::channel();
+    PENDING_RUNS.lock().unwrap().insert(call_id.clone(), sender);
+    let run = ToolRun {
+        call_id: call_id.clone(),
+        extension: &tool.extension,
+        tool: &tool.name,
+        path: &tool.path,
+        input: &input,
+    };
+    if let Err(e) = app.emit("ai-extension-tool-run", run) {
+        PENDING_RUNS.lock().unwrap().remove(&call_id);
+        return Err(e.to_string());
+    }
+
+    let result = tokio::time::timeout(TOOL_TIMEOUT, receiver).await;
+    PENDING_RUNS.lock().unwrap().remove(&call_id);
+    match result {
+        Ok(Ok(Ok(Value::String(text)))) => Ok(text),
+        Ok(Ok(Ok(Value::Null))) => Ok("Done".to_string()),
+        Ok(Ok(Ok(value))) => Ok(value.to_string()),
+        Ok(Ok(Err(e))) => Err(e),
+        Ok(Err(_)) => Err("The tool stopped without answering".to_string()),
+        Err(_) => Err("The tool took too long to answer".to_string()),
+    }
+}
+
+/// The installed extensions' AI tools with their safety classification
+#[tauri::command]
+pub fn list_extension_ai_tools(app: AppHandle) -> Vec<ExtensionTool> {
+    installed_tools(&app)
+}
+
+/// The sidecar's answer to an `ai-extension-tool-run`
+#[tauri::command]
+pub fn ai_extension_tool_result(
+    call_id: String,
+    result: Option<Value>,
+    error: Option<String>,
+) -> Result<(), String> {
+    let sender = PENDING_RUNS
+        .lock()
+        .unwrap()
+        .remove(&call_id)
+        .ok_or("No tool call is waiting for that result")?;
+    let _ = sender.send(match error {
+        Some(error) => Err(error),
+        None => Ok(result.unwrap_or(Value::Null)),
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_safety() {
+        assert_eq!(ToolSafety::infer("getIssues"), ToolSafety::ReadOnly);
+        assert_eq!(ToolSafety::infer("search-files"), ToolSafety::ReadOnly);
+        assert_eq!(ToolSafety::infer("deleteEvent"), ToolSafety::Destructive);
+        assert_eq!(ToolSafety::infer("create-issue"), ToolSafety::Write);
+        assert_eq!(ToolSafety::infer("getaway"), ToolSafety::Write);
+        assert!(!ToolSafety::ReadOnly.needs_approval());
+        assert!(ToolSafety::Destructive.needs_approval());
+    }
+
+    #[test]
+    fn test_parse_tools() {
+        let dir = std::env::temp_dir().join(format!("flare_ai_tools_{}", rand::random::<u32>()));
+        fs::create_dir_all(dir.join("tools")).unwrap();
+        for name in ["get-issues", "close-issue", "archive"] {
+            fs::write(dir.join("tools").join(format!("{}.js", name)), "").unwrap();
+        }
+        let package_json = json!({
+            "title": "Linear",
+            "tools": [
+                { "name": "get-issues", "title": "Get Issues", "description": "Lists issues",
+                  "input": { "type": "object", "properties": { "query": { "type": "string" } } } },
+                { "name": "close-issue", "confirmation": true },
+                { "name": "archive", "safety": "destructive" },
+                { "name": "missing-module" },
+                { "title": "No name" }
+            ]
+        });
+
+        let tools = parse_tools(&package_json, &dir, "linear");
+        assert_eq!(tools.len(), 3);
+        assert_eq!(tools[0].safety, ToolSafety::ReadOnly);
+        assert_eq!(tools[0].function_name(), "linear__get-issues");
+        assert_eq!(
+            tools[0].input_schema["properties"]["query"]["type"],
+            "string"
+        );
+        assert_eq!(tools[1].title, "close-issue");
+        assert_eq!(tools[1].safety, ToolSafety::Write);
+        assert_eq!(tools[1].input_schema["type"], "object");
+        assert_eq!(tools[2].safety, ToolSafety::Destructive);
+        assert_eq!(
+            tools[0].definition()["function"]["description"],
+            "Get Issues (Linear): Lists issues"
+        );
+
+        assert!(save_tools(&dir, "linear").unwrap().is_empty());
+        fs::write(dir.join("package.json"), package_json.to_string()).unwrap();
+        assert_eq!(save_tools(&dir, "linear").unwrap(), load_tools(&dir));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_function_name_is_cleaned_and_capped() {
+        let tool = ExtensionTool {
+            extension: "my.ext".to_string(),
+            extension_title: "My Ext".to_string(),
+            name: "do it".repeat(20),
+            title: "Do It".to_string(),
+            description: String::new(),
+            instructions: None,
+            input_schema: json!({}),
+            safety: ToolSafety::Write,
+            path: String::new(),
+        };
+        let name = tool.function_name();
+        assert!(name.starts_with("my_ext__do_it"));
+        assert_eq!(name.len(), MAX_FUNCTION_NAME_LEN);
+    }
+}
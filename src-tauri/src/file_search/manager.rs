@@ -4,7 +4,7 @@ use std::sync::{Arc, Mutex};
 use rusqlite::{params, Connection, OptionalExtension, Result as RusqliteResult};
 use tauri::{AppHandle, Manager};
 
-use super::types::IndexedFile;
+use super::types::{FileCursor, IndexedFile};
 use crate::error::AppError;
 
 #[derive(Clone)]
@@ -168,25 +168,45 @@ impl FileSearchManager {
     }
 
     pub fn search_files(&self, term: &str, limit: u32) -> Result<Vec<IndexedFile>, AppError> {
+        self.search_files_after(term, None, limit)
+    }
+
+    /// One page of matches following `after`
+    pub fn search_files_after(
+        &self,
+        term: &str,
+        after: Option<&FileCursor>,
+        limit: u32,
+    ) -> Result<Vec<IndexedFile>, AppError> {
         let db = self.db.lock().unwrap();
         let mut stmt = db.prepare(
             "SELECT t1.path, t1.name, t1.parent_path, t1.file_type, t1.last_modified
              FROM file_index t1 JOIN file_index_fts t2 ON t1.rowid = t2.rowid
              WHERE t2.name MATCH ?1
-             ORDER BY t1.last_modified DESC
-             LIMIT ?2",
+               AND (?2 IS NULL OR t1.last_modified < ?2 OR (t1.last_modified = ?2 AND t1.path > ?3))
+             ORDER BY t1.last_modified DESC, t1.path ASC
+             LIMIT ?4",
         )?;
 
-        let search_term = format!("\"{}\"*", term);
-        let files_iter = stmt.query_map(params![search_term, limit], |row| {
-            Ok(IndexedFile {
-                path: row.get(0)?,
-                name: row.get(1)?,
-                parent_path: row.get(2)?,
-                file_type: row.get(3)?,
-                last_modified: row.get(4)?,
-            })
-        })?;
+        // Quoted so FTS syntax in the term is matched literally
+        let search_term = format!("\"{}\"*", term.replace('"', "\"\""));
+        let files_iter = stmt.query_map(
+            params![
+                search_term,
+                after.map(|cursor| cursor.last_modified),
+                after.map(|cursor| cursor.path.as_str()),
+                limit
+            ],
+            |row| {
+                Ok(IndexedFile {
+                    path: row.get(0)?,
+                    name: row.get(1)?,
+                    parent_path: row.get(2)?,
+                    file_type: row.get(3)?,
+                    last_modified: row.get(4)?,
+                })
+            },
+        )?;
 
         files_iter
             .collect::<RusqliteResult<Vec<_>>>()
@@ -3,8 +3,11 @@ pub mod manager;
 pub mod types;
 pub mod watcher;
 
+use crate::streaming::{self, StreamOptions, StreamPage, StreamRegistry, StreamSummary};
 use manager::FileSearchManager;
+use tauri::ipc::Channel;
 use tauri::{AppHandle, Manager, State};
+use types::{FileCursor, IndexedFile};
 
 #[tauri::command]
 pub fn search_files(
@@ -14,6 +17,48 @@ pub fn search_files(
     manager.search_files(&term, 100).map_err(|e| e.to_string())
 }
 
+/// Sends matches over `on_page` a page at a time, up to `max_items`. Pass
+/// the returned `next_cursor` back as `cursor` to continue; a new file
+/// search cancels the one still running.
+#[tauri::command]
+pub async fn search_files_stream(
+    manager: State<'_, FileSearchManager>,
+    streams: State<'_, StreamRegistry>,
+    term: String,
+    options: StreamOptions,
+    on_page: Channel<StreamPage<IndexedFile>>,
+) -> Result<StreamSummary, String> {
+    let manager = manager.inner().clone();
+    let streams = streams.inner().clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        streaming::run(
+            &streams,
+            "files",
+            options,
+            |after: Option<&FileCursor>, limit| {
+                if term.trim().is_empty() {
+                    return Ok((Vec::new(), None));
+                }
+                let files = manager
+                    .search_files_after(&term, after, limit)
+                    .map_err(|e| e.to_string())?;
+                let next = match files.last() {
+                    Some(last) if files.len() as u32 == limit => Some(FileCursor {
+                        last_modified: last.last_modified,
+                        path: last.path.clone(),
+                    }),
+                    _ => None,
+                };
+                Ok((files, next))
+            },
+            |page| on_page.send(page).is_ok(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 pub fn init(app_handle: AppHandle) {
     let file_search_manager = match FileSearchManager::new(app_handle.clone()) {
         Ok(manager) => manager,
@@ -9,3 +9,12 @@ pub struct IndexedFile {
     pub file_type: String,  // "file", "directory"
     pub last_modified: i64, // unix timestamp
 }
+
+/// Keyset position in search results, which are ordered by modification
+/// time, newest first, then by path
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCursor {
+    pub last_modified: i64,
+    pub path: String,
+}
@@ -142,82 +142,195 @@ fn get_selected_finder_items_windows() -> Result<Vec<FileSystemItem>, String> {
     Ok(paths)
 }
 
+/// The file managers with a way to ask for their selection
 #[cfg(target_os = "linux")]
-async fn get_from_file_manager() -> Result<Vec<FileSystemItem>, String> {
-    use url::Url;
-    use zbus;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileManagerKind {
+    Nautilus,
+    Nemo,
+    Dolphin,
+}
 
-    let connection = match zbus::Connection::session().await {
-        Ok(c) => c,
-        Err(_) => return Ok(vec![]),
-    };
+#[cfg(target_os = "linux")]
+impl FileManagerKind {
+    fn from_service(name: &str) -> Option<Self> {
+        if name == "org.gnome.Nautilus" {
+            Some(FileManagerKind::Nautilus)
+        } else if name == "org.Nemo" {
+            Some(FileManagerKind::Nemo)
+        } else if name.starts_with("org.kde.dolphin-") {
+            Some(FileManagerKind::Dolphin)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+const FILE_MANAGER1: &str = "org.freedesktop.FileManager1";
+
+/// Finds which file manager implements org.freedesktop.FileManager1 by
+/// looking for its own service name on the same connection
+#[cfg(target_os = "linux")]
+async fn file_manager_service(connection: &zbus::Connection) -> Option<(FileManagerKind, String)> {
+    let dbus = zbus::fdo::DBusProxy::new(connection).await.ok()?;
+    let owner = dbus
+        .get_name_owner(FILE_MANAGER1.try_into().ok()?)
+        .await
+        .ok()?;
+    for name in dbus.list_names().await.ok()? {
+        let Some(kind) = FileManagerKind::from_service(name.as_str()) else {
+            continue;
+        };
+        let name_owner = dbus.get_name_owner(name.inner().clone()).await.ok();
+        if name_owner.as_ref() == Some(&owner) {
+            return Some((kind, name.to_string()));
+        }
+    }
+    None
+}
 
-    let proxy = match zbus::Proxy::new(
-        &connection,
-        "org.freedesktop.FileManager1",
+#[cfg(target_os = "linux")]
+fn uris_to_paths(uris: &[String]) -> Vec<std::path::PathBuf> {
+    uris.iter()
+        .filter_map(|uri| url::Url::parse(uri).ok())
+        .filter(|url| url.scheme() == "file")
+        .filter_map(|url| url.to_file_path().ok())
+        .collect()
+}
+
+/// Nautilus and Nemo windows report their selection as a property
+#[cfg(target_os = "linux")]
+async fn window_selection(
+    connection: &zbus::Connection,
+    service: &str,
+    window_interface: &str,
+) -> zbus::Result<Vec<std::path::PathBuf>> {
+    let proxy = zbus::Proxy::new(
+        connection,
+        service,
         "/org/freedesktop/FileManager1",
-        "org.freedesktop.FileManager1",
+        FILE_MANAGER1,
     )
-    .await
-    {
-        Ok(p) => p,
-        Err(_) => return Ok(vec![]),
-    };
+    .await?;
+    let response = proxy.call_method("GetWindows", &()).await?;
+    let body = response.body();
+    let windows: Vec<zbus::zvariant::ObjectPath> = body.deserialize()?;
 
-    let fm_service: String = proxy.destination().to_string();
-    if fm_service.is_empty() {
-        return Ok(vec![]);
+    for window_path in windows.iter().rev() {
+        let window_proxy =
+            zbus::Proxy::new(connection, service, window_path, window_interface).await?;
+        if window_proxy
+            .get_property::<bool>("Active")
+            .await
+            .unwrap_or(false)
+        {
+            let uris: Vec<String> = window_proxy.get_property("SelectedUris").await?;
+            return Ok(uris_to_paths(&uris));
+        }
     }
+    Ok(vec![])
+}
 
-    let fm_name = fm_service.split('.').last().unwrap_or_default();
-    let window_interface = format!("org.{}.Window", fm_name);
+/// Dolphin has no selection property, but its "Copy Location" action is
+/// reachable over DBus. The clipboard is put back afterwards.
+#[cfg(target_os = "linux")]
+async fn dolphin_selection(
+    connection: &zbus::Connection,
+    service: &str,
+) -> zbus::Result<Vec<std::path::PathBuf>> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| zbus::Error::Failure(e.to_string()))?;
+    let previous = clipboard.get_text().ok();
+    let _ = clipboard.clear();
 
-    if fm_name != "nautilus" && fm_name != "nemo" {
-        return Ok(vec![]);
+    let action = zbus::Proxy::new(
+        connection,
+        service,
+        "/dolphin/Dolphin_1/actions/copy_location",
+        "org.qtproject.Qt.QAction",
+    )
+    .await?;
+    action.call_method("trigger", &()).await?;
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let copied = clipboard.get_text().unwrap_or_default();
+    if let Some(previous) = previous {
+        let _ = clipboard.set_text(previous);
     }
+    Ok(copied
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| match line.strip_prefix("file://") {
+            Some(_) => uris_to_paths(&[line.to_string()]).into_iter().next(),
+            None => Some(std::path::PathBuf::from(line)),
+        })
+        .filter(|path| path.exists())
+        .collect())
+}
 
-    let response = match proxy.call_method("GetWindows", &()).await {
-        Ok(r) => r,
-        Err(_) => return Ok(vec![]),
+/// The items selected in the active window of the running file manager
+#[cfg(target_os = "linux")]
+pub(crate) async fn file_manager_selection() -> Result<Vec<std::path::PathBuf>, String> {
+    let connection = zbus::Connection::session()
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some((kind, service)) = file_manager_service(&connection).await else {
+        return Ok(vec![]);
+    };
+    let selection = match kind {
+        FileManagerKind::Nautilus => {
+            window_selection(&connection, &service, "org.gnome.Nautilus.Window").await
+        }
+        FileManagerKind::Nemo => window_selection(&connection, &service, "org.Nemo.Window").await,
+        FileManagerKind::Dolphin => dolphin_selection(&connection, &service).await,
     };
+    selection.map_err(|e| e.to_string())
+}
 
-    let body = response.body();
-    let windows: Vec<zbus::zvariant::ObjectPath> = body.deserialize().unwrap_or_default();
+/// Opens the folder containing `path` with it selected, falling back to
+/// opening the folder when no file manager implements FileManager1
+#[cfg(target_os = "linux")]
+pub(crate) async fn reveal_in_file_manager(path: &std::path::Path) -> Result<(), String> {
+    let uri = url::Url::from_file_path(path)
+        .map_err(|_| format!("Not an absolute path: {}", path.display()))?
+        .to_string();
 
-    for window_path in windows.iter().rev() {
-        let fm_service_ref = fm_service.as_str();
-        let window_interface_ref = window_interface.as_str();
-        let window_proxy = match zbus::Proxy::new(
+    let shown = async {
+        let connection = zbus::Connection::session().await?;
+        let proxy = zbus::Proxy::new(
             &connection,
-            fm_service_ref,
-            window_path,
-            window_interface_ref,
+            FILE_MANAGER1,
+            "/org/freedesktop/FileManager1",
+            FILE_MANAGER1,
         )
-        .await
-        {
-            Ok(p) => p,
-            Err(_) => continue,
-        };
-        if let Ok(is_active) = window_proxy.get_property::<bool>("Active").await {
-            if is_active {
-                if let Ok(uris) = window_proxy
-                    .get_property::<Vec<String>>("SelectedUris")
-                    .await
-                {
-                    let paths = uris
-                        .iter()
-                        .filter_map(|uri_str| Url::parse(uri_str).ok())
-                        .filter_map(|url| url.to_file_path().ok())
-                        .map(|path_buf| FileSystemItem {
-                            path: path_buf.to_string_lossy().into_owned(),
-                        })
-                        .collect();
-                    return Ok(paths);
-                }
-            }
-        }
+        .await?;
+        proxy.call_method("ShowItems", &(vec![uri], "")).await?;
+        Ok::<(), zbus::Error>(())
     }
-    Ok(vec![])
+    .await;
+
+    if let Err(e) = shown {
+        tracing::debug!(error = %e, "FileManager1.ShowItems failed, opening the folder");
+        let folder = path.parent().unwrap_or(path);
+        std::process::Command::new("xdg-open")
+            .arg(folder)
+            .spawn()
+            .map_err(|e| format!("Failed to open {}: {}", folder.display(), e))?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+async fn get_from_file_manager() -> Result<Vec<FileSystemItem>, String> {
+    Ok(file_manager_selection()
+        .await?
+        .into_iter()
+        .map(|path| FileSystemItem {
+            path: path.to_string_lossy().into_owned(),
+        })
+        .collect())
 }
 
 #[cfg(target_os = "linux")]
@@ -264,3 +377,33 @@ async fn get_selected_finder_items_linux() -> Result<Vec<FileSystemItem>, String
 
     Err("Could not determine selected files. Please copy them to your clipboard.".to_string())
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_manager_from_service() {
+        assert_eq!(
+            FileManagerKind::from_service("org.gnome.Nautilus"),
+            Some(FileManagerKind::Nautilus)
+        );
+        assert_eq!(
+            FileManagerKind::from_service("org.kde.dolphin-4242"),
+            Some(FileManagerKind::Dolphin)
+        );
+        assert_eq!(FileManagerKind::from_service(FILE_MANAGER1), None);
+    }
+
+    #[test]
+    fn test_uris_to_paths() {
+        let uris = vec![
+            "file:///home/me/My%20File.txt".to_string(),
+            "trash:///gone".to_string(),
+        ];
+        assert_eq!(
+            uris_to_paths(&uris),
+            vec![std::path::PathBuf::from("/home/me/My File.txt")]
+        );
+    }
+}
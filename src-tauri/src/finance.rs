@@ -0,0 +1,536 @@
+//! Stock and crypto quotes with a locally stored watchlist.
+//!
+//! Stocks, ETFs and indices come from Yahoo Finance's chart API, which needs
+//! neither a key nor page scraping; crypto prices come from CoinGecko. Quotes
+//! are cached briefly and requests to each provider are spaced out so a long
+//! watchlist stays within the free rate limits.
+
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+
+const YAHOO_CHART_URL: &str = "https://query1.finance.yahoo.com/v8/finance/chart/";
+const COINGECKO_PRICE_URL: &str = "https://api.coingecko.com/api/v3/simple/price";
+
+const WATCHLIST_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS watchlist (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    symbol TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    UNIQUE(symbol, provider)
+)";
+
+const CACHE_TTL: Duration = Duration::from_secs(60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_VS_CURRENCY: &str = "usd";
+
+/// Tickers people type for coins, mapped to CoinGecko ids
+const COIN_ALIASES: &[(&str, &str)] = &[
+    ("btc", "bitcoin"),
+    ("eth", "ethereum"),
+    ("sol", "solana"),
+    ("xrp", "ripple"),
+    ("ada", "cardano"),
+    ("doge", "dogecoin"),
+    ("dot", "polkadot"),
+    ("ltc", "litecoin"),
+    ("xmr", "monero"),
+    ("usdt", "tether"),
+    ("usdc", "usd-coin"),
+    ("bnb", "binancecoin"),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum QuoteProvider {
+    Yahoo,
+    CoinGecko,
+}
+
+impl QuoteProvider {
+    fn as_str(&self) -> &'static str {
+        match self {
+            QuoteProvider::Yahoo => "yahoo",
+            QuoteProvider::CoinGecko => "coinGecko",
+        }
+    }
+
+    fn from_db(s: &str) -> Self {
+        match s {
+            "coinGecko" => QuoteProvider::CoinGecko,
+            _ => QuoteProvider::Yahoo,
+        }
+    }
+
+    /// Minimum spacing between two requests to the provider
+    fn min_interval(&self) -> Duration {
+        match self {
+            QuoteProvider::Yahoo => Duration::from_millis(500),
+            // The public API allows roughly 30 calls a minute
+            QuoteProvider::CoinGecko => Duration::from_millis(2500),
+        }
+    }
+
+    /// Known coin tickers go to CoinGecko, everything else to Yahoo
+    fn guess(symbol: &str) -> Self {
+        if coin_id(symbol).is_some() {
+            QuoteProvider::CoinGecko
+        } else {
+            QuoteProvider::Yahoo
+        }
+    }
+}
+
+fn coin_id(symbol: &str) -> Option<&'static str> {
+    let symbol = symbol.to_lowercase();
+    COIN_ALIASES
+        .iter()
+        .find(|(alias, id)| *alias == symbol || *id == symbol)
+        .map(|(_, id)| *id)
+}
+
+/// Yahoo symbols are upper case (`AAPL`, `^GSPC`); CoinGecko ids lower case
+fn normalize_symbol(symbol: &str, provider: QuoteProvider) -> String {
+    let symbol = symbol.trim();
+    match provider {
+        QuoteProvider::Yahoo => symbol.to_uppercase(),
+        QuoteProvider::CoinGecko => coin_id(symbol)
+            .map(String::from)
+            .unwrap_or_else(|| symbol.to_lowercase()),
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Quote {
+    pub symbol: String,
+    pub name: Option<String>,
+    pub provider: QuoteProvider,
+    pub price: f64,
+    pub currency: String,
+    /// Change since the previous close (Yahoo) or over 24 hours (CoinGecko)
+    pub change: Option<f64>,
+    pub change_percent: Option<f64>,
+    pub market_time: Option<DateTime<Utc>>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchlistItem {
+    pub id: i64,
+    pub symbol: String,
+    pub provider: QuoteProvider,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Storable for WatchlistItem {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let provider: String = row.get(2)?;
+        let created_at_ts: i64 = row.get(3)?;
+        Ok(WatchlistItem {
+            id: row.get(0)?,
+            symbol: row.get(1)?,
+            provider: QuoteProvider::from_db(&provider),
+            created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchlistQuote {
+    #[serde(flatten)]
+    pub item: WatchlistItem,
+    pub quote: Option<Quote>,
+    /// Why the quote is missing, so one bad symbol doesn't hide the rest
+    pub error: Option<String>,
+}
+
+pub struct FinanceManager {
+    store: Store,
+    cache: Mutex<HashMap<(QuoteProvider, String), (Instant, Quote)>>,
+    /// When each provider may next be called
+    next_request: Mutex<HashMap<QuoteProvider, Instant>>,
+}
+
+impl FinanceManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "finance.sqlite")?;
+        Self::with_store(store)
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        Self::with_store(Store::new_in_memory()?)
+    }
+
+    fn with_store(store: Store) -> Result<Self, AppError> {
+        store.init_table(WATCHLIST_SCHEMA)?;
+        Ok(Self {
+            store,
+            cache: Mutex::new(HashMap::new()),
+            next_request: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn add(&self, symbol: &str, provider: QuoteProvider) -> Result<i64, AppError> {
+        self.store.execute(
+            "INSERT OR IGNORE INTO watchlist (symbol, provider, position, created_at)
+             VALUES (?1, ?2, (SELECT COALESCE(MAX(position), -1) + 1 FROM watchlist), ?3)",
+            params![symbol, provider.as_str(), Utc::now().timestamp()],
+        )?;
+        self.store
+            .conn()
+            .query_row(
+                "SELECT id FROM watchlist WHERE symbol = ?1 AND provider = ?2",
+                params![symbol, provider.as_str()],
+                |row| row.get(0),
+            )
+            .map_err(AppError::from)
+    }
+
+    pub fn remove(&self, id: i64) -> Result<(), AppError> {
+        self.store
+            .execute("DELETE FROM watchlist WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Result<Vec<WatchlistItem>, AppError> {
+        self.store.query(
+            "SELECT id, symbol, provider, created_at FROM watchlist ORDER BY position, id",
+            [],
+        )
+    }
+
+    /// Rewrites positions to follow `ids`; ids not listed keep their order after them
+    pub fn reorder(&self, ids: &[i64]) -> Result<(), AppError> {
+        let mut conn = self.store.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE watchlist SET position = position + ?1",
+            params![ids.len() as i64],
+        )?;
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE watchlist SET position = ?1 WHERE id = ?2",
+                params![position as i64, id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn cached(&self, key: &(QuoteProvider, String), now: Instant) -> Option<Quote> {
+        self.cache
+            .lock()
+            .unwrap()
+            .get(key)
+            .filter(|(stored_at, _)| now.duration_since(*stored_at) < CACHE_TTL)
+            .map(|(_, quote)| quote.clone())
+    }
+
+    fn store_quote(&self, key: (QuoteProvider, String), quote: Quote, now: Instant) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, (stored_at, _)| now.duration_since(*stored_at) < CACHE_TTL);
+        cache.insert(key, (now, quote));
+    }
+
+    /// Reserves the provider's next request slot and returns how long to wait for it
+    fn reserve_slot(&self, provider: QuoteProvider, now: Instant) -> Duration {
+        let mut next_request = self.next_request.lock().unwrap();
+        let slot = next_request
+            .get(&provider)
+            .copied()
+            .filter(|next| *next > now)
+            .unwrap_or(now);
+        next_request.insert(provider, slot + provider.min_interval());
+        slot - now
+    }
+
+    async fn quote(&self, symbol: &str, provider: QuoteProvider) -> Result<Quote, String> {
+        let key = (provider, symbol.to_string());
+        if let Some(quote) = self.cached(&key, Instant::now()) {
+            return Ok(quote);
+        }
+
+        let wait = self.reserve_slot(provider, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        let quote = match provider {
+            QuoteProvider::Yahoo => fetch_yahoo(symbol).await?,
+            QuoteProvider::CoinGecko => fetch_coingecko(symbol, DEFAULT_VS_CURRENCY).await?,
+        };
+        self.store_quote(key, quote.clone(), Instant::now());
+        Ok(quote)
+    }
+}
+
+fn percent_change(price: f64, previous: f64) -> Option<f64> {
+    (previous != 0.0).then(|| (price - previous) / previous * 100.0)
+}
+
+fn parse_yahoo(body: &Value) -> Result<Quote, String> {
+    if let Some(description) = body
+        .pointer("/chart/error/description")
+        .and_then(Value::as_str)
+    {
+        return Err(description.to_string());
+    }
+    let meta = body
+        .pointer("/chart/result/0/meta")
+        .ok_or("Symbol not found")?;
+    let number = |key: &str| meta.get(key).and_then(Value::as_f64);
+    let text = |key: &str| meta.get(key).and_then(Value::as_str).map(String::from);
+
+    let price = number("regularMarketPrice").ok_or("No price for symbol")?;
+    let previous = number("chartPreviousClose").or_else(|| number("previousClose"));
+    Ok(Quote {
+        symbol: text("symbol").unwrap_or_default(),
+        name: text("longName").or_else(|| text("shortName")),
+        provider: QuoteProvider::Yahoo,
+        price,
+        currency: text("currency").unwrap_or_else(|| "USD".to_string()),
+        change: previous.map(|previous| price - previous),
+        change_percent: previous.and_then(|previous| percent_change(price, previous)),
+        market_time: meta
+            .get("regularMarketTime")
+            .and_then(Value::as_i64)
+            .and_then(|ts| DateTime::from_timestamp(ts, 0)),
+        fetched_at: Utc::now(),
+    })
+}
+
+fn parse_coingecko(body: &Value, id: &str, vs_currency: &str) -> Result<Quote, String> {
+    let coin = body
+        .get(id)
+        .ok_or_else(|| format!("Unknown coin: {}", id))?;
+    let price = coin
+        .get(vs_currency)
+        .and_then(Value::as_f64)
+        .ok_or_else(|| format!("No {} price for {}", vs_currency.to_uppercase(), id))?;
+    let change_percent = coin
+        .get(format!("{}_24h_change", vs_currency))
+        .and_then(Value::as_f64);
+    Ok(Quote {
+        symbol: id.to_string(),
+        name: None,
+        provider: QuoteProvider::CoinGecko,
+        price,
+        currency: vs_currency.to_uppercase(),
+        // Derived from the percentage so both fields mean the same 24h window
+        change: change_percent.map(|pct| price - price / (1.0 + pct / 100.0)),
+        change_percent,
+        market_time: coin
+            .get("last_updated_at")
+            .and_then(Value::as_i64)
+            .and_then(|ts| DateTime::from_timestamp(ts, 0)),
+        fetched_at: Utc::now(),
+    })
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent("Mozilla/5.0 (X11; Linux x86_64) Flare launcher")
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+async fn get_json(request: reqwest::RequestBuilder) -> Result<Value, String> {
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err("Rate limited by the quote provider, try again shortly".to_string());
+    }
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    // Yahoo reports unknown symbols as a 404 with an error body
+    if !status.is_success() && body.pointer("/chart/error").is_none() {
+        return Err(format!("Quote provider error: {}", status));
+    }
+    Ok(body)
+}
+
+async fn fetch_yahoo(symbol: &str) -> Result<Quote, String> {
+    let url = format!("{}{}", YAHOO_CHART_URL, urlencoding::encode(symbol));
+    let request = http_client()?
+        .get(url)
+        .query(&[("interval", "1d"), ("range", "1d")]);
+    parse_yahoo(&get_json(request).await?)
+}
+
+async fn fetch_coingecko(id: &str, vs_currency: &str) -> Result<Quote, String> {
+    let request = http_client()?.get(COINGECKO_PRICE_URL).query(&[
+        ("ids", id),
+        ("vs_currencies", vs_currency),
+        ("include_24hr_change", "true"),
+        ("include_last_updated_at", "true"),
+    ]);
+    parse_coingecko(&get_json(request).await?, id, vs_currency)
+}
+
+/// Quote for `symbol`; the provider is guessed from the symbol when omitted
+#[tauri::command]
+pub async fn get_quote(
+    manager: State<'_, FinanceManager>,
+    symbol: String,
+    provider: Option<QuoteProvider>,
+) -> Result<Quote, String> {
+    if symbol.trim().is_empty() {
+        return Err("No symbol given".to_string());
+    }
+    let provider = provider.unwrap_or_else(|| QuoteProvider::guess(&symbol));
+    manager
+        .quote(&normalize_symbol(&symbol, provider), provider)
+        .await
+}
+
+#[tauri::command]
+pub async fn get_watchlist(
+    manager: State<'_, FinanceManager>,
+) -> Result<Vec<WatchlistQuote>, String> {
+    let items = manager.list().map_err(|e| e.to_string())?;
+    let mut quotes = Vec::with_capacity(items.len());
+    for item in items {
+        let (quote, error) = match manager.quote(&item.symbol, item.provider).await {
+            Ok(quote) => (Some(quote), None),
+            Err(e) => (None, Some(e)),
+        };
+        quotes.push(WatchlistQuote { item, quote, error });
+    }
+    Ok(quotes)
+}
+
+#[tauri::command]
+pub fn add_to_watchlist(
+    manager: State<'_, FinanceManager>,
+    symbol: String,
+    provider: Option<QuoteProvider>,
+) -> Result<i64, String> {
+    if symbol.trim().is_empty() {
+        return Err("No symbol given".to_string());
+    }
+    let provider = provider.unwrap_or_else(|| QuoteProvider::guess(&symbol));
+    manager
+        .add(&normalize_symbol(&symbol, provider), provider)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_from_watchlist(manager: State<'_, FinanceManager>, id: i64) -> Result<(), String> {
+    manager.remove(id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn reorder_watchlist(manager: State<'_, FinanceManager>, ids: Vec<i64>) -> Result<(), String> {
+    manager.reorder(&ids).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_provider_guess_and_symbols() {
+        assert_eq!(QuoteProvider::guess("BTC"), QuoteProvider::CoinGecko);
+        assert_eq!(QuoteProvider::guess("aapl"), QuoteProvider::Yahoo);
+        assert_eq!(
+            normalize_symbol(" Eth ", QuoteProvider::CoinGecko),
+            "ethereum"
+        );
+        assert_eq!(normalize_symbol("^gspc", QuoteProvider::Yahoo), "^GSPC");
+    }
+
+    #[test]
+    fn test_parse_yahoo() {
+        let body = json!({ "chart": { "result": [{ "meta": {
+            "symbol": "AAPL",
+            "currency": "USD",
+            "longName": "Apple Inc.",
+            "regularMarketPrice": 110.0,
+            "chartPreviousClose": 100.0,
+            "regularMarketTime": 1736960400
+        }}], "error": null }});
+        let quote = parse_yahoo(&body).unwrap();
+        assert_eq!(quote.name.as_deref(), Some("Apple Inc."));
+        assert_eq!(quote.change, Some(10.0));
+        assert_eq!(quote.change_percent, Some(10.0));
+        assert!(quote.market_time.is_some());
+
+        let missing = json!({ "chart": { "result": null, "error": {
+            "code": "Not Found",
+            "description": "No data found, symbol may be delisted"
+        }}});
+        assert_eq!(
+            parse_yahoo(&missing).unwrap_err(),
+            "No data found, symbol may be delisted"
+        );
+    }
+
+    #[test]
+    fn test_parse_coingecko() {
+        let body = json!({ "bitcoin": {
+            "usd": 50000.0,
+            "usd_24h_change": 25.0,
+            "last_updated_at": 1736960400
+        }});
+        let quote = parse_coingecko(&body, "bitcoin", "usd").unwrap();
+        assert_eq!(quote.currency, "USD");
+        assert_eq!(quote.change, Some(10000.0));
+        assert!(parse_coingecko(&body, "ethereum", "usd").is_err());
+    }
+
+    #[test]
+    fn test_reserve_slot_spaces_requests() {
+        let manager = FinanceManager::new_for_test().unwrap();
+        let now = Instant::now();
+        let interval = QuoteProvider::CoinGecko.min_interval();
+        assert_eq!(
+            manager.reserve_slot(QuoteProvider::CoinGecko, now),
+            Duration::ZERO
+        );
+        assert_eq!(
+            manager.reserve_slot(QuoteProvider::CoinGecko, now),
+            interval
+        );
+        assert_eq!(
+            manager.reserve_slot(QuoteProvider::CoinGecko, now),
+            interval * 2
+        );
+        assert_eq!(
+            manager.reserve_slot(QuoteProvider::Yahoo, now),
+            Duration::ZERO
+        );
+        assert_eq!(
+            manager.reserve_slot(QuoteProvider::CoinGecko, now + interval * 10),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_watchlist_round_trip() {
+        let manager = FinanceManager::new_for_test().unwrap();
+        let aapl = manager.add("AAPL", QuoteProvider::Yahoo).unwrap();
+        let btc = manager.add("bitcoin", QuoteProvider::CoinGecko).unwrap();
+        assert_eq!(manager.add("AAPL", QuoteProvider::Yahoo).unwrap(), aapl);
+
+        manager.reorder(&[btc]).unwrap();
+        let items = manager.list().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].symbol, "bitcoin");
+        assert_eq!(items[1].provider, QuoteProvider::Yahoo);
+
+        manager.remove(btc).unwrap();
+        assert_eq!(manager.list().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,437 @@
+pub mod portal;
+pub mod raw_listener;
+pub mod sequence;
+pub mod types;
+
+use portal::{PortalBackend, PortalStatus};
+use sequence::SequenceDetector;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use types::{HotkeyBinding, HotkeyProbeResult, KeyCombo, Modifier};
+
+pub const TOGGLE_LAUNCHER_ID: &str = "toggle_launcher";
+/// Pastes the next item of the clipboard history paste stack
+pub const PASTE_STACK_NEXT_ID: &str = "paste_stack_next";
+/// Bindings named `snap_layout:<id>` cycle the active window through a snap layout
+pub const SNAP_LAYOUT_PREFIX: &str = "snap_layout:";
+/// Bindings named `ai_command:<id>` run a saved AI command
+pub const AI_COMMAND_PREFIX: &str = "ai_command:";
+/// Opens the menu of actions on the selected text
+pub const SELECTION_ACTIONS_ID: &str = "selection_actions";
+
+fn default_bindings() -> HashMap<String, HotkeyBinding> {
+    HashMap::from([(
+        TOGGLE_LAUNCHER_ID.to_string(),
+        HotkeyBinding::Combo {
+            combo: KeyCombo::new(vec![Modifier::Super, Modifier::Alt], "Space"),
+        },
+    )])
+}
+
+pub struct HotkeyManager {
+    app: AppHandle,
+    bindings: Mutex<HashMap<String, HotkeyBinding>>,
+    /// Accelerators currently registered with the global-shortcut plugin, by binding id
+    registered: Mutex<HashMap<String, Shortcut>>,
+    detector: Arc<Mutex<SequenceDetector>>,
+    raw_listener_running: Mutex<bool>,
+    /// On Wayland the plugin can't grab keys, so combos are matched from evdev too
+    prefer_raw_combos: bool,
+    /// Takes the combos on Wayland when the desktop has a GlobalShortcuts portal
+    portal: Option<Arc<PortalBackend>>,
+}
+
+impl HotkeyManager {
+    pub fn new(app: &AppHandle) -> Self {
+        let bindings = read_bindings(app).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to read hotkeys, using defaults");
+            default_bindings()
+        });
+
+        Self {
+            app: app.clone(),
+            bindings: Mutex::new(bindings),
+            registered: Mutex::new(HashMap::new()),
+            detector: Arc::new(Mutex::new(SequenceDetector::new())),
+            raw_listener_running: Mutex::new(false),
+            prefer_raw_combos: std::env::var("WAYLAND_DISPLAY").is_ok(),
+            portal: None,
+        }
+    }
+
+    pub fn list(&self) -> HashMap<String, HotkeyBinding> {
+        self.bindings.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, id: String, binding: HotkeyBinding) -> Result<(), String> {
+        self.bindings.lock().unwrap().insert(id, binding);
+        self.persist()?;
+        self.apply()
+    }
+
+    pub fn remove(&self, id: &str) -> Result<(), String> {
+        self.bindings.lock().unwrap().remove(id);
+        self.persist()?;
+        self.apply()
+    }
+
+    /// The first other binding (by id) that would fire together with `binding`
+    pub fn find_conflict(&self, id: &str, binding: &HotkeyBinding) -> Option<String> {
+        let mut conflicts: Vec<String> = self
+            .list()
+            .into_iter()
+            .filter(|(other, existing)| other != id && existing.conflicts_with(binding))
+            .map(|(other, _)| other)
+            .collect();
+        conflicts.sort();
+        conflicts.into_iter().next()
+    }
+
+    fn active_portal(&self) -> Option<&Arc<PortalBackend>> {
+        self.portal.as_ref().filter(|portal| portal.is_enabled())
+    }
+
+    pub fn portal_status(&self) -> Option<PortalStatus> {
+        self.portal.as_ref().map(|portal| portal.status())
+    }
+
+    /// Re-registers every binding, routing combos to the portal, the plugin or
+    /// the raw listener and everything else to the raw listener
+    pub fn apply(&self) -> Result<(), String> {
+        let bindings = self.list();
+
+        self.unregister_all();
+
+        let portal = self.active_portal();
+        let use_raw_combos =
+            portal.is_none() && self.prefer_raw_combos && self.ensure_raw_listener(true);
+
+        let mut errors = Vec::new();
+        if let Some(portal) = portal {
+            let combos = bindings
+                .iter()
+                .filter_map(|(id, binding)| match binding {
+                    HotkeyBinding::Combo { combo } => Some((id.clone(), combo.clone())),
+                    _ => None,
+                })
+                .collect();
+            portal.bind_in_background(combos);
+        } else if !use_raw_combos {
+            for (id, binding) in &bindings {
+                if let HotkeyBinding::Combo { combo } = binding {
+                    if let Err(e) = self.register_combo(id, combo) {
+                        tracing::error!(id = %id, error = %e, "Failed to register hotkey");
+                        errors.push(format!("{}: {}", id, e));
+                    }
+                }
+            }
+        }
+
+        let raw_bindings: Vec<(String, HotkeyBinding)> = bindings.into_iter().collect();
+        let needs_raw = use_raw_combos || raw_bindings.iter().any(|(_, b)| b.requires_raw_events());
+        self.detector
+            .lock()
+            .unwrap()
+            .set_bindings(raw_bindings, use_raw_combos);
+
+        if needs_raw && !self.ensure_raw_listener(false) {
+            errors.push(
+                "Double-tap and chord hotkeys need read access to /dev/input \
+                 (add your user to the input group)"
+                    .to_string(),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    /// Tries to grab `combo` briefly and releases it again, so the settings UI can
+    /// warn about combos the desktop environment has already claimed.
+    pub fn probe(&self, combo: &KeyCombo) -> HotkeyProbeResult {
+        let combo = combo.normalized();
+        let conflicts_with = self.find_conflict(
+            "",
+            &HotkeyBinding::Combo {
+                combo: combo.clone(),
+            },
+        );
+        if let Some(id) = conflicts_with {
+            return HotkeyProbeResult {
+                available: false,
+                verified: true,
+                reason: Some(format!("Already bound to '{}'", id)),
+                conflicts_with: Some(id),
+            };
+        }
+
+        let accelerator = combo.to_accelerator();
+        let shortcut: Shortcut = match accelerator.parse() {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                return HotkeyProbeResult {
+                    available: false,
+                    verified: true,
+                    conflicts_with: None,
+                    reason: Some(format!("Invalid hotkey '{}': {}", accelerator, e)),
+                }
+            }
+        };
+
+        if self.active_portal().is_some() {
+            // The portal assigns the trigger itself and may ask the user first
+            return HotkeyProbeResult {
+                available: true,
+                verified: false,
+                conflicts_with: None,
+                reason: None,
+            };
+        }
+
+        if *self.raw_listener_running.lock().unwrap() && self.prefer_raw_combos {
+            // evdev sees every key press, so nothing can be "taken"; the compositor
+            // may still act on the same combo
+            return HotkeyProbeResult {
+                available: true,
+                verified: false,
+                conflicts_with: None,
+                reason: None,
+            };
+        }
+
+        let global_shortcut = self.app.global_shortcut();
+        match global_shortcut.register(shortcut) {
+            Ok(()) => {
+                if let Err(e) = global_shortcut.unregister(shortcut) {
+                    tracing::warn!(
+                        accelerator = %accelerator,
+                        error = %e,
+                        "Failed to release probed hotkey"
+                    );
+                }
+                HotkeyProbeResult {
+                    available: true,
+                    verified: true,
+                    conflicts_with: None,
+                    reason: None,
+                }
+            }
+            Err(e) => {
+                tracing::debug!(accelerator = %accelerator, error = %e, "Hotkey probe rejected");
+                HotkeyProbeResult {
+                    available: false,
+                    verified: true,
+                    conflicts_with: None,
+                    reason: Some(e.to_string()),
+                }
+            }
+        }
+    }
+
+    fn register_combo(&self, id: &str, combo: &KeyCombo) -> Result<(), String> {
+        let accelerator = combo.to_accelerator();
+        let shortcut: Shortcut = accelerator
+            .parse()
+            .map_err(|e| format!("Invalid hotkey '{}': {}", accelerator, e))?;
+
+        let binding_id = id.to_string();
+        self.app
+            .global_shortcut()
+            .on_shortcut(shortcut, move |app, _shortcut, event| {
+                if event.state() == ShortcutState::Pressed {
+                    trigger(app, &binding_id);
+                }
+            })
+            .map_err(|e| e.to_string())?;
+
+        tracing::info!(id = %id, accelerator = %accelerator, "Registered global hotkey");
+        self.registered
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), shortcut);
+        Ok(())
+    }
+
+    fn unregister_all(&self) {
+        let mut registered = self.registered.lock().unwrap();
+        for (id, shortcut) in registered.drain() {
+            if let Err(e) = self.app.global_shortcut().unregister(shortcut) {
+                tracing::warn!(id = %id, error = %e, "Failed to unregister hotkey");
+            }
+        }
+    }
+
+    /// Starts the evdev listener once. `quiet` suppresses the error log when the
+    /// caller has its own fallback.
+    fn ensure_raw_listener(&self, quiet: bool) -> bool {
+        let mut running = self.raw_listener_running.lock().unwrap();
+        if *running {
+            return true;
+        }
+
+        let app = self.app.clone();
+        let on_trigger: Arc<dyn Fn(String) + Send + Sync> = Arc::new(move |id: String| {
+            trigger(&app, &id);
+        });
+
+        match raw_listener::start(Arc::clone(&self.detector), on_trigger) {
+            Ok(()) => {
+                tracing::info!("Raw hotkey listener started");
+                *running = true;
+                true
+            }
+            Err(e) => {
+                if quiet {
+                    tracing::debug!(error = %e, "Raw hotkey listener unavailable");
+                } else {
+                    tracing::error!(error = %e, "Failed to start raw hotkey listener");
+                }
+                false
+            }
+        }
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let path = get_hotkeys_path(&self.app)?;
+        let content = serde_json::to_string_pretty(&*self.bindings.lock().unwrap())
+            .map_err(|e| e.to_string())?;
+        fs::write(path, content).map_err(|e| e.to_string())
+    }
+}
+
+/// Runs the action bound to `id`. The launcher toggle, the paste stack, the
+/// selection menu and snap layouts are handled natively, everything else is
+/// forwarded to the frontend.
+pub(crate) fn trigger(app: &AppHandle, id: &str) {
+    tracing::debug!(id = %id, "Hotkey triggered");
+    if id == TOGGLE_LAUNCHER_ID {
+        toggle_main_window(app);
+    } else if id == PASTE_STACK_NEXT_ID {
+        crate::clipboard_history::paste_stack_hotkey(app);
+    } else if id == SELECTION_ACTIONS_ID {
+        crate::selection_actions::open_menu(app);
+    } else if let Some(layout_id) = id
+        .strip_prefix(SNAP_LAYOUT_PREFIX)
+        .and_then(|layout_id| layout_id.parse::<i64>().ok())
+    {
+        if let Err(e) = crate::window_management::snap_to_layout(app, layout_id, None) {
+            tracing::error!(layout_id, error = %e, "Failed to snap window to layout");
+        }
+    } else if let Some(command_id) = id.strip_prefix(AI_COMMAND_PREFIX) {
+        crate::ai::commands::run_from_hotkey(app, command_id);
+    } else if let Err(e) = app.emit("hotkey-triggered", id) {
+        tracing::error!(id = %id, error = %e, "Failed to emit hotkey event");
+    }
+}
+
+pub(crate) fn toggle_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        tracing::error!("Main window not found");
+        return;
+    };
+
+    match window.is_visible() {
+        Ok(true) => {
+            tracing::debug!("Window visible, hiding");
+            let _ = window.hide();
+        }
+        Ok(false) => {
+            tracing::debug!("Window hidden, showing");
+            crate::activation::present(&window);
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to check window visibility");
+        }
+    }
+}
+
+fn get_hotkeys_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| "Failed to get app local data dir".to_string())?;
+
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(data_dir.join("hotkeys.json"))
+}
+
+fn read_bindings(app: &AppHandle) -> Result<HashMap<String, HotkeyBinding>, String> {
+    let path = get_hotkeys_path(app)?;
+    if !path.exists() {
+        return Ok(default_bindings());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if content.trim().is_empty() {
+        return Ok(default_bindings());
+    }
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+/// Creates the manager, registers all saved hotkeys and puts it in app state
+pub fn init(app: &AppHandle) {
+    let mut manager = HotkeyManager::new(app);
+    if manager.prefer_raw_combos {
+        manager.portal = tauri::async_runtime::block_on(PortalBackend::connect(app));
+    }
+    // Managed first, so a portal that refuses can re-apply from its task
+    app.manage(manager);
+    if let Err(e) = app.state::<HotkeyManager>().apply() {
+        tracing::error!(error = %e, "Some hotkeys could not be registered");
+    }
+}
+
+#[tauri::command]
+pub fn hotkey_list(
+    manager: tauri::State<HotkeyManager>,
+) -> Result<HashMap<String, HotkeyBinding>, String> {
+    Ok(manager.list())
+}
+
+#[tauri::command]
+pub fn hotkey_set(
+    manager: tauri::State<HotkeyManager>,
+    id: String,
+    binding: HotkeyBinding,
+) -> Result<(), String> {
+    manager.set(id, binding)
+}
+
+#[tauri::command]
+pub fn hotkey_remove(manager: tauri::State<HotkeyManager>, id: String) -> Result<(), String> {
+    manager.remove(&id)
+}
+
+#[tauri::command]
+pub fn probe_hotkey(
+    manager: tauri::State<HotkeyManager>,
+    modifiers: Vec<Modifier>,
+    key: String,
+) -> HotkeyProbeResult {
+    manager.probe(&KeyCombo::new(modifiers, &key))
+}
+
+/// Whether the portal backend is in use and the triggers it assigned
+#[tauri::command]
+pub fn hotkey_portal_status(manager: tauri::State<HotkeyManager>) -> Option<PortalStatus> {
+    manager.portal_status()
+}
+
+/// Opens the desktop's own dialog for changing portal shortcut triggers
+#[tauri::command]
+pub async fn hotkey_portal_configure(
+    manager: tauri::State<'_, HotkeyManager>,
+) -> Result<(), String> {
+    match &manager.portal {
+        Some(portal) => portal.configure().await,
+        None => Err("Hotkeys aren't bound through the desktop portal".to_string()),
+    }
+}
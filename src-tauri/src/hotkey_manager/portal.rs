@@ -0,0 +1,495 @@
+//! Global shortcuts through the xdg-desktop-portal GlobalShortcuts
+//! interface, for Wayland compositors that don't let the plugin grab keys.
+//!
+//! Every combo binding is bound under its binding id, so the portal keeps
+//! the trigger the user approved or changed for that id across sessions and
+//! restarts. The triggers it reports back are saved next to the hotkeys so
+//! the settings UI can show them before the portal answers.
+
+use super::types::{KeyCombo, Modifier};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+const PORTAL_SERVICE: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SHORTCUTS_INTERFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+/// Binding can show a dialog, so the user gets a while to answer it
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// `a(sa{sv})`: shortcut ids with their properties
+type ShortcutEntries = Vec<(String, HashMap<String, OwnedValue>)>;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PortalShortcut {
+    pub id: String,
+    pub description: String,
+    /// How the portal describes the trigger it assigned, e.g. `Logo+Alt+Space`
+    pub trigger: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PortalStatus {
+    pub active: bool,
+    pub version: u32,
+    /// Version 2 can open the desktop's own dialog for changing triggers
+    pub can_configure: bool,
+    pub shortcuts: Vec<PortalShortcut>,
+}
+
+pub struct PortalBackend {
+    app: AppHandle,
+    connection: zbus::Connection,
+    version: u32,
+    enabled: AtomicBool,
+    session: tokio::sync::Mutex<Option<OwnedObjectPath>>,
+    /// Session path the signal listener matches against
+    current_session: Mutex<Option<String>>,
+    shortcuts: Mutex<Vec<PortalShortcut>>,
+}
+
+/// Shortcut trigger in the shortcuts spec format, e.g. `LOGO+ALT+space`
+pub fn preferred_trigger(combo: &KeyCombo) -> String {
+    let combo = combo.normalized();
+    let mut parts: Vec<String> = combo
+        .modifiers
+        .iter()
+        .map(|modifier| {
+            match modifier {
+                Modifier::Ctrl => "CTRL",
+                Modifier::Alt => "ALT",
+                Modifier::Shift => "SHIFT",
+                Modifier::Super => "LOGO",
+            }
+            .to_string()
+        })
+        .collect();
+    parts.push(keysym(&combo.key));
+    parts.join("+")
+}
+
+/// XKB keysym name for a W3C key code
+fn keysym(code: &str) -> String {
+    if let Some(letter) = code.strip_prefix("Key") {
+        return letter.to_lowercase();
+    }
+    if let Some(digit) = code.strip_prefix("Digit") {
+        return digit.to_string();
+    }
+    match code {
+        "Space" => "space",
+        "Enter" => "Return",
+        "Backspace" => "BackSpace",
+        "ArrowUp" => "Up",
+        "ArrowDown" => "Down",
+        "ArrowLeft" => "Left",
+        "ArrowRight" => "Right",
+        "PageUp" => "Prior",
+        "PageDown" => "Next",
+        "Minus" => "minus",
+        "Equal" => "equal",
+        "Comma" => "comma",
+        "Period" => "period",
+        "Slash" => "slash",
+        "Semicolon" => "semicolon",
+        "Backquote" => "grave",
+        other => other,
+    }
+    .to_string()
+}
+
+fn describe(id: &str) -> String {
+    if id == super::TOGGLE_LAUNCHER_ID {
+        "Show or hide Flare".to_string()
+    } else if id == super::PASTE_STACK_NEXT_ID {
+        "Paste the next clipboard stack item".to_string()
+    } else if id == super::SELECTION_ACTIONS_ID {
+        "Show actions for the selected text".to_string()
+    } else {
+        id.to_string()
+    }
+}
+
+/// Where the portal will emit the Response for a request made with `token`
+fn request_path(unique_name: &str, token: &str) -> String {
+    let sender = unique_name.trim_start_matches(':').replace('.', "_");
+    format!("{}/request/{}/{}", PORTAL_PATH, sender, token)
+}
+
+fn new_token() -> String {
+    format!("flare_{}", uuid::Uuid::new_v4().simple())
+}
+
+fn string_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Str(s) => Some(s.to_string()),
+        Value::ObjectPath(path) => Some(path.to_string()),
+        _ => None,
+    }
+}
+
+fn shortcuts_from(entries: ShortcutEntries) -> Vec<PortalShortcut> {
+    entries
+        .into_iter()
+        .map(|(id, properties)| PortalShortcut {
+            description: properties
+                .get("description")
+                .and_then(|value| string_value(value))
+                .unwrap_or_else(|| describe(&id)),
+            trigger: properties
+                .get("trigger_description")
+                .and_then(|value| string_value(value))
+                .filter(|trigger| !trigger.is_empty()),
+            id,
+        })
+        .collect()
+}
+
+fn shortcuts_path(app: &AppHandle) -> Result<PathBuf, String> {
+    super::get_hotkeys_path(app).map(|path| path.with_file_name("portal_shortcuts.json"))
+}
+
+fn read_saved(app: &AppHandle) -> Vec<PortalShortcut> {
+    shortcuts_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+impl PortalBackend {
+    /// The backend, when the session bus has a GlobalShortcuts portal
+    pub async fn connect(app: &AppHandle) -> Option<Arc<Self>> {
+        let connection = zbus::Connection::session().await.ok()?;
+        let proxy = zbus::Proxy::new(
+            &connection,
+            PORTAL_SERVICE,
+            PORTAL_PATH,
+            SHORTCUTS_INTERFACE,
+        )
+        .await
+        .ok()?;
+        let version = match proxy.get_property::<u32>("version").await {
+            Ok(version) => version,
+            Err(e) => {
+                tracing::debug!(error = %e, "GlobalShortcuts portal unavailable");
+                return None;
+            }
+        };
+        tracing::info!(version, "Using the GlobalShortcuts portal for hotkeys");
+
+        let backend = Arc::new(Self {
+            app: app.clone(),
+            connection,
+            version,
+            enabled: AtomicBool::new(true),
+            session: tokio::sync::Mutex::new(None),
+            current_session: Mutex::new(None),
+            shortcuts: Mutex::new(read_saved(app)),
+        });
+        let listener = Arc::clone(&backend);
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = listener.listen().await {
+                tracing::error!(error = %e, "Stopped listening to the GlobalShortcuts portal");
+            }
+        });
+        Some(backend)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    pub fn status(&self) -> PortalStatus {
+        PortalStatus {
+            active: self.is_enabled(),
+            version: self.version,
+            can_configure: self.version >= 2,
+            shortcuts: self.shortcuts.lock().unwrap().clone(),
+        }
+    }
+
+    /// Replaces the bound shortcuts with `combos`. If the portal refuses, it's
+    /// disabled and the hotkeys are applied again without it.
+    pub fn bind_in_background(self: &Arc<Self>, combos: Vec<(String, KeyCombo)>) {
+        let backend = Arc::clone(self);
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = backend.rebind(combos).await {
+                tracing::error!(error = %e, "GlobalShortcuts portal failed, falling back");
+                backend.enabled.store(false, Ordering::SeqCst);
+                if let Some(manager) = backend.app.try_state::<super::HotkeyManager>() {
+                    if let Err(e) = manager.apply() {
+                        tracing::error!(error = %e, "Some hotkeys could not be registered");
+                    }
+                }
+            }
+        });
+    }
+
+    /// Opens the desktop's dialog for changing the triggers
+    pub async fn configure(&self) -> Result<(), String> {
+        if self.version < 2 {
+            return Err("This desktop can only change shortcuts in its own settings".to_string());
+        }
+        let session = self.session.lock().await;
+        let session = session.as_ref().ok_or("No shortcuts are bound yet")?;
+        let options: HashMap<&str, Value> = HashMap::new();
+        self.connection
+            .call_method(
+                Some(PORTAL_SERVICE),
+                PORTAL_PATH,
+                Some(SHORTCUTS_INTERFACE),
+                "ConfigureShortcuts",
+                &(session, "", options),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// A portal session can only bind once, so every change starts a new one
+    async fn rebind(&self, combos: Vec<(String, KeyCombo)>) -> Result<(), String> {
+        let mut session = self.session.lock().await;
+        if let Some(old) = session.take() {
+            self.close_session(&old).await;
+        }
+        if combos.is_empty() {
+            return Ok(());
+        }
+
+        let token = new_token();
+        let options = HashMap::from([
+            ("handle_token", Value::from(token.as_str())),
+            ("session_handle_token", Value::from(new_token())),
+        ]);
+        let results = self.request("CreateSession", &token, &(options,)).await?;
+        let path = results
+            .get("session_handle")
+            .and_then(|value| string_value(value))
+            .ok_or("The portal didn't return a session")?;
+        let path = OwnedObjectPath::try_from(path).map_err(|e| e.to_string())?;
+        *self.current_session.lock().unwrap() = Some(path.to_string());
+
+        let shortcuts: Vec<(String, HashMap<&str, Value>)> = combos
+            .iter()
+            .map(|(id, combo)| {
+                let properties = HashMap::from([
+                    ("description", Value::from(describe(id))),
+                    ("preferred_trigger", Value::from(preferred_trigger(combo))),
+                ]);
+                (id.clone(), properties)
+            })
+            .collect();
+        let token = new_token();
+        let options = HashMap::from([("handle_token", Value::from(token.as_str()))]);
+        let body = (ObjectPath::from(&path), shortcuts, "", options);
+        let mut results = self.request("BindShortcuts", &token, &body).await?;
+        *session = Some(path);
+
+        let bound = results
+            .remove("shortcuts")
+            .and_then(|value| ShortcutEntries::try_from(value).ok())
+            .map(shortcuts_from)
+            .unwrap_or_default();
+        tracing::info!(count = bound.len(), "Bound hotkeys through the portal");
+        self.update_shortcuts(bound);
+        Ok(())
+    }
+
+    async fn close_session(&self, session: &OwnedObjectPath) {
+        let closed = self
+            .connection
+            .call_method(
+                Some(PORTAL_SERVICE),
+                session.as_str(),
+                Some("org.freedesktop.portal.Session"),
+                "Close",
+                &(),
+            )
+            .await;
+        if let Err(e) = closed {
+            tracing::debug!(error = %e, "Failed to close GlobalShortcuts session");
+        }
+    }
+
+    /// Calls a method that answers through a Request object and waits for
+    /// its Response. `token` is the `handle_token` the caller put in the
+    /// options; the match is set up first so a fast answer isn't lost.
+    async fn request<B>(
+        &self,
+        method: &str,
+        token: &str,
+        body: &B,
+    ) -> Result<HashMap<String, OwnedValue>, String>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        let unique_name = self
+            .connection
+            .unique_name()
+            .ok_or("Not connected to the session bus")?
+            .to_string();
+        let rule = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.portal.Request")
+            .and_then(|rule| rule.member("Response"))
+            .and_then(|rule| rule.path(request_path(&unique_name, token)))
+            .map_err(|e| e.to_string())?
+            .build();
+        let mut responses = zbus::MessageStream::for_match_rule(rule, &self.connection, Some(1))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        self.connection
+            .call_method(
+                Some(PORTAL_SERVICE),
+                PORTAL_PATH,
+                Some(SHORTCUTS_INTERFACE),
+                method,
+                body,
+            )
+            .await
+            .map_err(|e| format!("{} failed: {}", method, e))?;
+
+        let message = tokio::time::timeout(REQUEST_TIMEOUT, responses.next())
+            .await
+            .map_err(|_| format!("The portal didn't answer {}", method))?
+            .ok_or("The session bus closed")?
+            .map_err(|e| e.to_string())?;
+        let (code, results): (u32, HashMap<String, OwnedValue>) =
+            message.body().deserialize().map_err(|e| e.to_string())?;
+        match code {
+            0 => Ok(results),
+            1 => Err(format!("{} was cancelled", method)),
+            _ => Err(format!("{} was refused", method)),
+        }
+    }
+
+    fn update_shortcuts(&self, shortcuts: Vec<PortalShortcut>) {
+        *self.shortcuts.lock().unwrap() = shortcuts.clone();
+        match shortcuts_path(&self.app) {
+            Ok(path) => {
+                let content = serde_json::to_string_pretty(&shortcuts).unwrap_or_default();
+                if let Err(e) = fs::write(path, content) {
+                    tracing::warn!(error = %e, "Failed to save portal shortcuts");
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "Failed to save portal shortcuts"),
+        }
+        let _ = self.app.emit("hotkey-portal-changed", shortcuts);
+    }
+
+    /// Runs bindings on `Activated` and picks up triggers changed in the
+    /// desktop settings. Runs until the bus connection closes.
+    async fn listen(&self) -> Result<(), String> {
+        let rule = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface(SHORTCUTS_INTERFACE)
+            .map_err(|e| e.to_string())?
+            .build();
+        let mut stream = zbus::MessageStream::for_match_rule(rule, &self.connection, None)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        while let Some(message) = stream.next().await {
+            let Ok(message) = message else {
+                continue;
+            };
+            let header = message.header();
+            let Some(member) = header.member() else {
+                continue;
+            };
+            let current = self.current_session.lock().unwrap().clone();
+            match member.as_str() {
+                "Activated" => {
+                    let body = message.body();
+                    let Ok((session, id, _, options)) = body.deserialize::<(
+                        OwnedObjectPath,
+                        String,
+                        u64,
+                        HashMap<String, OwnedValue>,
+                    )>() else {
+                        continue;
+                    };
+                    if current.as_deref() == Some(session.as_str()) && self.is_enabled() {
+                        // Lets the launcher take focus if this shortcut shows it
+                        if let Some(token) = options
+                            .get("activation_token")
+                            .and_then(|value| string_value(value))
+                        {
+                            crate::activation::set_activation_token(token);
+                        }
+                        super::trigger(&self.app, &id);
+                    }
+                }
+                "ShortcutsChanged" => {
+                    let body = message.body();
+                    let Ok((session, entries)) =
+                        body.deserialize::<(OwnedObjectPath, ShortcutEntries)>()
+                    else {
+                        continue;
+                    };
+                    if current.as_deref() == Some(session.as_str()) {
+                        self.update_shortcuts(shortcuts_from(entries));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferred_trigger() {
+        let combo = KeyCombo::new(vec![Modifier::Super, Modifier::Alt], "Space");
+        assert_eq!(preferred_trigger(&combo), "ALT+LOGO+space");
+        let combo = KeyCombo::new(vec![Modifier::Ctrl, Modifier::Shift], "k");
+        assert_eq!(preferred_trigger(&combo), "CTRL+SHIFT+k");
+        assert_eq!(preferred_trigger(&KeyCombo::new(vec![], "F5")), "F5");
+        assert_eq!(
+            preferred_trigger(&KeyCombo::new(vec![Modifier::Super], "left")),
+            "LOGO+Left"
+        );
+    }
+
+    #[test]
+    fn test_request_path() {
+        assert_eq!(
+            request_path(":1.245", "flare_abc"),
+            "/org/freedesktop/portal/desktop/request/1_245/flare_abc"
+        );
+    }
+
+    #[test]
+    fn test_shortcuts_from_portal_reply() {
+        let entries = vec![(
+            super::super::TOGGLE_LAUNCHER_ID.to_string(),
+            HashMap::from([(
+                "trigger_description".to_string(),
+                OwnedValue::try_from(Value::from("Logo+Alt+Space")).unwrap(),
+            )]),
+        )];
+        assert_eq!(
+            shortcuts_from(entries),
+            vec![PortalShortcut {
+                id: "toggle_launcher".to_string(),
+                description: "Show or hide Flare".to_string(),
+                trigger: Some("Logo+Alt+Space".to_string()),
+            }]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use super::sequence::{RawKey, RawKeyEvent, SequenceDetector};
+use super::types::{normalize_key, Modifier};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+#[cfg(target_os = "linux")]
+use evdev::KeyCode;
+
+/// Reads key events straight from /dev/input and feeds them to the detector.
+///
+/// rdev can't be used here: its listener is a process-wide singleton that the
+/// snippet engine already owns on X11. Reading evdev needs the user to be in
+/// the `input` group, the same requirement as Wayland snippet expansion.
+#[cfg(target_os = "linux")]
+pub fn start(
+    detector: Arc<Mutex<SequenceDetector>>,
+    on_trigger: Arc<dyn Fn(String) + Send + Sync>,
+) -> Result<(), String> {
+    let devices: Vec<_> = evdev::enumerate()
+        .map(|(_, device)| device)
+        .filter(|d| {
+            d.supported_keys()
+                .is_some_and(|keys| keys.contains(KeyCode::KEY_ENTER))
+        })
+        .collect();
+
+    if devices.is_empty() {
+        return Err("No keyboard devices found. Check permissions for /dev/input/*.".to_string());
+    }
+
+    for mut device in devices {
+        let detector = Arc::clone(&detector);
+        let on_trigger = Arc::clone(&on_trigger);
+        let device_name = device.name().unwrap_or("Unnamed Device").to_string();
+
+        thread::spawn(move || loop {
+            match device.fetch_events() {
+                Ok(events) => {
+                    for ev in events {
+                        if ev.event_type() != evdev::EventType::KEY {
+                            continue;
+                        }
+                        let key = map_evdev_key(KeyCode::new(ev.code()));
+                        let event = match ev.value() {
+                            0 => RawKeyEvent::Up(key),
+                            1 => RawKeyEvent::Down(key),
+                            // Auto-repeat
+                            _ => continue,
+                        };
+
+                        let triggered = detector
+                            .lock()
+                            .ok()
+                            .and_then(|mut d| d.handle(event, Instant::now()));
+                        if let Some(id) = triggered {
+                            on_trigger(id);
+                        }
+                    }
+                }
+                Err(e) => {
+                    if e.kind() != std::io::ErrorKind::WouldBlock {
+                        tracing::error!(
+                            device = %device_name,
+                            error = %e,
+                            "Error fetching evdev events for hotkeys"
+                        );
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn start(
+    _detector: Arc<Mutex<SequenceDetector>>,
+    _on_trigger: Arc<dyn Fn(String) + Send + Sync>,
+) -> Result<(), String> {
+    Err("Raw key listening is only supported on Linux".to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn map_evdev_key(code: KeyCode) -> RawKey {
+    match code {
+        KeyCode::KEY_LEFTCTRL | KeyCode::KEY_RIGHTCTRL => RawKey::Modifier(Modifier::Ctrl),
+        KeyCode::KEY_LEFTALT | KeyCode::KEY_RIGHTALT => RawKey::Modifier(Modifier::Alt),
+        KeyCode::KEY_LEFTSHIFT | KeyCode::KEY_RIGHTSHIFT => RawKey::Modifier(Modifier::Shift),
+        KeyCode::KEY_LEFTMETA | KeyCode::KEY_RIGHTMETA => RawKey::Modifier(Modifier::Super),
+        other => {
+            // Debug output is the kernel name, e.g. KEY_A, KEY_SPACE, KEY_F5
+            let name = format!("{:?}", other);
+            let name = name.strip_prefix("KEY_").unwrap_or(&name);
+            RawKey::Other(normalize_key(name))
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_evdev_key() {
+        assert_eq!(
+            map_evdev_key(KeyCode::KEY_RIGHTCTRL),
+            RawKey::Modifier(Modifier::Ctrl)
+        );
+        assert_eq!(
+            map_evdev_key(KeyCode::KEY_A),
+            RawKey::Other("KeyA".to_string())
+        );
+        assert_eq!(
+            map_evdev_key(KeyCode::KEY_SPACE),
+            RawKey::Other("Space".to_string())
+        );
+        assert_eq!(
+            map_evdev_key(KeyCode::KEY_ESC),
+            RawKey::Other("Escape".to_string())
+        );
+    }
+}
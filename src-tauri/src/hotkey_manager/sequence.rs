@@ -0,0 +1,318 @@
+use super::types::{HotkeyBinding, KeyCombo, Modifier};
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+/// Maximum gap between the two taps of a double-tap modifier
+pub const DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(350);
+/// How long a chord prefix stays armed waiting for the second key
+pub const CHORD_TIMEOUT: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawKey {
+    Modifier(Modifier),
+    /// Any non-modifier key, as a normalized W3C code name (e.g. `KeyA`)
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawKeyEvent {
+    Down(RawKey),
+    Up(RawKey),
+}
+
+/// State machine that turns a stream of raw key events into triggered binding ids.
+///
+/// Used for bindings the global-shortcut plugin can't express (double taps, chords),
+/// and for plain combos when running on a compositor where the plugin can't grab keys.
+pub struct SequenceDetector {
+    bindings: Vec<(String, HotkeyBinding)>,
+    match_combos: bool,
+    held_modifiers: BTreeSet<Modifier>,
+    tap_candidate: Option<Modifier>,
+    last_tap: Option<(Modifier, Instant)>,
+    armed_prefix: Option<(KeyCombo, Instant)>,
+}
+
+impl SequenceDetector {
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+            match_combos: false,
+            held_modifiers: BTreeSet::new(),
+            tap_candidate: None,
+            last_tap: None,
+            armed_prefix: None,
+        }
+    }
+
+    /// Replace the bindings the detector is watching for.
+    /// When `match_combos` is set, plain combos are detected here as well.
+    pub fn set_bindings(&mut self, bindings: Vec<(String, HotkeyBinding)>, match_combos: bool) {
+        self.bindings = bindings
+            .into_iter()
+            .filter(|(_, binding)| match_combos || binding.requires_raw_events())
+            .map(|(id, binding)| (id, normalize_binding(binding)))
+            .collect();
+        self.match_combos = match_combos;
+        self.armed_prefix = None;
+        self.last_tap = None;
+    }
+
+    pub fn handle(&mut self, event: RawKeyEvent, now: Instant) -> Option<String> {
+        match event {
+            RawKeyEvent::Down(RawKey::Modifier(modifier)) => {
+                // Key repeat sends repeated downs; only a fresh, lone press counts as a tap
+                if self.held_modifiers.insert(modifier) {
+                    self.tap_candidate = if self.held_modifiers.len() == 1 {
+                        Some(modifier)
+                    } else {
+                        None
+                    };
+                }
+                None
+            }
+            RawKeyEvent::Up(RawKey::Modifier(modifier)) => {
+                self.held_modifiers.remove(&modifier);
+                if self.tap_candidate.take() != Some(modifier) {
+                    return None;
+                }
+
+                match self.last_tap {
+                    Some((last, at))
+                        if last == modifier && now.duration_since(at) <= DOUBLE_TAP_WINDOW =>
+                    {
+                        self.last_tap = None;
+                        self.find(|binding| {
+                            matches!(binding, HotkeyBinding::DoubleTap { modifier: m } if *m == modifier)
+                        })
+                    }
+                    _ => {
+                        self.last_tap = Some((modifier, now));
+                        None
+                    }
+                }
+            }
+            RawKeyEvent::Down(RawKey::Other(key)) => {
+                self.tap_candidate = None;
+                self.last_tap = None;
+
+                let combo = KeyCombo::new(self.held_modifiers.iter().copied().collect(), &key);
+
+                if let Some((prefix, armed_at)) = self.armed_prefix.take() {
+                    if now.duration_since(armed_at) <= CHORD_TIMEOUT {
+                        let found = self.find(|binding| {
+                            matches!(binding, HotkeyBinding::Chord { prefix: p, key: k } if *p == prefix && *k == combo)
+                        });
+                        if found.is_some() {
+                            return found;
+                        }
+                    }
+                }
+
+                let is_prefix = self.bindings.iter().any(|(_, binding)| {
+                    matches!(binding, HotkeyBinding::Chord { prefix, .. } if *prefix == combo)
+                });
+                if is_prefix {
+                    self.armed_prefix = Some((combo, now));
+                    return None;
+                }
+
+                if self.match_combos {
+                    return self.find(|binding| {
+                        matches!(binding, HotkeyBinding::Combo { combo: c } if *c == combo)
+                    });
+                }
+                None
+            }
+            RawKeyEvent::Up(RawKey::Other(_)) => None,
+        }
+    }
+
+    fn find<F>(&self, predicate: F) -> Option<String>
+    where
+        F: Fn(&HotkeyBinding) -> bool,
+    {
+        self.bindings
+            .iter()
+            .find(|(_, binding)| predicate(binding))
+            .map(|(id, _)| id.clone())
+    }
+}
+
+fn normalize_binding(binding: HotkeyBinding) -> HotkeyBinding {
+    match binding {
+        HotkeyBinding::Combo { combo } => HotkeyBinding::Combo {
+            combo: combo.normalized(),
+        },
+        HotkeyBinding::Chord { prefix, key } => HotkeyBinding::Chord {
+            prefix: prefix.normalized(),
+            key: key.normalized(),
+        },
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn down_mod(m: Modifier) -> RawKeyEvent {
+        RawKeyEvent::Down(RawKey::Modifier(m))
+    }
+
+    fn up_mod(m: Modifier) -> RawKeyEvent {
+        RawKeyEvent::Up(RawKey::Modifier(m))
+    }
+
+    fn down_key(k: &str) -> RawKeyEvent {
+        RawKeyEvent::Down(RawKey::Other(k.to_string()))
+    }
+
+    fn detector_with(bindings: Vec<(&str, HotkeyBinding)>, match_combos: bool) -> SequenceDetector {
+        let mut detector = SequenceDetector::new();
+        detector.set_bindings(
+            bindings
+                .into_iter()
+                .map(|(id, b)| (id.to_string(), b))
+                .collect(),
+            match_combos,
+        );
+        detector
+    }
+
+    #[test]
+    fn test_double_tap_triggers_within_window() {
+        let mut detector = detector_with(
+            vec![(
+                "launcher",
+                HotkeyBinding::DoubleTap {
+                    modifier: Modifier::Ctrl,
+                },
+            )],
+            false,
+        );
+        let start = Instant::now();
+
+        assert_eq!(detector.handle(down_mod(Modifier::Ctrl), start), None);
+        assert_eq!(detector.handle(up_mod(Modifier::Ctrl), start), None);
+        assert_eq!(
+            detector.handle(down_mod(Modifier::Ctrl), start + Duration::from_millis(100)),
+            None
+        );
+        assert_eq!(
+            detector.handle(up_mod(Modifier::Ctrl), start + Duration::from_millis(150)),
+            Some("launcher".to_string())
+        );
+    }
+
+    #[test]
+    fn test_double_tap_expires() {
+        let mut detector = detector_with(
+            vec![(
+                "launcher",
+                HotkeyBinding::DoubleTap {
+                    modifier: Modifier::Ctrl,
+                },
+            )],
+            false,
+        );
+        let start = Instant::now();
+        let late = start + DOUBLE_TAP_WINDOW + Duration::from_millis(50);
+
+        detector.handle(down_mod(Modifier::Ctrl), start);
+        detector.handle(up_mod(Modifier::Ctrl), start);
+        detector.handle(down_mod(Modifier::Ctrl), late);
+        assert_eq!(detector.handle(up_mod(Modifier::Ctrl), late), None);
+    }
+
+    #[test]
+    fn test_double_tap_cancelled_by_other_key() {
+        let mut detector = detector_with(
+            vec![(
+                "launcher",
+                HotkeyBinding::DoubleTap {
+                    modifier: Modifier::Ctrl,
+                },
+            )],
+            false,
+        );
+        let now = Instant::now();
+
+        // Ctrl+C then a lone Ctrl tap must not count as a double tap
+        detector.handle(down_mod(Modifier::Ctrl), now);
+        detector.handle(down_key("c"), now);
+        detector.handle(up_mod(Modifier::Ctrl), now);
+        detector.handle(down_mod(Modifier::Ctrl), now);
+        assert_eq!(detector.handle(up_mod(Modifier::Ctrl), now), None);
+    }
+
+    #[test]
+    fn test_chord_sequence() {
+        let mut detector = detector_with(
+            vec![(
+                "clipboard",
+                HotkeyBinding::Chord {
+                    prefix: KeyCombo::new(vec![Modifier::Ctrl], "x"),
+                    key: KeyCombo::new(vec![], "c"),
+                },
+            )],
+            false,
+        );
+        let now = Instant::now();
+
+        detector.handle(down_mod(Modifier::Ctrl), now);
+        assert_eq!(detector.handle(down_key("KeyX"), now), None);
+        detector.handle(up_mod(Modifier::Ctrl), now);
+        assert_eq!(
+            detector.handle(down_key("KeyC"), now + Duration::from_millis(200)),
+            Some("clipboard".to_string())
+        );
+        // The prefix is consumed by the chord
+        assert_eq!(detector.handle(down_key("KeyC"), now), None);
+    }
+
+    #[test]
+    fn test_chord_times_out() {
+        let mut detector = detector_with(
+            vec![(
+                "clipboard",
+                HotkeyBinding::Chord {
+                    prefix: KeyCombo::new(vec![Modifier::Ctrl], "x"),
+                    key: KeyCombo::new(vec![], "c"),
+                },
+            )],
+            false,
+        );
+        let now = Instant::now();
+
+        detector.handle(down_mod(Modifier::Ctrl), now);
+        detector.handle(down_key("KeyX"), now);
+        detector.handle(up_mod(Modifier::Ctrl), now);
+        assert_eq!(
+            detector.handle(down_key("KeyC"), now + CHORD_TIMEOUT * 2),
+            None
+        );
+    }
+
+    #[test]
+    fn test_combos_only_matched_when_enabled() {
+        let binding = HotkeyBinding::Combo {
+            combo: KeyCombo::new(vec![Modifier::Super, Modifier::Alt], "Space"),
+        };
+        let now = Instant::now();
+
+        let mut detector = detector_with(vec![("launcher", binding.clone())], false);
+        assert!(detector.bindings.is_empty());
+        detector.handle(down_mod(Modifier::Super), now);
+        detector.handle(down_mod(Modifier::Alt), now);
+        assert_eq!(detector.handle(down_key("Space"), now), None);
+
+        let mut detector = detector_with(vec![("launcher", binding)], true);
+        detector.handle(down_mod(Modifier::Alt), now);
+        detector.handle(down_mod(Modifier::Super), now);
+        assert_eq!(
+            detector.handle(down_key("space"), now),
+            Some("launcher".to_string())
+        );
+    }
+}
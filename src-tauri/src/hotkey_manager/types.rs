@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum Modifier {
+    Ctrl,
+    Alt,
+    Shift,
+    Super,
+}
+
+impl Modifier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Modifier::Ctrl => "ctrl",
+            Modifier::Alt => "alt",
+            Modifier::Shift => "shift",
+            Modifier::Super => "super",
+        }
+    }
+}
+
+/// A single modifier+key combination, e.g. `Super+Alt+Space`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyCombo {
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+    pub key: String,
+}
+
+impl KeyCombo {
+    pub fn new(modifiers: Vec<Modifier>, key: &str) -> Self {
+        Self {
+            modifiers,
+            key: key.to_string(),
+        }
+        .normalized()
+    }
+
+    /// Sorts and dedupes modifiers and maps the key to its W3C code name so
+    /// combos coming from the UI, evdev and rdev compare equal.
+    pub fn normalized(&self) -> Self {
+        let mut modifiers = self.modifiers.clone();
+        modifiers.sort();
+        modifiers.dedup();
+        Self {
+            modifiers,
+            key: normalize_key(&self.key),
+        }
+    }
+
+    /// Accelerator string understood by tauri_plugin_global_shortcut
+    pub fn to_accelerator(&self) -> String {
+        let normalized = self.normalized();
+        let mut parts: Vec<String> = normalized
+            .modifiers
+            .iter()
+            .map(|m| m.as_str().to_string())
+            .collect();
+        parts.push(normalized.key);
+        parts.join("+")
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum HotkeyBinding {
+    /// Plain modifier+key combo, registered with the global-shortcut plugin
+    Combo {
+        #[serde(flatten)]
+        combo: KeyCombo,
+    },
+    /// Tapping a modifier twice in quick succession (e.g. Ctrl, Ctrl)
+    DoubleTap { modifier: Modifier },
+    /// Emacs-style two-step sequence: a prefix combo followed by a second combo
+    Chord { prefix: KeyCombo, key: KeyCombo },
+}
+
+impl HotkeyBinding {
+    /// Whether this binding needs raw key events rather than the global-shortcut plugin
+    pub fn requires_raw_events(&self) -> bool {
+        !matches!(self, HotkeyBinding::Combo { .. })
+    }
+
+    pub fn normalized(&self) -> Self {
+        match self {
+            HotkeyBinding::Combo { combo } => HotkeyBinding::Combo {
+                combo: combo.normalized(),
+            },
+            HotkeyBinding::DoubleTap { modifier } => HotkeyBinding::DoubleTap {
+                modifier: *modifier,
+            },
+            HotkeyBinding::Chord { prefix, key } => HotkeyBinding::Chord {
+                prefix: prefix.normalized(),
+                key: key.normalized(),
+            },
+        }
+    }
+
+    /// Whether pressing one would also fire the other; a combo clashes with
+    /// chords that use it as their prefix
+    pub fn conflicts_with(&self, other: &HotkeyBinding) -> bool {
+        match (self.normalized(), other.normalized()) {
+            (a, b) if a == b => true,
+            (HotkeyBinding::Combo { combo }, HotkeyBinding::Chord { prefix, .. })
+            | (HotkeyBinding::Chord { prefix, .. }, HotkeyBinding::Combo { combo }) => {
+                combo == prefix
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Outcome of a temporary registration attempt made by `probe_hotkey`
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyProbeResult {
+    pub available: bool,
+    /// False when the backend can't tell whether another client holds the grab
+    /// (e.g. on Wayland, where combos are read from evdev instead)
+    pub verified: bool,
+    /// Id of one of our own bindings already using this combo
+    pub conflicts_with: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// Maps user-entered and backend key names onto W3C `KeyboardEvent.code` names
+pub fn normalize_key(key: &str) -> String {
+    let key = key.trim();
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphabetic() {
+            return format!("Key{}", c.to_ascii_uppercase());
+        }
+        if c.is_ascii_digit() {
+            return format!("Digit{}", c);
+        }
+    }
+
+    match key.to_ascii_lowercase().as_str() {
+        "space" => "Space",
+        "enter" | "return" => "Enter",
+        "esc" | "escape" => "Escape",
+        "tab" => "Tab",
+        "backspace" => "Backspace",
+        "delete" | "del" => "Delete",
+        "up" | "arrowup" => "ArrowUp",
+        "down" | "arrowdown" => "ArrowDown",
+        "left" | "arrowleft" => "ArrowLeft",
+        "right" | "arrowright" => "ArrowRight",
+        "home" => "Home",
+        "end" => "End",
+        "pageup" => "PageUp",
+        "pagedown" => "PageDown",
+        "minus" => "Minus",
+        "equal" => "Equal",
+        "comma" => "Comma",
+        "dot" | "period" => "Period",
+        "slash" => "Slash",
+        "semicolon" => "Semicolon",
+        "grave" | "backquote" => "Backquote",
+        _ => return key.to_string(),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_key() {
+        assert_eq!(normalize_key("k"), "KeyK");
+        assert_eq!(normalize_key("KeyK"), "KeyK");
+        assert_eq!(normalize_key("7"), "Digit7");
+        assert_eq!(normalize_key("space"), "Space");
+        assert_eq!(normalize_key("ESC"), "Escape");
+        assert_eq!(normalize_key("F5"), "F5");
+    }
+
+    #[test]
+    fn test_combo_normalization_and_accelerator() {
+        let combo = KeyCombo {
+            modifiers: vec![Modifier::Super, Modifier::Alt, Modifier::Super],
+            key: "space".into(),
+        };
+        assert_eq!(combo.to_accelerator(), "alt+super+Space");
+        assert_eq!(
+            combo.normalized(),
+            KeyCombo::new(vec![Modifier::Alt, Modifier::Super], "Space")
+        );
+    }
+
+    #[test]
+    fn test_binding_serialization() {
+        let binding: HotkeyBinding =
+            serde_json::from_str(r#"{"kind":"doubleTap","modifier":"ctrl"}"#).unwrap();
+        assert_eq!(
+            binding,
+            HotkeyBinding::DoubleTap {
+                modifier: Modifier::Ctrl
+            }
+        );
+        assert!(binding.requires_raw_events());
+
+        let combo: HotkeyBinding =
+            serde_json::from_str(r#"{"kind":"combo","modifiers":["super"],"key":"k"}"#).unwrap();
+        assert!(!combo.requires_raw_events());
+    }
+
+    #[test]
+    fn test_binding_conflicts() {
+        let combo = |modifiers: Vec<Modifier>, key: &str| HotkeyBinding::Combo {
+            combo: KeyCombo::new(modifiers, key),
+        };
+        let super_k = combo(vec![Modifier::Super], "k");
+        assert!(super_k.conflicts_with(&HotkeyBinding::Combo {
+            combo: KeyCombo {
+                modifiers: vec![Modifier::Super, Modifier::Super],
+                key: "KeyK".into(),
+            },
+        }));
+        assert!(!super_k.conflicts_with(&combo(vec![Modifier::Super, Modifier::Shift], "k")));
+
+        let chord = |key: &str| HotkeyBinding::Chord {
+            prefix: KeyCombo::new(vec![Modifier::Super], "k"),
+            key: KeyCombo::new(vec![], key),
+        };
+        assert!(super_k.conflicts_with(&chord("a")));
+        assert!(chord("a").conflicts_with(&super_k));
+        assert!(!chord("a").conflicts_with(&chord("b")));
+
+        let double_ctrl = HotkeyBinding::DoubleTap {
+            modifier: Modifier::Ctrl,
+        };
+        assert!(double_ctrl.conflicts_with(&double_ctrl.clone()));
+        assert!(!double_ctrl.conflicts_with(&super_k));
+    }
+}
@@ -0,0 +1,664 @@
+//! A small REST client: named requests saved in `http_requests.sqlite`, run
+//! with the response captured (status, headers, timing and a pretty-printed
+//! body) and every run kept in a history table.
+//!
+//! Passwords and tokens of saved requests go to the secrets vault rather
+//! than the database, and are never sent back to the frontend.
+
+use crate::error::AppError;
+use crate::secrets;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::State;
+
+const REQUESTS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS http_requests (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    method TEXT NOT NULL,
+    url TEXT NOT NULL,
+    headers TEXT NOT NULL,
+    body TEXT,
+    auth TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+)";
+const HISTORY_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS http_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    request_id INTEGER,
+    method TEXT NOT NULL,
+    url TEXT NOT NULL,
+    status INTEGER,
+    duration_ms INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    response_headers TEXT NOT NULL,
+    response_body TEXT,
+    error TEXT,
+    created_at INTEGER NOT NULL
+)";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Bodies beyond this are cut off in the captured response
+const MAX_BODY_BYTES: usize = 5 * 1024 * 1024;
+/// Bodies beyond this aren't kept in history
+const MAX_HISTORY_BODY_BYTES: usize = 256 * 1024;
+const MAX_HISTORY_ENTRIES: i64 = 500;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+    /// Disabled headers are kept on the request but not sent
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Secret fields are write-only: accepted from the frontend, never serialized,
+/// so they reach neither the database nor the UI
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum HttpAuth {
+    #[default]
+    None,
+    Basic {
+        username: String,
+        #[serde(default, skip_serializing)]
+        password: Option<String>,
+    },
+    Bearer {
+        #[serde(default, skip_serializing)]
+        token: Option<String>,
+    },
+    /// API key sent in a custom header, e.g. `X-Api-Key`
+    Header {
+        name: String,
+        #[serde(default, skip_serializing)]
+        value: Option<String>,
+    },
+}
+
+impl HttpAuth {
+    fn secret(&self) -> Option<&str> {
+        match self {
+            HttpAuth::None => None,
+            HttpAuth::Basic { password, .. } => password.as_deref(),
+            HttpAuth::Bearer { token } => token.as_deref(),
+            HttpAuth::Header { value, .. } => value.as_deref(),
+        }
+    }
+
+    fn with_secret(self, secret: Option<String>) -> Self {
+        match self {
+            HttpAuth::None => HttpAuth::None,
+            HttpAuth::Basic { username, password } => HttpAuth::Basic {
+                username,
+                password: password.or(secret),
+            },
+            HttpAuth::Bearer { token } => HttpAuth::Bearer {
+                token: token.or(secret),
+            },
+            HttpAuth::Header { name, value } => HttpAuth::Header {
+                name,
+                value: value.or(secret),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpRequest {
+    /// Set for saved requests
+    #[serde(default)]
+    pub id: Option<i64>,
+    #[serde(default)]
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<HttpHeader>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub auth: HttpAuth,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedRequest {
+    #[serde(flatten)]
+    pub request: HttpRequest,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Storable for SavedRequest {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let headers_json: String = row.get(4)?;
+        let auth_json: String = row.get(6)?;
+        let created_at_ts: i64 = row.get(7)?;
+        let updated_at_ts: i64 = row.get(8)?;
+        Ok(SavedRequest {
+            request: HttpRequest {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                method: row.get(2)?,
+                url: row.get(3)?,
+                headers: serde_json::from_str(&headers_json).unwrap_or_default(),
+                body: row.get(5)?,
+                auth: serde_json::from_str(&auth_json).unwrap_or_default(),
+            },
+            created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
+            updated_at: DateTime::from_timestamp(updated_at_ts, 0).unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub headers: Vec<HttpHeader>,
+    pub content_type: Option<String>,
+    pub body: String,
+    /// Indented copy of a JSON body
+    pub pretty_body: Option<String>,
+    /// Size of the body as received, before any truncation
+    pub size: usize,
+    pub truncated: bool,
+    pub duration_ms: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub request_id: Option<i64>,
+    pub method: String,
+    pub url: String,
+    /// `None` when the request failed before a response arrived
+    pub status: Option<u16>,
+    pub duration_ms: u64,
+    pub size: usize,
+    pub response_headers: Vec<HttpHeader>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Storable for HistoryEntry {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let headers_json: String = row.get(7)?;
+        let created_at_ts: i64 = row.get(10)?;
+        Ok(HistoryEntry {
+            id: row.get(0)?,
+            request_id: row.get(1)?,
+            method: row.get(2)?,
+            url: row.get(3)?,
+            status: row.get(4)?,
+            duration_ms: row.get::<_, i64>(5)? as u64,
+            size: row.get::<_, i64>(6)? as usize,
+            response_headers: serde_json::from_str(&headers_json).unwrap_or_default(),
+            response_body: row.get(8)?,
+            error: row.get(9)?,
+            created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
+        })
+    }
+}
+
+fn secret_key(id: i64) -> String {
+    format!("request:{}", id)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, AppError> {
+    serde_json::to_string(value).map_err(|e| AppError::Serialization(e.to_string()))
+}
+
+pub struct HttpRequestManager {
+    store: Store,
+}
+
+impl HttpRequestManager {
+    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, AppError> {
+        Self::with_store(Store::new(app_handle, "http_requests.sqlite")?)
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        Self::with_store(Store::new_in_memory()?)
+    }
+
+    fn with_store(store: Store) -> Result<Self, AppError> {
+        store.init_table(REQUESTS_SCHEMA)?;
+        store.init_table(HISTORY_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    /// Inserts the request, or updates it when it has an id. Returns the id.
+    fn save_row(&self, request: &HttpRequest) -> Result<i64, AppError> {
+        let headers = to_json(&request.headers)?;
+        let auth = to_json(&request.auth)?;
+        let now = Utc::now().timestamp();
+        match request.id {
+            Some(id) => {
+                let updated = self.store.execute(
+                    "UPDATE http_requests
+                     SET name = ?1, method = ?2, url = ?3, headers = ?4, body = ?5, auth = ?6, updated_at = ?7
+                     WHERE id = ?8",
+                    params![
+                        request.name,
+                        request.method,
+                        request.url,
+                        headers,
+                        request.body,
+                        auth,
+                        now,
+                        id
+                    ],
+                )?;
+                if updated == 0 {
+                    return Err(rusqlite::Error::QueryReturnedNoRows.into());
+                }
+                Ok(id)
+            }
+            None => {
+                self.store.execute(
+                    "INSERT INTO http_requests (name, method, url, headers, body, auth, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+                    params![
+                        request.name,
+                        request.method,
+                        request.url,
+                        headers,
+                        request.body,
+                        auth,
+                        now
+                    ],
+                )?;
+                Ok(self.store.last_insert_rowid())
+            }
+        }
+    }
+
+    /// Saves the request and moves its secret, if one was given, to the vault
+    pub fn save(&self, request: &HttpRequest) -> Result<i64, AppError> {
+        let id = self.save_row(request)?;
+        match (&request.auth, request.auth.secret()) {
+            (HttpAuth::None, _) => secrets::delete(secrets::HTTP, &secret_key(id))?,
+            (_, Some(secret)) => secrets::set(secrets::HTTP, &secret_key(id), secret)?,
+            // Editing without retyping the secret keeps the stored one
+            (_, None) => {}
+        }
+        Ok(id)
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<SavedRequest>, AppError> {
+        self.store.query_row(
+            "SELECT id, name, method, url, headers, body, auth, created_at, updated_at
+             FROM http_requests WHERE id = ?",
+            params![id],
+        )
+    }
+
+    pub fn list(&self) -> Result<Vec<SavedRequest>, AppError> {
+        self.store.query(
+            "SELECT id, name, method, url, headers, body, auth, created_at, updated_at
+             FROM http_requests ORDER BY name COLLATE NOCASE, id",
+            [],
+        )
+    }
+
+    pub fn delete(&self, id: i64) -> Result<(), AppError> {
+        self.store
+            .execute("DELETE FROM http_requests WHERE id = ?", params![id])?;
+        secrets::delete(secrets::HTTP, &secret_key(id))
+    }
+
+    pub fn record(
+        &self,
+        request: &HttpRequest,
+        result: &Result<HttpResponse, String>,
+    ) -> Result<i64, AppError> {
+        let (status, duration_ms, size, headers, body, error) = match result {
+            Ok(response) => (
+                Some(response.status),
+                response.duration_ms as i64,
+                response.size as i64,
+                to_json(&response.headers)?,
+                Some(&response.body).filter(|body| body.len() <= MAX_HISTORY_BODY_BYTES),
+                None,
+            ),
+            Err(e) => (None, 0, 0, "[]".to_string(), None, Some(e)),
+        };
+        self.store.execute(
+            "INSERT INTO http_history
+             (request_id, method, url, status, duration_ms, size, response_headers, response_body, error, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                request.id,
+                request.method,
+                request.url,
+                status,
+                duration_ms,
+                size,
+                headers,
+                body,
+                error,
+                Utc::now().timestamp()
+            ],
+        )?;
+        let id = self.store.last_insert_rowid();
+        self.store.execute(
+            "DELETE FROM http_history WHERE id <= ?",
+            params![id - MAX_HISTORY_ENTRIES],
+        )?;
+        Ok(id)
+    }
+
+    pub fn history(
+        &self,
+        request_id: Option<i64>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<HistoryEntry>, AppError> {
+        self.store.query(
+            "SELECT id, request_id, method, url, status, duration_ms, size, response_headers,
+                    response_body, error, created_at
+             FROM http_history WHERE ?1 IS NULL OR request_id = ?1
+             ORDER BY id DESC LIMIT ?2 OFFSET ?3",
+            params![request_id, limit, offset],
+        )
+    }
+
+    pub fn clear_history(&self) -> Result<(), AppError> {
+        self.store.execute("DELETE FROM http_history", [])?;
+        Ok(())
+    }
+}
+
+fn pretty_json(body: &str, content_type: Option<&str>) -> Option<String> {
+    let looks_like_json = content_type.is_some_and(|ct| ct.contains("json"))
+        || matches!(body.trim_start().chars().next(), Some('{' | '['));
+    if !looks_like_json {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    serde_json::to_string_pretty(&value).ok()
+}
+
+fn build_request(
+    client: &reqwest::Client,
+    request: &HttpRequest,
+) -> Result<reqwest::RequestBuilder, String> {
+    let method = reqwest::Method::from_bytes(request.method.trim().to_uppercase().as_bytes())
+        .map_err(|_| format!("Invalid method: {}", request.method))?;
+    let url = reqwest::Url::parse(request.url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+
+    let mut builder = client.request(method, url);
+    for header in request.headers.iter().filter(|h| h.enabled) {
+        if !header.name.trim().is_empty() {
+            builder = builder.header(header.name.trim(), &header.value);
+        }
+    }
+    builder = match &request.auth {
+        HttpAuth::None => builder,
+        HttpAuth::Basic { username, password } => builder.basic_auth(username, password.as_ref()),
+        HttpAuth::Bearer { token } => builder.bearer_auth(token.as_deref().unwrap_or_default()),
+        HttpAuth::Header { name, value } => {
+            builder.header(name.trim(), value.as_deref().unwrap_or_default())
+        }
+    };
+    if let Some(body) = request.body.as_ref().filter(|body| !body.is_empty()) {
+        builder = builder.body(body.clone());
+    }
+    Ok(builder)
+}
+
+async fn execute(request: &HttpRequest) -> Result<HttpResponse, String> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent("Flare launcher")
+        .build()
+        .map_err(|e| e.to_string())?;
+    let builder = build_request(&client, request)?;
+
+    let started = Instant::now();
+    let response = builder.send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let headers: Vec<HttpHeader> = response
+        .headers()
+        .iter()
+        .map(|(name, value)| HttpHeader {
+            name: name.to_string(),
+            value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            enabled: true,
+        })
+        .collect();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    let truncated = bytes.len() > MAX_BODY_BYTES;
+    let body = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_BODY_BYTES)]).into_owned();
+    let pretty_body = if truncated {
+        None
+    } else {
+        pretty_json(&body, content_type.as_deref())
+    };
+
+    Ok(HttpResponse {
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or_default().to_string(),
+        headers,
+        content_type,
+        body,
+        pretty_body,
+        size: bytes.len(),
+        truncated,
+        duration_ms,
+    })
+}
+
+/// Runs the request and records it in history. Secrets missing from a saved
+/// request are filled in from the vault.
+async fn run(
+    manager: &HttpRequestManager,
+    mut request: HttpRequest,
+) -> Result<HttpResponse, String> {
+    if let Some(id) = request.id {
+        let secret = secrets::get(secrets::HTTP, &secret_key(id)).map_err(|e| e.to_string())?;
+        request.auth = request.auth.with_secret(secret);
+    }
+    let result = execute(&request).await;
+    if let Err(e) = manager.record(&request, &result) {
+        tracing::warn!(error = %e, "Failed to save HTTP request history");
+    }
+    result
+}
+
+#[tauri::command]
+pub fn http_list_requests(
+    manager: State<'_, HttpRequestManager>,
+) -> Result<Vec<SavedRequest>, String> {
+    manager.list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn http_save_request(
+    manager: State<'_, HttpRequestManager>,
+    request: HttpRequest,
+) -> Result<i64, String> {
+    if request.name.trim().is_empty() {
+        return Err("Saved requests need a name".to_string());
+    }
+    manager.save(&request).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn http_delete_request(manager: State<'_, HttpRequestManager>, id: i64) -> Result<(), String> {
+    manager.delete(id).map_err(|e| e.to_string())
+}
+
+/// Runs an ad-hoc request, or an edited copy of a saved one
+#[tauri::command]
+pub async fn http_execute(
+    manager: State<'_, HttpRequestManager>,
+    request: HttpRequest,
+) -> Result<HttpResponse, String> {
+    run(&manager, request).await
+}
+
+#[tauri::command]
+pub async fn http_run_saved(
+    manager: State<'_, HttpRequestManager>,
+    id: i64,
+) -> Result<HttpResponse, String> {
+    let saved = manager
+        .get(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No saved request {}", id))?;
+    run(&manager, saved.request).await
+}
+
+#[tauri::command]
+pub fn http_get_history(
+    manager: State<'_, HttpRequestManager>,
+    request_id: Option<i64>,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<HistoryEntry>, String> {
+    manager
+        .history(request_id, limit, offset)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn http_clear_history(manager: State<'_, HttpRequestManager>) -> Result<(), String> {
+    manager.clear_history().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> HttpRequest {
+        HttpRequest {
+            id: None,
+            name: "Get user".to_string(),
+            method: "get".to_string(),
+            url: "https://api.example.com/users/1".to_string(),
+            headers: vec![HttpHeader {
+                name: "Accept".to_string(),
+                value: "application/json".to_string(),
+                enabled: true,
+            }],
+            body: None,
+            auth: HttpAuth::None,
+        }
+    }
+
+    #[test]
+    fn test_auth_secrets_are_not_serialized() {
+        let auth: HttpAuth = serde_json::from_str(r#"{"type":"bearer","token":"s3cret"}"#).unwrap();
+        assert_eq!(auth.secret(), Some("s3cret"));
+        assert_eq!(
+            serde_json::to_string(&auth).unwrap(),
+            r#"{"type":"bearer"}"#
+        );
+
+        let stored: HttpAuth = serde_json::from_str(r#"{"type":"basic","username":"me"}"#).unwrap();
+        assert_eq!(
+            stored.with_secret(Some("pw".to_string())),
+            HttpAuth::Basic {
+                username: "me".to_string(),
+                password: Some("pw".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_pretty_json() {
+        assert_eq!(
+            pretty_json(r#"{"a":1}"#, Some("application/json; charset=utf-8")).as_deref(),
+            Some("{\n  \"a\": 1\n}")
+        );
+        assert_eq!(pretty_json("[1]", None).as_deref(), Some("[\n  1\n]"));
+        assert_eq!(pretty_json("<html>", Some("text/html")), None);
+        assert_eq!(pretty_json("{broken", Some("application/json")), None);
+    }
+
+    #[test]
+    fn test_build_request() {
+        let client = reqwest::Client::new();
+        let mut req = request();
+        req.headers.push(HttpHeader {
+            name: "X-Skip".to_string(),
+            value: "1".to_string(),
+            enabled: false,
+        });
+        req.auth = HttpAuth::Header {
+            name: "X-Api-Key".to_string(),
+            value: Some("key".to_string()),
+        };
+        let built = build_request(&client, &req).unwrap().build().unwrap();
+        assert_eq!(built.method(), reqwest::Method::GET);
+        assert_eq!(built.headers()["accept"], "application/json");
+        assert_eq!(built.headers()["x-api-key"], "key");
+        assert!(built.headers().get("x-skip").is_none());
+
+        req.url = "not a url".to_string();
+        assert!(build_request(&client, &req).is_err());
+    }
+
+    #[test]
+    fn test_saved_requests_and_history() {
+        let manager = HttpRequestManager::new_for_test().unwrap();
+        let id = manager.save_row(&request()).unwrap();
+
+        let mut saved = manager.get(id).unwrap().unwrap().request;
+        assert_eq!(saved.headers.len(), 1);
+        saved.url = "https://api.example.com/users/2".to_string();
+        assert_eq!(manager.save_row(&saved).unwrap(), id);
+        assert_eq!(manager.list().unwrap()[0].request.url, saved.url);
+
+        let response = HttpResponse {
+            status: 200,
+            status_text: "OK".to_string(),
+            headers: Vec::new(),
+            content_type: None,
+            body: "{}".to_string(),
+            pretty_body: None,
+            size: 2,
+            truncated: false,
+            duration_ms: 42,
+        };
+        manager.record(&saved, &Ok(response)).unwrap();
+        manager
+            .record(&request(), &Err("connection refused".to_string()))
+            .unwrap();
+
+        let history = manager.history(None, 10, 0).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].error.as_deref(), Some("connection refused"));
+        assert_eq!(history[0].status, None);
+        let for_request = manager.history(Some(id), 10, 0).unwrap();
+        assert_eq!(for_request.len(), 1);
+        assert_eq!(for_request[0].status, Some(200));
+        assert_eq!(for_request[0].duration_ms, 42);
+
+        manager.clear_history().unwrap();
+        assert!(manager.history(None, 10, 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,340 @@
+//! The HUD: a small always-on-top window for confirmations and progress.
+//! One message is shown at a time; a new one replaces it. Messages can
+//! carry an icon or emoji, a success or error style, a progress bar that
+//! `hud_update` moves along, and buttons. A button click comes back through
+//! `hud_action`, which announces it with `HUD_ACTION_EVENT` and hands the
+//! HUD window the command to invoke.
+//!
+//! A message is hidden after its duration, except while it shows progress
+//! that hasn't completed; finishing or restyling it starts the countdown.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+pub const HUD_MESSAGE_EVENT: &str = "hud-message";
+pub const HUD_ACTION_EVENT: &str = "hud-action";
+
+const DEFAULT_DURATION: Duration = Duration::from_secs(2);
+/// Long enough to reach for a button
+const ACTIONS_DURATION: Duration = Duration::from_secs(6);
+const MAX_DURATION: Duration = Duration::from_secs(60);
+const MAX_ACTIONS: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum HudStyle {
+    #[default]
+    Info,
+    Success,
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HudAction {
+    pub id: String,
+    pub title: String,
+    /// The command the HUD window invokes when clicked; without one the
+    /// click is only announced
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// Everything but the title, as `show_hud` takes it
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HudOptions {
+    /// An icon name or an emoji
+    pub icon: Option<String>,
+    pub style: HudStyle,
+    pub duration_ms: Option<u64>,
+    /// 0 to 1
+    pub progress: Option<f64>,
+    pub actions: Vec<HudAction>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HudMessage {
+    pub id: u64,
+    pub title: String,
+    pub icon: Option<String>,
+    pub style: HudStyle,
+    pub progress: Option<f64>,
+    pub actions: Vec<HudAction>,
+    #[serde(skip)]
+    duration: Duration,
+}
+
+impl HudMessage {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self::with_options(title.into(), HudOptions::default())
+    }
+
+    fn with_options(title: String, options: HudOptions) -> Self {
+        let mut actions = options.actions;
+        actions.truncate(MAX_ACTIONS);
+        let duration = match options.duration_ms {
+            Some(ms) => Duration::from_millis(ms).min(MAX_DURATION),
+            None if !actions.is_empty() => ACTIONS_DURATION,
+            None => DEFAULT_DURATION,
+        };
+        Self {
+            id: 0,
+            title,
+            icon: options.icon,
+            style: options.style,
+            progress: options.progress.map(clamp_progress),
+            actions,
+            duration,
+        }
+    }
+
+    pub fn style(mut self, style: HudStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Stays up until progress completes
+    fn is_pending(&self) -> bool {
+        self.progress.is_some_and(|progress| progress < 1.0) && self.style == HudStyle::Info
+    }
+}
+
+fn clamp_progress(progress: f64) -> f64 {
+    if progress.is_finite() {
+        progress.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+struct HudState {
+    next_id: u64,
+    current: Option<HudMessage>,
+    /// Bumped whenever the message changes, so an older countdown doesn't
+    /// hide a newer message
+    generation: u64,
+}
+
+static STATE: Lazy<Mutex<HudState>> = Lazy::new(|| {
+    Mutex::new(HudState {
+        next_id: 1,
+        current: None,
+        generation: 0,
+    })
+});
+
+fn window(app: &AppHandle) -> Result<WebviewWindow, String> {
+    if let Some(window) = app.get_webview_window("hud") {
+        return Ok(window);
+    }
+    tauri::WebviewWindowBuilder::new(app, "hud", tauri::WebviewUrl::App("/hud".into()))
+        .decorations(false)
+        .transparent(true)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .min_inner_size(300.0, 80.0)
+        .max_inner_size(300.0, 80.0)
+        .inner_size(300.0, 80.0)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn hide(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("hud") {
+        let _ = window.hide();
+    }
+}
+
+/// Hides the message after its duration unless it changed meanwhile
+fn schedule_hide(app: &AppHandle, generation: u64, duration: Duration) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(duration).await;
+        let mut state = STATE.lock().unwrap();
+        if state.generation == generation {
+            state.current = None;
+            drop(state);
+            hide(&app);
+        }
+    });
+}
+
+/// Sends `message` to the window and (re)starts its countdown
+fn present(app: &AppHandle, window: &WebviewWindow, message: &HudMessage) -> Result<(), String> {
+    let generation = {
+        let mut state = STATE.lock().unwrap();
+        state.generation += 1;
+        state.current = Some(message.clone());
+        state.generation
+    };
+    window.show().map_err(|e| e.to_string())?;
+    window
+        .emit(HUD_MESSAGE_EVENT, message)
+        .map_err(|e| e.to_string())?;
+    // Clicks pass through unless there is something to click
+    window
+        .set_ignore_cursor_events(message.actions.is_empty())
+        .map_err(|e| e.to_string())?;
+    window.set_focus().map_err(|e| e.to_string())?;
+    if !message.is_pending() {
+        schedule_hide(app, generation, message.duration);
+    }
+    Ok(())
+}
+
+/// Shows `message`, replacing whatever is on the HUD, and returns its id
+/// for `update`
+pub fn show(app: &AppHandle, mut message: HudMessage) -> Result<u64, String> {
+    message.id = {
+        let mut state = STATE.lock().unwrap();
+        state.next_id += 1;
+        state.next_id - 1
+    };
+    let window = window(app)?;
+    present(app, &window, &message)?;
+    Ok(message.id)
+}
+
+#[tauri::command]
+pub async fn show_hud(
+    app: AppHandle,
+    title: String,
+    options: Option<HudOptions>,
+) -> Result<u64, String> {
+    show(
+        &app,
+        HudMessage::with_options(title, options.unwrap_or_default()),
+    )
+}
+
+/// Changes the message `id` if it's still shown; false once it's gone or
+/// replaced
+#[tauri::command]
+pub async fn hud_update(
+    app: AppHandle,
+    id: u64,
+    title: Option<String>,
+    progress: Option<f64>,
+    style: Option<HudStyle>,
+    icon: Option<String>,
+) -> Result<bool, String> {
+    let message = {
+        let state = STATE.lock().unwrap();
+        let Some(current) = state.current.as_ref().filter(|current| current.id == id) else {
+            return Ok(false);
+        };
+        let mut message = current.clone();
+        if let Some(title) = title {
+            message.title = title;
+        }
+        if let Some(progress) = progress {
+            message.progress = Some(clamp_progress(progress));
+        }
+        if let Some(style) = style {
+            message.style = style;
+        }
+        if icon.is_some() {
+            message.icon = icon;
+        }
+        message
+    };
+    let window = window(&app)?;
+    present(&app, &window, &message)?;
+    Ok(true)
+}
+
+/// Hides the HUD, or only the message `id` when given
+#[tauri::command]
+pub fn hud_dismiss(app: AppHandle, id: Option<u64>) {
+    let mut state = STATE.lock().unwrap();
+    if id.is_some_and(|id| {
+        state
+            .current
+            .as_ref()
+            .is_none_or(|current| current.id != id)
+    }) {
+        return;
+    }
+    state.current = None;
+    state.generation += 1;
+    drop(state);
+    hide(&app);
+}
+
+/// Called by the HUD window when a button is clicked; dismisses the message
+/// and returns the action for the window to run
+#[tauri::command]
+pub fn hud_action(app: AppHandle, id: u64, action_id: String) -> Result<HudAction, String> {
+    let action = STATE
+        .lock()
+        .unwrap()
+        .current
+        .as_ref()
+        .filter(|current| current.id == id)
+        .and_then(|current| current.actions.iter().find(|a| a.id == action_id).cloned())
+        .ok_or("The HUD message is gone")?;
+    let _ = app.emit(
+        HUD_ACTION_EVENT,
+        serde_json::json!({ "hudId": id, "actionId": action.id }),
+    );
+    hud_dismiss(app, Some(id));
+    Ok(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let plain = HudMessage::new("Copied");
+        assert_eq!(plain.duration, DEFAULT_DURATION);
+        assert!(!plain.is_pending());
+
+        let action = HudAction {
+            id: "undo".to_string(),
+            title: "Undo".to_string(),
+            command: Some("undo_last_action".to_string()),
+            args: serde_json::Value::Null,
+        };
+        let with_actions = HudMessage::with_options(
+            "Moved to Trash".to_string(),
+            HudOptions {
+                actions: vec![action; 5],
+                ..Default::default()
+            },
+        );
+        assert_eq!(with_actions.duration, ACTIONS_DURATION);
+        assert_eq!(with_actions.actions.len(), MAX_ACTIONS);
+
+        let installing = HudMessage::with_options(
+            "Installing".to_string(),
+            HudOptions {
+                progress: Some(1.5),
+                duration_ms: Some(10 * 60 * 1000),
+                ..Default::default()
+            },
+        );
+        assert_eq!(installing.progress, Some(1.0));
+        assert_eq!(installing.duration, MAX_DURATION);
+        assert!(!installing.is_pending());
+        assert!(HudMessage {
+            progress: Some(0.4),
+            ..installing.clone()
+        }
+        .is_pending());
+        assert!(!HudMessage {
+            progress: Some(0.4),
+            ..installing.style(HudStyle::Error)
+        }
+        .is_pending());
+    }
+}
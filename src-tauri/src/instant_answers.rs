@@ -0,0 +1,364 @@
+//! Instant answers shown under search results, from DuckDuckGo's Instant
+//! Answer API with the Wikipedia summary API as an optional fallback.
+//!
+//! Calls are debounced on the backend: each request waits briefly and gives up
+//! if a newer one arrived meanwhile, so the palette can call this on every
+//! keystroke. Results (including "no answer") are cached per query.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+const DUCKDUCKGO_URL: &str = "https://api.duckduckgo.com/";
+const WIKIPEDIA_SUMMARY_URL: &str = "https://en.wikipedia.org/api/rest_v1/page/summary/";
+
+const DEBOUNCE: Duration = Duration::from_millis(300);
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const CACHE_CAPACITY: usize = 200;
+const MIN_QUERY_LENGTH: usize = 3;
+const MAX_RELATED_TOPICS: usize = 5;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum AnswerKind {
+    /// A direct answer, e.g. a conversion or a fact
+    Answer,
+    /// A summary of the topic
+    Abstract,
+    Definition,
+    /// Only related topics were found (disambiguation)
+    Related,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedTopic {
+    pub text: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstantAnswer {
+    pub kind: AnswerKind,
+    pub title: String,
+    pub text: String,
+    /// Where the text comes from, e.g. "Wikipedia"
+    pub source: Option<String>,
+    pub url: Option<String>,
+    pub image: Option<String>,
+    pub related: Vec<RelatedTopic>,
+}
+
+#[derive(Default)]
+pub struct InstantAnswerService {
+    generation: AtomicU64,
+    cache: Mutex<HashMap<String, (Instant, Option<InstantAnswer>)>>,
+}
+
+impl InstantAnswerService {
+    fn cached(&self, key: &str, now: Instant) -> Option<Option<InstantAnswer>> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(key)
+            .filter(|(stored_at, _)| now.duration_since(*stored_at) < CACHE_TTL)
+            .map(|(_, answer)| answer.clone())
+    }
+
+    fn store(&self, key: String, answer: Option<InstantAnswer>, now: Instant) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, (stored_at, _)| now.duration_since(*stored_at) < CACHE_TTL);
+        if cache.len() >= CACHE_CAPACITY {
+            if let Some(oldest) = cache
+                .iter()
+                .min_by_key(|(_, (stored_at, _))| *stored_at)
+                .map(|(key, _)| key.clone())
+            {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(key, (now, answer));
+    }
+}
+
+fn non_empty(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+}
+
+/// DuckDuckGo returns relative image paths like `/i/abc.png`
+fn duckduckgo_image(body: &Value) -> Option<String> {
+    non_empty(body, "Image").map(|image| {
+        if image.starts_with('/') {
+            format!("https://duckduckgo.com{}", image)
+        } else {
+            image
+        }
+    })
+}
+
+fn related_topics(body: &Value) -> Vec<RelatedTopic> {
+    let Some(topics) = body.get("RelatedTopics").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    // Grouped topics nest their entries under `Topics`
+    topics
+        .iter()
+        .flat_map(
+            |topic| match topic.get("Topics").and_then(Value::as_array) {
+                Some(nested) => nested.iter().collect::<Vec<_>>(),
+                None => vec![topic],
+            },
+        )
+        .filter_map(|topic| {
+            Some(RelatedTopic {
+                text: non_empty(topic, "Text")?,
+                url: non_empty(topic, "FirstURL")?,
+            })
+        })
+        .take(MAX_RELATED_TOPICS)
+        .collect()
+}
+
+fn parse_duckduckgo(query: &str, body: &Value) -> Option<InstantAnswer> {
+    let heading = non_empty(body, "Heading").unwrap_or_else(|| query.to_string());
+    let related = related_topics(body);
+
+    if let Some(answer) = non_empty(body, "Answer") {
+        return Some(InstantAnswer {
+            kind: AnswerKind::Answer,
+            title: heading,
+            text: answer,
+            source: non_empty(body, "AnswerType"),
+            url: None,
+            image: None,
+            related: Vec::new(),
+        });
+    }
+    if let Some(text) = non_empty(body, "AbstractText") {
+        return Some(InstantAnswer {
+            kind: AnswerKind::Abstract,
+            title: heading,
+            text,
+            source: non_empty(body, "AbstractSource"),
+            url: non_empty(body, "AbstractURL"),
+            image: duckduckgo_image(body),
+            related,
+        });
+    }
+    if let Some(text) = non_empty(body, "Definition") {
+        return Some(InstantAnswer {
+            kind: AnswerKind::Definition,
+            title: heading,
+            text,
+            source: non_empty(body, "DefinitionSource"),
+            url: non_empty(body, "DefinitionURL"),
+            image: None,
+            related,
+        });
+    }
+    if !related.is_empty() {
+        return Some(InstantAnswer {
+            kind: AnswerKind::Related,
+            title: heading,
+            text: String::new(),
+            source: Some("DuckDuckGo".to_string()),
+            url: None,
+            image: None,
+            related,
+        });
+    }
+    None
+}
+
+fn parse_wikipedia(body: &Value) -> Option<InstantAnswer> {
+    // Disambiguation pages have no useful extract
+    if body.get("type").and_then(Value::as_str) != Some("standard") {
+        return None;
+    }
+    Some(InstantAnswer {
+        kind: AnswerKind::Abstract,
+        title: non_empty(body, "title")?,
+        text: non_empty(body, "extract")?,
+        source: Some("Wikipedia".to_string()),
+        url: body
+            .pointer("/content_urls/desktop/page")
+            .and_then(Value::as_str)
+            .map(String::from),
+        image: body
+            .pointer("/thumbnail/source")
+            .and_then(Value::as_str)
+            .map(String::from),
+        related: Vec::new(),
+    })
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .user_agent("Flare launcher")
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+async fn fetch_duckduckgo(client: &reqwest::Client, query: &str) -> Result<Value, String> {
+    client
+        .get(DUCKDUCKGO_URL)
+        .query(&[
+            ("q", query),
+            ("format", "json"),
+            ("no_html", "1"),
+            ("skip_disambig", "1"),
+        ])
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn fetch_wikipedia(client: &reqwest::Client, query: &str) -> Result<Option<Value>, String> {
+    let url = format!(
+        "{}{}",
+        WIKIPEDIA_SUMMARY_URL,
+        urlencoding::encode(&query.replace(' ', "_"))
+    );
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+    response.json().await.map(Some).map_err(|e| e.to_string())
+}
+
+async fn lookup(query: &str, include_wikipedia: bool) -> Result<Option<InstantAnswer>, String> {
+    let client = http_client()?;
+    let body = fetch_duckduckgo(&client, query).await?;
+    if let Some(answer) = parse_duckduckgo(query, &body) {
+        return Ok(Some(answer));
+    }
+    if !include_wikipedia {
+        return Ok(None);
+    }
+    Ok(fetch_wikipedia(&client, query)
+        .await?
+        .as_ref()
+        .and_then(parse_wikipedia))
+}
+
+/// Returns `None` both when there is no answer and when a newer query
+/// superseded this one during the debounce
+#[tauri::command]
+pub async fn get_instant_answer(
+    service: State<'_, InstantAnswerService>,
+    query: String,
+    include_wikipedia: Option<bool>,
+) -> Result<Option<InstantAnswer>, String> {
+    let query = query.trim().to_string();
+    if query.chars().count() < MIN_QUERY_LENGTH {
+        return Ok(None);
+    }
+    let include_wikipedia = include_wikipedia.unwrap_or(true);
+    let key = format!("{}:{}", include_wikipedia, query.to_lowercase());
+
+    if let Some(answer) = service.cached(&key, Instant::now()) {
+        return Ok(answer);
+    }
+
+    let generation = service.generation.fetch_add(1, Ordering::SeqCst) + 1;
+    tokio::time::sleep(DEBOUNCE).await;
+    if service.generation.load(Ordering::SeqCst) != generation {
+        return Ok(None);
+    }
+
+    let answer = lookup(&query, include_wikipedia).await?;
+    service.store(key, answer.clone(), Instant::now());
+    Ok(answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_duckduckgo_abstract() {
+        let body = json!({
+            "Heading": "Rust (programming language)",
+            "AbstractText": "Rust is a general-purpose programming language.",
+            "AbstractSource": "Wikipedia",
+            "AbstractURL": "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+            "Image": "/i/rust.png",
+            "RelatedTopics": [
+                { "Text": "Cargo - package manager", "FirstURL": "https://duckduckgo.com/Cargo" },
+                { "Name": "See also", "Topics": [
+                    { "Text": "Servo", "FirstURL": "https://duckduckgo.com/Servo" }
+                ]}
+            ]
+        });
+        let answer = parse_duckduckgo("rust", &body).unwrap();
+        assert_eq!(answer.kind, AnswerKind::Abstract);
+        assert_eq!(answer.title, "Rust (programming language)");
+        assert_eq!(answer.source.as_deref(), Some("Wikipedia"));
+        assert_eq!(
+            answer.image.as_deref(),
+            Some("https://duckduckgo.com/i/rust.png")
+        );
+        assert_eq!(answer.related.len(), 2);
+        assert_eq!(answer.related[1].text, "Servo");
+    }
+
+    #[test]
+    fn test_parse_duckduckgo_prefers_answer() {
+        let body = json!({
+            "Answer": "42",
+            "AnswerType": "calc",
+            "AbstractText": "ignored"
+        });
+        let answer = parse_duckduckgo("6*7", &body).unwrap();
+        assert_eq!(answer.kind, AnswerKind::Answer);
+        assert_eq!(answer.title, "6*7");
+        assert_eq!(answer.text, "42");
+
+        assert!(parse_duckduckgo("x", &json!({ "AbstractText": "" })).is_none());
+    }
+
+    #[test]
+    fn test_parse_wikipedia() {
+        let body = json!({
+            "type": "standard",
+            "title": "Szeged",
+            "extract": "Szeged is the third largest city of Hungary.",
+            "content_urls": { "desktop": { "page": "https://en.wikipedia.org/wiki/Szeged" } }
+        });
+        let answer = parse_wikipedia(&body).unwrap();
+        assert_eq!(answer.title, "Szeged");
+        assert_eq!(
+            answer.url.as_deref(),
+            Some("https://en.wikipedia.org/wiki/Szeged")
+        );
+        assert!(answer.image.is_none());
+
+        let disambiguation =
+            json!({ "type": "disambiguation", "title": "X", "extract": "X may refer to" });
+        assert!(parse_wikipedia(&disambiguation).is_none());
+    }
+
+    #[test]
+    fn test_cache_expiry() {
+        let service = InstantAnswerService::default();
+        let now = Instant::now();
+        service.store("q".to_string(), None, now);
+        assert_eq!(service.cached("q", now), Some(None));
+        assert_eq!(service.cached("q", now + CACHE_TTL), None);
+        assert_eq!(service.cached("other", now), None);
+    }
+}
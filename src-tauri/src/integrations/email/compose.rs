@@ -0,0 +1,226 @@
+use crate::integrations::notes::template::moment_to_chrono;
+use chrono::NaiveDateTime;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+static PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{\s*(\w+)(?::([^}]*))?\s*\}\}").unwrap());
+
+/// Where compose links open
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ComposeClient {
+    /// `mailto:`, handled by the system's default mail app
+    #[default]
+    Mailto,
+    Gmail,
+    Outlook,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailDraft {
+    /// Comma-separated addresses
+    #[serde(default)]
+    pub to: String,
+    #[serde(default)]
+    pub cc: String,
+    #[serde(default)]
+    pub bcc: String,
+    #[serde(default)]
+    pub subject: String,
+    #[serde(default)]
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub draft: EmailDraft,
+}
+
+/// Fills `{{name}}` from `values` (case-insensitive), plus `{{date}}` and
+/// `{{time}}` with an optional moment.js format as in note templates.
+/// Unknown placeholders are left as they are.
+pub fn fill(text: &str, values: &HashMap<String, String>, now: NaiveDateTime) -> String {
+    PLACEHOLDER
+        .replace_all(text, |caps: &Captures| {
+            let name = caps[1].to_lowercase();
+            if let Some((_, value)) = values.iter().find(|(key, _)| key.to_lowercase() == name) {
+                return value.clone();
+            }
+            let format = caps.get(2).map(|m| m.as_str().trim());
+            match name.as_str() {
+                "date" => now
+                    .format(&moment_to_chrono(format.unwrap_or("YYYY-MM-DD")))
+                    .to_string(),
+                "time" => now
+                    .format(&moment_to_chrono(format.unwrap_or("HH:mm")))
+                    .to_string(),
+                _ => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Placeholder names used anywhere in the draft
+pub fn placeholders(draft: &EmailDraft) -> Vec<String> {
+    [
+        &draft.to,
+        &draft.cc,
+        &draft.bcc,
+        &draft.subject,
+        &draft.body,
+    ]
+    .iter()
+    .flat_map(|text| PLACEHOLDER.captures_iter(text))
+    .map(|caps| caps[1].to_lowercase())
+    .collect()
+}
+
+impl EmailDraft {
+    pub fn fill(&self, values: &HashMap<String, String>, now: NaiveDateTime) -> Self {
+        Self {
+            to: fill(&self.to, values, now),
+            cc: fill(&self.cc, values, now),
+            bcc: fill(&self.bcc, values, now),
+            subject: fill(&self.subject, values, now),
+            body: fill(&self.body, values, now),
+        }
+    }
+
+    /// Fields set in `other` replace this draft's
+    pub fn merge(mut self, other: EmailDraft) -> Self {
+        let take = |field: &mut String, value: String| {
+            if !value.trim().is_empty() {
+                *field = value;
+            }
+        };
+        take(&mut self.to, other.to);
+        take(&mut self.cc, other.cc);
+        take(&mut self.bcc, other.bcc);
+        take(&mut self.subject, other.subject);
+        take(&mut self.body, other.body);
+        self
+    }
+}
+
+fn query(params: &[(&str, &str)]) -> String {
+    params
+        .iter()
+        .filter(|(_, value)| !value.trim().is_empty())
+        .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value.trim())))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// The link that opens `draft` in `client`
+pub fn compose_url(client: ComposeClient, draft: &EmailDraft) -> String {
+    match client {
+        ComposeClient::Mailto => {
+            // RFC 6068 allows `@` and `,` unescaped in the address list
+            let to = urlencoding::encode(draft.to.trim())
+                .replace("%40", "@")
+                .replace("%2C", ",");
+            let params = query(&[
+                ("cc", &draft.cc),
+                ("bcc", &draft.bcc),
+                ("subject", &draft.subject),
+                ("body", &draft.body),
+            ]);
+            if params.is_empty() {
+                format!("mailto:{}", to)
+            } else {
+                format!("mailto:{}?{}", to, params)
+            }
+        }
+        ComposeClient::Gmail => format!(
+            "https://mail.google.com/mail/?view=cm&fs=1&{}",
+            query(&[
+                ("to", &draft.to),
+                ("cc", &draft.cc),
+                ("bcc", &draft.bcc),
+                ("su", &draft.subject),
+                ("body", &draft.body),
+            ])
+        ),
+        ComposeClient::Outlook => format!(
+            "https://outlook.office.com/mail/deeplink/compose?{}",
+            query(&[
+                ("to", &draft.to),
+                ("cc", &draft.cc),
+                ("bcc", &draft.bcc),
+                ("subject", &draft.subject),
+                ("body", &draft.body),
+            ])
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 1, 15)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_fill() {
+        let values = HashMap::from([("Name".to_string(), "Ann".to_string())]);
+        assert_eq!(
+            fill(
+                "Hi {{name}}, report for {{date:D MMM}} {{missing}}",
+                &values,
+                now()
+            ),
+            "Hi Ann, report for 15 Jan {{missing}}"
+        );
+    }
+
+    #[test]
+    fn test_compose_urls() {
+        let draft = EmailDraft {
+            to: "ann@example.com, bob@example.com".to_string(),
+            subject: "Status & plans".to_string(),
+            body: "Line 1\nLine 2".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            compose_url(ComposeClient::Mailto, &draft),
+            "mailto:ann@example.com,%20bob@example.com?subject=Status%20%26%20plans&body=Line%201%0ALine%202"
+        );
+        assert!(compose_url(ComposeClient::Gmail, &draft)
+            .starts_with("https://mail.google.com/mail/?view=cm&fs=1&to=ann%40example.com"));
+        assert_eq!(
+            compose_url(ComposeClient::Mailto, &EmailDraft::default()),
+            "mailto:"
+        );
+    }
+
+    #[test]
+    fn test_merge_and_placeholders() {
+        let template = EmailDraft {
+            to: "team@example.com".to_string(),
+            subject: "Standup {{date}}".to_string(),
+            body: "{{clipboard}}".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(placeholders(&template), vec!["date", "clipboard"]);
+        let merged = template.merge(EmailDraft {
+            subject: "Custom".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(merged.to, "team@example.com");
+        assert_eq!(merged.subject, "Custom");
+    }
+}
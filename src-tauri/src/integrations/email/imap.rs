@@ -0,0 +1,431 @@
+//! Just enough IMAP4rev1 (RFC 3501) for unread counts, recent headers and
+//! IDLE (RFC 2177): tagged commands, literals and untagged responses.
+
+use base64::Engine;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf,
+    WriteHalf,
+};
+use tokio::net::TcpStream;
+use tokio_native_tls::TlsStream;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSummary {
+    pub uid: u32,
+    pub subject: String,
+    pub from: String,
+    pub date: String,
+    pub seen: bool,
+}
+
+/// What an IDLE wait ended with
+#[derive(Debug, PartialEq)]
+pub enum IdleEvent {
+    /// The server reported new, removed or changed messages
+    Changed,
+    Timeout,
+}
+
+pub struct ImapSession<S> {
+    reader: BufReader<ReadHalf<S>>,
+    writer: WriteHalf<S>,
+    tag: u32,
+}
+
+pub async fn connect_tls(
+    host: &str,
+    port: u16,
+) -> Result<ImapSession<TlsStream<TcpStream>>, String> {
+    let tcp = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((host, port)))
+        .await
+        .map_err(|_| format!("Timed out connecting to {}", host))?
+        .map_err(|e| format!("Failed to connect to {}: {}", host, e))?;
+    let connector = tokio_native_tls::native_tls::TlsConnector::new().map_err(|e| e.to_string())?;
+    let tls = tokio_native_tls::TlsConnector::from(connector)
+        .connect(host, tcp)
+        .await
+        .map_err(|e| format!("TLS handshake with {} failed: {}", host, e))?;
+    ImapSession::start(tls).await
+}
+
+/// Quotes a string argument, escaping `"` and `\`
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `{123}` at the end of a line announces a literal of that many bytes
+fn literal_length(line: &str) -> Option<usize> {
+    let line = line.trim_end();
+    let start = line.rfind('{')?;
+    line.strip_suffix('}')?[start + 1..].parse().ok()
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> ImapSession<S> {
+    /// Wraps a connected stream and reads the server greeting
+    pub async fn start(stream: S) -> Result<Self, String> {
+        let (reader, writer) = tokio::io::split(stream);
+        let mut session = Self {
+            reader: BufReader::new(reader),
+            writer,
+            tag: 0,
+        };
+        let greeting = session.read_response_line().await?;
+        if !greeting.starts_with("* OK") && !greeting.starts_with("* PREAUTH") {
+            return Err(format!("Unexpected IMAP greeting: {}", greeting.trim()));
+        }
+        Ok(session)
+    }
+
+    async fn read_line(&mut self) -> Result<String, String> {
+        let mut line = String::new();
+        let read = tokio::time::timeout(COMMAND_TIMEOUT, self.reader.read_line(&mut line))
+            .await
+            .map_err(|_| "IMAP server timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Err("IMAP connection closed".to_string());
+        }
+        Ok(line)
+    }
+
+    /// One logical response line, with any literals inlined
+    async fn read_response_line(&mut self) -> Result<String, String> {
+        let mut response = self.read_line().await?;
+        while let Some(length) = literal_length(&response) {
+            let mut literal = vec![0u8; length];
+            self.reader
+                .read_exact(&mut literal)
+                .await
+                .map_err(|e| e.to_string())?;
+            response.push_str(&String::from_utf8_lossy(&literal));
+            response.push_str(&self.read_line().await?);
+        }
+        Ok(response)
+    }
+
+    async fn send(&mut self, command: &str) -> Result<String, String> {
+        self.tag += 1;
+        let tag = format!("A{}", self.tag);
+        self.writer
+            .write_all(format!("{} {}\r\n", tag, command).as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        self.writer.flush().await.map_err(|e| e.to_string())?;
+        Ok(tag)
+    }
+
+    /// Runs a command and returns its untagged responses
+    pub async fn command(&mut self, command: &str) -> Result<Vec<String>, String> {
+        let tag = self.send(command).await?;
+        let mut untagged = Vec::new();
+        loop {
+            let line = self.read_response_line().await?;
+            let Some(status) = line.strip_prefix(&format!("{} ", tag)) else {
+                untagged.push(line);
+                continue;
+            };
+            if status.starts_with("OK") {
+                return Ok(untagged);
+            }
+            // Don't echo the command back: it may contain the password
+            let verb = command.split_whitespace().next().unwrap_or_default();
+            return Err(format!("IMAP {} failed: {}", verb, status.trim()));
+        }
+    }
+
+    pub async fn login(&mut self, username: &str, password: &str) -> Result<(), String> {
+        self.command(&format!("LOGIN {} {}", quote(username), quote(password)))
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn supports_idle(&mut self) -> Result<bool, String> {
+        let lines = self.command("CAPABILITY").await?;
+        Ok(lines.iter().any(|line| {
+            line.split_whitespace()
+                .any(|cap| cap.eq_ignore_ascii_case("IDLE"))
+        }))
+    }
+
+    /// Opens `mailbox` read-only and returns how many messages it holds
+    pub async fn examine(&mut self, mailbox: &str) -> Result<u32, String> {
+        let lines = self.command(&format!("EXAMINE {}", quote(mailbox))).await?;
+        Ok(lines
+            .iter()
+            .find_map(|line| parse_exists(line))
+            .unwrap_or(0))
+    }
+
+    /// Number of unseen messages in the selected mailbox
+    pub async fn count_unseen(&mut self) -> Result<u32, String> {
+        let lines = self.command("SEARCH UNSEEN").await?;
+        Ok(lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("* SEARCH"))
+            .map(|ids| ids.split_whitespace().count() as u32)
+            .sum())
+    }
+
+    /// Subjects of the newest `limit` messages in the selected mailbox,
+    /// newest first. `exists` is the count returned by `examine`.
+    pub async fn recent(&mut self, exists: u32, limit: u32) -> Result<Vec<MessageSummary>, String> {
+        if exists == 0 || limit == 0 {
+            return Ok(Vec::new());
+        }
+        let first = exists.saturating_sub(limit - 1).max(1);
+        let lines = self
+            .command(&format!(
+                "FETCH {}:{} (UID FLAGS BODY.PEEK[HEADER.FIELDS (SUBJECT FROM DATE)])",
+                first, exists
+            ))
+            .await?;
+        let mut messages: Vec<MessageSummary> =
+            lines.iter().filter_map(|line| parse_fetch(line)).collect();
+        messages.reverse();
+        Ok(messages)
+    }
+
+    /// Waits for mailbox changes with IDLE, ending it after `timeout`.
+    /// Servers drop idle clients after 30 minutes, so keep `timeout` below that.
+    pub async fn idle(&mut self, timeout: Duration) -> Result<IdleEvent, String> {
+        let tag = self.send("IDLE").await?;
+        let continuation = self.read_line().await?;
+        if !continuation.starts_with('+') {
+            return Err(format!("IDLE rejected: {}", continuation.trim()));
+        }
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        let event = loop {
+            let mut line = String::new();
+            match tokio::time::timeout_at(deadline, self.reader.read_line(&mut line)).await {
+                Err(_) => break IdleEvent::Timeout,
+                Ok(Ok(0)) => return Err("IMAP connection closed".to_string()),
+                Ok(Err(e)) => return Err(e.to_string()),
+                Ok(Ok(_)) if is_mailbox_change(&line) => break IdleEvent::Changed,
+                Ok(Ok(_)) => continue,
+            }
+        };
+
+        self.writer
+            .write_all(b"DONE\r\n")
+            .await
+            .map_err(|e| e.to_string())?;
+        self.writer.flush().await.map_err(|e| e.to_string())?;
+        loop {
+            let line = self.read_response_line().await?;
+            if line.starts_with(&format!("{} ", tag)) {
+                return Ok(event);
+            }
+        }
+    }
+
+    pub async fn logout(mut self) {
+        let _ = self.command("LOGOUT").await;
+    }
+}
+
+fn parse_exists(line: &str) -> Option<u32> {
+    let rest = line.strip_prefix("* ")?;
+    let (count, kind) = rest.trim_end().split_once(' ')?;
+    kind.eq_ignore_ascii_case("EXISTS")
+        .then(|| count.parse().ok())
+        .flatten()
+}
+
+fn is_mailbox_change(line: &str) -> bool {
+    let upper = line.to_ascii_uppercase();
+    upper.starts_with("* ")
+        && (upper.contains(" EXISTS") || upper.contains(" EXPUNGE") || upper.contains(" FETCH"))
+}
+
+/// Value of a header in an unfolded header block
+fn header(block: &str, name: &str) -> Option<String> {
+    let mut value: Option<String> = None;
+    for line in block.split("\r\n").flat_map(|l| l.split('\n')) {
+        if let Some(current) = value.as_mut() {
+            if line.starts_with([' ', '\t']) {
+                current.push(' ');
+                current.push_str(line.trim());
+                continue;
+            }
+            break;
+        }
+        if let Some((key, rest)) = line.split_once(':') {
+            if key.trim().eq_ignore_ascii_case(name) {
+                value = Some(rest.trim().to_string());
+            }
+        }
+    }
+    value
+}
+
+fn parse_fetch(line: &str) -> Option<MessageSummary> {
+    if !line.starts_with("* ") || !line.contains("FETCH") {
+        return None;
+    }
+    let uid = line
+        .split_once("UID ")?
+        .1
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()?;
+    let flags = line
+        .split_once("FLAGS (")
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .map(|(flags, _)| flags)
+        .unwrap_or_default();
+    Some(MessageSummary {
+        uid,
+        subject: header(line, "Subject")
+            .map(|s| decode_words(&s))
+            .unwrap_or_default(),
+        from: header(line, "From")
+            .map(|s| decode_words(&s))
+            .unwrap_or_default(),
+        date: header(line, "Date").unwrap_or_default(),
+        seen: flags
+            .split_whitespace()
+            .any(|f| f.eq_ignore_ascii_case("\\Seen")),
+    })
+}
+
+fn decode_charset(bytes: &[u8], charset: &str) -> String {
+    match charset.to_ascii_lowercase().as_str() {
+        // Latin-1 maps byte-for-byte onto the first 256 code points
+        "iso-8859-1" | "latin1" | "windows-1252" => bytes.iter().map(|&b| b as char).collect(),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+fn decode_q(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut chars = text.bytes();
+    while let Some(b) = chars.next() {
+        match b {
+            b'_' => bytes.push(b' '),
+            b'=' => {
+                let hex: Vec<u8> = chars.by_ref().take(2).collect();
+                match std::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                {
+                    Some(byte) => bytes.push(byte),
+                    None => {
+                        bytes.push(b'=');
+                        bytes.extend(hex);
+                    }
+                }
+            }
+            b => bytes.push(b),
+        }
+    }
+    bytes
+}
+
+/// Decodes RFC 2047 encoded words like `=?UTF-8?B?SGk=?=`; whitespace
+/// between adjacent encoded words is dropped as the RFC requires
+pub fn decode_words(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+    let mut previous_was_word = false;
+    while let Some(start) = rest.find("=?") {
+        let (before, candidate) = rest.split_at(start);
+        let word = candidate[2..].splitn(3, '?').collect::<Vec<_>>();
+        let parsed = match word.as_slice() {
+            [charset, encoding, tail] => tail.find("?=").map(|end| {
+                let payload = &tail[..end];
+                let bytes = match encoding.to_ascii_uppercase().as_str() {
+                    "B" => base64::engine::general_purpose::STANDARD
+                        .decode(payload)
+                        .unwrap_or_default(),
+                    _ => decode_q(payload),
+                };
+                let consumed = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+                (decode_charset(&bytes, charset), consumed)
+            }),
+            _ => None,
+        };
+        match parsed {
+            Some((text, consumed)) => {
+                if !(previous_was_word && before.trim().is_empty()) {
+                    decoded.push_str(before);
+                }
+                decoded.push_str(&text);
+                rest = &candidate[consumed..];
+                previous_was_word = true;
+            }
+            None => {
+                decoded.push_str(before);
+                decoded.push_str("=?");
+                rest = &candidate[2..];
+                previous_was_word = false;
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_words() {
+        assert_eq!(decode_words("=?UTF-8?B?SGVsbG8gd29ybGQ=?="), "Hello world");
+        assert_eq!(
+            decode_words("=?iso-8859-1?Q?Gr=FC=DFe_aus?= Szeged"),
+            "Grüße aus Szeged"
+        );
+        assert_eq!(decode_words("=?UTF-8?Q?a?= =?UTF-8?Q?b?="), "ab");
+        assert_eq!(decode_words("plain =? text"), "plain =? text");
+    }
+
+    #[test]
+    fn test_parse_fetch() {
+        let line = "* 12 FETCH (UID 345 FLAGS (\\Seen) BODY[HEADER.FIELDS (SUBJECT FROM DATE)] {80}\r\n\
+                    Subject: Quarterly\r\n report\r\nFrom: Ann <ann@example.com>\r\nDate: Wed, 15 Jan 2025\r\n\r\n)\r\n";
+        let message = parse_fetch(line).unwrap();
+        assert_eq!(message.uid, 345);
+        assert_eq!(message.subject, "Quarterly report");
+        assert_eq!(message.from, "Ann <ann@example.com>");
+        assert!(message.seen);
+        assert_eq!(parse_exists("* 42 EXISTS\r\n"), Some(42));
+        assert_eq!(literal_length("BODY[] {80}\r\n"), Some(80));
+    }
+
+    #[tokio::test]
+    async fn test_session_with_literal_and_search() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 1024];
+            server.write_all(b"* OK ready\r\n").await.unwrap();
+            let _ = server.read(&mut buf).await.unwrap();
+            server
+                .write_all(
+                    b"* 1 FETCH (UID 7 BODY[] {5}\r\nhello)\r\n* SEARCH 3 4\r\nA1 OK done\r\n",
+                )
+                .await
+                .unwrap();
+            let _ = server.read(&mut buf).await.unwrap();
+            server
+                .write_all(b"A2 NO [AUTHENTICATIONFAILED] nope\r\n")
+                .await
+                .unwrap();
+        });
+
+        let mut session = ImapSession::start(client).await.unwrap();
+        let lines = session.command("NOOP").await.unwrap();
+        assert_eq!(lines[0], "* 1 FETCH (UID 7 BODY[] {5}\r\nhello)\r\n");
+        assert_eq!(lines.len(), 2);
+        let error = session.login("me", "secret").await.unwrap_err();
+        assert!(!error.contains("secret"));
+    }
+}
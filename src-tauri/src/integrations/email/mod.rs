@@ -0,0 +1,354 @@
+//! Email quick actions: unread counts for IMAP accounts, kept live with IDLE,
+//! the latest subjects of an inbox, and compose links built from templates.
+//!
+//! Account passwords (or app passwords) live in the secrets vault; only the
+//! connection details are stored in `email_settings.json`.
+
+pub mod compose;
+pub mod imap;
+
+pub use compose::{ComposeClient, EmailDraft, EmailTemplate};
+pub use imap::MessageSummary;
+
+use crate::error::AppError;
+use crate::power_awareness;
+use crate::secrets;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_opener::open_url;
+
+/// Re-issued before the 30 minute limit servers apply to IDLE
+const IDLE_TIMEOUT: Duration = Duration::from_secs(25 * 60);
+/// For servers without IDLE
+const POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const RECONNECT_DELAY: Duration = Duration::from_secs(60);
+const DEFAULT_RECENT_LIMIT: u32 = 10;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailAccount {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub username: String,
+    #[serde(default = "default_mailbox")]
+    pub mailbox: String,
+}
+
+fn default_port() -> u16 {
+    993
+}
+
+fn default_mailbox() -> String {
+    "INBOX".to_string()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailSettings {
+    #[serde(default)]
+    pub accounts: Vec<EmailAccount>,
+    #[serde(default)]
+    pub templates: Vec<EmailTemplate>,
+    #[serde(default)]
+    pub client: ComposeClient,
+}
+
+impl EmailSettings {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("email_settings.json"))
+    }
+
+    pub fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UnreadChanged {
+    account_id: String,
+    unread: u32,
+}
+
+/// Live unread counts, one IMAP connection per account
+#[derive(Default)]
+pub struct EmailWatcher {
+    unread: Mutex<HashMap<String, u32>>,
+    tasks: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl EmailWatcher {
+    /// Stops every running watcher and starts one per account
+    fn restart(&self, app: &AppHandle, accounts: &[EmailAccount]) {
+        let mut tasks = self.tasks.lock().unwrap();
+        for (_, task) in tasks.drain() {
+            task.abort();
+        }
+        self.unread
+            .lock()
+            .unwrap()
+            .retain(|id, _| accounts.iter().any(|account| &account.id == id));
+        for account in accounts {
+            let task = tauri::async_runtime::spawn(watch_account(app.clone(), account.clone()));
+            tasks.insert(account.id.clone(), task);
+        }
+    }
+
+    fn set_unread(&self, app: &AppHandle, account_id: &str, unread: u32) {
+        let previous = self
+            .unread
+            .lock()
+            .unwrap()
+            .insert(account_id.to_string(), unread);
+        if previous != Some(unread) {
+            let _ = app.emit(
+                "email-unread-changed",
+                UnreadChanged {
+                    account_id: account_id.to_string(),
+                    unread,
+                },
+            );
+        }
+    }
+}
+
+fn account_password(account_id: &str) -> Result<String, String> {
+    secrets::get(secrets::EMAIL, account_id)
+        .map_err(|e| format!("Failed to retrieve email password: {}", e))?
+        .ok_or_else(|| "No password saved for this account".to_string())
+}
+
+async fn open_session(
+    account: &EmailAccount,
+    password: &str,
+) -> Result<imap::ImapSession<tokio_native_tls::TlsStream<tokio::net::TcpStream>>, String> {
+    let mut session = imap::connect_tls(&account.host, account.port).await?;
+    session.login(&account.username, password).await?;
+    Ok(session)
+}
+
+async fn run_watcher(app: &AppHandle, account: &EmailAccount) -> Result<(), String> {
+    let password = account_password(&account.id)?;
+    let mut session = open_session(account, &password).await?;
+    let supports_idle = session.supports_idle().await?;
+    session.examine(&account.mailbox).await?;
+
+    loop {
+        let unread = session.count_unseen().await?;
+        app.state::<EmailWatcher>()
+            .set_unread(app, &account.id, unread);
+        if supports_idle {
+            session.idle(IDLE_TIMEOUT).await?;
+        } else {
+            power_awareness::sleep(POLL_INTERVAL).await;
+            session.command("NOOP").await?;
+        }
+    }
+}
+
+async fn watch_account(app: AppHandle, account: EmailAccount) {
+    loop {
+        if let Err(e) = run_watcher(&app, &account).await {
+            tracing::warn!(error = %e, account = %account.name, "Email watcher disconnected");
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Starts watching every configured account
+pub fn init(app: &AppHandle) {
+    app.manage(EmailWatcher::default());
+    let settings = EmailSettings::load(app).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Failed to read email settings");
+        EmailSettings::default()
+    });
+    app.state::<EmailWatcher>().restart(app, &settings.accounts);
+}
+
+fn load_settings(app: &AppHandle) -> Result<EmailSettings, String> {
+    EmailSettings::load(app).map_err(|e| e.to_string())
+}
+
+fn save_settings(app: &AppHandle, settings: &EmailSettings) -> Result<(), String> {
+    settings.save(app).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn email_get_settings(app: AppHandle) -> Result<EmailSettings, String> {
+    load_settings(&app)
+}
+
+/// Adds or updates an account; `password` is only replaced when given
+#[tauri::command]
+pub fn email_save_account(
+    app: AppHandle,
+    mut account: EmailAccount,
+    password: Option<String>,
+) -> Result<EmailAccount, String> {
+    if account.id.is_empty() {
+        account.id = uuid::Uuid::new_v4().to_string();
+    }
+    if let Some(password) = password.filter(|p| !p.is_empty()) {
+        secrets::set(secrets::EMAIL, &account.id, &password)
+            .map_err(|e| format!("Failed to store email password: {}", e))?;
+    }
+
+    let mut settings = load_settings(&app)?;
+    match settings.accounts.iter_mut().find(|a| a.id == account.id) {
+        Some(existing) => *existing = account.clone(),
+        None => settings.accounts.push(account.clone()),
+    }
+    save_settings(&app, &settings)?;
+    app.state::<EmailWatcher>()
+        .restart(&app, &settings.accounts);
+    Ok(account)
+}
+
+#[tauri::command]
+pub fn email_delete_account(app: AppHandle, id: String) -> Result<(), String> {
+    let mut settings = load_settings(&app)?;
+    settings.accounts.retain(|account| account.id != id);
+    save_settings(&app, &settings)?;
+    secrets::delete(secrets::EMAIL, &id).map_err(|e| e.to_string())?;
+    app.state::<EmailWatcher>()
+        .restart(&app, &settings.accounts);
+    Ok(())
+}
+
+/// Logs in with the given details and returns the unread count, without
+/// saving anything
+#[tauri::command]
+pub async fn email_test_account(account: EmailAccount, password: String) -> Result<u32, String> {
+    let mut session = open_session(&account, &password).await?;
+    session.examine(&account.mailbox).await?;
+    let unread = session.count_unseen().await?;
+    session.logout().await;
+    Ok(unread)
+}
+
+/// Unread count per account id, as last reported by the watchers
+#[tauri::command]
+pub fn email_unread_counts(watcher: State<'_, EmailWatcher>) -> HashMap<String, u32> {
+    watcher.unread.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub async fn email_recent(
+    app: AppHandle,
+    account_id: String,
+    limit: Option<u32>,
+) -> Result<Vec<MessageSummary>, String> {
+    let account = load_settings(&app)?
+        .accounts
+        .into_iter()
+        .find(|account| account.id == account_id)
+        .ok_or_else(|| format!("Email account {} not found", account_id))?;
+    let password = account_password(&account.id)?;
+    let mut session = open_session(&account, &password).await?;
+    let exists = session.examine(&account.mailbox).await?;
+    let messages = session
+        .recent(exists, limit.unwrap_or(DEFAULT_RECENT_LIMIT))
+        .await?;
+    session.logout().await;
+    Ok(messages)
+}
+
+#[tauri::command]
+pub fn email_save_templates(app: AppHandle, templates: Vec<EmailTemplate>) -> Result<(), String> {
+    let mut settings = load_settings(&app)?;
+    settings.templates = templates
+        .into_iter()
+        .map(|mut template| {
+            if template.id.is_empty() {
+                template.id = uuid::Uuid::new_v4().to_string();
+            }
+            template
+        })
+        .collect();
+    save_settings(&app, &settings)
+}
+
+#[tauri::command]
+pub fn email_set_client(app: AppHandle, client: ComposeClient) -> Result<(), String> {
+    let mut settings = load_settings(&app)?;
+    settings.client = client;
+    save_settings(&app, &settings)
+}
+
+/// Builds a compose link from a template and/or `draft` and opens it.
+/// `{{clipboard}}` and `{{selection}}` are filled in unless `values` has them.
+#[tauri::command]
+pub fn email_compose(
+    app: AppHandle,
+    template_id: Option<String>,
+    draft: Option<EmailDraft>,
+    values: Option<HashMap<String, String>>,
+) -> Result<String, String> {
+    let settings = load_settings(&app)?;
+    let base = match template_id {
+        Some(id) => settings
+            .templates
+            .iter()
+            .find(|template| template.id == id)
+            .ok_or_else(|| format!("Email template {} not found", id))?
+            .draft
+            .clone(),
+        None => EmailDraft::default(),
+    };
+    let draft = base.merge(draft.unwrap_or_default());
+
+    let mut values = values.unwrap_or_default();
+    let used = compose::placeholders(&draft);
+    let has = |values: &HashMap<String, String>, name: &str| {
+        values.keys().any(|key| key.eq_ignore_ascii_case(name))
+    };
+    if used.iter().any(|p| p == "clipboard") && !has(&values, "clipboard") {
+        let clipboard = arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .unwrap_or_default();
+        values.insert("clipboard".to_string(), clipboard);
+    }
+    if used.iter().any(|p| p == "selection") && !has(&values, "selection") {
+        values.insert("selection".to_string(), selection::get_text());
+    }
+
+    let url = compose::compose_url(
+        settings.client,
+        &draft.fill(&values, Local::now().naive_local()),
+    );
+    open_url(&url, None::<String>).map_err(|e| e.to_string())?;
+    Ok(url)
+}
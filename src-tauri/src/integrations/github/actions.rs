@@ -0,0 +1,214 @@
+use super::{types::*, GitHubClient, GitHubError, DEFAULT_LIMIT};
+use crate::notifications::{self, NotificationOptions, Urgency};
+use crate::power_awareness;
+use once_cell::sync::Lazy;
+use reqwest::Method;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Polls are conditional, so an unchanged run doesn't use up the rate limit
+const WATCH_INTERVAL: Duration = Duration::from_secs(15);
+/// Runs still going after this long are given up on
+const MAX_WATCH: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Runs being watched, so watching one twice doesn't notify twice
+static WATCHED_RUNS: Lazy<Mutex<HashSet<u64>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+impl GitHubClient {
+    /// List up to `limit` recent workflow runs, newest first, optionally only
+    /// for one branch
+    pub async fn list_workflow_runs(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<WorkflowRun>, GitHubError> {
+        let mut path = format!("/repos/{}/{}/actions/runs", owner, repo);
+
+        if let Some(branch) = branch {
+            path.push_str(&format!("?branch={}", urlencoding::encode(branch)));
+        }
+
+        self.paginate(&path, limit.unwrap_or(DEFAULT_LIMIT), |body| {
+            serde_json::from_str::<WorkflowRuns>(body)
+                .map(|runs| runs.workflow_runs)
+                .map_err(|e| GitHubError::InvalidResponse {
+                    message: e.to_string(),
+                })
+        })
+        .await
+    }
+
+    /// Get a specific workflow run
+    pub async fn get_workflow_run(
+        &self,
+        owner: &str,
+        repo: &str,
+        run_id: u64,
+    ) -> Result<WorkflowRun, GitHubError> {
+        let path = format!("/repos/{}/{}/actions/runs/{}", owner, repo, run_id);
+        self.get_json(&path).await
+    }
+
+    /// Re-run a workflow run, either every job or only the ones that failed
+    pub async fn rerun_workflow_run(
+        &self,
+        owner: &str,
+        repo: &str,
+        run_id: u64,
+        failed_only: bool,
+    ) -> Result<(), GitHubError> {
+        let action = if failed_only {
+            "rerun-failed-jobs"
+        } else {
+            "rerun"
+        };
+        let path = format!(
+            "/repos/{}/{}/actions/runs/{}/{}",
+            owner, repo, run_id, action
+        );
+        self.send(Method::POST, &path, None).await?;
+        Ok(())
+    }
+}
+
+/// The notification's summary and body for a finished run
+fn completion_message(run: &WorkflowRun, repo: &str) -> (String, String) {
+    let name = run.name.as_deref().unwrap_or("Workflow");
+    let outcome = match run.conclusion.as_deref() {
+        Some("success") => "passed",
+        Some("failure") => "failed",
+        Some("cancelled") => "was cancelled",
+        Some("timed_out") => "timed out",
+        Some("skipped") => "was skipped",
+        Some("action_required") => "needs approval",
+        Some(other) => other,
+        None => "finished",
+    };
+    let mut body = format!("{} #{}", repo, run.run_number);
+    if let Some(branch) = &run.head_branch {
+        body.push_str(&format!(" on {}", branch));
+    }
+    body.push_str(&format!(": {}", run.display_title));
+    (format!("{} {}", name, outcome), body)
+}
+
+fn notify_completed(run: &WorkflowRun, repo: &str) {
+    let (summary, body) = completion_message(run, repo);
+    let succeeded = run.conclusion.as_deref() == Some("success");
+    notifications::send_in_background(
+        summary,
+        body,
+        NotificationOptions {
+            urgency: Some(if succeeded {
+                Urgency::Normal
+            } else {
+                Urgency::Critical
+            }),
+            icon: Some(
+                if succeeded {
+                    "emblem-ok-symbolic"
+                } else {
+                    "dialog-error-symbolic"
+                }
+                .to_string(),
+            ),
+            ..Default::default()
+        },
+    );
+}
+
+/// Polls a run in the background and shows a notification when it
+/// completes. Returns the run as it is now; a run that's already
+/// completed isn't watched.
+pub async fn watch_workflow_run(
+    client: GitHubClient,
+    owner: String,
+    repo: String,
+    run_id: u64,
+) -> Result<WorkflowRun, GitHubError> {
+    let run = client.get_workflow_run(&owner, &repo, run_id).await?;
+    if run.is_completed() {
+        return Ok(run);
+    }
+    if !WATCHED_RUNS
+        .lock()
+        .is_ok_and(|mut watched| watched.insert(run_id))
+    {
+        return Ok(run);
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let started = Instant::now();
+        while started.elapsed() < MAX_WATCH {
+            power_awareness::sleep(WATCH_INTERVAL).await;
+            match client.get_workflow_run(&owner, &repo, run_id).await {
+                Ok(run) if run.is_completed() => {
+                    notify_completed(&run, &format!("{}/{}", owner, repo));
+                    break;
+                }
+                Ok(_) => {}
+                // Keep polling through rate limits and network blips; a
+                // revoked token or a deleted run won't come back
+                Err(GitHubError::RateLimited { .. } | GitHubError::Network { .. }) => {}
+                Err(e) => {
+                    tracing::warn!(error = %e, run_id, "Stopped watching workflow run");
+                    break;
+                }
+            }
+        }
+        if let Ok(mut watched) = WATCHED_RUNS.lock() {
+            watched.remove(&run_id);
+        }
+    });
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(conclusion: Option<&str>) -> WorkflowRun {
+        serde_json::from_value(serde_json::json!({
+            "id": 42,
+            "name": "CI",
+            "display_title": "Fix the flaky test",
+            "status": "completed",
+            "conclusion": conclusion,
+            "head_branch": "main",
+            "head_sha": "abc123",
+            "event": "push",
+            "run_number": 318,
+            "html_url": "https://github.com/o/r/actions/runs/42",
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:05:00Z"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_completion_message() {
+        assert_eq!(
+            completion_message(&run(Some("success")), "o/r"),
+            (
+                "CI passed".to_string(),
+                "o/r #318 on main: Fix the flaky test".to_string()
+            )
+        );
+        assert_eq!(
+            completion_message(&run(Some("failure")), "o/r").0,
+            "CI failed"
+        );
+        assert_eq!(completion_message(&run(None), "o/r").0, "CI finished");
+    }
+
+    #[test]
+    fn test_is_completed() {
+        let mut run = run(None);
+        assert!(run.is_completed());
+        run.status = Some("in_progress".to_string());
+        assert!(!run.is_completed());
+    }
+}
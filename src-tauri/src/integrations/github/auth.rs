@@ -1,3 +1,4 @@
+use crate::secrets;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
@@ -5,6 +6,7 @@ const GITHUB_CLIENT_ID: &str = "Ov23liLBXQcwvZPYjDGh"; // Flareup GitHub OAuth A
 const DEVICE_CODE_URL: &str = "https://github.com/login/device/code";
 const ACCESS_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
 const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const TOKEN_KEY: &str = "access_token";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceCodeResponse {
@@ -101,40 +103,22 @@ pub async fn poll_for_token(device_code: &str) -> Result<Option<String>, String>
     }
 }
 
-/// Store the GitHub access token in the keyring
+/// Store the GitHub access token in the secrets vault
 pub fn store_token(token: &str) -> Result<(), String> {
-    let entry = keyring::Entry::new("flareup", "github")
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-    
-    entry
-        .set_password(token)
-        .map_err(|e| format!("Failed to store token: {}", e))?;
-    
-    Ok(())
+    secrets::set(secrets::GITHUB, TOKEN_KEY, token)
+        .map_err(|e| format!("Failed to store token: {}", e))
 }
 
-/// Retrieve the GitHub access token from the keyring
+/// Retrieve the GitHub access token from the secrets vault
 pub fn get_token() -> Result<Option<String>, String> {
-    let entry = keyring::Entry::new("flareup", "github")
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-    
-    match entry.get_password() {
-        Ok(token) => Ok(Some(token)),
-        Err(keyring::Error::NoEntry) => Ok(None),
-        Err(e) => Err(format!("Failed to retrieve token: {}", e)),
-    }
+    secrets::get(secrets::GITHUB, TOKEN_KEY)
+        .map_err(|e| format!("Failed to retrieve token: {}", e))
 }
 
-/// Delete the GitHub access token from the keyring
+/// Delete the GitHub access token from the secrets vault
 pub fn delete_token() -> Result<(), String> {
-    let entry = keyring::Entry::new("flareup", "github")
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-    
-    match entry.delete_credential() {
-        Ok(()) => Ok(()),
-        Err(keyring::Error::NoEntry) => Ok(()), // Already deleted
-        Err(e) => Err(format!("Failed to delete token: {}", e)),
-    }
+    secrets::delete(secrets::GITHUB, TOKEN_KEY)
+        .map_err(|e| format!("Failed to delete token: {}", e))
 }
 
 #[cfg(test)]
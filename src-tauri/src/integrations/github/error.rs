@@ -0,0 +1,120 @@
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::fmt;
+
+/// Errors from GitHub API calls, tagged with a `kind` so the UI can tell a
+/// missing or revoked token apart from rate limiting
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum GitHubError {
+    /// No token has been stored yet
+    NotAuthenticated,
+    /// The token was rejected, revoked or lacks a scope the call needs
+    Unauthorized {
+        message: String,
+    },
+    /// `reset_at` is a Unix timestamp; `retry_after_secs` is how long GitHub
+    /// asked us to wait, when it said
+    RateLimited {
+        reset_at: Option<i64>,
+        retry_after_secs: Option<u64>,
+    },
+    NotFound {
+        message: String,
+    },
+    Api {
+        status: u16,
+        message: String,
+    },
+    Network {
+        message: String,
+    },
+    InvalidResponse {
+        message: String,
+    },
+    Storage {
+        message: String,
+    },
+}
+
+impl fmt::Display for GitHubError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitHubError::NotAuthenticated => {
+                write!(f, "No GitHub token found. Please authenticate first.")
+            }
+            GitHubError::Unauthorized { message } => {
+                write!(f, "GitHub rejected the token: {}", message)
+            }
+            GitHubError::RateLimited {
+                retry_after_secs: Some(secs),
+                ..
+            } => write!(f, "GitHub rate limit exceeded; retry in {}s", secs),
+            GitHubError::RateLimited { .. } => write!(f, "GitHub rate limit exceeded"),
+            GitHubError::NotFound { message } => write!(f, "Not found on GitHub: {}", message),
+            GitHubError::Api { status, message } => {
+                write!(f, "GitHub API error {}: {}", status, message)
+            }
+            GitHubError::Network { message } => write!(f, "Failed to reach GitHub: {}", message),
+            GitHubError::InvalidResponse { message } => {
+                write!(f, "Failed to parse GitHub response: {}", message)
+            }
+            GitHubError::Storage { message } => write!(f, "GitHub token storage: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for GitHubError {}
+
+impl From<reqwest::Error> for GitHubError {
+    fn from(error: reqwest::Error) -> Self {
+        GitHubError::Network {
+            message: error.to_string(),
+        }
+    }
+}
+
+fn header_number<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// The `message` of a GitHub error body, or the body itself
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| json.get("message")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// Turns a failed response into an error. GitHub answers rate-limited calls
+/// with 403 as well as 429, so the headers decide which one a 403 is.
+pub(super) fn classify(status: StatusCode, headers: &HeaderMap, body: &str) -> GitHubError {
+    let message = error_message(body);
+    let retry_after_secs = header_number::<u64>(headers, "retry-after");
+    let exhausted = header_number::<u64>(headers, "x-ratelimit-remaining") == Some(0);
+    let rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN
+            && (exhausted
+                || retry_after_secs.is_some()
+                || message.to_lowercase().contains("rate limit")));
+
+    if rate_limited {
+        return GitHubError::RateLimited {
+            reset_at: header_number(headers, "x-ratelimit-reset"),
+            retry_after_secs,
+        };
+    }
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => GitHubError::Unauthorized { message },
+        StatusCode::NOT_FOUND => GitHubError::NotFound { message },
+        _ => GitHubError::Api {
+            status: status.as_u16(),
+            message,
+        },
+    }
+}
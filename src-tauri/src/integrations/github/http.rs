@@ -0,0 +1,423 @@
+//! Request plumbing shared by the GitHub endpoints: conditional requests
+//! against a cache of ETags, waiting out short rate limits, and following
+//! `Link` headers across pages.
+
+use super::error::{classify, GitHubError};
+use super::types::SearchResult;
+use super::{GitHubClient, GITHUB_API_BASE};
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, ETAG, IF_NONE_MATCH, LINK};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Items a paginated call returns when the caller doesn't ask for a number
+pub const DEFAULT_LIMIT: usize = 100;
+/// GitHub's cap on `per_page`
+const MAX_PER_PAGE: usize = 100;
+/// Longer waits are left to the caller as a `RateLimited` error
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(30);
+const MAX_RETRIES: u32 = 3;
+/// What GitHub asks for when a secondary limit gives no `retry-after`
+const SECONDARY_LIMIT_WAIT: Duration = Duration::from_secs(60);
+const MAX_CACHED_RESPONSES: usize = 256;
+
+struct CachedResponse {
+    etag: String,
+    page: Page,
+    /// Insertion order, for evicting the oldest entry
+    sequence: u64,
+}
+
+/// Responses by token and URL. Answering `If-None-Match` with 304 doesn't
+/// count against GitHub's rate limit, so unchanged lists come back for free.
+static RESPONSE_CACHE: Lazy<Mutex<HashMap<String, CachedResponse>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Page {
+    pub body: String,
+    /// The `rel="next"` URL from the `Link` header
+    pub next: Option<String>,
+}
+
+/// Forgets every cached response, e.g. after signing out
+pub fn clear_response_cache() {
+    if let Ok(mut cache) = RESPONSE_CACHE.lock() {
+        cache.clear();
+    }
+}
+
+fn cached(key: &str) -> Option<(String, Page)> {
+    let cache = RESPONSE_CACHE.lock().ok()?;
+    let entry = cache.get(key)?;
+    Some((entry.etag.clone(), entry.page.clone()))
+}
+
+fn cache_response(key: String, etag: String, page: Page) {
+    let Ok(mut cache) = RESPONSE_CACHE.lock() else {
+        return;
+    };
+    if cache.len() >= MAX_CACHED_RESPONSES && !cache.contains_key(&key) {
+        let oldest = cache
+            .iter()
+            .min_by_key(|(_, entry)| entry.sequence)
+            .map(|(key, _)| key.clone());
+        if let Some(oldest) = oldest {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(
+        key,
+        CachedResponse {
+            etag,
+            page,
+            sequence: SEQUENCE.fetch_add(1, Ordering::Relaxed),
+        },
+    );
+}
+
+fn next_link(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == "rel=\"next\"")
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+/// How long to wait before retrying a rate-limited call, from `retry-after`
+/// or else the time the primary limit resets
+fn retry_wait(headers: &HeaderMap, now: i64) -> Duration {
+    let number = |name: &str| -> Option<i64> { headers.get(name)?.to_str().ok()?.parse().ok() };
+    if let Some(secs) = number("retry-after") {
+        return Duration::from_secs(secs.max(0) as u64);
+    }
+    match (number("x-ratelimit-remaining"), number("x-ratelimit-reset")) {
+        (Some(0), Some(reset)) => Duration::from_secs((reset - now).max(1) as u64),
+        _ => SECONDARY_LIMIT_WAIT,
+    }
+}
+
+/// `path` with its `per_page` set to `per_page`
+fn with_per_page(path: &str, per_page: usize) -> String {
+    let Ok(mut url) = url::Url::parse(&format!("{}{}", GITHUB_API_BASE, path)) else {
+        return path.to_string();
+    };
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| name != "per_page")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("per_page", &per_page.to_string());
+    url[url::Position::BeforePath..].to_string()
+}
+
+fn parse<T: DeserializeOwned>(body: &str) -> Result<T, GitHubError> {
+    serde_json::from_str(body).map_err(|e| GitHubError::InvalidResponse {
+        message: e.to_string(),
+    })
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+impl GitHubClient {
+    fn cache_key(&self, url: &str) -> String {
+        let token_hash = Sha256::digest(self.token.as_bytes());
+        format!("{} {}", hex::encode(&token_hash[..8]), url)
+    }
+
+    /// Sends a request, retrying after rate limits short enough to wait out.
+    /// GETs are conditional when an earlier response had an ETag.
+    pub(super) async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        payload: Option<&Value>,
+    ) -> Result<Page, GitHubError> {
+        let key = (method == Method::GET).then(|| self.cache_key(path));
+        let mut attempts = 0;
+        loop {
+            let mut request = self.build_request(method.clone(), path);
+            if let Some(payload) = payload {
+                request = request.json(payload);
+            }
+            let cached = key.as_deref().and_then(cached);
+            if let Some((etag, _)) = &cached {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+
+            let response = request.send().await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+
+            if status == StatusCode::NOT_MODIFIED {
+                match cached {
+                    Some((_, page)) => return Ok(page),
+                    None if attempts >= MAX_RETRIES => {
+                        return Err(GitHubError::InvalidResponse {
+                            message: "304 Not Modified for an uncached request".to_string(),
+                        })
+                    }
+                    None => {}
+                }
+            } else if status.is_success() {
+                let page = Page {
+                    body: response.text().await?,
+                    next: next_link(&headers),
+                };
+                let etag = headers.get(ETAG).and_then(|etag| etag.to_str().ok());
+                if let (Some(key), Some(etag)) = (key, etag) {
+                    cache_response(key, etag.to_string(), page.clone());
+                }
+                return Ok(page);
+            } else {
+                let body = response.text().await.unwrap_or_default();
+                let error = classify(status, &headers, &body);
+                let wait = retry_wait(&headers, now_secs());
+                let retry = matches!(error, GitHubError::RateLimited { .. })
+                    && wait <= MAX_RETRY_WAIT
+                    && attempts < MAX_RETRIES;
+                if !retry {
+                    return Err(error);
+                }
+                tracing::debug!(
+                    path,
+                    wait_secs = wait.as_secs(),
+                    "GitHub rate limited, retrying"
+                );
+                tokio::time::sleep(wait).await;
+            }
+            // A 304 for a response that's been evicted since also lands here,
+            // and is asked for again without the condition
+            attempts += 1;
+        }
+    }
+
+    pub(super) async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, GitHubError> {
+        parse(&self.send(Method::GET, path, None).await?.body)
+    }
+
+    pub(super) async fn send_json<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        payload: &Value,
+    ) -> Result<T, GitHubError> {
+        parse(&self.send(method, path, Some(payload)).await?.body)
+    }
+
+    /// Follows `next` links until `limit` items have been collected
+    pub(super) async fn paginate<T>(
+        &self,
+        path: &str,
+        limit: usize,
+        mut items_of: impl FnMut(&str) -> Result<Vec<T>, GitHubError>,
+    ) -> Result<Vec<T>, GitHubError> {
+        let limit = limit.max(1);
+        let mut items = Vec::new();
+        let mut next = Some(with_per_page(path, limit.min(MAX_PER_PAGE)));
+        while let Some(url) = next.take() {
+            let page = self.send(Method::GET, &url, None).await?;
+            let page_items = items_of(&page.body)?;
+            if page_items.is_empty() {
+                break;
+            }
+            items.extend(page_items);
+            if items.len() < limit {
+                next = page.next;
+            }
+        }
+        items.truncate(limit);
+        Ok(items)
+    }
+
+    /// Up to `limit` items of a list endpoint, across as many pages as needed
+    pub(super) async fn get_list<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        limit: usize,
+    ) -> Result<Vec<T>, GitHubError> {
+        self.paginate(path, limit, parse::<Vec<T>>).await
+    }
+
+    /// Like `get_list` for the search endpoints, which wrap their items
+    pub(super) async fn get_search<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        limit: usize,
+    ) -> Result<SearchResult<T>, GitHubError> {
+        let mut total_count = 0;
+        let mut incomplete_results = false;
+        let items = self
+            .paginate(path, limit, |body| {
+                let page: SearchResult<T> = parse(body)?;
+                total_count = page.total_count;
+                incomplete_results |= page.incomplete_results;
+                Ok(page.items)
+            })
+            .await?;
+        Ok(SearchResult {
+            total_count,
+            incomplete_results,
+            items,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_next_link() {
+        let link = headers(&[(
+            "link",
+            "<https://api.github.com/user/repos?page=1>; rel=\"prev\", \
+             <https://api.github.com/user/repos?page=3>; rel=\"next\", \
+             <https://api.github.com/user/repos?page=9>; rel=\"last\"",
+        )]);
+        assert_eq!(
+            next_link(&link).as_deref(),
+            Some("https://api.github.com/user/repos?page=3")
+        );
+        let last_page = headers(&[("link", "<https://api.github.com/x?page=1>; rel=\"first\"")]);
+        assert_eq!(next_link(&last_page), None);
+        assert_eq!(next_link(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_with_per_page() {
+        assert_eq!(
+            with_per_page("/user/repos?per_page=100&sort=updated", 20),
+            "/user/repos?sort=updated&per_page=20"
+        );
+        assert_eq!(
+            with_per_page("/repos/o/r/issues?state=open", 100),
+            "/repos/o/r/issues?state=open&per_page=100"
+        );
+        assert_eq!(with_per_page("/issues", 5), "/issues?per_page=5");
+    }
+
+    #[test]
+    fn test_retry_wait() {
+        assert_eq!(
+            retry_wait(&headers(&[("retry-after", "7")]), 0),
+            Duration::from_secs(7)
+        );
+        let exhausted = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1020"),
+        ]);
+        assert_eq!(retry_wait(&exhausted, 1000), Duration::from_secs(20));
+        assert_eq!(retry_wait(&exhausted, 2000), Duration::from_secs(1));
+        assert_eq!(retry_wait(&HeaderMap::new(), 0), SECONDARY_LIMIT_WAIT);
+    }
+
+    #[test]
+    fn test_classify() {
+        let exhausted = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1700000000"),
+        ]);
+        assert_eq!(
+            classify(
+                StatusCode::FORBIDDEN,
+                &exhausted,
+                r#"{"message":"API rate limit exceeded"}"#
+            ),
+            GitHubError::RateLimited {
+                reset_at: Some(1_700_000_000),
+                retry_after_secs: None
+            }
+        );
+        assert_eq!(
+            classify(
+                StatusCode::FORBIDDEN,
+                &headers(&[("x-ratelimit-remaining", "4999")]),
+                r#"{"message":"Resource not accessible by integration"}"#
+            ),
+            GitHubError::Unauthorized {
+                message: "Resource not accessible by integration".to_string()
+            }
+        );
+        assert!(matches!(
+            classify(
+                StatusCode::UNAUTHORIZED,
+                &HeaderMap::new(),
+                r#"{"message":"Bad credentials"}"#
+            ),
+            GitHubError::Unauthorized { .. }
+        ));
+        assert_eq!(
+            classify(StatusCode::UNPROCESSABLE_ENTITY, &HeaderMap::new(), "oops"),
+            GitHubError::Api {
+                status: 422,
+                message: "oops".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_error_serializes_with_kind() {
+        let error = GitHubError::RateLimited {
+            reset_at: Some(10),
+            retry_after_secs: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({"kind": "rateLimited", "resetAt": 10, "retryAfterSecs": null})
+        );
+        assert_eq!(
+            serde_json::to_value(GitHubError::NotAuthenticated).unwrap(),
+            serde_json::json!({"kind": "notAuthenticated"})
+        );
+    }
+
+    #[test]
+    fn test_response_cache_evicts_oldest() {
+        clear_response_cache();
+        let page = |body: &str| Page {
+            body: body.to_string(),
+            next: None,
+        };
+        for i in 0..MAX_CACHED_RESPONSES {
+            cache_response(format!("k{}", i), "\"e\"".to_string(), page("x"));
+        }
+        cache_response("new".to_string(), "\"e2\"".to_string(), page("y"));
+        assert!(cached("k0").is_none());
+        assert_eq!(cached("new"), Some(("\"e2\"".to_string(), page("y"))));
+        clear_response_cache();
+        assert!(cached("new").is_none());
+    }
+}
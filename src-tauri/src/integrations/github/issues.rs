@@ -1,53 +1,33 @@
-use super::{types::*, GitHubClient};
+use super::{types::*, GitHubClient, GitHubError, DEFAULT_LIMIT};
+use reqwest::Method;
 
 impl GitHubClient {
-    /// List issues for a repository
+    /// List up to `limit` issues for a repository
     pub async fn list_issues(
         &self,
         owner: &str,
         repo: &str,
         state: Option<&str>,
-    ) -> Result<Vec<Issue>, String> {
+        limit: Option<usize>,
+    ) -> Result<Vec<Issue>, GitHubError> {
         let mut path = format!("/repos/{}/{}/issues", owner, repo);
 
         if let Some(state) = state {
-            path.push_str(&format!("?state={}", state));
+            path.push_str(&format!("?state={}", urlencoding::encode(state)));
         }
 
-        let response = self
-            .build_request(reqwest::Method::GET, &path)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to list issues: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("GitHub API error: {}", response.status()));
-        }
-
-        response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse issues response: {}", e))
+        self.get_list(&path, limit.unwrap_or(DEFAULT_LIMIT)).await
     }
 
     /// Get a specific issue
-    pub async fn get_issue(&self, owner: &str, repo: &str, number: u64) -> Result<Issue, String> {
+    pub async fn get_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Issue, GitHubError> {
         let path = format!("/repos/{}/{}/issues/{}", owner, repo, number);
-
-        let response = self
-            .build_request(reqwest::Method::GET, &path)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get issue: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("GitHub API error: {}", response.status()));
-        }
-
-        response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse issue response: {}", e))
+        self.get_json(&path).await
     }
 
     /// Create a new issue
@@ -59,7 +39,7 @@ impl GitHubClient {
         body: Option<String>,
         labels: Option<Vec<String>>,
         assignees: Option<Vec<String>>,
-    ) -> Result<Issue, String> {
+    ) -> Result<Issue, GitHubError> {
         let path = format!("/repos/{}/{}/issues", owner, repo);
 
         let mut payload = serde_json::json!({
@@ -78,23 +58,7 @@ impl GitHubClient {
             payload["assignees"] = serde_json::json!(assignees);
         }
 
-        let response = self
-            .build_request(reqwest::Method::POST, &path)
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to create issue: {}", e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("GitHub API error {}: {}", status, error_text));
-        }
-
-        response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse created issue response: {}", e))
+        self.send_json(Method::POST, &path, &payload).await
     }
 
     /// Update an existing issue
@@ -108,7 +72,7 @@ impl GitHubClient {
         state: Option<&str>,
         labels: Option<Vec<String>>,
         assignees: Option<Vec<String>>,
-    ) -> Result<Issue, String> {
+    ) -> Result<Issue, GitHubError> {
         let path = format!("/repos/{}/{}/issues/{}", owner, repo, number);
 
         let mut payload = serde_json::json!({});
@@ -133,50 +97,32 @@ impl GitHubClient {
             payload["assignees"] = serde_json::json!(assignees);
         }
 
-        let response = self
-            .build_request(reqwest::Method::PATCH, &path)
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to update issue: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("GitHub API error: {}", response.status()));
-        }
-
-        response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse updated issue response: {}", e))
+        self.send_json(Method::PATCH, &path, &payload).await
     }
 
     /// Close an issue
-    pub async fn close_issue(&self, owner: &str, repo: &str, number: u64) -> Result<Issue, String> {
+    pub async fn close_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Issue, GitHubError> {
         self.update_issue(owner, repo, number, None, None, Some("closed"), None, None)
             .await
     }
 
-    /// List issues assigned to the authenticated user
-    pub async fn list_my_issues(&self, state: Option<&str>) -> Result<Vec<Issue>, String> {
+    /// List up to `limit` issues assigned to the authenticated user
+    pub async fn list_my_issues(
+        &self,
+        state: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Issue>, GitHubError> {
         let mut path = "/issues".to_string();
 
         if let Some(state) = state {
-            path.push_str(&format!("?state={}", state));
+            path.push_str(&format!("?state={}", urlencoding::encode(state)));
         }
 
-        let response = self
-            .build_request(reqwest::Method::GET, &path)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to list my issues: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("GitHub API error: {}", response.status()));
-        }
-
-        response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse my issues response: {}", e))
+        self.get_list(&path, limit.unwrap_or(DEFAULT_LIMIT)).await
     }
 }
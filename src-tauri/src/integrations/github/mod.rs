@@ -1,4 +1,7 @@
+pub mod actions;
 pub mod auth;
+pub mod error;
+pub mod http;
 pub mod issues;
 pub mod search;
 pub mod types;
@@ -6,6 +9,8 @@ pub mod types;
 pub use auth::{
     delete_token, get_token, poll_for_token, start_device_flow, store_token, DeviceCodeResponse,
 };
+pub use error::GitHubError;
+pub use http::{clear_response_cache, DEFAULT_LIMIT};
 pub use types::*;
 
 use reqwest::Client;
@@ -27,14 +32,21 @@ impl GitHubClient {
     }
 
     /// Create a new client from stored token
-    pub fn from_stored_token() -> Result<Self, String> {
-        let token = get_token()?.ok_or("No GitHub token found. Please authenticate first.")?;
+    pub fn from_stored_token() -> Result<Self, GitHubError> {
+        let token = get_token()
+            .map_err(|message| GitHubError::Storage { message })?
+            .ok_or(GitHubError::NotAuthenticated)?;
         Ok(Self::new(token))
     }
 
-    /// Helper to build authenticated requests
+    /// Helper to build authenticated requests; `path` can also be a full API
+    /// URL, like the ones in pagination links
     fn build_request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
-        let url = format!("{}{}", GITHUB_API_BASE, path);
+        let url = if path.starts_with(GITHUB_API_BASE) {
+            path.to_string()
+        } else {
+            format!("{}{}", GITHUB_API_BASE, path)
+        };
         self.http_client
             .request(method, &url)
             .header("Authorization", format!("Bearer {}", self.token))
@@ -44,20 +56,7 @@ impl GitHubClient {
     }
 
     /// Test the authentication by getting the current user
-    pub async fn get_current_user(&self) -> Result<User, String> {
-        let response = self
-            .build_request(reqwest::Method::GET, "/user")
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get current user: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("GitHub API error: {}", response.status()));
-        }
-
-        response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse user response: {}", e))
+    pub async fn get_current_user(&self) -> Result<User, GitHubError> {
+        self.get_json("/user").await
     }
 }
@@ -114,3 +114,35 @@ pub struct SearchResult<T> {
     pub incomplete_results: bool,
     pub items: Vec<T>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRun {
+    pub id: u64,
+    pub name: Option<String>,
+    pub display_title: String,
+    /// `queued`, `in_progress`, `completed`, ...
+    pub status: Option<String>,
+    /// Set once completed: `success`, `failure`, `cancelled`, ...
+    pub conclusion: Option<String>,
+    pub head_branch: Option<String>,
+    pub head_sha: String,
+    pub event: String,
+    pub run_number: u64,
+    #[serde(default)]
+    pub run_attempt: Option<u64>,
+    pub html_url: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl WorkflowRun {
+    pub fn is_completed(&self) -> bool {
+        self.status.as_deref() == Some("completed")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRuns {
+    pub total_count: u64,
+    pub workflow_runs: Vec<WorkflowRun>,
+}
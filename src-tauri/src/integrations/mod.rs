@@ -1 +1,5 @@
+pub mod email;
 pub mod github;
+pub mod notes;
+pub mod slack;
+pub mod tasks;
@@ -0,0 +1,534 @@
+//! Quick capture into an Obsidian vault or a plain Markdown directory:
+//! appending to today's daily note, turning the selection into a new note,
+//! and searching note titles and headings.
+//!
+//! Obsidian vaults (those with a `.obsidian` folder) pick up the daily note
+//! folder, file name format and template from the Daily notes core plugin.
+
+pub mod search;
+pub mod template;
+
+pub use search::NoteMatch;
+
+use crate::error::AppError;
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_opener::{open_path, open_url};
+use template::{moment_to_chrono, render, TemplateContext};
+
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+const MAX_TITLE_LENGTH: usize = 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NotesSettings {
+    /// Vault or notes directory; `~/` is expanded
+    pub vault_path: Option<String>,
+    /// Folder for daily notes, relative to the vault
+    #[serde(default = "default_daily_folder")]
+    pub daily_folder: String,
+    /// moment.js style file name format, as in Obsidian
+    #[serde(default = "default_daily_format")]
+    pub daily_format: String,
+    /// Template for new daily notes, relative to the vault
+    #[serde(default)]
+    pub daily_template: Option<String>,
+    /// Line appended to the daily note for each capture
+    #[serde(default = "default_daily_entry")]
+    pub daily_entry: String,
+    /// Folder for notes created from the selection, relative to the vault
+    #[serde(default)]
+    pub new_note_folder: String,
+    #[serde(default)]
+    pub new_note_template: Option<String>,
+    /// Use Obsidian's own daily note settings when the vault has them
+    #[serde(default = "default_true")]
+    pub follow_obsidian_settings: bool,
+}
+
+impl Default for NotesSettings {
+    fn default() -> Self {
+        Self {
+            vault_path: None,
+            daily_folder: default_daily_folder(),
+            daily_format: default_daily_format(),
+            daily_template: None,
+            daily_entry: default_daily_entry(),
+            new_note_folder: String::new(),
+            new_note_template: None,
+            follow_obsidian_settings: true,
+        }
+    }
+}
+
+fn default_daily_folder() -> String {
+    "Daily".to_string()
+}
+
+fn default_daily_format() -> String {
+    "YYYY-MM-DD".to_string()
+}
+
+fn default_daily_entry() -> String {
+    "- {{time}} {{content}}".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// `.obsidian/daily-notes.json`, written by the Daily notes core plugin
+#[derive(Deserialize, Debug, Default)]
+struct ObsidianDailyNotes {
+    #[serde(default)]
+    folder: String,
+    #[serde(default)]
+    format: String,
+    #[serde(default)]
+    template: String,
+}
+
+/// A note that was just written to
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRef {
+    pub path: String,
+    pub title: String,
+    /// `obsidian://` link for vaults, `None` for plain directories
+    pub obsidian_uri: Option<String>,
+}
+
+struct DailyNoteConfig {
+    folder: String,
+    format: String,
+    template: Option<String>,
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn is_obsidian_vault(root: &Path) -> bool {
+    root.join(".obsidian").is_dir()
+}
+
+impl NotesSettings {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("notes_settings.json"))
+    }
+
+    pub fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+
+    pub(crate) fn vault_root(&self) -> Result<PathBuf, String> {
+        let path = self
+            .vault_path
+            .as_deref()
+            .filter(|path| !path.trim().is_empty())
+            .ok_or("No notes folder configured")?;
+        let root = expand_home(path.trim());
+        if !root.is_dir() {
+            return Err(format!("Notes folder {} does not exist", root.display()));
+        }
+        Ok(root)
+    }
+
+    fn daily_config(&self, root: &Path) -> DailyNoteConfig {
+        let mut config = DailyNoteConfig {
+            folder: self.daily_folder.clone(),
+            format: self.daily_format.clone(),
+            template: self.daily_template.clone(),
+        };
+        if !self.follow_obsidian_settings {
+            return config;
+        }
+        let obsidian: Option<ObsidianDailyNotes> =
+            fs::read_to_string(root.join(".obsidian/daily-notes.json"))
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok());
+        if let Some(obsidian) = obsidian {
+            // Obsidian stores an empty string for "vault root" / "default"
+            config.folder = obsidian.folder;
+            if !obsidian.format.is_empty() {
+                config.format = obsidian.format;
+            }
+            if !obsidian.template.is_empty() {
+                config.template = Some(obsidian.template);
+            }
+        }
+        config
+    }
+}
+
+/// Resolves a vault-relative path, refusing anything that escapes the vault
+fn vault_path(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative.trim_matches('/'));
+    if relative
+        .components()
+        .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        return Err(format!(
+            "{} is outside the notes folder",
+            relative.display()
+        ));
+    }
+    Ok(root.join(relative))
+}
+
+fn with_md_extension(path: PathBuf) -> PathBuf {
+    if search::is_note(&path) {
+        path
+    } else {
+        let mut name = path.into_os_string();
+        name.push(".md");
+        PathBuf::from(name)
+    }
+}
+
+/// Templates are referenced without `.md`, as Obsidian does
+fn read_template(root: &Path, template: Option<&str>) -> Result<Option<String>, String> {
+    let Some(template) = template.filter(|t| !t.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let path = with_md_extension(vault_path(root, template)?);
+    fs::read_to_string(&path)
+        .map(Some)
+        .map_err(|e| format!("Failed to read template {}: {}", path.display(), e))
+}
+
+/// Characters that are invalid in file names or break Obsidian links
+fn sanitize_title(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    cleaned.trim_matches('.').trim().to_string()
+}
+
+/// First line of the content, without Markdown heading markers
+fn title_from_content(content: &str) -> Option<String> {
+    let line = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())?;
+    let title = sanitize_title(line.trim_start_matches('#'));
+    let title: String = title.chars().take(MAX_TITLE_LENGTH).collect();
+    Some(title.trim().to_string()).filter(|title| !title.is_empty())
+}
+
+/// `Title.md`, or `Title 1.md`, `Title 2.md`, ... when taken
+fn unique_note_path(dir: &Path, title: &str) -> (PathBuf, String) {
+    let mut candidate = title.to_string();
+    let mut n = 1;
+    while dir.join(format!("{}.md", candidate)).exists() {
+        candidate = format!("{} {}", title, n);
+        n += 1;
+    }
+    (dir.join(format!("{}.md", candidate)), candidate)
+}
+
+fn note_ref(root: &Path, path: &Path, title: String) -> NoteRef {
+    let obsidian_uri = is_obsidian_vault(root).then(|| {
+        let vault = root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let file = path
+            .strip_prefix(root)
+            .unwrap_or(path)
+            .with_extension("")
+            .to_string_lossy()
+            .to_string();
+        format!(
+            "obsidian://open?vault={}&file={}",
+            urlencoding::encode(&vault),
+            urlencoding::encode(&file)
+        )
+    });
+    NoteRef {
+        path: path.to_string_lossy().to_string(),
+        title,
+        obsidian_uri,
+    }
+}
+
+fn append_to_daily_note(
+    settings: &NotesSettings,
+    root: &Path,
+    text: &str,
+    now: NaiveDateTime,
+) -> Result<NoteRef, String> {
+    let config = settings.daily_config(root);
+    let name = now.format(&moment_to_chrono(&config.format)).to_string();
+    let folder = vault_path(root, &config.folder)?;
+    // Formats like `YYYY/MM/YYYY-MM-DD` put notes in dated subfolders
+    let path = with_md_extension(vault_path(&folder, &name)?);
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or(name);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut existing = if path.exists() {
+        fs::read_to_string(&path).map_err(|e| e.to_string())?
+    } else {
+        let template = read_template(root, config.template.as_deref())?.unwrap_or_default();
+        let ctx = TemplateContext {
+            title: &title,
+            content: "",
+            now,
+        };
+        render(&template, &ctx)
+    };
+
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        existing.push('\n');
+    }
+    let ctx = TemplateContext {
+        title: &title,
+        content: text.trim(),
+        now,
+    };
+    existing.push_str(&render(&settings.daily_entry, &ctx));
+    existing.push('\n');
+
+    fs::write(&path, existing).map_err(|e| e.to_string())?;
+    Ok(note_ref(root, &path, title))
+}
+
+fn create_note(
+    settings: &NotesSettings,
+    root: &Path,
+    title: Option<&str>,
+    content: &str,
+    now: NaiveDateTime,
+) -> Result<NoteRef, String> {
+    let title = title
+        .map(sanitize_title)
+        .filter(|title| !title.is_empty())
+        .or_else(|| title_from_content(content))
+        .unwrap_or_else(|| format!("Untitled {}", now.format("%Y-%m-%d %H%M")));
+
+    let dir = vault_path(root, &settings.new_note_folder)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let (path, title) = unique_note_path(&dir, &title);
+
+    let body = match read_template(root, settings.new_note_template.as_deref())? {
+        Some(template) => render(
+            &template,
+            &TemplateContext {
+                title: &title,
+                content,
+                now,
+            },
+        ),
+        None => format!("{}\n", content.trim_end()),
+    };
+    fs::write(&path, body).map_err(|e| e.to_string())?;
+
+    Ok(note_ref(root, &path, title))
+}
+
+fn load_settings(app: &AppHandle) -> Result<NotesSettings, String> {
+    NotesSettings::load(app).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_notes_settings(app: AppHandle) -> Result<NotesSettings, String> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_notes_settings(app: AppHandle, settings: NotesSettings) -> Result<(), String> {
+    settings.save(&app).map_err(|e| e.to_string())
+}
+
+/// Appends `text` to today's daily note, creating it from the template first
+#[tauri::command]
+pub fn notes_append_to_daily(app: AppHandle, text: String) -> Result<NoteRef, String> {
+    if text.trim().is_empty() {
+        return Err("Nothing to append".to_string());
+    }
+    let settings = load_settings(&app)?;
+    let root = settings.vault_root()?;
+    append_to_daily_note(&settings, &root, &text, Local::now().naive_local())
+}
+
+/// Creates a note from `content`, or from the current selection when omitted
+#[tauri::command]
+pub fn notes_create_from_selection(
+    app: AppHandle,
+    title: Option<String>,
+    content: Option<String>,
+) -> Result<NoteRef, String> {
+    let content = content.unwrap_or_else(selection::get_text);
+    if content.trim().is_empty() {
+        return Err("No text selected".to_string());
+    }
+    let settings = load_settings(&app)?;
+    let root = settings.vault_root()?;
+    create_note(
+        &settings,
+        &root,
+        title.as_deref(),
+        &content,
+        Local::now().naive_local(),
+    )
+}
+
+#[tauri::command]
+pub fn search_notes(
+    app: AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<NoteMatch>, String> {
+    let root = load_settings(&app)?.vault_root()?;
+    Ok(search::search(
+        &root,
+        &query,
+        limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+    ))
+}
+
+/// Opens a note in Obsidian when it lives in a vault, otherwise in the
+/// default Markdown editor
+#[tauri::command]
+pub fn notes_open(app: AppHandle, path: String) -> Result<(), String> {
+    let root = load_settings(&app)?
+        .vault_root()?
+        .canonicalize()
+        .map_err(|e| e.to_string())?;
+    // Resolves `..` and symlinks, which `starts_with` alone would let through
+    let path = PathBuf::from(path)
+        .canonicalize()
+        .map_err(|e| e.to_string())?;
+    if !path.starts_with(&root) {
+        return Err("Note is outside the notes folder".to_string());
+    }
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match note_ref(&root, &path, title).obsidian_uri {
+        Some(uri) => open_url(uri, None::<String>).map_err(|e| e.to_string()),
+        None => open_path(&path, None::<String>).map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 1, 15)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap()
+    }
+
+    fn temp_vault() -> PathBuf {
+        let root = env::temp_dir().join(format!("flare_vault_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_sanitize_and_title_from_content() {
+        assert_eq!(sanitize_title("a/b: c?"), "a b c");
+        assert_eq!(
+            title_from_content("\n## Meeting notes\nbody").as_deref(),
+            Some("Meeting notes")
+        );
+        assert_eq!(title_from_content("  \n "), None);
+    }
+
+    #[test]
+    fn test_vault_path_rejects_escapes() {
+        let root = Path::new("/vault");
+        assert_eq!(vault_path(root, "Daily/").unwrap(), root.join("Daily"));
+        assert!(vault_path(root, "../etc").is_err());
+    }
+
+    #[test]
+    fn test_append_to_daily_note_uses_obsidian_config() {
+        let root = temp_vault();
+        fs::create_dir_all(root.join(".obsidian")).unwrap();
+        fs::create_dir_all(root.join("Templates")).unwrap();
+        fs::write(
+            root.join(".obsidian/daily-notes.json"),
+            r#"{"folder":"Journal","format":"YYYY/YYYY-MM-DD","template":"Templates/Daily"}"#,
+        )
+        .unwrap();
+        fs::write(root.join("Templates/Daily.md"), "# {{title}}").unwrap();
+
+        let settings = NotesSettings::default();
+        append_to_daily_note(&settings, &root, "first", now()).unwrap();
+        let note = append_to_daily_note(&settings, &root, "second", now()).unwrap();
+
+        let content = fs::read_to_string(&note.path).unwrap();
+        assert!(note.path.ends_with("Journal/2025/2025-01-15.md"));
+        assert_eq!(content, "# 2025-01-15\n- 09:30 first\n- 09:30 second\n");
+        assert!(note
+            .obsidian_uri
+            .unwrap()
+            .ends_with("&file=Journal%2F2025%2F2025-01-15"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_create_note_from_selection() {
+        let root = temp_vault();
+        let settings = NotesSettings {
+            new_note_folder: "Inbox".to_string(),
+            ..Default::default()
+        };
+        let first = create_note(&settings, &root, None, "Idea: a launcher", now()).unwrap();
+        let second = create_note(&settings, &root, None, "Idea: a launcher", now()).unwrap();
+
+        assert_eq!(first.title, "Idea a launcher");
+        assert_eq!(second.title, "Idea a launcher 1");
+        assert_eq!(first.obsidian_uri, None);
+        assert_eq!(
+            fs::read_to_string(&first.path).unwrap(),
+            "Idea: a launcher\n"
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Title matches outrank heading matches of the same quality
+const TITLE_BONUS: f64 = 10.0;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteMatch {
+    pub path: String,
+    pub title: String,
+    /// Matching heading, `None` when the title itself matched
+    pub heading: Option<String>,
+    /// 1-based line of the heading, 0 for title matches
+    pub line: usize,
+    pub score: f64,
+}
+
+pub fn is_note(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md"))
+}
+
+/// Every Markdown file under `root`, skipping hidden entries like `.obsidian`
+/// and `.trash`
+pub fn collect_notes(root: &Path) -> Vec<PathBuf> {
+    let mut notes = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => pending.push(path),
+                Ok(_) if is_note(&path) => notes.push(path),
+                _ => {}
+            }
+        }
+    }
+    notes
+}
+
+/// ATX headings with their 1-based line numbers, ignoring front matter and
+/// fenced code blocks
+pub fn extract_headings(content: &str) -> Vec<(usize, String)> {
+    let mut headings = Vec::new();
+    let mut in_fence = false;
+    let mut in_front_matter = false;
+
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if i == 0 && line.trim_end() == "---" {
+            in_front_matter = true;
+            continue;
+        }
+        if in_front_matter {
+            in_front_matter = line.trim_end() != "---";
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let hashes = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+            let text = trimmed[hashes..].trim().trim_end_matches('#').trim();
+            if !text.is_empty() {
+                headings.push((i + 1, text.to_string()));
+            }
+        }
+    }
+    headings
+}
+
+/// Exact > prefix > word prefix > substring > all words present
+fn score(query: &str, text: &str) -> Option<f64> {
+    let text = text.to_lowercase();
+    if text == query {
+        return Some(100.0);
+    }
+    if text.starts_with(query) {
+        return Some(80.0);
+    }
+    if text
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.starts_with(query))
+    {
+        return Some(60.0);
+    }
+    if text.contains(query) {
+        return Some(40.0);
+    }
+    let mut words = query.split_whitespace().peekable();
+    if words.peek().is_some() && words.all(|word| text.contains(word)) {
+        return Some(20.0);
+    }
+    None
+}
+
+fn title_of(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Matches `query` against note titles and headings, best first
+pub fn search(root: &Path, query: &str, limit: usize) -> Vec<NoteMatch> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    for path in collect_notes(root) {
+        let title = title_of(&path);
+        let path_str = path.to_string_lossy().to_string();
+        if let Some(score) = score(&query, &title) {
+            matches.push(NoteMatch {
+                path: path_str.clone(),
+                title: title.clone(),
+                heading: None,
+                line: 0,
+                score: score + TITLE_BONUS,
+            });
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for (line, heading) in extract_headings(&content) {
+            if let Some(score) = score(&query, &heading) {
+                matches.push(NoteMatch {
+                    path: path_str.clone(),
+                    title: title.clone(),
+                    heading: Some(heading),
+                    line,
+                    score,
+                });
+            }
+        }
+    }
+
+    matches.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.title.cmp(&b.title))
+            .then_with(|| a.line.cmp(&b.line))
+    });
+    matches.truncate(limit);
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_headings() {
+        let content = "---\ntitle: x\n# not a heading\n---\n# Intro\ntext\n```\n# code\n```\n## Next steps ##\n#tag";
+        assert_eq!(
+            extract_headings(content),
+            vec![(5, "Intro".to_string()), (10, "Next steps".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_score_ordering() {
+        assert_eq!(score("rust", "rust"), Some(100.0));
+        assert_eq!(score("rust", "Rust tips"), Some(80.0));
+        assert_eq!(score("tips", "Rust tips"), Some(60.0));
+        assert_eq!(score("ust", "Rust tips"), Some(40.0));
+        assert_eq!(score("tips rust", "Rust tips"), Some(20.0));
+        assert_eq!(score("go", "Rust tips"), None);
+    }
+
+    #[test]
+    fn test_search_vault() {
+        let root = std::env::temp_dir().join(format!("flare_notes_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("Projects")).unwrap();
+        fs::create_dir_all(root.join(".obsidian")).unwrap();
+        fs::write(
+            root.join("Projects/Launcher.md"),
+            "# Roadmap\n## Launcher plugins",
+        )
+        .unwrap();
+        fs::write(root.join("Inbox.md"), "# Launcher ideas").unwrap();
+        fs::write(root.join(".obsidian/launcher.md"), "# Launcher").unwrap();
+
+        let results = search(&root, "launcher", 10);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].title, "Launcher");
+        assert_eq!(results[0].heading, None);
+        assert!(results.iter().all(|m| !m.path.contains(".obsidian")));
+    }
+}
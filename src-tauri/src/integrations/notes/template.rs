@@ -0,0 +1,126 @@
+use chrono::NaiveDateTime;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+const DEFAULT_DATE_FORMAT: &str = "YYYY-MM-DD";
+const DEFAULT_TIME_FORMAT: &str = "HH:mm";
+
+static PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{\s*(\w+)(?::([^}]*))?\s*\}\}").unwrap());
+
+/// Values for `{{title}}`, `{{content}}`, `{{date}}` and `{{time}}`
+pub struct TemplateContext<'a> {
+    pub title: &'a str,
+    pub content: &'a str,
+    pub now: NaiveDateTime,
+}
+
+/// Converts the moment.js tokens Obsidian uses (`YYYY-MM-DD`, `ddd HH:mm`)
+/// into a chrono format string. Text in `[brackets]` is kept literally.
+pub fn moment_to_chrono(format: &str) -> String {
+    const TOKENS: &[(&str, &str)] = &[
+        ("YYYY", "%Y"),
+        ("YY", "%y"),
+        ("MMMM", "%B"),
+        ("MMM", "%b"),
+        ("MM", "%m"),
+        ("M", "%-m"),
+        ("dddd", "%A"),
+        ("ddd", "%a"),
+        ("DD", "%d"),
+        ("D", "%-d"),
+        ("HH", "%H"),
+        ("H", "%-H"),
+        ("hh", "%I"),
+        ("h", "%-I"),
+        ("mm", "%M"),
+        ("ss", "%S"),
+        ("A", "%p"),
+        ("a", "%P"),
+        ("ww", "%V"),
+    ];
+
+    let mut converted = String::with_capacity(format.len() * 2);
+    let mut rest = format;
+    'outer: while let Some(c) = rest.chars().next() {
+        if c == '[' {
+            if let Some(end) = rest.find(']') {
+                converted.push_str(&rest[1..end].replace('%', "%%"));
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        for (token, replacement) in TOKENS {
+            if let Some(after) = rest.strip_prefix(token) {
+                converted.push_str(replacement);
+                rest = after;
+                continue 'outer;
+            }
+        }
+        if c == '%' {
+            converted.push('%');
+        }
+        converted.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    converted
+}
+
+/// Fills placeholders with the Obsidian core Templates syntax: `{{date}}`,
+/// `{{time}}`, `{{date:dddd D MMMM}}`, `{{title}}` and `{{content}}`
+/// (alias `{{selection}}`). Unknown placeholders are left untouched.
+pub fn render(template: &str, ctx: &TemplateContext) -> String {
+    PLACEHOLDER
+        .replace_all(template, |caps: &Captures| {
+            let format = caps.get(2).map(|m| m.as_str().trim());
+            match &caps[1].to_lowercase()[..] {
+                "date" => {
+                    let format = moment_to_chrono(format.unwrap_or(DEFAULT_DATE_FORMAT));
+                    ctx.now.format(&format).to_string()
+                }
+                "time" => {
+                    let format = moment_to_chrono(format.unwrap_or(DEFAULT_TIME_FORMAT));
+                    ctx.now.format(&format).to_string()
+                }
+                "title" => ctx.title.to_string(),
+                "content" | "selection" => ctx.content.to_string(),
+                _ => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_moment_to_chrono() {
+        assert_eq!(moment_to_chrono("YYYY-MM-DD"), "%Y-%m-%d");
+        assert_eq!(moment_to_chrono("dddd, D MMMM"), "%A, %-d %B");
+        assert_eq!(moment_to_chrono("[Week] ww"), "Week %V");
+        assert_eq!(moment_to_chrono("YYYY/MM/100%"), "%Y/%m/100%%");
+    }
+
+    #[test]
+    fn test_render() {
+        let ctx = TemplateContext {
+            title: "Ideas",
+            content: "Use less regex",
+            now: NaiveDate::from_ymd_opt(2025, 1, 15)
+                .unwrap()
+                .and_hms_opt(9, 5, 0)
+                .unwrap(),
+        };
+        assert_eq!(
+            render("# {{title}}\n{{date}} {{time}}\n{{ selection }}", &ctx),
+            "# Ideas\n2025-01-15 09:05\nUse less regex"
+        );
+        assert_eq!(render("{{date:ddd D MMM}}", &ctx), "Wed 15 Jan");
+        assert_eq!(
+            render("{{tp.file.title}} {{unknown}}", &ctx),
+            "{{tp.file.title}} {{unknown}}"
+        );
+    }
+}
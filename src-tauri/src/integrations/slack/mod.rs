@@ -0,0 +1,430 @@
+//! Slack from the launcher: set your status, send a message, find channels
+//! and see which conversations have unread messages.
+//!
+//! Uses a user token (`xoxp-…`) kept in the OAuth token store under the
+//! `slack` provider id, where OAuth sign-in flows save it as well.
+
+pub mod types;
+
+pub use types::*;
+
+use crate::oauth;
+use futures_util::future::join_all;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tauri::AppHandle;
+
+const SLACK_API_BASE: &str = "https://slack.com/api";
+const PROVIDER_ID: &str = "slack";
+const CONVERSATION_TYPES: &str = "public_channel,private_channel,mpim,im";
+const PAGE_SIZE: &str = "200";
+const MAX_PAGES: usize = 10;
+/// Unread counts need a request per conversation, so only the most recent
+/// ones are checked
+const MAX_UNREAD_CHECKS: usize = 40;
+const MAX_SEARCH_RESULTS: usize = 20;
+
+pub struct SlackClient {
+    token: String,
+    http_client: Client,
+}
+
+/// `coffee` and `:coffee:` both become `:coffee:`
+fn normalize_emoji(emoji: &str) -> String {
+    let name = emoji.trim().trim_matches(':');
+    if name.is_empty() {
+        String::new()
+    } else {
+        format!(":{}:", name)
+    }
+}
+
+/// Slack wants a Unix timestamp, with 0 meaning the status never expires
+fn status_expiration(minutes: Option<i64>, now: i64) -> i64 {
+    match minutes {
+        Some(minutes) if minutes > 0 => now + minutes * 60,
+        _ => 0,
+    }
+}
+
+fn conversation_kind(raw: &RawConversation) -> ConversationKind {
+    if raw.is_im {
+        ConversationKind::DirectMessage
+    } else if raw.is_mpim {
+        ConversationKind::GroupMessage
+    } else if raw.is_private || raw.is_group {
+        ConversationKind::PrivateChannel
+    } else {
+        ConversationKind::Channel
+    }
+}
+
+fn to_conversation(raw: &RawConversation, name: String, team_id: &str) -> Conversation {
+    Conversation {
+        id: raw.id.clone(),
+        name,
+        kind: conversation_kind(raw),
+        unread_count: raw.unread_count_display,
+        url: format!("slack://channel?team={}&id={}", team_id, raw.id),
+    }
+}
+
+/// Channels whose name contains `query`, prefix matches first
+fn rank_channels<'a>(query: &str, channels: &'a [RawConversation]) -> Vec<&'a RawConversation> {
+    let query = query.trim().trim_start_matches('#').to_lowercase();
+    let mut matches: Vec<(bool, &RawConversation)> = channels
+        .iter()
+        .filter(|channel| !channel.is_im)
+        .filter_map(|channel| {
+            let name = channel.name.as_deref()?.to_lowercase();
+            name.contains(&query)
+                .then_some((!name.starts_with(&query), channel))
+        })
+        .collect();
+    matches
+        .sort_by(|(a_rank, a), (b_rank, b)| a_rank.cmp(b_rank).then_with(|| a.name.cmp(&b.name)));
+    matches.into_iter().map(|(_, channel)| channel).collect()
+}
+
+/// Slack answers HTTP 200 with `ok: false` for API errors
+fn parse_response<T: DeserializeOwned>(method: &str, body: Value) -> Result<T, String> {
+    let envelope: Envelope = serde_json::from_value(body.clone())
+        .map_err(|e| format!("Failed to parse {} response: {}", method, e))?;
+    if !envelope.ok {
+        let error = envelope
+            .error
+            .unwrap_or_else(|| "unknown_error".to_string());
+        return Err(format!("Slack API error ({}): {}", method, error));
+    }
+    serde_json::from_value(body).map_err(|e| format!("Failed to parse {} response: {}", method, e))
+}
+
+impl SlackClient {
+    pub fn new(token: String) -> Self {
+        Self {
+            token,
+            http_client: Client::new(),
+        }
+    }
+
+    pub fn from_stored_token(app: &AppHandle) -> Result<Self, String> {
+        let token = oauth::get_access_token(app, PROVIDER_ID)?
+            .ok_or("Slack is not connected. Please sign in first.")?;
+        Ok(Self::new(token))
+    }
+
+    /// Calls a Web API method with form parameters, which every method accepts
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &[(&str, String)],
+    ) -> Result<T, String> {
+        let response = self
+            .http_client
+            .post(format!("{}/{}", SLACK_API_BASE, method))
+            .bearer_auth(&self.token)
+            .form(params)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to call {}: {}", method, e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Slack API error: {}", response.status()));
+        }
+
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse {} response: {}", method, e))?;
+        parse_response(method, body)
+    }
+
+    pub async fn auth_test(&self) -> Result<AuthTest, String> {
+        self.call("auth.test", &[]).await
+    }
+
+    pub async fn set_status(&self, text: &str, emoji: &str, expiration: i64) -> Result<(), String> {
+        let profile = serde_json::json!({
+            "status_text": text,
+            "status_emoji": normalize_emoji(emoji),
+            "status_expiration": expiration,
+        });
+        self.call::<Value>("users.profile.set", &[("profile", profile.to_string())])
+            .await
+            .map(|_| ())
+    }
+
+    /// Conversations the user is a member of, most recently created first
+    async fn user_conversations(&self) -> Result<Vec<RawConversation>, String> {
+        let mut conversations = Vec::new();
+        let mut cursor = String::new();
+        for _ in 0..MAX_PAGES {
+            let mut params = vec![
+                ("types", CONVERSATION_TYPES.to_string()),
+                ("exclude_archived", "true".to_string()),
+                ("limit", PAGE_SIZE.to_string()),
+            ];
+            if !cursor.is_empty() {
+                params.push(("cursor", cursor.clone()));
+            }
+            let page: ConversationsPage = self.call("users.conversations", &params).await?;
+            conversations.extend(page.channels);
+            cursor = page.response_metadata.next_cursor;
+            if cursor.is_empty() {
+                break;
+            }
+        }
+        Ok(conversations)
+    }
+
+    async fn open_dm(&self, user: &str) -> Result<String, String> {
+        let opened: OpenedConversation = self
+            .call("conversations.open", &[("users", user.to_string())])
+            .await?;
+        Ok(opened.channel.id)
+    }
+
+    async fn user_name(&self, user: &str) -> String {
+        let info: Result<UserInfo, String> =
+            self.call("users.info", &[("user", user.to_string())]).await;
+        let Ok(info) = info else {
+            return user.to_string();
+        };
+        info.user
+            .profile
+            .and_then(|profile| {
+                profile
+                    .display_name
+                    .filter(|name| !name.is_empty())
+                    .or(profile.real_name)
+            })
+            .unwrap_or(info.user.name)
+    }
+
+    /// Resolves `#name`, a user id (opens the DM) or a conversation id
+    async fn resolve_channel(&self, channel: &str) -> Result<String, String> {
+        let channel = channel.trim();
+        if let Some(name) = channel.strip_prefix('#') {
+            return self
+                .user_conversations()
+                .await?
+                .into_iter()
+                .find(|c| {
+                    c.name
+                        .as_deref()
+                        .is_some_and(|n| n.eq_ignore_ascii_case(name))
+                })
+                .map(|c| c.id)
+                .ok_or_else(|| format!("Channel #{} not found", name));
+        }
+        if channel.starts_with('U') || channel.starts_with('W') {
+            return self.open_dm(channel).await;
+        }
+        Ok(channel.to_string())
+    }
+
+    pub async fn post_message(&self, channel: &str, text: &str) -> Result<SentMessage, String> {
+        let channel = self.resolve_channel(channel).await?;
+        let posted: PostedMessage = self
+            .call(
+                "chat.postMessage",
+                &[("channel", channel), ("text", text.to_string())],
+            )
+            .await?;
+        Ok(SentMessage {
+            channel: posted.channel,
+            ts: posted.ts,
+        })
+    }
+
+    pub async fn search_channels(&self, query: &str) -> Result<Vec<Conversation>, String> {
+        let team_id = self.auth_test().await?.team_id;
+        let conversations = self.user_conversations().await?;
+        Ok(rank_channels(query, &conversations)
+            .into_iter()
+            .take(MAX_SEARCH_RESULTS)
+            .map(|raw| to_conversation(raw, raw.name.clone().unwrap_or_default(), &team_id))
+            .collect())
+    }
+
+    /// Fills in the unread count, counting messages after `last_read` when
+    /// Slack doesn't report one
+    async fn with_unread_count(&self, raw: RawConversation) -> Option<RawConversation> {
+        let info: ConversationInfo = self
+            .call("conversations.info", &[("channel", raw.id.clone())])
+            .await
+            .ok()?;
+        let mut conversation = info.channel;
+        if conversation.unread_count_display.is_none() {
+            let last_read = conversation.last_read.clone()?;
+            let history: History = self
+                .call(
+                    "conversations.history",
+                    &[
+                        ("channel", raw.id.clone()),
+                        ("oldest", last_read),
+                        ("limit", "100".to_string()),
+                    ],
+                )
+                .await
+                .ok()?;
+            conversation.unread_count_display = Some(history.messages.len() as u32);
+        }
+        // users.conversations omits the DM partner in some workspaces
+        conversation.user = conversation.user.or(raw.user);
+        Some(conversation)
+    }
+
+    pub async fn unread_conversations(&self) -> Result<Vec<Conversation>, String> {
+        let team_id = self.auth_test().await?.team_id;
+        let conversations = self.user_conversations().await?;
+        let checked = join_all(
+            conversations
+                .into_iter()
+                .take(MAX_UNREAD_CHECKS)
+                .map(|raw| self.with_unread_count(raw)),
+        )
+        .await;
+
+        let mut unread = Vec::new();
+        for raw in checked.into_iter().flatten() {
+            if raw.unread_count_display.unwrap_or(0) == 0 {
+                continue;
+            }
+            let name = match (&raw.name, &raw.user) {
+                (_, Some(user)) if raw.is_im => self.user_name(user).await,
+                (Some(name), _) => name.clone(),
+                _ => raw.id.clone(),
+            };
+            unread.push(to_conversation(&raw, name, &team_id));
+        }
+        unread.sort_by_key(|channel| std::cmp::Reverse(channel.unread_count));
+        Ok(unread)
+    }
+}
+
+/// Checks the token with `auth.test` before saving it
+#[tauri::command]
+pub async fn slack_connect(app: AppHandle, token: String) -> Result<AuthTest, String> {
+    let token = token.trim().to_string();
+    let auth = SlackClient::new(token.clone()).auth_test().await?;
+    oauth::set_access_token(&app, PROVIDER_ID, &token, None)?;
+    Ok(auth)
+}
+
+#[tauri::command]
+pub fn slack_disconnect(app: AppHandle) -> Result<(), String> {
+    oauth::remove_tokens(&app, PROVIDER_ID)
+}
+
+#[tauri::command]
+pub fn slack_is_connected(app: AppHandle) -> Result<bool, String> {
+    Ok(oauth::get_access_token(&app, PROVIDER_ID)?.is_some())
+}
+
+/// Sets the status; `expiration_minutes` of `None` or 0 keeps it until cleared
+#[tauri::command]
+pub async fn slack_set_status(
+    app: AppHandle,
+    text: String,
+    emoji: Option<String>,
+    expiration_minutes: Option<i64>,
+) -> Result<(), String> {
+    let client = SlackClient::from_stored_token(&app)?;
+    let expiration = status_expiration(expiration_minutes, chrono::Utc::now().timestamp());
+    client
+        .set_status(text.trim(), emoji.as_deref().unwrap_or(""), expiration)
+        .await
+}
+
+#[tauri::command]
+pub async fn slack_clear_status(app: AppHandle) -> Result<(), String> {
+    SlackClient::from_stored_token(&app)?
+        .set_status("", "", 0)
+        .await
+}
+
+/// `channel` is `#name`, a conversation id or a user id for a DM
+#[tauri::command]
+pub async fn slack_send_message(
+    app: AppHandle,
+    channel: String,
+    text: String,
+) -> Result<SentMessage, String> {
+    if text.trim().is_empty() {
+        return Err("Message is empty".to_string());
+    }
+    SlackClient::from_stored_token(&app)?
+        .post_message(&channel, &text)
+        .await
+}
+
+#[tauri::command]
+pub async fn slack_search_channels(
+    app: AppHandle,
+    query: String,
+) -> Result<Vec<Conversation>, String> {
+    SlackClient::from_stored_token(&app)?
+        .search_channels(&query)
+        .await
+}
+
+#[tauri::command]
+pub async fn slack_list_unread(app: AppHandle) -> Result<Vec<Conversation>, String> {
+    SlackClient::from_stored_token(&app)?
+        .unread_conversations()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn channel(id: &str, name: &str) -> RawConversation {
+        serde_json::from_value(json!({ "id": id, "name": name, "is_channel": true })).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_emoji_and_expiration() {
+        assert_eq!(normalize_emoji("coffee"), ":coffee:");
+        assert_eq!(normalize_emoji(":coffee:"), ":coffee:");
+        assert_eq!(normalize_emoji(" "), "");
+        assert_eq!(status_expiration(Some(30), 1_000), 2_800);
+        assert_eq!(status_expiration(Some(0), 1_000), 0);
+        assert_eq!(status_expiration(None, 1_000), 0);
+    }
+
+    #[test]
+    fn test_rank_channels() {
+        let channels = vec![
+            channel("C1", "team-design"),
+            channel("C2", "design"),
+            channel("C3", "random"),
+        ];
+        let ranked: Vec<&str> = rank_channels("#Design", &channels)
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect();
+        assert_eq!(ranked, vec!["C2", "C1"]);
+    }
+
+    #[test]
+    fn test_parse_response_error() {
+        let error = parse_response::<AuthTest>(
+            "auth.test",
+            json!({ "ok": false, "error": "invalid_auth" }),
+        )
+        .unwrap_err();
+        assert_eq!(error, "Slack API error (auth.test): invalid_auth");
+
+        let dm: RawConversation =
+            serde_json::from_value(json!({ "id": "D1", "is_im": true, "user": "U1" })).unwrap();
+        assert_eq!(conversation_kind(&dm), ConversationKind::DirectMessage);
+        assert_eq!(
+            to_conversation(&dm, "Ann".to_string(), "T1").url,
+            "slack://channel?team=T1&id=D1"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+/// Every Web API response carries `ok` and, on failure, an error code
+#[derive(Debug, Deserialize)]
+pub struct Envelope {
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuthTest {
+    pub user_id: String,
+    pub user: String,
+    pub team: String,
+    pub team_id: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ResponseMetadata {
+    #[serde(default)]
+    pub next_cursor: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawConversation {
+    pub id: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub is_group: bool,
+    #[serde(default)]
+    pub is_im: bool,
+    #[serde(default)]
+    pub is_mpim: bool,
+    #[serde(default)]
+    pub is_private: bool,
+    /// Other member of a DM
+    pub user: Option<String>,
+    pub last_read: Option<String>,
+    pub unread_count_display: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationsPage {
+    #[serde(default)]
+    pub channels: Vec<RawConversation>,
+    #[serde(default)]
+    pub response_metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationInfo {
+    pub channel: RawConversation,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct History {
+    #[serde(default)]
+    pub messages: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostedMessage {
+    pub channel: String,
+    pub ts: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpenedConversation {
+    pub channel: RawConversation,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserProfile {
+    pub display_name: Option<String>,
+    pub real_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawUser {
+    pub name: String,
+    pub profile: Option<UserProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserInfo {
+    pub user: RawUser,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConversationKind {
+    Channel,
+    PrivateChannel,
+    DirectMessage,
+    GroupMessage,
+}
+
+/// A channel or DM as shown in the launcher
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversation {
+    pub id: String,
+    /// Channel name, or the other person's name for DMs
+    pub name: String,
+    pub kind: ConversationKind,
+    pub unread_count: Option<u32>,
+    /// `slack://` link that opens the conversation in the desktop app
+    pub url: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SentMessage {
+    pub channel: String,
+    pub ts: String,
+}
@@ -0,0 +1,146 @@
+use super::{Task, TaskDue, TaskSource};
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use tauri::AppHandle;
+
+const TASKS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS tasks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    content TEXT NOT NULL,
+    description TEXT,
+    due_date TEXT,
+    due_at INTEGER,
+    priority INTEGER NOT NULL DEFAULT 1,
+    completed_at INTEGER,
+    created_at INTEGER NOT NULL
+)";
+
+impl Storable for Task {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let id: i64 = row.get(0)?;
+        let due_date: Option<String> = row.get(3)?;
+        let due_at: Option<i64> = row.get(4)?;
+        let completed_at: Option<i64> = row.get(6)?;
+        Ok(Task {
+            id: id.to_string(),
+            content: row.get(1)?,
+            description: row.get(2)?,
+            due: due_date.map(|date| TaskDue {
+                date,
+                datetime: due_at
+                    .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
+                    .map(|dt| dt.to_rfc3339()),
+                label: None,
+            }),
+            priority: row.get(5)?,
+            completed: completed_at.is_some(),
+            url: None,
+            source: TaskSource::Local,
+        })
+    }
+}
+
+/// Task list kept on this machine, used when no Todoist account is connected
+pub struct LocalTaskManager {
+    store: Store,
+}
+
+impl LocalTaskManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "tasks.sqlite")?;
+        store.init_table(TASKS_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(TASKS_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    /// `due` is `(YYYY-MM-DD, timestamp)`, the timestamp only when a time of
+    /// day was given
+    pub fn add(
+        &self,
+        content: &str,
+        description: Option<&str>,
+        due: Option<(String, Option<i64>)>,
+        priority: u8,
+    ) -> Result<Task, AppError> {
+        let (due_date, due_at) = due.unzip();
+        self.store.execute(
+            "INSERT INTO tasks (content, description, due_date, due_at, priority, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                content,
+                description,
+                due_date,
+                due_at.flatten(),
+                priority,
+                Utc::now().timestamp()
+            ],
+        )?;
+        let id = self.store.last_insert_rowid();
+        self.store
+            .query_row(
+                "SELECT id, content, description, due_date, due_at, priority, completed_at
+                 FROM tasks WHERE id = ?",
+                params![id],
+            )?
+            .ok_or(AppError::Rusqlite(rusqlite::Error::QueryReturnedNoRows))
+    }
+
+    /// Open tasks due on or before `today` (YYYY-MM-DD)
+    pub fn list_today(&self, today: &str) -> Result<Vec<Task>, AppError> {
+        self.store.query(
+            "SELECT id, content, description, due_date, due_at, priority, completed_at
+             FROM tasks
+             WHERE completed_at IS NULL AND due_date IS NOT NULL AND due_date <= ?
+             ORDER BY due_date ASC, due_at IS NULL, due_at ASC, priority DESC",
+            params![today],
+        )
+    }
+
+    pub fn complete(&self, id: i64) -> Result<(), AppError> {
+        self.store.execute(
+            "UPDATE tasks SET completed_at = ? WHERE id = ?",
+            params![Utc::now().timestamp(), id],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_today_and_complete() {
+        let manager = LocalTaskManager::new_for_test().unwrap();
+        let overdue = manager
+            .add("overdue", None, Some(("2025-01-14".to_string(), None)), 1)
+            .unwrap();
+        manager
+            .add(
+                "today",
+                Some("notes"),
+                Some(("2025-01-15".to_string(), Some(0))),
+                4,
+            )
+            .unwrap();
+        manager
+            .add("later", None, Some(("2025-01-20".to_string(), None)), 1)
+            .unwrap();
+        manager.add("someday", None, None, 1).unwrap();
+
+        let today = manager.list_today("2025-01-15").unwrap();
+        let names: Vec<&str> = today.iter().map(|t| t.content.as_str()).collect();
+        assert_eq!(names, vec!["overdue", "today"]);
+        assert!(today[1].due.as_ref().unwrap().datetime.is_some());
+
+        manager.complete(overdue.id.parse().unwrap()).unwrap();
+        assert_eq!(manager.list_today("2025-01-15").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,172 @@
+//! Quick-add tasks. With a Todoist API token in the secrets vault tasks go to
+//! Todoist; without one they are kept in a local SQLite list, so the same
+//! commands work either way.
+
+pub mod local;
+pub mod todoist;
+
+pub use local::LocalTaskManager;
+pub use todoist::TodoistClient;
+
+use crate::reminders::{self, parse_natural_time};
+use crate::secrets;
+use chrono::Local;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+const TOKEN_KEY: &str = "api_token";
+const DEFAULT_PRIORITY: u8 = 1;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskSource {
+    Todoist,
+    Local,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDue {
+    /// YYYY-MM-DD
+    pub date: String,
+    /// RFC 3339, only when the task is due at a specific time
+    pub datetime: Option<String>,
+    /// Human readable form, e.g. "tomorrow at 12" (Todoist only)
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    pub id: String,
+    pub content: String,
+    pub description: Option<String>,
+    pub due: Option<TaskDue>,
+    /// 1 (normal) to 4 (urgent), as in the Todoist API
+    pub priority: u8,
+    pub completed: bool,
+    pub url: Option<String>,
+    pub source: TaskSource,
+}
+
+/// Content and due date of a quick-add text like "pay rent friday"
+struct QuickAdd {
+    content: String,
+    date: Option<String>,
+    datetime: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+fn parse_quick_add(text: &str) -> Result<QuickAdd, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Task is empty".to_string());
+    }
+    // Text without a recognisable date is a task without a due date
+    let Ok(parsed) = parse_natural_time(text, Local::now().naive_local()) else {
+        return Ok(QuickAdd {
+            content: text.to_string(),
+            date: None,
+            datetime: None,
+        });
+    };
+    let datetime = if parsed.has_time {
+        Some(reminders::to_utc(parsed.due)?)
+    } else {
+        None
+    };
+    Ok(QuickAdd {
+        content: parsed.message,
+        date: Some(parsed.due.date().format("%Y-%m-%d").to_string()),
+        datetime,
+    })
+}
+
+fn todoist_client() -> Result<Option<TodoistClient>, String> {
+    let token = secrets::get(secrets::TODOIST, TOKEN_KEY)
+        .map_err(|e| format!("Failed to retrieve Todoist token: {}", e))?;
+    Ok(token.map(TodoistClient::new))
+}
+
+/// "todoist" or "local", whichever backend the task commands will use
+#[tauri::command]
+pub fn tasks_backend() -> Result<TaskSource, String> {
+    Ok(match todoist_client()? {
+        Some(_) => TaskSource::Todoist,
+        None => TaskSource::Local,
+    })
+}
+
+/// Verifies the token against the Todoist API before storing it
+#[tauri::command]
+pub async fn tasks_connect_todoist(token: String) -> Result<(), String> {
+    let token = token.trim().to_string();
+    TodoistClient::new(token.clone()).verify().await?;
+    secrets::set(secrets::TODOIST, TOKEN_KEY, &token)
+        .map_err(|e| format!("Failed to store Todoist token: {}", e))
+}
+
+#[tauri::command]
+pub fn tasks_disconnect_todoist() -> Result<(), String> {
+    secrets::delete(secrets::TODOIST, TOKEN_KEY)
+        .map_err(|e| format!("Failed to delete Todoist token: {}", e))
+}
+
+/// Adds a task from quick-add text; a date in the text ("tomorrow at 5pm")
+/// becomes the due date
+#[tauri::command]
+pub async fn tasks_add(
+    app: AppHandle,
+    text: String,
+    description: Option<String>,
+    priority: Option<u8>,
+) -> Result<Task, String> {
+    let quick_add = parse_quick_add(&text)?;
+    let priority = priority.unwrap_or(DEFAULT_PRIORITY).clamp(1, 4);
+    let description = description.as_deref().filter(|d| !d.trim().is_empty());
+
+    match todoist_client()? {
+        Some(client) => {
+            let due = quick_add
+                .date
+                .map(|date| (date, quick_add.datetime.map(|dt| dt.to_rfc3339())));
+            client
+                .add_task(&quick_add.content, description, due, priority)
+                .await
+        }
+        None => {
+            let due = quick_add
+                .date
+                .map(|date| (date, quick_add.datetime.map(|dt| dt.timestamp())));
+            app.state::<LocalTaskManager>()
+                .add(&quick_add.content, description, due, priority)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Open tasks due today, overdue ones included
+#[tauri::command]
+pub async fn tasks_list_today(app: AppHandle) -> Result<Vec<Task>, String> {
+    match todoist_client()? {
+        Some(client) => client.list_today().await,
+        None => {
+            let today = Local::now().date_naive().format("%Y-%m-%d").to_string();
+            app.state::<LocalTaskManager>()
+                .list_today(&today)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn tasks_complete(app: AppHandle, id: String) -> Result<(), String> {
+    match todoist_client()? {
+        Some(client) => client.complete_task(&id).await,
+        None => {
+            let id: i64 = id.parse().map_err(|_| format!("Invalid task id: {}", id))?;
+            app.state::<LocalTaskManager>()
+                .complete(id)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use super::{Task, TaskDue, TaskSource};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+const TODOIST_API_BASE: &str = "https://api.todoist.com/rest/v2";
+
+#[derive(Debug, Deserialize)]
+struct TodoistDue {
+    date: String,
+    datetime: Option<String>,
+    string: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TodoistTask {
+    id: String,
+    content: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    is_completed: bool,
+    priority: u8,
+    due: Option<TodoistDue>,
+    url: Option<String>,
+}
+
+impl From<TodoistTask> for Task {
+    fn from(task: TodoistTask) -> Self {
+        Task {
+            id: task.id,
+            content: task.content,
+            description: Some(task.description).filter(|d| !d.is_empty()),
+            due: task.due.map(|due| TaskDue {
+                date: due.date,
+                datetime: due.datetime,
+                label: due.string,
+            }),
+            priority: task.priority,
+            completed: task.is_completed,
+            url: task.url,
+            source: TaskSource::Todoist,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct NewTodoistTask<'a> {
+    content: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due_date: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due_datetime: Option<&'a str>,
+    priority: u8,
+}
+
+pub struct TodoistClient {
+    token: String,
+    http_client: Client,
+}
+
+impl TodoistClient {
+    pub fn new(token: String) -> Self {
+        Self {
+            token,
+            http_client: Client::new(),
+        }
+    }
+
+    fn build_request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", TODOIST_API_BASE, path);
+        self.http_client
+            .request(method, &url)
+            .bearer_auth(&self.token)
+            .header("User-Agent", "Flareup")
+    }
+
+    /// Checks the token by listing projects
+    pub async fn verify(&self) -> Result<(), String> {
+        let response = self
+            .build_request(reqwest::Method::GET, "/projects")
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach Todoist: {}", e))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+                Err("Todoist rejected the API token".to_string())
+            }
+            status => Err(format!("Todoist API error: {}", status)),
+        }
+    }
+
+    /// Adds a task; `due` is `(date, RFC 3339 datetime)`, the datetime only
+    /// when a time of day was given
+    pub async fn add_task(
+        &self,
+        content: &str,
+        description: Option<&str>,
+        due: Option<(String, Option<String>)>,
+        priority: u8,
+    ) -> Result<Task, String> {
+        let (due_date, due_datetime) = match &due {
+            Some((_, Some(datetime))) => (None, Some(datetime.as_str())),
+            Some((date, None)) => (Some(date.as_str()), None),
+            None => (None, None),
+        };
+        let body = NewTodoistTask {
+            content,
+            description,
+            due_date,
+            due_datetime,
+            priority,
+        };
+
+        let response = self
+            .build_request(reqwest::Method::POST, "/tasks")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to add task: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Todoist API error: {}", response.status()));
+        }
+
+        response
+            .json::<TodoistTask>()
+            .await
+            .map(Task::from)
+            .map_err(|e| format!("Failed to parse task response: {}", e))
+    }
+
+    /// Open tasks due today, plus overdue ones
+    pub async fn list_today(&self) -> Result<Vec<Task>, String> {
+        let response = self
+            .build_request(reqwest::Method::GET, "/tasks")
+            .query(&[("filter", "today | overdue")])
+            .send()
+            .await
+            .map_err(|e| format!("Failed to list tasks: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Todoist API error: {}", response.status()));
+        }
+
+        response
+            .json::<Vec<TodoistTask>>()
+            .await
+            .map(|tasks| tasks.into_iter().map(Task::from).collect())
+            .map_err(|e| format!("Failed to parse tasks response: {}", e))
+    }
+
+    pub async fn complete_task(&self, id: &str) -> Result<(), String> {
+        let path = format!("/tasks/{}/close", urlencoding::encode(id));
+        let response = self
+            .build_request(reqwest::Method::POST, &path)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to complete task: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Todoist API error: {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_todoist_task() {
+        let json = r#"{
+            "id": "2995104339",
+            "content": "Buy milk",
+            "description": "",
+            "is_completed": false,
+            "priority": 4,
+            "due": {
+                "date": "2025-01-16",
+                "is_recurring": false,
+                "datetime": "2025-01-16T12:00:00Z",
+                "string": "tomorrow at 12"
+            },
+            "url": "https://todoist.com/showTask?id=2995104339"
+        }"#;
+        let task: Task = serde_json::from_str::<TodoistTask>(json).unwrap().into();
+        assert_eq!(task.id, "2995104339");
+        assert_eq!(task.description, None);
+        assert_eq!(task.priority, 4);
+        let due = task.due.unwrap();
+        assert_eq!(due.date, "2025-01-16");
+        assert_eq!(due.label.as_deref(), Some("tomorrow at 12"));
+        assert_eq!(task.source, TaskSource::Todoist);
+    }
+}
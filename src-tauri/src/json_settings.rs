@@ -0,0 +1,74 @@
+//! Settings kept as one pretty-printed JSON file in the app's local data
+//! directory. A missing or empty file reads as the defaults.
+
+use crate::error::AppError;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+pub trait JsonSettings: Serialize + DeserializeOwned + Default {
+    /// File name within the app local data directory
+    const FILE_NAME: &'static str;
+
+    fn path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join(Self::FILE_NAME))
+    }
+
+    fn load(app: &AppHandle) -> Result<Self, AppError> {
+        load_from(&Self::path(app)?)
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        save_to(&Self::path(app)?, self)
+    }
+}
+
+pub fn load_from<T: DeserializeOwned + Default>(path: &Path) -> Result<T, AppError> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let content = fs::read_to_string(path)?;
+    if content.trim().is_empty() {
+        return Ok(T::default());
+    }
+    serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+}
+
+pub fn save_to<T: Serialize>(path: &Path, value: &T) -> Result<(), AppError> {
+    let content =
+        serde_json::to_string_pretty(value).map_err(|e| AppError::Serialization(e.to_string()))?;
+    fs::write(path, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_round_trip_and_defaults() {
+        let path =
+            std::env::temp_dir().join(format!("flare-settings-{}.json", rand::random::<u32>()));
+        let missing: BTreeMap<String, bool> = load_from(&path).unwrap();
+        assert!(missing.is_empty());
+
+        fs::write(&path, "  \n").unwrap();
+        let empty: BTreeMap<String, bool> = load_from(&path).unwrap();
+        assert!(empty.is_empty());
+
+        let value = BTreeMap::from([("a".to_string(), true)]);
+        save_to(&path, &value).unwrap();
+        assert_eq!(load_from::<BTreeMap<String, bool>>(&path).unwrap(), value);
+
+        fs::write(&path, "{").unwrap();
+        assert!(load_from::<BTreeMap<String, bool>>(&path).is_err());
+        let _ = fs::remove_file(path);
+    }
+}
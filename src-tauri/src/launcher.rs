@@ -0,0 +1,375 @@
+//! Starts desktop entries following the freedesktop Desktop Entry spec: Exec
+//! quoting, field codes, `Terminal=true`, startup hints, and `gtk-launch` /
+//! `gio launch` as a fallback when the command can't be spawned directly.
+
+use crate::app::{App, LaunchOptions};
+use std::{
+    env,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Terminal emulators tried when `$TERMINAL` is unset, with the arguments
+/// that precede the command to run
+const TERMINALS: &[(&str, &[&str])] = &[
+    ("x-terminal-emulator", &["-e"]),
+    ("kitty", &[]),
+    ("alacritty", &["-e"]),
+    ("foot", &[]),
+    ("wezterm", &["start", "--"]),
+    ("ghostty", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("tilix", &["-e"]),
+    ("xterm", &["-e"]),
+];
+
+/// Splits an Exec value into arguments, honouring double quotes and the
+/// escapes the spec allows inside them
+pub fn tokenize_exec(exec: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_token = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' => in_quotes = false,
+                '\\' => match chars.next() {
+                    Some(next @ ('"' | '`' | '$' | '\\')) => current.push(next),
+                    Some(next) => {
+                        current.push('\\');
+                        current.push(next);
+                    }
+                    None => return Err("Exec ends with a dangling escape".to_string()),
+                },
+                _ => current.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_quotes = true;
+                has_token = true;
+            }
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+                has_token = true;
+            }
+            c if c.is_whitespace() => {
+                if has_token {
+                    args.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            _ => {
+                current.push(c);
+                has_token = true;
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err("Unterminated quote in Exec".to_string());
+    }
+    if has_token {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+/// `file:///tmp/a%20b` -> `/tmp/a b`; other values are passed through
+fn to_local_path(file: &str) -> String {
+    match file.strip_prefix("file://") {
+        Some(path) => urlencoding::decode(path)
+            .map(|p| p.into_owned())
+            .unwrap_or_else(|_| path.to_string()),
+        None => file.to_string(),
+    }
+}
+
+struct FieldContext<'a> {
+    files: &'a [String],
+    name: &'a str,
+    icon: Option<&'a str>,
+    desktop_file: Option<&'a str>,
+}
+
+fn expand_inline(arg: &str, ctx: &FieldContext) -> String {
+    let mut expanded = String::new();
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => expanded.push('%'),
+            Some('c') => expanded.push_str(ctx.name),
+            Some('k') => expanded.push_str(ctx.desktop_file.unwrap_or_default()),
+            Some('f') => {
+                if let Some(file) = ctx.files.first() {
+                    expanded.push_str(&to_local_path(file));
+                }
+            }
+            Some('u') => {
+                if let Some(file) = ctx.files.first() {
+                    expanded.push_str(file);
+                }
+            }
+            // Deprecated and list codes make no sense inside a larger argument
+            _ => {}
+        }
+    }
+    expanded
+}
+
+/// Replaces field codes; `%f`/`%u` take the first file and are dropped when
+/// there is none, `%F`/`%U` expand to every file
+fn expand_field_codes(args: Vec<String>, ctx: &FieldContext) -> Vec<String> {
+    let mut expanded = Vec::with_capacity(args.len());
+    for arg in args {
+        match arg.as_str() {
+            "%f" => expanded.extend(ctx.files.first().map(|f| to_local_path(f))),
+            "%F" => expanded.extend(ctx.files.iter().map(|f| to_local_path(f))),
+            "%u" => expanded.extend(ctx.files.first().cloned()),
+            "%U" => expanded.extend(ctx.files.iter().cloned()),
+            "%i" => {
+                if let Some(icon) = ctx.icon {
+                    expanded.push("--icon".to_string());
+                    expanded.push(icon.to_string());
+                }
+            }
+            "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => {}
+            _ => {
+                let value = expand_inline(&arg, ctx);
+                if !value.is_empty() || !arg.contains('%') {
+                    expanded.push(value);
+                }
+            }
+        }
+    }
+    expanded
+}
+
+pub(crate) fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(program);
+    if path.is_absolute() {
+        return path.is_file().then_some(path);
+    }
+    env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+fn wrap_in_terminal(args: Vec<String>) -> Result<Vec<String>, String> {
+    if let Ok(terminal) = env::var("TERMINAL") {
+        if !terminal.is_empty() && find_in_path(&terminal).is_some() {
+            return Ok([terminal, "-e".to_string()]
+                .into_iter()
+                .chain(args)
+                .collect());
+        }
+    }
+    let (terminal, prefix) = TERMINALS
+        .iter()
+        .find(|(terminal, _)| find_in_path(terminal).is_some())
+        .ok_or("No terminal emulator found for Terminal=true app")?;
+
+    Ok(std::iter::once(terminal.to_string())
+        .chain(prefix.iter().map(|arg| arg.to_string()))
+        .chain(args)
+        .collect())
+}
+
+/// X11 startup notification id; Wayland activation tokens have to come from
+/// the compositor, so none is set there
+fn startup_id(name: &str) -> Option<String> {
+    if env::var("WAYLAND_DISPLAY").is_ok() || env::var("DISPLAY").is_err() {
+        return None;
+    }
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let name: String = name.chars().filter(|c| c.is_alphanumeric()).collect();
+    Some(format!(
+        "flare-{}-{}_TIME{}",
+        std::process::id(),
+        name,
+        millis
+    ))
+}
+
+fn spawn(args: &[String], options: &LaunchOptions, name: &str) -> std::io::Result<()> {
+    let (program, rest) = args
+        .split_first()
+        .ok_or_else(|| std::io::Error::other("Empty exec command"))?;
+
+    let mut command = Command::new(program);
+    command
+        .args(rest)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    if let Some(dir) = options.working_dir.as_deref() {
+        command.current_dir(dir);
+    }
+    if let Some(desktop_file) = options.desktop_file.as_deref() {
+        // Lets docks and shells match the new window to its desktop entry
+        command.env("GIO_LAUNCHED_DESKTOP_FILE", desktop_file);
+        command.env("BAMF_DESKTOP_FILE_HINT", desktop_file);
+    }
+    if options.startup_notify {
+        if let Some(id) = startup_id(name) {
+            command.env("DESKTOP_STARTUP_ID", id);
+        }
+    }
+
+    let mut child = command.spawn()?;
+    // Reap the child so finished apps don't linger as zombies
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+/// Hands the entry to the desktop's own launcher
+fn launch_with_desktop_tools(app: &App, files: &[String]) -> Result<(), String> {
+    if let Some(id) = app.id.as_deref().filter(|id| id.ends_with(".desktop")) {
+        if find_in_path("gtk-launch").is_some() {
+            let args: Vec<String> = ["gtk-launch".to_string(), id.to_string()]
+                .into_iter()
+                .chain(files.iter().cloned())
+                .collect();
+            if spawn(&args, &LaunchOptions::default(), &app.name).is_ok() {
+                return Ok(());
+            }
+        }
+    }
+    if let Some(desktop_file) = app.launch.desktop_file.as_deref() {
+        if find_in_path("gio").is_some() {
+            let args: Vec<String> = ["gio", "launch", desktop_file]
+                .into_iter()
+                .map(String::from)
+                .chain(files.iter().cloned())
+                .collect();
+            if spawn(&args, &LaunchOptions::default(), &app.name).is_ok() {
+                return Ok(());
+            }
+        }
+    }
+    Err(format!("Failed to launch {}", app.name))
+}
+
+/// Launches `exec` (the app's own Exec or one of its actions) with optional
+/// file or URL arguments
+pub fn launch(app: &App, exec: &str, files: &[String]) -> Result<(), String> {
+    let ctx = FieldContext {
+        files,
+        name: &app.name,
+        icon: app.icon_path.as_deref(),
+        desktop_file: app.launch.desktop_file.as_deref(),
+    };
+
+    let args = match tokenize_exec(exec) {
+        Ok(args) => expand_field_codes(args, &ctx),
+        Err(e) => {
+            tracing::warn!(error = %e, app = %app.name, "Invalid Exec, using desktop launcher");
+            return launch_with_desktop_tools(app, files);
+        }
+    };
+    if args.is_empty() {
+        return Err("Empty exec command".to_string());
+    }
+    let args = if app.launch.terminal {
+        wrap_in_terminal(args)?
+    } else {
+        args
+    };
+
+    match spawn(&args, &app.launch, &app.name) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            tracing::warn!(
+                error = %e,
+                app = %app.name,
+                "Direct launch failed, using desktop launcher"
+            );
+            launch_with_desktop_tools(app, files)
+                .map_err(|_| format!("Failed to launch app: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx<'a>(files: &'a [String]) -> FieldContext<'a> {
+        FieldContext {
+            files,
+            name: "Editor",
+            icon: Some("editor"),
+            desktop_file: Some("/usr/share/applications/editor.desktop"),
+        }
+    }
+
+    #[test]
+    fn test_tokenize_exec() {
+        assert_eq!(
+            tokenize_exec(r#"app --flag "two words" "say \"hi\"" %U"#).unwrap(),
+            vec!["app", "--flag", "two words", "say \"hi\"", "%U"]
+        );
+        assert_eq!(tokenize_exec(r#""" x"#).unwrap(), vec!["", "x"]);
+        assert_eq!(
+            tokenize_exec(r#""/opt/My App/run" --x"#).unwrap(),
+            vec!["/opt/My App/run", "--x"]
+        );
+        assert!(tokenize_exec(r#"app "unterminated"#).is_err());
+    }
+
+    #[test]
+    fn test_expand_field_codes_without_files() {
+        let args = tokenize_exec("editor %F --name=%c %i %% %U").unwrap();
+        assert_eq!(
+            expand_field_codes(args, &ctx(&[])),
+            vec!["editor", "--name=Editor", "--icon", "editor", "%"]
+        );
+    }
+
+    #[test]
+    fn test_expand_field_codes_with_files() {
+        let files = vec![
+            "file:///tmp/a%20b.txt".to_string(),
+            "https://example.com".to_string(),
+        ];
+        let args = tokenize_exec("editor %f --open=%u %k").unwrap();
+        assert_eq!(
+            expand_field_codes(args, &ctx(&files)),
+            vec![
+                "editor",
+                "/tmp/a b.txt",
+                "--open=file:///tmp/a%20b.txt",
+                "/usr/share/applications/editor.desktop"
+            ]
+        );
+
+        let args = tokenize_exec("editor %F").unwrap();
+        assert_eq!(
+            expand_field_codes(args, &ctx(&files)),
+            vec!["editor", "/tmp/a b.txt", "https://example.com"]
+        );
+    }
+}
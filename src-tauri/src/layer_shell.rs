@@ -0,0 +1,66 @@
+//! Turns the launcher into a layer-shell overlay on compositors that support
+//! wlr-layer-shell (Sway, Hyprland, river, ...), the way wofi and fuzzel
+//! run. An overlay sits above every toplevel and gets the keyboard as soon
+//! as it's mapped, so none of the toplevel focus and stacking rules apply.
+//!
+//! GNOME has no layer-shell, and setting `FLARE_NO_LAYER_SHELL` keeps a
+//! normal toplevel everywhere else.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the main window is a layer surface
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/// Converts the main window. Has to run on the main thread during setup.
+#[cfg(target_os = "linux")]
+pub fn init(app: &AppHandle) {
+    use gtk::prelude::{GtkWindowExt, WidgetExt};
+    use gtk_layer_shell::{KeyboardMode, Layer, LayerShell};
+
+    if std::env::var("WAYLAND_DISPLAY").is_err()
+        || std::env::var_os("FLARE_NO_LAYER_SHELL").is_some()
+    {
+        return;
+    }
+    if !gtk_layer_shell::is_supported() {
+        tracing::debug!("Compositor has no layer-shell, keeping a normal window");
+        return;
+    }
+    let Some(window) = app.get_webview_window("main") else {
+        tracing::error!("Main window not found");
+        return;
+    };
+    let gtk_window = match window.gtk_window() {
+        Ok(gtk_window) => gtk_window,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to get the main GTK window");
+            return;
+        }
+    };
+
+    let visible = gtk_window.is_visible();
+    // The layer surface role has to be set before GTK creates the surface
+    if gtk_window.is_realized() {
+        gtk_window.hide();
+        gtk_window.unrealize();
+    }
+    gtk_window.init_layer_shell();
+    gtk_window.set_namespace("flare");
+    gtk_window.set_layer(Layer::Overlay);
+    // No anchors, so the compositor centres it on the focused output
+    gtk_window.set_keyboard_mode(KeyboardMode::Exclusive);
+    ACTIVE.store(true, Ordering::SeqCst);
+    tracing::info!("Launcher window is a layer-shell overlay");
+
+    if visible {
+        gtk_window.present();
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn init(_app: &AppHandle) {}
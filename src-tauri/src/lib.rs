@@ -1,41 +1,118 @@
+mod activation;
 mod ai;
+mod aliases;
+mod annotate;
 mod app;
+mod app_watcher;
+mod appimage;
+mod autostart;
+mod bench;
 mod browser_extension;
 mod cache;
+mod checksum;
+mod cli;
 mod cli_substitutes;
 mod clipboard;
 pub mod clipboard_history;
+mod command_registry;
+mod compositor;
+mod confirm;
+mod context;
+mod currencies;
+mod data_tools;
+mod dbus;
 mod desktop;
+mod diagnostics;
+mod dictionary;
+mod docs;
+mod drag;
 mod error;
+mod exec;
 mod extension_shims;
 mod extensions;
 mod file_search;
 mod filesystem;
+mod finance;
 mod frecency;
+mod hotkey_manager;
+mod http_requests;
+mod hud;
+mod instant_answers;
 mod integrations;
+mod json_settings;
+mod launcher;
+mod layer_shell;
+mod logs;
+mod maintenance;
+mod matcher;
+mod network_info;
+mod notifications;
 mod oauth;
+mod ocr;
+mod open_with;
+mod paste;
+mod power_awareness;
+mod process;
+mod profiles;
 mod quick_toggles;
 mod quicklinks;
+mod reminders;
+mod secrets;
+mod selection_actions;
+mod session;
+mod sharing;
+mod shim_registry;
+mod shred;
 mod snippets;
 mod soulver;
 mod store;
+mod streaming;
 mod system;
 mod system_monitors;
+mod text_actions;
+mod timers;
+mod translate;
+mod undo;
+mod unfurl;
+mod wallpaper;
+mod weather;
+mod web_search;
+mod window_management;
+mod window_prefs;
 
 use crate::snippets::input_manager::{EvdevInputManager, InputManager, RdevInputManager};
 use crate::{app::App, cache::AppCache};
+use ai::cache::AiCacheManager;
+use ai::commands::AiCommandManager;
+use ai::embeddings::EmbeddingManager;
+use ai::presets::AiPresetManager;
 use ai::AiUsageManager;
+use aliases::AliasManager;
 use browser_extension::WsState;
+use command_registry::CommandRegistry;
+use docs::DocsManager;
+use extensions::catalog::CatalogManager;
+use extensions::storage::ExtensionStorageManager;
+use finance::FinanceManager;
 use frecency::FrecencyManager;
+use http_requests::HttpRequestManager;
+use instant_answers::InstantAnswerService;
+use integrations::tasks::LocalTaskManager;
+use profiles::ProfileManager;
 use quicklinks::QuicklinkManager;
 use selection::get_text;
+use session::SessionManager;
 use snippets::engine::ExpansionEngine;
 use snippets::manager::SnippetManager;
-use std::process::Command;
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use streaming::StreamRegistry;
 use tauri::{Emitter, Manager};
+use translate::TranslationHistoryManager;
+use unfurl::UnfurlService;
+use web_search::WebSearchManager;
+use window_management::arrangements::WindowArrangementManager;
+use window_management::layouts::SnapLayoutManager;
 
 #[tauri::command]
 fn get_installed_apps(app: tauri::AppHandle) -> Vec<App> {
@@ -48,67 +125,47 @@ fn get_installed_apps(app: tauri::AppHandle) -> Vec<App> {
     }
 }
 
-#[tauri::command]
-fn launch_app(exec: String) -> Result<(), String> {
-    let exec_parts: Vec<&str> = exec.split_whitespace().collect();
-    if exec_parts.is_empty() {
-        return Err("Empty exec command".to_string());
-    }
-
-    let mut command = Command::new(exec_parts[0]);
-    for arg in &exec_parts[1..] {
-        if !arg.starts_with('%') {
-            command.arg(arg);
-        }
-    }
-
-    command
-        .spawn()
-        .map_err(|e| format!("Failed to launch app: {}", e))?;
-
-    Ok(())
+/// Finds the installed app an exec line belongs to, so its desktop entry
+/// metadata (terminal, working dir, startup hints) applies to the launch
+fn find_app_for_exec(app: &tauri::AppHandle, exec: &str) -> App {
+    AppCache::get_apps(app)
+        .ok()
+        .and_then(|apps| apps.into_iter().find(|a| a.exec.as_deref() == Some(exec)))
+        .unwrap_or_else(|| App::new(exec.to_string()).with_exec(Some(exec.to_string())))
 }
 
 #[tauri::command]
-fn get_selected_text() -> String {
-    get_text()
+fn launch_app(
+    app: tauri::AppHandle,
+    exec: String,
+    files: Option<Vec<String>>,
+) -> Result<(), String> {
+    let installed = find_app_for_exec(&app, &exec);
+    launcher::launch(&installed, &exec, &files.unwrap_or_default())
 }
 
 #[tauri::command]
-async fn show_hud(app: tauri::AppHandle, title: String) -> Result<(), String> {
-    let hud_window = match app.get_webview_window("hud") {
-        Some(window) => window,
-        None => {
-            tauri::WebviewWindowBuilder::new(&app, "hud", tauri::WebviewUrl::App("/hud".into()))
-                .decorations(false)
-                .transparent(true)
-                .always_on_top(true)
-                .skip_taskbar(true)
-                .center()
-                .min_inner_size(300.0, 80.0)
-                .max_inner_size(300.0, 80.0)
-                .inner_size(300.0, 80.0)
-                .build()
-                .map_err(|e| e.to_string())?
-        }
-    };
-
-    let window_clone = hud_window.clone();
-    window_clone.show().map_err(|e| e.to_string())?;
-    window_clone
-        .emit("hud-message", &title)
-        .map_err(|e| e.to_string())?;
-    window_clone
-        .set_ignore_cursor_events(true)
-        .map_err(|e| e.to_string())?;
-    window_clone.set_focus().map_err(|e| e.to_string())?;
-
-    tauri::async_runtime::spawn(async move {
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-        let _ = window_clone.hide();
-    });
+fn launch_app_action(
+    app: tauri::AppHandle,
+    app_id: String,
+    action_id: String,
+) -> Result<(), String> {
+    let apps = AppCache::get_apps(&app).map_err(|e| e.to_string())?;
+    let installed = apps
+        .iter()
+        .find(|a| a.id.as_deref() == Some(app_id.as_str()))
+        .ok_or_else(|| format!("App not found: {}", app_id))?;
+    let action = installed
+        .actions
+        .iter()
+        .find(|a| a.id == action_id)
+        .ok_or_else(|| format!("Action not found: {}", action_id))?;
+    launcher::launch(installed, &action.exec, &[])
+}
 
-    Ok(())
+#[tauri::command]
+fn get_selected_text() -> String {
+    get_text()
 }
 
 #[tauri::command]
@@ -151,70 +208,6 @@ fn get_discovered_plugins(app: tauri::AppHandle) -> Result<Vec<extensions::Plugi
     extensions::discover_plugins(&app)
 }
 
-fn setup_background_refresh(app: tauri::AppHandle) {
-    thread::spawn(move || {
-        thread::sleep(Duration::from_secs(60));
-        loop {
-            AppCache::refresh_background(app.clone());
-            thread::sleep(Duration::from_secs(300));
-        }
-    });
-}
-
-fn setup_global_shortcut(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error>> {
-    use tauri_plugin_global_shortcut::{
-        Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState,
-    };
-
-    let spotlight_shortcut = Shortcut::new(Some(Modifiers::SUPER | Modifiers::ALT), Code::Space);
-
-    // Register the shortcut handler
-    tracing::info!("Registering global shortcut: Super+Alt+Space");
-    app.global_shortcut()
-        .on_shortcut(spotlight_shortcut, move |app, shortcut, event| {
-            tracing::debug!(
-                shortcut = ?shortcut,
-                state = ?event.state(),
-                "Hotkey event received"
-            );
-
-            if event.state() == ShortcutState::Pressed {
-                tracing::debug!("Processing hotkey PRESSED event");
-
-                if let Some(window) = app.get_webview_window("main") {
-                    match window.is_visible() {
-                        Ok(true) => {
-                            tracing::debug!("Window visible, hiding");
-                            let _ = window.hide();
-                        }
-                        Ok(false) => {
-                            tracing::debug!("Window hidden, showing");
-                            let _ = window.show();
-                            // Small delay to ensure window is fully visible before focusing
-                            let window_clone = window.clone();
-                            tauri::async_runtime::spawn(async move {
-                                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-                                let _ = window_clone.set_focus();
-                            });
-                        }
-                        Err(e) => {
-                            tracing::error!(error = %e, "Failed to check window visibility");
-                        }
-                    }
-                } else {
-                    tracing::error!("Main window not found");
-                }
-            } else {
-                tracing::trace!("Ignoring hotkey RELEASED event");
-            }
-        })?;
-
-    app.global_shortcut().register(spotlight_shortcut)?;
-    tracing::info!("Global shortcut registered successfully");
-
-    Ok(())
-}
-
 fn setup_input_listener(app: &tauri::AppHandle) {
     let snippet_manager = app.state::<SnippetManager>().inner().clone();
     let snippet_manager_arc = Arc::new(snippet_manager);
@@ -255,9 +248,36 @@ fn shim_translate_path(path: String) -> String {
     extension_shims::PathShim::translate_path(&path)
 }
 
+/// Runs for the extension the main window has running, whose permission the
+/// script needs
 #[tauri::command]
-fn shim_run_applescript(script: String) -> extension_shims::ShimResult {
-    extension_shims::AppleScriptShim::run_apple_script(&script)
+async fn shim_run_applescript(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    script: String,
+) -> extension_shims::ShimResult {
+    let capability = extension_shims::ShimCapability::of_script(&script);
+    let extension = extension_shims::running_extension();
+    let authorized = match extension_shims::check_caller(&window) {
+        Ok(()) => extension_shims::authorize(&app, extension.as_deref(), capability, &script).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = authorized {
+        return extension_shims::ShimResult {
+            success: false,
+            output: None,
+            error: Some(e),
+        };
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        extension_shims::AppleScriptShim::run_apple_script(&script)
+    })
+    .await
+    .unwrap_or_else(|e| extension_shims::ShimResult {
+        success: false,
+        output: None,
+        error: Some(e.to_string()),
+    })
 }
 
 #[tauri::command]
@@ -291,6 +311,34 @@ fn monitor_get_battery() -> Option<system_monitors::BatteryInfo> {
     system_monitors::get_battery_info()
 }
 
+#[tauri::command]
+fn monitor_get_battery_alerts(
+    app: tauri::AppHandle,
+) -> Result<system_monitors::BatteryAlertSettings, String> {
+    system_monitors::BatteryAlertSettings::load(&app).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn monitor_set_battery_alerts(
+    app: tauri::AppHandle,
+    settings: system_monitors::BatteryAlertSettings,
+) -> Result<(), String> {
+    if settings.thresholds.iter().any(|threshold| *threshold > 100) {
+        return Err("Battery alert thresholds are percentages".to_string());
+    }
+    settings.save(&app).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn monitor_get_gpu() -> Vec<system_monitors::GpuInfo> {
+    system_monitors::get_gpu_info()
+}
+
+#[tauri::command]
+fn monitor_get_history(seconds: Option<u64>) -> Vec<system_monitors::MonitorSample> {
+    system_monitors::get_history(seconds)
+}
+
 // Quick toggle commands
 #[tauri::command]
 async fn toggle_wifi(enable: bool) -> Result<(), String> {
@@ -323,8 +371,8 @@ async fn get_dark_mode_state() -> Result<bool, String> {
 }
 
 #[tauri::command]
-fn set_brightness(percentage: u32) -> Result<(), String> {
-    quick_toggles::set_brightness(percentage)
+async fn set_brightness(percentage: u32) -> Result<(), String> {
+    quick_toggles::set_brightness(percentage).await
 }
 
 #[tauri::command]
@@ -355,11 +403,13 @@ fn github_is_authenticated() -> Result<bool, String> {
 
 #[tauri::command]
 fn github_logout() -> Result<(), String> {
+    integrations::github::clear_response_cache();
     integrations::github::delete_token()
 }
 
 #[tauri::command]
-async fn github_get_current_user() -> Result<integrations::github::User, String> {
+async fn github_get_current_user(
+) -> Result<integrations::github::User, integrations::github::GitHubError> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
     client.get_current_user().await
 }
@@ -370,9 +420,12 @@ async fn github_list_issues(
     owner: String,
     repo: String,
     state: Option<String>,
-) -> Result<Vec<integrations::github::Issue>, String> {
+    limit: Option<usize>,
+) -> Result<Vec<integrations::github::Issue>, integrations::github::GitHubError> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
-    client.list_issues(&owner, &repo, state.as_deref()).await
+    client
+        .list_issues(&owner, &repo, state.as_deref(), limit)
+        .await
 }
 
 #[tauri::command]
@@ -380,7 +433,7 @@ async fn github_get_issue(
     owner: String,
     repo: String,
     number: u64,
-) -> Result<integrations::github::Issue, String> {
+) -> Result<integrations::github::Issue, integrations::github::GitHubError> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
     client.get_issue(&owner, &repo, number).await
 }
@@ -393,7 +446,7 @@ async fn github_create_issue(
     body: Option<String>,
     labels: Option<Vec<String>>,
     assignees: Option<Vec<String>>,
-) -> Result<integrations::github::Issue, String> {
+) -> Result<integrations::github::Issue, integrations::github::GitHubError> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
     client
         .create_issue(&owner, &repo, title, body, labels, assignees)
@@ -410,7 +463,7 @@ async fn github_update_issue(
     state: Option<String>,
     labels: Option<Vec<String>>,
     assignees: Option<Vec<String>>,
-) -> Result<integrations::github::Issue, String> {
+) -> Result<integrations::github::Issue, integrations::github::GitHubError> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
     client
         .update_issue(
@@ -431,7 +484,7 @@ async fn github_close_issue(
     owner: String,
     repo: String,
     number: u64,
-) -> Result<integrations::github::Issue, String> {
+) -> Result<integrations::github::Issue, integrations::github::GitHubError> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
     client.close_issue(&owner, &repo, number).await
 }
@@ -439,66 +492,138 @@ async fn github_close_issue(
 #[tauri::command]
 async fn github_list_my_issues(
     state: Option<String>,
-) -> Result<Vec<integrations::github::Issue>, String> {
+    limit: Option<usize>,
+) -> Result<Vec<integrations::github::Issue>, integrations::github::GitHubError> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
-    client.list_my_issues(state.as_deref()).await
+    client.list_my_issues(state.as_deref(), limit).await
 }
 
 // GitHub Search commands
 #[tauri::command]
 async fn github_search_issues(
     query: String,
-) -> Result<integrations::github::SearchResult<integrations::github::Issue>, String> {
+    limit: Option<usize>,
+) -> Result<
+    integrations::github::SearchResult<integrations::github::Issue>,
+    integrations::github::GitHubError,
+> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
-    client.search_issues(&query).await
+    client.search_issues(&query, limit).await
 }
 
 #[tauri::command]
 async fn github_search_repos(
     query: String,
-) -> Result<integrations::github::SearchResult<integrations::github::Repository>, String> {
+    limit: Option<usize>,
+) -> Result<
+    integrations::github::SearchResult<integrations::github::Repository>,
+    integrations::github::GitHubError,
+> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
-    client.search_repos(&query).await
+    client.search_repos(&query, limit).await
 }
 
 // GitHub Repository commands
 #[tauri::command]
-async fn github_list_repos() -> Result<Vec<integrations::github::Repository>, String> {
+async fn github_list_repos(
+    limit: Option<usize>,
+) -> Result<Vec<integrations::github::Repository>, integrations::github::GitHubError> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
-    client.list_user_repos().await
+    client.list_user_repos(limit).await
 }
 
 #[tauri::command]
 async fn github_get_repo(
     owner: String,
     repo: String,
-) -> Result<integrations::github::Repository, String> {
+) -> Result<integrations::github::Repository, integrations::github::GitHubError> {
     let client = integrations::github::GitHubClient::from_stored_token()?;
     client.get_repo(&owner, &repo).await
 }
 
+// GitHub Actions commands
+#[tauri::command]
+async fn github_list_workflow_runs(
+    owner: String,
+    repo: String,
+    branch: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<integrations::github::WorkflowRun>, integrations::github::GitHubError> {
+    let client = integrations::github::GitHubClient::from_stored_token()?;
+    client
+        .list_workflow_runs(&owner, &repo, branch.as_deref(), limit)
+        .await
+}
+
+#[tauri::command]
+async fn github_rerun_workflow_run(
+    owner: String,
+    repo: String,
+    run_id: u64,
+    failed_only: bool,
+) -> Result<(), integrations::github::GitHubError> {
+    let client = integrations::github::GitHubClient::from_stored_token()?;
+    client
+        .rerun_workflow_run(&owner, &repo, run_id, failed_only)
+        .await
+}
+
+#[tauri::command]
+async fn github_watch_workflow_run(
+    owner: String,
+    repo: String,
+    run_id: u64,
+) -> Result<integrations::github::WorkflowRun, integrations::github::GitHubError> {
+    let client = integrations::github::GitHubClient::from_stored_token()?;
+    integrations::github::actions::watch_workflow_run(client, owner, repo, run_id).await
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Initialize tracing subscriber for structured logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::INFO.into()),
-        )
-        .init();
+    start(false)
+}
 
-    let app = tauri::Builder::default()
+/// Runs the app. With `start_hidden` the launcher stays hidden until the
+/// hotkey first opens it.
+pub fn start(start_hidden: bool) {
+    bench::mark_process_start();
+    logs::init();
+    let bench_options = bench::options_from_args(std::env::args().skip(1));
+    let cli_action = match cli::action_from_args(std::env::args().skip(1)) {
+        Some(Ok(action)) => Some(action),
+        Some(Err(usage)) => {
+            eprintln!("{}", usage);
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    // Only `flare toggle` should show the launcher when it's the first instance
+    let start_hidden = start_hidden
+        || cli_action
+            .as_ref()
+            .is_some_and(|a| a != &cli::CliAction::Toggle);
+
+    let mut builder = tauri::Builder::default()
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_http::init())
-        .manage(WsState::default())
-        .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+        .manage(WsState::default());
+    // A benchmark run must not hand off to (and toggle) a running instance
+    if bench_options.is_none() {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            // A login autostart while we're already running
+            if args.iter().any(|arg| arg == autostart::HIDDEN_FLAG) {
+                return;
+            }
+            if let Some(Ok(action)) = cli::action_from_args(args.iter().skip(1).cloned()) {
+                cli::perform(app, action);
+                return;
+            }
             if args.len() > 1 && args[1].starts_with("raycast://") {
                 if let Some(window) = app.get_webview_window("main") {
                     let _ = window.emit("deep-link", args[1].to_string());
-                    window.show().unwrap();
-                    window.set_focus().unwrap();
+                    activation::present(&window);
                 }
                 return;
             }
@@ -507,11 +632,13 @@ pub fn run() {
                 if let Ok(true) = window.is_visible() {
                     let _ = window.hide();
                 } else {
-                    let _ = window.show();
-                    let _ = window.set_focus();
+                    activation::present(&window);
                 }
             }
-        }))
+        }));
+    }
+
+    let app = builder
         .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_shell::init())
@@ -519,14 +646,60 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             get_installed_apps,
+            cache::get_app_source_settings,
+            cache::set_app_source_settings,
+            window_prefs::get_window_prefs,
+            window_prefs::set_window_prefs,
+            session::save_launcher_session,
+            session::load_launcher_session,
+            session::clear_launcher_session,
+            session::get_session_settings,
+            session::set_session_settings,
             launch_app,
+            launch_app_action,
+            open_with::get_open_with_apps,
+            open_with::open_with,
             get_selected_text,
-            show_hud,
+            selection_actions::get_selection_actions,
+            selection_actions::run_selection_action,
+            hud::show_hud,
+            hud::hud_update,
+            hud::hud_dismiss,
+            hud::hud_action,
+            confirm::request_confirmation,
+            confirm::get_pending_confirmation,
+            confirm::confirmation_respond,
+            undo::undo_last_action,
+            maintenance::app_quit,
+            maintenance::app_restart,
+            maintenance::reload_extensions,
+            maintenance::clear_caches,
             get_discovered_plugins,
             filesystem::get_selected_finder_items,
             extensions::install_extension,
+            extensions::uninstall_extension,
+            cli_substitutes::list_substitution_rules,
+            cli_substitutes::validate_substitution_rules,
+            extensions::catalog::sync_store_catalog,
+            extensions::catalog::store_catalog_status,
+            extensions::catalog::search_store,
+            extensions::catalog::install_store_extension,
+            extensions::dedupe::get_extension_sizes,
+            extensions::tools::list_extension_ai_tools,
+            extensions::tools::ai_extension_tool_result,
+            extensions::api::get_extension_api_report,
+            extensions::storage::extension_storage_get,
+            extensions::storage::extension_storage_all_items,
+            extensions::storage::extension_storage_set,
+            extensions::storage::extension_storage_remove,
+            extensions::storage::extension_storage_clear,
             browser_extension::browser_extension_check_connection,
             browser_extension::browser_extension_request,
+            browser_extension::browser_extension_status,
+            browser_extension::browser_list_connections,
+            browser_extension::browser_extension_broadcast,
+            browser_extension::browser_extension_pairing_token,
+            browser_extension::browser_extension_unpair,
             clipboard::clipboard_read_text,
             clipboard::clipboard_read,
             clipboard::clipboard_copy,
@@ -535,22 +708,104 @@ pub fn run() {
             oauth::oauth_set_tokens,
             oauth::oauth_get_tokens,
             oauth::oauth_remove_tokens,
+            secrets::secrets_set,
+            secrets::secrets_delete,
+            secrets::secrets_list,
+            secrets::secrets_audit_log,
+            secrets::extension_secret_set,
+            secrets::extension_secret_get,
+            secrets::extension_secret_delete,
+            secrets::extension_secret_list,
             clipboard_history::history_get_items,
+            clipboard_history::history_get_items_stream,
             clipboard_history::history_get_item_content,
             clipboard_history::history_delete_item,
             clipboard_history::history_toggle_pin,
             clipboard_history::history_clear_all,
             clipboard_history::history_item_was_copied,
+            clipboard_history::history_paste_as_plain_text,
+            clipboard_history::history_paste_with_formatting,
+            clipboard_history::history_get_item_rich_content,
+            clipboard_history::history_paste_stack_start,
+            clipboard_history::history_paste_stack_next,
+            clipboard_history::history_paste_stack_get,
+            clipboard_history::history_paste_stack_clear,
+            clipboard_history::history_get_ignore_rules,
+            clipboard_history::history_set_ignore_rules,
+            clipboard_history::history_pause,
+            clipboard_history::history_resume,
+            clipboard_history::history_get_pause_state,
+            clipboard_history::sync::history_sync_status,
+            clipboard_history::sync::history_sync_set_settings,
+            clipboard_history::sync::history_sync_set_passphrase,
+            clipboard_history::sync::history_sync_set_backend_secret,
+            clipboard_history::sync::history_sync_now,
             quicklinks::create_quicklink,
             quicklinks::list_quicklinks,
             quicklinks::update_quicklink,
             quicklinks::delete_quicklink,
             quicklinks::execute_quicklink,
+            quicklinks::list_quicklink_groups,
+            quicklinks::list_quicklink_tags,
+            quicklinks::dynamic::list_dynamic_quicklinks,
+            quicklinks::dynamic::create_dynamic_quicklink,
+            quicklinks::dynamic::update_dynamic_quicklink,
+            quicklinks::dynamic::delete_dynamic_quicklink,
+            quicklinks::dynamic::refresh_dynamic_quicklink,
+            web_search::list_search_engines,
+            web_search::create_search_engine,
+            web_search::update_search_engine,
+            web_search::delete_search_engine,
+            web_search::set_default_search_engine,
+            web_search::resolve_web_search,
+            web_search::web_search,
+            web_search::get_search_suggestions,
+            aliases::list_aliases,
+            aliases::create_alias,
+            aliases::update_alias,
+            aliases::delete_alias,
+            aliases::resolve_alias,
+            aliases::record_alias_usage,
+            aliases::run_alias_script,
+            command_registry::list_command_overrides,
+            command_registry::get_command_override,
+            command_registry::set_command_override,
+            command_registry::reset_command_override,
+            command_registry::reorder_pinned_commands,
+            command_registry::pin_item,
+            command_registry::unpin_item,
+            command_registry::get_pinned,
+            command_registry::apply_command_overrides,
+            profiles::list_profiles,
+            profiles::create_profile,
+            profiles::update_profile,
+            profiles::delete_profile,
+            profiles::reorder_profiles,
+            profiles::get_active_profiles,
+            matcher::fuzzy_rank,
+            streaming::cancel_stream,
+            diagnostics::run_doctor,
+            logs::get_recent_logs,
+            logs::get_log_directory,
+            logs::export_debug_bundle,
+            instant_answers::get_instant_answer,
+            unfurl::unfurl_url,
+            unfurl::unfurl_clipboard,
+            unfurl::unfurl_quicklink,
             system::get_applications,
             system::get_default_application,
             system::get_frontmost_application,
             system::show_in_finder,
+            system::system_power,
             system::trash,
+            shred::shred_file,
+            shred::shred_file_warnings,
+            sharing::share_list_devices,
+            sharing::share_send,
+            sharing::receive::share_receive_start,
+            sharing::receive::share_receive_stop,
+            sharing::receive::share_receive_status,
+            sharing::receive::share_receive_respond,
             record_usage,
             get_frecency_data,
             delete_frecency_entry,
@@ -560,10 +815,22 @@ pub fn run() {
             snippets::list_snippets,
             snippets::update_snippet,
             snippets::delete_snippet,
+            snippets::search_snippets,
+            snippets::list_snippet_folders,
+            snippets::list_snippet_tags,
+            snippets::set_snippet_tags,
+            snippets::move_snippets,
+            snippets::delete_snippets,
             snippets::import_snippets,
+            snippets::import_snippets_from_file,
+            snippets::export_snippets_to_file,
             snippets::paste_snippet_content,
             snippets::snippet_was_used,
+            snippets::ime::snippet_input_method_status,
+            snippets::ime::set_snippet_pause_while_composing,
             file_search::search_files,
+            file_search::search_files_stream,
+            drag::start_drag,
             ai::set_ai_api_key,
             ai::is_ai_api_key_set,
             ai::clear_ai_api_key,
@@ -573,14 +840,164 @@ pub fn run() {
             ai::set_ai_settings,
             ai::ai_can_access,
             soulver::calculate_soulver,
+            currencies::get_currency_rates,
+            currencies::get_rate,
+            currencies::convert_currency,
+            currencies::refresh_currency_rates,
             shim_translate_path,
             shim_run_applescript,
             shim_get_system_info,
+            extension_shims::shim_set_running_extension,
+            extension_shims::shim_permission_respond,
+            extension_shims::list_shim_permissions,
+            extension_shims::revoke_shim_permission,
+            process::list_running_processes,
+            shim_registry::list_missing_tools,
+            shim_registry::install_missing_tools,
+            shim_registry::list_installed_shims,
             monitor_get_cpu,
             monitor_get_memory,
             monitor_get_disks,
             monitor_get_network,
             monitor_get_battery,
+            monitor_get_battery_alerts,
+            monitor_set_battery_alerts,
+            monitor_get_gpu,
+            monitor_get_history,
+            network_info::network_get_public_ip,
+            network_info::network_get_info,
+            timers::create_timer,
+            timers::create_stopwatch,
+            timers::create_alarm,
+            timers::list_timers,
+            timers::cancel_timer,
+            timers::pause_timer,
+            timers::resume_timer,
+            reminders::create_reminder,
+            reminders::create_reminder_at,
+            reminders::parse_reminder_text,
+            reminders::list_reminders,
+            reminders::snooze_reminder,
+            reminders::complete_reminder,
+            reminders::delete_reminder,
+            integrations::tasks::tasks_backend,
+            integrations::tasks::tasks_connect_todoist,
+            integrations::tasks::tasks_disconnect_todoist,
+            integrations::tasks::tasks_add,
+            integrations::tasks::tasks_list_today,
+            integrations::tasks::tasks_complete,
+            integrations::notes::get_notes_settings,
+            integrations::notes::set_notes_settings,
+            integrations::notes::notes_append_to_daily,
+            integrations::notes::notes_create_from_selection,
+            integrations::notes::search_notes,
+            integrations::notes::notes_open,
+            integrations::slack::slack_connect,
+            integrations::slack::slack_disconnect,
+            integrations::slack::slack_is_connected,
+            integrations::slack::slack_set_status,
+            integrations::slack::slack_clear_status,
+            integrations::slack::slack_send_message,
+            integrations::slack::slack_search_channels,
+            integrations::slack::slack_list_unread,
+            integrations::email::email_get_settings,
+            integrations::email::email_save_account,
+            integrations::email::email_delete_account,
+            integrations::email::email_test_account,
+            integrations::email::email_unread_counts,
+            integrations::email::email_recent,
+            integrations::email::email_save_templates,
+            integrations::email::email_set_client,
+            integrations::email::email_compose,
+            weather::weather_search_locations,
+            weather::weather_get_settings,
+            weather::weather_set_location,
+            weather::weather_set_units,
+            weather::weather_forecast,
+            weather::weather_current,
+            weather::weather_hourly,
+            weather::weather_daily,
+            wallpaper::set_wallpaper,
+            wallpaper::get_current_wallpaper,
+            wallpaper::wallpaper_get_settings,
+            wallpaper::wallpaper_set_rotation,
+            wallpaper::wallpaper_rotate_now,
+            wallpaper::wallpaper_set_unsplash_key,
+            finance::get_quote,
+            finance::get_watchlist,
+            finance::add_to_watchlist,
+            finance::remove_from_watchlist,
+            finance::reorder_watchlist,
+            docs::docs_list_available,
+            docs::docs_list_installed,
+            docs::docs_install,
+            docs::docs_uninstall,
+            docs::search_docs,
+            docs::docs_get_page,
+            http_requests::http_list_requests,
+            http_requests::http_save_request,
+            http_requests::http_delete_request,
+            http_requests::http_execute,
+            http_requests::http_run_saved,
+            http_requests::http_get_history,
+            http_requests::http_clear_history,
+            checksum::hash_file,
+            data_tools::data_validate,
+            data_tools::data_pretty,
+            data_tools::data_minify,
+            data_tools::data_convert,
+            data_tools::data_query,
+            dictionary::define_word,
+            dictionary::synonyms,
+            translate::translate_text,
+            translate::get_translate_settings,
+            translate::set_translate_settings,
+            translate::set_translate_api_key,
+            translate::is_translate_api_key_set,
+            translate::clear_translate_api_key,
+            translate::get_translation_history,
+            translate::delete_translation_history_entry,
+            translate::clear_translation_history,
+            text_actions::text_action_preview,
+            text_actions::text_action_run,
+            text_actions::text_count,
+            text_actions::text_lorem_ipsum,
+            ocr::capture_region_and_ocr,
+            ocr::ocr_clipboard_image,
+            annotate::annotate_capture,
+            annotate::annotate_clipboard_image,
+            annotate::annotate_apply,
+            ocr::ocr_list_languages,
+            ocr::get_ocr_settings,
+            ocr::set_ocr_settings,
+            hotkey_manager::hotkey_list,
+            hotkey_manager::hotkey_set,
+            hotkey_manager::hotkey_remove,
+            hotkey_manager::probe_hotkey,
+            hotkey_manager::hotkey_portal_status,
+            hotkey_manager::hotkey_portal_configure,
+            window_management::snap_active_window,
+            window_management::create_snap_layout,
+            window_management::list_snap_layouts,
+            window_management::update_snap_layout,
+            window_management::delete_snap_layout,
+            window_management::snap_active_window_to_layout,
+            window_management::save_window_layout,
+            window_management::restore_window_layout,
+            window_management::list_window_layouts,
+            window_management::delete_window_layout,
+            compositor::list_open_windows,
+            context::get_active_window_info,
+            power_awareness::get_power_status,
+            power_awareness::set_power_settings,
+            power_awareness::set_low_power_mode,
+            compositor::focus_open_window,
+            compositor::list_workspaces,
+            compositor::move_window_to_workspace,
+            compositor::compositor_name,
+            autostart::autostart_status,
+            autostart::enable_autostart,
+            autostart::disable_autostart,
             toggle_wifi,
             get_wifi_state,
             toggle_bluetooth,
@@ -605,14 +1022,57 @@ pub fn run() {
             github_search_repos,
             github_list_repos,
             github_get_repo,
+            github_list_workflow_runs,
+            github_rerun_workflow_run,
+            github_watch_workflow_run,
             ai::get_ollama_models,
             ai::create_conversation,
             ai::list_conversations,
             ai::get_conversation,
             ai::update_conversation,
-            ai::delete_conversation
+            ai::delete_conversation,
+            ai::presets::list_ai_presets,
+            ai::presets::get_ai_preset,
+            ai::presets::create_ai_preset,
+            ai::presets::update_ai_preset,
+            ai::presets::delete_ai_preset,
+            ai::presets::duplicate_ai_preset,
+            ai::presets::set_default_ai_preset,
+            ai::presets::get_default_ai_preset,
+            ai::presets::get_ai_preset_defaults,
+            ai::presets::export_ai_presets,
+            ai::presets::import_ai_presets,
+            ai::commands::list_ai_commands,
+            ai::commands::get_ai_command,
+            ai::commands::create_ai_command,
+            ai::commands::update_ai_command,
+            ai::commands::delete_ai_command,
+            ai::tools::set_web_search_api_key,
+            ai::tools::is_web_search_api_key_set,
+            ai::tools::clear_web_search_api_key,
+            ai::embeddings::list_embedding_sources,
+            ai::embeddings::add_embedding_source,
+            ai::embeddings::remove_embedding_source,
+            ai::embeddings::index_embeddings,
+            ai::embeddings::search_embeddings,
+            ai::embeddings::set_embeddings_api_key,
+            ai::embeddings::clear_embeddings_api_key,
+            ai::tokens::count_tokens,
+            ai::cache::get_ai_cache_stats,
+            ai::cache::clear_ai_cache,
+            ai::ollama::ollama_load_model,
+            ai::ollama::ollama_unload_model
         ])
-        .setup(|app| {
+        .setup(move |app| {
+            if start_hidden {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.hide();
+                }
+            }
+            layer_shell::init(app.handle());
+            window_prefs::init(app.handle());
+            secrets::init(app.handle());
+
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(browser_extension::run_server(app_handle));
 
@@ -623,11 +1083,41 @@ pub fn run() {
             app.manage(FrecencyManager::new(app.handle())?);
             app.manage(SnippetManager::new(app.handle())?);
             app.manage(AiUsageManager::new(app.handle())?);
-
-            setup_background_refresh(app.handle().clone());
-            if let Err(e) = setup_global_shortcut(app) {
-                tracing::error!(error = %e, "Failed to set up global shortcut");
-            }
+            app.manage(AiPresetManager::new(app.handle())?);
+            app.manage(AiCommandManager::new(app.handle())?);
+            app.manage(EmbeddingManager::new(app.handle())?);
+            app.manage(AiCacheManager::new(app.handle())?);
+            app.manage(SnapLayoutManager::new(app.handle())?);
+            app.manage(WindowArrangementManager::new(app.handle())?);
+            app.manage(TranslationHistoryManager::new(app.handle())?);
+            app.manage(ExtensionStorageManager::new(app.handle())?);
+            app.manage(CatalogManager::new(app.handle())?);
+            app.manage(WebSearchManager::new(app.handle())?);
+            app.manage(AliasManager::new(app.handle())?);
+            app.manage(CommandRegistry::new(app.handle())?);
+            app.manage(ProfileManager::new(app.handle())?);
+            app.manage(SessionManager::new(app.handle())?);
+            app.manage(InstantAnswerService::default());
+            app.manage(UnfurlService::default());
+            app.manage(StreamRegistry::default());
+            app.manage(LocalTaskManager::new(app.handle())?);
+            app.manage(FinanceManager::new(app.handle())?);
+            app.manage(DocsManager::new(app.handle())?);
+            app.manage(HttpRequestManager::new(app.handle())?);
+
+            app_watcher::start(app.handle().clone());
+            system_monitors::start_background_sampling();
+            system_monitors::start_battery_watch(app.handle());
+            hotkey_manager::init(app.handle());
+            timers::init(app.handle());
+            reminders::init(app.handle());
+            quicklinks::dynamic::init(app.handle());
+            wallpaper::init(app.handle());
+            integrations::email::init(app.handle());
+            snippets::ime::init(app.handle());
+            context::init(app.handle());
+            power_awareness::init(app.handle());
+            undo::init(app.handle());
             setup_input_listener(app.handle());
 
             let soulver_core_path = app
@@ -637,6 +1127,14 @@ pub fn run() {
                 .join("SoulverWrapper/Vendor/SoulverCore-linux");
 
             soulver::initialize(soulver_core_path.to_str().unwrap());
+            currencies::init(app.handle());
+
+            if let Some(options) = bench_options {
+                bench::start(app.handle().clone(), options);
+            }
+            if let Some(action) = cli_action.filter(|action| action != &cli::CliAction::Toggle) {
+                cli::perform(app.handle(), action);
+            }
 
             Ok(())
         })
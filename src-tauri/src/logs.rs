@@ -0,0 +1,503 @@
+//! Log capture for the in-app log viewer and bug reports. Tracing output goes
+//! to stderr and, as JSON lines, to daily-rotated files under
+//! `<app local data>/logs`. Panics are logged and also written to their own
+//! `crash-*.log` file, since the process may not live long enough to flush
+//! the regular log.
+
+use crate::diagnostics;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{Builder, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+use zip::write::SimpleFileOptions;
+
+/// Must match `identifier` in tauri.conf.json; logging starts before the app
+/// handle (and its path resolver) exists
+const APP_IDENTIFIER: &str = "dev.byteatatime.flare";
+const LOG_FILE_PREFIX: &str = "flare";
+const MAX_LOG_FILES: usize = 7;
+const DEFAULT_LOG_LIMIT: usize = 500;
+const MAX_CRASH_FILES: usize = 10;
+const REDACTED: &str = "[redacted]";
+/// Settings keys whose values never leave the machine
+const SENSITIVE_KEY_PARTS: &[&str] = &[
+    "token",
+    "secret",
+    "password",
+    "passphrase",
+    "apikey",
+    "api_key",
+    "accesskey",
+    "access_key",
+    "auth",
+    "cookie",
+    "credential",
+    "private",
+];
+
+static LOG_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+fn log_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join(APP_IDENTIFIER).join("logs"))
+}
+
+/// Sets up the global subscriber; file logging is skipped (with a warning)
+/// when the log directory can't be created
+pub fn init() {
+    let env_filter = || EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into());
+    let registry = tracing_subscriber::registry()
+        .with(env_filter())
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr));
+
+    let file_appender = log_dir()
+        .filter(|dir| fs::create_dir_all(dir).is_ok())
+        .and_then(|dir| {
+            Builder::new()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(LOG_FILE_PREFIX)
+                .filename_suffix("log")
+                .max_log_files(MAX_LOG_FILES)
+                .build(dir)
+                .ok()
+        });
+
+    match file_appender {
+        Some(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = LOG_GUARD.set(guard);
+            registry
+                .with(
+                    tracing_subscriber::fmt::layer()
+                        .json()
+                        .with_ansi(false)
+                        .with_writer(writer),
+                )
+                .init();
+        }
+        None => {
+            registry.init();
+            tracing::warn!("Could not create the log directory, logging to stdout only");
+        }
+    }
+
+    install_panic_hook();
+}
+
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_default();
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        let thread = std::thread::current()
+            .name()
+            .unwrap_or("unnamed")
+            .to_string();
+        let backtrace = std::backtrace::Backtrace::force_capture();
+
+        tracing::error!(%location, %thread, "Panic: {}", message);
+        if let Some(dir) = log_dir() {
+            write_crash_report(&dir, &message, &location, &thread, &backtrace.to_string());
+        }
+        default_hook(info);
+    }));
+}
+
+fn write_crash_report(dir: &Path, message: &str, location: &str, thread: &str, backtrace: &str) {
+    let now = Utc::now();
+    let path = dir.join(format!("crash-{}.log", now.format("%Y%m%d-%H%M%S%.3f")));
+    let report = format!(
+        "Flare {} crashed at {}\nThread: {}\nLocation: {}\nMessage: {}\n\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        now.to_rfc3339(),
+        thread,
+        location,
+        message,
+        backtrace
+    );
+    let _ = fs::write(path, report);
+
+    let mut crashes = crash_files(dir);
+    while crashes.len() > MAX_CRASH_FILES {
+        let _ = fs::remove_file(crashes.remove(0));
+    }
+}
+
+/// Oldest first, going by the timestamp in the name
+fn sorted_files(dir: &Path, keep: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(&keep)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn log_files(dir: &Path) -> Vec<PathBuf> {
+    sorted_files(dir, |name| {
+        name.starts_with(&format!("{}.", LOG_FILE_PREFIX)) && name.ends_with(".log")
+    })
+}
+
+fn crash_files(dir: &Path) -> Vec<PathBuf> {
+    sorted_files(dir, |name| {
+        name.starts_with("crash-") && name.ends_with(".log")
+    })
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// Structured fields other than the message
+    pub fields: Map<String, Value>,
+}
+
+/// Higher is more severe; unknown levels sort with TRACE
+fn severity(level: &str) -> u8 {
+    match level.to_ascii_uppercase().as_str() {
+        "ERROR" => 4,
+        "WARN" => 3,
+        "INFO" => 2,
+        "DEBUG" => 1,
+        _ => 0,
+    }
+}
+
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    let mut fields = value
+        .get("fields")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let message = match fields.remove("message") {
+        Some(Value::String(message)) => message,
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    Some(LogEntry {
+        timestamp: value
+            .get("timestamp")
+            .and_then(Value::as_str)
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&Utc)),
+        level: value.get("level")?.as_str()?.to_string(),
+        target: value
+            .get("target")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        message,
+        fields,
+    })
+}
+
+/// Newest entries first, reading files from the latest backwards until
+/// `limit` matching entries are found
+fn recent_entries(
+    dir: &Path,
+    min_level: Option<&str>,
+    search: Option<&str>,
+    limit: usize,
+) -> Vec<LogEntry> {
+    let min_severity = min_level.map(severity).unwrap_or(0);
+    let search = search
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty());
+    let mut entries = Vec::new();
+
+    for file in log_files(dir).iter().rev() {
+        let Ok(handle) = fs::File::open(file) else {
+            continue;
+        };
+        let mut file_entries: Vec<LogEntry> = BufReader::new(handle)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| parse_line(&line))
+            .filter(|entry| severity(&entry.level) >= min_severity)
+            .filter(|entry| match &search {
+                Some(search) => {
+                    entry.message.to_lowercase().contains(search)
+                        || entry.target.to_lowercase().contains(search)
+                }
+                None => true,
+            })
+            .collect();
+        file_entries.reverse();
+        entries.extend(file_entries);
+        if entries.len() >= limit {
+            break;
+        }
+    }
+    entries.truncate(limit);
+    entries
+}
+
+fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_lowercase().replace('-', "_");
+    SENSITIVE_KEY_PARTS.iter().any(|part| key.contains(part))
+}
+
+/// Replaces values under sensitive-looking keys, at any depth
+fn sanitize(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_sensitive_key(key) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    sanitize(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(sanitize),
+        _ => {}
+    }
+}
+
+/// Every JSON settings file in the app's data dirs, sanitized
+fn sanitized_settings(dirs: &[PathBuf]) -> Vec<(String, Value)> {
+    let mut settings = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(mut value) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            else {
+                continue;
+            };
+            sanitize(&mut value);
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            settings.push((name, value));
+        }
+    }
+    settings.sort_by(|a, b| a.0.cmp(&b.0));
+    settings.dedup_by(|a, b| a.0 == b.0);
+    settings
+}
+
+fn system_info() -> Value {
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "kernel": fs::read_to_string("/proc/sys/kernel/osrelease")
+            .map(|s| s.trim().to_string())
+            .ok(),
+        "distribution": fs::read_to_string("/etc/os-release").ok().and_then(|content| {
+            content
+                .lines()
+                .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+                .map(|name| name.trim_matches('"').to_string())
+        }),
+        "desktop": std::env::var("XDG_CURRENT_DESKTOP").ok(),
+        "sessionType": std::env::var("XDG_SESSION_TYPE").ok(),
+        "generatedAt": Utc::now().to_rfc3339(),
+    })
+}
+
+fn write_bundle(
+    destination: &Path,
+    log_dir: Option<&Path>,
+    settings: &[(String, Value)],
+    doctor: &Value,
+) -> Result<(), String> {
+    let file = fs::File::create(destination).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default();
+    let mut add = |name: &str, bytes: &[u8]| -> Result<(), String> {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(bytes).map_err(|e| e.to_string())
+    };
+
+    let to_json = |value: &Value| serde_json::to_vec_pretty(value).unwrap_or_default();
+    add("system.json", &to_json(&system_info()))?;
+    add("doctor.json", &to_json(doctor))?;
+    for (name, value) in settings {
+        add(&format!("settings/{}", name), &to_json(value))?;
+    }
+    if let Some(dir) = log_dir {
+        for path in log_files(dir).into_iter().chain(crash_files(dir)) {
+            let (Some(name), Ok(bytes)) = (path.file_name(), fs::read(&path)) else {
+                continue;
+            };
+            add(&format!("logs/{}", name.to_string_lossy()), &bytes)?;
+        }
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_recent_logs(
+    limit: Option<usize>,
+    min_level: Option<String>,
+    search: Option<String>,
+) -> Result<Vec<LogEntry>, String> {
+    let dir = log_dir().ok_or("Could not determine the log directory")?;
+    Ok(recent_entries(
+        &dir,
+        min_level.as_deref(),
+        search.as_deref(),
+        limit.unwrap_or(DEFAULT_LOG_LIMIT),
+    ))
+}
+
+#[tauri::command]
+pub fn get_log_directory() -> Result<String, String> {
+    log_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .ok_or_else(|| "Could not determine the log directory".to_string())
+}
+
+/// Zips logs, crash reports, sanitized settings and the doctor report for
+/// attaching to a bug report; returns the bundle's path
+#[tauri::command]
+pub async fn export_debug_bundle(
+    app: AppHandle,
+    destination: Option<String>,
+) -> Result<String, String> {
+    let doctor = match diagnostics::run_doctor().await {
+        Ok(report) => serde_json::to_value(report).map_err(|e| e.to_string())?,
+        Err(e) => serde_json::json!({ "error": e }),
+    };
+    let destination = match destination {
+        Some(path) => PathBuf::from(path),
+        None => dirs::download_dir()
+            .or_else(dirs::home_dir)
+            .ok_or("Could not determine a folder for the bundle")?
+            .join(format!(
+                "flare-debug-{}.zip",
+                Utc::now().format("%Y%m%d-%H%M%S")
+            )),
+    };
+    let settings_dirs: Vec<PathBuf> =
+        [app.path().app_local_data_dir(), app.path().app_config_dir()]
+            .into_iter()
+            .flatten()
+            .collect();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let settings = sanitized_settings(&settings_dirs);
+        write_bundle(&destination, log_dir().as_deref(), &settings, &doctor)?;
+        Ok(destination.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn log_line(level: &str, message: &str) -> String {
+        json!({
+            "timestamp": "2026-01-02T03:04:05.000000Z",
+            "level": level,
+            "fields": { "message": message, "id": 3 },
+            "target": "flare_lib::test"
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let entry = parse_line(&log_line("WARN", "Disk almost full")).unwrap();
+        assert_eq!(entry.level, "WARN");
+        assert_eq!(entry.message, "Disk almost full");
+        assert_eq!(entry.target, "flare_lib::test");
+        assert_eq!(entry.fields.get("id"), Some(&json!(3)));
+        assert!(entry.timestamp.is_some());
+        assert!(parse_line("not json").is_none());
+    }
+
+    #[test]
+    fn test_recent_entries_filters_and_orders() {
+        let dir = std::env::temp_dir().join(format!("flare_logs_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let older = [log_line("INFO", "one"), log_line("ERROR", "two")].join("\n");
+        let newer = [log_line("DEBUG", "three"), log_line("WARN", "four")].join("\n");
+        fs::write(dir.join("flare.2026-01-01.log"), older).unwrap();
+        fs::write(dir.join("flare.2026-01-02.log"), newer).unwrap();
+        fs::write(dir.join("crash-20260101.log"), "ignored").unwrap();
+
+        let messages = |entries: Vec<LogEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.message).collect()
+        };
+        assert_eq!(
+            messages(recent_entries(&dir, None, None, 10)),
+            vec!["four", "three", "two", "one"]
+        );
+        assert_eq!(
+            messages(recent_entries(&dir, Some("warn"), None, 10)),
+            vec!["four", "two"]
+        );
+        assert_eq!(
+            messages(recent_entries(&dir, None, Some("TW"), 10)),
+            vec!["two"]
+        );
+        assert_eq!(messages(recent_entries(&dir, None, None, 1)), vec!["four"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let mut value = json!({
+            "apiKey": "abc",
+            "github": { "access_token": "ghp_x", "username": "me" },
+            "accounts": [{ "password": "hunter2", "host": "imap" }],
+            "refreshToken": null,
+            "theme": "dark"
+        });
+        sanitize(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "apiKey": REDACTED,
+                "github": { "access_token": REDACTED, "username": "me" },
+                "accounts": [{ "password": REDACTED, "host": "imap" }],
+                "refreshToken": null,
+                "theme": "dark"
+            })
+        );
+    }
+}
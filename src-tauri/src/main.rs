@@ -2,5 +2,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
-    flare_lib::run()
+    // Autostart entries pass --hidden so logging in doesn't show the launcher
+    let start_hidden = std::env::args().skip(1).any(|arg| arg == "--hidden");
+    flare_lib::start(start_hidden)
 }
@@ -0,0 +1,148 @@
+//! Maintenance actions for the settings UI: quitting, restarting, picking up
+//! extensions changed on disk and starting over with empty caches.
+//!
+//! Everything under the app cache directory can be rebuilt: the scanned app
+//! list, parsed desktop entries with their resolved icon paths, icons pulled
+//! out of AppImages and the sidecar's extension cache. Downloaded wallpapers
+//! are kept, since the desktop may be showing one of them. `clear_caches`
+//! rescans the apps right away so the next search isn't the one paying for
+//! it, then announces `CACHES_CLEARED_EVENT` so windows drop the icons and
+//! assets they still hold.
+
+use crate::cache::AppCache;
+use crate::extensions::{self, PluginInfo};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const EXTENSIONS_RELOADED_EVENT: &str = "extensions-reloaded";
+pub const CACHES_CLEARED_EVENT: &str = "caches-cleared";
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearedCaches {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+fn size_of(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| size_of(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Empties `dir` except for the entries named in `keep`, keeping the
+/// directory itself. Entries that can't be removed, e.g. files held open by
+/// the sidecar, are left for next time.
+fn clear_dir(dir: &Path, keep: &[&str]) -> ClearedCaches {
+    let mut cleared = ClearedCaches::default();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return cleared;
+    };
+    for entry in entries.flatten() {
+        if keep.iter().any(|name| entry.file_name() == *name) {
+            continue;
+        }
+        let path = entry.path();
+        let bytes = size_of(&path);
+        let removed = match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => fs::remove_dir_all(&path),
+            _ => fs::remove_file(&path),
+        };
+        match removed {
+            Ok(()) => {
+                cleared.entries += 1;
+                cleared.bytes += bytes;
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, path = %path.display(), "Failed to clear cache entry")
+            }
+        }
+    }
+    cleared
+}
+
+#[tauri::command]
+pub fn app_quit(app: AppHandle) {
+    app.exit(0);
+}
+
+#[tauri::command]
+pub fn app_restart(app: AppHandle) {
+    app.restart();
+}
+
+/// Rediscovers installed extensions and announces them with
+/// `EXTENSIONS_RELOADED_EVENT`, so the launcher restarts the sidecar on the
+/// new code
+#[tauri::command]
+pub fn reload_extensions(app: AppHandle) -> Result<Vec<PluginInfo>, String> {
+    let plugins = extensions::discover_plugins(&app)?;
+    app.emit(EXTENSIONS_RELOADED_EVENT, &plugins)
+        .map_err(|e| e.to_string())?;
+    Ok(plugins)
+}
+
+#[tauri::command]
+pub async fn clear_caches(app: AppHandle) -> Result<ClearedCaches, String> {
+    let cache_dir = app.path().app_cache_dir().map_err(|e| e.to_string())?;
+    let handle = app.clone();
+    let cleared = tauri::async_runtime::spawn_blocking(move || {
+        let cleared = clear_dir(&cache_dir, &[crate::wallpaper::PHOTO_CACHE_DIR]);
+        crate::exec::clear();
+        crate::integrations::github::clear_response_cache();
+        if let Err(e) = AppCache::refresh_and_get_apps(&handle) {
+            tracing::warn!(error = ?e, "Failed to rescan apps after clearing caches");
+        }
+        cleared
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tracing::info!(
+        entries = cleared.entries,
+        bytes = cleared.bytes,
+        "Cleared caches"
+    );
+    app.emit(CACHES_CLEARED_EVENT, &cleared)
+        .map_err(|e| e.to_string())?;
+    Ok(cleared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_dir_keeps_the_directory() {
+        let dir = std::env::temp_dir().join(format!("flare-caches-{}", rand::random::<u32>()));
+        fs::create_dir_all(dir.join("appimages").join("abc")).unwrap();
+        fs::create_dir_all(dir.join("wallpapers")).unwrap();
+        fs::write(dir.join("apps.bincode"), [0u8; 10]).unwrap();
+        fs::write(dir.join("appimages").join("abc").join("icon.png"), [0u8; 5]).unwrap();
+        fs::write(dir.join("wallpapers").join("photo.jpg"), [0u8; 7]).unwrap();
+
+        let cleared = clear_dir(&dir, &["wallpapers"]);
+        assert_eq!(
+            cleared,
+            ClearedCaches {
+                entries: 2,
+                bytes: 15
+            }
+        );
+        assert!(dir.join("wallpapers").join("photo.jpg").exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert_eq!(
+            clear_dir(&dir.join("missing"), &[]),
+            ClearedCaches::default()
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+}
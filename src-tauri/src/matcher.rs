@@ -0,0 +1,440 @@
+//! Fuzzy matching and ranking for palette results, modelled on fzf's v2
+//! algorithm: a Smith-Waterman style alignment where matches score more at
+//! word boundaries, after delimiters, on camelCase humps and in consecutive
+//! runs, and gaps cost a little. The alignment is traced back so the caller
+//! gets the matched positions for highlighting.
+//!
+//! A term found as a contiguous run is always highlighted there, even when a
+//! scattered alignment through a stronger boundary would score higher, since
+//! that's the match people expect to see.
+//!
+//! Queries are split on whitespace and every term has to match. Matching is
+//! smart-case: case-insensitive unless the term contains an uppercase letter.
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+const BONUS_BOUNDARY_WHITE: i32 = BONUS_BOUNDARY + 2;
+const BONUS_BOUNDARY_DELIMITER: i32 = BONUS_BOUNDARY + 1;
+const BONUS_NON_WORD: i32 = SCORE_MATCH / 2;
+const BONUS_CAMEL_123: i32 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+/// Longer texts are matched on their prefix only
+const MAX_TEXT_CHARS: usize = 512;
+const DEFAULT_RANK_LIMIT: usize = 200;
+
+/// Ordered so that everything after `NonWord` is part of a word
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum CharClass {
+    White,
+    NonWord,
+    Delimiter,
+    Lower,
+    Upper,
+    Letter,
+    Number,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::White
+    } else if matches!(c, '/' | ',' | ':' | ';' | '|') {
+        CharClass::Delimiter
+    } else if c.is_lowercase() {
+        CharClass::Lower
+    } else if c.is_uppercase() {
+        CharClass::Upper
+    } else if c.is_numeric() {
+        CharClass::Number
+    } else if c.is_alphabetic() {
+        CharClass::Letter
+    } else {
+        CharClass::NonWord
+    }
+}
+
+fn bonus_for(prev: CharClass, class: CharClass) -> i32 {
+    if class > CharClass::Delimiter {
+        match prev {
+            CharClass::White => return BONUS_BOUNDARY_WHITE,
+            CharClass::Delimiter => return BONUS_BOUNDARY_DELIMITER,
+            CharClass::NonWord => return BONUS_BOUNDARY,
+            _ => {}
+        }
+    }
+    if (prev == CharClass::Lower && class == CharClass::Upper)
+        || (prev != CharClass::Number && class == CharClass::Number)
+    {
+        return BONUS_CAMEL_123;
+    }
+    match class {
+        CharClass::NonWord | CharClass::Delimiter => BONUS_NON_WORD,
+        CharClass::White => BONUS_BOUNDARY_WHITE,
+        _ => 0,
+    }
+}
+
+/// The start of the text counts as following whitespace
+fn position_bonuses(text: &[char]) -> Vec<i32> {
+    let mut prev = CharClass::White;
+    text.iter()
+        .map(|&c| {
+            let class = char_class(c);
+            let bonus = bonus_for(prev, class);
+            prev = class;
+            bonus
+        })
+        .collect()
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FuzzyMatch {
+    pub score: i32,
+    /// Matched positions in the text, in chars (not bytes), ascending
+    pub indices: Vec<usize>,
+}
+
+fn fold_case(c: char, case_sensitive: bool) -> char {
+    if case_sensitive {
+        c
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Best contiguous occurrence of `pattern`, scored the way the alignment
+/// scores a consecutive run
+fn match_contiguous(pattern: &[char], haystack: &[char], bonuses: &[i32]) -> Option<FuzzyMatch> {
+    let m = pattern.len();
+    let mut best: Option<FuzzyMatch> = None;
+    for start in 0..haystack.len().checked_sub(m - 1)? {
+        if haystack[start..start + m] != *pattern {
+            continue;
+        }
+        let mut score = SCORE_MATCH + bonuses[start] * BONUS_FIRST_CHAR_MULTIPLIER;
+        let mut run = 1;
+        for j in start + 1..start + m {
+            let bonus = bonuses[j];
+            let chunk_bonus = bonuses[j - run];
+            let b = if bonus >= BONUS_BOUNDARY && bonus > chunk_bonus {
+                run = 1;
+                bonus
+            } else {
+                run += 1;
+                bonus.max(BONUS_CONSECUTIVE).max(chunk_bonus)
+            };
+            score += SCORE_MATCH + b;
+        }
+        if best.as_ref().is_none_or(|best| score > best.score) {
+            best = Some(FuzzyMatch {
+                score,
+                indices: (start..start + m).collect(),
+            });
+        }
+    }
+    best
+}
+
+/// Best alignment of one term; `text` and `bonuses` are per char
+fn match_term(term: &str, text: &[char], bonuses: &[i32]) -> Option<FuzzyMatch> {
+    let case_sensitive = term.chars().any(char::is_uppercase);
+    let pattern: Vec<char> = term.chars().map(|c| fold_case(c, case_sensitive)).collect();
+    let haystack: Vec<char> = text.iter().map(|&c| fold_case(c, case_sensitive)).collect();
+    let m = pattern.len();
+    if let Some(contiguous) = match_contiguous(&pattern, &haystack, bonuses) {
+        return Some(contiguous);
+    }
+
+    // Cheap subsequence check first; it also bounds the columns worth scoring
+    let first = haystack.iter().position(|&c| c == pattern[0])?;
+    let mut matched = 0;
+    for &c in &haystack[first..] {
+        if c == pattern[matched] {
+            matched += 1;
+            if matched == m {
+                break;
+            }
+        }
+    }
+    if matched < m {
+        return None;
+    }
+    let last = haystack.iter().rposition(|&c| c == pattern[m - 1])?;
+    let width = last - first + 1;
+
+    // score[i][col]: best alignment of pattern[..=i] with pattern[i] at
+    // first + col; run: length of the consecutive chunk ending there;
+    // from: the column pattern[i - 1] was matched at
+    let mut score: Vec<Option<i32>> = vec![None; m * width];
+    let mut run = vec![0usize; m * width];
+    let mut from = vec![0usize; m * width];
+
+    for (i, &wanted) in pattern.iter().enumerate() {
+        // Best predecessor at least one char back, with the gap already paid
+        let mut gap_best: Option<(i32, usize)> = None;
+        for col in 0..width {
+            if i > 0 && col >= 2 {
+                let k = col - 2;
+                let extended = gap_best.map(|(s, k)| (s + SCORE_GAP_EXTENSION, k));
+                let started = score[(i - 1) * width + k].map(|s| (s + SCORE_GAP_START, k));
+                gap_best = match (extended, started) {
+                    (Some(e), Some(s)) => Some(if s.0 > e.0 { s } else { e }),
+                    (e, s) => e.or(s),
+                };
+            }
+
+            let j = first + col;
+            if haystack[j] != wanted {
+                continue;
+            }
+            let cell = i * width + col;
+            let bonus = bonuses[j];
+            if i == 0 {
+                score[cell] = Some(SCORE_MATCH + bonus * BONUS_FIRST_CHAR_MULTIPLIER);
+                run[cell] = 1;
+                continue;
+            }
+
+            let mut best = gap_best.map(|(s, k)| (s + SCORE_MATCH + bonus, 1, k));
+            if col > 0 {
+                let prev = (i - 1) * width + col - 1;
+                if let Some(prev_score) = score[prev] {
+                    let mut consecutive = run[prev] + 1;
+                    let chunk_bonus = bonuses[j + 1 - consecutive];
+                    // A strong boundary inside a run starts a new chunk
+                    let b = if bonus >= BONUS_BOUNDARY && bonus > chunk_bonus {
+                        consecutive = 1;
+                        bonus
+                    } else {
+                        bonus.max(BONUS_CONSECUTIVE).max(chunk_bonus)
+                    };
+                    let diagonal = prev_score + SCORE_MATCH + b;
+                    match best {
+                        Some((gapped, _, _)) if gapped > diagonal => {}
+                        _ => best = Some((diagonal, consecutive, col - 1)),
+                    }
+                }
+            }
+            if let Some((s, r, f)) = best {
+                score[cell] = Some(s);
+                run[cell] = r;
+                from[cell] = f;
+            }
+        }
+    }
+
+    let last_row = (m - 1) * width;
+    let (mut col, best) = (0..width)
+        .filter_map(|col| score[last_row + col].map(|s| (col, s)))
+        .fold(None, |best: Option<(usize, i32)>, (col, s)| match best {
+            Some((_, b)) if b >= s => best,
+            _ => Some((col, s)),
+        })?;
+
+    let mut indices = Vec::with_capacity(m);
+    for i in (0..m).rev() {
+        indices.push(first + col);
+        if i > 0 {
+            col = from[i * width + col];
+        }
+    }
+    indices.reverse();
+    Some(FuzzyMatch {
+        score: best,
+        indices,
+    })
+}
+
+/// Matches every whitespace-separated term of `query` against `text`; the
+/// score is the sum of the terms' scores
+pub fn fuzzy_match(query: &str, text: &str) -> Option<FuzzyMatch> {
+    let text: Vec<char> = text.chars().take(MAX_TEXT_CHARS).collect();
+    let bonuses = position_bonuses(&text);
+
+    let mut result = FuzzyMatch {
+        score: 0,
+        indices: Vec::new(),
+    };
+    for term in query.split_whitespace() {
+        let matched = match_term(term, &text, &bonuses)?;
+        result.score += matched.score;
+        result.indices.extend(matched.indices);
+    }
+    result.indices.sort_unstable();
+    result.indices.dedup();
+    Some(result)
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchCandidate {
+    pub id: String,
+    pub text: String,
+    /// Extra terms the item can be found by; they aren't highlighted
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedMatch {
+    pub id: String,
+    pub score: i32,
+    /// Char positions in the candidate's text to highlight
+    pub indices: Vec<usize>,
+    /// Set when a keyword matched better than the text
+    pub keyword: Option<String>,
+}
+
+fn match_candidate(query: &str, candidate: &MatchCandidate) -> Option<RankedMatch> {
+    let text = fuzzy_match(query, &candidate.text);
+    let keyword = candidate
+        .keywords
+        .iter()
+        .filter_map(|keyword| fuzzy_match(query, keyword).map(|m| (keyword, m.score)))
+        .max_by_key(|(_, score)| *score);
+
+    match (text, keyword) {
+        (Some(text), Some((keyword, score))) if score > text.score => Some(RankedMatch {
+            id: candidate.id.clone(),
+            score,
+            indices: text.indices,
+            keyword: Some(keyword.clone()),
+        }),
+        (Some(text), _) => Some(RankedMatch {
+            id: candidate.id.clone(),
+            score: text.score,
+            indices: text.indices,
+            keyword: None,
+        }),
+        (None, Some((keyword, score))) => Some(RankedMatch {
+            id: candidate.id.clone(),
+            score,
+            indices: Vec::new(),
+            keyword: Some(keyword.clone()),
+        }),
+        (None, None) => None,
+    }
+}
+
+/// Best matches first; ties go to the shorter text, then to input order. An
+/// empty query keeps the input order.
+pub fn rank(query: &str, candidates: &[MatchCandidate], limit: usize) -> Vec<RankedMatch> {
+    if query.trim().is_empty() {
+        return candidates
+            .iter()
+            .take(limit)
+            .map(|candidate| RankedMatch {
+                id: candidate.id.clone(),
+                score: 0,
+                indices: Vec::new(),
+                keyword: None,
+            })
+            .collect();
+    }
+
+    let mut matches: Vec<(usize, usize, RankedMatch)> = candidates
+        .par_iter()
+        .enumerate()
+        .filter_map(|(position, candidate)| {
+            match_candidate(query, candidate).map(|m| (position, candidate.text.chars().count(), m))
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        b.2.score
+            .cmp(&a.2.score)
+            .then(a.1.cmp(&b.1))
+            .then(a.0.cmp(&b.0))
+    });
+    matches.truncate(limit);
+    matches.into_iter().map(|(_, _, m)| m).collect()
+}
+
+#[tauri::command]
+pub async fn fuzzy_rank(
+    query: String,
+    candidates: Vec<MatchCandidate>,
+    limit: Option<usize>,
+) -> Result<Vec<RankedMatch>, String> {
+    let limit = limit.unwrap_or(DEFAULT_RANK_LIMIT);
+    tauri::async_runtime::spawn_blocking(move || rank(&query, &candidates, limit))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indices(query: &str, text: &str) -> Vec<usize> {
+        fuzzy_match(query, text).unwrap().indices
+    }
+
+    fn score(query: &str, text: &str) -> i32 {
+        fuzzy_match(query, text)
+            .map(|m| m.score)
+            .unwrap_or(i32::MIN)
+    }
+
+    #[test]
+    fn test_prefers_word_boundaries() {
+        assert_eq!(indices("gc", "Google Chrome"), vec![0, 7]);
+        assert!(score("gc", "Google Chrome") > score("gc", "logic"));
+        assert_eq!(indices("fb", "FooBar"), vec![0, 3]);
+        assert_eq!(indices("set", "system settings"), vec![7, 8, 9]);
+        assert_eq!(indices("rs", "src/main.rs"), vec![9, 10]);
+    }
+
+    #[test]
+    fn test_consecutive_beats_scattered() {
+        assert!(score("term", "Terminal") > score("term", "Text Editor Remote Mail"));
+        assert!(score("code", "VS Code") > score("code", "cloud orchestration desk"));
+    }
+
+    #[test]
+    fn test_smart_case_and_terms() {
+        assert!(fuzzy_match("fire", "Firefox").is_some());
+        assert!(fuzzy_match("Fire", "firefox").is_none());
+        assert!(fuzzy_match("xyz", "Firefox").is_none());
+        assert_eq!(indices("fox fi", "Firefox"), vec![0, 1, 4, 5, 6]);
+        assert!(fuzzy_match("fox zz", "Firefox").is_none());
+        assert_eq!(indices("é", "Café"), vec![3]);
+    }
+
+    #[test]
+    fn test_rank() {
+        let candidate = |id: &str, text: &str, keywords: &[&str]| MatchCandidate {
+            id: id.to_string(),
+            text: text.to_string(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+        };
+        let candidates = vec![
+            candidate("logic", "Logic Analyzer", &[]),
+            candidate("chrome", "Google Chrome", &["browser"]),
+            candidate("calc", "Calculator", &[]),
+            candidate("web", "Epiphany", &["browser", "web"]),
+        ];
+
+        let ranked = rank("gc", &candidates, 10);
+        let ids: Vec<&str> = ranked.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["chrome", "logic"]);
+        assert_eq!(ranked[0].indices, vec![0, 7]);
+
+        // Keyword-only matches rank without highlights; the shorter text wins a tie
+        let ranked = rank("browser", &candidates, 10);
+        let ids: Vec<&str> = ranked.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["web", "chrome"]);
+        assert_eq!(ranked[0].keyword.as_deref(), Some("browser"));
+        assert!(ranked[0].indices.is_empty());
+
+        assert_eq!(rank("", &candidates, 2).len(), 2);
+        assert_eq!(rank("c", &candidates, 1).len(), 1);
+    }
+}
@@ -0,0 +1,199 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use sysinfo::Networks;
+
+/// Plain-text "what's my IP" endpoints, tried in order
+const PUBLIC_IP_PROVIDERS: &[&str] = &[
+    "https://api.ipify.org",
+    "https://ifconfig.me/ip",
+    "https://icanhazip.com",
+    "https://ipinfo.io/ip",
+];
+const PUBLIC_IP_CACHE_TTL: Duration = Duration::from_secs(300);
+
+static PUBLIC_IP_CACHE: Lazy<Mutex<Option<(PublicIp, Instant)>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicIp {
+    pub ip: String,
+    pub provider: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InterfaceAddresses {
+    pub name: String,
+    pub mac_address: String,
+    pub addresses: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkDetails {
+    pub interfaces: Vec<InterfaceAddresses>,
+    pub default_gateway: Option<String>,
+    pub gateway_interface: Option<String>,
+    pub dns_servers: Vec<String>,
+}
+
+async fn fetch_public_ip() -> Result<PublicIp, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut errors = Vec::new();
+    for provider in PUBLIC_IP_PROVIDERS {
+        let result = async {
+            let response = client.get(*provider).send().await?.error_for_status()?;
+            response.text().await
+        }
+        .await;
+
+        match result {
+            Ok(body) => {
+                let ip = body.trim();
+                if ip.parse::<IpAddr>().is_ok() {
+                    return Ok(PublicIp {
+                        ip: ip.to_string(),
+                        provider: provider.to_string(),
+                    });
+                }
+                errors.push(format!("{}: unexpected response", provider));
+            }
+            Err(e) => {
+                tracing::debug!(provider = %provider, error = %e, "Public IP provider failed");
+                errors.push(format!("{}: {}", provider, e));
+            }
+        }
+    }
+
+    Err(format!(
+        "Could not determine public IP ({})",
+        errors.join("; ")
+    ))
+}
+
+/// Parses /proc/net/route and returns the default route's (interface, gateway)
+fn parse_default_route(content: &str) -> Option<(String, Ipv4Addr)> {
+    content.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            return None;
+        }
+        // The gateway is a little-endian hex u32
+        let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+        Some((fields[0].to_string(), Ipv4Addr::from(gateway.to_le_bytes())))
+    })
+}
+
+fn parse_nameservers(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            match parts.next() {
+                Some("nameserver") => parts.next().map(|s| s.to_string()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// DNS servers from resolv.conf. When systemd-resolved's stub (127.0.0.53) is
+/// the only entry, report the upstream servers it forwards to instead.
+fn get_dns_servers() -> Vec<String> {
+    let servers = fs::read_to_string("/etc/resolv.conf")
+        .map(|content| parse_nameservers(&content))
+        .unwrap_or_default();
+
+    if servers.iter().all(|s| s == "127.0.0.53") {
+        if let Ok(content) = fs::read_to_string("/run/systemd/resolve/resolv.conf") {
+            let upstream = parse_nameservers(&content);
+            if !upstream.is_empty() {
+                return upstream;
+            }
+        }
+    }
+    servers
+}
+
+fn get_interfaces() -> Vec<InterfaceAddresses> {
+    let networks = Networks::new_with_refreshed_list();
+    let mut interfaces: Vec<InterfaceAddresses> = networks
+        .iter()
+        .map(|(name, data)| InterfaceAddresses {
+            name: name.clone(),
+            mac_address: data.mac_address().to_string(),
+            addresses: data
+                .ip_networks()
+                .iter()
+                .map(|network| format!("{}/{}", network.addr, network.prefix))
+                .collect(),
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
+}
+
+#[tauri::command]
+pub async fn network_get_public_ip(force_refresh: Option<bool>) -> Result<PublicIp, String> {
+    if !force_refresh.unwrap_or(false) {
+        if let Some((cached, fetched_at)) = PUBLIC_IP_CACHE.lock().unwrap().as_ref() {
+            if fetched_at.elapsed() < PUBLIC_IP_CACHE_TTL {
+                return Ok(cached.clone());
+            }
+        }
+    }
+
+    let public_ip = fetch_public_ip().await?;
+    *PUBLIC_IP_CACHE.lock().unwrap() = Some((public_ip.clone(), Instant::now()));
+    Ok(public_ip)
+}
+
+#[tauri::command]
+pub fn network_get_info() -> NetworkDetails {
+    let route = fs::read_to_string("/proc/net/route")
+        .ok()
+        .and_then(|content| parse_default_route(&content));
+
+    NetworkDetails {
+        interfaces: get_interfaces(),
+        default_gateway: route.as_ref().map(|(_, gateway)| gateway.to_string()),
+        gateway_interface: route.map(|(interface, _)| interface),
+        dns_servers: get_dns_servers(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_default_route() {
+        let content =
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+                       wlp2s0\t0000A8C0\t00000000\t0001\t0\t0\t600\t00FFFFFF\t0\t0\t0\n\
+                       wlp2s0\t00000000\t0100A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0\n";
+        assert_eq!(
+            parse_default_route(content),
+            Some(("wlp2s0".to_string(), Ipv4Addr::new(192, 168, 0, 1)))
+        );
+        assert_eq!(parse_default_route("Iface\tDestination\n"), None);
+    }
+
+    #[test]
+    fn test_parse_nameservers() {
+        let content = "# Generated by NetworkManager\nsearch lan\n\
+                       nameserver 1.1.1.1\nnameserver 2606:4700:4700::1111\n";
+        assert_eq!(
+            parse_nameservers(content),
+            vec!["1.1.1.1".to_string(), "2606:4700:4700::1111".to_string()]
+        );
+    }
+}
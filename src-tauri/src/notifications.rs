@@ -0,0 +1,145 @@
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use tokio::sync::OnceCell;
+use zbus::zvariant::Value;
+
+#[derive(Debug, Clone, Default)]
+pub struct NotificationOptions {
+    /// Freedesktop sound theme name, e.g. `complete` or `alarm-clock-elapsed`
+    pub sound: Option<String>,
+    pub urgency: Option<Urgency>,
+    pub icon: Option<String>,
+    /// Buttons as `(action key, label)`; picking one emits `ActionInvoked`
+    pub actions: Vec<(String, String)>,
+    /// Keep the notification until the user dismisses it
+    pub persistent: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Urgency {
+    Normal = 1,
+    Critical = 2,
+}
+
+static CONNECTION: OnceCell<zbus::Connection> = OnceCell::const_new();
+
+/// One session bus connection for every notification: servers send action
+/// signals to the connection that created the notification
+async fn connection() -> Result<&'static zbus::Connection, String> {
+    CONNECTION
+        .get_or_try_init(zbus::Connection::session)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Show a desktop notification through org.freedesktop.Notifications
+pub async fn send(summary: &str, body: &str, options: NotificationOptions) -> Result<u32, String> {
+    let connection = connection().await?;
+
+    let mut hints: HashMap<&str, Value> = HashMap::new();
+    if let Some(sound) = options.sound.as_deref() {
+        hints.insert("sound-name", Value::from(sound));
+        if !server_plays_sounds(connection).await {
+            play_sound(sound);
+        }
+    }
+    if let Some(urgency) = options.urgency {
+        hints.insert("urgency", Value::from(urgency as u8));
+    }
+
+    let actions: Vec<&str> = options
+        .actions
+        .iter()
+        .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+        .collect();
+    let timeout: i32 = if options.persistent { 0 } else { -1 };
+    let reply = connection
+        .call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                "Flare",
+                0u32,
+                options.icon.as_deref().unwrap_or(""),
+                summary,
+                body,
+                actions,
+                hints,
+                timeout,
+            ),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    reply.body().deserialize::<u32>().map_err(|e| e.to_string())
+}
+
+async fn server_plays_sounds(connection: &zbus::Connection) -> bool {
+    let reply = connection
+        .call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "GetCapabilities",
+            &(),
+        )
+        .await;
+    reply
+        .ok()
+        .and_then(|reply| reply.body().deserialize::<Vec<String>>().ok())
+        .is_some_and(|caps| caps.iter().any(|c| c == "sound"))
+}
+
+/// Play a sound theme event with libcanberra, for servers that ignore `sound-name`
+pub fn play_sound(name: &str) {
+    match std::process::Command::new("canberra-gtk-play")
+        .args(["-i", name])
+        .spawn()
+    {
+        // Reaped off-thread so finished players don't linger as zombies
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => tracing::debug!(error = %e, "canberra-gtk-play not available"),
+    }
+}
+
+/// Fire-and-forget variant for background tasks; failures are only logged
+pub fn send_in_background(summary: String, body: String, options: NotificationOptions) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = send(&summary, &body, options).await {
+            tracing::warn!(error = %e, "Failed to show notification");
+        }
+    });
+}
+
+/// Calls `handler(notification_id, action_key)` whenever the user picks an
+/// action on one of our notifications. Runs until the bus connection closes.
+pub async fn listen_for_actions<F>(handler: F) -> Result<(), String>
+where
+    F: Fn(u32, String) + Send + 'static,
+{
+    let connection = connection().await?;
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface("org.freedesktop.Notifications")
+        .and_then(|rule| rule.member("ActionInvoked"))
+        .map_err(|e| e.to_string())?
+        .build();
+    let mut stream = zbus::MessageStream::for_match_rule(rule, connection, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    while let Some(message) = stream.next().await {
+        let Ok(message) = message else {
+            continue;
+        };
+        match message.body().deserialize::<(u32, String)>() {
+            Ok((id, action)) => handler(id, action),
+            Err(e) => tracing::debug!(error = %e, "Unexpected ActionInvoked payload"),
+        }
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::secrets;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::HashMap;
@@ -18,23 +19,16 @@ struct StoredTokenSet {
 
 type TokenStore = HashMap<String, StoredTokenSet>;
 
-fn get_storage_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+/// Tokens used to be kept in plain JSON here; they now live in the secrets vault
+fn get_legacy_storage_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     let data_dir = app
         .path()
         .app_local_data_dir()
         .map_err(|_| "Failed to get app local data dir".to_string())?;
-
-    if !data_dir.exists() {
-        fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
-    }
-
     Ok(data_dir.join("oauth_tokens.json"))
 }
 
-fn read_store(path: &Path) -> Result<TokenStore, String> {
-    if !path.exists() {
-        return Ok(HashMap::new());
-    }
+fn read_legacy_store(path: &Path) -> Result<TokenStore, String> {
     let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
     if content.trim().is_empty() {
         return Ok(HashMap::new());
@@ -42,9 +36,66 @@ fn read_store(path: &Path) -> Result<TokenStore, String> {
     serde_json::from_str(&content).map_err(|e| e.to_string())
 }
 
-fn write_store(path: &Path, store: &TokenStore) -> Result<(), String> {
-    let content = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
-    fs::write(path, content).map_err(|e| e.to_string())
+/// Moves tokens from the legacy JSON file into the vault, then removes the file
+fn migrate_legacy_store(app: &tauri::AppHandle) -> Result<(), String> {
+    let path = get_legacy_storage_path(app)?;
+    if !path.exists() {
+        return Ok(());
+    }
+    for (provider_id, token_set) in read_legacy_store(&path)? {
+        if !secrets::exists(secrets::OAUTH, &provider_id).map_err(|e| e.to_string())? {
+            write_tokens(&provider_id, &token_set)?;
+        }
+    }
+    fs::remove_file(&path).map_err(|e| e.to_string())
+}
+
+fn write_tokens(provider_id: &str, token_set: &StoredTokenSet) -> Result<(), String> {
+    let value = serde_json::to_string(token_set).map_err(|e| e.to_string())?;
+    secrets::set(secrets::OAUTH, provider_id, &value).map_err(|e| e.to_string())
+}
+
+/// Access token saved for `provider_id`, for integrations that call the
+/// provider's API from Rust
+pub fn get_access_token(
+    app: &tauri::AppHandle,
+    provider_id: &str,
+) -> Result<Option<String>, String> {
+    migrate_legacy_store(app)?;
+    match secrets::get(secrets::OAUTH, provider_id).map_err(|e| e.to_string())? {
+        Some(raw) => {
+            let token_set: StoredTokenSet =
+                serde_json::from_str(&raw).map_err(|e| e.to_string())?;
+            Ok(Some(token_set.access_token))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Saves a token obtained outside an OAuth flow, e.g. pasted by the user
+pub fn set_access_token(
+    app: &tauri::AppHandle,
+    provider_id: &str,
+    access_token: &str,
+    scope: Option<String>,
+) -> Result<(), String> {
+    migrate_legacy_store(app)?;
+    write_tokens(
+        provider_id,
+        &StoredTokenSet {
+            access_token: access_token.to_string(),
+            refresh_token: None,
+            expires_in: None,
+            scope,
+            id_token: None,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        },
+    )
+}
+
+pub fn remove_tokens(app: &tauri::AppHandle, provider_id: &str) -> Result<(), String> {
+    migrate_legacy_store(app)?;
+    secrets::delete(secrets::OAUTH, provider_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -53,13 +104,9 @@ pub fn oauth_set_tokens(
     provider_id: String,
     tokens: serde_json::Value,
 ) -> Result<(), String> {
-    let path = get_storage_path(&app)?;
-    let mut store = read_store(&path)?;
-
+    migrate_legacy_store(&app)?;
     let token_set: StoredTokenSet = serde_json::from_value(tokens).map_err(|e| e.to_string())?;
-
-    store.insert(provider_id, token_set);
-    write_store(&path, &store)
+    write_tokens(&provider_id, &token_set)
 }
 
 #[tauri::command]
@@ -67,20 +114,19 @@ pub fn oauth_get_tokens(
     app: tauri::AppHandle,
     provider_id: String,
 ) -> Result<Option<serde_json::Value>, String> {
-    let path = get_storage_path(&app)?;
-    let store = read_store(&path)?;
-    if let Some(token_set) = store.get(&provider_id) {
-        let value = serde_json::to_value(token_set).map_err(|e| e.to_string())?;
-        Ok(Some(value))
-    } else {
-        Ok(None)
+    migrate_legacy_store(&app)?;
+    match secrets::get(secrets::OAUTH, &provider_id).map_err(|e| e.to_string())? {
+        Some(raw) => {
+            let token_set: StoredTokenSet =
+                serde_json::from_str(&raw).map_err(|e| e.to_string())?;
+            let value = serde_json::to_value(token_set).map_err(|e| e.to_string())?;
+            Ok(Some(value))
+        }
+        None => Ok(None),
     }
 }
 
 #[tauri::command]
 pub fn oauth_remove_tokens(app: tauri::AppHandle, provider_id: String) -> Result<(), String> {
-    let path = get_storage_path(&app)?;
-    let mut store = read_store(&path)?;
-    store.remove(&provider_id);
-    write_store(&path, &store)
+    remove_tokens(&app, &provider_id)
 }
@@ -0,0 +1,292 @@
+use image::{ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OcrSettings {
+    /// Tesseract language packs to combine, e.g. `["eng", "deu"]`
+    #[serde(default = "default_languages")]
+    pub languages: Vec<String>,
+    /// Tesseract `--psm` value; `None` leaves tesseract's automatic segmentation
+    #[serde(default)]
+    pub page_segmentation_mode: Option<u8>,
+}
+
+impl Default for OcrSettings {
+    fn default() -> Self {
+        Self {
+            languages: default_languages(),
+            page_segmentation_mode: None,
+        }
+    }
+}
+
+fn default_languages() -> Vec<String> {
+    vec!["eng".to_string()]
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OcrResult {
+    pub text: String,
+    pub languages: Vec<String>,
+}
+
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| "Failed to get app local data dir".to_string())?;
+
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(data_dir.join("ocr_settings.json"))
+}
+
+fn read_settings(path: &Path) -> Result<OcrSettings, String> {
+    if !path.exists() {
+        return Ok(OcrSettings::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if content.trim().is_empty() {
+        return Ok(OcrSettings::default());
+    }
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+/// Parses `tesseract --list-langs`, whose first line is a header naming the tessdata dir
+fn parse_language_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("List of available languages"))
+        .skip(1)
+        .map(str::trim)
+        .filter(|lang| !lang.is_empty() && *lang != "osd")
+        .map(str::to_string)
+        .collect()
+}
+
+fn installed_languages() -> Result<Vec<String>, String> {
+    let output = Command::new("tesseract")
+        .arg("--list-langs")
+        .output()
+        .map_err(|_| "tesseract is not installed".to_string())?;
+    // Older versions print the list on stderr
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(parse_language_list(&text))
+}
+
+fn clean_ocr_output(raw: &str) -> String {
+    let text = raw.replace('\u{c}', "");
+    text.lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Runs tesseract on an encoded image, piping it through stdin
+fn recognize(image: &[u8], settings: &OcrSettings) -> Result<String, String> {
+    let languages = if settings.languages.is_empty() {
+        default_languages()
+    } else {
+        settings.languages.clone()
+    };
+
+    let mut command = Command::new("tesseract");
+    command.args(["stdin", "stdout", "-l", &languages.join("+")]);
+    if let Some(psm) = settings.page_segmentation_mode {
+        command.args(["--psm", &psm.to_string()]);
+    }
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|_| "tesseract is not installed".to_string())?;
+    child
+        .stdin
+        .take()
+        .ok_or("Failed to open tesseract stdin")?
+        .write_all(image)
+        .map_err(|e| e.to_string())?;
+
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(clean_ocr_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn run_capture_tool(program: &str, args: &[&str]) -> Option<Result<(), String>> {
+    match Command::new(program).args(args).status() {
+        Ok(status) if status.success() => Some(Ok(())),
+        Ok(_) => Some(Err("Selection cancelled".to_string())),
+        Err(_) => None,
+    }
+}
+
+/// Lets the user drag out a region with whichever screenshot tool is installed
+pub(crate) fn capture_region(path: &Path) -> Result<Vec<u8>, String> {
+    let target = path.to_string_lossy().to_string();
+    let is_wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
+
+    let captured = if is_wayland {
+        match Command::new("slurp").output() {
+            Ok(output) if output.status.success() => {
+                let geometry = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                run_capture_tool("grim", &["-g", &geometry, &target])
+            }
+            Ok(_) => Some(Err("Selection cancelled".to_string())),
+            Err(_) => None,
+        }
+    } else {
+        run_capture_tool("maim", &["-s", &target])
+    };
+
+    let captured = captured
+        .or_else(|| run_capture_tool("gnome-screenshot", &["-a", "-f", &target]))
+        .or_else(|| run_capture_tool("spectacle", &["-b", "-n", "-r", "-o", &target]))
+        .ok_or_else(|| {
+            "No screenshot tool found (install grim and slurp, maim, gnome-screenshot or spectacle)"
+                .to_string()
+        })?;
+    captured?;
+
+    let bytes = fs::read(path).map_err(|_| "Selection cancelled".to_string())?;
+    let _ = fs::remove_file(path);
+    Ok(bytes)
+}
+
+pub(crate) fn clipboard_image_png() -> Result<Vec<u8>, String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    let image = clipboard
+        .get_image()
+        .map_err(|_| "No image on the clipboard".to_string())?;
+    let buffer = RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.into_owned(),
+    )
+    .ok_or("Clipboard image has an unexpected size")?;
+
+    let mut png = Cursor::new(Vec::new());
+    buffer
+        .write_to(&mut png, ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png.into_inner())
+}
+
+fn finish(app: &AppHandle, text: String, settings: OcrSettings) -> Result<OcrResult, String> {
+    if text.is_empty() {
+        return Err("No text recognized".to_string());
+    }
+    app.clipboard()
+        .write_text(text.clone())
+        .map_err(|e| e.to_string())?;
+    Ok(OcrResult {
+        text,
+        languages: settings.languages,
+    })
+}
+
+#[tauri::command]
+pub fn get_ocr_settings(app: AppHandle) -> Result<OcrSettings, String> {
+    read_settings(&get_settings_path(&app)?)
+}
+
+#[tauri::command]
+pub fn set_ocr_settings(app: AppHandle, settings: OcrSettings) -> Result<(), String> {
+    let installed = installed_languages()?;
+    if let Some(missing) = settings
+        .languages
+        .iter()
+        .find(|lang| !installed.contains(lang))
+    {
+        return Err(format!(
+            "Language pack '{}' is not installed (tesseract-ocr-{})",
+            missing, missing
+        ));
+    }
+
+    let path = get_settings_path(&app)?;
+    let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn ocr_list_languages() -> Result<Vec<String>, String> {
+    installed_languages()
+}
+
+/// Hides the launcher, lets the user select a screen region and copies the
+/// recognized text to the clipboard
+#[tauri::command]
+pub async fn capture_region_and_ocr(app: AppHandle) -> Result<OcrResult, String> {
+    let settings = get_ocr_settings(app.clone())?;
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.hide();
+        // Give the compositor a moment to unmap the window before capturing
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    let path = std::env::temp_dir().join(format!("flare_ocr_{}.png", uuid::Uuid::new_v4()));
+    let ocr_settings = settings.clone();
+    let text = tauri::async_runtime::spawn_blocking(move || {
+        let image = capture_region(&path)?;
+        recognize(&image, &ocr_settings)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    finish(&app, text, settings)
+}
+
+#[tauri::command]
+pub async fn ocr_clipboard_image(app: AppHandle) -> Result<OcrResult, String> {
+    let settings = get_ocr_settings(app.clone())?;
+    let ocr_settings = settings.clone();
+    let text = tauri::async_runtime::spawn_blocking(move || {
+        let image = clipboard_image_png()?;
+        recognize(&image, &ocr_settings)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    finish(&app, text, settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_language_list() {
+        let output = "List of available languages in \"/usr/share/tessdata/\" (4):\n\
+                      deu\neng\nosd\nchi_sim\n";
+        assert_eq!(parse_language_list(output), vec!["deu", "eng", "chi_sim"]);
+        assert!(parse_language_list("").is_empty());
+    }
+
+    #[test]
+    fn test_clean_ocr_output() {
+        assert_eq!(
+            clean_ocr_output("  Hello   \nworld \n\n\u{c}"),
+            "Hello\nworld"
+        );
+    }
+}
@@ -0,0 +1,379 @@
+//! "Open with…" for files and links: which installed apps can take a path,
+//! from the shared MIME database and the user's `mimeapps.list`, and
+//! launching one of them with it through its `Exec` field codes.
+//!
+//! The MIME type comes from the `globs2` extension table, with `xdg-mime`
+//! asked only when no glob matches; links are typed by scheme, like
+//! `x-scheme-handler/https`.
+
+use crate::app::App;
+use crate::cache::AppCache;
+use crate::exec;
+use crate::launcher;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const FALLBACK_MIME: &str = "application/octet-stream";
+const FILE_FIELD_CODES: &[&str] = &["%f", "%F", "%u", "%U"];
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenWithApp {
+    pub id: String,
+    pub name: String,
+    pub icon_path: Option<String>,
+    /// The app the desktop opens this type with
+    pub is_default: bool,
+}
+
+fn split_dirs(var: &str, fallback: &str) -> Vec<PathBuf> {
+    env::var(var)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| fallback.to_string())
+        .split(':')
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// `XDG_DATA_HOME` then `XDG_DATA_DIRS`, most important first
+fn data_dirs() -> Vec<PathBuf> {
+    dirs::data_dir()
+        .into_iter()
+        .chain(split_dirs("XDG_DATA_DIRS", "/usr/local/share:/usr/share"))
+        .collect()
+}
+
+/// Every `mimeapps.list`, most important first
+fn mimeapps_files() -> Vec<PathBuf> {
+    dirs::config_dir()
+        .into_iter()
+        .chain(split_dirs("XDG_CONFIG_DIRS", "/etc/xdg"))
+        .chain(data_dirs().into_iter().map(|dir| dir.join("applications")))
+        .map(|dir| dir.join("mimeapps.list"))
+        .collect()
+}
+
+/// The type `globs2` gives `file_name`: the heaviest matching `*.ext` glob,
+/// the longest among equals so `.tar.gz` beats `.gz`
+fn glob_mime_type(globs: &str, file_name: &str) -> Option<String> {
+    let file_name = file_name.to_lowercase();
+    globs
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let weight: u32 = fields.next()?.parse().ok()?;
+            let mime = fields.next()?;
+            let suffix = fields.next()?.strip_prefix('*')?;
+            (suffix.starts_with('.')
+                && !suffix.contains(['*', '?', '['])
+                && file_name.ends_with(&suffix.to_lowercase()))
+            .then_some((weight, suffix.len(), mime))
+        })
+        .max_by_key(|(weight, len, _)| (*weight, *len))
+        .map(|(_, _, mime)| mime.to_string())
+}
+
+/// The types `mime` is a kind of, per the database's `subclasses`, nearest
+/// first
+fn parent_types(subclasses: &str, mime: &str) -> Vec<String> {
+    let mut parents: Vec<String> = Vec::new();
+    let mut pending = vec![mime.to_string()];
+    while let Some(child) = pending.pop() {
+        for line in subclasses.lines() {
+            let Some((sub, parent)) = line.split_once(' ') else {
+                continue;
+            };
+            if sub == child && parent != mime && !parents.iter().any(|p| p == parent) {
+                parents.push(parent.to_string());
+                pending.push(parent.to_string());
+            }
+        }
+    }
+    if mime.starts_with("text/")
+        && mime != "text/plain"
+        && !parents.iter().any(|p| p == "text/plain")
+    {
+        parents.push("text/plain".to_string());
+    }
+    parents
+}
+
+fn read_database(name: &str) -> String {
+    data_dirs()
+        .into_iter()
+        .filter_map(|dir| fs::read_to_string(dir.join("mime").join(name)).ok())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+async fn mime_type(path: &str) -> String {
+    if let Ok(url) = url::Url::parse(path) {
+        if url.scheme() != "file" && url.scheme().len() > 1 {
+            return format!("x-scheme-handler/{}", url.scheme());
+        }
+    }
+    let local = local_path(path);
+    if local.is_dir() {
+        return "inode/directory".to_string();
+    }
+    let file_name = local
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if let Some(mime) = glob_mime_type(&read_database("globs2"), &file_name) {
+        return mime;
+    }
+    let local = local.to_string_lossy().into_owned();
+    match exec::run_async("xdg-mime", &["query", "filetype", &local]).await {
+        Ok(output) if output.success && output.stdout.trim().contains('/') => {
+            output.stdout.trim().to_string()
+        }
+        _ => FALLBACK_MIME.to_string(),
+    }
+}
+
+/// `mimeapps.list` sections, merged across files
+#[derive(Default, Debug)]
+struct MimeApps {
+    defaults: HashMap<String, Vec<String>>,
+    added: HashMap<String, Vec<String>>,
+    removed: HashMap<String, Vec<String>>,
+}
+
+impl MimeApps {
+    /// Merges one file; files come most important first, so earlier
+    /// defaults and added associations stay in front
+    fn merge(&mut self, content: &str) {
+        let mut section = None;
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                section = match line {
+                    "[Default Applications]" => Some(&mut self.defaults),
+                    "[Added Associations]" => Some(&mut self.added),
+                    "[Removed Associations]" => Some(&mut self.removed),
+                    _ => None,
+                };
+                continue;
+            }
+            let (Some(entries), Some((mime, ids))) = (section.as_deref_mut(), line.split_once('='))
+            else {
+                continue;
+            };
+            let list = entries.entry(mime.trim().to_string()).or_default();
+            for id in ids.split(';').map(str::trim).filter(|id| !id.is_empty()) {
+                if !list.iter().any(|known| known == id) {
+                    list.push(id.to_string());
+                }
+            }
+        }
+    }
+
+    fn load() -> Self {
+        let mut mime_apps = Self::default();
+        for file in mimeapps_files() {
+            if let Ok(content) = fs::read_to_string(file) {
+                mime_apps.merge(&content);
+            }
+        }
+        mime_apps
+    }
+
+    fn get<'a>(map: &'a HashMap<String, Vec<String>>, mime: &str) -> &'a [String] {
+        map.get(mime).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// Apps for `types` (the type, then its parents): the default first, then
+/// apps the user associated, then apps declaring the type, minus removed
+/// associations
+fn candidates(apps: &[App], types: &[String], mime_apps: &MimeApps) -> Vec<OpenWithApp> {
+    let by_id = |id: &str| apps.iter().find(|app| app.id.as_deref() == Some(id));
+    let mut ordered: Vec<(&App, bool)> = Vec::new();
+    for (index, mime) in types.iter().enumerate() {
+        let removed = MimeApps::get(&mime_apps.removed, mime);
+        let allowed = |app: &&App| {
+            app.id
+                .as_deref()
+                .is_some_and(|id| !removed.iter().any(|r| r == id))
+        };
+        // Only the exact type's default is the default
+        let default = MimeApps::get(&mime_apps.defaults, mime)
+            .iter()
+            .find_map(|id| by_id(id).filter(allowed));
+        if let Some(app) = default {
+            if !ordered.iter().any(|(known, _)| known.id == app.id) {
+                ordered.push((app, index == 0));
+            }
+        }
+        let added = MimeApps::get(&mime_apps.added, mime)
+            .iter()
+            .filter_map(|id| by_id(id));
+        let declaring = apps
+            .iter()
+            .filter(|app| app.mime_types.iter().any(|declared| declared == mime));
+        for app in added.chain(declaring).filter(allowed) {
+            if !ordered.iter().any(|(known, _)| known.id == app.id) {
+                ordered.push((app, false));
+            }
+        }
+    }
+
+    ordered
+        .into_iter()
+        .filter_map(|(app, is_default)| {
+            Some(OpenWithApp {
+                id: app.id.clone()?,
+                name: app.name.clone(),
+                icon_path: app.icon_path.clone(),
+                is_default,
+            })
+        })
+        .collect()
+}
+
+fn local_path(path: &str) -> PathBuf {
+    match url::Url::parse(path) {
+        Ok(url) if url.scheme() == "file" => url.to_file_path().unwrap_or_else(|_| path.into()),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// `path` as the launcher's field codes take it: a `file://` URI for local
+/// files, which `%f`/`%F` turn back into a path, and links as they are
+fn file_argument(path: &str) -> String {
+    if url::Url::parse(path).is_ok_and(|url| url.scheme().len() > 1) {
+        return path.to_string();
+    }
+    let local = Path::new(path);
+    let absolute = if local.is_absolute() {
+        local.to_path_buf()
+    } else {
+        env::current_dir().unwrap_or_default().join(local)
+    };
+    url::Url::from_file_path(&absolute)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// `exec` with a place for the file; entries without a file field code get
+/// it appended, as other launchers do
+fn exec_with_file_field(exec: &str) -> String {
+    if FILE_FIELD_CODES.iter().any(|code| exec.contains(code)) {
+        exec.to_string()
+    } else {
+        format!("{} %f", exec)
+    }
+}
+
+/// Apps that can open `path`, the desktop's default first
+#[tauri::command]
+pub async fn get_open_with_apps(app: AppHandle, path: String) -> Result<Vec<OpenWithApp>, String> {
+    let mime = mime_type(&path).await;
+    let mut types = vec![mime.clone()];
+    types.extend(parent_types(&read_database("subclasses"), &mime));
+    let apps = AppCache::get_apps(&app).map_err(|e| e.to_string())?;
+    Ok(candidates(&apps, &types, &MimeApps::load()))
+}
+
+/// Opens `path` with the app whose desktop file id is `desktop_id`
+#[tauri::command]
+pub fn open_with(app: AppHandle, path: String, desktop_id: String) -> Result<(), String> {
+    let apps = AppCache::get_apps(&app).map_err(|e| e.to_string())?;
+    let installed = apps
+        .iter()
+        .find(|a| a.id.as_deref() == Some(desktop_id.as_str()))
+        .ok_or_else(|| format!("App not found: {}", desktop_id))?;
+    let exec = installed
+        .exec
+        .as_deref()
+        .ok_or_else(|| format!("{} has no Exec line", installed.name))?;
+    launcher::launch(
+        installed,
+        &exec_with_file_field(exec),
+        &[file_argument(&path)],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(id: &str, mime_types: &[&str]) -> App {
+        App::new(id.trim_end_matches(".desktop").to_string())
+            .with_id(Some(id.to_string()))
+            .with_mime_types(mime_types.iter().map(|mime| mime.to_string()).collect())
+    }
+
+    #[test]
+    fn test_glob_mime_type() {
+        let globs = "# comment\n\
+            50:application/gzip:*.gz\n\
+            50:application/x-compressed-tar:*.tar.gz\n\
+            50:text/x-python:*.py\n\
+            10:text/x-readme:README*\n";
+        assert_eq!(
+            glob_mime_type(globs, "backup.TAR.GZ").as_deref(),
+            Some("application/x-compressed-tar")
+        );
+        assert_eq!(
+            glob_mime_type(globs, "notes.gz").as_deref(),
+            Some("application/gzip")
+        );
+        assert_eq!(
+            glob_mime_type(globs, "script.py").as_deref(),
+            Some("text/x-python")
+        );
+        assert_eq!(glob_mime_type(globs, "Makefile"), None);
+    }
+
+    #[test]
+    fn test_parent_types() {
+        let subclasses = "application/x-shellscript application/x-executable\n\
+            application/x-shellscript text/plain\n";
+        assert_eq!(
+            parent_types(subclasses, "application/x-shellscript"),
+            ["application/x-executable", "text/plain"]
+        );
+        assert_eq!(parent_types(subclasses, "text/x-python"), ["text/plain"]);
+    }
+
+    #[test]
+    fn test_candidates_order() {
+        let apps = vec![
+            app("gedit.desktop", &["text/plain"]),
+            app("code.desktop", &["text/x-python"]),
+            app("vim.desktop", &["text/plain"]),
+            app("idle.desktop", &["text/x-python"]),
+        ];
+        let mut mime_apps = MimeApps::default();
+        mime_apps.merge(
+            "[Default Applications]\ntext/x-python=code.desktop\n\
+             [Added Associations]\ntext/x-python=vim.desktop;\n\
+             [Removed Associations]\ntext/x-python=idle.desktop;\n",
+        );
+        let types = ["text/x-python".to_string(), "text/plain".to_string()];
+        let open_with = candidates(&apps, &types, &mime_apps);
+        let ids: Vec<&str> = open_with.iter().map(|app| app.id.as_str()).collect();
+        assert_eq!(ids, ["code.desktop", "vim.desktop", "gedit.desktop"]);
+        assert!(open_with[0].is_default);
+        assert!(!open_with[1].is_default);
+    }
+
+    #[test]
+    fn test_exec_with_file_field() {
+        assert_eq!(exec_with_file_field("gedit %U"), "gedit %U");
+        assert_eq!(exec_with_file_field("mpv"), "mpv %f");
+    }
+
+    #[test]
+    fn test_file_argument() {
+        assert_eq!(file_argument("/tmp/a b.txt"), "file:///tmp/a%20b.txt");
+        assert_eq!(file_argument("https://example.com"), "https://example.com");
+    }
+}
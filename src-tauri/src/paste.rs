@@ -0,0 +1,234 @@
+//! Pasting into the frontmost app. Everything that types a result into
+//! another app goes through here: snippets, clipboard history, text actions
+//! and the frontend's paste-in-place and emoji picker (via
+//! `clipboard_paste`).
+//!
+//! The content is put on the clipboard, the paste shortcut is sent with the
+//! input backend in use (enigo on X11, a uinput device on Wayland) and the
+//! previous clipboard comes back once the target app has had time to read
+//! it. Terminals get Ctrl+Shift+V. Pastes in quick succession share the one
+//! restore, so the clipboard ends up where it was before the first.
+
+use crate::clipboard::{self, ClipboardContent, HTML_MIME};
+use crate::clipboard_history::manager::INTERNAL_CLIPBOARD_CHANGE;
+use crate::context;
+use crate::snippets::input_manager::{InputManager, RdevInputManager};
+use arboard::{Clipboard, ImageData};
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Known terminal emulator WM_CLASS names and app ids, lowercase
+pub const TERMINAL_CLASSES: &[&str] = &[
+    "gnome-terminal",
+    "org.gnome.terminal",
+    "konsole",
+    "xterm",
+    "urxvt",
+    "rxvt",
+    "terminator",
+    "tilix",
+    "alacritty",
+    "kitty",
+    "st",
+    "st-256color",
+    "foot",
+    "footclient",
+    "wezterm",
+    "hyper",
+    "guake",
+    "yakuake",
+    "tilda",
+    "terminology",
+    "xfce4-terminal",
+    "lxterminal",
+    "mate-terminal",
+    "qterminal",
+    "sakura",
+    "termite",
+    "cool-retro-term",
+    "eterm",
+    "rio",
+    "warp",
+    "tabby",
+    "blackbox",
+    "contour",
+    "deepin-terminal",
+];
+
+/// Between writing the clipboard and pressing the shortcut
+const SETTLE_DELAY: Duration = Duration::from_millis(20);
+/// Apps read the clipboard asynchronously after the shortcut; Electron apps
+/// on XWayland can take a couple of hundred milliseconds
+const RESTORE_DELAY: Duration = Duration::from_millis(400);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasteShortcut {
+    CtrlV,
+    CtrlShiftV,
+}
+
+impl PasteShortcut {
+    pub fn for_app(app_id: &str) -> Self {
+        if is_terminal(app_id) {
+            PasteShortcut::CtrlShiftV
+        } else {
+            PasteShortcut::CtrlV
+        }
+    }
+}
+
+/// Whether `app_id`, a WM class or Wayland app id, is a known terminal: the
+/// whole id or its last dotted part (`org.kde.konsole`) has to match, so
+/// `steam` isn't taken for `st`
+pub fn is_terminal(app_id: &str) -> bool {
+    let app_id = app_id.to_lowercase();
+    let last = app_id.rsplit('.').next().unwrap_or(&app_id);
+    TERMINAL_CLASSES
+        .iter()
+        .any(|terminal| *terminal == app_id || *terminal == last)
+}
+
+/// What was on the clipboard before the first paste
+struct Snapshot {
+    text: Option<String>,
+    html: Option<String>,
+    image: Option<ImageData<'static>>,
+}
+
+impl Snapshot {
+    fn take(clipboard: &mut Clipboard) -> Self {
+        let text = clipboard.get_text().ok();
+        let html = text
+            .as_ref()
+            .and_then(|_| clipboard::read_format(HTML_MIME));
+        let image = match text {
+            Some(_) => None,
+            None => clipboard.get_image().ok(),
+        };
+        Self { text, html, image }
+    }
+
+    fn restore(self, clipboard: &mut Clipboard) -> Result<(), arboard::Error> {
+        if let Some(image) = self.image {
+            return clipboard.set_image(image);
+        }
+        match (self.html, self.text) {
+            (Some(html), text) => clipboard.set_html(html, text),
+            (None, Some(text)) => clipboard.set_text(text),
+            (None, None) => clipboard.clear(),
+        }
+    }
+}
+
+struct PendingRestore {
+    generation: u64,
+    snapshot: Snapshot,
+}
+
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static PENDING: Lazy<Mutex<Option<PendingRestore>>> = Lazy::new(|| Mutex::new(None));
+
+/// Restores the snapshot unless a later paste took it over
+fn restore_after_delay(generation: u64) {
+    thread::spawn(move || {
+        thread::sleep(RESTORE_DELAY);
+        // Held while restoring, so a paste starting now snapshots the
+        // restored clipboard rather than ours
+        let mut pending = PENDING.lock().unwrap();
+        let Some(PendingRestore { snapshot, .. }) =
+            pending.take_if(|pending| pending.generation == generation)
+        else {
+            return;
+        };
+        match Clipboard::new() {
+            Ok(mut clipboard) => {
+                if let Err(e) = snapshot.restore(&mut clipboard) {
+                    tracing::warn!(error = %e, "Failed to restore the clipboard after pasting");
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "Failed to open the clipboard to restore it"),
+        }
+        INTERNAL_CLIPBOARD_CHANGE.store(false, Ordering::SeqCst);
+    });
+}
+
+fn focused_shortcut() -> PasteShortcut {
+    context::active_window_info().map_or(PasteShortcut::CtrlV, |window| {
+        PasteShortcut::for_app(window.window_class.as_deref().unwrap_or(&window.app_id))
+    })
+}
+
+/// Pastes `content` with `keys`. Blocks only until the shortcut is sent;
+/// the clipboard is restored in the background.
+pub fn paste_with(keys: &dyn InputManager, content: &ClipboardContent) -> Result<(), String> {
+    let mut clipboard =
+        Clipboard::new().map_err(|e| format!("Failed to open the clipboard: {}", e))?;
+    // Kept out of the history until the restore clears it
+    INTERNAL_CLIPBOARD_CHANGE.store(true, Ordering::SeqCst);
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let snapshot = PENDING
+        .lock()
+        .unwrap()
+        .take()
+        .map(|pending| pending.snapshot)
+        .unwrap_or_else(|| Snapshot::take(&mut clipboard));
+
+    let result = clipboard::write(&mut clipboard, content).and_then(|()| {
+        thread::sleep(SETTLE_DELAY);
+        keys.send_paste(focused_shortcut())
+            .map_err(|e| format!("Failed to send the paste shortcut: {}", e))
+    });
+
+    *PENDING.lock().unwrap() = Some(PendingRestore {
+        generation,
+        snapshot,
+    });
+    restore_after_delay(generation);
+    result
+}
+
+/// Pastes with the input backend snippet expansion uses, or enigo when it
+/// couldn't start
+pub async fn paste(app: &AppHandle, content: ClipboardContent) -> Result<(), String> {
+    let keys = match app.try_state::<Arc<dyn InputManager>>() {
+        Some(keys) => keys.inner().clone(),
+        None => {
+            Arc::new(RdevInputManager::new().map_err(|e| e.to_string())?) as Arc<dyn InputManager>
+        }
+    };
+    tauri::async_runtime::spawn_blocking(move || paste_with(keys.as_ref(), &content))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terminals_get_ctrl_shift_v() {
+        assert_eq!(
+            PasteShortcut::for_app("Alacritty"),
+            PasteShortcut::CtrlShiftV
+        );
+        assert_eq!(
+            PasteShortcut::for_app("org.kde.konsole"),
+            PasteShortcut::CtrlShiftV
+        );
+        assert_eq!(
+            PasteShortcut::for_app("org.gnome.Terminal"),
+            PasteShortcut::CtrlShiftV
+        );
+        assert_eq!(PasteShortcut::for_app("firefox"), PasteShortcut::CtrlV);
+        assert_eq!(PasteShortcut::for_app("steam"), PasteShortcut::CtrlV);
+        assert_eq!(PasteShortcut::for_app("Hyperspace"), PasteShortcut::CtrlV);
+        assert_eq!(
+            PasteShortcut::for_app("org.kde.dolphin"),
+            PasteShortcut::CtrlV
+        );
+    }
+}
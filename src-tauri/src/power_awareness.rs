@@ -0,0 +1,338 @@
+//! Backs off background work when power matters: on battery below the
+//! user's threshold, in low-power mode, or during quiet hours. Refreshers
+//! sleep through `sleep`/`scaled`, which stretch their intervals while
+//! throttled; nothing is skipped outright, only done less often.
+//!
+//! The power state is re-read at most every `STATE_TTL`, from the
+//! `power_supply` class in sysfs.
+
+use crate::error::AppError;
+use crate::system_monitors;
+use chrono::{Local, Timelike};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// Intervals are this many times longer while throttled
+const THROTTLE_FACTOR: u32 = 4;
+const STATE_TTL: Duration = Duration::from_secs(30);
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// The refreshers that go through this module, for the status view
+const THROTTLED_SERVICES: &[&str] = &[
+    "App directory checks",
+    "Clipboard sync",
+    "Currency rates",
+    "Dynamic quicklinks",
+    "Email polling",
+    "GitHub workflow watches",
+    "System monitor sampling",
+    "Wallpaper rotation",
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    /// Local time in minutes after midnight; may wrap past midnight
+    pub start_minute: u32,
+    pub end_minute: u32,
+}
+
+impl QuietHours {
+    fn contains(&self, minute: u32) -> bool {
+        if self.start_minute <= self.end_minute {
+            (self.start_minute..self.end_minute).contains(&minute)
+        } else {
+            minute >= self.start_minute || minute < self.end_minute
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerSettings {
+    #[serde(default)]
+    pub low_power_mode: bool,
+    /// Throttle on battery at or below this charge; 0 never does
+    #[serde(default = "default_battery_threshold")]
+    pub battery_threshold: u8,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+fn default_battery_threshold() -> u8 {
+    30
+}
+
+impl Default for PowerSettings {
+    fn default() -> Self {
+        Self {
+            low_power_mode: false,
+            battery_threshold: default_battery_threshold(),
+            quiet_hours: None,
+        }
+    }
+}
+
+impl PowerSettings {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("power_awareness.json"))
+    }
+
+    fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ThrottleReason {
+    LowPowerMode,
+    LowBattery,
+    QuietHours,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    pub battery_percent: Option<f64>,
+    pub throttled: bool,
+    pub reasons: Vec<ThrottleReason>,
+    /// How many times longer background intervals are right now
+    pub interval_factor: u32,
+    pub services: Vec<String>,
+    pub settings: PowerSettings,
+}
+
+struct PowerState {
+    settings: PowerSettings,
+    checked_at: Option<Instant>,
+    status: Option<PowerStatus>,
+}
+
+static STATE: Lazy<Mutex<PowerState>> = Lazy::new(|| {
+    Mutex::new(PowerState {
+        settings: PowerSettings::default(),
+        checked_at: None,
+        status: None,
+    })
+});
+
+/// True when a mains or USB supply is online. Machines without any supply
+/// listed are desktops, which count as plugged in.
+fn on_external_power(dir: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return true;
+    };
+    let mut has_battery = false;
+    for entry in entries.flatten() {
+        let read = |name: &str| {
+            fs::read_to_string(entry.path().join(name))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        match read("type").as_str() {
+            "Mains" | "USB" if read("online") == "1" => return true,
+            "Battery" => has_battery = true,
+            _ => {}
+        }
+    }
+    !has_battery
+}
+
+fn reasons(
+    settings: &PowerSettings,
+    on_battery: bool,
+    battery_percent: Option<f64>,
+    minute: u32,
+) -> Vec<ThrottleReason> {
+    let mut reasons = Vec::new();
+    if settings.low_power_mode {
+        reasons.push(ThrottleReason::LowPowerMode);
+    }
+    if on_battery
+        && settings.battery_threshold > 0
+        && battery_percent.is_some_and(|percent| percent <= settings.battery_threshold as f64)
+    {
+        reasons.push(ThrottleReason::LowBattery);
+    }
+    if settings
+        .quiet_hours
+        .is_some_and(|quiet_hours| quiet_hours.contains(minute))
+    {
+        reasons.push(ThrottleReason::QuietHours);
+    }
+    reasons
+}
+
+fn evaluate(settings: &PowerSettings) -> PowerStatus {
+    let on_battery = !on_external_power(Path::new(POWER_SUPPLY_DIR));
+    let battery_percent = system_monitors::get_battery_info()
+        .filter(|battery| battery.is_present)
+        .map(|battery| battery.percentage);
+    let now = Local::now();
+    let reasons = reasons(
+        settings,
+        on_battery,
+        battery_percent,
+        now.hour() * 60 + now.minute(),
+    );
+    let throttled = !reasons.is_empty();
+    PowerStatus {
+        on_battery,
+        battery_percent,
+        throttled,
+        reasons,
+        interval_factor: if throttled { THROTTLE_FACTOR } else { 1 },
+        services: THROTTLED_SERVICES.iter().map(|s| s.to_string()).collect(),
+        settings: settings.clone(),
+    }
+}
+
+pub fn status() -> PowerStatus {
+    let mut state = STATE.lock().unwrap();
+    if let (Some(checked_at), Some(status)) = (state.checked_at, &state.status) {
+        if checked_at.elapsed() < STATE_TTL {
+            return status.clone();
+        }
+    }
+    let status = evaluate(&state.settings);
+    state.checked_at = Some(Instant::now());
+    state.status = Some(status.clone());
+    status
+}
+
+/// `interval`, stretched while throttled
+pub fn scaled(interval: Duration) -> Duration {
+    interval * status().interval_factor
+}
+
+/// Sleeps for `interval`, or longer while throttled
+pub async fn sleep(interval: Duration) {
+    tokio::time::sleep(scaled(interval)).await;
+}
+
+fn set_settings(settings: PowerSettings) {
+    let mut state = STATE.lock().unwrap();
+    state.settings = settings;
+    state.checked_at = None;
+}
+
+pub fn init(app: &AppHandle) {
+    match PowerSettings::load(app) {
+        Ok(settings) => set_settings(settings),
+        Err(e) => tracing::warn!(error = %e, "Failed to read power settings, using defaults"),
+    }
+}
+
+#[tauri::command]
+pub fn get_power_status() -> PowerStatus {
+    status()
+}
+
+#[tauri::command]
+pub fn set_power_settings(app: AppHandle, settings: PowerSettings) -> Result<PowerStatus, String> {
+    if let Some(quiet_hours) = settings.quiet_hours {
+        if quiet_hours.start_minute >= 24 * 60 || quiet_hours.end_minute >= 24 * 60 {
+            return Err("Quiet hours must be within the day".to_string());
+        }
+    }
+    if settings.battery_threshold > 100 {
+        return Err("Battery threshold is a percentage".to_string());
+    }
+    settings.save(&app).map_err(|e| e.to_string())?;
+    set_settings(settings);
+    Ok(status())
+}
+
+/// Quick toggle for low-power mode
+#[tauri::command]
+pub fn set_low_power_mode(app: AppHandle, enabled: bool) -> Result<PowerStatus, String> {
+    let settings = PowerSettings {
+        low_power_mode: enabled,
+        ..STATE.lock().unwrap().settings.clone()
+    };
+    set_power_settings(app, settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reasons() {
+        let settings = PowerSettings {
+            quiet_hours: Some(QuietHours {
+                start_minute: 22 * 60,
+                end_minute: 7 * 60,
+            }),
+            ..Default::default()
+        };
+        assert!(reasons(&settings, false, Some(10.0), 12 * 60).is_empty());
+        assert_eq!(
+            reasons(&settings, true, Some(25.0), 12 * 60),
+            [ThrottleReason::LowBattery]
+        );
+        assert!(reasons(&settings, true, Some(80.0), 12 * 60).is_empty());
+        assert_eq!(
+            reasons(&settings, false, None, 23 * 60),
+            [ThrottleReason::QuietHours]
+        );
+
+        let low_power = PowerSettings {
+            low_power_mode: true,
+            battery_threshold: 0,
+            quiet_hours: None,
+        };
+        assert_eq!(
+            reasons(&low_power, true, Some(0.0), 0),
+            [ThrottleReason::LowPowerMode]
+        );
+    }
+
+    #[test]
+    fn test_on_external_power() {
+        let dir = std::env::temp_dir().join(format!("flare-power-{}", rand::random::<u32>()));
+        let supply = |name: &str, kind: &str, online: &str| {
+            let path = dir.join(name);
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("type"), kind).unwrap();
+            fs::write(path.join("online"), online).unwrap();
+        };
+        fs::create_dir_all(&dir).unwrap();
+        assert!(on_external_power(&dir));
+
+        supply("BAT0", "Battery\n", "");
+        supply("AC", "Mains\n", "0\n");
+        assert!(!on_external_power(&dir));
+        supply("AC", "Mains\n", "1\n");
+        assert!(on_external_power(&dir));
+        let _ = fs::remove_dir_all(dir);
+    }
+}
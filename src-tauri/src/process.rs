@@ -0,0 +1,73 @@
+//! Running processes, for extensions that list them through System Events
+//! or act on them by name.
+
+use serde::Serialize;
+use sysinfo::{ProcessesToUpdate, System};
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub exe: Option<String>,
+    pub memory_bytes: u64,
+}
+
+/// User-space processes, sorted by name. Kernel threads have no executable
+/// and are left out, as they never show up in System Events either.
+pub fn list_processes() -> Vec<ProcessInfo> {
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    let mut processes: Vec<ProcessInfo> = system
+        .processes()
+        .values()
+        .filter_map(|process| {
+            let exe = process.exe()?;
+            Some(ProcessInfo {
+                pid: process.pid().as_u32(),
+                name: process.name().to_string_lossy().into_owned(),
+                exe: Some(exe.to_string_lossy().into_owned()),
+                memory_bytes: process.memory(),
+            })
+        })
+        .collect();
+    processes.sort_by_key(|process| process.name.to_lowercase());
+    processes
+}
+
+/// Distinct process names, the way `name of every process` lists them
+pub fn process_names(processes: &[ProcessInfo]) -> Vec<String> {
+    let mut names: Vec<String> = processes
+        .iter()
+        .map(|process| process.name.clone())
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.dedup();
+    names
+}
+
+#[tauri::command]
+pub fn list_running_processes() -> Vec<ProcessInfo> {
+    list_processes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_names_are_distinct() {
+        let process = |pid: u32, name: &str| ProcessInfo {
+            pid,
+            name: name.to_string(),
+            exe: None,
+            memory_bytes: 0,
+        };
+        let processes = [
+            process(3, "firefox"),
+            process(1, "Code"),
+            process(2, "firefox"),
+        ];
+        assert_eq!(process_names(&processes), vec!["Code", "firefox"]);
+    }
+}
@@ -0,0 +1,489 @@
+//! Profiles change what the palette offers depending on where the user is.
+//! A profile applies while its app is frontmost and/or during its hours, and
+//! moves some items up (window management while a browser is focused,
+//! project commands in an editor) and hides others.
+//!
+//! Every matching profile applies, in the user's order. The palette's
+//! results go through `apply_command_overrides`, which runs the active
+//! profiles after the user's per-item overrides; pinned items stay first
+//! and are never hidden.
+
+use crate::command_registry::LaunchableItem;
+use crate::context;
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Datelike, Local, Timelike, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, Manager};
+
+const PROFILES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS profiles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    apps TEXT NOT NULL DEFAULT '[]',
+    start_minute INTEGER,
+    end_minute INTEGER,
+    days TEXT NOT NULL DEFAULT '[]',
+    boosted_items TEXT NOT NULL DEFAULT '[]',
+    hidden_items TEXT NOT NULL DEFAULT '[]',
+    position INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+)";
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub id: i64,
+    pub name: String,
+    pub enabled: bool,
+    /// App ids or WM_CLASS names, matched case-insensitively as substrings.
+    /// Empty matches whatever is frontmost.
+    pub apps: Vec<String>,
+    /// Local time in minutes after midnight; the window may wrap past
+    /// midnight. Unset means all day.
+    pub start_minute: Option<u32>,
+    pub end_minute: Option<u32>,
+    /// 0 is Monday; empty is every day
+    pub days: Vec<u8>,
+    /// Palette item ids moved to the top, in this order
+    pub boosted_items: Vec<String>,
+    pub hidden_items: Vec<String>,
+    pub position: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn from_json<T: serde::de::DeserializeOwned + Default>(
+    row: &rusqlite::Row,
+    index: usize,
+) -> RusqliteResult<T> {
+    Ok(serde_json::from_str(&row.get::<_, String>(index)?).unwrap_or_default())
+}
+
+impl Storable for Profile {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let created_at: i64 = row.get(10)?;
+        let updated_at: i64 = row.get(11)?;
+        Ok(Profile {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            enabled: row.get(2)?,
+            apps: from_json(row, 3)?,
+            start_minute: row.get(4)?,
+            end_minute: row.get(5)?,
+            days: from_json(row, 6)?,
+            boosted_items: from_json(row, 7)?,
+            hidden_items: from_json(row, 8)?,
+            position: row.get(9)?,
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_default(),
+            updated_at: DateTime::from_timestamp(updated_at, 0).unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileInput {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub apps: Vec<String>,
+    pub start_minute: Option<u32>,
+    pub end_minute: Option<u32>,
+    #[serde(default)]
+    pub days: Vec<u8>,
+    #[serde(default)]
+    pub boosted_items: Vec<String>,
+    #[serde(default)]
+    pub hidden_items: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// What profiles are matched against
+#[derive(Clone, Debug, PartialEq)]
+struct Situation {
+    app_id: Option<String>,
+    minute: u32,
+    /// 0 is Monday
+    weekday: u8,
+}
+
+impl Situation {
+    fn now() -> Self {
+        let now = Local::now();
+        Self {
+            app_id: context::active_window_info().map(|window| window.app_id),
+            minute: now.hour() * 60 + now.minute(),
+            weekday: now.weekday().num_days_from_monday() as u8,
+        }
+    }
+}
+
+impl Profile {
+    fn matches(&self, situation: &Situation) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let app_matches = self.apps.is_empty()
+            || situation.app_id.as_ref().is_some_and(|app_id| {
+                let app_id = app_id.to_lowercase();
+                self.apps
+                    .iter()
+                    .map(|app| app.trim().to_lowercase())
+                    .any(|app| !app.is_empty() && app_id.contains(&app))
+            });
+        let time_matches = match (self.start_minute, self.end_minute) {
+            (Some(start), Some(end)) if start <= end => (start..end).contains(&situation.minute),
+            (Some(start), Some(end)) => situation.minute >= start || situation.minute < end,
+            _ => true,
+        };
+        let day_matches = self.days.is_empty() || self.days.contains(&situation.weekday);
+        app_matches && time_matches && day_matches
+    }
+}
+
+fn clean_ids(ids: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    ids.iter()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty() && seen.insert(id.clone()))
+        .collect()
+}
+
+fn validate_profile(input: &ProfileInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    match (input.start_minute, input.end_minute) {
+        (Some(start), Some(end)) if start >= MINUTES_PER_DAY || end >= MINUTES_PER_DAY => {
+            return Err("Profile hours must be within the day".to_string());
+        }
+        (Some(start), Some(end)) if start == end => {
+            return Err("Profile hours must not start and end at the same time".to_string());
+        }
+        (Some(_), None) | (None, Some(_)) => {
+            return Err("Profile hours need both a start and an end".to_string());
+        }
+        _ => {}
+    }
+    if input.days.iter().any(|day| *day > 6) {
+        return Err("Profile days go from 0 (Monday) to 6 (Sunday)".to_string());
+    }
+    Ok(())
+}
+
+fn to_json(values: &[impl Serialize]) -> Result<String, AppError> {
+    serde_json::to_string(values).map_err(|e| AppError::Serialization(e.to_string()))
+}
+
+/// Boosted items move up after the pinned ones, in profile order; hidden
+/// items are dropped. The rest keep their ranked order.
+fn apply_profiles(items: Vec<LaunchableItem>, active: &[Profile]) -> Vec<LaunchableItem> {
+    if active.is_empty() {
+        return items;
+    }
+    let hidden: HashSet<&str> = active
+        .iter()
+        .flat_map(|profile| profile.hidden_items.iter().map(String::as_str))
+        .collect();
+    let mut boost_rank: HashMap<&str, usize> = HashMap::new();
+    for id in active.iter().flat_map(|profile| &profile.boosted_items) {
+        let next = boost_rank.len();
+        boost_rank.entry(id.as_str()).or_insert(next);
+    }
+
+    let mut pinned = Vec::new();
+    let mut boosted = Vec::new();
+    let mut rest = Vec::new();
+    for item in items {
+        if item.pinned {
+            pinned.push(item);
+        } else if hidden.contains(item.id.as_str()) {
+            continue;
+        } else if let Some(rank) = boost_rank.get(item.id.as_str()) {
+            boosted.push((*rank, item));
+        } else {
+            rest.push(item);
+        }
+    }
+    boosted.sort_by_key(|(rank, _)| *rank);
+    pinned
+        .into_iter()
+        .chain(boosted.into_iter().map(|(_, item)| item))
+        .chain(rest)
+        .collect()
+}
+
+const SELECT_PROFILES: &str = "SELECT id, name, enabled, apps, start_minute, end_minute, days, boosted_items, hidden_items, position, created_at, updated_at FROM profiles";
+
+pub struct ProfileManager {
+    store: Store,
+}
+
+impl ProfileManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "profiles.sqlite")?;
+        store.init_table(PROFILES_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(PROFILES_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    fn list_profiles(&self) -> Result<Vec<Profile>, AppError> {
+        self.store
+            .query(&format!("{} ORDER BY position, id", SELECT_PROFILES), [])
+    }
+
+    fn create_profile(&self, input: ProfileInput) -> Result<i64, AppError> {
+        let now = Utc::now().timestamp();
+        self.store.execute(
+            "INSERT INTO profiles (name, enabled, apps, start_minute, end_minute, days, boosted_items, hidden_items, position, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, (SELECT COALESCE(MAX(position), -1) + 1 FROM profiles), ?9, ?9)",
+            params![
+                input.name.trim(),
+                input.enabled,
+                to_json(&clean_ids(&input.apps))?,
+                input.start_minute,
+                input.end_minute,
+                to_json(&input.days)?,
+                to_json(&clean_ids(&input.boosted_items))?,
+                to_json(&clean_ids(&input.hidden_items))?,
+                now
+            ],
+        )?;
+        Ok(self.store.last_insert_rowid())
+    }
+
+    fn update_profile(&self, id: i64, input: ProfileInput) -> Result<(), AppError> {
+        let changed = self.store.execute(
+            "UPDATE profiles SET name = ?, enabled = ?, apps = ?, start_minute = ?, end_minute = ?, days = ?, boosted_items = ?, hidden_items = ?, updated_at = ?
+             WHERE id = ?",
+            params![
+                input.name.trim(),
+                input.enabled,
+                to_json(&clean_ids(&input.apps))?,
+                input.start_minute,
+                input.end_minute,
+                to_json(&input.days)?,
+                to_json(&clean_ids(&input.boosted_items))?,
+                to_json(&clean_ids(&input.hidden_items))?,
+                Utc::now().timestamp(),
+                id
+            ],
+        )?;
+        if changed == 0 {
+            return Err(AppError::Rusqlite(rusqlite::Error::QueryReturnedNoRows));
+        }
+        Ok(())
+    }
+
+    fn delete_profile(&self, id: i64) -> Result<(), AppError> {
+        self.store
+            .execute("DELETE FROM profiles WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    /// Sets the order profiles apply in; profiles not listed keep theirs
+    /// after the listed ones
+    fn reorder_profiles(&self, ids: &[i64]) -> Result<(), AppError> {
+        let mut conn = self.store.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE profiles SET position = position + ?",
+            params![ids.len() as i64],
+        )?;
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE profiles SET position = ? WHERE id = ?",
+                params![position as i64, id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn active_profiles(&self, situation: &Situation) -> Result<Vec<Profile>, AppError> {
+        Ok(self
+            .list_profiles()?
+            .into_iter()
+            .filter(|profile| profile.matches(situation))
+            .collect())
+    }
+}
+
+/// The last step of `apply_command_overrides`
+pub fn apply_active_profiles(
+    app: &AppHandle,
+    items: Vec<LaunchableItem>,
+) -> Result<Vec<LaunchableItem>, String> {
+    let Some(manager) = app.try_state::<ProfileManager>() else {
+        return Ok(items);
+    };
+    let active = manager
+        .active_profiles(&Situation::now())
+        .map_err(|e| e.to_string())?;
+    Ok(apply_profiles(items, &active))
+}
+
+#[tauri::command]
+pub fn list_profiles(app: AppHandle) -> Result<Vec<Profile>, String> {
+    app.state::<ProfileManager>()
+        .list_profiles()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_profile(app: AppHandle, profile: ProfileInput) -> Result<i64, String> {
+    validate_profile(&profile)?;
+    app.state::<ProfileManager>()
+        .create_profile(profile)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_profile(app: AppHandle, id: i64, profile: ProfileInput) -> Result<(), String> {
+    validate_profile(&profile)?;
+    app.state::<ProfileManager>()
+        .update_profile(id, profile)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_profile(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<ProfileManager>()
+        .delete_profile(id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn reorder_profiles(app: AppHandle, ids: Vec<i64>) -> Result<(), String> {
+    app.state::<ProfileManager>()
+        .reorder_profiles(&ids)
+        .map_err(|e| e.to_string())
+}
+
+/// The profiles that apply right now, for showing in the palette
+#[tauri::command]
+pub fn get_active_profiles(app: AppHandle) -> Result<Vec<Profile>, String> {
+    app.state::<ProfileManager>()
+        .active_profiles(&Situation::now())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_registry::CommandKind;
+
+    fn item(id: &str, pinned: bool) -> LaunchableItem {
+        LaunchableItem {
+            id: id.to_string(),
+            kind: CommandKind::Builtin,
+            title: id.to_string(),
+            icon: None,
+            pinned,
+            favorite: false,
+        }
+    }
+
+    fn situation(app_id: &str, minute: u32, weekday: u8) -> Situation {
+        Situation {
+            app_id: Some(app_id.to_string()),
+            minute,
+            weekday,
+        }
+    }
+
+    fn browsing() -> ProfileInput {
+        ProfileInput {
+            name: "Browsing".to_string(),
+            enabled: true,
+            apps: vec!["Firefox".to_string(), "chromium".to_string()],
+            boosted_items: vec!["builtin:window-left".to_string()],
+            hidden_items: vec!["builtin:snippets".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matching_by_app_and_hours() {
+        let manager = ProfileManager::new_for_test().unwrap();
+        manager.create_profile(browsing()).unwrap();
+        manager
+            .create_profile(ProfileInput {
+                name: "Late".to_string(),
+                enabled: true,
+                start_minute: Some(22 * 60),
+                end_minute: Some(6 * 60),
+                days: vec![0, 1, 2, 3, 4],
+                ..Default::default()
+            })
+            .unwrap();
+
+        let names = |situation: Situation| -> Vec<String> {
+            manager
+                .active_profiles(&situation)
+                .unwrap()
+                .into_iter()
+                .map(|profile| profile.name)
+                .collect()
+        };
+        assert_eq!(names(situation("firefox", 12 * 60, 0)), ["Browsing"]);
+        assert_eq!(names(situation("org.gnome.Nautilus", 23 * 60, 2)), ["Late"]);
+        assert_eq!(names(situation("firefox", 5 * 60, 4)), ["Browsing", "Late"]);
+        assert!(names(situation("code", 23 * 60, 6)).is_empty());
+    }
+
+    #[test]
+    fn test_apply_boosts_and_hides_after_pins() {
+        let manager = ProfileManager::new_for_test().unwrap();
+        manager.create_profile(browsing()).unwrap();
+        let active = manager
+            .active_profiles(&situation("firefox", 0, 0))
+            .unwrap();
+
+        let items = vec![
+            item("builtin:pinned", true),
+            item("builtin:snippets", false),
+            item("builtin:clipboard", false),
+            item("builtin:window-left", false),
+        ];
+        let ids: Vec<String> = apply_profiles(items, &active)
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        assert_eq!(
+            ids,
+            ["builtin:pinned", "builtin:window-left", "builtin:clipboard"]
+        );
+    }
+
+    #[test]
+    fn test_validate_profile() {
+        assert!(validate_profile(&browsing()).is_ok());
+        let half_hours = ProfileInput {
+            start_minute: Some(60),
+            ..browsing()
+        };
+        assert!(validate_profile(&half_hours).is_err());
+        let bad_day = ProfileInput {
+            days: vec![7],
+            ..browsing()
+        };
+        assert!(validate_profile(&bad_day).is_err());
+    }
+}
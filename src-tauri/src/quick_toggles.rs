@@ -1,56 +1,90 @@
+use crate::dbus::{bluez, logind, network_manager, settings_portal};
+use crate::exec;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
+
+/// The toggle states are polled while the view is open
+const STATE_TTL: Duration = Duration::from_secs(2);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToggleState {
     pub enabled: bool,
 }
 
-/// Toggle WiFi on/off via NetworkManager D-Bus
+/// Toggle WiFi on/off via NetworkManager D-Bus, or nmcli without it
 pub async fn toggle_wifi(enable: bool) -> Result<(), String> {
-    // Use nmcli command as a simpler alternative to D-Bus for now
+    match network_manager::set_wireless_enabled(enable).await {
+        Ok(()) => return Ok(()),
+        Err(e) => tracing::debug!(error = %e, "Falling back to nmcli to toggle WiFi"),
+    }
     let status = if enable { "on" } else { "off" };
     
-    std::process::Command::new("nmcli")
-        .args(&["radio", "wifi", status])
-        .output()
+    exec::run_async("nmcli", &["radio", "wifi", status])
+        .await
         .map_err(|e| format!("Failed to toggle WiFi (is NetworkManager installed?): {}", e))?;
+    exec::invalidate("nmcli");
     
     Ok(())
 }
 
 /// Get WiFi state via NetworkManager
 pub async fn get_wifi_state() -> Result<bool, String> {
-    let output = std::process::Command::new("nmcli")
-        .args(&["radio", "wifi"])
-        .output()
+    if let Ok(enabled) = network_manager::wireless_enabled().await {
+        return Ok(enabled);
+    }
+    let output = exec::cached_async("nmcli", &["radio", "wifi"], STATE_TTL)
+        .await
         .map_err(|e| format!("Failed to get WiFi state: {}", e))?;
     
-    let state = String::from_utf8_lossy(&output.stdout);
-    Ok(state.trim() == "enabled")
+    Ok(output.stdout.trim() == "enabled")
 }
 
-/// Toggle Bluetooth on/off via rfkill
-pub async fn toggle_bluetooth(enable: bool) -> Result<(), String> {
-    let action = if enable { "unblock" } else { "block" };
-    
-    std::process::Command::new("rfkill")
-        .args(&[action, "bluetooth"])
-        .output()
+/// Whether rfkill soft-blocks a Bluetooth radio, from sysfs
+fn bluetooth_soft_blocked() -> bool {
+    let Ok(entries) = fs::read_dir("/sys/class/rfkill") else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let read = |name: &str| fs::read_to_string(entry.path().join(name)).unwrap_or_default();
+        read("type").trim() == "bluetooth" && read("soft").trim() == "1"
+    })
+}
+
+async fn rfkill_bluetooth(action: &str) -> Result<(), String> {
+    exec::run_async("rfkill", &[action, "bluetooth"])
+        .await
         .map_err(|e| format!("Failed to toggle Bluetooth (is rfkill installed?): {}", e))?;
-    
+    exec::invalidate("rfkill");
     Ok(())
 }
 
-/// Get Bluetooth state via rfkill
+/// Toggle Bluetooth by powering the BlueZ adapters, or with rfkill when
+/// BlueZ isn't running
+pub async fn toggle_bluetooth(enable: bool) -> Result<(), String> {
+    // BlueZ can't power on an adapter rfkill blocks
+    if enable && bluetooth_soft_blocked() {
+        rfkill_bluetooth("unblock").await?;
+    }
+    match bluez::set_powered(enable).await {
+        Ok(adapters) if adapters > 0 => return Ok(()),
+        Ok(_) => {}
+        Err(e) => tracing::debug!(error = %e, "Falling back to rfkill to toggle Bluetooth"),
+    }
+    rfkill_bluetooth(if enable { "unblock" } else { "block" }).await
+}
+
+/// Get Bluetooth state from BlueZ, or rfkill
 pub async fn get_bluetooth_state() -> Result<bool, String> {
-    let output = std::process::Command::new("rfkill")
-        .args(&["list", "bluetooth"])
-        .output()
+    if let Ok(Some(powered)) = bluez::powered().await {
+        return Ok(powered);
+    }
+    let output = exec::cached_async("rfkill", &["list", "bluetooth"], STATE_TTL)
+        .await
         .map_err(|e| format!("Failed to get Bluetooth state: {}", e))?;
     
-    let state = String::from_utf8_lossy(&output.stdout);
+    let state = output.stdout;
     // If output contains "Soft blocked: no" and "Hard blocked: no", Bluetooth is enabled
     Ok(!state.contains("Soft blocked: yes") && !state.contains("Hard blocked: yes"))
 }
@@ -85,8 +119,12 @@ pub async fn toggle_dark_mode(enable: bool) -> Result<(), String> {
     }
 }
 
-/// Get dark mode state based on desktop environment
+/// Get dark mode state from the settings portal, or the desktop
+/// environment's own settings
 pub async fn get_dark_mode_state() -> Result<bool, String> {
+    if let Ok(Some(dark)) = settings_portal::prefers_dark().await {
+        return Ok(dark);
+    }
     let de = detect_desktop_environment().ok_or("Could not detect desktop environment")?;
     
     if de.contains("gnome") || de.contains("ubuntu") {
@@ -103,30 +141,25 @@ pub async fn get_dark_mode_state() -> Result<bool, String> {
 fn toggle_gnome_dark_mode(enable: bool) -> Result<(), String> {
     let color_scheme = if enable { "prefer-dark" } else { "default" };
     
-    std::process::Command::new("gsettings")
-        .args(&[
-            "set",
-            "org.gnome.desktop.interface",
-            "color-scheme",
-            color_scheme,
-        ])
-        .output()
-        .map_err(|e| format!("Failed to toggle GNOME dark mode: {}", e))?;
+    exec::run(
+        "gsettings",
+        &["set", "org.gnome.desktop.interface", "color-scheme", color_scheme],
+    )
+    .map_err(|e| format!("Failed to toggle GNOME dark mode: {}", e))?;
+    exec::invalidate("gsettings");
     
     Ok(())
 }
 
 fn get_gnome_dark_mode_state() -> Result<bool, String> {
-    let output = std::process::Command::new("gsettings")
-        .args(&[
-            "get",
-            "org.gnome.desktop.interface",
-            "color-scheme",
-        ])
-        .output()
-        .map_err(|e| format!("Failed to get GNOME color scheme: {}", e))?;
+    let output = exec::cached(
+        "gsettings",
+        &["get", "org.gnome.desktop.interface", "color-scheme"],
+        STATE_TTL,
+    )
+    .map_err(|e| format!("Failed to get GNOME color scheme: {}", e))?;
     
-    let scheme = String::from_utf8_lossy(&output.stdout);
+    let scheme = output.stdout;
     Ok(scheme.contains("dark"))
 }
 
@@ -138,61 +171,53 @@ fn toggle_kde_dark_mode(enable: bool) -> Result<(), String> {
         "org.kde.breeze.desktop"
     };
     
-    std::process::Command::new("lookandfeeltool")
-        .args(&["-a", theme])
-        .output()
+    exec::run("lookandfeeltool", &["-a", theme])
         .map_err(|e| format!("Failed to toggle KDE dark mode: {}", e))?;
+    exec::invalidate("kreadconfig5");
     
     Ok(())
 }
 
 fn get_kde_dark_mode_state() -> Result<bool, String> {
-    let output = std::process::Command::new("kreadconfig5")
-        .args(&[
-            "--file",
-            "kdeglobals",
-            "--group",
-            "General",
-            "--key",
-            "ColorScheme",
-        ])
-        .output()
-        .map_err(|e| format!("Failed to get KDE color scheme: {}", e))?;
+    let output = exec::cached(
+        "kreadconfig5",
+        &["--file", "kdeglobals", "--group", "General", "--key", "ColorScheme"],
+        STATE_TTL,
+    )
+    .map_err(|e| format!("Failed to get KDE color scheme: {}", e))?;
     
-    let scheme = String::from_utf8_lossy(&output.stdout);
+    let scheme = output.stdout;
     Ok(scheme.to_lowercase().contains("dark"))
 }
 
 fn toggle_xfce_dark_mode(enable: bool) -> Result<(), String> {
     let theme = if enable { "Adwaita-dark" } else { "Adwaita" };
     
-    std::process::Command::new("xfconf-query")
-        .args(&[
-            "-c", "xsettings",
-            "-p", "/Net/ThemeName",
-            "-s", theme,
-        ])
-        .output()
-        .map_err(|e| format!("Failed to toggle XFCE dark mode: {}", e))?;
+    exec::run(
+        "xfconf-query",
+        &["-c", "xsettings", "-p", "/Net/ThemeName", "-s", theme],
+    )
+    .map_err(|e| format!("Failed to toggle XFCE dark mode: {}", e))?;
+    exec::invalidate("xfconf-query");
     
     Ok(())
 }
 
 fn get_xfce_dark_mode_state() -> Result<bool, String> {
-    let output = std::process::Command::new("xfconf-query")
-        .args(&[
-            "-c", "xsettings",
-            "-p", "/Net/ThemeName",
-        ])
-        .output()
-        .map_err(|e| format!("Failed to get XFCE theme: {}", e))?;
+    let output = exec::cached(
+        "xfconf-query",
+        &["-c", "xsettings", "-p", "/Net/ThemeName"],
+        STATE_TTL,
+    )
+    .map_err(|e| format!("Failed to get XFCE theme: {}", e))?;
     
-    let theme = String::from_utf8_lossy(&output.stdout);
+    let theme = output.stdout;
     Ok(theme.to_lowercase().contains("dark"))
 }
 
-/// Set screen brightness (0-100)
-pub fn set_brightness(percentage: u32) -> Result<(), String> {
+/// Set screen brightness (0-100), through logind when it can, which needs no
+/// write access to the backlight
+pub async fn set_brightness(percentage: u32) -> Result<(), String> {
     let percentage = percentage.clamp(0, 100);
     
     // Find backlight device
@@ -219,6 +244,11 @@ pub fn set_brightness(percentage: u32) -> Result<(), String> {
             
             let target_brightness = (max_brightness as f64 * (percentage as f64 / 100.0)) as u32;
             
+            let device = entry.file_name().to_string_lossy().into_owned();
+            match logind::set_brightness("backlight", &device, target_brightness).await {
+                Ok(()) => return Ok(()),
+                Err(e) => tracing::debug!(error = %e, "Falling back to sysfs to set brightness"),
+            }
             fs::write(&brightness_path, target_brightness.to_string())
                 .map_err(|e| format!("Failed to set brightness: {}. You may need appropriate permissions (try adding user to 'video' group).", e))?;
             
@@ -0,0 +1,574 @@
+//! Dynamic quicklinks: a user script prints a JSON list of links, and its
+//! output replaces that source's quicklinks every refresh interval. The
+//! script gets `sh -c`, so it can be a one-liner or the path to a file.
+//!
+//! The output is either `[{"name": .., "link": ..}, ..]` or the same list
+//! under `"items"`; `application`, `icon`, `group` and `tags` are optional.
+
+use super::{normalize_group, replace_tags, QuicklinkManager};
+use crate::error::AppError;
+use crate::power_awareness;
+use crate::store::Storable;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub(super) const DYNAMIC_QUICKLINKS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS dynamic_quicklinks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    script TEXT NOT NULL,
+    group_name TEXT,
+    refresh_interval_secs INTEGER NOT NULL,
+    last_refreshed_at INTEGER,
+    last_error TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+)";
+const SELECT_SOURCES: &str = "SELECT id, name, script, group_name, refresh_interval_secs, last_refreshed_at, last_error, created_at, updated_at FROM dynamic_quicklinks";
+
+const DEFAULT_REFRESH_INTERVAL: u64 = 60 * 60;
+/// Scripts are usually cheap, but nothing needs refreshing more often
+const MIN_REFRESH_INTERVAL: u64 = 60;
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(30);
+/// More than this many links from one script is almost certainly a mistake
+const MAX_ITEMS: usize = 500;
+
+/// Sources being refreshed, so the timer and a manual refresh don't race
+static REFRESHING: Lazy<Mutex<HashSet<i64>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DynamicQuicklink {
+    pub id: i64,
+    pub name: String,
+    pub script: String,
+    pub group: Option<String>,
+    pub refresh_interval_secs: u64,
+    /// Unix seconds of the last run, successful or not
+    pub last_refreshed_at: Option<i64>,
+    /// Why the last run failed; cleared by the next one that succeeds
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl Storable for DynamicQuicklink {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        Ok(DynamicQuicklink {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            script: row.get(2)?,
+            group: row.get(3)?,
+            refresh_interval_secs: row.get(4)?,
+            last_refreshed_at: row.get(5)?,
+            last_error: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+        })
+    }
+}
+
+impl DynamicQuicklink {
+    fn is_due(&self, now: i64) -> bool {
+        self.last_refreshed_at
+            .is_none_or(|last| now - last >= self.refresh_interval_secs as i64)
+    }
+
+    /// Links the script doesn't put in a group go in the source's group, or
+    /// one named after the source
+    fn default_group(&self) -> String {
+        self.group.clone().unwrap_or_else(|| self.name.clone())
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DynamicQuicklinkInput {
+    pub name: String,
+    pub script: String,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub refresh_interval_secs: Option<u64>,
+}
+
+impl DynamicQuicklinkInput {
+    fn refresh_interval(&self) -> u64 {
+        self.refresh_interval_secs
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL)
+            .max(MIN_REFRESH_INTERVAL)
+    }
+}
+
+/// One link printed by a script
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ScriptItem {
+    name: String,
+    link: String,
+    #[serde(default)]
+    application: Option<String>,
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn validate_input(input: &DynamicQuicklinkInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("Name is required".to_string());
+    }
+    if input.script.trim().is_empty() {
+        return Err("Script is required".to_string());
+    }
+    Ok(())
+}
+
+/// The links in a script's output; the first link wins when several share
+/// a URL
+fn parse_output(stdout: &str) -> Result<Vec<ScriptItem>, String> {
+    let value: Value = serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("Script didn't print JSON: {}", e))?;
+    let items = match value {
+        Value::Array(_) => value,
+        Value::Object(mut object) => object
+            .remove("items")
+            .ok_or("Expected a list of links or an \"items\" list")?,
+        _ => return Err("Expected a list of links".to_string()),
+    };
+    let items: Vec<ScriptItem> =
+        serde_json::from_value(items).map_err(|e| format!("Invalid link: {}", e))?;
+    if items.len() > MAX_ITEMS {
+        return Err(format!(
+            "Script printed {} links, more than the {} allowed",
+            items.len(),
+            MAX_ITEMS
+        ));
+    }
+
+    let mut seen = HashSet::new();
+    Ok(items
+        .into_iter()
+        .filter(|item| !item.name.trim().is_empty() && !item.link.trim().is_empty())
+        .filter(|item| seen.insert(item.link.clone()))
+        .collect())
+}
+
+async fn run_script(script: &str) -> Result<String, String> {
+    let child = tokio::process::Command::new("sh")
+        // `$0` is the name shown in process listings
+        .arg("-c")
+        .arg(script)
+        .arg("flare-quicklinks")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run script: {}", e))?;
+    let output = tokio::time::timeout(SCRIPT_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| format!("Script took longer than {}s", SCRIPT_TIMEOUT.as_secs()))?
+        .map_err(|e| format!("Failed to run script: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => format!("Script failed with {}", output.status),
+            stderr => format!("Script failed: {}", stderr),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl QuicklinkManager {
+    fn create_dynamic(&self, input: &DynamicQuicklinkInput) -> Result<i64, AppError> {
+        let now = Utc::now().timestamp();
+        self.store.execute(
+            "INSERT INTO dynamic_quicklinks (name, script, group_name, refresh_interval_secs, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                input.name.trim(),
+                input.script,
+                normalize_group(input.group.as_deref()),
+                input.refresh_interval(),
+                now,
+                now
+            ],
+        )?;
+        Ok(self.store.last_insert_rowid())
+    }
+
+    fn list_dynamic(&self) -> Result<Vec<DynamicQuicklink>, AppError> {
+        self.store.query(
+            &format!("{} ORDER BY name COLLATE NOCASE", SELECT_SOURCES),
+            [],
+        )
+    }
+
+    fn get_dynamic(&self, id: i64) -> Result<Option<DynamicQuicklink>, AppError> {
+        self.store
+            .query_row(&format!("{} WHERE id = ?", SELECT_SOURCES), params![id])
+    }
+
+    /// Changing the script or interval makes the source due straight away
+    fn update_dynamic(&self, id: i64, input: &DynamicQuicklinkInput) -> Result<(), AppError> {
+        let now = Utc::now().timestamp();
+        self.store.execute(
+            "UPDATE dynamic_quicklinks SET name = ?, script = ?, group_name = ?, refresh_interval_secs = ?,
+             last_refreshed_at = NULL, updated_at = ? WHERE id = ?",
+            params![
+                input.name.trim(),
+                input.script,
+                normalize_group(input.group.as_deref()),
+                input.refresh_interval(),
+                now,
+                id
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes the source and every link it made
+    fn delete_dynamic(&self, id: i64) -> Result<(), AppError> {
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        tx.execute(
+            "DELETE FROM quicklink_tags WHERE quicklink_id IN (SELECT id FROM quicklinks WHERE source_id = ?)",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM quicklinks WHERE source_id = ?", params![id])?;
+        tx.execute("DELETE FROM dynamic_quicklinks WHERE id = ?", params![id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn due_dynamic(&self, now: i64) -> Result<Vec<DynamicQuicklink>, AppError> {
+        Ok(self
+            .list_dynamic()?
+            .into_iter()
+            .filter(|source| source.is_due(now))
+            .collect())
+    }
+
+    /// Makes `source`'s links match `items`. Links are matched up by URL, so
+    /// ones that survive a refresh keep their id (and with it their frecency).
+    fn replace_items(
+        &self,
+        source: &DynamicQuicklink,
+        items: &[ScriptItem],
+        now: i64,
+    ) -> Result<(), AppError> {
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        let mut existing: HashMap<String, i64> = HashMap::new();
+        {
+            let mut stmt = tx.prepare("SELECT link, id FROM quicklinks WHERE source_id = ?")?;
+            let mut rows = stmt.query(params![source.id])?;
+            while let Some(row) = rows.next()? {
+                existing.insert(row.get(0)?, row.get(1)?);
+            }
+        }
+
+        let mut kept = HashSet::new();
+        for item in items {
+            let group =
+                normalize_group(item.group.as_deref()).unwrap_or_else(|| source.default_group());
+            let id = match existing.get(&item.link) {
+                Some(&id) => {
+                    tx.execute(
+                        "UPDATE quicklinks SET name = ?, application = ?, icon = ?, group_name = ?, updated_at = ?
+                         WHERE id = ?",
+                        params![item.name, item.application, item.icon, group, now, id],
+                    )?;
+                    id
+                }
+                None => {
+                    tx.execute(
+                        "INSERT INTO quicklinks (name, link, application, icon, group_name, source_id, created_at, updated_at)
+                         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                        params![
+                            item.name,
+                            item.link,
+                            item.application,
+                            item.icon,
+                            group,
+                            source.id,
+                            now,
+                            now
+                        ],
+                    )?;
+                    tx.last_insert_rowid()
+                }
+            };
+            replace_tags(&tx, id, &item.tags)?;
+            kept.insert(id);
+        }
+
+        for id in existing.values().filter(|id| !kept.contains(id)) {
+            tx.execute(
+                "DELETE FROM quicklink_tags WHERE quicklink_id = ?",
+                params![id],
+            )?;
+            tx.execute("DELETE FROM quicklinks WHERE id = ?", params![id])?;
+        }
+        tx.execute(
+            "UPDATE dynamic_quicklinks SET last_refreshed_at = ?, last_error = NULL WHERE id = ?",
+            params![now, source.id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Keeps the links from the last good run, so a flaky script doesn't
+    /// empty its group
+    fn record_failure(&self, id: i64, error: &str, now: i64) -> Result<(), AppError> {
+        self.store.execute(
+            "UPDATE dynamic_quicklinks SET last_refreshed_at = ?, last_error = ? WHERE id = ?",
+            params![now, error, id],
+        )?;
+        Ok(())
+    }
+}
+
+/// Runs the source's script and replaces its links; returns how many it
+/// printed
+async fn refresh(app: &AppHandle, id: i64) -> Result<usize, String> {
+    if !REFRESHING
+        .lock()
+        .is_ok_and(|mut refreshing| refreshing.insert(id))
+    {
+        return Err("Already refreshing".to_string());
+    }
+    let result = run_and_store(app, id).await;
+    if let Ok(mut refreshing) = REFRESHING.lock() {
+        refreshing.remove(&id);
+    }
+    let _ = app.emit("quicklinks-changed", ());
+    result
+}
+
+async fn run_and_store(app: &AppHandle, id: i64) -> Result<usize, String> {
+    let source = app
+        .state::<QuicklinkManager>()
+        .get_dynamic(id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Dynamic quicklink not found")?;
+
+    let items = match run_script(&source.script).await {
+        Ok(stdout) => parse_output(&stdout),
+        Err(e) => Err(e),
+    };
+    let manager = app.state::<QuicklinkManager>();
+    let now = Utc::now().timestamp();
+    match items {
+        Ok(items) => {
+            manager
+                .replace_items(&source, &items, now)
+                .map_err(|e| e.to_string())?;
+            Ok(items.len())
+        }
+        Err(e) => {
+            tracing::warn!(id, name = %source.name, error = %e, "Dynamic quicklink refresh failed");
+            manager
+                .record_failure(id, &e, now)
+                .map_err(|e| e.to_string())?;
+            Err(e)
+        }
+    }
+}
+
+/// Starts the timer that refreshes dynamic quicklinks as they come due.
+/// Needs the `QuicklinkManager` to be managed already.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let due = app
+                .state::<QuicklinkManager>()
+                .due_dynamic(Utc::now().timestamp());
+            match due {
+                Ok(due) => {
+                    for source in due {
+                        // Failures are recorded on the source
+                        let _ = refresh(&app, source.id).await;
+                    }
+                }
+                Err(e) => tracing::error!(error = %e, "Failed to check dynamic quicklinks"),
+            }
+            power_awareness::sleep(TICK_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn list_dynamic_quicklinks(app: AppHandle) -> Result<Vec<DynamicQuicklink>, String> {
+    app.state::<QuicklinkManager>()
+        .list_dynamic()
+        .map_err(|e| e.to_string())
+}
+
+/// Creates the source and runs its script once
+#[tauri::command]
+pub async fn create_dynamic_quicklink(
+    app: AppHandle,
+    input: DynamicQuicklinkInput,
+) -> Result<DynamicQuicklink, String> {
+    validate_input(&input)?;
+    let id = app
+        .state::<QuicklinkManager>()
+        .create_dynamic(&input)
+        .map_err(|e| e.to_string())?;
+    let _ = refresh(&app, id).await;
+    app.state::<QuicklinkManager>()
+        .get_dynamic(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Dynamic quicklink not found".to_string())
+}
+
+#[tauri::command]
+pub async fn update_dynamic_quicklink(
+    app: AppHandle,
+    id: i64,
+    input: DynamicQuicklinkInput,
+) -> Result<(), String> {
+    validate_input(&input)?;
+    app.state::<QuicklinkManager>()
+        .update_dynamic(id, &input)
+        .map_err(|e| e.to_string())?;
+    let _ = refresh(&app, id).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_dynamic_quicklink(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<QuicklinkManager>()
+        .delete_dynamic(id)
+        .map_err(|e| e.to_string())?;
+    let _ = app.emit("quicklinks-changed", ());
+    Ok(())
+}
+
+/// Runs the script now; returns how many links it printed
+#[tauri::command]
+pub async fn refresh_dynamic_quicklink(app: AppHandle, id: i64) -> Result<usize, String> {
+    refresh(&app, id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, link: &str) -> ScriptItem {
+        ScriptItem {
+            name: name.to_string(),
+            link: link.to_string(),
+            application: None,
+            icon: None,
+            group: None,
+            tags: Vec::new(),
+        }
+    }
+
+    fn source_input(group: Option<&str>) -> DynamicQuicklinkInput {
+        DynamicQuicklinkInput {
+            name: "Projects".to_string(),
+            script: "ls ~/src".to_string(),
+            group: group.map(String::from),
+            refresh_interval_secs: Some(5),
+        }
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let items = parse_output(
+            r#"[{"name": "flare", "link": "https://github.com/o/flare", "tags": ["rust"]},
+                {"name": "dup", "link": "https://github.com/o/flare"},
+                {"name": "", "link": "https://x"}]"#,
+        )
+        .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].tags, vec!["rust"]);
+
+        let items = parse_output(r#"{"items": [{"name": "a", "link": "/tmp"}]}"#).unwrap();
+        assert_eq!(items, vec![item("a", "/tmp")]);
+
+        assert!(parse_output("not json").is_err());
+        assert!(parse_output(r#"{"links": []}"#).is_err());
+        assert!(parse_output(r#"[{"name": "a"}]"#).is_err());
+    }
+
+    #[test]
+    fn test_refresh_interval_is_clamped() {
+        assert_eq!(source_input(None).refresh_interval(), MIN_REFRESH_INTERVAL);
+        let mut input = source_input(None);
+        input.refresh_interval_secs = None;
+        assert_eq!(input.refresh_interval(), DEFAULT_REFRESH_INTERVAL);
+    }
+
+    #[test]
+    fn test_replace_items_keeps_ids_by_link() {
+        let manager = QuicklinkManager::new_in_memory().unwrap();
+        let id = manager.create_dynamic(&source_input(None)).unwrap();
+        let source = manager.get_dynamic(id).unwrap().unwrap();
+        assert!(source.is_due(0));
+
+        manager
+            .replace_items(&source, &[item("a", "/a"), item("b", "/b")], 100)
+            .unwrap();
+        let first = manager.list_quicklinks(Some("Projects"), None).unwrap();
+        assert_eq!(first.len(), 2);
+        assert!(first.iter().all(|link| link.source_id == Some(id)));
+
+        let mut renamed = item("b, renamed", "/b");
+        renamed.tags = vec!["moved".to_string()];
+        manager
+            .replace_items(&source, &[renamed, item("c", "/c")], 200)
+            .unwrap();
+        let second = manager.list_quicklinks(None, None).unwrap();
+        let names: Vec<_> = second.iter().map(|link| link.name.as_str()).collect();
+        assert_eq!(names, vec!["b, renamed", "c"]);
+        assert_eq!(second[0].id, first[1].id);
+        assert_eq!(second[0].tags, vec!["moved"]);
+
+        let source = manager.get_dynamic(id).unwrap().unwrap();
+        assert_eq!(source.last_refreshed_at, Some(200));
+        assert!(!source.is_due(200 + MIN_REFRESH_INTERVAL as i64 - 1));
+        assert!(source.is_due(200 + MIN_REFRESH_INTERVAL as i64));
+
+        manager.record_failure(id, "boom", 300).unwrap();
+        assert_eq!(manager.list_quicklinks(None, None).unwrap().len(), 2);
+
+        manager.delete_dynamic(id).unwrap();
+        assert!(manager.list_quicklinks(None, None).unwrap().is_empty());
+        assert!(manager.list_tags().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_items_use_the_source_group() {
+        let manager = QuicklinkManager::new_in_memory().unwrap();
+        let id = manager.create_dynamic(&source_input(Some("Work"))).unwrap();
+        let source = manager.get_dynamic(id).unwrap().unwrap();
+        let mut own = item("own", "/own");
+        own.group = Some("Elsewhere".to_string());
+        manager
+            .replace_items(&source, &[item("a", "/a"), own], 100)
+            .unwrap();
+        let groups: Vec<_> = manager
+            .list_groups()
+            .unwrap()
+            .into_iter()
+            .map(|group| group.name)
+            .collect();
+        assert_eq!(groups, vec!["Elsewhere", "Work"]);
+    }
+}
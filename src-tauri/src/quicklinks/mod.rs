@@ -0,0 +1,399 @@
+pub mod dynamic;
+
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_opener::{open_path, open_url};
+
+const QUICKLINKS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS quicklinks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    link TEXT NOT NULL,
+    application TEXT,
+    icon TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+)";
+const TAGS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS quicklink_tags (
+    quicklink_id INTEGER NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (quicklink_id, tag)
+)";
+
+/// Columns in the order `Storable for Quicklink` reads them; tags are joined
+/// with the unit separator, which can't appear in a tag
+const SELECT_QUICKLINKS: &str = "SELECT q.id, q.name, q.link, q.application, q.icon, q.created_at, q.updated_at, q.group_name, q.source_id,
+    (SELECT group_concat(tag, char(31)) FROM quicklink_tags WHERE quicklink_id = q.id)
+    FROM quicklinks q";
+const TAG_SEPARATOR: char = '\u{1f}';
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Quicklink {
+    id: i64,
+    name: String,
+    link: String,
+    application: Option<String>,
+    icon: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    group: Option<String>,
+    tags: Vec<String>,
+    /// The dynamic quicklink whose script produced this one; those are
+    /// replaced on every refresh rather than edited
+    source_id: Option<i64>,
+}
+
+impl Storable for Quicklink {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let created_at_ts: i64 = row.get(5)?;
+        let updated_at_ts: i64 = row.get(6)?;
+        let tags: Option<String> = row.get(9)?;
+        let mut tags: Vec<String> = tags
+            .map(|tags| tags.split(TAG_SEPARATOR).map(String::from).collect())
+            .unwrap_or_default();
+        tags.sort();
+        Ok(Quicklink {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            link: row.get(2)?,
+            application: row.get(3)?,
+            icon: row.get(4)?,
+            created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
+            updated_at: DateTime::from_timestamp(updated_at_ts, 0).unwrap_or_default(),
+            group: row.get(7)?,
+            tags,
+            source_id: row.get(8)?,
+        })
+    }
+}
+
+/// The editable fields of a quicklink
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuicklinkInput {
+    pub name: String,
+    pub link: String,
+    #[serde(default)]
+    pub application: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuicklinkGroup {
+    pub name: String,
+    pub quicklink_count: i64,
+}
+
+fn normalize_tags(tags: &[String]) -> BTreeSet<String> {
+    tags.iter()
+        .map(|tag| tag.replace(TAG_SEPARATOR, "").trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// A blank group is no group
+fn normalize_group(group: Option<&str>) -> Option<String> {
+    group
+        .map(str::trim)
+        .filter(|group| !group.is_empty())
+        .map(String::from)
+}
+
+/// Adds groups, tags and script-made links to a quicklinks table; safe to
+/// run on every start
+fn migrate_organization(db: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = db.prepare("PRAGMA table_info(quicklinks)")?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    if !columns.iter().any(|column| column == "group_name") {
+        db.execute("ALTER TABLE quicklinks ADD COLUMN group_name TEXT", [])?;
+    }
+    if !columns.iter().any(|column| column == "source_id") {
+        db.execute("ALTER TABLE quicklinks ADD COLUMN source_id INTEGER", [])?;
+    }
+    db.execute(TAGS_SCHEMA, [])?;
+    db.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quicklink_tags_tag ON quicklink_tags(tag)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Writes `quicklink`'s tags in place of whatever it had
+fn replace_tags(db: &Connection, id: i64, tags: &[String]) -> rusqlite::Result<()> {
+    db.execute(
+        "DELETE FROM quicklink_tags WHERE quicklink_id = ?1",
+        params![id],
+    )?;
+    for tag in normalize_tags(tags) {
+        db.execute(
+            "INSERT INTO quicklink_tags (quicklink_id, tag) VALUES (?1, ?2)",
+            params![id, tag],
+        )?;
+    }
+    Ok(())
+}
+
+pub struct QuicklinkManager {
+    store: Store,
+}
+
+impl QuicklinkManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "quicklinks.sqlite")?;
+        Self::with_store(store)
+    }
+
+    fn with_store(store: Store) -> Result<Self, AppError> {
+        store.init_table(QUICKLINKS_SCHEMA)?;
+        migrate_organization(&store.conn())?;
+        store.init_table(dynamic::DYNAMIC_QUICKLINKS_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_in_memory() -> Result<Self, AppError> {
+        Self::with_store(Store::new_in_memory()?)
+    }
+
+    fn create_quicklink(&self, input: &QuicklinkInput) -> Result<i64, AppError> {
+        let now = Utc::now().timestamp();
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        tx.execute(
+            "INSERT INTO quicklinks (name, link, application, icon, group_name, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                input.name,
+                input.link,
+                input.application,
+                input.icon,
+                normalize_group(input.group.as_deref()),
+                now,
+                now
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        replace_tags(&tx, id, &input.tags)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Every quicklink, or only those in `group` and carrying `tag`
+    fn list_quicklinks(
+        &self,
+        group: Option<&str>,
+        tag: Option<&str>,
+    ) -> Result<Vec<Quicklink>, AppError> {
+        self.store.query(
+            &format!(
+                "{} WHERE (?1 IS NULL OR q.group_name = ?1)
+                   AND (?2 IS NULL OR EXISTS (
+                       SELECT 1 FROM quicklink_tags t WHERE t.quicklink_id = q.id AND t.tag = ?2))
+                 ORDER BY q.name ASC",
+                SELECT_QUICKLINKS
+            ),
+            params![group, tag],
+        )
+    }
+
+    pub fn get_quicklink_link(&self, id: i64) -> Result<String, AppError> {
+        Ok(self.store.conn().query_row(
+            "SELECT link FROM quicklinks WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn update_quicklink(&self, id: i64, input: &QuicklinkInput) -> Result<(), AppError> {
+        let now = Utc::now().timestamp();
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        tx.execute(
+            "UPDATE quicklinks SET name = ?, link = ?, application = ?, icon = ?, group_name = ?, updated_at = ?
+             WHERE id = ?",
+            params![
+                input.name,
+                input.link,
+                input.application,
+                input.icon,
+                normalize_group(input.group.as_deref()),
+                now,
+                id
+            ],
+        )?;
+        replace_tags(&tx, id, &input.tags)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete_quicklink(&self, id: i64) -> Result<(), AppError> {
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        tx.execute(
+            "DELETE FROM quicklink_tags WHERE quicklink_id = ?",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM quicklinks WHERE id = ?", params![id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Every group in use, with how many quicklinks are in it
+    fn list_groups(&self) -> Result<Vec<QuicklinkGroup>, AppError> {
+        let db = self.store.conn();
+        let mut stmt = db.prepare(
+            "SELECT group_name, COUNT(*) FROM quicklinks WHERE group_name IS NOT NULL
+             GROUP BY group_name ORDER BY group_name COLLATE NOCASE",
+        )?;
+        let groups = stmt
+            .query_map([], |row| {
+                Ok(QuicklinkGroup {
+                    name: row.get(0)?,
+                    quicklink_count: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(groups)
+    }
+
+    /// Every tag in use, with how many quicklinks carry it
+    fn list_tags(&self) -> Result<Vec<(String, i64)>, AppError> {
+        let db = self.store.conn();
+        let mut stmt = db.prepare(
+            "SELECT tag, COUNT(*) FROM quicklink_tags GROUP BY tag ORDER BY tag COLLATE NOCASE",
+        )?;
+        let tags = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tags)
+    }
+}
+
+#[tauri::command]
+pub fn create_quicklink(app: AppHandle, input: QuicklinkInput) -> Result<i64, String> {
+    app.state::<QuicklinkManager>()
+        .create_quicklink(&input)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_quicklinks(
+    app: AppHandle,
+    group: Option<String>,
+    tag: Option<String>,
+) -> Result<Vec<Quicklink>, String> {
+    app.state::<QuicklinkManager>()
+        .list_quicklinks(group.as_deref(), tag.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_quicklink(app: AppHandle, id: i64, input: QuicklinkInput) -> Result<(), String> {
+    app.state::<QuicklinkManager>()
+        .update_quicklink(id, &input)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_quicklink(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<QuicklinkManager>()
+        .delete_quicklink(id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_quicklink_groups(app: AppHandle) -> Result<Vec<QuicklinkGroup>, String> {
+    app.state::<QuicklinkManager>()
+        .list_groups()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_quicklink_tags(app: AppHandle) -> Result<Vec<(String, i64)>, String> {
+    app.state::<QuicklinkManager>()
+        .list_tags()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn execute_quicklink(link: String, application: Option<String>) -> Result<(), String> {
+    if let Some(app_name) = application {
+        open_path(link, Some(app_name)).map_err(|e| e.to_string())
+    } else if link.starts_with("http://") || link.starts_with("https://") {
+        open_url(link, None::<String>).map_err(|e| e.to_string())
+    } else {
+        open_path(link, None::<String>).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(name: &str, group: Option<&str>, tags: &[&str]) -> QuicklinkInput {
+        QuicklinkInput {
+            name: name.to_string(),
+            link: format!("https://example.com/{}", name),
+            group: group.map(String::from),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_groups_and_tags() {
+        let manager = QuicklinkManager::new_in_memory().unwrap();
+        let docs = manager
+            .create_quicklink(&input("docs", Some("Work"), &["rust", " docs ", "rust"]))
+            .unwrap();
+        manager
+            .create_quicklink(&input("issues", Some("Work"), &["rust"]))
+            .unwrap();
+        manager
+            .create_quicklink(&input("news", Some("  "), &[]))
+            .unwrap();
+
+        let all = manager.list_quicklinks(None, None).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].tags, vec!["docs", "rust"]);
+        let news = all.iter().find(|q| q.name == "news").unwrap();
+        assert_eq!(news.group, None);
+
+        let work = manager.list_quicklinks(Some("Work"), None).unwrap();
+        assert_eq!(work.len(), 2);
+        let tagged = manager.list_quicklinks(Some("Work"), Some("docs")).unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].id, docs);
+
+        assert_eq!(
+            manager.list_groups().unwrap(),
+            vec![QuicklinkGroup {
+                name: "Work".to_string(),
+                quicklink_count: 2
+            }]
+        );
+        assert_eq!(
+            manager.list_tags().unwrap(),
+            vec![("docs".to_string(), 1), ("rust".to_string(), 2)]
+        );
+
+        manager
+            .update_quicklink(docs, &input("docs", None, &["reference"]))
+            .unwrap();
+        manager.delete_quicklink(docs).unwrap();
+        assert_eq!(manager.list_tags().unwrap(), vec![("rust".to_string(), 1)]);
+    }
+}
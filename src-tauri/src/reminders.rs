@@ -0,0 +1,780 @@
+//! Reminders typed in natural language ("remind me to call mom at 5pm",
+//! "in 20 minutes check the oven"), stored in SQLite and delivered as desktop
+//! notifications with Snooze and Complete buttons.
+
+use crate::error::AppError;
+use crate::notifications::{self, NotificationOptions, Urgency};
+use crate::store::{Storable, Store};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Utc, Weekday,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{params, Result as RusqliteResult};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const REMINDERS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS reminders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message TEXT NOT NULL,
+    due_at INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    snooze_count INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL
+)";
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_SNOOZE_MINUTES: i64 = 10;
+const REMINDER_SOUND: &str = "message-new-instant";
+const SNOOZE_ACTION: &str = "snooze";
+const COMPLETE_ACTION: &str = "complete";
+
+/// Time used when only a day is given ("tomorrow", "on friday")
+const DEFAULT_HOUR: u32 = 9;
+const TONIGHT_HOUR: u32 = 20;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReminderStatus {
+    Pending,
+    /// Notification shown, waiting for the user to snooze or complete it
+    Fired,
+    Completed,
+}
+
+impl ReminderStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReminderStatus::Pending => "pending",
+            ReminderStatus::Fired => "fired",
+            ReminderStatus::Completed => "completed",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "fired" => ReminderStatus::Fired,
+            "completed" => ReminderStatus::Completed,
+            _ => ReminderStatus::Pending,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    pub id: i64,
+    pub message: String,
+    pub due_at: DateTime<Utc>,
+    pub status: ReminderStatus,
+    pub snooze_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Storable for Reminder {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let due_at: i64 = row.get(2)?;
+        let status: String = row.get(3)?;
+        let created_at: i64 = row.get(5)?;
+        Ok(Reminder {
+            id: row.get(0)?,
+            message: row.get(1)?,
+            due_at: DateTime::from_timestamp(due_at, 0).unwrap_or_default(),
+            status: ReminderStatus::parse(&status),
+            snooze_count: row.get(4)?,
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_default(),
+        })
+    }
+}
+
+/// What the parser understood, so the palette can preview it before saving
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedReminder {
+    pub message: String,
+    pub due_at: DateTime<Utc>,
+}
+
+static TIME_12H: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\b(?:at\s+)?(\d{1,2})(?::(\d{2}))?\s*(am|pm)\b").unwrap());
+static TIME_24H: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bat\s+(\d{1,2})(?::(\d{2}))?\b").unwrap());
+static TIME_NAMED: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\b(?:at\s+)?(noon|midnight)\b").unwrap());
+static DAY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?:on\s+)?(today|tonight|tomorrow|(next\s+)?(monday|tuesday|wednesday|thursday|friday|saturday|sunday))\b",
+    )
+    .unwrap()
+});
+static DURATION_START: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bin\s+").unwrap());
+static LEADING_FILLER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^(?:remind\s+me\s*)?(?:(?:to|that|about)\s+)?").unwrap());
+static TRAILING_FILLER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)(?:\s+(?:to|at|on|in|and))+$").unwrap());
+
+fn unit_seconds(unit: &str) -> Option<f64> {
+    match unit {
+        "s" | "sec" | "secs" | "second" | "seconds" => Some(1.0),
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(60.0),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(3600.0),
+        "d" | "day" | "days" => Some(86_400.0),
+        "w" | "wk" | "wks" | "week" | "weeks" => Some(604_800.0),
+        _ => None,
+    }
+}
+
+/// Splits into numbers, words and commas with their byte ranges;
+/// `1h30m` becomes `1`, `h`, `30`, `m`
+fn lex(text: &str) -> Vec<(Range<usize>, String)> {
+    let mut tokens: Vec<(Range<usize>, String)> = Vec::new();
+    let class = |c: char| {
+        if c.is_ascii_digit() || c == '.' {
+            1
+        } else if c.is_alphabetic() {
+            2
+        } else if c == ',' {
+            3
+        } else {
+            0
+        }
+    };
+    let mut current: Option<(usize, u8)> = None;
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        let kind = class(c);
+        if let Some((start, current_kind)) = current {
+            if kind == current_kind && kind != 3 {
+                continue;
+            }
+            tokens.push((start..i, text[start..i].to_lowercase()));
+            current = None;
+        }
+        if kind != 0 {
+            current = Some((i, kind));
+        }
+    }
+    tokens
+}
+
+/// Reads "20m", "1h30m", "2 hours and 5 minutes", "half an hour" from the
+/// start of `text`; returns the duration and how many bytes it spans
+fn scan_duration(text: &str) -> Option<(ChronoDuration, usize)> {
+    let tokens = lex(text);
+    let mut seconds = 0.0;
+    let mut end = None;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        if end.is_some() && matches!(tokens[i].1.as_str(), "and" | ",") {
+            i += 1;
+        }
+        let Some((_, amount)) = tokens.get(i) else {
+            break;
+        };
+        let (value, consumed) = match amount.as_str() {
+            "a" | "an" => (1.0, 1),
+            "half" if matches!(tokens.get(i + 1).map(|t| t.1.as_str()), Some("a" | "an")) => {
+                (0.5, 2)
+            }
+            number => match number.parse::<f64>() {
+                Ok(value) => (value, 1),
+                Err(_) => break,
+            },
+        };
+        let Some((range, unit)) = tokens.get(i + consumed) else {
+            break;
+        };
+        let Some(unit_secs) = unit_seconds(unit) else {
+            break;
+        };
+        seconds += value * unit_secs;
+        end = Some(range.end);
+        i += consumed + 1;
+    }
+
+    end.filter(|_| seconds >= 1.0)
+        .map(|end| (ChronoDuration::seconds(seconds.round() as i64), end))
+}
+
+fn find_duration(text: &str) -> Option<(ChronoDuration, Range<usize>)> {
+    DURATION_START.find_iter(text).find_map(|start| {
+        scan_duration(&text[start.end()..])
+            .map(|(duration, len)| (duration, start.start()..start.end() + len))
+    })
+}
+
+fn find_time(text: &str) -> Result<Option<(NaiveTime, Range<usize>)>, String> {
+    let invalid = || "Invalid time of day".to_string();
+
+    if let Some(caps) = TIME_12H.captures(text) {
+        let hour: u32 = caps[1].parse().map_err(|_| invalid())?;
+        let minute: u32 = caps
+            .get(2)
+            .map_or(Ok(0), |m| m.as_str().parse())
+            .map_err(|_| invalid())?;
+        if !(1..=12).contains(&hour) {
+            return Err(invalid());
+        }
+        let hour = match (&caps[3].to_lowercase()[..], hour) {
+            ("am", 12) => 0,
+            ("am", hour) => hour,
+            ("pm", 12) => 12,
+            (_, hour) => hour + 12,
+        };
+        let time = NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(invalid)?;
+        return Ok(Some((time, caps.get(0).unwrap().range())));
+    }
+    if let Some(caps) = TIME_NAMED.captures(text) {
+        let hour = if caps[1].eq_ignore_ascii_case("noon") {
+            12
+        } else {
+            0
+        };
+        let time = NaiveTime::from_hms_opt(hour, 0, 0).ok_or_else(invalid)?;
+        return Ok(Some((time, caps.get(0).unwrap().range())));
+    }
+    if let Some(caps) = TIME_24H.captures(text) {
+        let hour: u32 = caps[1].parse().map_err(|_| invalid())?;
+        let minute: u32 = caps
+            .get(2)
+            .map_or(Ok(0), |m| m.as_str().parse())
+            .map_err(|_| invalid())?;
+        let time = NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(invalid)?;
+        return Ok(Some((time, caps.get(0).unwrap().range())));
+    }
+    Ok(None)
+}
+
+fn parse_weekday(name: &str) -> Option<Weekday> {
+    name.parse().ok()
+}
+
+/// Resolves the day phrase against `today`, also telling whether it was
+/// "tonight" and whether it named a weekday
+fn find_day(text: &str, today: NaiveDate) -> Option<(NaiveDate, bool, bool, Range<usize>)> {
+    let caps = DAY.captures(text)?;
+    let range = caps.get(0)?.range();
+    let word = caps[1].to_lowercase();
+    let (date, tonight, weekday) = match word.as_str() {
+        "today" => (today, false, false),
+        "tonight" => (today, true, false),
+        "tomorrow" => (today.succ_opt()?, false, false),
+        _ => {
+            let target = parse_weekday(&caps[3])?;
+            let mut ahead = (target.num_days_from_monday() as i64
+                - today.weekday().num_days_from_monday() as i64)
+                .rem_euclid(7);
+            if caps.get(2).is_some() && ahead == 0 {
+                ahead = 7;
+            }
+            (today + ChronoDuration::days(ahead), false, true)
+        }
+    };
+    Some((date, tonight, weekday, range))
+}
+
+fn clean_message(text: &str, spans: &mut [Range<usize>]) -> String {
+    spans.sort_by_key(|span| std::cmp::Reverse(span.start));
+    let mut message = text.to_string();
+    for span in spans.iter() {
+        message.replace_range(span.clone(), " ");
+    }
+    let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+    let message = LEADING_FILLER.replace(&message, "");
+    let message = TRAILING_FILLER.replace(&message, "");
+    let message = message.trim_matches(|c: char| c.is_whitespace() || c == ',' || c == '.');
+    if message.is_empty() {
+        "Reminder".to_string()
+    } else {
+        message.to_string()
+    }
+}
+
+/// A phrase like "call bob tomorrow at 3pm" split into what and when
+#[derive(Debug, Clone, PartialEq)]
+pub struct NaturalTime {
+    pub message: String,
+    /// Local time; a bare day resolves to 09:00 on that day
+    pub due: NaiveDateTime,
+    /// False when only a day was given, so callers can treat it as all-day
+    pub has_time: bool,
+}
+
+/// Finds a relative ("in 1h30m") or absolute ("friday at noon") time in
+/// `input`, resolved against `now`. A bare time that already passed today
+/// means tomorrow.
+pub fn parse_natural_time(input: &str, now: NaiveDateTime) -> Result<NaturalTime, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Text is empty".to_string());
+    }
+
+    if let Some((duration, span)) = find_duration(input) {
+        return Ok(NaturalTime {
+            message: clean_message(input, &mut [span]),
+            due: now + duration,
+            has_time: true,
+        });
+    }
+
+    let time = find_time(input)?;
+    let day = find_day(input, now.date());
+    let mut spans = Vec::new();
+
+    let (due, has_time) = match (day, time) {
+        (None, None) => {
+            return Err("Couldn't find a time; try \"in 10 minutes\" or \"at 5pm\"".to_string())
+        }
+        (None, Some((time, span))) => {
+            spans.push(span);
+            let today = now.date().and_time(time);
+            if today > now {
+                (today, true)
+            } else {
+                (today + ChronoDuration::days(1), true)
+            }
+        }
+        (Some((date, tonight, weekday, day_span)), time) => {
+            spans.push(day_span);
+            let default_hour = if tonight { TONIGHT_HOUR } else { DEFAULT_HOUR };
+            let has_time = time.is_some() || tonight;
+            let time = match time {
+                Some((time, span)) => {
+                    spans.push(span);
+                    time
+                }
+                None => NaiveTime::from_hms_opt(default_hour, 0, 0).unwrap_or_default(),
+            };
+            let due = date.and_time(time);
+            if due <= now && weekday {
+                // "on monday" said on a Monday afternoon means next week
+                (due + ChronoDuration::days(7), has_time)
+            } else if due <= now && has_time {
+                return Err("That time has already passed".to_string());
+            } else {
+                (due, has_time)
+            }
+        }
+    };
+
+    Ok(NaturalTime {
+        message: clean_message(input, &mut spans),
+        due,
+        has_time,
+    })
+}
+
+/// Parses e.g. "remind me to stretch in 1h30m" or "call bob tomorrow at 3pm"
+/// relative to `now`, returning the message and the local time it's due
+pub fn parse_reminder(input: &str, now: NaiveDateTime) -> Result<(String, NaiveDateTime), String> {
+    let parsed = parse_natural_time(input, now)?;
+    if parsed.due <= now {
+        return Err("That time has already passed".to_string());
+    }
+    Ok((parsed.message, parsed.due))
+}
+
+pub fn to_utc(local: NaiveDateTime) -> Result<DateTime<Utc>, String> {
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| "That time doesn't exist in the local time zone".to_string())
+}
+
+pub fn parse_reminder_now(input: &str) -> Result<ParsedReminder, String> {
+    let (message, due) = parse_reminder(input, Local::now().naive_local())?;
+    Ok(ParsedReminder {
+        message,
+        due_at: to_utc(due)?,
+    })
+}
+
+pub struct ReminderManager {
+    store: Store,
+    /// Notification id -> reminder id, for routing notification actions
+    notifications: Mutex<HashMap<u32, i64>>,
+}
+
+impl ReminderManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "reminders.sqlite")?;
+        store.init_table(REMINDERS_SCHEMA)?;
+        Ok(Self {
+            store,
+            notifications: Mutex::new(HashMap::new()),
+        })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(REMINDERS_SCHEMA)?;
+        Ok(Self {
+            store,
+            notifications: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn create(&self, message: &str, due_at: i64, now: i64) -> Result<Reminder, AppError> {
+        self.store.execute(
+            "INSERT INTO reminders (message, due_at, created_at) VALUES (?, ?, ?)",
+            params![message, due_at, now],
+        )?;
+        let id = self.store.last_insert_rowid();
+        self.get(id)?
+            .ok_or_else(|| AppError::Rusqlite(rusqlite::Error::QueryReturnedNoRows))
+    }
+
+    fn get(&self, id: i64) -> Result<Option<Reminder>, AppError> {
+        self.store.query_row(
+            "SELECT id, message, due_at, status, snooze_count, created_at FROM reminders WHERE id = ?",
+            params![id],
+        )
+    }
+
+    fn list(&self, include_completed: bool) -> Result<Vec<Reminder>, AppError> {
+        self.store.query(
+            "SELECT id, message, due_at, status, snooze_count, created_at FROM reminders
+             WHERE ? OR status != 'completed'
+             ORDER BY due_at ASC",
+            params![include_completed],
+        )
+    }
+
+    /// Marks pending reminders that are due as fired and returns them,
+    /// including ones that came due while the app wasn't running
+    fn take_due(&self, now: i64) -> Result<Vec<Reminder>, AppError> {
+        let due: Vec<Reminder> = self.store.query(
+            "SELECT id, message, due_at, status, snooze_count, created_at FROM reminders
+             WHERE status = 'pending' AND due_at <= ?
+             ORDER BY due_at ASC",
+            params![now],
+        )?;
+        for reminder in &due {
+            self.set_status(reminder.id, ReminderStatus::Fired)?;
+        }
+        Ok(due
+            .into_iter()
+            .map(|reminder| Reminder {
+                status: ReminderStatus::Fired,
+                ..reminder
+            })
+            .collect())
+    }
+
+    fn set_status(&self, id: i64, status: ReminderStatus) -> Result<(), AppError> {
+        self.store.execute(
+            "UPDATE reminders SET status = ? WHERE id = ?",
+            params![status.as_str(), id],
+        )?;
+        Ok(())
+    }
+
+    fn snooze(&self, id: i64, until: i64) -> Result<(), AppError> {
+        self.store.execute(
+            "UPDATE reminders SET due_at = ?, status = 'pending', snooze_count = snooze_count + 1
+             WHERE id = ?",
+            params![until, id],
+        )?;
+        Ok(())
+    }
+
+    fn complete(&self, id: i64) -> Result<(), AppError> {
+        self.set_status(id, ReminderStatus::Completed)
+    }
+
+    fn delete(&self, id: i64) -> Result<(), AppError> {
+        self.store
+            .execute("DELETE FROM reminders WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    fn track_notification(&self, notification_id: u32, reminder_id: i64) {
+        self.notifications
+            .lock()
+            .unwrap()
+            .insert(notification_id, reminder_id);
+    }
+
+    fn reminder_for_notification(&self, notification_id: u32) -> Option<i64> {
+        self.notifications.lock().unwrap().remove(&notification_id)
+    }
+}
+
+fn notify(app: &AppHandle, reminder: &Reminder) {
+    let app = app.clone();
+    let reminder = reminder.clone();
+    tauri::async_runtime::spawn(async move {
+        let options = NotificationOptions {
+            sound: Some(REMINDER_SOUND.to_string()),
+            urgency: Some(Urgency::Normal),
+            icon: Some("appointment-soon-symbolic".to_string()),
+            actions: vec![
+                (
+                    SNOOZE_ACTION.to_string(),
+                    format!("Snooze {} min", DEFAULT_SNOOZE_MINUTES),
+                ),
+                (COMPLETE_ACTION.to_string(), "Complete".to_string()),
+            ],
+            persistent: true,
+        };
+        match notifications::send("Reminder", &reminder.message, options).await {
+            Ok(notification_id) => app
+                .state::<ReminderManager>()
+                .track_notification(notification_id, reminder.id),
+            Err(e) => tracing::warn!(error = %e, "Failed to show reminder notification"),
+        }
+    });
+}
+
+fn handle_action(app: &AppHandle, notification_id: u32, action: &str) {
+    if action != SNOOZE_ACTION && action != COMPLETE_ACTION {
+        return;
+    }
+    let manager = app.state::<ReminderManager>();
+    let Some(id) = manager.reminder_for_notification(notification_id) else {
+        return;
+    };
+    let result = match action {
+        SNOOZE_ACTION => {
+            let until = Utc::now() + ChronoDuration::minutes(DEFAULT_SNOOZE_MINUTES);
+            manager.snooze(id, until.timestamp())
+        }
+        _ => manager.complete(id),
+    };
+    match result {
+        Ok(()) => {
+            let _ = app.emit("reminders-changed", ());
+        }
+        Err(e) => tracing::error!(error = %e, id, "Failed to update reminder"),
+    }
+}
+
+/// Starts the ticker that delivers due reminders, and listens for the
+/// Snooze/Complete buttons on their notifications
+pub fn init(app: &AppHandle) {
+    match ReminderManager::new(app) {
+        Ok(manager) => {
+            app.manage(manager);
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to open reminders database");
+            return;
+        }
+    }
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let due = handle
+                .state::<ReminderManager>()
+                .take_due(Utc::now().timestamp());
+            match due {
+                Ok(due) => {
+                    for reminder in due {
+                        tracing::info!(id = reminder.id, "Reminder due");
+                        notify(&handle, &reminder);
+                        let _ = handle.emit("reminder-fired", &reminder);
+                    }
+                }
+                Err(e) => tracing::error!(error = %e, "Failed to check reminders"),
+            }
+        }
+    });
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let listener = notifications::listen_for_actions(move |notification_id, action| {
+            handle_action(&handle, notification_id, &action)
+        });
+        if let Err(e) = listener.await {
+            tracing::warn!(error = %e, "Notification actions unavailable");
+        }
+    });
+}
+
+/// Parses `text` and saves the reminder
+#[tauri::command]
+pub fn create_reminder(app: AppHandle, text: String) -> Result<Reminder, String> {
+    let parsed = parse_reminder_now(&text)?;
+    app.state::<ReminderManager>()
+        .create(
+            &parsed.message,
+            parsed.due_at.timestamp(),
+            Utc::now().timestamp(),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// `due_at` is an RFC 3339 timestamp
+#[tauri::command]
+pub fn create_reminder_at(
+    app: AppHandle,
+    message: String,
+    due_at: String,
+) -> Result<Reminder, String> {
+    let due_at = DateTime::parse_from_rfc3339(&due_at)
+        .map_err(|e| format!("Invalid reminder time: {}", e))?
+        .timestamp();
+    let now = Utc::now().timestamp();
+    if due_at <= now {
+        return Err("Reminder time must be in the future".to_string());
+    }
+    app.state::<ReminderManager>()
+        .create(message.trim(), due_at, now)
+        .map_err(|e| e.to_string())
+}
+
+/// Preview of what `create_reminder` would save
+#[tauri::command]
+pub fn parse_reminder_text(text: String) -> Result<ParsedReminder, String> {
+    parse_reminder_now(&text)
+}
+
+#[tauri::command]
+pub fn list_reminders(
+    app: AppHandle,
+    include_completed: Option<bool>,
+) -> Result<Vec<Reminder>, String> {
+    app.state::<ReminderManager>()
+        .list(include_completed.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn snooze_reminder(app: AppHandle, id: i64, minutes: Option<i64>) -> Result<(), String> {
+    let minutes = minutes.unwrap_or(DEFAULT_SNOOZE_MINUTES).max(1);
+    let until = Utc::now() + ChronoDuration::minutes(minutes);
+    app.state::<ReminderManager>()
+        .snooze(id, until.timestamp())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn complete_reminder(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<ReminderManager>()
+        .complete(id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_reminder(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<ReminderManager>()
+        .delete(id)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wednesday 2025-01-15 14:00
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 1, 15)
+            .unwrap()
+            .and_hms_opt(14, 0, 0)
+            .unwrap()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_durations() {
+        let (message, due) = parse_reminder("remind me to check the oven in 20m", now()).unwrap();
+        assert_eq!(message, "check the oven");
+        assert_eq!(due, at(15, 14, 20));
+
+        let (message, due) = parse_reminder("in 1h30m stretch", now()).unwrap();
+        assert_eq!(message, "stretch");
+        assert_eq!(due, at(15, 15, 30));
+
+        let (_, due) = parse_reminder("in 2 hours and 5 minutes call back", now()).unwrap();
+        assert_eq!(due, at(15, 16, 5));
+
+        let (_, due) = parse_reminder("tea in half an hour", now()).unwrap();
+        assert_eq!(due, at(15, 14, 30));
+    }
+
+    #[test]
+    fn test_parse_times_and_days() {
+        let (message, due) = parse_reminder("remind me to call mom at 5pm", now()).unwrap();
+        assert_eq!(message, "call mom");
+        assert_eq!(due, at(15, 17, 0));
+
+        // Already past today, so tomorrow
+        let (_, due) = parse_reminder("standup at 9:30am", now()).unwrap();
+        assert_eq!(due, at(16, 9, 30));
+
+        let (message, due) = parse_reminder("call bob tomorrow at 15:45", now()).unwrap();
+        assert_eq!(message, "call bob");
+        assert_eq!(due, at(16, 15, 45));
+
+        let (_, due) = parse_reminder("water plants on friday", now()).unwrap();
+        assert_eq!(due, at(17, 9, 0));
+
+        let (_, due) = parse_reminder("review next wednesday at noon", now()).unwrap();
+        assert_eq!(due, at(22, 12, 0));
+
+        let (_, due) = parse_reminder("take out trash tonight", now()).unwrap();
+        assert_eq!(due, at(15, 20, 0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_reminder("buy milk", now()).is_err());
+        assert!(parse_reminder("today at 8am", now()).is_err());
+        assert!(parse_reminder("at 13pm", now()).is_err());
+        assert!(parse_reminder("put it in 2 boxes", now()).is_err());
+    }
+
+    #[test]
+    fn test_parse_natural_time_without_time() {
+        let parsed = parse_natural_time("file taxes today", now()).unwrap();
+        assert_eq!(parsed.message, "file taxes");
+        assert_eq!(parsed.due.date(), now().date());
+        assert!(!parsed.has_time);
+
+        assert!(
+            parse_natural_time("file taxes tomorrow at 5pm", now())
+                .unwrap()
+                .has_time
+        );
+    }
+
+    #[test]
+    fn test_take_due_snooze_and_complete() {
+        let manager = ReminderManager::new_for_test().unwrap();
+        let early = manager.create("early", 100, 0).unwrap();
+        let late = manager.create("late", 500, 0).unwrap();
+
+        let due = manager.take_due(200).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, early.id);
+        assert!(manager.take_due(200).unwrap().is_empty());
+
+        manager.snooze(early.id, 300).unwrap();
+        let snoozed = manager.get(early.id).unwrap().unwrap();
+        assert_eq!(snoozed.status, ReminderStatus::Pending);
+        assert_eq!(snoozed.snooze_count, 1);
+
+        manager.complete(late.id).unwrap();
+        assert_eq!(manager.list(false).unwrap().len(), 1);
+        assert_eq!(manager.list(true).unwrap().len(), 2);
+    }
+}
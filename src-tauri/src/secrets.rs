@@ -0,0 +1,467 @@
+//! Single entry point for everything Flare keeps in the system keyring.
+//!
+//! Secrets are addressed by `(namespace, key)`. Values only ever live in the
+//! keyring; a small SQLite index keeps metadata for listing (the keyring
+//! itself cannot enumerate entries) and an audit log of every read, write and
+//! delete. Extensions get an `extension:<name>` namespace each and can only
+//! reach their own: the launcher fills in `extension` for the running one
+//! rather than trusting the sidecar. Values are only read back inside the
+//! backend; the settings UI can set, delete and list secrets, never fetch one.
+
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Result as RusqliteResult};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+const KEYRING_SERVICE: &str = "dev.byteatatime.flare.secrets";
+
+pub const AI: &str = "ai";
+pub const BROWSER: &str = "browser";
+pub const CLIPBOARD_SYNC: &str = "clipboard_sync";
+pub const EMAIL: &str = "email";
+pub const GITHUB: &str = "github";
+pub const HTTP: &str = "http";
+pub const OAUTH: &str = "oauth";
+pub const TODOIST: &str = "todoist";
+pub const TRANSLATE: &str = "translate";
+pub const WALLPAPER: &str = "wallpaper";
+const EXTENSION_PREFIX: &str = "extension:";
+
+/// Entries written by older versions before the vault existed:
+/// (service, username, namespace, key)
+const LEGACY_ENTRIES: &[(&str, &str, &str, &str)] = &[
+    (
+        "dev.byteatatime.flare.ai",
+        "openrouter_api_key",
+        AI,
+        "openrouter_api_key",
+    ),
+    ("flareup", "github", GITHUB, "access_token"),
+];
+
+const SECRETS_INDEX_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS secrets_index (
+    namespace TEXT NOT NULL,
+    key TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    last_read_at INTEGER,
+    PRIMARY KEY (namespace, key)
+)";
+
+const SECRETS_AUDIT_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS secrets_audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    namespace TEXT NOT NULL,
+    key TEXT NOT NULL,
+    action TEXT NOT NULL,
+    accessor TEXT NOT NULL,
+    at INTEGER NOT NULL
+)";
+
+/// Audit entries are trimmed to this many rows
+const AUDIT_LOG_LIMIT: i64 = 5000;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretMetadata {
+    pub namespace: String,
+    pub key: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub last_read_at: Option<DateTime<Utc>>,
+}
+
+impl Storable for SecretMetadata {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let created_at: i64 = row.get(2)?;
+        let updated_at: i64 = row.get(3)?;
+        let last_read_at: Option<i64> = row.get(4)?;
+        Ok(SecretMetadata {
+            namespace: row.get(0)?,
+            key: row.get(1)?,
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_default(),
+            updated_at: DateTime::from_timestamp(updated_at, 0).unwrap_or_default(),
+            last_read_at: last_read_at.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretAuditEntry {
+    pub id: i64,
+    pub namespace: String,
+    pub key: String,
+    /// `read`, `write` or `delete`
+    pub action: String,
+    /// Module or extension that performed the access
+    pub accessor: String,
+    pub at: DateTime<Utc>,
+}
+
+impl Storable for SecretAuditEntry {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let at: i64 = row.get(5)?;
+        Ok(SecretAuditEntry {
+            id: row.get(0)?,
+            namespace: row.get(1)?,
+            key: row.get(2)?,
+            action: row.get(3)?,
+            accessor: row.get(4)?,
+            at: DateTime::from_timestamp(at, 0).unwrap_or_default(),
+        })
+    }
+}
+
+pub struct SecretIndex {
+    store: Store,
+}
+
+impl SecretIndex {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "secrets.sqlite")?;
+        store.init_table(SECRETS_INDEX_SCHEMA)?;
+        store.init_table(SECRETS_AUDIT_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(SECRETS_INDEX_SCHEMA)?;
+        store.init_table(SECRETS_AUDIT_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    fn audit(
+        &self,
+        namespace: &str,
+        key: &str,
+        action: &str,
+        accessor: &str,
+    ) -> Result<(), AppError> {
+        self.store.execute(
+            "INSERT INTO secrets_audit_log (namespace, key, action, accessor, at) VALUES (?, ?, ?, ?, ?)",
+            params![namespace, key, action, accessor, Utc::now().timestamp()],
+        )?;
+        self.store.execute(
+            "DELETE FROM secrets_audit_log WHERE id <= (SELECT MAX(id) FROM secrets_audit_log) - ?",
+            params![AUDIT_LOG_LIMIT],
+        )?;
+        Ok(())
+    }
+
+    fn record_write(&self, namespace: &str, key: &str, accessor: &str) -> Result<(), AppError> {
+        let now = Utc::now().timestamp();
+        self.store.execute(
+            "INSERT INTO secrets_index (namespace, key, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)
+             ON CONFLICT(namespace, key) DO UPDATE SET updated_at = excluded.updated_at",
+            params![namespace, key, now],
+        )?;
+        self.audit(namespace, key, "write", accessor)
+    }
+
+    fn record_read(&self, namespace: &str, key: &str, accessor: &str) -> Result<(), AppError> {
+        let now = Utc::now().timestamp();
+        // Secrets that predate the index get an entry on first read
+        self.store.execute(
+            "INSERT INTO secrets_index (namespace, key, created_at, updated_at, last_read_at) VALUES (?1, ?2, ?3, ?3, ?3)
+             ON CONFLICT(namespace, key) DO UPDATE SET last_read_at = excluded.last_read_at",
+            params![namespace, key, now],
+        )?;
+        self.audit(namespace, key, "read", accessor)
+    }
+
+    fn record_delete(&self, namespace: &str, key: &str, accessor: &str) -> Result<(), AppError> {
+        self.store.execute(
+            "DELETE FROM secrets_index WHERE namespace = ? AND key = ?",
+            params![namespace, key],
+        )?;
+        self.audit(namespace, key, "delete", accessor)
+    }
+
+    pub fn list(&self, namespace: Option<&str>) -> Result<Vec<SecretMetadata>, AppError> {
+        match namespace {
+            Some(namespace) => self.store.query(
+                "SELECT namespace, key, created_at, updated_at, last_read_at FROM secrets_index
+                 WHERE namespace = ? ORDER BY key",
+                params![namespace],
+            ),
+            None => self.store.query(
+                "SELECT namespace, key, created_at, updated_at, last_read_at FROM secrets_index
+                 ORDER BY namespace, key",
+                [],
+            ),
+        }
+    }
+
+    pub fn audit_log(
+        &self,
+        namespace: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<SecretAuditEntry>, AppError> {
+        match namespace {
+            Some(namespace) => self.store.query(
+                "SELECT id, namespace, key, action, accessor, at FROM secrets_audit_log
+                 WHERE namespace = ? ORDER BY id DESC LIMIT ?",
+                params![namespace, limit],
+            ),
+            None => self.store.query(
+                "SELECT id, namespace, key, action, accessor, at FROM secrets_audit_log
+                 ORDER BY id DESC LIMIT ?",
+                params![limit],
+            ),
+        }
+    }
+}
+
+static INDEX: Lazy<Mutex<Option<SecretIndex>>> = Lazy::new(|| Mutex::new(None));
+
+/// Runs `f` against the index when it's available. Index failures never block
+/// access to the secret itself; they are only logged.
+fn with_index(f: impl FnOnce(&SecretIndex) -> Result<(), AppError>) {
+    if let Some(index) = INDEX.lock().unwrap().as_ref() {
+        if let Err(e) = f(index) {
+            tracing::warn!(error = %e, "Failed to update secrets index");
+        }
+    }
+}
+
+fn validate(namespace: &str, key: &str) -> Result<(), AppError> {
+    let valid_namespace = !namespace.is_empty()
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':' | '@'));
+    if !valid_namespace {
+        return Err(AppError::Secrets(format!(
+            "Invalid secret namespace '{}'",
+            namespace
+        )));
+    }
+    if key.is_empty() {
+        return Err(AppError::Secrets(
+            "Secret key must not be empty".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn entry(namespace: &str, key: &str) -> Result<keyring::Entry, AppError> {
+    validate(namespace, key)?;
+    keyring::Entry::new(KEYRING_SERVICE, &format!("{}/{}", namespace, key)).map_err(AppError::from)
+}
+
+pub fn extension_namespace(extension: &str) -> String {
+    format!("{}{}", EXTENSION_PREFIX, extension)
+}
+
+pub fn set(namespace: &str, key: &str, value: &str) -> Result<(), AppError> {
+    set_as(namespace, key, value, namespace)
+}
+
+fn set_as(namespace: &str, key: &str, value: &str, accessor: &str) -> Result<(), AppError> {
+    entry(namespace, key)?.set_password(value)?;
+    with_index(|index| index.record_write(namespace, key, accessor));
+    Ok(())
+}
+
+pub fn get(namespace: &str, key: &str) -> Result<Option<String>, AppError> {
+    get_as(namespace, key, namespace)
+}
+
+/// Reads a secret on behalf of `accessor`, which is what the audit log records
+pub fn get_as(namespace: &str, key: &str, accessor: &str) -> Result<Option<String>, AppError> {
+    match entry(namespace, key)?.get_password() {
+        Ok(value) => {
+            with_index(|index| index.record_read(namespace, key, accessor));
+            Ok(Some(value))
+        }
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Checks for a secret without revealing (or auditing a read of) its value
+pub fn exists(namespace: &str, key: &str) -> Result<bool, AppError> {
+    match entry(namespace, key)?.get_password() {
+        Ok(_) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Succeeds when the keyring backend answers, whether or not anything is stored
+pub fn probe() -> Result<(), AppError> {
+    exists("flare", "keyring-probe").map(|_| ())
+}
+
+pub fn delete(namespace: &str, key: &str) -> Result<(), AppError> {
+    delete_as(namespace, key, namespace)
+}
+
+fn delete_as(namespace: &str, key: &str, accessor: &str) -> Result<(), AppError> {
+    match entry(namespace, key)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {
+            with_index(|index| index.record_delete(namespace, key, accessor));
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Moves secrets stored by older versions under their own keyring services
+fn migrate_legacy_entries() {
+    for (service, username, namespace, key) in LEGACY_ENTRIES {
+        let Ok(legacy) = keyring::Entry::new(service, username) else {
+            continue;
+        };
+        let Ok(value) = legacy.get_password() else {
+            continue;
+        };
+        if matches!(exists(namespace, key), Ok(false)) {
+            if let Err(e) = set_as(namespace, key, &value, "migration") {
+                tracing::warn!(error = %e, namespace, key, "Failed to migrate legacy secret");
+                continue;
+            }
+        }
+        if let Err(e) = legacy.delete_credential() {
+            tracing::warn!(error = %e, service, username, "Failed to remove legacy secret");
+        }
+    }
+}
+
+pub fn init(app_handle: &AppHandle) {
+    {
+        let mut index = INDEX.lock().unwrap();
+        if index.is_none() {
+            match SecretIndex::new(app_handle) {
+                Ok(created) => *index = Some(created),
+                Err(e) => tracing::error!(error = ?e, "Failed to open secrets index"),
+            }
+        }
+    }
+    migrate_legacy_entries();
+}
+
+fn reject_extension_namespace(namespace: &str) -> Result<(), String> {
+    if namespace.starts_with(EXTENSION_PREFIX) {
+        return Err("Extension secrets can only be accessed by their extension".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn secrets_set(namespace: String, key: String, value: String) -> Result<(), String> {
+    reject_extension_namespace(&namespace)?;
+    set_as(&namespace, &key, &value, "settings").map_err(|e| e.to_string())
+}
+
+/// Deleting is allowed for any namespace so users can revoke extension secrets
+#[tauri::command]
+pub fn secrets_delete(namespace: String, key: String) -> Result<(), String> {
+    delete_as(&namespace, &key, "settings").map_err(|e| e.to_string())
+}
+
+/// Metadata only; values are never part of the listing
+#[tauri::command]
+pub fn secrets_list(namespace: Option<String>) -> Result<Vec<SecretMetadata>, String> {
+    match INDEX.lock().unwrap().as_ref() {
+        Some(index) => index.list(namespace.as_deref()).map_err(|e| e.to_string()),
+        None => Err("Secrets index not initialized".to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn secrets_audit_log(
+    namespace: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<SecretAuditEntry>, String> {
+    match INDEX.lock().unwrap().as_ref() {
+        Some(index) => index
+            .audit_log(namespace.as_deref(), limit.unwrap_or(100))
+            .map_err(|e| e.to_string()),
+        None => Err("Secrets index not initialized".to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn extension_secret_set(extension: String, key: String, value: String) -> Result<(), String> {
+    set_as(&extension_namespace(&extension), &key, &value, &extension).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn extension_secret_get(extension: String, key: String) -> Result<Option<String>, String> {
+    get_as(&extension_namespace(&extension), &key, &extension).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn extension_secret_delete(extension: String, key: String) -> Result<(), String> {
+    delete_as(&extension_namespace(&extension), &key, &extension).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn extension_secret_list(extension: String) -> Result<Vec<SecretMetadata>, String> {
+    secrets_list(Some(extension_namespace(&extension)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("ai", "openrouter_api_key").is_ok());
+        assert!(validate(&extension_namespace("raycast-github"), "token").is_ok());
+        assert!(validate("", "key").is_err());
+        assert!(validate("bad/namespace", "key").is_err());
+        assert!(validate("ai", "").is_err());
+    }
+
+    #[test]
+    fn test_extension_namespaces_are_rejected_by_generic_commands() {
+        assert!(reject_extension_namespace("github").is_ok());
+        assert!(reject_extension_namespace(&extension_namespace("demo")).is_err());
+    }
+
+    #[test]
+    fn test_index_tracks_metadata_and_audit() {
+        let index = SecretIndex::new_for_test().unwrap();
+        index.record_write(GITHUB, "access_token", GITHUB).unwrap();
+        index
+            .record_write("extension:demo", "token", "demo")
+            .unwrap();
+        index
+            .record_read(GITHUB, "access_token", "settings")
+            .unwrap();
+
+        let all = index.list(None).unwrap();
+        assert_eq!(all.len(), 2);
+        let github = index.list(Some(GITHUB)).unwrap();
+        assert_eq!(github.len(), 1);
+        assert!(github[0].last_read_at.is_some());
+
+        index
+            .record_delete("extension:demo", "token", "settings")
+            .unwrap();
+        assert!(index.list(Some("extension:demo")).unwrap().is_empty());
+
+        let log = index.audit_log(None, 10).unwrap();
+        let actions: Vec<(&str, &str)> = log
+            .iter()
+            .map(|e| (e.action.as_str(), e.accessor.as_str()))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("delete", "settings"),
+                ("read", "settings"),
+                ("write", "demo"),
+                ("write", GITHUB)
+            ]
+        );
+        assert_eq!(index.audit_log(Some(GITHUB), 10).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,282 @@
+//! Actions on the selected text, behind one hotkey. The hotkey grabs the
+//! selection before the launcher takes focus, then opens the launcher on a
+//! menu of the actions that fit it: translate, define, search the web, save
+//! it as a snippet or quicklink, or run any AI command that takes the
+//! `{selection}`.
+//!
+//! Translating, defining and searching run here and hand back a result to
+//! show; saving and AI commands come back as prefilled work for the
+//! frontend, which owns those forms and the chat view.
+
+use crate::ai::commands::{AiCommand, AiCommandManager};
+use crate::context;
+use crate::dictionary::{self, WordDefinition};
+use crate::hotkey_manager::AI_COMMAND_PREFIX;
+use crate::translate::{self, Translation};
+use crate::web_search::{self, WebSearchManager};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const SELECTION_ACTIONS_REQUESTED_EVENT: &str = "selection-actions-requested";
+
+/// Longer selections are a phrase or a paragraph, not something to look up
+const MAX_DEFINE_WORDS: usize = 3;
+const MAX_SEARCH_CHARS: usize = 500;
+
+/// The selection the open menu was made for
+static SELECTION: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectionAction {
+    pub id: String,
+    pub title: String,
+    pub icon: Option<String>,
+}
+
+impl SelectionAction {
+    fn new(id: &str, title: &str, icon: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            title: title.to_string(),
+            icon: Some(icon.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectionMenu {
+    pub text: String,
+    /// App id of the window the text was selected in
+    pub app: Option<String>,
+    pub actions: Vec<SelectionAction>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SelectionOutcome {
+    Translation {
+        translation: Translation,
+    },
+    Definition {
+        definition: WordDefinition,
+    },
+    /// Handed off to another app, e.g. the browser for a web search
+    Done,
+    /// Open the snippet form with this content
+    CreateSnippet {
+        content: String,
+    },
+    /// Open the quicklink form with this link
+    CreateQuicklink {
+        link: String,
+    },
+    /// Run the command in the chat view with this selection
+    AiCommand {
+        command: AiCommand,
+        selection: String,
+    },
+}
+
+fn is_link(text: &str) -> bool {
+    url::Url::parse(text).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// The built-in actions that fit `text`, in menu order
+fn builtin_actions(text: &str) -> Vec<SelectionAction> {
+    let text = text.trim();
+    let mut actions = vec![SelectionAction::new("translate", "Translate", "languages")];
+    if text.split_whitespace().count() <= MAX_DEFINE_WORDS {
+        actions.push(SelectionAction::new("define", "Define", "book-open"));
+    }
+    if text.chars().count() <= MAX_SEARCH_CHARS {
+        actions.push(SelectionAction::new(
+            "search_web",
+            "Search the Web",
+            "search",
+        ));
+    }
+    actions.push(SelectionAction::new(
+        "create_snippet",
+        "Save as Snippet",
+        "text-quote",
+    ));
+    if is_link(text) {
+        actions.push(SelectionAction::new(
+            "create_quicklink",
+            "Save as Quicklink",
+            "link",
+        ));
+    }
+    actions
+}
+
+fn ai_command_actions(app: &AppHandle) -> Vec<SelectionAction> {
+    let commands = match app.state::<AiCommandManager>().list() {
+        Ok(commands) => commands,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to list AI commands for the selection menu");
+            return Vec::new();
+        }
+    };
+    commands
+        .into_iter()
+        .filter(|command| command.prompt.contains("{selection}"))
+        .map(|command| SelectionAction {
+            id: format!("{}{}", AI_COMMAND_PREFIX, command.id),
+            title: command.name,
+            icon: command.icon,
+        })
+        .collect()
+}
+
+/// Takes the selection, remembering it for `run_selection_action`
+fn capture(app: &AppHandle) -> SelectionMenu {
+    let text = selection::get_text();
+    *SELECTION.lock().unwrap() = Some(text.clone());
+    let mut actions = Vec::new();
+    if !text.trim().is_empty() {
+        actions = builtin_actions(&text);
+        actions.extend(ai_command_actions(app));
+    }
+    SelectionMenu {
+        app: context::active_window_info().map(|window| window.app_id),
+        text,
+        actions,
+    }
+}
+
+/// The hotkey: captures the selection while its app still has focus, then
+/// shows the menu
+pub fn open_menu(app: &AppHandle) {
+    let menu = capture(app);
+    if let Some(window) = app.get_webview_window("main") {
+        crate::activation::present(&window);
+    }
+    if let Err(e) = app.emit(SELECTION_ACTIONS_REQUESTED_EVENT, menu) {
+        tracing::error!(error = %e, "Failed to emit selection actions event");
+    }
+}
+
+/// The language to translate into when none is picked, from the locale
+fn locale_language() -> String {
+    std::env::var("LC_MESSAGES")
+        .or_else(|_| std::env::var("LANG"))
+        .ok()
+        .and_then(|locale| {
+            let language = locale.split(['_', '.', '@']).next()?.to_string();
+            (language.len() == 2).then_some(language)
+        })
+        .unwrap_or_else(|| "en".to_string())
+}
+
+/// Captures the selection for a menu the frontend opened itself
+#[tauri::command]
+pub fn get_selection_actions(app: AppHandle) -> SelectionMenu {
+    capture(&app)
+}
+
+/// Runs `action_id` on the selection the menu was made for. `argument` is
+/// the target language for `translate`.
+#[tauri::command]
+pub async fn run_selection_action(
+    app: AppHandle,
+    action_id: String,
+    argument: Option<String>,
+) -> Result<SelectionOutcome, String> {
+    let text = SELECTION
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(selection::get_text);
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err("Nothing is selected".to_string());
+    }
+
+    if let Some(command_id) = action_id.strip_prefix(AI_COMMAND_PREFIX) {
+        let command = app
+            .state::<AiCommandManager>()
+            .get(command_id)
+            .map_err(|e| e.to_string())?;
+        return Ok(SelectionOutcome::AiCommand {
+            command,
+            selection: text,
+        });
+    }
+
+    match action_id.as_str() {
+        "translate" => {
+            let target_lang = argument.unwrap_or_else(locale_language);
+            let translation =
+                translate::translate_text(app, Some(text), target_lang, None, None).await?;
+            Ok(SelectionOutcome::Translation { translation })
+        }
+        "define" => {
+            let definition = dictionary::define_word(app, Some(text), None).await?;
+            Ok(SelectionOutcome::Definition { definition })
+        }
+        "search_web" => {
+            // Not resolved by keyword: a selection starting with "g " is
+            // still the query
+            let engine = app
+                .state::<WebSearchManager>()
+                .fallback_engine()
+                .map_err(|e| e.to_string())?
+                .ok_or("No search engine configured")?;
+            web_search::web_search(app, Some(engine.id), text)?;
+            Ok(SelectionOutcome::Done)
+        }
+        "create_snippet" => Ok(SelectionOutcome::CreateSnippet { content: text }),
+        "create_quicklink" if is_link(&text) => {
+            Ok(SelectionOutcome::CreateQuicklink { link: text })
+        }
+        _ => Err(format!("Unknown selection action: {}", action_id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(text: &str) -> Vec<String> {
+        builtin_actions(text)
+            .into_iter()
+            .map(|action| action.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_actions_fit_the_selection() {
+        assert_eq!(
+            ids("serendipity"),
+            ["translate", "define", "search_web", "create_snippet"]
+        );
+        assert_eq!(
+            ids("https://example.com/docs"),
+            [
+                "translate",
+                "define",
+                "search_web",
+                "create_snippet",
+                "create_quicklink"
+            ]
+        );
+        let paragraph = "word ".repeat(200);
+        assert_eq!(ids(&paragraph), ["translate", "create_snippet"]);
+    }
+
+    #[test]
+    fn test_is_link() {
+        assert!(is_link("https://example.com"));
+        assert!(!is_link("mailto:someone@example.com"));
+        assert!(!is_link("example.com"));
+    }
+}
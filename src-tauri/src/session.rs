@@ -0,0 +1,307 @@
+//! Where the launcher was left: the query, the selected tab and command,
+//! and the stack of views the user navigated into. The frontend saves it as
+//! it changes and asks for it when the launcher opens; whether it gets one
+//! back depends on the restore setting, which either always resumes, always
+//! pops to the root search, or resumes only when it was closed recently.
+
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+const SESSIONS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS launcher_sessions (
+    window_label TEXT PRIMARY KEY,
+    query TEXT NOT NULL,
+    tab TEXT,
+    command TEXT,
+    navigation TEXT NOT NULL,
+    saved_at INTEGER NOT NULL
+)";
+
+const DEFAULT_WINDOW: &str = "main";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RestoreBehavior {
+    /// Reopen where the user left off
+    Resume,
+    /// Always open on the root search
+    PopToRoot,
+    /// Resume if the launcher was closed for less than `resume_within_secs`
+    #[default]
+    ResumeRecent,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SessionSettings {
+    pub restore: RestoreBehavior,
+    pub resume_within_secs: i64,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            restore: RestoreBehavior::default(),
+            resume_within_secs: 90,
+        }
+    }
+}
+
+impl SessionSettings {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("session_settings.json"))
+    }
+
+    fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+
+    /// Whether a session saved at `saved_at` should be reopened at `now`
+    fn resumes(&self, saved_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        match self.restore {
+            RestoreBehavior::Resume => true,
+            RestoreBehavior::PopToRoot => false,
+            RestoreBehavior::ResumeRecent => {
+                (now - saved_at).num_seconds() < self.resume_within_secs
+            }
+        }
+    }
+}
+
+/// One view pushed onto the launcher, e.g. an extension's list or detail
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigationEntry {
+    pub view: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Whatever the view needs to come back as it was; opaque to the backend
+    #[serde(default)]
+    pub state: serde_json::Value,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInput {
+    pub query: String,
+    pub tab: Option<String>,
+    pub command: Option<String>,
+    #[serde(default)]
+    pub navigation: Vec<NavigationEntry>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LauncherSession {
+    pub query: String,
+    pub tab: Option<String>,
+    pub command: Option<String>,
+    pub navigation: Vec<NavigationEntry>,
+    pub saved_at: DateTime<Utc>,
+}
+
+impl Storable for LauncherSession {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let navigation: String = row.get(3)?;
+        let saved_at: i64 = row.get(4)?;
+        Ok(LauncherSession {
+            query: row.get(0)?,
+            tab: row.get(1)?,
+            command: row.get(2)?,
+            // A stack the frontend no longer understands restores to the root
+            navigation: serde_json::from_str(&navigation).unwrap_or_default(),
+            saved_at: DateTime::from_timestamp(saved_at, 0).unwrap_or_default(),
+        })
+    }
+}
+
+pub struct SessionManager {
+    store: Store,
+}
+
+impl SessionManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "session.sqlite")?;
+        store.init_table(SESSIONS_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(SESSIONS_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    fn save(&self, window: &str, input: SessionInput, now: DateTime<Utc>) -> Result<(), AppError> {
+        let navigation = serde_json::to_string(&input.navigation)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        self.store.execute(
+            "INSERT INTO launcher_sessions (window_label, query, tab, command, navigation, saved_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(window_label) DO UPDATE SET query = excluded.query, tab = excluded.tab,
+             command = excluded.command, navigation = excluded.navigation,
+             saved_at = excluded.saved_at",
+            params![
+                window,
+                input.query,
+                input.tab,
+                input.command,
+                navigation,
+                now.timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get(&self, window: &str) -> Result<Option<LauncherSession>, AppError> {
+        self.store.query_row(
+            "SELECT query, tab, command, navigation, saved_at FROM launcher_sessions
+             WHERE window_label = ?",
+            params![window],
+        )
+    }
+
+    fn clear(&self, window: &str) -> Result<(), AppError> {
+        self.store.execute(
+            "DELETE FROM launcher_sessions WHERE window_label = ?",
+            params![window],
+        )?;
+        Ok(())
+    }
+}
+
+/// Saves the launcher's state for `window` (the main window by default)
+#[tauri::command]
+pub fn save_launcher_session(
+    app: AppHandle,
+    session: SessionInput,
+    window: Option<String>,
+) -> Result<(), String> {
+    app.state::<SessionManager>()
+        .save(
+            window.as_deref().unwrap_or(DEFAULT_WINDOW),
+            session,
+            Utc::now(),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// The session to reopen, or `None` when the launcher should start at the
+/// root search
+#[tauri::command]
+pub fn load_launcher_session(
+    app: AppHandle,
+    window: Option<String>,
+) -> Result<Option<LauncherSession>, String> {
+    let settings = SessionSettings::load(&app).map_err(|e| e.to_string())?;
+    let session = app
+        .state::<SessionManager>()
+        .get(window.as_deref().unwrap_or(DEFAULT_WINDOW))
+        .map_err(|e| e.to_string())?;
+    Ok(session.filter(|session| settings.resumes(session.saved_at, Utc::now())))
+}
+
+#[tauri::command]
+pub fn clear_launcher_session(app: AppHandle, window: Option<String>) -> Result<(), String> {
+    app.state::<SessionManager>()
+        .clear(window.as_deref().unwrap_or(DEFAULT_WINDOW))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_session_settings(app: AppHandle) -> Result<SessionSettings, String> {
+    SessionSettings::load(&app).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_session_settings(app: AppHandle, settings: SessionSettings) -> Result<(), String> {
+    if settings.resume_within_secs < 0 {
+        return Err("The resume window can't be negative".to_string());
+    }
+    settings.save(&app).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn input(query: &str, navigation: Vec<NavigationEntry>) -> SessionInput {
+        SessionInput {
+            query: query.to_string(),
+            tab: Some("apps".to_string()),
+            command: None,
+            navigation,
+        }
+    }
+
+    #[test]
+    fn test_save_replaces_and_clear_removes() {
+        let manager = SessionManager::new_for_test().unwrap();
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        manager
+            .save("main", input("fire", Vec::new()), now)
+            .unwrap();
+        let detail = NavigationEntry {
+            view: "extension".to_string(),
+            title: Some("GitHub".to_string()),
+            state: serde_json::json!({ "command": "search-repositories" }),
+        };
+        manager
+            .save("main", input("firefox", vec![detail.clone()]), now)
+            .unwrap();
+
+        let session = manager.get("main").unwrap().unwrap();
+        assert_eq!(session.query, "firefox");
+        assert_eq!(session.tab.as_deref(), Some("apps"));
+        assert_eq!(session.navigation, [detail]);
+        assert_eq!(session.saved_at, now);
+        assert!(manager.get("settings").unwrap().is_none());
+
+        manager.clear("main").unwrap();
+        assert!(manager.get("main").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_resumes() {
+        let saved_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let soon = saved_at + Duration::seconds(30);
+        let later = saved_at + Duration::minutes(10);
+        let settings = |restore| SessionSettings {
+            restore,
+            ..SessionSettings::default()
+        };
+
+        assert!(settings(RestoreBehavior::Resume).resumes(saved_at, later));
+        assert!(!settings(RestoreBehavior::PopToRoot).resumes(saved_at, soon));
+        assert!(settings(RestoreBehavior::ResumeRecent).resumes(saved_at, soon));
+        assert!(!settings(RestoreBehavior::ResumeRecent).resumes(saved_at, later));
+    }
+}
@@ -0,0 +1,131 @@
+//! KDE Connect through its daemon's DBus interface. Pairing and transfers
+//! are the daemon's business; we list the paired devices that are in reach
+//! and hand it files and text.
+
+use std::collections::HashMap;
+use std::path::Path;
+use zbus::zvariant::OwnedValue;
+
+const SERVICE: &str = "org.kde.kdeconnect";
+const DAEMON_PATH: &str = "/modules/kdeconnect";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Device {
+    pub id: String,
+    pub name: String,
+    /// `phone`, `tablet`, `desktop`, ...
+    pub device_type: Option<String>,
+}
+
+fn device_path(id: &str) -> String {
+    format!("{}/devices/{}", DAEMON_PATH, id)
+}
+
+/// KDE Connect wants URLs, and resolves `file://` ones itself
+fn file_url(path: &str) -> Result<String, String> {
+    let path = std::fs::canonicalize(Path::new(path))
+        .map_err(|e| format!("Can't share {}: {}", path, e))?;
+    url::Url::from_file_path(&path)
+        .map(String::from)
+        .map_err(|_| format!("Can't share {}", path.display()))
+}
+
+async fn device_type(connection: &zbus::Connection, id: &str) -> Option<String> {
+    let reply = connection
+        .call_method(
+            Some(SERVICE),
+            device_path(id).as_str(),
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &("org.kde.kdeconnect.device", "type"),
+        )
+        .await
+        .ok()?;
+    let value: OwnedValue = reply.body().deserialize().ok()?;
+    String::try_from(value).ok()
+}
+
+/// Paired devices that are reachable right now
+pub async fn devices() -> Result<Vec<Device>, String> {
+    let connection = zbus::Connection::session()
+        .await
+        .map_err(|e| e.to_string())?;
+    let reply = connection
+        .call_method(
+            Some(SERVICE),
+            DAEMON_PATH,
+            Some("org.kde.kdeconnect.daemon"),
+            "deviceNames",
+            &(true, true),
+        )
+        .await
+        .map_err(|e| format!("KDE Connect isn't running: {}", e))?;
+    let names: HashMap<String, String> = reply.body().deserialize().map_err(|e| e.to_string())?;
+
+    let mut devices = Vec::new();
+    for (id, name) in names {
+        let device_type = device_type(&connection, &id).await;
+        devices.push(Device {
+            id,
+            name,
+            device_type,
+        });
+    }
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(devices)
+}
+
+async fn call_share<B>(id: &str, method: &str, body: &B) -> Result<(), String>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    let connection = zbus::Connection::session()
+        .await
+        .map_err(|e| e.to_string())?;
+    connection
+        .call_method(
+            Some(SERVICE),
+            format!("{}/share", device_path(id)).as_str(),
+            Some("org.kde.kdeconnect.device.share"),
+            method,
+            body,
+        )
+        .await
+        .map_err(|e| format!("KDE Connect couldn't share: {}", e))?;
+    Ok(())
+}
+
+pub async fn send_files(id: &str, paths: &[String]) -> Result<(), String> {
+    let urls = paths
+        .iter()
+        .map(|path| file_url(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    call_share(id, "shareUrls", &(urls,)).await
+}
+
+pub async fn send_text(id: &str, text: &str) -> Result<(), String> {
+    call_share(id, "shareText", &(text,)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(
+            device_path("abc_123"),
+            "/modules/kdeconnect/devices/abc_123"
+        );
+
+        let dir = std::env::temp_dir().join(format!("flare kdeconnect {}", rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("my photo.jpg");
+        std::fs::write(&file, b"x").unwrap();
+        let url = file_url(&file.to_string_lossy()).unwrap();
+        assert!(url.starts_with("file:///"));
+        assert!(url.ends_with("/my%20photo.jpg"));
+        assert!(file_url(&dir.join("missing").to_string_lossy()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,488 @@
+//! The LocalSend v2 protocol: devices announce themselves as JSON over UDP
+//! multicast, and files go over HTTP(S) in two steps, `prepare-upload` to
+//! ask the receiver (who may decline) and one `upload` per file.
+//!
+//! LocalSend serves HTTPS with self-signed certificates identified by the
+//! fingerprint in their announcement, so certificate checks are off for
+//! these requests; they only ever go to addresses found on the local
+//! network.
+
+use futures_util::stream;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::net::UdpSocket;
+
+pub const PORT: u16 = 53317;
+pub(super) const MULTICAST_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 167);
+const PROTOCOL_VERSION: &str = "2.0";
+pub(super) const API: &str = "/api/localsend/v2";
+/// The receiver has to accept on their phone, which can take a while
+const PREPARE_TIMEOUT: Duration = Duration::from_secs(120);
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Who we say we are; the fingerprint only has to be unique per run since
+/// we don't serve HTTPS
+pub static IDENTITY: Lazy<DeviceInfo> = Lazy::new(|| DeviceInfo {
+    alias: format!(
+        "Flare on {}",
+        sysinfo::System::host_name().unwrap_or_else(|| "Linux".to_string())
+    ),
+    version: PROTOCOL_VERSION.to_string(),
+    device_model: Some("Linux".to_string()),
+    device_type: Some("desktop".to_string()),
+    fingerprint: uuid::Uuid::new_v4().simple().to_string(),
+    port: PORT,
+    protocol: "http".to_string(),
+    download: false,
+});
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    pub alias: String,
+    pub version: String,
+    #[serde(default)]
+    pub device_model: Option<String>,
+    /// `mobile`, `desktop`, `web`, `headless` or `server`
+    #[serde(default)]
+    pub device_type: Option<String>,
+    pub fingerprint: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_protocol")]
+    pub protocol: String,
+    #[serde(default)]
+    pub download: bool,
+}
+
+fn default_port() -> u16 {
+    PORT
+}
+
+fn default_protocol() -> String {
+    "https".to_string()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Announcement {
+    #[serde(flatten)]
+    pub info: DeviceInfo,
+    /// `true` when asking others to answer, `false` in an answer
+    #[serde(default)]
+    pub announce: bool,
+}
+
+/// A device that answered discovery, and where to reach it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Device {
+    pub info: DeviceInfo,
+    pub address: IpAddr,
+}
+
+impl Device {
+    pub(super) fn url(&self, path: &str) -> String {
+        let scheme = if self.info.protocol == "http" {
+            "http"
+        } else {
+            "https"
+        };
+        format!(
+            "{}://{}{}/{}",
+            scheme,
+            SocketAddr::new(self.address, self.info.port),
+            API,
+            path
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct FileMetadata {
+    pub id: String,
+    pub file_name: String,
+    pub size: u64,
+    pub file_type: String,
+    /// Text shown to the receiver straight away, for text shares
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PrepareUpload<'a> {
+    info: &'a DeviceInfo,
+    files: HashMap<String, FileMetadata>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PrepareResponse {
+    session_id: String,
+    /// File id to upload token
+    files: HashMap<String, String>,
+}
+
+/// Guessed from the extension; receivers use it for the icon and to decide
+/// where to save
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("heic") => "image/heic",
+        Some("svg") => "image/svg+xml",
+        Some("mp4") => "video/mp4",
+        Some("mov") => "video/quicktime",
+        Some("webm") => "video/webm",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg" | "opus") => "audio/ogg",
+        Some("flac") => "audio/flac",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        Some("apk") => "application/vnd.android.package-archive",
+        Some("txt" | "md") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+pub(super) fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .connect_timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// A socket in the multicast group. Other LocalSend apps on this machine
+/// listen on the same port, so it's shared.
+pub(super) fn multicast_socket() -> Result<UdpSocket, String> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket =
+        Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).map_err(|e| e.to_string())?;
+    socket.set_reuse_address(true).map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true).map_err(|e| e.to_string())?;
+    socket
+        .bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, PORT).into())
+        .map_err(|e| format!("Can't listen for LocalSend devices: {}", e))?;
+    socket
+        .join_multicast_v4(&MULTICAST_GROUP, &Ipv4Addr::UNSPECIFIED)
+        .map_err(|e| e.to_string())?;
+    socket.set_nonblocking(true).map_err(|e| e.to_string())?;
+    UdpSocket::from_std(socket.into()).map_err(|e| e.to_string())
+}
+
+/// Announces us and collects the devices that answer within `wait`
+pub async fn discover(wait: Duration) -> Result<Vec<Device>, String> {
+    let socket = multicast_socket()?;
+    let announcement = Announcement {
+        info: IDENTITY.clone(),
+        announce: true,
+    };
+    let message = serde_json::to_vec(&announcement).map_err(|e| e.to_string())?;
+    socket
+        .send_to(&message, SocketAddrV4::new(MULTICAST_GROUP, PORT))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut devices: HashMap<String, Device> = HashMap::new();
+    let mut buffer = vec![0u8; 8192];
+    let deadline = tokio::time::Instant::now() + wait;
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await
+    {
+        let Ok((len, from)) = received else {
+            break;
+        };
+        let Ok(announcement) = serde_json::from_slice::<Announcement>(&buffer[..len]) else {
+            continue;
+        };
+        if announcement.info.fingerprint == IDENTITY.fingerprint {
+            continue;
+        }
+        devices.insert(
+            announcement.info.fingerprint.clone(),
+            Device {
+                info: announcement.info,
+                address: from.ip(),
+            },
+        );
+    }
+    Ok(devices.into_values().collect())
+}
+
+/// Uploads `path` as one streamed request body, so large videos don't have
+/// to fit in memory
+async fn upload_file(
+    client: &reqwest::Client,
+    device: &Device,
+    session_id: &str,
+    file_id: &str,
+    token: &str,
+    path: &Path,
+) -> Result<(), String> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| format!("Can't open {}: {}", path.display(), e))?;
+    let chunks = stream::try_unfold(file, |mut file| async move {
+        let mut chunk = vec![0u8; CHUNK_SIZE];
+        let read = file.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok::<_, std::io::Error>(None);
+        }
+        chunk.truncate(read);
+        Ok(Some((chunk, file)))
+    });
+
+    let res = client
+        .post(device.url("upload"))
+        .query(&[
+            ("sessionId", session_id),
+            ("fileId", file_id),
+            ("token", token),
+        ])
+        .body(reqwest::Body::wrap_stream(chunks))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(format!(
+            "Upload of {} failed: {}",
+            path.display(),
+            res.status()
+        ));
+    }
+    Ok(())
+}
+
+fn prepare_error(status: reqwest::StatusCode) -> String {
+    match status.as_u16() {
+        403 => "The receiver declined".to_string(),
+        409 => "The receiver is busy with another transfer".to_string(),
+        429 => "Too many requests; try again in a moment".to_string(),
+        _ => format!("The receiver refused the transfer: {}", status),
+    }
+}
+
+/// Asks the receiver to accept `files` and returns the session, or `None`
+/// when there's nothing left to upload (text shown as a message)
+async fn prepare(
+    client: &reqwest::Client,
+    device: &Device,
+    files: HashMap<String, FileMetadata>,
+) -> Result<Option<PrepareResponse>, String> {
+    let res = client
+        .post(device.url("prepare-upload"))
+        .timeout(PREPARE_TIMEOUT)
+        .json(&PrepareUpload {
+            info: &IDENTITY,
+            files,
+        })
+        .send()
+        .await
+        .map_err(|e| format!("Can't reach {}: {}", device.info.alias, e))?;
+    match res.status() {
+        reqwest::StatusCode::NO_CONTENT => Ok(None),
+        status if status.is_success() => res.json().await.map(Some).map_err(|e| e.to_string()),
+        status => Err(prepare_error(status)),
+    }
+}
+
+async fn cancel(client: &reqwest::Client, device: &Device, session_id: &str) {
+    let _ = client
+        .post(device.url("cancel"))
+        .query(&[("sessionId", session_id)])
+        .send()
+        .await;
+}
+
+fn file_metadata(paths: &[String]) -> Result<HashMap<String, FileMetadata>, String> {
+    paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let path = Path::new(path);
+            let metadata = std::fs::metadata(path)
+                .map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+            if !metadata.is_file() {
+                return Err(format!("{} is not a file", path.display()));
+            }
+            let id = format!("file{}", index);
+            let file = FileMetadata {
+                id: id.clone(),
+                file_name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| id.clone()),
+                size: metadata.len(),
+                file_type: mime_type(path).to_string(),
+                preview: None,
+            };
+            Ok((id, file))
+        })
+        .collect()
+}
+
+/// Sends the files at `paths`, one after another
+pub async fn send_files(device: &Device, paths: &[String]) -> Result<(), String> {
+    let files = file_metadata(paths)?;
+    let by_id: HashMap<String, &String> = paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| (format!("file{}", index), path))
+        .collect();
+    let client = http_client()?;
+    let Some(session) = prepare(&client, device, files).await? else {
+        return Ok(());
+    };
+    for (file_id, token) in &session.files {
+        let Some(path) = by_id.get(file_id) else {
+            continue;
+        };
+        if let Err(e) = upload_file(
+            &client,
+            device,
+            &session.session_id,
+            file_id,
+            token,
+            Path::new(path),
+        )
+        .await
+        {
+            cancel(&client, device, &session.session_id).await;
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Sends `text` the way LocalSend's own apps do: as a text file whose
+/// preview is the text, which receivers show as a message
+pub async fn send_text(device: &Device, text: &str) -> Result<(), String> {
+    let id = "text".to_string();
+    let files = HashMap::from([(
+        id.clone(),
+        FileMetadata {
+            id: id.clone(),
+            file_name: "message.txt".to_string(),
+            size: text.len() as u64,
+            file_type: "text/plain".to_string(),
+            preview: Some(text.to_string()),
+        },
+    )]);
+    let client = http_client()?;
+    let Some(session) = prepare(&client, device, files).await? else {
+        return Ok(());
+    };
+    let Some(token) = session.files.get(&id) else {
+        return Ok(());
+    };
+    let res = client
+        .post(device.url("upload"))
+        .query(&[
+            ("sessionId", session.session_id.as_str()),
+            ("fileId", id.as_str()),
+            ("token", token.as_str()),
+        ])
+        .body(text.to_string())
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(format!("Sending text failed: {}", res.status()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_announcement_round_trip() {
+        let json = r#"{"alias":"Pixel","version":"2.1","deviceModel":"Google","deviceType":"mobile",
+            "fingerprint":"abc","port":53317,"protocol":"https","download":true,"announce":false}"#;
+        let announcement: Announcement = serde_json::from_str(json).unwrap();
+        assert_eq!(announcement.info.alias, "Pixel");
+        assert_eq!(announcement.info.device_type.as_deref(), Some("mobile"));
+        assert!(!announcement.announce);
+
+        let minimal: Announcement =
+            serde_json::from_str(r#"{"alias":"Old","version":"2.0","fingerprint":"x"}"#).unwrap();
+        assert_eq!(minimal.info.port, PORT);
+        assert_eq!(minimal.info.protocol, "https");
+
+        let ours = serde_json::to_value(Announcement {
+            info: IDENTITY.clone(),
+            announce: true,
+        })
+        .unwrap();
+        assert_eq!(ours["announce"], true);
+        assert_eq!(ours["deviceType"], "desktop");
+        assert_eq!(ours["protocol"], "http");
+    }
+
+    #[test]
+    fn test_device_url() {
+        let mut device = Device {
+            info: IDENTITY.clone(),
+            address: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)),
+        };
+        device.info.protocol = "https".to_string();
+        assert_eq!(
+            device.url("prepare-upload"),
+            "https://192.168.1.20:53317/api/localsend/v2/prepare-upload"
+        );
+        device.info.protocol = "http".to_string();
+        device.info.port = 8080;
+        assert_eq!(
+            device.url("upload"),
+            "http://192.168.1.20:8080/api/localsend/v2/upload"
+        );
+    }
+
+    #[test]
+    fn test_file_metadata() {
+        let dir = std::env::temp_dir().join(format!("flare_localsend_{}", rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let photo = dir.join("Photo.JPG");
+        std::fs::write(&photo, b"12345").unwrap();
+
+        let files = file_metadata(&[photo.to_string_lossy().into_owned()]).unwrap();
+        assert_eq!(
+            files["file0"],
+            FileMetadata {
+                id: "file0".to_string(),
+                file_name: "Photo.JPG".to_string(),
+                size: 5,
+                file_type: "image/jpeg".to_string(),
+                preview: None,
+            }
+        );
+        assert!(file_metadata(&[dir.to_string_lossy().into_owned()]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prepare_response() {
+        let response: PrepareResponse =
+            serde_json::from_str(r#"{"sessionId":"s1","files":{"file0":"t0"}}"#).unwrap();
+        assert_eq!(response.session_id, "s1");
+        assert_eq!(response.files["file0"], "t0");
+        assert_eq!(
+            prepare_error(reqwest::StatusCode::FORBIDDEN),
+            "The receiver declined"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! Sending files and text to nearby devices, over LocalSend or through KDE
+//! Connect. Discovery asks both at once; a transfer runs in the background
+//! and ends with a notification and a `share-finished` event. See
+//! [`receive`] for the other direction.
+
+mod kdeconnect;
+mod localsend;
+pub mod receive;
+
+use crate::notifications::{self, NotificationOptions, Urgency};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+const DEFAULT_DISCOVERY_WAIT: Duration = Duration::from_secs(2);
+const MAX_DISCOVERY_WAIT: Duration = Duration::from_secs(10);
+
+/// LocalSend devices from the last discovery, by fingerprint; sending needs
+/// their address
+static LOCALSEND_DEVICES: Lazy<Mutex<HashMap<String, localsend::Device>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ShareBackend {
+    LocalSend,
+    KdeConnect,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareDevice {
+    pub id: String,
+    pub name: String,
+    pub backend: ShareBackend,
+    pub device_type: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum SharePayload {
+    Files {
+        paths: Vec<String>,
+    },
+    Text {
+        text: String,
+    },
+    /// Whatever text is on the clipboard when the share starts
+    Clipboard,
+}
+
+/// `share-finished` payload
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ShareFinished {
+    transfer_id: String,
+    device_id: String,
+    device_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// What's being sent, resolved up front so the clipboard can't change under
+/// a transfer
+#[derive(Clone, Debug, PartialEq)]
+enum Content {
+    Files(Vec<String>),
+    Text(String),
+}
+
+impl Content {
+    fn describe(&self) -> String {
+        match self {
+            Content::Files(paths) if paths.len() == 1 => std::path::Path::new(&paths[0])
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "1 file".to_string()),
+            Content::Files(paths) => format!("{} files", paths.len()),
+            Content::Text(_) => "Text".to_string(),
+        }
+    }
+}
+
+fn resolve(app: &AppHandle, payload: SharePayload) -> Result<Content, String> {
+    let content = match payload {
+        SharePayload::Files { paths } => Content::Files(paths),
+        SharePayload::Text { text } => Content::Text(text),
+        SharePayload::Clipboard => Content::Text(app.clipboard().read_text().unwrap_or_default()),
+    };
+    match &content {
+        Content::Files(paths) if paths.is_empty() => Err("Nothing to share".to_string()),
+        Content::Files(paths) => match paths
+            .iter()
+            .find(|path| !std::path::Path::new(path).is_file())
+        {
+            Some(missing) => Err(format!("{} is not a file", missing)),
+            None => Ok(content),
+        },
+        Content::Text(text) if text.is_empty() => Err("Nothing to share".to_string()),
+        Content::Text(_) => Ok(content),
+    }
+}
+
+async fn send(backend: ShareBackend, device_id: &str, content: &Content) -> Result<(), String> {
+    match backend {
+        ShareBackend::LocalSend => {
+            let device = LOCALSEND_DEVICES
+                .lock()
+                .unwrap()
+                .get(device_id)
+                .cloned()
+                .ok_or("Device not found; search for devices again")?;
+            match content {
+                Content::Files(paths) => localsend::send_files(&device, paths).await,
+                Content::Text(text) => localsend::send_text(&device, text).await,
+            }
+        }
+        ShareBackend::KdeConnect => match content {
+            Content::Files(paths) => kdeconnect::send_files(device_id, paths).await,
+            Content::Text(text) => kdeconnect::send_text(device_id, text).await,
+        },
+    }
+}
+
+fn notify_finished(device_name: &str, content: &Content, result: &Result<(), String>) {
+    let (summary, body, urgency) = match result {
+        Ok(()) => (
+            format!("Sent to {}", device_name),
+            content.describe(),
+            Urgency::Normal,
+        ),
+        Err(e) => (
+            format!("Couldn't send to {}", device_name),
+            e.clone(),
+            Urgency::Critical,
+        ),
+    };
+    notifications::send_in_background(
+        summary,
+        body,
+        NotificationOptions {
+            urgency: Some(urgency),
+            icon: Some("document-send-symbolic".to_string()),
+            ..Default::default()
+        },
+    );
+}
+
+/// Devices on both backends; a backend that isn't available just finds
+/// nothing. `timeout_ms` is how long to wait for LocalSend devices to answer.
+#[tauri::command]
+pub async fn share_list_devices(timeout_ms: Option<u64>) -> Result<Vec<ShareDevice>, String> {
+    let wait = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_DISCOVERY_WAIT)
+        .min(MAX_DISCOVERY_WAIT);
+    let (localsend, kdeconnect) =
+        futures_util::join!(localsend::discover(wait), kdeconnect::devices());
+
+    let mut devices = Vec::new();
+    match localsend {
+        Ok(found) => {
+            let mut known = LOCALSEND_DEVICES.lock().unwrap();
+            known.clear();
+            for device in found {
+                devices.push(ShareDevice {
+                    id: device.info.fingerprint.clone(),
+                    name: device.info.alias.clone(),
+                    backend: ShareBackend::LocalSend,
+                    device_type: device.info.device_type.clone(),
+                });
+                known.insert(device.info.fingerprint.clone(), device);
+            }
+        }
+        Err(e) => tracing::debug!(error = %e, "LocalSend discovery failed"),
+    }
+    match kdeconnect {
+        Ok(found) => devices.extend(found.into_iter().map(|device| ShareDevice {
+            id: device.id,
+            name: device.name,
+            backend: ShareBackend::KdeConnect,
+            device_type: device.device_type,
+        })),
+        Err(e) => tracing::debug!(error = %e, "KDE Connect unavailable"),
+    }
+    devices.sort_by_key(|device| device.name.to_lowercase());
+    Ok(devices)
+}
+
+/// Starts sending to a device found by `share_list_devices` and returns the
+/// transfer's id, which `share-finished` reports back with
+#[tauri::command]
+pub fn share_send(
+    app: AppHandle,
+    backend: ShareBackend,
+    device_id: String,
+    device_name: String,
+    payload: SharePayload,
+) -> Result<String, String> {
+    let content = resolve(&app, payload)?;
+    let transfer_id = uuid::Uuid::new_v4().to_string();
+
+    let id = transfer_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = send(backend, &device_id, &content).await;
+        if let Err(e) = &result {
+            tracing::warn!(error = %e, backend = ?backend, "Share failed");
+        }
+        notify_finished(&device_name, &content, &result);
+        let _ = app.emit(
+            "share-finished",
+            ShareFinished {
+                transfer_id: id,
+                device_id,
+                device_name,
+                error: result.err(),
+            },
+        );
+    });
+    Ok(transfer_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_deserializes() {
+        assert_eq!(
+            serde_json::from_str::<SharePayload>(r#"{"kind":"files","paths":["/a"]}"#).unwrap(),
+            SharePayload::Files {
+                paths: vec!["/a".to_string()]
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<SharePayload>(r#"{"kind":"clipboard"}"#).unwrap(),
+            SharePayload::Clipboard
+        );
+        assert_eq!(
+            serde_json::from_str::<ShareBackend>(r#""kdeConnect""#).unwrap(),
+            ShareBackend::KdeConnect
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(
+            Content::Files(vec!["/tmp/photo.jpg".to_string()]).describe(),
+            "photo.jpg"
+        );
+        assert_eq!(
+            Content::Files(vec!["/a".to_string(), "/b".to_string()]).describe(),
+            "2 files"
+        );
+        assert_eq!(Content::Text("hi".to_string()).describe(), "Text");
+    }
+}
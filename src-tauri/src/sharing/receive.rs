@@ -0,0 +1,1118 @@
+//! Receive mode: a LocalSend-compatible inbox. While it's on we answer
+//! discovery, and every incoming transfer waits for the user to accept it,
+//! from the launcher (`share_receive_respond`) or the notification's
+//! buttons. Accepted files land in the downloads folder and the file index;
+//! a text message is copied to the clipboard instead.
+//!
+//! The HTTP side is just enough HTTP/1.1 for LocalSend's five endpoints, one
+//! request per connection.
+
+use super::localsend::{self, Announcement, DeviceInfo, FileMetadata, API, IDENTITY, PORT};
+use crate::browser_extension::protocol::tokens_match;
+use crate::file_search::manager::FileSearchManager;
+use crate::file_search::types::IndexedFile;
+use crate::notifications::{self, NotificationOptions};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddrV4};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+/// How long a transfer waits on the user before it's declined
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long a session may go without any upload before a new sender can
+/// take its place, and how long an upload may stall before it's dropped
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const MAX_CHUNK_LINE_BYTES: usize = 1024;
+const MAX_JSON_BYTES: usize = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const BUFFER_SIZE: usize = 64 * 1024;
+const ACCEPT_ACTION: &str = "share-accept";
+const DECLINE_ACTION: &str = "share-decline";
+
+/// The server's tasks, while receive mode is on
+static RECEIVER: Lazy<Mutex<Vec<tauri::async_runtime::JoinHandle<()>>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+/// The transfer in progress; LocalSend receivers take one at a time
+static SESSION: Lazy<Mutex<Option<Session>>> = Lazy::new(|| Mutex::new(None));
+/// Transfers waiting for the user, by request id
+static APPROVALS: Lazy<Mutex<HashMap<String, Approval>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct Approval {
+    decide: oneshot::Sender<bool>,
+    notification_id: Option<u32>,
+}
+
+struct IncomingFile {
+    meta: FileMetadata,
+    token: String,
+    destination: PathBuf,
+    done: bool,
+    /// Set while a request is writing the file, so a second upload of the
+    /// same file can't race it
+    uploading: bool,
+}
+
+struct Session {
+    id: String,
+    sender: String,
+    address: IpAddr,
+    files: HashMap<String, IncomingFile>,
+    /// When the session was prepared or an upload last ended
+    last_activity: Instant,
+}
+
+impl Session {
+    fn is_stale(&self) -> bool {
+        !self.files.values().any(|file| file.uploading)
+            && self.last_activity.elapsed() >= SESSION_IDLE_TIMEOUT
+    }
+}
+
+#[derive(Deserialize)]
+struct PrepareRequest {
+    info: DeviceInfo,
+    files: HashMap<String, FileMetadata>,
+}
+
+/// `share-receive-request` payload
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReceiveRequest {
+    request_id: String,
+    sender: String,
+    device_type: Option<String>,
+    files: Vec<FileMetadata>,
+}
+
+/// `share-receive-progress` payload
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReceiveProgress<'a> {
+    session_id: &'a str,
+    file_id: &'a str,
+    file_name: &'a str,
+    bytes_received: u64,
+    total_bytes: u64,
+}
+
+/// `share-receive-finished` payload
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReceiveFinished {
+    session_id: String,
+    sender: String,
+    paths: Vec<String>,
+    cancelled: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiveStatus {
+    pub running: bool,
+    pub alias: String,
+    pub port: u16,
+}
+
+#[derive(Debug, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Body {
+    Length(u64),
+    Chunked { left: usize, done: bool },
+}
+
+impl Request {
+    fn body(&self) -> Body {
+        let chunked = self
+            .headers
+            .get("transfer-encoding")
+            .is_some_and(|value| value.to_ascii_lowercase().contains("chunked"));
+        if chunked {
+            return Body::Chunked {
+                left: 0,
+                done: false,
+            };
+        }
+        Body::Length(
+            self.headers
+                .get("content-length")
+                .and_then(|len| len.trim().parse().ok())
+                .unwrap_or(0),
+        )
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// One line of the request head or a chunk header into `line`, reading no
+/// more than `limit` bytes. A line that doesn't end within the limit, or at
+/// all, is an error.
+async fn read_limited_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    line: &mut String,
+    limit: usize,
+) -> io::Result<usize> {
+    line.clear();
+    let len = (&mut *reader).take(limit as u64).read_line(line).await?;
+    if !line.ends_with('\n') {
+        return Err(invalid("Line too long or cut off"));
+    }
+    Ok(len)
+}
+
+/// Reads the request line and headers, no more than `MAX_HEAD_BYTES` in all
+async fn read_head<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Request> {
+    let mut line = String::new();
+    let mut read = read_limited_line(reader, &mut line, MAX_HEAD_BYTES).await?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(invalid("Malformed request line"));
+    };
+    let url = url::Url::parse(&format!("http://localhost{}", target))
+        .map_err(|_| invalid("Malformed request target"))?;
+    let mut request = Request {
+        method: method.to_string(),
+        path: url.path().to_string(),
+        query: url.query_pairs().into_owned().collect(),
+        headers: HashMap::new(),
+    };
+
+    loop {
+        read += read_limited_line(reader, &mut line, MAX_HEAD_BYTES - read).await?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            return Ok(request);
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            request
+                .headers
+                .insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+}
+
+/// The next piece of the body into `buffer`; 0 at the end
+async fn read_body<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    body: &mut Body,
+    buffer: &mut [u8],
+) -> io::Result<usize> {
+    match body {
+        Body::Length(0) => Ok(0),
+        Body::Length(remaining) => {
+            let want = (*remaining).min(buffer.len() as u64) as usize;
+            let read = reader.read(&mut buffer[..want]).await?;
+            if read == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            *remaining -= read as u64;
+            Ok(read)
+        }
+        Body::Chunked { done: true, .. } => Ok(0),
+        Body::Chunked { left, done } => {
+            if *left == 0 {
+                let mut line = String::new();
+                read_limited_line(reader, &mut line, MAX_CHUNK_LINE_BYTES).await?;
+                let size = line.trim().split(';').next().unwrap_or_default();
+                let size = usize::from_str_radix(size.trim(), 16)
+                    .map_err(|_| invalid("Malformed chunk size"))?;
+                if size == 0 {
+                    // Trailers, up to the blank line
+                    let mut trailers = 0;
+                    loop {
+                        let limit = MAX_HEAD_BYTES.saturating_sub(trailers).max(1);
+                        trailers += read_limited_line(reader, &mut line, limit).await?;
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                    }
+                    *done = true;
+                    return Ok(0);
+                }
+                *left = size;
+            }
+            let want = (*left).min(buffer.len());
+            let read = reader.read(&mut buffer[..want]).await?;
+            if read == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            *left -= read;
+            if *left == 0 {
+                let mut crlf = [0u8; 2];
+                reader.read_exact(&mut crlf).await?;
+            }
+            Ok(read)
+        }
+    }
+}
+
+async fn read_json<R: AsyncBufRead + Unpin, T: serde::de::DeserializeOwned>(
+    reader: &mut R,
+    request: &Request,
+) -> io::Result<T> {
+    let mut body = request.body();
+    let mut data = Vec::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    loop {
+        let read = read_body(reader, &mut body, &mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        data.extend_from_slice(&buffer[..read]);
+        if data.len() > MAX_JSON_BYTES {
+            return Err(invalid("Body too large"));
+        }
+    }
+    serde_json::from_slice(&data).map_err(|e| invalid(&e.to_string()))
+}
+
+async fn respond(stream: &mut TcpStream, status: u16, body: Option<Value>) {
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    };
+    let body = body.map(|body| body.to_string()).unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// The last path component of a name the sender chose, or `None` when
+/// there's nothing safe left
+fn safe_file_name(name: &str) -> Option<String> {
+    let name = name.replace('\\', "/");
+    let base = name.rsplit('/').next()?.trim();
+    match base {
+        "" | "." | ".." => None,
+        base => Some(base.to_string()),
+    }
+}
+
+/// `dir/name`, or `dir/name (2).ext` and so on when that's taken on disk or
+/// by another file in the same transfer
+fn unique_destination(dir: &Path, name: &str, taken: &HashSet<PathBuf>) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() && !taken.contains(&candidate) {
+        return candidate;
+    }
+    let path = Path::new(name);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.to_string());
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists() && !taken.contains(candidate))
+        .unwrap_or(candidate)
+}
+
+fn inbox_dir() -> PathBuf {
+    dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// A lone text file with a preview is a message, not a download
+fn as_message(files: &HashMap<String, FileMetadata>) -> Option<&str> {
+    match files.values().collect::<Vec<_>>().as_slice() {
+        [file] if file.file_type.starts_with("text/") => file.preview.as_deref(),
+        _ => None,
+    }
+}
+
+fn describe_request(sender: &str, files: &HashMap<String, FileMetadata>) -> String {
+    if let Some(text) = as_message(files) {
+        let preview: String = text.chars().take(120).collect();
+        return format!("{} sent a message: {}", sender, preview);
+    }
+    match files.values().next() {
+        Some(file) if files.len() == 1 => format!("{} wants to send {}", sender, file.file_name),
+        _ => format!("{} wants to send {} files", sender, files.len()),
+    }
+}
+
+/// Asks the user about a transfer and waits for the answer
+async fn ask(app: &AppHandle, info: &DeviceInfo, files: &HashMap<String, FileMetadata>) -> bool {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let (decide, decision) = oneshot::channel();
+    APPROVALS.lock().unwrap().insert(
+        request_id.clone(),
+        Approval {
+            decide,
+            notification_id: None,
+        },
+    );
+
+    let mut listed: Vec<FileMetadata> = files.values().cloned().collect();
+    listed.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    let _ = app.emit(
+        "share-receive-request",
+        ReceiveRequest {
+            request_id: request_id.clone(),
+            sender: info.alias.clone(),
+            device_type: info.device_type.clone(),
+            files: listed,
+        },
+    );
+    let options = NotificationOptions {
+        actions: vec![
+            (ACCEPT_ACTION.to_string(), "Accept".to_string()),
+            (DECLINE_ACTION.to_string(), "Decline".to_string()),
+        ],
+        persistent: true,
+        icon: Some("document-save-symbolic".to_string()),
+        ..Default::default()
+    };
+    match notifications::send(
+        "Incoming transfer",
+        &describe_request(&info.alias, files),
+        options,
+    )
+    .await
+    {
+        Ok(id) => {
+            if let Some(approval) = APPROVALS.lock().unwrap().get_mut(&request_id) {
+                approval.notification_id = Some(id);
+            }
+        }
+        Err(e) => tracing::debug!(error = %e, "Couldn't show the transfer prompt"),
+    }
+
+    let accepted = matches!(
+        tokio::time::timeout(APPROVAL_TIMEOUT, decision).await,
+        Ok(Ok(true))
+    );
+    APPROVALS.lock().unwrap().remove(&request_id);
+    accepted
+}
+
+fn decide(request_id: &str, accept: bool) -> bool {
+    match APPROVALS.lock().unwrap().remove(request_id) {
+        Some(approval) => approval.decide.send(accept).is_ok(),
+        None => false,
+    }
+}
+
+fn handle_action(notification_id: u32, action: &str) {
+    let accept = match action {
+        ACCEPT_ACTION => true,
+        DECLINE_ACTION => false,
+        _ => return,
+    };
+    let request_id = APPROVALS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(_, approval)| approval.notification_id == Some(notification_id))
+        .map(|(id, _)| id.clone());
+    if let Some(request_id) = request_id {
+        decide(&request_id, accept);
+    }
+}
+
+async fn prepare_upload(
+    app: &AppHandle,
+    from: IpAddr,
+    prepare: PrepareRequest,
+) -> (u16, Option<Value>) {
+    let stale = {
+        let mut current = SESSION.lock().unwrap();
+        if current.as_ref().is_some_and(Session::is_stale) {
+            current.take()
+        } else {
+            None
+        }
+    };
+    if let Some(session) = stale {
+        tracing::info!(sender = %session.sender, "Dropping idle share session");
+        finish(app, session, true);
+    }
+    if SESSION.lock().unwrap().is_some() {
+        return (
+            409,
+            Some(json!({ "message": "Blocked by another session" })),
+        );
+    }
+    if prepare.files.is_empty() {
+        return (400, None);
+    }
+    if !ask(app, &prepare.info, &prepare.files).await {
+        return (403, Some(json!({ "message": "Declined" })));
+    }
+
+    if let Some(text) = as_message(&prepare.files) {
+        if let Err(e) = app.clipboard().write_text(text.to_string()) {
+            tracing::warn!(error = %e, "Failed to copy received text");
+        }
+        notifications::send_in_background(
+            format!("Message from {}", prepare.info.alias),
+            "Copied to the clipboard".to_string(),
+            NotificationOptions::default(),
+        );
+        return (204, None);
+    }
+
+    let dir = inbox_dir();
+    let mut taken = HashSet::new();
+    let mut files = HashMap::new();
+    for (id, meta) in prepare.files {
+        let name = safe_file_name(&meta.file_name).unwrap_or_else(|| format!("{}.bin", id));
+        let destination = unique_destination(&dir, &name, &taken);
+        taken.insert(destination.clone());
+        files.insert(
+            id,
+            IncomingFile {
+                meta,
+                token: uuid::Uuid::new_v4().simple().to_string(),
+                destination,
+                done: false,
+                uploading: false,
+            },
+        );
+    }
+    let session = Session {
+        id: uuid::Uuid::new_v4().to_string(),
+        sender: prepare.info.alias,
+        address: from,
+        files,
+        last_activity: Instant::now(),
+    };
+    let tokens: HashMap<&String, &String> = session
+        .files
+        .iter()
+        .map(|(id, file)| (id, &file.token))
+        .collect();
+    let body = json!({ "sessionId": session.id, "files": tokens });
+
+    let mut current = SESSION.lock().unwrap();
+    if current.is_some() {
+        return (
+            409,
+            Some(json!({ "message": "Blocked by another session" })),
+        );
+    }
+    *current = Some(session);
+    (200, Some(body))
+}
+
+fn index_file(app: &AppHandle, path: &Path) {
+    let Some(manager) = app.try_state::<FileSearchManager>() else {
+        return;
+    };
+    let file = IndexedFile {
+        path: path.to_string_lossy().into_owned(),
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        parent_path: path
+            .parent()
+            .map(|parent| parent.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        file_type: "file".to_string(),
+        last_modified: Utc::now().timestamp(),
+    };
+    if let Err(e) = manager.add_file(&file) {
+        tracing::warn!(error = %e, "Failed to index received file");
+    }
+}
+
+/// Ends the session, announcing what it saved
+fn finish(app: &AppHandle, session: Session, cancelled: bool) {
+    let mut paths: Vec<String> = session
+        .files
+        .values()
+        .filter(|file| file.done)
+        .map(|file| file.destination.to_string_lossy().into_owned())
+        .collect();
+    paths.sort();
+    for file in session.files.values().filter(|file| !file.done) {
+        let _ = std::fs::remove_file(partial_path(&file.destination));
+    }
+    if !cancelled {
+        let body = match paths.as_slice() {
+            [path] => Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            _ => format!("{} files in Downloads", paths.len()),
+        };
+        notifications::send_in_background(
+            format!("Received from {}", session.sender),
+            body,
+            NotificationOptions::default(),
+        );
+    }
+    let _ = app.emit(
+        "share-receive-finished",
+        ReceiveFinished {
+            session_id: session.id,
+            sender: session.sender,
+            paths,
+            cancelled,
+        },
+    );
+}
+
+fn partial_path(destination: &Path) -> PathBuf {
+    let mut name = destination.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+/// Writes the upload to a `.part` file next to `destination`, moving it
+/// into place once complete, and returns where it ended up. The partial file
+/// is removed if anything fails, including a body larger than the size the
+/// sender announced.
+async fn receive_file<R: AsyncBufRead + Unpin>(
+    app: &AppHandle,
+    reader: &mut R,
+    request: &Request,
+    session_id: &str,
+    file_id: &str,
+    meta: &FileMetadata,
+    destination: &Path,
+) -> io::Result<PathBuf> {
+    let partial = partial_path(destination);
+    let mut file = tokio::fs::File::create(&partial).await?;
+    let written = write_body(app, reader, request, session_id, file_id, meta, &mut file).await;
+    drop(file);
+    let saved = match written {
+        Ok(()) => move_into_place(&partial, destination).await,
+        Err(e) => Err(e),
+    };
+    if saved.is_err() {
+        let _ = tokio::fs::remove_file(&partial).await;
+    }
+    saved
+}
+
+/// Links `partial` in at `destination`, or at the next free name if
+/// something has appeared there since the session was prepared, so an
+/// existing file is never replaced
+async fn move_into_place(partial: &Path, destination: &Path) -> io::Result<PathBuf> {
+    let dir = destination.parent().unwrap_or(Path::new("."));
+    let name = destination
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut target = destination.to_path_buf();
+    loop {
+        match tokio::fs::hard_link(partial, &target).await {
+            Ok(()) => break,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                target = unique_destination(dir, &name, &HashSet::from([target]));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let _ = tokio::fs::remove_file(partial).await;
+    Ok(target)
+}
+
+async fn write_body<R: AsyncBufRead + Unpin>(
+    app: &AppHandle,
+    reader: &mut R,
+    request: &Request,
+    session_id: &str,
+    file_id: &str,
+    meta: &FileMetadata,
+    file: &mut tokio::fs::File,
+) -> io::Result<()> {
+    let mut body = request.body();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut received = 0u64;
+    let mut last_emit = Instant::now();
+    loop {
+        let read = tokio::time::timeout(
+            SESSION_IDLE_TIMEOUT,
+            read_body(reader, &mut body, &mut buffer),
+        )
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+        if read == 0 {
+            break;
+        }
+        if received + read as u64 > meta.size {
+            return Err(invalid("Upload is larger than announced"));
+        }
+        file.write_all(&buffer[..read]).await?;
+        received += read as u64;
+        if last_emit.elapsed() >= PROGRESS_INTERVAL || received == meta.size {
+            last_emit = Instant::now();
+            let _ = app.emit(
+                "share-receive-progress",
+                ReceiveProgress {
+                    session_id,
+                    file_id,
+                    file_name: &meta.file_name,
+                    bytes_received: received,
+                    total_bytes: meta.size,
+                },
+            );
+        }
+    }
+    file.flush().await
+}
+
+/// Lets `file_id` be uploaded again after a failed attempt. Only touches the
+/// session the upload belongs to.
+fn release_upload(session_id: &str, file_id: &str) {
+    let mut current = SESSION.lock().unwrap();
+    if let Some(session) = current.as_mut().filter(|session| session.id == session_id) {
+        session.last_activity = Instant::now();
+        if let Some(file) = session.files.get_mut(file_id) {
+            file.uploading = false;
+        }
+    }
+}
+
+async fn upload<R: AsyncBufRead + Unpin>(
+    app: &AppHandle,
+    reader: &mut R,
+    from: IpAddr,
+    request: &Request,
+) -> (u16, Option<Value>) {
+    let param = |name: &str| request.query.get(name).cloned().unwrap_or_default();
+    let (session_id, file_id, token) = (param("sessionId"), param("fileId"), param("token"));
+    let target = {
+        let mut session = SESSION.lock().unwrap();
+        session.as_mut().and_then(|session| {
+            let valid_session = session.id == session_id && session.address == from;
+            let file = session.files.get_mut(&file_id)?;
+            let valid =
+                valid_session && tokens_match(&file.token, &token) && !file.done && !file.uploading;
+            file.uploading |= valid;
+            valid.then(|| (file.meta.clone(), file.destination.clone()))
+        })
+    };
+    let Some((meta, destination)) = target else {
+        return (403, Some(json!({ "message": "Invalid token or session" })));
+    };
+
+    let saved = match receive_file(
+        app,
+        reader,
+        request,
+        &session_id,
+        &file_id,
+        &meta,
+        &destination,
+    )
+    .await
+    {
+        Ok(saved) => saved,
+        Err(e) => {
+            release_upload(&session_id, &file_id);
+            tracing::warn!(error = %e, "Receiving file failed");
+            return (500, Some(json!({ "message": e.to_string() })));
+        }
+    };
+    index_file(app, &saved);
+
+    let finished = {
+        let mut current = SESSION.lock().unwrap();
+        if let Some(session) = current.as_mut().filter(|session| session.id == session_id) {
+            session.last_activity = Instant::now();
+            if let Some(file) = session.files.get_mut(&file_id) {
+                file.uploading = false;
+                file.done = true;
+                file.destination = saved;
+            }
+        }
+        let all_done = current.as_ref().is_some_and(|session| {
+            session.id == session_id && session.files.values().all(|f| f.done)
+        });
+        if all_done {
+            current.take()
+        } else {
+            None
+        }
+    };
+    if let Some(session) = finished {
+        finish(app, session, false);
+    }
+    (200, None)
+}
+
+fn cancel(app: &AppHandle, from: IpAddr, session_id: &str) -> (u16, Option<Value>) {
+    let cancelled = {
+        let mut current = SESSION.lock().unwrap();
+        let matches = current
+            .as_ref()
+            .is_some_and(|session| session.id == session_id && session.address == from);
+        if matches {
+            current.take()
+        } else {
+            None
+        }
+    };
+    if let Some(session) = cancelled {
+        finish(app, session, true);
+    }
+    (200, None)
+}
+
+async fn handle_connection(app: AppHandle, stream: TcpStream, from: IpAddr) {
+    let mut reader = BufReader::new(stream);
+    let request = match read_head(&mut reader).await {
+        Ok(request) => request,
+        Err(e) => {
+            tracing::debug!(error = %e, "Bad LocalSend request");
+            return;
+        }
+    };
+    let endpoint = request
+        .path
+        .strip_prefix(API)
+        .unwrap_or_default()
+        .trim_start_matches('/');
+
+    let (status, body) = match (request.method.as_str(), endpoint) {
+        ("GET", "info") | ("POST", "register") => (200, Some(json!(*IDENTITY))),
+        ("POST", "prepare-upload") => match read_json(&mut reader, &request).await {
+            Ok(prepare) => prepare_upload(&app, from, prepare).await,
+            Err(_) => (400, None),
+        },
+        ("POST", "upload") => upload(&app, &mut reader, from, &request).await,
+        ("POST", "cancel") => cancel(
+            &app,
+            from,
+            request
+                .query
+                .get("sessionId")
+                .map(String::as_str)
+                .unwrap_or_default(),
+        ),
+        _ => (404, None),
+    };
+    respond(reader.get_mut(), status, body).await;
+}
+
+/// Answers other devices' discovery announcements so we show up in their
+/// lists, and announces us once on start
+async fn answer_discovery() {
+    let socket = match localsend::multicast_socket() {
+        Ok(socket) => socket,
+        Err(e) => {
+            tracing::warn!(error = %e, "LocalSend discovery unavailable");
+            return;
+        }
+    };
+    let group = SocketAddrV4::new(localsend::MULTICAST_GROUP, PORT);
+    let announcement = |announce: bool| {
+        serde_json::to_vec(&Announcement {
+            info: IDENTITY.clone(),
+            announce,
+        })
+        .unwrap_or_default()
+    };
+    let _ = socket.send_to(&announcement(true), group).await;
+
+    let client = localsend::http_client().ok();
+    let mut buffer = vec![0u8; 8192];
+    loop {
+        let Ok((len, from)) = socket.recv_from(&mut buffer).await else {
+            continue;
+        };
+        let Ok(theirs) = serde_json::from_slice::<Announcement>(&buffer[..len]) else {
+            continue;
+        };
+        if !theirs.announce || theirs.info.fingerprint == IDENTITY.fingerprint {
+            continue;
+        }
+        // Register over HTTP as the protocol prefers, falling back to UDP
+        let device = localsend::Device {
+            info: theirs.info,
+            address: from.ip(),
+        };
+        let registered = match &client {
+            Some(client) => client
+                .post(device.url("register"))
+                .timeout(Duration::from_secs(2))
+                .json(&*IDENTITY)
+                .send()
+                .await
+                .is_ok_and(|res| res.status().is_success()),
+            None => false,
+        };
+        if !registered {
+            let _ = socket.send_to(&announcement(false), group).await;
+        }
+    }
+}
+
+/// Turns receive mode on; devices on the network can then find us and ask
+/// to send
+#[tauri::command]
+pub async fn share_receive_start(app: AppHandle) -> Result<ReceiveStatus, String> {
+    if !RECEIVER.lock().unwrap().is_empty() {
+        return Ok(status());
+    }
+    let listener = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, PORT))
+        .await
+        .map_err(|e| {
+            format!(
+                "Can't receive on port {} (is LocalSend running?): {}",
+                PORT, e
+            )
+        })?;
+
+    let server_app = app.clone();
+    let server = tauri::async_runtime::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, from)) => {
+                    tokio::spawn(handle_connection(server_app.clone(), stream, from.ip()));
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "LocalSend receiver failed to accept");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    });
+    let discovery = tauri::async_runtime::spawn(answer_discovery());
+    let actions = tauri::async_runtime::spawn(async {
+        let listener = notifications::listen_for_actions(|notification_id, action| {
+            handle_action(notification_id, &action)
+        });
+        if let Err(e) = listener.await {
+            tracing::debug!(error = %e, "Transfer prompts can't be answered from notifications");
+        }
+    });
+
+    *RECEIVER.lock().unwrap() = vec![server, discovery, actions];
+    tracing::info!(port = PORT, "Receiving files over LocalSend");
+    Ok(status())
+}
+
+/// Turns receive mode off, dropping any transfer in progress
+#[tauri::command]
+pub fn share_receive_stop(app: AppHandle) -> Result<(), String> {
+    for task in RECEIVER.lock().unwrap().drain(..) {
+        task.abort();
+    }
+    for (_, approval) in APPROVALS.lock().unwrap().drain() {
+        let _ = approval.decide.send(false);
+    }
+    let session = SESSION.lock().unwrap().take();
+    if let Some(session) = session {
+        finish(&app, session, true);
+    }
+    Ok(())
+}
+
+fn status() -> ReceiveStatus {
+    ReceiveStatus {
+        running: !RECEIVER.lock().unwrap().is_empty(),
+        alias: IDENTITY.alias.clone(),
+        port: PORT,
+    }
+}
+
+#[tauri::command]
+pub fn share_receive_status() -> ReceiveStatus {
+    status()
+}
+
+/// Accepts or declines a transfer announced by `share-receive-request`
+#[tauri::command]
+pub fn share_receive_respond(request_id: String, accept: bool) -> Result<(), String> {
+    if decide(&request_id, accept) {
+        Ok(())
+    } else {
+        Err("That transfer is no longer waiting".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, file_type: &str, preview: Option<&str>) -> FileMetadata {
+        FileMetadata {
+            id: name.to_string(),
+            file_name: name.to_string(),
+            size: 5,
+            file_type: file_type.to_string(),
+            preview: preview.map(String::from),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_head() {
+        let raw = b"POST /api/localsend/v2/upload?sessionId=s&fileId=f%201&token=t HTTP/1.1\r\n\
+                    Host: 10.0.0.2:53317\r\nContent-Length: 5\r\n\r\nhello";
+        let mut reader = &raw[..];
+        let request = read_head(&mut reader).await.unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/api/localsend/v2/upload");
+        assert_eq!(request.query["fileId"], "f 1");
+        assert_eq!(request.body(), Body::Length(5));
+
+        let mut buffer = [0u8; 16];
+        let mut body = request.body();
+        let read = read_body(&mut reader, &mut body, &mut buffer)
+            .await
+            .unwrap();
+        assert_eq!(&buffer[..read], b"hello");
+        assert_eq!(
+            read_body(&mut reader, &mut body, &mut buffer)
+                .await
+                .unwrap(),
+            0
+        );
+
+        let mut cut_off = &b"GET / HTTP/1.1\r\nHost: x\r\n"[..];
+        assert!(read_head(&mut cut_off).await.is_err());
+
+        let endless = vec![b'G'; MAX_HEAD_BYTES * 2];
+        assert!(read_head(&mut &endless[..]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chunked_body() {
+        let mut reader = &b"5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\n\r\n"[..];
+        let mut body = Body::Chunked {
+            left: 0,
+            done: false,
+        };
+        let mut buffer = [0u8; 4];
+        let mut data = Vec::new();
+        loop {
+            let read = read_body(&mut reader, &mut body, &mut buffer)
+                .await
+                .unwrap();
+            if read == 0 {
+                break;
+            }
+            data.extend_from_slice(&buffer[..read]);
+        }
+        assert_eq!(data, b"hello world");
+    }
+
+    #[tokio::test]
+    async fn test_chunk_lines_are_capped() {
+        let size = format!("{}\r\n", "0".repeat(MAX_CHUNK_LINE_BYTES));
+        let trailer = format!("0\r\nX-Pad: {}\r\n\r\n", "a".repeat(MAX_HEAD_BYTES));
+        for input in [size, trailer, "5\r\nhel".to_string()] {
+            let mut body = Body::Chunked {
+                left: 0,
+                done: false,
+            };
+            let mut buffer = [0u8; 16];
+            let mut reader = input.as_bytes();
+            let mut failed = false;
+            loop {
+                match read_body(&mut reader, &mut body, &mut buffer).await {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(_) => {
+                        failed = true;
+                        break;
+                    }
+                }
+            }
+            assert!(failed, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_safe_file_name() {
+        assert_eq!(safe_file_name("photo.jpg").as_deref(), Some("photo.jpg"));
+        assert_eq!(safe_file_name("../../.bashrc").as_deref(), Some(".bashrc"));
+        assert_eq!(safe_file_name("dir\\evil.exe").as_deref(), Some("evil.exe"));
+        assert_eq!(safe_file_name("a/.."), None);
+        assert_eq!(safe_file_name(""), None);
+    }
+
+    #[test]
+    fn test_unique_destination() {
+        let dir = std::env::temp_dir().join(format!("flare_receive_{}", rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), b"x").unwrap();
+
+        let mut taken = HashSet::new();
+        let first = unique_destination(&dir, "a.txt", &taken);
+        assert_eq!(first, dir.join("a (2).txt"));
+        taken.insert(first);
+        assert_eq!(
+            unique_destination(&dir, "a.txt", &taken),
+            dir.join("a (3).txt")
+        );
+        assert_eq!(unique_destination(&dir, "b", &taken), dir.join("b"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_move_into_place_keeps_existing_files() {
+        let dir = std::env::temp_dir().join(format!("flare_receive_{}", rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let destination = dir.join("a.txt");
+        let partial = partial_path(&destination);
+
+        std::fs::write(&partial, b"first").unwrap();
+        assert_eq!(
+            move_into_place(&partial, &destination).await.unwrap(),
+            destination
+        );
+        assert!(!partial.exists());
+
+        std::fs::write(&partial, b"second").unwrap();
+        let saved = move_into_place(&partial, &destination).await.unwrap();
+        assert_eq!(saved, dir.join("a (2).txt"));
+        assert_eq!(std::fs::read(&destination).unwrap(), b"first");
+        assert_eq!(std::fs::read(&saved).unwrap(), b"second");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_messages() {
+        let text = HashMap::from([("t".to_string(), file("t.txt", "text/plain", Some("hi")))]);
+        assert_eq!(as_message(&text), Some("hi"));
+        assert_eq!(describe_request("Pixel", &text), "Pixel sent a message: hi");
+
+        let photo = HashMap::from([("p".to_string(), file("p.jpg", "image/jpeg", None))]);
+        assert_eq!(as_message(&photo), None);
+        assert_eq!(
+            describe_request("Pixel", &photo),
+            "Pixel wants to send p.jpg"
+        );
+    }
+}
@@ -0,0 +1,530 @@
+//! The host tools the extension shims shell out to, which distro packages
+//! provide them, and installing the ones an extension is missing.
+//!
+//! An extension needs a tool when its code uses the AppleScript pattern that
+//! shim translates into that tool. Installing detects the package manager
+//! from `/etc/os-release`, runs it through `pkexec` so the user authorizes
+//! it, streams its output as `shim-install-output` events, then checks each
+//! tool with its test command and records the ones that now work.
+
+use crate::launcher::find_in_path;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use walkdir::WalkDir;
+
+const INSTALLED_FILE_NAME: &str = "installed_shims.json";
+/// Extension bundles beyond this are vendored chunks, not command code
+const MAX_SCANNED_FILE_SIZE: u64 = 8 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PackageManager {
+    Apt,
+    Dnf,
+    Pacman,
+    Zypper,
+}
+
+impl PackageManager {
+    /// From `/etc/os-release`, trying `ID` before the families in `ID_LIKE`
+    fn detect(os_release: &str) -> Option<Self> {
+        let field = |key: &str| {
+            os_release
+                .lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+                .map(|value| value.trim_matches('"').to_lowercase())
+                .unwrap_or_default()
+        };
+        let id = field("ID");
+        let id_like = field("ID_LIKE");
+        std::iter::once(id.as_str())
+            .chain(id_like.split_whitespace())
+            .find_map(|id| match id {
+                "debian" | "ubuntu" | "linuxmint" | "pop" | "elementary" | "raspbian" => {
+                    Some(PackageManager::Apt)
+                }
+                "fedora" | "rhel" | "centos" | "rocky" | "almalinux" | "nobara" => {
+                    Some(PackageManager::Dnf)
+                }
+                "arch" | "manjaro" | "endeavouros" | "cachyos" | "garuda" => {
+                    Some(PackageManager::Pacman)
+                }
+                "opensuse" | "opensuse-tumbleweed" | "opensuse-leap" | "suse" | "sles" => {
+                    Some(PackageManager::Zypper)
+                }
+                _ => None,
+            })
+    }
+
+    /// The non-interactive install command, before `pkexec`
+    fn install_command(self, packages: &[&str]) -> Vec<String> {
+        let base: &[&str] = match self {
+            PackageManager::Apt => &["apt-get", "install", "-y"],
+            PackageManager::Dnf => &["dnf", "install", "-y"],
+            PackageManager::Pacman => &["pacman", "-S", "--needed", "--noconfirm"],
+            PackageManager::Zypper => &["zypper", "--non-interactive", "install"],
+        };
+        base.iter()
+            .chain(packages)
+            .map(|arg| arg.to_string())
+            .collect()
+    }
+}
+
+/// A package that provides a tool, per package manager
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinuxPackage {
+    Apt(&'static str),
+    Dnf(&'static str),
+    Pacman(&'static str),
+    Zypper(&'static str),
+}
+
+impl LinuxPackage {
+    fn name_for(self, manager: PackageManager) -> Option<&'static str> {
+        match (self, manager) {
+            (LinuxPackage::Apt(name), PackageManager::Apt)
+            | (LinuxPackage::Dnf(name), PackageManager::Dnf)
+            | (LinuxPackage::Pacman(name), PackageManager::Pacman)
+            | (LinuxPackage::Zypper(name), PackageManager::Zypper) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+pub struct ShimTool {
+    pub command: &'static str,
+    pub purpose: &'static str,
+    /// AppleScript that the shims translate into this tool
+    pub triggers: &'static [&'static str],
+    /// Succeeds once the tool works
+    pub test_command: &'static [&'static str],
+    pub packages: &'static [LinuxPackage],
+}
+
+pub const SHIM_TOOLS: &[ShimTool] = &[
+    ShimTool {
+        command: "gtk-launch",
+        purpose: "Activating applications",
+        triggers: &["to activate"],
+        test_command: &["gtk-launch", "--version"],
+        packages: &[
+            LinuxPackage::Apt("libgtk-3-bin"),
+            LinuxPackage::Dnf("gtk3"),
+            LinuxPackage::Pacman("gtk3"),
+            LinuxPackage::Zypper("gtk3-tools"),
+        ],
+    },
+    ShimTool {
+        command: "xdg-open",
+        purpose: "Opening applications and locations",
+        triggers: &["to activate", "open location"],
+        test_command: &["xdg-open", "--version"],
+        packages: &[
+            LinuxPackage::Apt("xdg-utils"),
+            LinuxPackage::Dnf("xdg-utils"),
+            LinuxPackage::Pacman("xdg-utils"),
+            LinuxPackage::Zypper("xdg-utils"),
+        ],
+    },
+    ShimTool {
+        command: "pkill",
+        purpose: "Quitting applications",
+        triggers: &["to quit"],
+        test_command: &["pkill", "--version"],
+        packages: &[
+            LinuxPackage::Apt("procps"),
+            LinuxPackage::Dnf("procps-ng"),
+            LinuxPackage::Pacman("procps-ng"),
+            LinuxPackage::Zypper("procps"),
+        ],
+    },
+    ShimTool {
+        command: "notify-send",
+        purpose: "Showing notifications",
+        triggers: &["display notification"],
+        test_command: &["notify-send", "--version"],
+        packages: &[
+            LinuxPackage::Apt("libnotify-bin"),
+            LinuxPackage::Dnf("libnotify"),
+            LinuxPackage::Pacman("libnotify"),
+            LinuxPackage::Zypper("libnotify-tools"),
+        ],
+    },
+    ShimTool {
+        command: "pactl",
+        purpose: "Setting the volume",
+        triggers: &["set volume"],
+        test_command: &["pactl", "--version"],
+        packages: &[
+            LinuxPackage::Apt("pulseaudio-utils"),
+            LinuxPackage::Dnf("pulseaudio-utils"),
+            LinuxPackage::Pacman("libpulse"),
+            LinuxPackage::Zypper("pulseaudio-utils"),
+        ],
+    },
+];
+
+impl ShimTool {
+    fn package_for(&self, manager: PackageManager) -> Option<&'static str> {
+        self.packages
+            .iter()
+            .find_map(|package| package.name_for(manager))
+    }
+
+    fn is_present(&self) -> bool {
+        find_in_path(self.command).is_some()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledShim {
+    pub command: String,
+    pub package: String,
+    pub package_manager: PackageManager,
+    pub extension: String,
+    pub installed_at: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingTool {
+    pub command: String,
+    pub purpose: String,
+    /// `None` when the distro isn't recognised or has no package for it
+    pub package: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShimInstallReport {
+    pub installed: Vec<String>,
+    pub failed: Vec<String>,
+    /// Missing tools no package was found for
+    pub unavailable: Vec<String>,
+    pub command: Option<Vec<String>>,
+}
+
+/// `shim-install-output` payload
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct InstallOutput<'a> {
+    extension: &'a str,
+    stream: &'static str,
+    line: String,
+}
+
+/// The tools whose triggers appear in `sources`, which only count when the
+/// code runs AppleScript at all
+fn tools_needed_by<'a>(sources: impl IntoIterator<Item = &'a str>) -> Vec<&'static ShimTool> {
+    let mut needed: Vec<&'static ShimTool> = Vec::new();
+    for source in sources {
+        if !source.contains("runAppleScript") {
+            continue;
+        }
+        let source = source.to_lowercase();
+        for tool in SHIM_TOOLS {
+            let used = tool.triggers.iter().any(|trigger| source.contains(trigger));
+            if used && !needed.iter().any(|known| known.command == tool.command) {
+                needed.push(tool);
+            }
+        }
+    }
+    needed
+}
+
+fn extension_sources(extension_dir: &Path) -> Vec<String> {
+    WalkDir::new(extension_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "js"))
+        .filter(|entry| {
+            entry
+                .metadata()
+                .is_ok_and(|metadata| metadata.len() <= MAX_SCANNED_FILE_SIZE)
+        })
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .collect()
+}
+
+fn extension_dir(app: &AppHandle, slug: &str) -> Result<PathBuf, String> {
+    if slug.is_empty() || slug.contains('/') || slug.contains("..") {
+        return Err("Invalid extension".to_string());
+    }
+    let dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| "Failed to get app local data dir".to_string())?
+        .join("plugins")
+        .join(slug);
+    if !dir.is_dir() {
+        return Err(format!("Extension '{}' isn't installed", slug));
+    }
+    Ok(dir)
+}
+
+fn installed_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_local_data_dir()
+        .map(|dir| dir.join(INSTALLED_FILE_NAME))
+        .map_err(|_| "Failed to get app local data dir".to_string())
+}
+
+fn load_installed(path: &Path) -> Vec<InstalledShim> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn record_installed(path: &Path, shims: Vec<InstalledShim>) -> Result<(), String> {
+    let mut installed = load_installed(path);
+    installed.retain(|existing| !shims.iter().any(|new| new.command == existing.command));
+    installed.extend(shims);
+    let data = serde_json::to_string_pretty(&installed).map_err(|e| e.to_string())?;
+    fs::write(path, data).map_err(|e| e.to_string())
+}
+
+fn detect_package_manager() -> Option<PackageManager> {
+    fs::read_to_string("/etc/os-release")
+        .ok()
+        .and_then(|content| PackageManager::detect(&content))
+}
+
+fn missing_tools(extension_dir: &Path) -> Vec<&'static ShimTool> {
+    let sources = extension_sources(extension_dir);
+    tools_needed_by(sources.iter().map(String::as_str))
+        .into_iter()
+        .filter(|tool| !tool.is_present())
+        .collect()
+}
+
+async fn forward_lines<R: AsyncRead + Unpin>(
+    app: &AppHandle,
+    extension: &str,
+    stream: &'static str,
+    reader: Option<R>,
+) {
+    let Some(reader) = reader else { return };
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let _ = app.emit(
+            "shim-install-output",
+            InstallOutput {
+                extension,
+                stream,
+                line,
+            },
+        );
+    }
+}
+
+/// Runs the install command, forwarding its output line by line
+async fn run_streamed(app: &AppHandle, extension: &str, args: &[String]) -> Result<(), String> {
+    let mut child = tokio::process::Command::new("pkexec")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run pkexec: {}", e))?;
+
+    let stdout = forward_lines(app, extension, "stdout", child.stdout.take());
+    let stderr = forward_lines(app, extension, "stderr", child.stderr.take());
+    tokio::join!(stdout, stderr);
+
+    let status = child.wait().await.map_err(|e| e.to_string())?;
+    match status.code() {
+        Some(0) => Ok(()),
+        // pkexec's codes for a dismissed or refused authorization
+        Some(126) | Some(127) => Err("Authorization was declined".to_string()),
+        _ => Err(format!("The package manager failed ({})", status)),
+    }
+}
+
+async fn passes_test(tool: &ShimTool) -> bool {
+    let Some((program, args)) = tool.test_command.split_first() else {
+        return tool.is_present();
+    };
+    tokio::process::Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+/// The host tools an installed extension needs that aren't on this system,
+/// with the package that would provide each
+#[tauri::command]
+pub fn list_missing_tools(
+    app: AppHandle,
+    extension_slug: String,
+) -> Result<Vec<MissingTool>, String> {
+    let dir = extension_dir(&app, &extension_slug)?;
+    let manager = detect_package_manager();
+    Ok(missing_tools(&dir)
+        .into_iter()
+        .map(|tool| MissingTool {
+            command: tool.command.to_string(),
+            purpose: tool.purpose.to_string(),
+            package: manager
+                .and_then(|manager| tool.package_for(manager))
+                .map(str::to_string),
+        })
+        .collect())
+}
+
+/// Installs the packages for the tools the extension is missing, asking for
+/// authorization through pkexec
+#[tauri::command]
+pub async fn install_missing_tools(
+    app: AppHandle,
+    extension_slug: String,
+) -> Result<ShimInstallReport, String> {
+    let dir = extension_dir(&app, &extension_slug)?;
+    let missing = missing_tools(&dir);
+    let mut report = ShimInstallReport::default();
+    if missing.is_empty() {
+        return Ok(report);
+    }
+    let manager =
+        detect_package_manager().ok_or("Couldn't tell which package manager this system uses")?;
+    if find_in_path("pkexec").is_none() {
+        return Err("pkexec is needed to install packages".to_string());
+    }
+
+    let mut to_install: Vec<(&ShimTool, &str)> = Vec::new();
+    for tool in missing {
+        match tool.package_for(manager) {
+            Some(package) => to_install.push((tool, package)),
+            None => report.unavailable.push(tool.command.to_string()),
+        }
+    }
+    if to_install.is_empty() {
+        return Ok(report);
+    }
+    let mut packages: Vec<&str> = to_install.iter().map(|(_, package)| *package).collect();
+    packages.sort_unstable();
+    packages.dedup();
+    let command = manager.install_command(&packages);
+    tracing::info!(extension = %extension_slug, packages = ?packages, "Installing shim packages");
+    report.command = Some(command.clone());
+    run_streamed(&app, &extension_slug, &command).await?;
+
+    let now = Utc::now().timestamp();
+    let mut recorded = Vec::new();
+    for (tool, package) in to_install {
+        if passes_test(tool).await {
+            report.installed.push(tool.command.to_string());
+            recorded.push(InstalledShim {
+                command: tool.command.to_string(),
+                package: package.to_string(),
+                package_manager: manager,
+                extension: extension_slug.clone(),
+                installed_at: now,
+            });
+        } else {
+            report.failed.push(tool.command.to_string());
+        }
+    }
+    if !recorded.is_empty() {
+        record_installed(&installed_path(&app)?, recorded)?;
+    }
+    Ok(report)
+}
+
+/// Tools installed through `install_missing_tools`
+#[tauri::command]
+pub fn list_installed_shims(app: AppHandle) -> Result<Vec<InstalledShim>, String> {
+    Ok(load_installed(&installed_path(&app)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_package_manager() {
+        let ubuntu = "NAME=\"Ubuntu\"\nID=ubuntu\nID_LIKE=debian\n";
+        assert_eq!(PackageManager::detect(ubuntu), Some(PackageManager::Apt));
+        let derivative = "ID=\"somethingnew\"\nID_LIKE=\"rhel fedora\"\n";
+        assert_eq!(
+            PackageManager::detect(derivative),
+            Some(PackageManager::Dnf)
+        );
+        let tumbleweed = "ID=\"opensuse-tumbleweed\"\nID_LIKE=\"opensuse suse\"\n";
+        assert_eq!(
+            PackageManager::detect(tumbleweed),
+            Some(PackageManager::Zypper)
+        );
+        // VERSION_ID mustn't be read as ID
+        assert_eq!(PackageManager::detect("VERSION_ID=arch\n"), None);
+        assert_eq!(PackageManager::detect("ID=nixos\n"), None);
+    }
+
+    #[test]
+    fn test_install_command_and_packages() {
+        assert_eq!(
+            PackageManager::Pacman.install_command(&["libnotify"]),
+            vec!["pacman", "-S", "--needed", "--noconfirm", "libnotify"]
+        );
+        let notify = SHIM_TOOLS
+            .iter()
+            .find(|tool| tool.command == "notify-send")
+            .unwrap();
+        assert_eq!(
+            notify.package_for(PackageManager::Apt),
+            Some("libnotify-bin")
+        );
+        for tool in SHIM_TOOLS {
+            for manager in [
+                PackageManager::Apt,
+                PackageManager::Dnf,
+                PackageManager::Pacman,
+                PackageManager::Zypper,
+            ] {
+                assert!(tool.package_for(manager).is_some(), "{}", tool.command);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tools_needed_by() {
+        let code = r#"await runAppleScript('display notification "Hi"'); await runAppleScript(`tell application "Music" to quit`)"#;
+        let needed: Vec<&str> = tools_needed_by([code])
+            .iter()
+            .map(|tool| tool.command)
+            .collect();
+        assert_eq!(needed, vec!["pkill", "notify-send"]);
+        // Only AppleScript goes through the shims
+        assert!(tools_needed_by(["console.log('display notification')"]).is_empty());
+    }
+
+    #[test]
+    fn test_record_installed_replaces_by_command() {
+        let path = std::env::temp_dir().join(format!("flare_shims_{}.json", rand::random::<u32>()));
+        let shim = |command: &str, extension: &str| InstalledShim {
+            command: command.to_string(),
+            package: "pkg".to_string(),
+            package_manager: PackageManager::Apt,
+            extension: extension.to_string(),
+            installed_at: 1,
+        };
+        record_installed(&path, vec![shim("pactl", "a"), shim("pkill", "a")]).unwrap();
+        record_installed(&path, vec![shim("pactl", "b")]).unwrap();
+        let installed = load_installed(&path);
+        assert_eq!(installed.len(), 2);
+        assert_eq!(installed[1].extension, "b");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+//! Overwrite-then-delete for sensitive files: random passes and a final
+//! zero pass over the file's bytes, then it's truncated, renamed to a random
+//! name and unlinked.
+//!
+//! This only helps where writes land on the blocks the file already
+//! occupies. SSDs remap writes for wear levelling, and copy-on-write or
+//! log-structured filesystems (btrfs, ZFS, ...) write new blocks by design,
+//! so there the old contents can survive; snapshots and backups keep copies
+//! too. The warnings say so before and after.
+
+use crate::confirm::{self, ConfirmationRequest, DangerLevel};
+use rand::RngCore;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const DEFAULT_PASSES: u32 = 3;
+const MAX_PASSES: u32 = 35;
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+const GENERAL_WARNING: &str = "Shredding can't guarantee the data is gone on SSDs, flash drives or copy-on-write filesystems, and doesn't reach snapshots or backups. Full-disk encryption is the reliable protection.";
+
+/// Filesystems that write somewhere new instead of overwriting in place
+const COPY_ON_WRITE: &[&str] = &[
+    "btrfs", "zfs", "bcachefs", "f2fs", "nilfs2", "jffs2", "ubifs",
+];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShredReport {
+    pub path: String,
+    pub passes: u32,
+    pub bytes_overwritten: u64,
+    pub warnings: Vec<String>,
+}
+
+/// The type of the filesystem `path` is on, from the longest matching mount
+/// point in `mounts` (`/proc/self/mounts` format)
+fn filesystem_type(mounts: &str, path: &Path) -> Option<String> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            // Spaces in mount points are escaped as \040
+            let mount_point = fields.next()?.replace("\\040", " ");
+            let fs_type = fields.next()?;
+            path.starts_with(&mount_point)
+                .then(|| (mount_point.len(), fs_type.to_string()))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, fs_type)| fs_type)
+}
+
+fn filesystem_warning(fs_type: &str) -> Option<String> {
+    if COPY_ON_WRITE.contains(&fs_type) {
+        return Some(format!(
+            "This file is on {}, a copy-on-write filesystem: overwriting writes new blocks and the old contents stay on disk until they're reused.",
+            fs_type
+        ));
+    }
+    if fs_type == "tmpfs" {
+        return Some("This file is in memory (tmpfs); it may have been swapped out.".to_string());
+    }
+    None
+}
+
+/// What shredding `path` can't promise, most specific first
+fn warnings_for(path: &Path) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if let Some(warning) = fs::read_to_string("/proc/self/mounts")
+        .ok()
+        .and_then(|mounts| filesystem_type(&mounts, &canonical))
+        .and_then(|fs_type| filesystem_warning(&fs_type))
+    {
+        warnings.push(warning);
+    }
+    warnings.push(GENERAL_WARNING.to_string());
+    warnings
+}
+
+/// Writes `passes` rounds of random bytes, then zeros, over the whole file,
+/// flushing each to disk before the next
+fn overwrite(path: &Path, passes: u32) -> io::Result<u64> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let len = file.metadata()?.len();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut rng = rand::rng();
+
+    for pass in 0..=passes {
+        let random = pass < passes;
+        file.seek(SeekFrom::Start(0))?;
+        let mut remaining = len;
+        while remaining > 0 {
+            let chunk = remaining.min(BUFFER_SIZE as u64) as usize;
+            if random {
+                rng.fill_bytes(&mut buffer[..chunk]);
+            } else {
+                buffer[..chunk].fill(0);
+            }
+            file.write_all(&buffer[..chunk])?;
+            remaining -= chunk as u64;
+        }
+        file.sync_all()?;
+    }
+
+    file.set_len(0)?;
+    file.sync_all()?;
+    Ok(len * (passes as u64 + 1))
+}
+
+/// Renames the file to a random name in the same directory, so the
+/// original name doesn't linger in the directory entry
+fn anonymize(path: &Path) -> io::Result<PathBuf> {
+    let name_len = path.file_name().map_or(8, |name| name.len().clamp(8, 32));
+    let random: String = uuid::Uuid::new_v4()
+        .simple()
+        .to_string()
+        .chars()
+        .take(name_len)
+        .collect();
+    let renamed = path.with_file_name(random);
+    fs::rename(path, &renamed)?;
+    Ok(renamed)
+}
+
+fn shred(path: &Path, passes: u32) -> Result<u64, String> {
+    let metadata =
+        fs::symlink_metadata(path).map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+    if metadata.file_type().is_symlink() {
+        return Err("Won't shred a symlink; shred the file it points to instead".to_string());
+    }
+    if !metadata.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    if metadata.permissions().readonly() {
+        return Err(format!("{} is read-only", path.display()));
+    }
+
+    let overwritten = overwrite(path, passes)
+        .map_err(|e| format!("Failed to overwrite {}: {}", path.display(), e))?;
+    let renamed = anonymize(path).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Failed to rename file before deleting it");
+        path.to_path_buf()
+    });
+    fs::remove_file(&renamed)
+        .map_err(|e| format!("Overwrote but failed to delete the file: {}", e))?;
+    Ok(overwritten)
+}
+
+/// What shredding this file can't guarantee, to show before confirming
+#[tauri::command]
+pub fn shred_file_warnings(path: String) -> Vec<String> {
+    warnings_for(Path::new(&path))
+}
+
+/// Overwrites the file `passes` times (3 by default) plus a zero pass, then
+/// deletes it, once the user confirms with the warnings in front of them.
+/// There's no undo.
+#[tauri::command]
+pub async fn shred_file(
+    app: AppHandle,
+    path: String,
+    passes: Option<u32>,
+) -> Result<ShredReport, String> {
+    let passes = passes.unwrap_or(DEFAULT_PASSES).clamp(1, MAX_PASSES);
+    let warnings = warnings_for(Path::new(&path));
+    let name = Path::new(&path)
+        .file_name()
+        .map_or_else(|| path.clone(), |name| name.to_string_lossy().into_owned());
+    let request = ConfirmationRequest::new(format!("Shred “{}”?", name), DangerLevel::High)
+        .details(format!(
+            "It will be overwritten and deleted. This can't be undone.\n\n{}",
+            warnings.join("\n\n")
+        ))
+        .confirm_label("Shred");
+    if !confirm::request(&app, request).await {
+        return Err("Shredding was cancelled".to_string());
+    }
+    let target = PathBuf::from(&path);
+    let bytes_overwritten = tauri::async_runtime::spawn_blocking(move || shred(&target, passes))
+        .await
+        .map_err(|e| e.to_string())??;
+    tracing::info!(passes, "Shredded file");
+    Ok(ShredReport {
+        path,
+        passes,
+        bytes_overwritten,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("flare_shred_test_{}", rand::random::<u32>()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_shred_removes_file() {
+        let dir = temp_dir();
+        let path = dir.join("secret.txt");
+        fs::write(&path, vec![b'x'; BUFFER_SIZE + 10]).unwrap();
+
+        let overwritten = shred(&path, 2).unwrap();
+        assert_eq!(overwritten, 3 * (BUFFER_SIZE as u64 + 10));
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_overwrite_zeroes_then_truncates() {
+        let dir = temp_dir();
+        let path = dir.join("data");
+        fs::write(&path, b"hello").unwrap();
+        assert_eq!(overwrite(&path, 0).unwrap(), 5);
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_refuses_directories_and_missing_files() {
+        let dir = temp_dir();
+        assert!(shred(&dir, 1).is_err());
+        assert!(shred(&dir.join("missing"), 1).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_filesystem_type() {
+        let mounts = "/dev/sda1 / ext4 rw 0 0\n\
+                      /dev/sda2 /home btrfs rw 0 0\n\
+                      tmpfs /tmp tmpfs rw 0 0\n\
+                      /dev/sdb1 /media/my\\040disk vfat rw 0 0\n";
+        let fs_type = |path: &str| filesystem_type(mounts, Path::new(path));
+        assert_eq!(fs_type("/home/me/file").as_deref(), Some("btrfs"));
+        assert_eq!(fs_type("/etc/hosts").as_deref(), Some("ext4"));
+        assert_eq!(fs_type("/media/my disk/x").as_deref(), Some("vfat"));
+        // Path components, not string prefixes
+        assert_eq!(fs_type("/homework").as_deref(), Some("ext4"));
+
+        assert!(filesystem_warning("btrfs").is_some());
+        assert!(filesystem_warning("tmpfs").is_some());
+        assert!(filesystem_warning("ext4").is_none());
+    }
+}
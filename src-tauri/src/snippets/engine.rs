@@ -2,8 +2,10 @@ use crate::clipboard_history::manager::{
     ClipboardHistoryManager, MANAGER as CLIPBOARD_MANAGER_STATIC,
 };
 use crate::error::AppError;
+use crate::snippets::ime::{self, ImeMonitor};
 use crate::snippets::input_manager::{InputEvent, InputManager};
 use crate::snippets::manager::SnippetManager;
+use crate::snippets::triggers::{TriggerIndex, TypedKeys};
 use arboard::Clipboard;
 use chrono::{DateTime, Duration, Local, Months};
 use enigo::Key as EnigoKey;
@@ -18,7 +20,7 @@ use uuid::Uuid;
 const BUFFER_SIZE: usize = 30;
 const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
 
-static PLACEHOLDER_REGEX: Lazy<Regex> = Lazy::new(|| {
+pub(crate) static PLACEHOLDER_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r#"\{(?P<name>\w+)(?P<attributes>(?:\s+\w+=(?:"[^"]*"|\S+))*)?(?P<modifiers>(?:\s*\|\s*[\w%-]+)*)\}"#).unwrap()
 });
 static ATTRIBUTE_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -39,18 +41,32 @@ struct ParsedPlaceholder<'a> {
     modifiers: Vec<&'a str>,
 }
 
+/// The keyword index with what's been typed against it
+struct Matcher {
+    /// Snippet revision the index was built from
+    revision: Option<u64>,
+    index: TriggerIndex,
+    typed: TypedKeys,
+}
+
 pub struct ExpansionEngine {
-    buffer: Arc<Mutex<String>>,
+    matcher: Arc<Mutex<Matcher>>,
     snippet_manager: Arc<SnippetManager>,
     input_manager: Arc<dyn InputManager>,
+    ime: Arc<ImeMonitor>,
 }
 
 impl ExpansionEngine {
     pub fn new(snippet_manager: Arc<SnippetManager>, input_manager: Arc<dyn InputManager>) -> Self {
         Self {
-            buffer: Arc::new(Mutex::new(String::with_capacity(BUFFER_SIZE))),
+            matcher: Arc::new(Mutex::new(Matcher {
+                revision: None,
+                index: TriggerIndex::new(&[]),
+                typed: TypedKeys::new(BUFFER_SIZE),
+            })),
             snippet_manager,
             input_manager,
+            ime: Arc::new(ImeMonitor::default()),
         }
     }
 
@@ -64,52 +80,68 @@ impl ExpansionEngine {
 
     fn clone_for_thread(&self) -> Self {
         Self {
-            buffer: self.buffer.clone(),
+            matcher: self.matcher.clone(),
             snippet_manager: self.snippet_manager.clone(),
             input_manager: self.input_manager.clone(),
+            ime: self.ime.clone(),
+        }
+    }
+
+    /// Rebuilds the keyword index after snippets changed
+    fn refresh_index(&self, matcher: &mut Matcher) {
+        let revision = self.snippet_manager.revision();
+        if matcher.revision == Some(revision) {
+            return;
+        }
+        match self.snippet_manager.list_snippets(None) {
+            Ok(snippets) => {
+                matcher.index = TriggerIndex::new(&snippets);
+                matcher.typed.clear();
+                matcher.revision = Some(revision);
+            }
+            Err(e) => eprintln!("[ExpansionEngine] Failed to load snippets: {}", e),
         }
     }
 
     fn handle_key_press(&self, event: InputEvent) {
         let InputEvent::KeyPress(ch) = event;
-        let mut buffer = self.buffer.lock().unwrap();
+        let mut matcher = self.matcher.lock().unwrap();
+        self.refresh_index(&mut matcher);
+        let Matcher { index, typed, .. } = &mut *matcher;
 
-        match ch {
+        let state = match ch {
             '\u{8}' => {
-                buffer.pop();
+                typed.pop();
+                return;
             }
             '\n' | '\t' | '\u{1b}' => {
-                buffer.clear();
-            }
-            ch if ch.is_control() => (),
-            _ => {
-                buffer.push(ch);
-                if buffer.len() > BUFFER_SIZE {
-                    buffer.remove(0);
-                }
+                typed.clear();
+                return;
             }
-        }
+            ch if ch.is_control() => return,
+            _ => typed.push(index, ch),
+        };
 
-        if let Ok(snippets) = self.snippet_manager.list_snippets(None) {
-            for snippet in snippets {
-                if buffer.ends_with(&snippet.keyword) {
-                    let (keyword, content, id) =
-                        (snippet.keyword.clone(), snippet.content.clone(), snippet.id);
-                    let manager = self.snippet_manager.clone();
-                    drop(buffer);
-                    self.expand_snippet(&keyword, &content);
-                    thread::spawn(move || {
-                        let _ = manager.snippet_was_used(id);
-                    });
-                    break;
-                }
-            }
+        let Some(trigger) = index.matched(state).cloned() else {
+            return;
+        };
+        // The keys went into the input method's preedit, not the app
+        if ime::pause_while_composing() && self.ime.composing() {
+            typed.clear();
+            return;
         }
+        drop(matcher);
+
+        self.expand_snippet(&trigger.keyword, &trigger.content);
+        let manager = self.snippet_manager.clone();
+        thread::spawn(move || {
+            let _ = manager.snippet_was_used(trigger.id);
+        });
     }
 
     fn expand_snippet(&self, keyword: &str, content: &str) {
         let mut backspaces = String::new();
-        for _ in 0..keyword.len() {
+        for _ in 0..keyword.chars().count() {
             backspaces.push('\u{8}');
         }
 
@@ -160,12 +192,11 @@ impl ExpansionEngine {
             }
         });
 
-        let mut buffer = self.buffer.lock().unwrap();
-        buffer.clear();
+        self.matcher.lock().unwrap().typed.clear();
     }
 }
 
-fn parse_attributes(attr_str: &str) -> HashMap<&str, &str> {
+pub(crate) fn parse_attributes(attr_str: &str) -> HashMap<&str, &str> {
     ATTRIBUTE_REGEX
         .captures_iter(attr_str)
         .filter_map(|cap| {
@@ -201,7 +232,7 @@ fn apply_modifiers(mut value: String, modifiers: &[&str]) -> String {
     value
 }
 
-fn translate_date_format(format_str: &str) -> String {
+pub(crate) fn translate_date_format(format_str: &str) -> String {
     let mut result = String::with_capacity(format_str.len());
     let mut in_literal = false;
     let mut chars = format_str.chars().peekable();
@@ -0,0 +1,292 @@
+//! Input method awareness for snippet expansion.
+//!
+//! The expansion engine sees raw key presses. While fcitx5 or IBus is
+//! composing (pinyin, kana, hangul...), those keys go into the input
+//! method's preedit and the application receives the converted text
+//! instead, so a keyword matched on raw keys wasn't actually typed and the
+//! backspaces sent to erase it would eat into the preedit. Committed text
+//! isn't observable outside the focused application, so expansion steps
+//! aside while an input method is composing, unless the user turns that off.
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// Keywords typed in a burst share one lookup
+const STATE_TTL: Duration = Duration::from_millis(500);
+const FCITX_SERVICE: &str = "org.fcitx.Fcitx5";
+/// IBus runs on its own bus, but registers its portal on the session bus
+const IBUS_PORTAL_SERVICE: &str = "org.freedesktop.portal.IBus";
+/// `Controller1.State` while an input method rather than a layout is active
+const FCITX_STATE_ACTIVE: i32 = 2;
+
+static PAUSE_WHILE_COMPOSING: AtomicBool = AtomicBool::new(true);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum InputMethod {
+    Fcitx5,
+    IBus,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InputMethodStatus {
+    pub input_method: Option<InputMethod>,
+    pub composing: bool,
+    pub pause_while_composing: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+struct ExpansionSettings {
+    pause_while_composing: bool,
+}
+
+impl Default for ExpansionSettings {
+    fn default() -> Self {
+        Self {
+            pause_while_composing: true,
+        }
+    }
+}
+
+impl ExpansionSettings {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("snippet_expansion.json"))
+    }
+
+    fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+}
+
+/// The framework the environment points toolkits at, from the variables
+/// GTK, Qt, SDL and XIM clients read
+fn configured_input_method(var: impl Fn(&str) -> Option<String>) -> Option<InputMethod> {
+    [
+        "GTK_IM_MODULE",
+        "QT_IM_MODULE",
+        "SDL_IM_MODULE",
+        "XMODIFIERS",
+    ]
+    .iter()
+    .filter_map(|name| var(name))
+    .find_map(|value| {
+        let value = value.trim().to_lowercase();
+        let value = value.strip_prefix("@im=").unwrap_or(&value);
+        if value.starts_with("fcitx") {
+            Some(InputMethod::Fcitx5)
+        } else if value.starts_with("ibus") {
+            Some(InputMethod::IBus)
+        } else {
+            None
+        }
+    })
+}
+
+/// IBus engines named `xkb:...` are plain keyboard layouts
+fn ibus_engine_composes(engine: &str) -> bool {
+    let engine = engine.trim();
+    !engine.is_empty() && !engine.starts_with("xkb:")
+}
+
+pub fn pause_while_composing() -> bool {
+    PAUSE_WHILE_COMPOSING.load(Ordering::SeqCst)
+}
+
+/// Loads the expansion setting during setup
+pub fn init(app: &AppHandle) {
+    match ExpansionSettings::load(app) {
+        Ok(settings) => {
+            PAUSE_WHILE_COMPOSING.store(settings.pause_while_composing, Ordering::SeqCst)
+        }
+        Err(e) => tracing::warn!(error = %e, "Failed to read snippet expansion settings"),
+    }
+}
+
+/// Whether an input method is composing, looked up when a keyword matches.
+/// The framework is detected on every lookup, since input method daemons
+/// are often started after Flare at login.
+#[derive(Default)]
+pub struct ImeMonitor {
+    connection: OnceLock<Option<zbus::blocking::Connection>>,
+    state: Mutex<Option<(Instant, bool)>>,
+}
+
+impl ImeMonitor {
+    fn connection(&self) -> Option<&zbus::blocking::Connection> {
+        self.connection
+            .get_or_init(|| zbus::blocking::Connection::session().ok())
+            .as_ref()
+    }
+
+    fn has_owner(&self, service: &str) -> bool {
+        let Some(connection) = self.connection() else {
+            return false;
+        };
+        connection
+            .call_method(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                Some("org.freedesktop.DBus"),
+                "NameHasOwner",
+                &(service,),
+            )
+            .ok()
+            .and_then(|reply| reply.body().deserialize::<bool>().ok())
+            .unwrap_or(false)
+    }
+
+    /// The running framework, preferring the one the environment selects
+    pub fn input_method(&self) -> Option<InputMethod> {
+        let running = |method: InputMethod| match method {
+            InputMethod::Fcitx5 => self.has_owner(FCITX_SERVICE),
+            InputMethod::IBus => self.has_owner(IBUS_PORTAL_SERVICE),
+        };
+        configured_input_method(|name| std::env::var(name).ok())
+            .filter(|&method| running(method))
+            .or_else(|| {
+                [InputMethod::Fcitx5, InputMethod::IBus]
+                    .into_iter()
+                    .find(|&method| running(method))
+            })
+    }
+
+    fn query(&self) -> Result<bool, String> {
+        match self.input_method() {
+            Some(InputMethod::Fcitx5) => {
+                let connection = self.connection().ok_or("No session bus")?;
+                let reply = connection
+                    .call_method(
+                        Some(FCITX_SERVICE),
+                        "/controller",
+                        Some("org.fcitx.Fcitx.Controller1"),
+                        "State",
+                        &(),
+                    )
+                    .map_err(|e| e.to_string())?;
+                let state: i32 = reply.body().deserialize().map_err(|e| e.to_string())?;
+                Ok(state == FCITX_STATE_ACTIVE)
+            }
+            Some(InputMethod::IBus) => {
+                let output = Command::new("ibus")
+                    .arg("engine")
+                    .output()
+                    .map_err(|e| format!("Failed to run ibus: {}", e))?;
+                Ok(output.status.success()
+                    && ibus_engine_composes(&String::from_utf8_lossy(&output.stdout)))
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn composing(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if let Some((at, composing)) = *state {
+            if at.elapsed() < STATE_TTL {
+                return composing;
+            }
+        }
+        let composing = self.query().unwrap_or_else(|e| {
+            tracing::debug!(error = %e, "Failed to read the input method state");
+            false
+        });
+        *state = Some((Instant::now(), composing));
+        composing
+    }
+}
+
+#[tauri::command]
+pub fn snippet_input_method_status() -> InputMethodStatus {
+    let monitor = ImeMonitor::default();
+    InputMethodStatus {
+        input_method: monitor.input_method(),
+        composing: monitor.composing(),
+        pause_while_composing: pause_while_composing(),
+    }
+}
+
+#[tauri::command]
+pub fn set_snippet_pause_while_composing(app: AppHandle, enabled: bool) -> Result<(), String> {
+    ExpansionSettings {
+        pause_while_composing: enabled,
+    }
+    .save(&app)
+    .map_err(|e| e.to_string())?;
+    PAUSE_WHILE_COMPOSING.store(enabled, Ordering::SeqCst);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_configured_input_method() {
+        assert_eq!(configured_input_method(env(&[])), None);
+        assert_eq!(
+            configured_input_method(env(&[("XMODIFIERS", "@im=fcitx")])),
+            Some(InputMethod::Fcitx5)
+        );
+        assert_eq!(
+            configured_input_method(env(&[
+                ("GTK_IM_MODULE", "ibus"),
+                ("XMODIFIERS", "@im=fcitx")
+            ])),
+            Some(InputMethod::IBus)
+        );
+        // Wayland sessions with text-input set only some of them
+        assert_eq!(
+            configured_input_method(env(&[
+                ("GTK_IM_MODULE", "wayland"),
+                ("QT_IM_MODULE", "fcitx")
+            ])),
+            Some(InputMethod::Fcitx5)
+        );
+    }
+
+    #[test]
+    fn test_ibus_engine_composes() {
+        assert!(!ibus_engine_composes("xkb:us::eng\n"));
+        assert!(ibus_engine_composes("libpinyin\n"));
+        assert!(ibus_engine_composes("anthy"));
+        assert!(!ibus_engine_composes(""));
+    }
+}
@@ -1,11 +1,11 @@
-use crate::clipboard_history::manager::INTERNAL_CLIPBOARD_CHANGE;
-use anyhow::{Context, Result};
-use arboard::Clipboard;
+use crate::clipboard::ClipboardContent;
+use crate::paste::{self, PasteShortcut};
+use anyhow::{Context, Error, Result};
 use enigo::{Enigo, Key as EnigoKey, Keyboard};
 use lazy_static::lazy_static;
 use rdev::Key;
 use std::collections::HashMap;
-use std::sync::{atomic::Ordering, Arc, Mutex};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
@@ -14,166 +14,25 @@ use evdev::{uinput::VirtualDevice, KeyCode};
 #[cfg(target_os = "linux")]
 use xkbcommon::xkb;
 
-/// List of known terminal emulator WM_CLASS names (lowercase for comparison)
-const TERMINAL_CLASSES: &[&str] = &[
-    "gnome-terminal",
-    "konsole",
-    "xterm",
-    "urxvt",
-    "rxvt",
-    "terminator",
-    "tilix",
-    "alacritty",
-    "kitty",
-    "st",
-    "foot",
-    "wezterm",
-    "hyper",
-    "guake",
-    "yakuake",
-    "tilda",
-    "terminology",
-    "xfce4-terminal",
-    "lxterminal",
-    "mate-terminal",
-    "qterminal",
-    "sakura",
-    "termite",
-    "cool-retro-term",
-    "eterm",
-    "rio",
-    "warp",
-    "tabby",
-    "blackbox",
-    "contour",
-    "deepin-terminal",
-];
-
-/// Check if the currently focused X11 window is a terminal emulator
-#[cfg(target_os = "linux")]
-fn is_focused_window_terminal() -> bool {
-    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, GetPropertyReply};
-
-    let result = (|| -> Option<bool> {
-        let (conn, _screen_num) = x11rb::connect(None).ok()?;
-
-        // Get the focused window
-        let focus = conn.get_input_focus().ok()?.reply().ok()?;
-        let mut window = focus.focus;
-
-        // If focus is on root or None, no terminal
-        if window == x11rb::NONE || window == 0 {
-            return Some(false);
-        }
-
-        // Get WM_CLASS atom
-        let wm_class_atom = conn
-            .intern_atom(false, b"WM_CLASS")
-            .ok()?
-            .reply()
-            .ok()?
-            .atom;
-
-        // Try to get WM_CLASS from the focused window, walking up the tree if needed
-        for _ in 0..10 {
-            if window == x11rb::NONE || window == 0 {
-                break;
-            }
-
-            let reply: GetPropertyReply = conn
-                .get_property(false, window, wm_class_atom, AtomEnum::STRING, 0, 1024)
-                .ok()?
-                .reply()
-                .ok()?;
-
-            if reply.value_len > 0 {
-                // WM_CLASS is two null-terminated strings: instance name and class name
-                let value = String::from_utf8_lossy(&reply.value).to_lowercase();
-                let parts: Vec<&str> = value.split('\0').collect();
-
-                for part in parts {
-                    let part = part.trim();
-                    if part.is_empty() {
-                        continue;
-                    }
-                    for terminal in TERMINAL_CLASSES {
-                        if part.contains(terminal) {
-                            return Some(true);
-                        }
-                    }
-                }
-                // Found WM_CLASS but it's not a terminal
-                return Some(false);
-            }
-
-            // Walk up to parent window
-            let tree = conn.query_tree(window).ok()?.reply().ok()?;
-            if tree.parent == tree.root || tree.parent == x11rb::NONE {
-                break;
-            }
-            window = tree.parent;
-        }
-
-        Some(false)
-    })();
-
-    result.unwrap_or(false)
-}
-
 #[derive(Debug, Clone)]
 pub enum InputEvent {
     KeyPress(char),
 }
 
-struct InternalClipboardGuard;
-
-impl InternalClipboardGuard {
-    fn new() -> Self {
-        INTERNAL_CLIPBOARD_CHANGE.store(true, Ordering::SeqCst);
-        Self
-    }
-}
-
-impl Drop for InternalClipboardGuard {
-    fn drop(&mut self) {
-        INTERNAL_CLIPBOARD_CHANGE.store(false, Ordering::SeqCst);
-    }
-}
-
 pub trait InputManager: Send + Sync {
     fn start_listening(&self, callback: Box<dyn Fn(InputEvent) + Send + Sync>) -> Result<()>;
+    /// Pastes `text` through the clipboard; a run of backspaces is typed
     fn inject_text(&self, text: &str) -> Result<()>;
     fn inject_key_clicks(&self, key: EnigoKey, count: usize) -> Result<()>;
+    /// Presses the paste shortcut, leaving the clipboard to `paste`
+    fn send_paste(&self, shortcut: PasteShortcut) -> Result<()>;
 }
 
-fn with_clipboard_text<F>(text: &str, is_terminal: bool, paste_action: F) -> Result<()>
-where
-    F: FnOnce(bool) -> Result<()>,
-{
-    const CLIPBOARD_PASTE_DELAY: Duration = Duration::from_millis(5);
-    let _guard = InternalClipboardGuard::new();
-
-    let mut clipboard = Clipboard::new().context("Failed to initialize clipboard")?;
-    let original_content = clipboard.get_text().ok();
-
-    clipboard
-        .set_text(text)
-        .context("Failed to set clipboard text")?;
-    thread::sleep(CLIPBOARD_PASTE_DELAY);
-
-    let paste_result = paste_action(is_terminal);
-
-    thread::sleep(CLIPBOARD_PASTE_DELAY);
-
-    if let Some(original) = original_content {
-        if let Err(e) = clipboard.set_text(original) {
-            eprintln!("Failed to restore clipboard content: {}", e);
-        }
-    } else if let Err(e) = clipboard.set_text("") {
-        eprintln!("Failed to clear clipboard: {}", e);
+fn paste_text(keys: &dyn InputManager, text: &str) -> Result<()> {
+    if text.chars().all(|c| c == '\u{8}') {
+        return keys.inject_key_clicks(EnigoKey::Backspace, text.len());
     }
-
-    paste_result
+    paste::paste_with(keys, &ClipboardContent::from_text(text.to_string())).map_err(Error::msg)
 }
 
 pub struct RdevInputManager {
@@ -221,26 +80,7 @@ impl InputManager for RdevInputManager {
     }
 
     fn inject_text(&self, text: &str) -> Result<()> {
-        if text.chars().all(|c| c == '\u{8}') {
-            return self.inject_key_clicks(EnigoKey::Backspace, text.len());
-        }
-
-        let is_terminal = is_focused_window_terminal();
-
-        with_clipboard_text(text, is_terminal, |is_terminal| {
-            let mut enigo = self.enigo.lock().unwrap();
-            enigo.key(EnigoKey::Control, enigo::Direction::Press)?;
-            if is_terminal {
-                // Terminals use Ctrl+Shift+V for paste
-                enigo.key(EnigoKey::Shift, enigo::Direction::Press)?;
-            }
-            enigo.key(EnigoKey::Unicode('v'), enigo::Direction::Click)?;
-            if is_terminal {
-                enigo.key(EnigoKey::Shift, enigo::Direction::Release)?;
-            }
-            enigo.key(EnigoKey::Control, enigo::Direction::Release)?;
-            Ok(())
-        })
+        paste_text(self, text)
     }
 
     fn inject_key_clicks(&self, key: EnigoKey, count: usize) -> Result<()> {
@@ -250,6 +90,25 @@ impl InputManager for RdevInputManager {
         }
         Ok(())
     }
+
+    fn send_paste(&self, shortcut: PasteShortcut) -> Result<()> {
+        #[cfg(target_os = "macos")]
+        let modifier = EnigoKey::Meta;
+        #[cfg(not(target_os = "macos"))]
+        let modifier = EnigoKey::Control;
+
+        let mut enigo = self.enigo.lock().unwrap();
+        enigo.key(modifier, enigo::Direction::Press)?;
+        if shortcut == PasteShortcut::CtrlShiftV {
+            enigo.key(EnigoKey::Shift, enigo::Direction::Press)?;
+        }
+        enigo.key(EnigoKey::Unicode('v'), enigo::Direction::Click)?;
+        if shortcut == PasteShortcut::CtrlShiftV {
+            enigo.key(EnigoKey::Shift, enigo::Direction::Release)?;
+        }
+        enigo.key(modifier, enigo::Direction::Release)?;
+        Ok(())
+    }
 }
 
 // this implementation for wayland, because wayland is a pain and rdev no worky
@@ -512,60 +371,56 @@ impl InputManager for EvdevInputManager {
     }
 
     fn inject_text(&self, text: &str) -> Result<()> {
-        if text.chars().all(|c| c == '\u{8}') {
-            return self.inject_key_clicks(EnigoKey::Backspace, text.len());
+        paste_text(self, text)
+    }
+
+    fn inject_key_clicks(&self, key: EnigoKey, count: usize) -> Result<()> {
+        if let Some(keycode) = Self::enigo_to_evdev(key) {
+            let mut device = self.virtual_device.lock().unwrap();
+            for _ in 0..count {
+                self.send_key_click(&mut *device, keycode)?;
+            }
         }
+        Ok(())
+    }
 
-        let is_terminal = is_focused_window_terminal();
+    fn send_paste(&self, shortcut: PasteShortcut) -> Result<()> {
+        let mut device = self.virtual_device.lock().unwrap();
+        let syn = evdev::InputEvent::new(
+            evdev::EventType::SYNCHRONIZATION.0,
+            evdev::SynchronizationCode::SYN_REPORT.0,
+            0,
+        );
 
-        with_clipboard_text(text, is_terminal, |is_terminal| {
-            let mut device = self.virtual_device.lock().unwrap();
-            let syn = evdev::InputEvent::new(
-                evdev::EventType::SYNCHRONIZATION.0,
-                evdev::SynchronizationCode::SYN_REPORT.0,
-                0,
-            );
+        // Press Ctrl
+        device.emit(&[
+            evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTCTRL.0, 1),
+            syn.clone(),
+        ])?;
 
-            // Press Ctrl
+        // For terminals, also press Shift (Ctrl+Shift+V)
+        if shortcut == PasteShortcut::CtrlShiftV {
             device.emit(&[
-                evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTCTRL.0, 1),
+                evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTSHIFT.0, 1),
                 syn.clone(),
             ])?;
+        }
 
-            // For terminals, also press Shift (Ctrl+Shift+V)
-            if is_terminal {
-                device.emit(&[
-                    evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTSHIFT.0, 1),
-                    syn.clone(),
-                ])?;
-            }
-
-            self.send_key_click(&mut device, KeyCode::KEY_V)?;
-
-            // Release Shift if pressed
-            if is_terminal {
-                device.emit(&[
-                    evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTSHIFT.0, 0),
-                    syn.clone(),
-                ])?;
-            }
+        self.send_key_click(&mut device, KeyCode::KEY_V)?;
 
-            // Release Ctrl
+        // Release Shift if pressed
+        if shortcut == PasteShortcut::CtrlShiftV {
             device.emit(&[
-                evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTCTRL.0, 0),
-                syn,
+                evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTSHIFT.0, 0),
+                syn.clone(),
             ])?;
-            Ok(())
-        })
-    }
-
-    fn inject_key_clicks(&self, key: EnigoKey, count: usize) -> Result<()> {
-        if let Some(keycode) = Self::enigo_to_evdev(key) {
-            let mut device = self.virtual_device.lock().unwrap();
-            for _ in 0..count {
-                self.send_key_click(&mut *device, keycode)?;
-            }
         }
+
+        // Release Ctrl
+        device.emit(&[
+            evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTCTRL.0, 0),
+            syn,
+        ])?;
         Ok(())
     }
 }
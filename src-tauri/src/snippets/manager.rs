@@ -1,8 +1,10 @@
 use crate::error::AppError;
-use crate::snippets::types::Snippet;
+use crate::snippets::types::{Snippet, SnippetFolder};
 use crate::store::{Storable, Store};
 use chrono::{DateTime, Utc};
-use rusqlite::params;
+use rusqlite::{params, Connection};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tauri::AppHandle;
 
@@ -14,10 +16,106 @@ const SNIPPETS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS snippets (
     created_at INTEGER NOT NULL,
     updated_at INTEGER NOT NULL
 )";
+const TAGS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS snippet_tags (
+    snippet_id INTEGER NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (snippet_id, tag)
+)";
+const FTS_SCHEMA: &str = "CREATE VIRTUAL TABLE IF NOT EXISTS snippets_fts
+    USING fts5(name, keyword, content, content='snippets', content_rowid='id', tokenize = 'unicode61')";
+const FTS_TRIGGERS: &[&str] = &[
+    "CREATE TRIGGER IF NOT EXISTS snippets_after_insert AFTER INSERT ON snippets BEGIN
+        INSERT INTO snippets_fts(rowid, name, keyword, content) VALUES (new.id, new.name, new.keyword, new.content);
+     END;",
+    "CREATE TRIGGER IF NOT EXISTS snippets_after_delete AFTER DELETE ON snippets BEGIN
+        INSERT INTO snippets_fts(snippets_fts, rowid, name, keyword, content) VALUES ('delete', old.id, old.name, old.keyword, old.content);
+     END;",
+    "CREATE TRIGGER IF NOT EXISTS snippets_after_update AFTER UPDATE OF name, keyword, content ON snippets BEGIN
+        INSERT INTO snippets_fts(snippets_fts, rowid, name, keyword, content) VALUES ('delete', old.id, old.name, old.keyword, old.content);
+        INSERT INTO snippets_fts(rowid, name, keyword, content) VALUES (new.id, new.name, new.keyword, new.content);
+     END;",
+];
+
+/// Columns in the order `Storable for Snippet` reads them; tags are joined with
+/// the unit separator, which can't appear in a tag
+const SELECT_SNIPPETS: &str = "SELECT s.id, s.name, s.keyword, s.content, s.created_at, s.updated_at, s.times_used, s.last_used_at, s.folder,
+    (SELECT group_concat(tag, char(31)) FROM snippet_tags WHERE snippet_id = s.id)
+    FROM snippets s";
+const TAG_SEPARATOR: char = '\u{1f}';
+
+/// Each word becomes a quoted prefix query, so `sig em` matches "Email signature"
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| term.replace('"', ""))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"*", term))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn normalize_tags(tags: &[String]) -> BTreeSet<String> {
+    tags.iter()
+        .map(|tag| tag.replace(TAG_SEPARATOR, "").trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+fn normalize_folder(folder: Option<&str>) -> Option<String> {
+    let path: Vec<&str> = folder?
+        .split('/')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    (!path.is_empty()).then(|| path.join("/"))
+}
+
+/// Placeholders for an `IN (...)` list of `count` values
+fn in_list(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+/// Adds folders, tags and full-text search to a snippets table; safe to run
+/// on every start
+fn migrate_organization(db: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = db.prepare("PRAGMA table_info(snippets)")?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    if !columns.contains(&"folder".to_string()) {
+        db.execute("ALTER TABLE snippets ADD COLUMN folder TEXT", [])?;
+    }
+    db.execute(TAGS_SCHEMA, [])?;
+    db.execute(
+        "CREATE INDEX IF NOT EXISTS idx_snippet_tags_tag ON snippet_tags(tag)",
+        [],
+    )?;
+
+    let has_fts: bool = db.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'snippets_fts')",
+        [],
+        |row| row.get(0),
+    )?;
+    db.execute(FTS_SCHEMA, [])?;
+    for trigger in FTS_TRIGGERS {
+        db.execute(trigger, [])?;
+    }
+    if !has_fts {
+        // Index the snippets that existed before full-text search was added
+        db.execute(
+            "INSERT INTO snippets_fts(snippets_fts) VALUES ('rebuild')",
+            [],
+        )?;
+    }
+    Ok(())
+}
 
 #[derive(Clone)]
 pub struct SnippetManager {
     store: Arc<Store>,
+    /// Bumped whenever a keyword or content may have changed, so the
+    /// expansion engine knows to rebuild its keyword index
+    revision: Arc<AtomicU64>,
 }
 
 impl Storable for Snippet {
@@ -25,6 +123,11 @@ impl Storable for Snippet {
         let created_at_ts: i64 = row.get(4)?;
         let updated_at_ts: i64 = row.get(5)?;
         let last_used_at_ts: i64 = row.get(7)?;
+        let tags: Option<String> = row.get(9)?;
+        let mut tags: Vec<String> = tags
+            .map(|tags| tags.split(TAG_SEPARATOR).map(String::from).collect())
+            .unwrap_or_default();
+        tags.sort();
         Ok(Snippet {
             id: row.get(0)?,
             name: row.get(1)?,
@@ -34,6 +137,8 @@ impl Storable for Snippet {
             updated_at: DateTime::from_timestamp_nanos(updated_at_ts),
             times_used: row.get(6)?,
             last_used_at: DateTime::from_timestamp_nanos(last_used_at_ts),
+            folder: row.get(8)?,
+            tags,
         })
     }
 }
@@ -68,10 +173,12 @@ impl SnippetManager {
                 "CREATE INDEX IF NOT EXISTS idx_snippets_keyword ON snippets(keyword)",
                 [],
             )?;
+            migrate_organization(&db)?;
         }
 
         Ok(Self {
             store: Arc::new(store),
+            revision: Arc::new(AtomicU64::new(0)),
         })
     }
 
@@ -90,10 +197,12 @@ impl SnippetManager {
                 "ALTER TABLE snippets ADD COLUMN last_used_at INTEGER NOT NULL DEFAULT 0",
                 [],
             )?;
+            migrate_organization(&db)?;
         }
 
         Ok(Self {
             store: Arc::new(store),
+            revision: Arc::new(AtomicU64::new(0)),
         })
     }
 
@@ -109,11 +218,20 @@ impl SnippetManager {
              VALUES (?1, ?2, ?3, ?4, ?4, 0, 0)",
             params![name, keyword, content, now],
         )?;
+        self.bump_revision();
         Ok(self.store.last_insert_rowid())
     }
 
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    fn bump_revision(&self) {
+        self.revision.fetch_add(1, Ordering::SeqCst);
+    }
+
     pub fn list_snippets(&self, search_term: Option<String>) -> Result<Vec<Snippet>, AppError> {
-        let mut query = "SELECT id, name, keyword, content, created_at, updated_at, times_used, last_used_at FROM snippets".to_string();
+        let mut query = SELECT_SNIPPETS.to_string();
 
         if let Some(term) = search_term {
             if !term.is_empty() {
@@ -140,15 +258,131 @@ impl SnippetManager {
             "UPDATE snippets SET name = ?1, keyword = ?2, content = ?3, updated_at = ?4 WHERE id = ?5",
             params![name, keyword, content, now, id],
         )?;
+        self.bump_revision();
         Ok(())
     }
 
     pub fn delete_snippet(&self, id: i64) -> Result<(), AppError> {
-        self.store
-            .execute("DELETE FROM snippets WHERE id = ?1", params![id])?;
+        self.delete_snippets(&[id]).map(|_| ())
+    }
+
+    /// Deletes the snippets and their tags; returns how many were removed
+    pub fn delete_snippets(&self, ids: &[i64]) -> Result<usize, AppError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let list = in_list(ids.len());
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        tx.execute(
+            &format!("DELETE FROM snippet_tags WHERE snippet_id IN ({})", list),
+            rusqlite::params_from_iter(ids),
+        )?;
+        let deleted = tx.execute(
+            &format!("DELETE FROM snippets WHERE id IN ({})", list),
+            rusqlite::params_from_iter(ids),
+        )?;
+        tx.commit()?;
+        self.bump_revision();
+        Ok(deleted)
+    }
+
+    /// Moves the snippets into `folder`, or to the top level for `None`
+    pub fn move_snippets(&self, ids: &[i64], folder: Option<&str>) -> Result<usize, AppError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let folder = normalize_folder(folder);
+        let params = std::iter::once(rusqlite::types::Value::from(folder))
+            .chain(ids.iter().map(|id| rusqlite::types::Value::from(*id)));
+        self.store.execute(
+            &format!(
+                "UPDATE snippets SET folder = ? WHERE id IN ({})",
+                in_list(ids.len())
+            ),
+            rusqlite::params_from_iter(params),
+        )
+    }
+
+    pub fn list_folders(&self) -> Result<Vec<SnippetFolder>, AppError> {
+        let db = self.store.conn();
+        let mut stmt = db.prepare(
+            "SELECT folder, COUNT(*) FROM snippets WHERE folder IS NOT NULL
+             GROUP BY folder ORDER BY folder COLLATE NOCASE",
+        )?;
+        let folders = stmt
+            .query_map([], |row| {
+                Ok(SnippetFolder {
+                    path: row.get(0)?,
+                    snippet_count: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(folders)
+    }
+
+    /// Replaces the snippet's tags
+    pub fn set_tags(&self, id: i64, tags: &[String]) -> Result<(), AppError> {
+        let mut db = self.store.conn();
+        let tx = db.transaction()?;
+        tx.execute(
+            "DELETE FROM snippet_tags WHERE snippet_id = ?1",
+            params![id],
+        )?;
+        for tag in normalize_tags(tags) {
+            tx.execute(
+                "INSERT INTO snippet_tags (snippet_id, tag) VALUES (?1, ?2)",
+                params![id, tag],
+            )?;
+        }
+        tx.commit()?;
         Ok(())
     }
 
+    /// Every tag in use, with how many snippets carry it
+    pub fn list_tags(&self) -> Result<Vec<(String, i64)>, AppError> {
+        let db = self.store.conn();
+        let mut stmt = db.prepare(
+            "SELECT tag, COUNT(*) FROM snippet_tags GROUP BY tag ORDER BY tag COLLATE NOCASE",
+        )?;
+        let tags = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tags)
+    }
+
+    /// Full-text search over name, keyword and content, best matches first.
+    /// `tag` and `folder` narrow the results; a folder includes its subfolders.
+    pub fn search_snippets(
+        &self,
+        query: &str,
+        tag: Option<&str>,
+        folder: Option<&str>,
+    ) -> Result<Vec<Snippet>, AppError> {
+        let folder = normalize_folder(folder);
+        let filters = "(?2 IS NULL OR EXISTS (
+                SELECT 1 FROM snippet_tags t WHERE t.snippet_id = s.id AND t.tag = ?2))
+             AND (?3 IS NULL OR s.folder = ?3 OR s.folder LIKE ?3 || '/%')";
+        match fts_query(query) {
+            Some(fts) => self.store.query(
+                &format!(
+                    "{} JOIN snippets_fts ON snippets_fts.rowid = s.id
+                     WHERE snippets_fts MATCH ?1 AND {}
+                     ORDER BY bm25(snippets_fts), s.times_used DESC",
+                    SELECT_SNIPPETS, filters
+                ),
+                params![fts, tag, folder],
+            ),
+            None => self.store.query(
+                &format!(
+                    "{} WHERE ?1 IS NULL AND {} ORDER BY s.updated_at DESC",
+                    SELECT_SNIPPETS, filters
+                ),
+                params![Option::<String>::None, tag, folder],
+            ),
+        }
+    }
+
     pub fn snippet_was_used(&self, id: i64) -> Result<(), AppError> {
         let now = Utc::now().timestamp_nanos_opt().unwrap_or_default();
         self.store.execute(
@@ -158,17 +392,19 @@ impl SnippetManager {
         Ok(())
     }
 
-    #[cfg(test)]
     pub fn find_snippet_by_keyword(&self, keyword: &str) -> Result<Option<Snippet>, AppError> {
         self.store.query_row(
-            "SELECT id, name, keyword, content, created_at, updated_at, times_used, last_used_at FROM snippets WHERE keyword = ?1",
+            &format!("{} WHERE keyword = ?1", SELECT_SNIPPETS),
             params![keyword],
         )
     }
 
     pub fn find_snippet_by_name(&self, name: &str) -> Result<Option<Snippet>, AppError> {
         self.store.query_row(
-            "SELECT id, name, keyword, content, created_at, updated_at, times_used, last_used_at FROM snippets WHERE name = ?1 ORDER BY updated_at DESC LIMIT 1",
+            &format!(
+                "{} WHERE name = ?1 ORDER BY updated_at DESC LIMIT 1",
+                SELECT_SNIPPETS
+            ),
             params![name],
         )
     }
@@ -323,4 +559,106 @@ mod tests {
         let not_found = manager.find_snippet_by_name("Non Existent").unwrap();
         assert!(not_found.is_none());
     }
+
+    #[test]
+    fn test_search_snippets_with_tags_and_folders() {
+        let manager = SnippetManager::new_for_test().unwrap();
+        let sig = manager
+            .create_snippet(
+                "Email Signature".into(),
+                ";sig".into(),
+                "Best regards".into(),
+            )
+            .unwrap();
+        let addr = manager
+            .create_snippet("Home address".into(), ";addr".into(), "1 Main St".into())
+            .unwrap();
+        manager
+            .create_snippet("Shrug".into(), ";shrug".into(), "¯\\_(ツ)_/¯".into())
+            .unwrap();
+
+        manager
+            .set_tags(sig, &["work".into(), " email ".into(), "work".into()])
+            .unwrap();
+        manager.set_tags(addr, &["personal".into()]).unwrap();
+        manager.move_snippets(&[sig], Some("Work/ Email/")).unwrap();
+        manager.move_snippets(&[addr], Some("Personal")).unwrap();
+
+        let found = manager.search_snippets("regards", None, None).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].tags, vec!["email", "work"]);
+        assert_eq!(found[0].folder.as_deref(), Some("Work/Email"));
+
+        assert_eq!(
+            manager
+                .search_snippets("ema sig", None, None)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            manager.search_snippets("", Some("personal"), None).unwrap()[0].id,
+            addr
+        );
+        assert_eq!(
+            manager.search_snippets("", None, Some("Work")).unwrap()[0].id,
+            sig
+        );
+        assert!(manager
+            .search_snippets("regards", Some("personal"), None)
+            .unwrap()
+            .is_empty());
+        assert_eq!(manager.search_snippets("", None, None).unwrap().len(), 3);
+
+        // Edits are reflected in the full-text index
+        manager
+            .update_snippet(sig, "Signature".into(), ";sig".into(), "Cheers".into())
+            .unwrap();
+        assert!(manager
+            .search_snippets("regards", None, None)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            manager.search_snippets("cheers", None, None).unwrap().len(),
+            1
+        );
+
+        let folders = manager.list_folders().unwrap();
+        assert_eq!(folders.len(), 2);
+        assert_eq!(folders[0].path, "Personal");
+        assert_eq!(
+            manager.list_tags().unwrap(),
+            vec![
+                ("email".to_string(), 1),
+                ("personal".to_string(), 1),
+                ("work".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bulk_move_and_delete() {
+        let manager = SnippetManager::new_for_test().unwrap();
+        let ids: Vec<i64> = (0..3)
+            .map(|i| {
+                manager
+                    .create_snippet(format!("S{}", i), format!("k{}", i), "x".into())
+                    .unwrap()
+            })
+            .collect();
+        manager.set_tags(ids[0], &["a".into()]).unwrap();
+
+        assert_eq!(manager.move_snippets(&ids[..2], Some("Bulk")).unwrap(), 2);
+        assert_eq!(manager.list_folders().unwrap()[0].snippet_count, 2);
+        manager.move_snippets(&ids[..1], None).unwrap();
+        assert_eq!(manager.list_folders().unwrap()[0].snippet_count, 1);
+
+        assert_eq!(manager.delete_snippets(&ids[..2]).unwrap(), 2);
+        assert_eq!(manager.list_snippets(None).unwrap().len(), 1);
+        assert!(manager.list_tags().unwrap().is_empty());
+        assert!(manager
+            .search_snippets("s0", None, None)
+            .unwrap()
+            .is_empty());
+    }
 }
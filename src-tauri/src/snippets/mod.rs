@@ -1,22 +1,18 @@
 pub mod engine;
+pub mod ime;
 pub mod input_manager;
 pub mod manager;
+pub mod transfer;
+pub mod triggers;
 pub mod types;
 
 use crate::clipboard_history;
-use crate::error::AppError;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
+use std::path::Path;
 use std::sync::Arc;
 use tauri::{AppHandle, Manager};
-use types::Snippet;
-
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ImportSnippet {
-    name: String,
-    text: String,
-    keyword: String,
-}
+use transfer::{ConflictResolution, ImportReport, SnippetFormat};
+use types::{Snippet, SnippetFolder};
 
 #[derive(Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -64,6 +60,60 @@ pub fn delete_snippet(app: AppHandle, id: i64) -> Result<(), String> {
         .map_err(|e| e.to_string())
 }
 
+/// Full-text search, optionally limited to a tag and/or a folder (including
+/// its subfolders)
+#[tauri::command]
+pub fn search_snippets(
+    app: AppHandle,
+    query: String,
+    tag: Option<String>,
+    folder: Option<String>,
+) -> Result<Vec<Snippet>, String> {
+    app.state::<manager::SnippetManager>()
+        .search_snippets(&query, tag.as_deref(), folder.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_snippet_folders(app: AppHandle) -> Result<Vec<SnippetFolder>, String> {
+    app.state::<manager::SnippetManager>()
+        .list_folders()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_snippet_tags(app: AppHandle) -> Result<Vec<(String, i64)>, String> {
+    app.state::<manager::SnippetManager>()
+        .list_tags()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_snippet_tags(app: AppHandle, id: i64, tags: Vec<String>) -> Result<(), String> {
+    app.state::<manager::SnippetManager>()
+        .set_tags(id, &tags)
+        .map_err(|e| e.to_string())
+}
+
+/// Moves snippets into `folder`, or back to the top level when it is omitted
+#[tauri::command]
+pub fn move_snippets(
+    app: AppHandle,
+    ids: Vec<i64>,
+    folder: Option<String>,
+) -> Result<usize, String> {
+    app.state::<manager::SnippetManager>()
+        .move_snippets(&ids, folder.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_snippets(app: AppHandle, ids: Vec<i64>) -> Result<usize, String> {
+    app.state::<manager::SnippetManager>()
+        .delete_snippets(&ids)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn snippet_was_used(app: AppHandle, id: i64) -> Result<(), String> {
     app.state::<manager::SnippetManager>()
@@ -112,32 +162,66 @@ pub fn paste_snippet_content(app: AppHandle, content: String) -> Result<(), Stri
     Ok(())
 }
 
+/// Imports Raycast-format JSON, skipping snippets whose keyword is taken
 #[tauri::command]
 pub fn import_snippets(app: AppHandle, json_content: String) -> Result<ImportResult, String> {
-    let snippets: Vec<ImportSnippet> =
-        serde_json::from_str(&json_content).map_err(|e| e.to_string())?;
-
-    let manager = app.state::<manager::SnippetManager>();
-    let mut snippets_added = 0;
-    let mut duplicates_skipped = 0;
-
-    for snippet in snippets {
-        let keyword = snippet.keyword;
-
-        match manager.create_snippet(snippet.name, keyword, snippet.text) {
-            Ok(_) => snippets_added += 1,
-            Err(AppError::Rusqlite(rusqlite::Error::SqliteFailure(e, Some(msg))))
-                if e.code == rusqlite::ErrorCode::ConstraintViolation
-                    && msg.contains("UNIQUE constraint failed: snippets.keyword") =>
-            {
-                duplicates_skipped += 1;
-            }
-            Err(e) => return Err(e.to_string()),
-        }
-    }
+    let parsed = transfer::parse_raycast(&json_content)?;
+    let report = transfer::import(
+        &app.state::<manager::SnippetManager>(),
+        SnippetFormat::Raycast,
+        parsed,
+        ConflictResolution::Skip,
+        false,
+    )
+    .map_err(|e| e.to_string())?;
 
     Ok(ImportResult {
-        snippets_added,
-        duplicates_skipped,
+        snippets_added: report.added as u32,
+        duplicates_skipped: report.skipped as u32,
     })
 }
+
+/// Imports a Raycast, Alfred or Espanso file; the format is taken from the
+/// extension when omitted. With `dry_run` nothing is saved and the report
+/// previews what would happen.
+#[tauri::command]
+pub fn import_snippets_from_file(
+    app: AppHandle,
+    path: String,
+    format: Option<SnippetFormat>,
+    conflict: Option<ConflictResolution>,
+    dry_run: Option<bool>,
+) -> Result<ImportReport, String> {
+    let path = Path::new(&path);
+    let format = format
+        .or_else(|| SnippetFormat::from_path(path))
+        .ok_or("Unknown snippet file format")?;
+    let parsed = transfer::read(path, format)?;
+    transfer::import(
+        &app.state::<manager::SnippetManager>(),
+        format,
+        parsed,
+        conflict.unwrap_or_default(),
+        dry_run.unwrap_or(false),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Writes every snippet to `path` and returns how many were exported
+#[tauri::command]
+pub fn export_snippets_to_file(
+    app: AppHandle,
+    path: String,
+    format: Option<SnippetFormat>,
+) -> Result<usize, String> {
+    let path = Path::new(&path);
+    let format = format
+        .or_else(|| SnippetFormat::from_path(path))
+        .ok_or("Unknown snippet file format")?;
+    let snippets = app
+        .state::<manager::SnippetManager>()
+        .list_snippets(None)
+        .map_err(|e| e.to_string())?;
+    transfer::write(path, format, &snippets)?;
+    Ok(snippets.len())
+}
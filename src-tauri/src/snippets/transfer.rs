@@ -0,0 +1,828 @@
+//! Import and export in the snippet formats of other apps: Raycast JSON,
+//! Alfred `.alfredsnippets` bundles and Espanso match files.
+//!
+//! Flare's placeholders follow Raycast's, so Raycast snippets round-trip
+//! unchanged; Alfred and Espanso placeholders are translated where there is
+//! an equivalent. Imports are planned first, so the same plan serves as the
+//! dry-run preview and as the list of changes to apply.
+
+use crate::error::AppError;
+use crate::snippets::engine::{parse_attributes, translate_date_format, PLACEHOLDER_REGEX};
+use crate::snippets::manager::SnippetManager;
+use crate::snippets::types::Snippet;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+static ALFRED_CLIPBOARD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{clipboard:(\d+)\}").unwrap());
+static ALFRED_DATE_STYLE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{(date|time|datetime):\w+\}").unwrap());
+static ESPANSO_VAR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*([\w.-]+)\s*\}\}").unwrap());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SnippetFormat {
+    Raycast,
+    Alfred,
+    Espanso,
+}
+
+impl SnippetFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(SnippetFormat::Raycast),
+            "alfredsnippets" | "zip" => Some(SnippetFormat::Alfred),
+            "yml" | "yaml" => Some(SnippetFormat::Espanso),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictResolution {
+    /// Keep the existing snippet and drop the imported one
+    #[default]
+    Skip,
+    /// Replace the existing snippet's name and content
+    Overwrite,
+    /// Import under a free keyword such as `sig-2`
+    Rename,
+}
+
+/// A snippet as read from another app, already using Flare placeholders
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PortableSnippet {
+    pub name: String,
+    pub keyword: Option<String>,
+    pub content: String,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportAction {
+    Add,
+    Overwrite,
+    Rename,
+    Skip,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportItem {
+    pub name: String,
+    /// The keyword the snippet is (or would be) saved under
+    pub keyword: String,
+    pub original_keyword: Option<String>,
+    pub content: String,
+    pub action: ImportAction,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub format: SnippetFormat,
+    pub dry_run: bool,
+    pub items: Vec<ImportItem>,
+    pub added: usize,
+    pub overwritten: usize,
+    pub renamed: usize,
+    pub skipped: usize,
+    /// Entries the format allows but Flare can't represent, e.g. Espanso forms
+    pub unsupported: usize,
+}
+
+impl ImportReport {
+    fn new(
+        format: SnippetFormat,
+        dry_run: bool,
+        items: Vec<ImportItem>,
+        unsupported: usize,
+    ) -> Self {
+        let count = |action| items.iter().filter(|item| item.action == action).count();
+        ImportReport {
+            format,
+            dry_run,
+            added: count(ImportAction::Add),
+            overwritten: count(ImportAction::Overwrite),
+            renamed: count(ImportAction::Rename),
+            skipped: count(ImportAction::Skip),
+            unsupported,
+            items,
+        }
+    }
+}
+
+/// Snippets read from a file plus the number of entries that were dropped
+pub struct Parsed {
+    pub snippets: Vec<PortableSnippet>,
+    pub unsupported: usize,
+}
+
+// Raycast
+
+#[derive(Serialize, Deserialize)]
+struct RaycastSnippet {
+    name: String,
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keyword: Option<String>,
+}
+
+pub fn parse_raycast(json: &str) -> Result<Parsed, String> {
+    let snippets: Vec<RaycastSnippet> =
+        serde_json::from_str(json).map_err(|e| format!("Invalid Raycast snippets: {}", e))?;
+    Ok(Parsed {
+        snippets: snippets
+            .into_iter()
+            .map(|snippet| PortableSnippet {
+                name: snippet.name,
+                keyword: snippet.keyword,
+                content: snippet.text,
+            })
+            .collect(),
+        unsupported: 0,
+    })
+}
+
+pub fn write_raycast(snippets: &[Snippet]) -> Result<String, String> {
+    let snippets: Vec<RaycastSnippet> = snippets
+        .iter()
+        .map(|snippet| RaycastSnippet {
+            name: snippet.name.clone(),
+            text: snippet.content.clone(),
+            keyword: Some(snippet.keyword.clone()),
+        })
+        .collect();
+    serde_json::to_string_pretty(&snippets).map_err(|e| e.to_string())
+}
+
+// Alfred
+
+#[derive(Serialize, Deserialize)]
+struct AlfredFile {
+    alfredsnippet: AlfredSnippet,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AlfredSnippet {
+    snippet: String,
+    #[serde(default)]
+    uid: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    keyword: String,
+}
+
+fn plist_string(plist: &str, key: &str) -> Option<String> {
+    let pattern = format!(
+        r"<key>{}</key>\s*<string>([^<]*)</string>",
+        regex::escape(key)
+    );
+    Regex::new(&pattern)
+        .ok()?
+        .captures(plist)
+        .map(|cap| cap[1].to_string())
+}
+
+pub fn from_alfred_placeholders(text: &str) -> String {
+    let text = ALFRED_CLIPBOARD_RE.replace_all(text, "{clipboard offset=$1}");
+    ALFRED_DATE_STYLE_RE.replace_all(&text, "{$1}").into_owned()
+}
+
+pub fn to_alfred_placeholders(text: &str) -> String {
+    PLACEHOLDER_REGEX
+        .replace_all(text, |cap: &regex::Captures| {
+            let name = &cap["name"];
+            let attributes = parse_attributes(cap.name("attributes").map_or("", |m| m.as_str()));
+            match (name, attributes.get("offset")) {
+                ("clipboard", Some(offset)) => format!("{{clipboard:{}}}", offset),
+                ("clipboard" | "cursor" | "date" | "time" | "datetime", _) => {
+                    format!("{{{}}}", name)
+                }
+                _ => cap[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+pub fn parse_alfred(bytes: &[u8]) -> Result<Parsed, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| format!("Invalid Alfred snippet bundle: {}", e))?;
+
+    let mut prefix = String::new();
+    let mut suffix = String::new();
+    if let Ok(mut info) = archive.by_name("info.plist") {
+        let mut plist = String::new();
+        if info.read_to_string(&mut plist).is_ok() {
+            prefix = plist_string(&plist, "snippetkeywordprefix").unwrap_or_default();
+            suffix = plist_string(&plist, "snippetkeywordsuffix").unwrap_or_default();
+        }
+    }
+
+    let mut snippets = Vec::new();
+    let mut unsupported = 0;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+        if !file.name().ends_with(".json") {
+            continue;
+        }
+        let mut json = String::new();
+        let parsed = file
+            .read_to_string(&mut json)
+            .ok()
+            .and_then(|_| serde_json::from_str::<AlfredFile>(&json).ok());
+        let Some(AlfredFile { alfredsnippet }) = parsed else {
+            unsupported += 1;
+            continue;
+        };
+        let keyword = Some(alfredsnippet.keyword)
+            .filter(|keyword| !keyword.is_empty())
+            .map(|keyword| format!("{}{}{}", prefix, keyword, suffix));
+        snippets.push(PortableSnippet {
+            name: alfredsnippet.name,
+            keyword,
+            content: from_alfred_placeholders(&alfredsnippet.snippet),
+        });
+    }
+    Ok(Parsed {
+        snippets,
+        unsupported,
+    })
+}
+
+/// Alfred names files `<name> [<uid>].json`; slashes would become folders
+fn alfred_file_name(name: &str, uid: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if matches!(c, '/' | '\\' | ':') {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("{} [{}].json", name.trim(), uid)
+}
+
+const ALFRED_INFO_PLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>snippetkeywordprefix</key>
+	<string></string>
+	<key>snippetkeywordsuffix</key>
+	<string></string>
+</dict>
+</plist>
+"#;
+
+pub fn write_alfred(snippets: &[Snippet]) -> Result<Vec<u8>, String> {
+    let options = zip::write::SimpleFileOptions::default();
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("info.plist", options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(ALFRED_INFO_PLIST.as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    for snippet in snippets {
+        let uid = uuid::Uuid::new_v4().to_string().to_uppercase();
+        let file = AlfredFile {
+            alfredsnippet: AlfredSnippet {
+                snippet: to_alfred_placeholders(&snippet.content),
+                uid: uid.clone(),
+                name: snippet.name.clone(),
+                keyword: snippet.keyword.clone(),
+            },
+        };
+        let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+        zip.start_file(alfred_file_name(&snippet.name, &uid), options)
+            .map_err(|e| e.to_string())?;
+        zip.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    zip.finish()
+        .map(Cursor::into_inner)
+        .map_err(|e| e.to_string())
+}
+
+// Espanso
+
+#[derive(Serialize, Deserialize, Default)]
+struct EspansoFile {
+    #[serde(default)]
+    matches: Vec<EspansoMatch>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct EspansoMatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trigger: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    triggers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    replace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    vars: Vec<EspansoVar>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EspansoVar {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default, skip_serializing_if = "serde_yaml::Value::is_null")]
+    params: serde_yaml::Value,
+}
+
+/// strftime (`%Y-%m-%d`) to the Unicode patterns Flare's date placeholders use
+fn strftime_to_pattern(format: &str) -> String {
+    let mut pattern = String::new();
+    let mut literal = String::new();
+    let flush = |literal: &mut String, pattern: &mut String| {
+        if literal.is_empty() {
+            return;
+        }
+        if literal.chars().any(|c| c.is_ascii_alphabetic()) {
+            pattern.push_str(&format!("'{}'", literal));
+        } else {
+            pattern.push_str(literal);
+        }
+        literal.clear();
+    };
+
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+        let mut spec = chars.next();
+        let unpadded = spec == Some('-');
+        if unpadded {
+            spec = chars.next();
+        }
+        let token = match spec {
+            Some('Y') => "yyyy",
+            Some('y') => "yy",
+            Some('m') if unpadded => "M",
+            Some('m') => "MM",
+            Some('B') => "MMMM",
+            Some('b') | Some('h') => "MMM",
+            Some('d') if unpadded => "d",
+            Some('d') => "dd",
+            Some('e') => "d",
+            Some('A') => "EEEE",
+            Some('a') => "EEE",
+            Some('H') if unpadded => "H",
+            Some('H') => "HH",
+            Some('I') if unpadded => "h",
+            Some('I') => "hh",
+            Some('M') => "mm",
+            Some('S') => "ss",
+            Some('p') => "a",
+            Some('z') => "Z",
+            Some('%') => {
+                literal.push('%');
+                continue;
+            }
+            _ => continue,
+        };
+        flush(&mut literal, &mut pattern);
+        pattern.push_str(token);
+    }
+    flush(&mut literal, &mut pattern);
+    pattern
+}
+
+fn from_espanso_placeholders(replace: &str, vars: &[EspansoVar]) -> String {
+    let replaced = ESPANSO_VAR_RE.replace_all(replace, |cap: &regex::Captures| {
+        let Some(var) = vars.iter().find(|var| var.name == cap[1]) else {
+            return cap[0].to_string();
+        };
+        match var.kind.as_str() {
+            "clipboard" => "{clipboard}".to_string(),
+            "date" => match var.params.get("format").and_then(|f| f.as_str()) {
+                Some(format) => format!("{{date format=\"{}\"}}", strftime_to_pattern(format)),
+                None => "{date}".to_string(),
+            },
+            _ => cap[0].to_string(),
+        }
+    });
+    replaced.replace("$|$", "{cursor}")
+}
+
+fn to_espanso_match(snippet: &Snippet) -> EspansoMatch {
+    let mut vars: Vec<EspansoVar> = Vec::new();
+    let replace = PLACEHOLDER_REGEX.replace_all(&snippet.content, |cap: &regex::Captures| {
+        let name = &cap["name"];
+        let attributes = parse_attributes(cap.name("attributes").map_or("", |m| m.as_str()));
+        match name {
+            "cursor" => "$|$".to_string(),
+            "clipboard" if !attributes.contains_key("offset") => {
+                if !vars.iter().any(|var| var.name == "clipboard") {
+                    vars.push(EspansoVar {
+                        name: "clipboard".to_string(),
+                        kind: "clipboard".to_string(),
+                        params: serde_yaml::Value::Null,
+                    });
+                }
+                "{{clipboard}}".to_string()
+            }
+            "date" | "time" | "datetime" | "day" if !attributes.contains_key("offset") => {
+                let format = match attributes.get("format") {
+                    Some(format) => translate_date_format(format),
+                    None => match name {
+                        "date" => "%-d %b %Y",
+                        "time" => "%-I:%M %p",
+                        "datetime" => "%-d %b %Y at %-I:%M %p",
+                        _ => "%A",
+                    }
+                    .to_string(),
+                };
+                let var_name = format!("{}{}", name, vars.len() + 1);
+                let mut params = serde_yaml::Mapping::new();
+                params.insert("format".into(), format.into());
+                vars.push(EspansoVar {
+                    name: var_name.clone(),
+                    kind: "date".to_string(),
+                    params: serde_yaml::Value::Mapping(params),
+                });
+                format!("{{{{{}}}}}", var_name)
+            }
+            _ => cap[0].to_string(),
+        }
+    });
+    EspansoMatch {
+        trigger: Some(snippet.keyword.clone()),
+        replace: Some(replace.into_owned()),
+        label: Some(snippet.name.clone()).filter(|name| *name != snippet.keyword),
+        vars,
+        ..Default::default()
+    }
+}
+
+pub fn parse_espanso(yaml: &str) -> Result<Parsed, String> {
+    let file: EspansoFile =
+        serde_yaml::from_str(yaml).map_err(|e| format!("Invalid Espanso match file: {}", e))?;
+    let mut snippets = Vec::new();
+    let mut unsupported = 0;
+    for entry in file.matches {
+        // Forms, images and regex triggers have no Flare equivalent
+        let Some(replace) = entry.replace.as_deref() else {
+            unsupported += 1;
+            continue;
+        };
+        let content = from_espanso_placeholders(replace, &entry.vars);
+        let triggers: Vec<String> = entry
+            .trigger
+            .iter()
+            .chain(&entry.triggers)
+            .cloned()
+            .collect();
+        if triggers.is_empty() {
+            unsupported += 1;
+            continue;
+        }
+        for trigger in triggers {
+            snippets.push(PortableSnippet {
+                name: entry.label.clone().unwrap_or_else(|| trigger.clone()),
+                keyword: Some(trigger),
+                content: content.clone(),
+            });
+        }
+    }
+    Ok(Parsed {
+        snippets,
+        unsupported,
+    })
+}
+
+pub fn write_espanso(snippets: &[Snippet]) -> Result<String, String> {
+    let file = EspansoFile {
+        matches: snippets.iter().map(to_espanso_match).collect(),
+    };
+    serde_yaml::to_string(&file).map_err(|e| e.to_string())
+}
+
+// Planning and applying
+
+fn free_keyword(keyword: &str, taken: &HashSet<String>) -> String {
+    (2..)
+        .map(|n| format!("{}-{}", keyword, n))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap()
+}
+
+/// Decides what happens to each incoming snippet. Keywords are unique, so
+/// conflicts are checked against existing snippets and earlier entries of
+/// the same import.
+pub fn plan(
+    existing: &[Snippet],
+    incoming: Vec<PortableSnippet>,
+    resolution: ConflictResolution,
+) -> Vec<ImportItem> {
+    let existing_keywords: HashSet<String> = existing.iter().map(|s| s.keyword.clone()).collect();
+    let mut taken = existing_keywords.clone();
+    let mut imported: HashSet<String> = HashSet::new();
+
+    incoming
+        .into_iter()
+        .map(|snippet| {
+            let item = |keyword: String, action, reason: Option<&str>| ImportItem {
+                name: snippet.name.clone(),
+                keyword,
+                original_keyword: snippet.keyword.clone(),
+                content: snippet.content.clone(),
+                action,
+                reason: reason.map(String::from),
+            };
+            let Some(keyword) = snippet.keyword.clone().filter(|k| !k.trim().is_empty()) else {
+                return item(String::new(), ImportAction::Skip, Some("No keyword"));
+            };
+            if !taken.contains(&keyword) {
+                taken.insert(keyword.clone());
+                imported.insert(keyword.clone());
+                return item(keyword, ImportAction::Add, None);
+            }
+            let in_this_import = imported.contains(&keyword);
+            match resolution {
+                ConflictResolution::Overwrite if !in_this_import => {
+                    imported.insert(keyword.clone());
+                    item(keyword, ImportAction::Overwrite, None)
+                }
+                ConflictResolution::Rename => {
+                    let renamed = free_keyword(&keyword, &taken);
+                    taken.insert(renamed.clone());
+                    imported.insert(renamed.clone());
+                    item(renamed, ImportAction::Rename, None)
+                }
+                _ if in_this_import => item(
+                    keyword,
+                    ImportAction::Skip,
+                    Some("Keyword appears twice in the import"),
+                ),
+                _ => item(keyword, ImportAction::Skip, Some("Keyword already in use")),
+            }
+        })
+        .collect()
+}
+
+pub fn apply(manager: &SnippetManager, items: &[ImportItem]) -> Result<(), AppError> {
+    for item in items {
+        match item.action {
+            ImportAction::Add | ImportAction::Rename => {
+                manager.create_snippet(
+                    item.name.clone(),
+                    item.keyword.clone(),
+                    item.content.clone(),
+                )?;
+            }
+            ImportAction::Overwrite => {
+                if let Some(existing) = manager.find_snippet_by_keyword(&item.keyword)? {
+                    manager.update_snippet(
+                        existing.id,
+                        item.name.clone(),
+                        item.keyword.clone(),
+                        item.content.clone(),
+                    )?;
+                }
+            }
+            ImportAction::Skip => {}
+        }
+    }
+    Ok(())
+}
+
+pub fn read(path: &Path, format: SnippetFormat) -> Result<Parsed, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    match format {
+        SnippetFormat::Alfred => parse_alfred(&bytes),
+        SnippetFormat::Raycast => parse_raycast(&String::from_utf8_lossy(&bytes)),
+        SnippetFormat::Espanso => parse_espanso(&String::from_utf8_lossy(&bytes)),
+    }
+}
+
+pub fn import(
+    manager: &SnippetManager,
+    format: SnippetFormat,
+    parsed: Parsed,
+    resolution: ConflictResolution,
+    dry_run: bool,
+) -> Result<ImportReport, AppError> {
+    let existing = manager.list_snippets(None)?;
+    let items = plan(&existing, parsed.snippets, resolution);
+    if !dry_run {
+        apply(manager, &items)?;
+    }
+    Ok(ImportReport::new(
+        format,
+        dry_run,
+        items,
+        parsed.unsupported,
+    ))
+}
+
+pub fn write(path: &Path, format: SnippetFormat, snippets: &[Snippet]) -> Result<(), String> {
+    let bytes = match format {
+        SnippetFormat::Raycast => write_raycast(snippets)?.into_bytes(),
+        SnippetFormat::Alfred => write_alfred(snippets)?,
+        SnippetFormat::Espanso => write_espanso(snippets)?.into_bytes(),
+    };
+    std::fs::write(path, bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn snippet(name: &str, keyword: &str, content: &str) -> Snippet {
+        Snippet {
+            id: 0,
+            name: name.to_string(),
+            keyword: keyword.to_string(),
+            content: content.to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            times_used: 0,
+            last_used_at: Utc::now(),
+            folder: None,
+            tags: Vec::new(),
+        }
+    }
+
+    fn portable(keyword: Option<&str>) -> PortableSnippet {
+        PortableSnippet {
+            name: "Sig".to_string(),
+            keyword: keyword.map(String::from),
+            content: "Best".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_raycast_round_trip() {
+        let json = write_raycast(&[snippet("Sig", ";sig", "Best, {clipboard}")]).unwrap();
+        let parsed = parse_raycast(&json).unwrap();
+        assert_eq!(parsed.snippets[0].keyword.as_deref(), Some(";sig"));
+        assert_eq!(parsed.snippets[0].content, "Best, {clipboard}");
+
+        let parsed = parse_raycast(r#"[{"name": "No keyword", "text": "x"}]"#).unwrap();
+        assert_eq!(parsed.snippets[0].keyword, None);
+    }
+
+    #[test]
+    fn test_alfred_round_trip() {
+        let bundle = write_alfred(&[snippet(
+            "Paste/2",
+            "!p",
+            "{clipboard offset=1} at {date format=\"yyyy\"}",
+        )])
+        .unwrap();
+        let parsed = parse_alfred(&bundle).unwrap();
+        assert_eq!(parsed.snippets.len(), 1);
+        assert_eq!(parsed.snippets[0].name, "Paste/2");
+        assert_eq!(parsed.snippets[0].keyword.as_deref(), Some("!p"));
+        assert_eq!(parsed.snippets[0].content, "{clipboard offset=1} at {date}");
+        assert!(parse_alfred(b"not a zip").is_err());
+    }
+
+    #[test]
+    fn test_alfred_keyword_affixes() {
+        let plist = ALFRED_INFO_PLIST.replacen("<string></string>", "<string>;</string>", 1);
+        assert_eq!(
+            plist_string(&plist, "snippetkeywordprefix").as_deref(),
+            Some(";")
+        );
+        assert_eq!(
+            plist_string(&plist, "snippetkeywordsuffix").as_deref(),
+            Some("")
+        );
+        assert_eq!(
+            from_alfred_placeholders("{date:short} {cursor}"),
+            "{date} {cursor}"
+        );
+    }
+
+    #[test]
+    fn test_parse_espanso() {
+        let yaml = r#"
+matches:
+  - trigger: ":today"
+    replace: "Today is {{mydate}}$|$"
+    vars:
+      - name: mydate
+        type: date
+        params:
+          format: "%Y-%m-%d (%A)"
+  - triggers: [":cb", ":clip"]
+    label: Clipboard
+    replace: "{{clip}}"
+    vars:
+      - name: clip
+        type: clipboard
+  - trigger: ":form"
+    form: "Hi [[name]]"
+"#;
+        let parsed = parse_espanso(yaml).unwrap();
+        assert_eq!(parsed.unsupported, 1);
+        assert_eq!(parsed.snippets.len(), 3);
+        assert_eq!(
+            parsed.snippets[0].content,
+            "Today is {date format=\"yyyy-MM-dd (EEEE)\"}{cursor}"
+        );
+        assert_eq!(parsed.snippets[0].name, ":today");
+        assert_eq!(parsed.snippets[2].keyword.as_deref(), Some(":clip"));
+        assert_eq!(parsed.snippets[2].content, "{clipboard}");
+    }
+
+    #[test]
+    fn test_espanso_export() {
+        let yaml =
+            write_espanso(&[snippet("Stamp", ":ts", "{cursor} {date format=\"yyyy\"}")]).unwrap();
+        let parsed = parse_espanso(&yaml).unwrap();
+        assert_eq!(parsed.snippets[0].name, "Stamp");
+        assert_eq!(
+            parsed.snippets[0].content,
+            "{cursor} {date format=\"yyyy\"}"
+        );
+    }
+
+    #[test]
+    fn test_strftime_to_pattern() {
+        assert_eq!(strftime_to_pattern("%d/%m/%Y"), "dd/MM/yyyy");
+        assert_eq!(strftime_to_pattern("%-I:%M %p"), "h:mm a");
+        assert_eq!(strftime_to_pattern("Week of %b %e"), "'Week of 'MMM d");
+    }
+
+    #[test]
+    fn test_plan_conflicts() {
+        let existing = vec![snippet("Old", ";sig", "old")];
+        let incoming = || {
+            vec![
+                portable(Some(";sig")),
+                portable(Some(";sig")),
+                portable(None),
+            ]
+        };
+
+        let skip = plan(&existing, incoming(), ConflictResolution::Skip);
+        assert_eq!(
+            skip.iter().map(|i| i.action).collect::<Vec<_>>(),
+            vec![ImportAction::Skip, ImportAction::Skip, ImportAction::Skip]
+        );
+        assert_eq!(skip[2].reason.as_deref(), Some("No keyword"));
+
+        let overwrite = plan(&existing, incoming(), ConflictResolution::Overwrite);
+        assert_eq!(overwrite[0].action, ImportAction::Overwrite);
+        assert_eq!(overwrite[1].action, ImportAction::Skip);
+
+        let rename = plan(&existing, incoming(), ConflictResolution::Rename);
+        assert_eq!(rename[0].keyword, ";sig-2");
+        assert_eq!(rename[1].keyword, ";sig-3");
+        assert_eq!(rename[1].original_keyword.as_deref(), Some(";sig"));
+    }
+
+    #[test]
+    fn test_import_applies_plan() {
+        let manager = SnippetManager::new_for_test().unwrap();
+        manager
+            .create_snippet("Old".into(), ";sig".into(), "old".into())
+            .unwrap();
+        let parsed = || Parsed {
+            snippets: vec![portable(Some(";sig")), portable(Some(";new"))],
+            unsupported: 0,
+        };
+
+        let preview = import(
+            &manager,
+            SnippetFormat::Raycast,
+            parsed(),
+            ConflictResolution::Overwrite,
+            true,
+        )
+        .unwrap();
+        assert_eq!((preview.added, preview.overwritten), (1, 1));
+        assert_eq!(manager.list_snippets(None).unwrap().len(), 1);
+
+        import(
+            &manager,
+            SnippetFormat::Raycast,
+            parsed(),
+            ConflictResolution::Overwrite,
+            false,
+        )
+        .unwrap();
+        let sig = manager.find_snippet_by_keyword(";sig").unwrap().unwrap();
+        assert_eq!(sig.content, "Best");
+        assert!(manager.find_snippet_by_keyword(";new").unwrap().is_some());
+    }
+}
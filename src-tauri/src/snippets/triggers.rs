@@ -0,0 +1,181 @@
+//! Keyword matching for the expansion engine. All keywords go into one
+//! Aho-Corasick DFA that is advanced a byte at a time as keys come in, so a
+//! key press costs a few table lookups however many snippets there are. The
+//! index is rebuilt when snippets change, not per key press.
+
+use crate::snippets::types::Snippet;
+use aho_corasick::automaton::{Automaton, StateID};
+use aho_corasick::dfa::DFA;
+use aho_corasick::{Anchored, MatchKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trigger {
+    pub id: i64,
+    pub keyword: String,
+    pub content: String,
+}
+
+pub struct TriggerIndex {
+    /// `None` without keywords, since an automaton needs a pattern
+    dfa: Option<DFA>,
+    triggers: Vec<Trigger>,
+}
+
+impl TriggerIndex {
+    pub fn new(snippets: &[Snippet]) -> Self {
+        let triggers: Vec<Trigger> = snippets
+            .iter()
+            .filter(|snippet| !snippet.keyword.is_empty())
+            .map(|snippet| Trigger {
+                id: snippet.id,
+                keyword: snippet.keyword.clone(),
+                content: snippet.content.clone(),
+            })
+            .collect();
+        let dfa = if triggers.is_empty() {
+            None
+        } else {
+            // Standard semantics makes every keyword ending at a position
+            // visible from the state reached there
+            DFA::builder()
+                .match_kind(MatchKind::Standard)
+                .build(triggers.iter().map(|trigger| &trigger.keyword))
+                .map_err(|e| tracing::error!(error = %e, "Failed to build snippet keyword index"))
+                .ok()
+        };
+        Self { dfa, triggers }
+    }
+
+    pub fn keyword_count(&self) -> usize {
+        self.triggers.len()
+    }
+
+    /// The state before anything has been typed
+    pub fn start(&self) -> StateID {
+        self.dfa
+            .as_ref()
+            .and_then(|dfa| dfa.start_state(Anchored::No).ok())
+            .unwrap_or(StateID::ZERO)
+    }
+
+    pub fn advance(&self, state: StateID, ch: char) -> StateID {
+        let Some(dfa) = &self.dfa else {
+            return state;
+        };
+        let mut utf8 = [0; 4];
+        ch.encode_utf8(&mut utf8)
+            .bytes()
+            .fold(state, |state, byte| {
+                dfa.next_state(Anchored::No, state, byte)
+            })
+    }
+
+    /// The longest keyword that the typed text ends with, so `;addr` wins
+    /// over `addr` when both are keywords
+    pub fn matched(&self, state: StateID) -> Option<&Trigger> {
+        let dfa = self.dfa.as_ref()?;
+        if !dfa.is_match(state) {
+            return None;
+        }
+        (0..dfa.match_len(state))
+            .map(|index| &self.triggers[dfa.match_pattern(state, index).as_usize()])
+            .max_by_key(|trigger| trigger.keyword.len())
+    }
+}
+
+/// What has been typed since the last reset, as the index states after each
+/// key, so a backspace steps back without re-reading anything
+pub struct TypedKeys {
+    states: Vec<StateID>,
+    limit: usize,
+}
+
+impl TypedKeys {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            states: Vec::with_capacity(limit),
+            limit,
+        }
+    }
+
+    pub fn current(&self, index: &TriggerIndex) -> StateID {
+        self.states.last().copied().unwrap_or_else(|| index.start())
+    }
+
+    pub fn push(&mut self, index: &TriggerIndex, ch: char) -> StateID {
+        let state = index.advance(self.current(index), ch);
+        if self.states.len() == self.limit {
+            self.states.remove(0);
+        }
+        self.states.push(state);
+        state
+    }
+
+    pub fn pop(&mut self) {
+        self.states.pop();
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn snippet(id: i64, keyword: &str) -> Snippet {
+        Snippet {
+            id,
+            name: keyword.to_string(),
+            keyword: keyword.to_string(),
+            content: format!("content {}", id),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            times_used: 0,
+            last_used_at: Utc::now(),
+            folder: None,
+            tags: Vec::new(),
+        }
+    }
+
+    fn type_text(index: &TriggerIndex, typed: &mut TypedKeys, text: &str) -> Option<i64> {
+        text.chars()
+            .map(|ch| typed.push(index, ch))
+            .last()
+            .and_then(|state| index.matched(state))
+            .map(|trigger| trigger.id)
+    }
+
+    #[test]
+    fn test_matches_keyword_suffixes() {
+        let index = TriggerIndex::new(&[snippet(1, "addr"), snippet(2, ";addr"), snippet(3, "éé")]);
+        let mut typed = TypedKeys::new(30);
+
+        assert_eq!(type_text(&index, &mut typed, "my addr"), Some(1));
+        typed.clear();
+        assert_eq!(type_text(&index, &mut typed, "x;addr"), Some(2));
+        typed.clear();
+        assert_eq!(type_text(&index, &mut typed, "caféé"), Some(3));
+        typed.clear();
+        assert_eq!(type_text(&index, &mut typed, "add"), None);
+    }
+
+    #[test]
+    fn test_backspace_restores_previous_state() {
+        let index = TriggerIndex::new(&[snippet(1, "sig")]);
+        let mut typed = TypedKeys::new(30);
+        assert_eq!(type_text(&index, &mut typed, "six"), None);
+        typed.pop();
+        assert_eq!(type_text(&index, &mut typed, "g"), Some(1));
+    }
+
+    #[test]
+    fn test_empty_index_never_matches() {
+        let index = TriggerIndex::new(&[snippet(1, "")]);
+        assert_eq!(index.keyword_count(), 0);
+        let mut typed = TypedKeys::new(30);
+        assert_eq!(type_text(&index, &mut typed, "anything"), None);
+    }
+}
@@ -12,4 +12,14 @@ pub struct Snippet {
     pub updated_at: DateTime<Utc>,
     pub times_used: i32,
     pub last_used_at: DateTime<Utc>,
+    /// Slash-separated path such as `Work/Email`; `None` is the top level
+    pub folder: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetFolder {
+    pub path: String,
+    pub snippet_count: i64,
 }
@@ -1,3 +1,5 @@
+use crate::currencies;
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 use std::sync::Once;
@@ -21,6 +23,8 @@ extern "C" {
     fn initialize_soulver(resourcesPath: *const c_char);
     fn evaluate(expression: *const c_char) -> *mut c_char;
     fn free_string(ptr: *mut c_char);
+    #[link_name = "set_currency_rates"]
+    fn set_currency_rates_raw(ratesJson: *const c_char);
 }
 
 #[cfg(test)]
@@ -28,6 +32,8 @@ extern "C" {
     fn initialize_soulver(resourcesPath: *const c_char);
     fn evaluate(expression: *const c_char) -> *mut c_char;
     fn free_string(ptr: *mut c_char);
+    #[link_name = "set_currency_rates"]
+    fn set_currency_rates_raw(ratesJson: *const c_char);
 }
 
 struct StringPtrGuard(*mut c_char);
@@ -40,9 +46,32 @@ impl Drop for StringPtrGuard {
     }
 }
 
+/// Seeds the wrapper's currency provider so conversions work before (or
+/// without) its own network fetch
+pub fn set_currency_rates(rates: &HashMap<String, f64>) {
+    let Ok(json) = serde_json::to_string(rates) else {
+        return;
+    };
+    let Ok(c_json) = CString::new(json) else {
+        return;
+    };
+    unsafe { set_currency_rates_raw(c_json.as_ptr()) };
+}
+
+/// True when the wrapper produced no usable result
+fn is_empty_result(result_json: &str) -> bool {
+    match serde_json::from_str::<serde_json::Value>(result_json) {
+        Ok(value) => {
+            value.get("type").and_then(|t| t.as_str()) == Some("none")
+                || value.get("error").is_some_and(|e| !e.is_null())
+        }
+        Err(_) => false,
+    }
+}
+
 #[tauri::command]
 pub fn calculate_soulver(expression: String) -> Result<String, String> {
-    let c_expression = CString::new(expression).map_err(|e| e.to_string())?;
+    let c_expression = CString::new(expression.clone()).map_err(|e| e.to_string())?;
 
     let result_ptr = unsafe { evaluate(c_expression.as_ptr()) };
     let _guard = StringPtrGuard(result_ptr);
@@ -56,6 +85,12 @@ pub fn calculate_soulver(expression: String) -> Result<String, String> {
         c_result.to_str().map_err(|e| e.to_string())?.to_owned()
     };
 
+    if is_empty_result(&result_string) {
+        if let Some(fallback) = currencies::calculator_fallback(&expression) {
+            return Ok(fallback);
+        }
+    }
+
     Ok(result_string)
 }
 
@@ -75,6 +110,9 @@ mod tests {
         unsafe { MOCK_EVAL_RESPONSE }
     }
 
+    #[no_mangle]
+    pub extern "C" fn set_currency_rates(_: *const c_char) {}
+
     #[no_mangle]
     pub extern "C" fn free_string(ptr: *mut c_char) {
         if !ptr.is_null() {
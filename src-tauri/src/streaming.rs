@@ -0,0 +1,293 @@
+//! Cursor-based streaming for result sets too large to send in one response,
+//! like the file index or a long clipboard history.
+//!
+//! A stream command sends pages over an IPC channel as it reads them, and
+//! stops when the rows run out, after `max_items`, or when it's cancelled.
+//! Every page carries an opaque continuation token, so the frontend can
+//! resume the stream where it left off when the user scrolls further.
+//! Starting a stream cancels the previous one of the same kind, which keeps
+//! type-ahead from queueing up searches nobody will look at.
+
+use base64::Engine;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+const MAX_PAGE_SIZE: u32 = 1000;
+pub const DEFAULT_MAX_ITEMS: u32 = 1000;
+
+pub fn encode_cursor<C: Serialize>(position: &C) -> String {
+    let json = serde_json::to_vec(position).unwrap_or_default();
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+}
+
+pub fn decode_cursor<C: DeserializeOwned>(token: &str) -> Result<C, String> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(token)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| "Invalid continuation token".to_string())
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamPage<T> {
+    pub stream_id: String,
+    pub items: Vec<T>,
+    /// `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamSummary {
+    pub stream_id: String,
+    pub sent: usize,
+    /// Set when the stream stopped before the end of the results
+    pub next_cursor: Option<String>,
+    pub cancelled: bool,
+}
+
+pub struct StreamToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl StreamToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Each stream's kind and cancel flag, by id
+type ActiveStreams = HashMap<String, (&'static str, Arc<AtomicBool>)>;
+
+/// Live streams by id, with the kind they belong to
+#[derive(Clone, Default)]
+pub struct StreamRegistry {
+    active: Arc<Mutex<ActiveStreams>>,
+}
+
+impl StreamRegistry {
+    /// Registers a stream and cancels any other stream of the same kind
+    pub fn begin(&self, kind: &'static str, stream_id: &str) -> StreamToken {
+        let mut active = self.active.lock().unwrap();
+        active.retain(|_, (other_kind, cancelled)| {
+            if *other_kind == kind {
+                cancelled.store(true, Ordering::SeqCst);
+                false
+            } else {
+                true
+            }
+        });
+        let cancelled = Arc::new(AtomicBool::new(false));
+        active.insert(stream_id.to_string(), (kind, cancelled.clone()));
+        StreamToken { cancelled }
+    }
+
+    pub fn cancel(&self, stream_id: &str) -> bool {
+        match self.active.lock().unwrap().remove(stream_id) {
+            Some((_, cancelled)) => {
+                cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn finish(&self, stream_id: &str, token: &StreamToken) {
+        let mut active = self.active.lock().unwrap();
+        if active
+            .get(stream_id)
+            .is_some_and(|(_, cancelled)| Arc::ptr_eq(cancelled, &token.cancelled))
+        {
+            active.remove(stream_id);
+        }
+    }
+}
+
+/// Chosen by the frontend, so it can cancel a stream before the command returns
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamOptions {
+    pub stream_id: String,
+    /// A `next_cursor` from an earlier page or summary
+    pub cursor: Option<String>,
+    pub page_size: Option<u32>,
+    pub max_items: Option<u32>,
+}
+
+/// Runs a stream to completion. `fetch(after, limit)` reads up to `limit`
+/// rows following `after` and returns them with the position to resume
+/// from, or `None` once nothing is left; `send` delivers a page and returns
+/// false if nobody is listening any more.
+pub fn run<T, C>(
+    registry: &StreamRegistry,
+    kind: &'static str,
+    options: StreamOptions,
+    mut fetch: impl FnMut(Option<&C>, u32) -> Result<(Vec<T>, Option<C>), String>,
+    mut send: impl FnMut(StreamPage<T>) -> bool,
+) -> Result<StreamSummary, String>
+where
+    C: Serialize + DeserializeOwned,
+{
+    let mut cursor: Option<C> = options.cursor.as_deref().map(decode_cursor).transpose()?;
+    let page_size = options
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let max_items = options.max_items.unwrap_or(DEFAULT_MAX_ITEMS).max(1) as usize;
+    let stream_id = options.stream_id;
+
+    let token = registry.begin(kind, &stream_id);
+    let mut sent = 0;
+    let mut cancelled = false;
+    let result = loop {
+        let limit = page_size.min((max_items - sent) as u32);
+        let (items, next) = match fetch(cursor.as_ref(), limit) {
+            Ok(page) => page,
+            Err(e) => break Err(e),
+        };
+        // A superseded stream drops what it just read rather than send it
+        if token.is_cancelled() {
+            cancelled = true;
+            break Ok(());
+        }
+        sent += items.len();
+        cursor = next;
+        let page = StreamPage {
+            stream_id: stream_id.clone(),
+            items,
+            next_cursor: cursor.as_ref().map(encode_cursor),
+        };
+        if !send(page) {
+            cancelled = true;
+            break Ok(());
+        }
+        if cursor.is_none() || sent >= max_items {
+            break Ok(());
+        }
+    };
+    registry.finish(&stream_id, &token);
+    result?;
+
+    Ok(StreamSummary {
+        stream_id,
+        sent,
+        next_cursor: cursor.as_ref().map(encode_cursor),
+        cancelled,
+    })
+}
+
+#[tauri::command]
+pub fn cancel_stream(streams: State<StreamRegistry>, stream_id: String) -> bool {
+    streams.cancel(&stream_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(stream_id: &str, cursor: Option<String>, max_items: u32) -> StreamOptions {
+        StreamOptions {
+            stream_id: stream_id.to_string(),
+            cursor,
+            page_size: Some(3),
+            max_items: Some(max_items),
+        }
+    }
+
+    type Page = Result<(Vec<u32>, Option<u32>), String>;
+
+    /// Rows 0..total, resuming after the last row returned
+    fn numbers(total: u32) -> impl FnMut(Option<&u32>, u32) -> Page {
+        move |after, limit| {
+            let start = after.map_or(0, |n| n + 1);
+            let rows: Vec<u32> = (start..total).take(limit as usize).collect();
+            let next = if (rows.len() as u32) < limit {
+                None
+            } else {
+                rows.last().copied()
+            };
+            Ok((rows, next))
+        }
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let token = encode_cursor(&(42i64, "/home/me/a b.txt".to_string()));
+        assert!(!token.contains('/') && !token.contains('+'));
+        let decoded: (i64, String) = decode_cursor(&token).unwrap();
+        assert_eq!(decoded, (42, "/home/me/a b.txt".to_string()));
+        assert!(decode_cursor::<(i64, String)>("not a cursor").is_err());
+    }
+
+    #[test]
+    fn test_streams_pages_and_resumes() {
+        let registry = StreamRegistry::default();
+        let mut pages = Vec::new();
+        let summary = run(
+            &registry,
+            "numbers",
+            options("a", None, 5),
+            numbers(8),
+            |page| {
+                pages.push(page.items);
+                true
+            },
+        )
+        .unwrap();
+        assert_eq!(pages, vec![vec![0, 1, 2], vec![3, 4]]);
+        assert_eq!(summary.sent, 5);
+        assert!(!summary.cancelled);
+
+        let mut rest = Vec::new();
+        let summary = run(
+            &registry,
+            "numbers",
+            options("b", summary.next_cursor, 100),
+            numbers(8),
+            |page| {
+                rest.extend(page.items);
+                true
+            },
+        )
+        .unwrap();
+        assert_eq!(rest, vec![5, 6, 7]);
+        assert_eq!(summary.next_cursor, None);
+        assert!(registry.active.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_new_stream_cancels_previous_of_same_kind() {
+        let registry = StreamRegistry::default();
+        let first = registry.begin("files", "1");
+        let other = registry.begin("history", "2");
+        let second = registry.begin("files", "3");
+        assert!(first.is_cancelled());
+        assert!(!other.is_cancelled());
+        assert!(!second.is_cancelled());
+
+        assert!(registry.cancel("3"));
+        assert!(second.is_cancelled());
+        assert!(!registry.cancel("3"));
+    }
+
+    #[test]
+    fn test_stops_when_receiver_is_gone() {
+        let registry = StreamRegistry::default();
+        let summary = run(
+            &registry,
+            "numbers",
+            options("a", None, 100),
+            numbers(100),
+            |_| false,
+        )
+        .unwrap();
+        assert!(summary.cancelled);
+        assert_eq!(summary.sent, 3);
+        assert!(summary.next_cursor.is_some());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::confirm::{self, ConfirmationRequest, DangerLevel};
+use crate::dbus::logind;
 use std::process::Command;
 
 #[derive(serde::Serialize, Clone, Debug)]
@@ -8,9 +10,54 @@ pub struct Application {
     bundle_id: Option<String>,
 }
 
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PowerAction {
+    ShutDown,
+    Restart,
+    Sleep,
+    LogOut,
+}
+
+impl PowerAction {
+    fn confirmation(self) -> ConfirmationRequest {
+        let (message, label) = match self {
+            PowerAction::ShutDown => ("Shut down the computer?", "Shut Down"),
+            PowerAction::Restart => ("Restart the computer?", "Restart"),
+            PowerAction::Sleep => ("Put the computer to sleep?", "Sleep"),
+            PowerAction::LogOut => ("Log out?", "Log Out"),
+        };
+        let danger_level = match self {
+            PowerAction::Sleep => DangerLevel::Low,
+            _ => DangerLevel::Medium,
+        };
+        let request = ConfirmationRequest::new(message, danger_level).confirm_label(label);
+        match self {
+            PowerAction::Sleep => request,
+            _ => request.details("Unsaved work in open applications may be lost."),
+        }
+    }
+}
+
+/// Shuts down, restarts, suspends or logs out through logind, once the user
+/// confirms
+#[tauri::command]
+pub async fn system_power(app: tauri::AppHandle, action: PowerAction) -> Result<(), String> {
+    if !confirm::request(&app, action.confirmation()).await {
+        return Err("Cancelled".to_string());
+    }
+    let result = match action {
+        PowerAction::ShutDown => logind::power("PowerOff").await,
+        PowerAction::Restart => logind::power("Reboot").await,
+        PowerAction::Sleep => logind::power("Suspend").await,
+        PowerAction::LogOut => logind::terminate_session().await,
+    };
+    result.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
-pub fn trash(paths: Vec<String>) -> Result<(), String> {
-    trash::delete_all(paths).map_err(|e| e.to_string())
+pub fn trash(app: tauri::AppHandle, paths: Vec<String>) -> Result<(), String> {
+    crate::undo::trash_paths(&app, paths)
 }
 
 #[tauri::command]
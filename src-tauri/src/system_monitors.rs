@@ -1,10 +1,17 @@
+use crate::dbus::upower::{self, Battery};
+use crate::error::AppError;
+use crate::exec;
+use crate::notifications::{self, NotificationOptions, Urgency};
+use crate::power_awareness;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use sysinfo::{CpuRefreshKind, Disks, Networks, RefreshKind, System};
+use tauri::{AppHandle, Emitter, Manager};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CpuInfo {
@@ -52,10 +59,155 @@ pub struct BatteryInfo {
     pub is_charging: bool,
     pub is_present: bool,
     pub time_remaining_minutes: Option<u32>,
+    /// Full charge capacity as a percentage of the design capacity
+    pub health_percent: Option<f64>,
+    pub cycle_count: Option<u32>,
+}
+
+impl From<Battery> for BatteryInfo {
+    fn from(battery: Battery) -> Self {
+        let is_charging = battery.state.is_charging();
+        Self {
+            percentage: battery.percentage,
+            is_charging,
+            is_present: battery.is_present,
+            time_remaining_minutes: (!is_charging && battery.time_to_empty_secs > 0)
+                .then_some((battery.time_to_empty_secs / 60) as u32),
+            health_percent: battery.capacity,
+            cycle_count: battery.charge_cycles,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GpuInfo {
+    pub name: String,
+    pub vendor: String,
+    pub utilization_percent: Option<f64>,
+    pub memory_used_bytes: Option<u64>,
+    pub memory_total_bytes: Option<u64>,
+    pub temperature_celsius: Option<f64>,
+    pub frequency_mhz: Option<u32>,
+    pub max_frequency_mhz: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterfaceRate {
+    pub interface: String,
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorSample {
+    pub timestamp_ms: i64,
+    pub cpu_percent: f64,
+    pub memory_percent: f64,
+    pub memory_used_bytes: u64,
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+    pub interfaces: Vec<InterfaceRate>,
+}
+
+/// How often the background thread records a history sample
+const HISTORY_INTERVAL: Duration = Duration::from_secs(1);
+/// 10 minutes of samples at HISTORY_INTERVAL
+const HISTORY_CAPACITY: usize = 600;
+/// nvidia-smi takes a while to start, and the GPU view polls every second
+const GPU_QUERY_TTL: Duration = Duration::from_secs(2);
+
+/// Fixed-size ring buffer of monitor samples, oldest first
+pub struct MonitorHistory {
+    samples: VecDeque<MonitorSample>,
+    capacity: usize,
+}
+
+impl MonitorHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, sample: MonitorSample) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Samples recorded at or after `since_ms`
+    pub fn since(&self, since_ms: i64) -> Vec<MonitorSample> {
+        self.samples
+            .iter()
+            .filter(|s| s.timestamp_ms >= since_ms)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Tracks memory and network counters between history samples
+struct HistoryRecorder {
+    sys: System,
+    networks: Networks,
+    last_sample: Instant,
+}
+
+impl HistoryRecorder {
+    fn new() -> Self {
+        Self {
+            sys: System::new_with_specifics(
+                RefreshKind::new().with_memory(sysinfo::MemoryRefreshKind::everything()),
+            ),
+            networks: Networks::new_with_refreshed_list(),
+            last_sample: Instant::now(),
+        }
+    }
+
+    fn sample(&mut self, cpu_percent: f64) -> MonitorSample {
+        let elapsed = self.last_sample.elapsed().as_secs_f64().max(0.001);
+        self.last_sample = Instant::now();
+
+        self.sys.refresh_memory();
+        self.networks.refresh();
+
+        let total = self.sys.total_memory();
+        let used = self.sys.used_memory();
+        let memory_percent = if total > 0 {
+            (used as f64 / total as f64) * 100.0
+        } else {
+            0.0
+        };
+
+        // received()/transmitted() are deltas since the previous refresh
+        let interfaces: Vec<InterfaceRate> = self
+            .networks
+            .iter()
+            .map(|(name, data)| InterfaceRate {
+                interface: name.clone(),
+                rx_bytes_per_sec: data.received() as f64 / elapsed,
+                tx_bytes_per_sec: data.transmitted() as f64 / elapsed,
+            })
+            .collect();
+
+        MonitorSample {
+            timestamp_ms: chrono::Utc::now().timestamp_millis(),
+            cpu_percent,
+            memory_percent,
+            memory_used_bytes: used,
+            rx_bytes_per_sec: interfaces.iter().map(|i| i.rx_bytes_per_sec).sum(),
+            tx_bytes_per_sec: interfaces.iter().map(|i| i.tx_bytes_per_sec).sum(),
+            interfaces,
+        }
+    }
 }
 
-// Global cached CPU info updated by background thread
 lazy_static::lazy_static! {
+    static ref MONITOR_HISTORY: Arc<Mutex<MonitorHistory>> =
+        Arc::new(Mutex::new(MonitorHistory::new(HISTORY_CAPACITY)));
+
+    // Global cached CPU info updated by background thread
     static ref CPU_INFO_CACHE: Arc<Mutex<CpuInfo>> = {
         let cache = Arc::new(Mutex::new(CpuInfo {
             usage_percent: 0.0,
@@ -68,10 +220,11 @@ lazy_static::lazy_static! {
             let mut sys = System::new_with_specifics(
                 RefreshKind::new().with_cpu(CpuRefreshKind::everything()),
             );
+            let mut recorder = HistoryRecorder::new();
 
             loop {
                 // Sleep first to allow initial CPU measurement
-                thread::sleep(Duration::from_millis(500));
+                thread::sleep(crate::power_awareness::scaled(Duration::from_millis(500)));
                 sys.refresh_cpu_all();
 
                 let global_usage = sys.global_cpu_usage() as f64;
@@ -91,6 +244,13 @@ lazy_static::lazy_static! {
                         cores,
                     };
                 }
+
+                if recorder.last_sample.elapsed() >= HISTORY_INTERVAL {
+                    let sample = recorder.sample(global_usage);
+                    if let Ok(mut history) = MONITOR_HISTORY.lock() {
+                        history.push(sample);
+                    }
+                }
             }
         });
 
@@ -106,6 +266,25 @@ pub fn get_cpu_info() -> CpuInfo {
         .clone()
 }
 
+/// Start the background sampling thread so history is collected from launch
+pub fn start_background_sampling() {
+    lazy_static::initialize(&CPU_INFO_CACHE);
+}
+
+/// Get recorded samples from the last `seconds` (default: the whole 10 minute buffer)
+pub fn get_history(seconds: Option<u64>) -> Vec<MonitorSample> {
+    start_background_sampling();
+
+    let since_ms = match seconds {
+        Some(seconds) => chrono::Utc::now().timestamp_millis() - (seconds as i64 * 1000),
+        None => i64::MIN,
+    };
+    MONITOR_HISTORY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .since(since_ms)
+}
+
 /// Get current memory usage information
 pub fn get_memory_info() -> MemoryInfo {
     let mut sys = System::new_with_specifics(
@@ -231,7 +410,8 @@ fn read_battery_from_path(battery_path: &Path) -> Option<BatteryInfo> {
         .trim()
         .to_lowercase();
 
-    let is_charging = status.contains("charging") || status.contains("full");
+    // "Not charging" is plugged in but held below full
+    let is_charging = matches!(status.as_str(), "charging" | "full" | "not charging");
 
     // Try to calculate time remaining
     let time_remaining_minutes = if !is_charging {
@@ -265,14 +445,378 @@ fn read_battery_from_path(battery_path: &Path) -> Option<BatteryInfo> {
         None
     };
 
+    let full = |name: &str| read_sysfs_number::<f64>(&battery_path.join(name));
+    let health_percent = full("energy_full")
+        .zip(full("energy_full_design"))
+        .or_else(|| full("charge_full").zip(full("charge_full_design")))
+        .filter(|(_, design)| *design > 0.0)
+        .map(|(full, design)| (full / design * 100.0).min(100.0));
+
     Some(BatteryInfo {
         percentage: capacity,
         is_charging,
         is_present: true,
         time_remaining_minutes,
+        health_percent,
+        cycle_count: read_sysfs_number(&battery_path.join("cycle_count")),
     })
 }
 
+pub const BATTERY_LEVEL_CHANGED_EVENT: &str = "battery-level-changed";
+pub const BATTERY_CHARGING_CHANGED_EVENT: &str = "battery-charging-changed";
+/// Without UPower the battery is read from sysfs this often
+const BATTERY_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryAlertSettings {
+    #[serde(default = "default_alerts_enabled")]
+    pub enabled: bool,
+    /// Percentages to warn at while discharging, once each per discharge
+    #[serde(default = "default_alert_thresholds")]
+    pub thresholds: Vec<u8>,
+}
+
+fn default_alerts_enabled() -> bool {
+    true
+}
+
+fn default_alert_thresholds() -> Vec<u8> {
+    vec![20, 10, 5]
+}
+
+impl Default for BatteryAlertSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_alerts_enabled(),
+            thresholds: default_alert_thresholds(),
+        }
+    }
+}
+
+impl BatteryAlertSettings {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("battery_alerts.json"))
+    }
+
+    pub fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    pub fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+}
+
+/// What changed between two battery readings
+#[derive(Debug, Default, PartialEq)]
+struct BatteryChange {
+    level: bool,
+    charging: bool,
+    /// The threshold just crossed, if it should be announced
+    alert: Option<u8>,
+}
+
+#[derive(Debug, Default)]
+struct BatteryWatch {
+    last: Option<BatteryInfo>,
+    /// The lowest threshold announced since the charger was unplugged
+    alerted: Option<u8>,
+}
+
+impl BatteryWatch {
+    fn observe(&mut self, battery: &BatteryInfo, settings: &BatteryAlertSettings) -> BatteryChange {
+        let mut change = BatteryChange {
+            level: self
+                .last
+                .as_ref()
+                .is_none_or(|last| last.percentage.round() != battery.percentage.round()),
+            charging: self
+                .last
+                .as_ref()
+                .is_some_and(|last| last.is_charging != battery.is_charging),
+            alert: None,
+        };
+        self.last = Some(battery.clone());
+
+        if battery.is_charging {
+            self.alerted = None;
+            return change;
+        }
+        let crossed = settings
+            .thresholds
+            .iter()
+            .copied()
+            .filter(|threshold| battery.percentage <= *threshold as f64)
+            .min();
+        if let Some(threshold) = crossed {
+            if settings.enabled && self.alerted.is_none_or(|alerted| threshold < alerted) {
+                change.alert = Some(threshold);
+            }
+            self.alerted = Some(
+                self.alerted
+                    .map_or(threshold, |alerted| alerted.min(threshold)),
+            );
+        }
+        change
+    }
+}
+
+fn alert_low_battery(battery: &BatteryInfo, threshold: u8, settings: &BatteryAlertSettings) {
+    let lowest = settings.thresholds.iter().min() == Some(&threshold);
+    let mut body = format!("{:.0}% remaining", battery.percentage);
+    if let Some(minutes) = battery.time_remaining_minutes {
+        body.push_str(&format!(
+            ", about {}:{:02} left",
+            minutes / 60,
+            minutes % 60
+        ));
+    }
+    notifications::send_in_background(
+        "Battery Low".to_string(),
+        body,
+        NotificationOptions {
+            sound: Some("battery-caution".to_string()),
+            urgency: Some(if lowest {
+                Urgency::Critical
+            } else {
+                Urgency::Normal
+            }),
+            icon: Some("battery-caution".to_string()),
+            persistent: lowest,
+            ..Default::default()
+        },
+    );
+}
+
+fn handle_battery(app: &AppHandle, watch: &mut BatteryWatch, battery: BatteryInfo) {
+    if !battery.is_present {
+        return;
+    }
+    let settings = BatteryAlertSettings::load(app).unwrap_or_default();
+    let change = watch.observe(&battery, &settings);
+    if change.level {
+        if let Err(e) = app.emit(BATTERY_LEVEL_CHANGED_EVENT, &battery) {
+            tracing::error!(error = %e, "Failed to emit battery level event");
+        }
+    }
+    if change.charging {
+        if let Err(e) = app.emit(BATTERY_CHARGING_CHANGED_EVENT, &battery) {
+            tracing::error!(error = %e, "Failed to emit battery charging event");
+        }
+    }
+    if let Some(threshold) = change.alert {
+        alert_low_battery(&battery, threshold, &settings);
+    }
+}
+
+/// Follows the battery through UPower's change signals, or by polling sysfs
+/// where UPower isn't running, emitting level and charging changes and
+/// warning when it runs low
+pub fn start_battery_watch(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut watch = BatteryWatch::default();
+        match upower::display_battery().await {
+            Ok(battery) => {
+                handle_battery(&app, &mut watch, battery.into());
+                let result = upower::watch(|battery| {
+                    handle_battery(&app, &mut watch, battery.into());
+                })
+                .await;
+                if let Err(e) = result {
+                    tracing::warn!(error = %e, "Lost the UPower battery watch, polling instead");
+                }
+            }
+            Err(e) => tracing::debug!(error = %e, "UPower unavailable, polling the battery"),
+        }
+
+        if get_battery_info().is_none() {
+            return;
+        }
+        loop {
+            if let Some(battery) = get_battery_info() {
+                handle_battery(&app, &mut watch, battery);
+            }
+            power_awareness::sleep(BATTERY_POLL_INTERVAL).await;
+        }
+    });
+}
+/// Get usage information for all GPUs.
+/// NVIDIA cards are queried through nvidia-smi, AMD and Intel through the
+/// amdgpu / i915 sysfs interfaces under /sys/class/drm.
+pub fn get_gpu_info() -> Vec<GpuInfo> {
+    let mut gpus = get_nvidia_gpus();
+
+    let drm_path = Path::new("/sys/class/drm");
+    let Ok(entries) = fs::read_dir(drm_path) else {
+        return gpus;
+    };
+
+    let mut cards: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            // card0, card1... but not connectors such as card0-HDMI-A-1
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.strip_prefix("card")
+                        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+                })
+        })
+        .collect();
+    cards.sort();
+
+    for card in cards {
+        let device = card.join("device");
+        let vendor = read_sysfs_string(&device.join("vendor")).unwrap_or_default();
+        match vendor.as_str() {
+            "0x1002" => gpus.push(read_amd_gpu(&device)),
+            "0x8086" => gpus.push(read_intel_gpu(&card, &device)),
+            // NVIDIA is handled by nvidia-smi above
+            _ => {}
+        }
+    }
+
+    gpus
+}
+
+fn get_nvidia_gpus() -> Vec<GpuInfo> {
+    let output = exec::cached(
+        "nvidia-smi",
+        &[
+            "--query-gpu=name,utilization.gpu,memory.used,memory.total,temperature.gpu,clocks.gr,clocks.max.gr",
+            "--format=csv,noheader,nounits",
+        ],
+        GPU_QUERY_TTL,
+    );
+
+    match output {
+        Ok(output) if output.success => parse_nvidia_smi(&output.stdout),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_nvidia_smi(output: &str) -> Vec<GpuInfo> {
+    const MIB: u64 = 1024 * 1024;
+
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+            if fields.len() < 5 {
+                return None;
+            }
+            // Unsupported values are reported as "[N/A]"
+            let number = |i: usize| fields.get(i).and_then(|f| f.parse::<f64>().ok());
+            Some(GpuInfo {
+                name: fields[0].to_string(),
+                vendor: "NVIDIA".to_string(),
+                utilization_percent: number(1),
+                memory_used_bytes: number(2).map(|mib| mib as u64 * MIB),
+                memory_total_bytes: number(3).map(|mib| mib as u64 * MIB),
+                temperature_celsius: number(4),
+                frequency_mhz: number(5).map(|mhz| mhz as u32),
+                max_frequency_mhz: number(6).map(|mhz| mhz as u32),
+            })
+        })
+        .collect()
+}
+
+fn read_amd_gpu(device: &Path) -> GpuInfo {
+    let (frequency_mhz, max_frequency_mhz) = read_amd_sclk(device);
+    GpuInfo {
+        name: read_sysfs_string(&device.join("product_name"))
+            .unwrap_or_else(|| "AMD Radeon Graphics".to_string()),
+        vendor: "AMD".to_string(),
+        utilization_percent: read_sysfs_number(&device.join("gpu_busy_percent")),
+        memory_used_bytes: read_sysfs_number(&device.join("mem_info_vram_used")),
+        memory_total_bytes: read_sysfs_number(&device.join("mem_info_vram_total")),
+        temperature_celsius: read_hwmon_temperature(device),
+        frequency_mhz,
+        max_frequency_mhz,
+    }
+}
+
+/// pp_dpm_sclk lists the clock states, with the active one marked by `*`
+fn read_amd_sclk(device: &Path) -> (Option<u32>, Option<u32>) {
+    let Some(content) = read_sysfs_string(&device.join("pp_dpm_sclk")) else {
+        return (None, None);
+    };
+    let parse_mhz = |line: &str| -> Option<u32> {
+        line.split_whitespace()
+            .nth(1)?
+            .trim_end_matches('*')
+            .to_lowercase()
+            .trim_end_matches("mhz")
+            .parse()
+            .ok()
+    };
+    let current = content
+        .lines()
+        .find(|line| line.trim_end().ends_with('*'))
+        .and_then(parse_mhz);
+    let max = content.lines().filter_map(parse_mhz).max();
+    (current, max)
+}
+
+/// i915 doesn't expose a busy percentage in sysfs, so report the current
+/// vs. maximum GT frequency instead; memory is shared with the system
+fn read_intel_gpu(card: &Path, device: &Path) -> GpuInfo {
+    GpuInfo {
+        name: read_sysfs_string(&device.join("label"))
+            .unwrap_or_else(|| "Intel Graphics".to_string()),
+        vendor: "Intel".to_string(),
+        utilization_percent: None,
+        memory_used_bytes: None,
+        memory_total_bytes: None,
+        temperature_celsius: read_hwmon_temperature(device),
+        frequency_mhz: read_sysfs_number(&card.join("gt_act_freq_mhz"))
+            .or_else(|| read_sysfs_number(&card.join("gt_cur_freq_mhz"))),
+        max_frequency_mhz: read_sysfs_number(&card.join("gt_max_freq_mhz")),
+    }
+}
+
+fn read_hwmon_temperature(device: &Path) -> Option<f64> {
+    let entries = fs::read_dir(device.join("hwmon")).ok()?;
+    for entry in entries.flatten() {
+        if let Some(millidegrees) = read_sysfs_number::<f64>(&entry.path().join("temp1_input")) {
+            return Some(millidegrees / 1000.0);
+        }
+    }
+    None
+}
+
+fn read_sysfs_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn read_sysfs_number<T: std::str::FromStr>(path: &Path) -> Option<T> {
+    read_sysfs_string(path)?.parse().ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -302,6 +846,89 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_nvidia_smi() {
+        let output = "NVIDIA GeForce RTX 3070, 12, 1024, 8192, 45, 1500, 2100\n\
+                      Tesla T4, [N/A], 0, 15360, [N/A], [N/A], [N/A]\n";
+        let gpus = parse_nvidia_smi(output);
+
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].name, "NVIDIA GeForce RTX 3070");
+        assert_eq!(gpus[0].utilization_percent, Some(12.0));
+        assert_eq!(gpus[0].memory_total_bytes, Some(8192 * 1024 * 1024));
+        assert_eq!(gpus[0].max_frequency_mhz, Some(2100));
+        assert_eq!(gpus[1].utilization_percent, None);
+        assert_eq!(gpus[1].temperature_celsius, None);
+    }
+
+    fn sample_at(timestamp_ms: i64) -> MonitorSample {
+        MonitorSample {
+            timestamp_ms,
+            cpu_percent: 0.0,
+            memory_percent: 0.0,
+            memory_used_bytes: 0,
+            rx_bytes_per_sec: 0.0,
+            tx_bytes_per_sec: 0.0,
+            interfaces: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_history_ring_buffer() {
+        let mut history = MonitorHistory::new(3);
+        for ts in 0..5 {
+            history.push(sample_at(ts));
+        }
+
+        let all: Vec<i64> = history
+            .since(i64::MIN)
+            .iter()
+            .map(|s| s.timestamp_ms)
+            .collect();
+        assert_eq!(all, vec![2, 3, 4]);
+        assert_eq!(history.since(4).len(), 1);
+    }
+
+    #[test]
+    fn test_battery_watch() {
+        let battery = |percentage: f64, is_charging: bool| BatteryInfo {
+            percentage,
+            is_charging,
+            is_present: true,
+            time_remaining_minutes: None,
+            health_percent: None,
+            cycle_count: None,
+        };
+        let settings = BatteryAlertSettings::default();
+        let mut watch = BatteryWatch::default();
+
+        let first = watch.observe(&battery(50.0, false), &settings);
+        assert!(first.level && !first.charging && first.alert.is_none());
+        assert!(!watch.observe(&battery(50.2, false), &settings).level);
+
+        assert_eq!(
+            watch.observe(&battery(19.0, false), &settings).alert,
+            Some(20)
+        );
+        assert_eq!(watch.observe(&battery(18.0, false), &settings).alert, None);
+        assert_eq!(
+            watch.observe(&battery(9.0, false), &settings).alert,
+            Some(10)
+        );
+
+        let plugged = watch.observe(&battery(9.0, true), &settings);
+        assert!(plugged.charging && plugged.alert.is_none());
+        let unplugged = watch.observe(&battery(9.0, false), &settings);
+        assert!(unplugged.charging);
+        assert_eq!(unplugged.alert, Some(10));
+
+        let quiet = BatteryAlertSettings {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(watch.observe(&battery(4.0, false), &quiet).alert, None);
+    }
+
     #[test]
     fn test_network_info() {
         let networks = get_network_info();
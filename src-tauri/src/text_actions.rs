@@ -0,0 +1,600 @@
+use crate::clipboard::{self, ClipboardContent};
+use base64::Engine;
+use md5::Md5;
+use rand::seq::IndexedRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TextAction {
+    Uppercase,
+    Lowercase,
+    TitleCase,
+    SentenceCase,
+    CamelCase,
+    PascalCase,
+    SnakeCase,
+    KebabCase,
+    ConstantCase,
+    Slugify,
+    JsonPretty,
+    JsonMinify,
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    UrlDecode,
+    Md5,
+    Sha256,
+}
+
+/// Where a text action reads its input from
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum TextSource {
+    /// The current selection, falling back to the clipboard when nothing is selected
+    #[default]
+    Selection,
+    Clipboard,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TextStats {
+    pub characters: usize,
+    pub characters_without_spaces: usize,
+    pub words: usize,
+    pub lines: usize,
+    pub paragraphs: usize,
+    pub bytes: usize,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum LoremUnit {
+    Words,
+    Sentences,
+    Paragraphs,
+}
+
+const LOREM_OPENING: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit.";
+const LOREM_WORDS: &[&str] = &[
+    "lorem",
+    "ipsum",
+    "dolor",
+    "sit",
+    "amet",
+    "consectetur",
+    "adipiscing",
+    "elit",
+    "sed",
+    "do",
+    "eiusmod",
+    "tempor",
+    "incididunt",
+    "ut",
+    "labore",
+    "et",
+    "dolore",
+    "magna",
+    "aliqua",
+    "enim",
+    "ad",
+    "minim",
+    "veniam",
+    "quis",
+    "nostrud",
+    "exercitation",
+    "ullamco",
+    "laboris",
+    "nisi",
+    "aliquip",
+    "ex",
+    "ea",
+    "commodo",
+    "consequat",
+    "duis",
+    "aute",
+    "irure",
+    "in",
+    "reprehenderit",
+    "voluptate",
+    "velit",
+    "esse",
+    "cillum",
+    "eu",
+    "fugiat",
+    "nulla",
+    "pariatur",
+    "excepteur",
+    "sint",
+    "occaecat",
+    "cupidatat",
+    "non",
+    "proident",
+    "sunt",
+    "culpa",
+    "qui",
+    "officia",
+    "deserunt",
+    "mollit",
+    "anim",
+    "id",
+    "est",
+    "laborum",
+];
+
+/// Splits identifiers and prose into words, breaking on separators and on
+/// case changes (`parseHTTPResponse` becomes `parse`, `HTTP`, `Response`).
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let chars: Vec<char> = text.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if let Some(prev) = current.chars().last() {
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            let to_upper = (prev.is_lowercase() || prev.is_numeric()) && c.is_uppercase();
+            let acronym_end = prev.is_uppercase() && c.is_uppercase() && next_is_lower;
+            if to_upper || acronym_end {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// Capitalizes each word in place, keeping the original spacing and punctuation
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut at_word_start = true;
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '\'' {
+            if at_word_start {
+                result.extend(c.to_uppercase());
+            } else {
+                result.extend(c.to_lowercase());
+            }
+            at_word_start = false;
+        } else {
+            result.push(c);
+            at_word_start = true;
+        }
+    }
+    result
+}
+
+fn sentence_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut capitalize_next = true;
+    for c in text.chars() {
+        if c.is_alphabetic() {
+            if capitalize_next {
+                result.extend(c.to_uppercase());
+                capitalize_next = false;
+            } else {
+                result.extend(c.to_lowercase());
+            }
+        } else {
+            if matches!(c, '.' | '!' | '?' | '\n') {
+                capitalize_next = true;
+            } else if c.is_numeric() {
+                capitalize_next = false;
+            }
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn join_words(text: &str, separator: &str, upper: bool) -> String {
+    split_words(text)
+        .iter()
+        .map(|w| {
+            if upper {
+                w.to_uppercase()
+            } else {
+                w.to_lowercase()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn camel_case(text: &str, upper_first: bool) -> String {
+    split_words(text)
+        .iter()
+        .enumerate()
+        .map(|(i, w)| {
+            if i == 0 && !upper_first {
+                w.to_lowercase()
+            } else {
+                capitalize(w)
+            }
+        })
+        .collect()
+}
+
+/// Lower-case ASCII slug; common Latin diacritics are folded, other
+/// non-alphanumeric runs collapse into a single dash.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    let mut pending_dash = false;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let folded = match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ą' => "a",
+            'æ' => "ae",
+            'ç' | 'ć' | 'č' => "c",
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => "e",
+            'ì' | 'í' | 'î' | 'ï' | 'ī' => "i",
+            'ñ' | 'ń' | 'ň' => "n",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+            'ý' | 'ÿ' => "y",
+            'ß' => "ss",
+            'ł' => "l",
+            'ś' | 'š' => "s",
+            'ź' | 'ż' | 'ž' => "z",
+            'ř' => "r",
+            'ť' => "t",
+            'ď' => "d",
+            _ => "",
+        };
+        if !folded.is_empty() || c.is_ascii_alphanumeric() {
+            if pending_dash && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_dash = false;
+            if folded.is_empty() {
+                slug.push(c);
+            } else {
+                slug.push_str(folded);
+            }
+        } else {
+            pending_dash = true;
+        }
+    }
+    slug
+}
+
+pub fn apply(action: TextAction, text: &str) -> Result<String, String> {
+    let result = match action {
+        TextAction::Uppercase => text.to_uppercase(),
+        TextAction::Lowercase => text.to_lowercase(),
+        TextAction::TitleCase => title_case(text),
+        TextAction::SentenceCase => sentence_case(text),
+        TextAction::CamelCase => camel_case(text, false),
+        TextAction::PascalCase => camel_case(text, true),
+        TextAction::SnakeCase => join_words(text, "_", false),
+        TextAction::KebabCase => join_words(text, "-", false),
+        TextAction::ConstantCase => join_words(text, "_", true),
+        TextAction::Slugify => slugify(text),
+        TextAction::JsonPretty | TextAction::JsonMinify => {
+            let value: serde_json::Value =
+                serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?;
+            if action == TextAction::JsonPretty {
+                serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?
+            } else {
+                serde_json::to_string(&value).map_err(|e| e.to_string())?
+            }
+        }
+        TextAction::Base64Encode => base64::engine::general_purpose::STANDARD.encode(text),
+        TextAction::Base64Decode => {
+            // Accept the URL-safe alphabet and missing padding as well
+            let cleaned: String = text
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| match c {
+                    '-' => '+',
+                    '_' => '/',
+                    c => c,
+                })
+                .collect();
+            let bytes = base64::engine::general_purpose::STANDARD_NO_PAD
+                .decode(cleaned.trim_end_matches('='))
+                .map_err(|e| format!("Invalid base64: {}", e))?;
+            String::from_utf8(bytes).map_err(|_| "Decoded data is not valid UTF-8".to_string())?
+        }
+        TextAction::UrlEncode => urlencoding::encode(text).into_owned(),
+        TextAction::UrlDecode => urlencoding::decode(&text.replace('+', " "))
+            .map_err(|e| format!("Invalid URL encoding: {}", e))?
+            .into_owned(),
+        TextAction::Md5 => hex::encode(Md5::digest(text.as_bytes())),
+        TextAction::Sha256 => hex::encode(Sha256::digest(text.as_bytes())),
+    };
+    Ok(result)
+}
+
+pub fn count(text: &str) -> TextStats {
+    TextStats {
+        characters: text.chars().count(),
+        characters_without_spaces: text.chars().filter(|c| !c.is_whitespace()).count(),
+        words: text.split_whitespace().count(),
+        lines: if text.is_empty() {
+            0
+        } else {
+            text.lines().count()
+        },
+        paragraphs: text.split("\n\n").filter(|p| !p.trim().is_empty()).count(),
+        bytes: text.len(),
+    }
+}
+
+fn lorem_sentence(rng: &mut impl Rng) -> String {
+    let length = rng.random_range(6..=14);
+    let words: Vec<&str> = (0..length)
+        .map(|_| *LOREM_WORDS.choose(rng).unwrap())
+        .collect();
+    let mut sentence = capitalize(&words.join(" "));
+    if length > 9 {
+        // Add a comma roughly in the middle of longer sentences
+        if let Some(pos) = sentence
+            .match_indices(' ')
+            .nth(length / 2 - 1)
+            .map(|(i, _)| i)
+        {
+            sentence.insert(pos, ',');
+        }
+    }
+    sentence.push('.');
+    sentence
+}
+
+fn lorem_paragraph(rng: &mut impl Rng, first: bool) -> String {
+    let sentences = rng.random_range(4..=7);
+    let mut paragraph: Vec<String> = (0..sentences).map(|_| lorem_sentence(rng)).collect();
+    if first {
+        paragraph[0] = LOREM_OPENING.to_string();
+    }
+    paragraph.join(" ")
+}
+
+/// Placeholder text that always opens with the classic "Lorem ipsum dolor sit amet"
+pub fn lorem_ipsum(count: usize, unit: LoremUnit) -> String {
+    let mut rng = rand::rng();
+    match unit {
+        LoremUnit::Words => {
+            let opening: Vec<&str> = LOREM_OPENING
+                .trim_end_matches('.')
+                .split(' ')
+                .map(|w| w.trim_end_matches(','))
+                .collect();
+            let words: Vec<&str> = (0..count)
+                .map(|i| {
+                    opening
+                        .get(i)
+                        .copied()
+                        .unwrap_or_else(|| *LOREM_WORDS.choose(&mut rng).unwrap())
+                })
+                .collect();
+            words.join(" ")
+        }
+        LoremUnit::Sentences => (0..count)
+            .map(|i| {
+                if i == 0 {
+                    LOREM_OPENING.to_string()
+                } else {
+                    lorem_sentence(&mut rng)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        LoremUnit::Paragraphs => (0..count)
+            .map(|i| lorem_paragraph(&mut rng, i == 0))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    }
+}
+
+fn read_source(app: &tauri::AppHandle, source: TextSource) -> Result<String, String> {
+    let text = match source {
+        TextSource::Selection => {
+            let selected = selection::get_text();
+            if selected.is_empty() {
+                app.clipboard().read_text().unwrap_or_default()
+            } else {
+                selected
+            }
+        }
+        TextSource::Clipboard => app.clipboard().read_text().unwrap_or_default(),
+    };
+    if text.is_empty() {
+        return Err("No text selected or on the clipboard".to_string());
+    }
+    Ok(text)
+}
+
+/// Transforms text without touching the selection; used for previews
+#[tauri::command]
+pub fn text_action_preview(action: TextAction, text: String) -> Result<String, String> {
+    apply(action, &text)
+}
+
+/// Transforms the selection (or clipboard) and pastes the result back over it
+#[tauri::command]
+pub async fn text_action_run(
+    app: tauri::AppHandle,
+    action: TextAction,
+    source: Option<TextSource>,
+) -> Result<String, String> {
+    let text = read_source(&app, source.unwrap_or_default())?;
+    let result = apply(action, &text)?;
+    clipboard::clipboard_paste(app, ClipboardContent::from_text(result.clone())).await?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn text_count(
+    app: tauri::AppHandle,
+    text: Option<String>,
+    source: Option<TextSource>,
+) -> Result<TextStats, String> {
+    let text = match text {
+        Some(text) => text,
+        None => read_source(&app, source.unwrap_or_default())?,
+    };
+    Ok(count(&text))
+}
+
+#[tauri::command]
+pub async fn text_lorem_ipsum(
+    app: tauri::AppHandle,
+    count: usize,
+    unit: LoremUnit,
+    paste: Option<bool>,
+) -> Result<String, String> {
+    let text = lorem_ipsum(count.max(1), unit);
+    if paste.unwrap_or(false) {
+        clipboard::clipboard_paste(app, ClipboardContent::from_text(text.clone())).await?;
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words("parseHTTPResponse"),
+            vec!["parse", "HTTP", "Response"]
+        );
+        assert_eq!(
+            split_words("hello_world-foo bar"),
+            vec!["hello", "world", "foo", "bar"]
+        );
+        assert_eq!(split_words("Version2Update"), vec!["Version2", "Update"]);
+    }
+
+    #[test]
+    fn test_case_conversions() {
+        let input = "the quick brownFox";
+        assert_eq!(
+            apply(TextAction::CamelCase, input).unwrap(),
+            "theQuickBrownFox"
+        );
+        assert_eq!(
+            apply(TextAction::PascalCase, input).unwrap(),
+            "TheQuickBrownFox"
+        );
+        assert_eq!(
+            apply(TextAction::SnakeCase, input).unwrap(),
+            "the_quick_brown_fox"
+        );
+        assert_eq!(
+            apply(TextAction::KebabCase, input).unwrap(),
+            "the-quick-brown-fox"
+        );
+        assert_eq!(
+            apply(TextAction::ConstantCase, input).unwrap(),
+            "THE_QUICK_BROWN_FOX"
+        );
+        assert_eq!(
+            apply(TextAction::TitleCase, "hello wORLD, it's me").unwrap(),
+            "Hello World, It's Me"
+        );
+        assert_eq!(
+            apply(TextAction::SentenceCase, "HELLO THERE. how ARE you?").unwrap(),
+            "Hello there. How are you?"
+        );
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("  Héllo, Wörld! 2024 "), "hello-world-2024");
+        assert_eq!(slugify("Straße & Café"), "strasse-cafe");
+        assert_eq!(slugify("---"), "");
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            apply(TextAction::JsonMinify, "{ \"a\": [1, 2] }").unwrap(),
+            "{\"a\":[1,2]}"
+        );
+        assert_eq!(
+            apply(TextAction::JsonPretty, "{\"a\":1}").unwrap(),
+            "{\n  \"a\": 1\n}"
+        );
+        assert!(apply(TextAction::JsonPretty, "{oops").is_err());
+    }
+
+    #[test]
+    fn test_base64_and_url() {
+        assert_eq!(apply(TextAction::Base64Encode, "hi?").unwrap(), "aGk/");
+        assert_eq!(apply(TextAction::Base64Decode, "aGk/").unwrap(), "hi?");
+        assert_eq!(apply(TextAction::Base64Decode, "aGk_").unwrap(), "hi?");
+        assert_eq!(apply(TextAction::Base64Decode, "aGVsbG8").unwrap(), "hello");
+        assert_eq!(apply(TextAction::UrlEncode, "a b&c").unwrap(), "a%20b%26c");
+        assert_eq!(apply(TextAction::UrlDecode, "a+b%26c").unwrap(), "a b&c");
+    }
+
+    #[test]
+    fn test_hashes() {
+        assert_eq!(
+            apply(TextAction::Md5, "").unwrap(),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+        assert_eq!(
+            apply(
+                TextAction::Md5,
+                "The quick brown fox jumps over the lazy dog"
+            )
+            .unwrap(),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+        assert_eq!(
+            apply(TextAction::Sha256, "abc").unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_count() {
+        let stats = count("Hello world.\nSecond line\n\nNew paragraph");
+        assert_eq!(stats.words, 6);
+        assert_eq!(stats.lines, 4);
+        assert_eq!(stats.paragraphs, 2);
+        assert_eq!(stats.characters, 39);
+        assert_eq!(stats.characters_without_spaces, 33);
+        assert_eq!(count("").lines, 0);
+    }
+
+    #[test]
+    fn test_lorem_ipsum() {
+        assert_eq!(
+            lorem_ipsum(5, LoremUnit::Words),
+            "Lorem ipsum dolor sit amet"
+        );
+        assert_eq!(lorem_ipsum(12, LoremUnit::Words).split(' ').count(), 12);
+        assert!(lorem_ipsum(3, LoremUnit::Sentences).starts_with(LOREM_OPENING));
+        assert_eq!(
+            lorem_ipsum(3, LoremUnit::Paragraphs).split("\n\n").count(),
+            3
+        );
+    }
+}
@@ -0,0 +1,481 @@
+use crate::notifications::{self, NotificationOptions, Urgency};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+const COMPLETION_SOUND: &str = "alarm-clock-elapsed";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TimerKind {
+    /// Counts down from a fixed duration
+    Countdown,
+    /// Counts up until cancelled
+    Stopwatch,
+    /// Fires at a wall-clock time
+    Alarm,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TimerStatus {
+    Running,
+    Paused,
+    Completed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Timer {
+    pub id: String,
+    pub label: Option<String>,
+    pub kind: TimerKind,
+    /// Total length for countdowns, unused for stopwatches
+    pub duration_ms: Option<u64>,
+    /// Wall-clock time (ms since epoch) an alarm goes off
+    pub alarm_at_ms: Option<i64>,
+    /// Time accumulated in previous runs, before the last pause
+    pub accumulated_ms: u64,
+    /// Start of the current run (ms since epoch) while running
+    pub running_since_ms: Option<i64>,
+    pub status: TimerStatus,
+    pub sound: bool,
+    pub created_at_ms: i64,
+}
+
+impl Timer {
+    fn new(kind: TimerKind, label: Option<String>, sound: bool, now_ms: i64) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            label,
+            kind,
+            duration_ms: None,
+            alarm_at_ms: None,
+            accumulated_ms: 0,
+            running_since_ms: Some(now_ms),
+            status: TimerStatus::Running,
+            sound,
+            created_at_ms: now_ms,
+        }
+    }
+
+    pub fn elapsed_ms(&self, now_ms: i64) -> u64 {
+        let current_run = self
+            .running_since_ms
+            .map_or(0, |since| (now_ms - since).max(0) as u64);
+        self.accumulated_ms + current_run
+    }
+
+    pub fn remaining_ms(&self, now_ms: i64) -> Option<u64> {
+        match self.kind {
+            TimerKind::Countdown => self
+                .duration_ms
+                .map(|duration| duration.saturating_sub(self.elapsed_ms(now_ms))),
+            TimerKind::Alarm => self.alarm_at_ms.map(|at| (at - now_ms).max(0) as u64),
+            TimerKind::Stopwatch => None,
+        }
+    }
+
+    fn is_due(&self, now_ms: i64) -> bool {
+        self.status == TimerStatus::Running && self.remaining_ms(now_ms) == Some(0)
+    }
+
+    fn pause(&mut self, now_ms: i64) -> Result<(), String> {
+        if self.kind == TimerKind::Alarm {
+            return Err("Alarms can't be paused".to_string());
+        }
+        if self.status != TimerStatus::Running {
+            return Err("Timer is not running".to_string());
+        }
+        self.accumulated_ms = self.elapsed_ms(now_ms);
+        self.running_since_ms = None;
+        self.status = TimerStatus::Paused;
+        Ok(())
+    }
+
+    fn resume(&mut self, now_ms: i64) -> Result<(), String> {
+        if self.status != TimerStatus::Paused {
+            return Err("Timer is not paused".to_string());
+        }
+        self.running_since_ms = Some(now_ms);
+        self.status = TimerStatus::Running;
+        Ok(())
+    }
+
+    fn complete(&mut self, now_ms: i64) {
+        self.accumulated_ms = self.elapsed_ms(now_ms);
+        self.running_since_ms = None;
+        self.status = TimerStatus::Completed;
+    }
+
+    fn title(&self) -> String {
+        match (&self.label, self.kind) {
+            (Some(label), _) => label.clone(),
+            (None, TimerKind::Alarm) => "Alarm".to_string(),
+            (None, _) => "Timer".to_string(),
+        }
+    }
+}
+
+/// Timer plus derived values, as sent to the frontend
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerSnapshot {
+    #[serde(flatten)]
+    pub timer: Timer,
+    pub elapsed_ms: u64,
+    pub remaining_ms: Option<u64>,
+}
+
+impl TimerSnapshot {
+    fn of(timer: &Timer, now_ms: i64) -> Self {
+        Self {
+            timer: timer.clone(),
+            elapsed_ms: timer.elapsed_ms(now_ms),
+            remaining_ms: timer.remaining_ms(now_ms),
+        }
+    }
+}
+
+pub struct TimerManager {
+    path: Option<PathBuf>,
+    timers: Mutex<Vec<Timer>>,
+}
+
+impl TimerManager {
+    pub fn new(app: &AppHandle) -> Result<Self, String> {
+        let path = get_timers_path(app)?;
+        let timers = read_timers(&path)?;
+        Ok(Self {
+            path: Some(path),
+            timers: Mutex::new(timers),
+        })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Self {
+        Self {
+            path: None,
+            timers: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn persist(&self, timers: &[Timer]) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content = serde_json::to_string_pretty(timers).map_err(|e| e.to_string())?;
+        fs::write(path, content).map_err(|e| e.to_string())
+    }
+
+    fn insert(&self, timer: Timer) -> Result<Timer, String> {
+        let mut timers = self.timers.lock().unwrap();
+        timers.push(timer.clone());
+        self.persist(&timers)?;
+        Ok(timer)
+    }
+
+    pub fn create_countdown(
+        &self,
+        duration_ms: u64,
+        label: Option<String>,
+        sound: bool,
+        now_ms: i64,
+    ) -> Result<Timer, String> {
+        if duration_ms == 0 {
+            return Err("Timer duration must be positive".to_string());
+        }
+        let mut timer = Timer::new(TimerKind::Countdown, label, sound, now_ms);
+        timer.duration_ms = Some(duration_ms);
+        self.insert(timer)
+    }
+
+    pub fn create_stopwatch(&self, label: Option<String>, now_ms: i64) -> Result<Timer, String> {
+        self.insert(Timer::new(TimerKind::Stopwatch, label, false, now_ms))
+    }
+
+    pub fn create_alarm(
+        &self,
+        at_ms: i64,
+        label: Option<String>,
+        sound: bool,
+        now_ms: i64,
+    ) -> Result<Timer, String> {
+        if at_ms <= now_ms {
+            return Err("Alarm time must be in the future".to_string());
+        }
+        let mut timer = Timer::new(TimerKind::Alarm, label, sound, now_ms);
+        timer.alarm_at_ms = Some(at_ms);
+        self.insert(timer)
+    }
+
+    pub fn list(&self, now_ms: i64) -> Vec<TimerSnapshot> {
+        self.timers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|timer| TimerSnapshot::of(timer, now_ms))
+            .collect()
+    }
+
+    pub fn cancel(&self, id: &str) -> Result<(), String> {
+        let mut timers = self.timers.lock().unwrap();
+        let before = timers.len();
+        timers.retain(|timer| timer.id != id);
+        if timers.len() == before {
+            return Err(format!("Timer {} not found", id));
+        }
+        self.persist(&timers)
+    }
+
+    fn update<F>(&self, id: &str, f: F) -> Result<(), String>
+    where
+        F: FnOnce(&mut Timer) -> Result<(), String>,
+    {
+        let mut timers = self.timers.lock().unwrap();
+        let timer = timers
+            .iter_mut()
+            .find(|timer| timer.id == id)
+            .ok_or_else(|| format!("Timer {} not found", id))?;
+        f(timer)?;
+        self.persist(&timers)
+    }
+
+    pub fn pause(&self, id: &str, now_ms: i64) -> Result<(), String> {
+        self.update(id, |timer| timer.pause(now_ms))
+    }
+
+    pub fn resume(&self, id: &str, now_ms: i64) -> Result<(), String> {
+        self.update(id, |timer| timer.resume(now_ms))
+    }
+
+    /// Marks every due timer as completed and returns them.
+    /// Also catches timers that expired while the app wasn't running.
+    pub fn complete_due(&self, now_ms: i64) -> Vec<Timer> {
+        let mut timers = self.timers.lock().unwrap();
+        let mut completed = Vec::new();
+        for timer in timers.iter_mut().filter(|timer| timer.is_due(now_ms)) {
+            timer.complete(now_ms);
+            completed.push(timer.clone());
+        }
+        if !completed.is_empty() {
+            if let Err(e) = self.persist(&timers) {
+                tracing::error!(error = %e, "Failed to persist timers");
+            }
+        }
+        completed
+    }
+
+    fn has_running(&self) -> bool {
+        self.timers
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|timer| timer.status == TimerStatus::Running)
+    }
+}
+
+fn get_timers_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| "Failed to get app local data dir".to_string())?;
+
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(data_dir.join("timers.json"))
+}
+
+fn read_timers(path: &Path) -> Result<Vec<Timer>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+fn now_ms() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+fn notify_completed(timer: &Timer) {
+    let body = match timer.kind {
+        TimerKind::Alarm => "Your alarm went off".to_string(),
+        _ => "Time's up!".to_string(),
+    };
+    notifications::send_in_background(
+        timer.title(),
+        body,
+        NotificationOptions {
+            sound: timer.sound.then(|| COMPLETION_SOUND.to_string()),
+            urgency: Some(Urgency::Critical),
+            icon: Some("alarm-symbolic".to_string()),
+            ..Default::default()
+        },
+    );
+}
+
+/// Loads saved timers and starts the ticker that emits `timers-tick` for the
+/// HUD and `timer-completed` when a countdown or alarm finishes.
+pub fn init(app: &AppHandle) {
+    let manager = match TimerManager::new(app) {
+        Ok(manager) => manager,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to load timers");
+            TimerManager {
+                path: get_timers_path(app).ok(),
+                timers: Mutex::new(Vec::new()),
+            }
+        }
+    };
+    app.manage(manager);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let manager = app.state::<TimerManager>();
+            let now = now_ms();
+
+            for timer in manager.complete_due(now) {
+                tracing::info!(id = %timer.id, "Timer completed");
+                notify_completed(&timer);
+                let _ = app.emit("timer-completed", TimerSnapshot::of(&timer, now));
+            }
+
+            if manager.has_running() {
+                let _ = app.emit("timers-tick", manager.list(now));
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn create_timer(
+    app: AppHandle,
+    duration_secs: u64,
+    label: Option<String>,
+    sound: Option<bool>,
+) -> Result<Timer, String> {
+    // Stored as i64 milliseconds
+    let duration_ms = duration_secs
+        .checked_mul(1000)
+        .filter(|ms| *ms <= i64::MAX as u64)
+        .ok_or("Timer duration is too long")?;
+    app.state::<TimerManager>().create_countdown(
+        duration_ms,
+        label,
+        sound.unwrap_or(true),
+        now_ms(),
+    )
+}
+
+#[tauri::command]
+pub fn create_stopwatch(app: AppHandle, label: Option<String>) -> Result<Timer, String> {
+    app.state::<TimerManager>()
+        .create_stopwatch(label, now_ms())
+}
+
+/// `at` is an RFC 3339 timestamp
+#[tauri::command]
+pub fn create_alarm(
+    app: AppHandle,
+    at: String,
+    label: Option<String>,
+    sound: Option<bool>,
+) -> Result<Timer, String> {
+    let at_ms = chrono::DateTime::parse_from_rfc3339(&at)
+        .map_err(|e| format!("Invalid alarm time: {}", e))?
+        .timestamp_millis();
+    app.state::<TimerManager>()
+        .create_alarm(at_ms, label, sound.unwrap_or(true), now_ms())
+}
+
+#[tauri::command]
+pub fn list_timers(app: AppHandle) -> Vec<TimerSnapshot> {
+    app.state::<TimerManager>().list(now_ms())
+}
+
+#[tauri::command]
+pub fn cancel_timer(app: AppHandle, id: String) -> Result<(), String> {
+    app.state::<TimerManager>().cancel(&id)
+}
+
+#[tauri::command]
+pub fn pause_timer(app: AppHandle, id: String) -> Result<(), String> {
+    app.state::<TimerManager>().pause(&id, now_ms())
+}
+
+#[tauri::command]
+pub fn resume_timer(app: AppHandle, id: String) -> Result<(), String> {
+    app.state::<TimerManager>().resume(&id, now_ms())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_countdown_pause_and_resume() {
+        let manager = TimerManager::new_for_test();
+        let timer = manager
+            .create_countdown(10_000, Some("Tea".to_string()), false, 0)
+            .unwrap();
+
+        manager.pause(&timer.id, 4_000).unwrap();
+        // Time spent paused doesn't count
+        let snapshot = &manager.list(60_000)[0];
+        assert_eq!(snapshot.remaining_ms, Some(6_000));
+        assert_eq!(snapshot.timer.status, TimerStatus::Paused);
+
+        manager.resume(&timer.id, 60_000).unwrap();
+        assert_eq!(manager.list(61_000)[0].remaining_ms, Some(5_000));
+        assert!(manager.resume(&timer.id, 61_000).is_err());
+    }
+
+    #[test]
+    fn test_complete_due() {
+        let manager = TimerManager::new_for_test();
+        let short = manager.create_countdown(1_000, None, true, 0).unwrap();
+        manager.create_countdown(5_000, None, true, 0).unwrap();
+        manager.create_stopwatch(None, 0).unwrap();
+
+        assert!(manager.complete_due(500).is_empty());
+        let completed = manager.complete_due(1_200);
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].id, short.id);
+        // Already completed timers aren't reported again
+        assert!(manager.complete_due(1_300).is_empty());
+    }
+
+    #[test]
+    fn test_alarm_and_stopwatch() {
+        let manager = TimerManager::new_for_test();
+        assert!(manager.create_alarm(100, None, true, 200).is_err());
+        let alarm = manager.create_alarm(10_000, None, true, 0).unwrap();
+        assert!(manager.pause(&alarm.id, 1_000).is_err());
+
+        let stopwatch = manager.create_stopwatch(None, 0).unwrap();
+        manager.pause(&stopwatch.id, 3_000).unwrap();
+        let snapshots = manager.list(9_000);
+        let stopwatch = snapshots
+            .iter()
+            .find(|s| s.timer.kind == TimerKind::Stopwatch)
+            .unwrap();
+        assert_eq!(stopwatch.elapsed_ms, 3_000);
+        assert_eq!(stopwatch.remaining_ms, None);
+
+        manager.cancel(&alarm.id).unwrap();
+        assert_eq!(manager.list(9_000).len(), 1);
+    }
+}
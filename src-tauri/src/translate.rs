@@ -0,0 +1,602 @@
+use crate::error::AppError;
+use crate::secrets;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const TRANSLATION_HISTORY_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS translations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    source_text TEXT NOT NULL,
+    translated_text TEXT NOT NULL,
+    source_lang TEXT,
+    target_lang TEXT NOT NULL,
+    backend TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+)";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum TranslateBackend {
+    #[default]
+    DeepL,
+    LibreTranslate,
+    Ollama,
+}
+
+impl TranslateBackend {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TranslateBackend::DeepL => "deepL",
+            TranslateBackend::LibreTranslate => "libreTranslate",
+            TranslateBackend::Ollama => "ollama",
+        }
+    }
+
+    fn from_db(s: &str) -> Self {
+        match s {
+            "libreTranslate" => TranslateBackend::LibreTranslate,
+            "ollama" => TranslateBackend::Ollama,
+            _ => TranslateBackend::DeepL,
+        }
+    }
+
+    fn secret_key(&self) -> Option<&'static str> {
+        match self {
+            TranslateBackend::DeepL => Some("deepl_api_key"),
+            TranslateBackend::LibreTranslate => Some("libretranslate_api_key"),
+            TranslateBackend::Ollama => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslateSettings {
+    #[serde(default)]
+    pub backend: TranslateBackend,
+    #[serde(default = "default_libretranslate_url")]
+    pub libretranslate_url: String,
+    #[serde(default = "default_ollama_url")]
+    pub ollama_url: String,
+    #[serde(default = "default_ollama_model")]
+    pub ollama_model: String,
+    #[serde(default = "default_true")]
+    pub save_history: bool,
+}
+
+impl Default for TranslateSettings {
+    fn default() -> Self {
+        Self {
+            backend: TranslateBackend::default(),
+            libretranslate_url: default_libretranslate_url(),
+            ollama_url: default_ollama_url(),
+            ollama_model: default_ollama_model(),
+            save_history: true,
+        }
+    }
+}
+
+fn default_libretranslate_url() -> String {
+    "https://libretranslate.com".to_string()
+}
+
+fn default_ollama_url() -> String {
+    "http://localhost:11434".to_string()
+}
+
+fn default_ollama_model() -> String {
+    "llama3".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Translation {
+    pub source_text: String,
+    pub translated_text: String,
+    /// Language the backend reported (or was told) the source was in
+    pub source_lang: Option<String>,
+    pub target_lang: String,
+    pub backend: TranslateBackend,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationHistoryEntry {
+    pub id: i64,
+    #[serde(flatten)]
+    pub translation: Translation,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Storable for TranslationHistoryEntry {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let backend: String = row.get(5)?;
+        let created_at_ts: i64 = row.get(6)?;
+        Ok(TranslationHistoryEntry {
+            id: row.get(0)?,
+            translation: Translation {
+                source_text: row.get(1)?,
+                translated_text: row.get(2)?,
+                source_lang: row.get(3)?,
+                target_lang: row.get(4)?,
+                backend: TranslateBackend::from_db(&backend),
+            },
+            created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
+        })
+    }
+}
+
+pub struct TranslationHistoryManager {
+    store: Store,
+}
+
+impl TranslationHistoryManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "translations.sqlite")?;
+        store.init_table(TRANSLATION_HISTORY_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(TRANSLATION_HISTORY_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    pub fn record(&self, translation: &Translation) -> Result<i64, AppError> {
+        self.store.execute(
+            "INSERT INTO translations (source_text, translated_text, source_lang, target_lang, backend, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                translation.source_text,
+                translation.translated_text,
+                translation.source_lang,
+                translation.target_lang,
+                translation.backend.as_str(),
+                Utc::now().timestamp()
+            ],
+        )?;
+        Ok(self.store.last_insert_rowid())
+    }
+
+    pub fn list(&self, limit: u32, offset: u32) -> Result<Vec<TranslationHistoryEntry>, AppError> {
+        self.store.query(
+            "SELECT id, source_text, translated_text, source_lang, target_lang, backend, created_at
+             FROM translations ORDER BY created_at DESC, id DESC LIMIT ?1 OFFSET ?2",
+            params![limit, offset],
+        )
+    }
+
+    pub fn delete(&self, id: i64) -> Result<(), AppError> {
+        self.store
+            .execute("DELETE FROM translations WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<(), AppError> {
+        self.store.execute("DELETE FROM translations", [])?;
+        Ok(())
+    }
+}
+
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| "Failed to get app local data dir".to_string())?;
+
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(data_dir.join("translate_settings.json"))
+}
+
+fn read_settings(path: &Path) -> Result<TranslateSettings, String> {
+    if !path.exists() {
+        return Ok(TranslateSettings::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if content.trim().is_empty() {
+        return Ok(TranslateSettings::default());
+    }
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+fn require_secret_key(backend: TranslateBackend) -> Result<&'static str, AppError> {
+    backend
+        .secret_key()
+        .ok_or_else(|| AppError::Translate(format!("{} does not use an API key", backend.as_str())))
+}
+
+fn get_api_key(backend: TranslateBackend) -> Result<Option<String>, String> {
+    require_secret_key(backend)
+        .and_then(|key| secrets::get(secrets::TRANSLATE, key))
+        .map_err(|e| e.to_string())
+}
+
+/// DeepL free-tier keys end in `:fx` and are served from a separate host
+fn deepl_endpoint(api_key: &str) -> &'static str {
+    if api_key.ends_with(":fx") {
+        "https://api-free.deepl.com/v2/translate"
+    } else {
+        "https://api.deepl.com/v2/translate"
+    }
+}
+
+/// DeepL wants upper-case codes and distinguishes regional English/Portuguese
+/// targets; a bare `en`/`pt` target is mapped to the most common variant.
+fn deepl_target_lang(lang: &str) -> String {
+    match lang.to_ascii_lowercase().as_str() {
+        "en" => "EN-US".to_string(),
+        "pt" => "PT-PT".to_string(),
+        other => other.to_ascii_uppercase(),
+    }
+}
+
+fn normalize_lang(lang: &str) -> String {
+    lang.trim().to_ascii_lowercase()
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+async fn error_from_response(backend: &str, response: reqwest::Response) -> String {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    format!("{} returned {}: {}", backend, status, body.trim())
+}
+
+async fn translate_deepl(
+    text: &str,
+    source_lang: Option<&str>,
+    target_lang: &str,
+) -> Result<(String, Option<String>), String> {
+    let api_key = get_api_key(TranslateBackend::DeepL)?
+        .ok_or_else(|| "DeepL API key is not set".to_string())?;
+
+    let mut body = json!({
+        "text": [text],
+        "target_lang": deepl_target_lang(target_lang),
+    });
+    if let Some(source) = source_lang {
+        body["source_lang"] = json!(source.to_ascii_uppercase());
+    }
+
+    let response = client()?
+        .post(deepl_endpoint(&api_key))
+        .header("Authorization", format!("DeepL-Auth-Key {}", api_key))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(error_from_response("DeepL", response).await);
+    }
+
+    let json: Value = response.json().await.map_err(|e| e.to_string())?;
+    parse_deepl_response(&json).ok_or_else(|| "Unexpected response from DeepL".to_string())
+}
+
+fn parse_deepl_response(json: &Value) -> Option<(String, Option<String>)> {
+    let first = json.get("translations")?.get(0)?;
+    let text = first.get("text")?.as_str()?.to_string();
+    let detected = first
+        .get("detected_source_language")
+        .and_then(|l| l.as_str())
+        .map(normalize_lang);
+    Some((text, detected))
+}
+
+async fn translate_libretranslate(
+    settings: &TranslateSettings,
+    text: &str,
+    source_lang: Option<&str>,
+    target_lang: &str,
+) -> Result<(String, Option<String>), String> {
+    let mut body = json!({
+        "q": text,
+        "source": source_lang.unwrap_or("auto"),
+        "target": normalize_lang(target_lang),
+        "format": "text",
+    });
+    // Self-hosted instances usually run without keys
+    if let Some(key) = get_api_key(TranslateBackend::LibreTranslate)? {
+        body["api_key"] = json!(key);
+    }
+
+    let url = format!(
+        "{}/translate",
+        settings.libretranslate_url.trim_end_matches('/')
+    );
+    let response = client()?
+        .post(url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(error_from_response("LibreTranslate", response).await);
+    }
+
+    let json: Value = response.json().await.map_err(|e| e.to_string())?;
+    let translated = json
+        .get("translatedText")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| "Unexpected response from LibreTranslate".to_string())?;
+    let detected = json
+        .get("detectedLanguage")
+        .and_then(|d| d.get("language"))
+        .and_then(|l| l.as_str())
+        .map(normalize_lang)
+        .or_else(|| source_lang.map(normalize_lang));
+    Ok((translated.to_string(), detected))
+}
+
+fn ollama_prompt(text: &str, source_lang: Option<&str>, target_lang: &str) -> String {
+    let source = match source_lang {
+        Some(lang) => format!("from the language with ISO 639-1 code \"{}\" ", lang),
+        None => String::new(),
+    };
+    format!(
+        "Translate the text below {}into the language with ISO 639-1 code \"{}\". \
+         Reply with a JSON object with two keys: \"sourceLanguage\" (the ISO 639-1 code \
+         of the original text) and \"translation\" (the translated text only, preserving \
+         line breaks and formatting).\n\n{}",
+        source, target_lang, text
+    )
+}
+
+async fn translate_ollama(
+    settings: &TranslateSettings,
+    text: &str,
+    source_lang: Option<&str>,
+    target_lang: &str,
+) -> Result<(String, Option<String>), String> {
+    let body = json!({
+        "model": settings.ollama_model,
+        "stream": false,
+        "format": "json",
+        "options": { "temperature": 0 },
+        "messages": [{
+            "role": "user",
+            "content": ollama_prompt(text, source_lang, target_lang),
+        }],
+    });
+
+    let url = format!("{}/api/chat", settings.ollama_url.trim_end_matches('/'));
+    let response = client()?
+        .post(url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach Ollama: {}", e))?;
+    if !response.status().is_success() {
+        return Err(error_from_response("Ollama", response).await);
+    }
+
+    let json: Value = response.json().await.map_err(|e| e.to_string())?;
+    let content = json
+        .get("message")
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_str())
+        .ok_or_else(|| "Unexpected response from Ollama".to_string())?;
+    parse_ollama_content(content, source_lang)
+        .ok_or_else(|| "Ollama did not return a translation".to_string())
+}
+
+fn parse_ollama_content(
+    content: &str,
+    source_lang: Option<&str>,
+) -> Option<(String, Option<String>)> {
+    let parsed: Value = serde_json::from_str(content.trim()).ok()?;
+    let translation = parsed.get("translation")?.as_str()?.to_string();
+    let detected = parsed
+        .get("sourceLanguage")
+        .and_then(|l| l.as_str())
+        .map(normalize_lang)
+        .or_else(|| source_lang.map(normalize_lang));
+    Some((translation, detected))
+}
+
+#[tauri::command]
+pub fn get_translate_settings(app: AppHandle) -> Result<TranslateSettings, String> {
+    read_settings(&get_settings_path(&app)?)
+}
+
+#[tauri::command]
+pub fn set_translate_settings(app: AppHandle, settings: TranslateSettings) -> Result<(), String> {
+    let path = get_settings_path(&app)?;
+    let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_translate_api_key(backend: TranslateBackend, key: String) -> Result<(), String> {
+    require_secret_key(backend)
+        .and_then(|secret| secrets::set(secrets::TRANSLATE, secret, &key))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn is_translate_api_key_set(backend: TranslateBackend) -> Result<bool, String> {
+    match backend.secret_key() {
+        Some(secret) => secrets::exists(secrets::TRANSLATE, secret).map_err(|e| e.to_string()),
+        None => Ok(false),
+    }
+}
+
+#[tauri::command]
+pub fn clear_translate_api_key(backend: TranslateBackend) -> Result<(), String> {
+    require_secret_key(backend)
+        .and_then(|secret| secrets::delete(secrets::TRANSLATE, secret))
+        .map_err(|e| e.to_string())
+}
+
+/// Translates `text`, or the current selection when it is omitted. The source
+/// language is auto-detected by the backend unless `source_lang` is given.
+#[tauri::command]
+pub async fn translate_text(
+    app: AppHandle,
+    text: Option<String>,
+    target_lang: String,
+    source_lang: Option<String>,
+    backend: Option<TranslateBackend>,
+) -> Result<Translation, String> {
+    let text = match text {
+        Some(text) => text,
+        None => tauri::async_runtime::spawn_blocking(selection::get_text)
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+    if text.trim().is_empty() {
+        return Err("Nothing to translate".to_string());
+    }
+    let target_lang = normalize_lang(&target_lang);
+    if target_lang.is_empty() {
+        return Err("No target language given".to_string());
+    }
+    let source_lang = source_lang
+        .map(|s| normalize_lang(&s))
+        .filter(|s| !s.is_empty() && s != "auto");
+
+    let settings = get_translate_settings(app.clone())?;
+    let backend = backend.unwrap_or(settings.backend);
+    let source = source_lang.as_deref();
+    let (translated_text, detected) = match backend {
+        TranslateBackend::DeepL => translate_deepl(&text, source, &target_lang).await?,
+        TranslateBackend::LibreTranslate => {
+            translate_libretranslate(&settings, &text, source, &target_lang).await?
+        }
+        TranslateBackend::Ollama => {
+            translate_ollama(&settings, &text, source, &target_lang).await?
+        }
+    };
+
+    let translation = Translation {
+        source_text: text,
+        translated_text,
+        source_lang: detected,
+        target_lang,
+        backend,
+    };
+
+    if settings.save_history {
+        if let Err(e) = app
+            .state::<TranslationHistoryManager>()
+            .record(&translation)
+        {
+            tracing::warn!(error = %e, "Failed to save translation history");
+        }
+    }
+
+    Ok(translation)
+}
+
+#[tauri::command]
+pub fn get_translation_history(
+    app: AppHandle,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<TranslationHistoryEntry>, String> {
+    app.state::<TranslationHistoryManager>()
+        .list(limit, offset)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_translation_history_entry(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<TranslationHistoryManager>()
+        .delete(id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_translation_history(app: AppHandle) -> Result<(), String> {
+    app.state::<TranslationHistoryManager>()
+        .clear()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deepl_endpoint_and_target() {
+        assert_eq!(
+            deepl_endpoint("abc:fx"),
+            "https://api-free.deepl.com/v2/translate"
+        );
+        assert_eq!(deepl_endpoint("abc"), "https://api.deepl.com/v2/translate");
+        assert_eq!(deepl_target_lang("en"), "EN-US");
+        assert_eq!(deepl_target_lang("de"), "DE");
+        assert_eq!(deepl_target_lang("en-gb"), "EN-GB");
+    }
+
+    #[test]
+    fn test_parse_deepl_response() {
+        let json = json!({
+            "translations": [{ "detected_source_language": "DE", "text": "Hello" }]
+        });
+        assert_eq!(
+            parse_deepl_response(&json),
+            Some(("Hello".to_string(), Some("de".to_string())))
+        );
+        assert_eq!(parse_deepl_response(&json!({})), None);
+    }
+
+    #[test]
+    fn test_parse_ollama_content() {
+        let content = r#"{"sourceLanguage": "FR", "translation": "Good morning"}"#;
+        assert_eq!(
+            parse_ollama_content(content, None),
+            Some(("Good morning".to_string(), Some("fr".to_string())))
+        );
+        assert_eq!(
+            parse_ollama_content(r#"{"translation": "Hi"}"#, Some("es")),
+            Some(("Hi".to_string(), Some("es".to_string())))
+        );
+        assert_eq!(parse_ollama_content("not json", None), None);
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let manager = TranslationHistoryManager::new_for_test().unwrap();
+        let translation = Translation {
+            source_text: "Hallo".to_string(),
+            translated_text: "Hello".to_string(),
+            source_lang: Some("de".to_string()),
+            target_lang: "en".to_string(),
+            backend: TranslateBackend::LibreTranslate,
+        };
+        let id = manager.record(&translation).unwrap();
+
+        let history = manager.list(10, 0).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, id);
+        assert_eq!(history[0].translation.translated_text, "Hello");
+        assert_eq!(
+            history[0].translation.backend,
+            TranslateBackend::LibreTranslate
+        );
+
+        manager.delete(id).unwrap();
+        assert!(manager.list(10, 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,346 @@
+//! A short undo window for destructive commands. Instead of acting right
+//! away, trashing files first moves them into a staging directory and
+//! deleting a clipboard item first tombstones its row; `UNDO_WINDOW` later
+//! the action is finished for real. Until then `undo_last_action` puts the
+//! most recent one back and confirms it on the HUD.
+//!
+//! The stack only lives in memory. Each staging directory gets its manifest
+//! before anything is moved into it, so files left behind by a crash are
+//! still sent on to the trash on the next start, and a directory without
+//! one goes to the trash whole rather than being deleted.
+
+use crate::clipboard_history::manager::MANAGER;
+use crate::hud::{self, HudMessage, HudStyle};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const UNDO_AVAILABLE_EVENT: &str = "undo-available";
+
+const UNDO_WINDOW: Duration = Duration::from_secs(30);
+/// Older entries are finished early once the stack is this deep
+const MAX_ENTRIES: usize = 20;
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct StagedFile {
+    original: PathBuf,
+    staged: PathBuf,
+}
+
+#[derive(Debug)]
+enum UndoAction {
+    Trash(Vec<StagedFile>),
+    DeleteHistoryItem(i64),
+}
+
+struct UndoEntry {
+    id: u64,
+    title: String,
+    action: UndoAction,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoAvailable {
+    pub title: String,
+    pub expires_in_secs: u64,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static STACK: Lazy<Mutex<Vec<UndoEntry>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+fn staging_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_local_data_dir()
+        .map(|dir| dir.join("undo"))
+        .map_err(|e| e.to_string())
+}
+
+/// Trashed from where it was when possible, so restoring it from the trash
+/// puts it back there rather than in the staging directory. Files the
+/// manifest lists but that never got staged are left alone.
+fn trash_staged(file: &StagedFile) {
+    if fs::symlink_metadata(&file.staged).is_err() {
+        return;
+    }
+    let path = if !file.original.exists() && fs::rename(&file.staged, &file.original).is_ok() {
+        &file.original
+    } else {
+        &file.staged
+    };
+    if let Err(e) = trash::delete(path) {
+        tracing::error!(error = %e, path = %path.display(), "Failed to trash file");
+    }
+}
+
+fn remove_entry_dir(files: &[StagedFile]) {
+    if let Some(entry_dir) = files.first().and_then(|file| file.staged.parent()) {
+        let _ = fs::remove_dir_all(entry_dir);
+    }
+}
+
+/// Moves what `action` held back on to its final place
+fn finish(action: UndoAction) {
+    match action {
+        UndoAction::Trash(files) => {
+            files.iter().for_each(trash_staged);
+            remove_entry_dir(&files);
+        }
+        UndoAction::DeleteHistoryItem(id) => {
+            if let Some(manager) = MANAGER.lock().unwrap().as_ref() {
+                if let Err(e) = manager.purge_tombstone(id) {
+                    tracing::warn!(error = %e, id, "Failed to purge clipboard item");
+                }
+            }
+        }
+    }
+}
+
+fn revert(action: UndoAction) -> Result<(), String> {
+    match action {
+        UndoAction::Trash(files) => {
+            let mut failed = Vec::new();
+            for file in &files {
+                // Something new took its place; this one still goes
+                if file.original.exists() {
+                    failed.push(file.original.display().to_string());
+                    trash_staged(file);
+                    continue;
+                }
+                if let Some(parent) = file.original.parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+                if fs::rename(&file.staged, &file.original).is_err() {
+                    failed.push(file.original.display().to_string());
+                }
+            }
+            remove_entry_dir(&files);
+            if failed.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("Couldn't restore {}", failed.join(", ")))
+            }
+        }
+        UndoAction::DeleteHistoryItem(id) => {
+            let guard = MANAGER.lock().unwrap();
+            let manager = guard
+                .as_ref()
+                .ok_or("Clipboard history manager not initialized")?;
+            match manager.restore_item(id).map_err(|e| e.to_string())? {
+                true => Ok(()),
+                false => Err("The item was copied again meanwhile".to_string()),
+            }
+        }
+    }
+}
+
+fn expire(id: u64) {
+    let entry = {
+        let mut stack = STACK.lock().unwrap();
+        let index = stack.iter().position(|entry| entry.id == id);
+        index.map(|index| stack.remove(index))
+    };
+    if let Some(entry) = entry {
+        finish(entry.action);
+    }
+}
+
+fn push(app: &AppHandle, id: u64, title: String, action: UndoAction) {
+    let overflow = {
+        let mut stack = STACK.lock().unwrap();
+        stack.push(UndoEntry {
+            id,
+            title: title.clone(),
+            action,
+        });
+        let excess = stack.len().saturating_sub(MAX_ENTRIES);
+        stack.drain(..excess).collect::<Vec<_>>()
+    };
+    for entry in overflow {
+        finish(entry.action);
+    }
+
+    let _ = app.emit(
+        UNDO_AVAILABLE_EVENT,
+        UndoAvailable {
+            title,
+            expires_in_secs: UNDO_WINDOW.as_secs(),
+        },
+    );
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(UNDO_WINDOW).await;
+        expire(id);
+    });
+}
+
+fn trash_title(files: &[StagedFile]) -> String {
+    match files {
+        [file] => format!(
+            "Moved “{}” to Trash",
+            file.original
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default()
+        ),
+        files => format!("Moved {} items to Trash", files.len()),
+    }
+}
+
+fn write_manifest(entry_dir: &Path, files: &[StagedFile]) -> Result<(), String> {
+    let manifest = serde_json::to_string(files).map_err(|e| e.to_string())?;
+    fs::write(entry_dir.join(MANIFEST), manifest).map_err(|e| e.to_string())
+}
+
+/// Moves staged files back where they came from, for when staging can't be
+/// recorded
+fn unstage(files: &[StagedFile]) {
+    for file in files {
+        if let Err(e) = fs::rename(&file.staged, &file.original) {
+            tracing::error!(error = %e, path = %file.original.display(), "Failed to unstage file");
+        }
+    }
+}
+
+/// Stages `paths` for the trash. Files that can't be moved into the staging
+/// directory, e.g. on another filesystem, are trashed right away.
+pub fn trash_paths(app: &AppHandle, paths: Vec<String>) -> Result<(), String> {
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    let entry_dir = staging_dir(app)?.join(id.to_string());
+    fs::create_dir_all(&entry_dir).map_err(|e| e.to_string())?;
+
+    let planned: Vec<StagedFile> = paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| StagedFile {
+            // Not canonicalized: a symlink is trashed itself, not its target
+            original: std::path::absolute(path).unwrap_or_else(|_| PathBuf::from(path)),
+            staged: entry_dir.join(index.to_string()),
+        })
+        .collect();
+    if let Err(e) = write_manifest(&entry_dir, &planned) {
+        let _ = fs::remove_dir_all(&entry_dir);
+        return Err(e);
+    }
+
+    let mut staged = Vec::new();
+    let mut immediate = Vec::new();
+    for (file, path) in planned.into_iter().zip(paths) {
+        match fs::rename(&file.original, &file.staged) {
+            Ok(()) => staged.push(file),
+            Err(_) => immediate.push(path),
+        }
+    }
+    if staged.is_empty() {
+        let _ = fs::remove_dir_all(&entry_dir);
+    } else if !immediate.is_empty() {
+        if let Err(e) = write_manifest(&entry_dir, &staged) {
+            unstage(&staged);
+            let _ = fs::remove_dir_all(&entry_dir);
+            return Err(e);
+        }
+    }
+    if !staged.is_empty() {
+        push(app, id, trash_title(&staged), UndoAction::Trash(staged));
+    }
+    if !immediate.is_empty() {
+        trash::delete_all(immediate).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Tombstones the clipboard item `id`
+pub fn delete_history_item(app: &AppHandle, id: i64) -> Result<(), String> {
+    let guard = MANAGER.lock().unwrap();
+    let manager = guard
+        .as_ref()
+        .ok_or("Clipboard history manager not initialized")?;
+    if manager.tombstone_item(id).map_err(|e| e.to_string())? > 0 {
+        drop(guard);
+        push(
+            app,
+            NEXT_ID.fetch_add(1, Ordering::SeqCst),
+            "Deleted clipboard item".to_string(),
+            UndoAction::DeleteHistoryItem(id),
+        );
+    }
+    Ok(())
+}
+
+fn read_manifest(entry_dir: &Path) -> Option<Vec<StagedFile>> {
+    let content = fs::read_to_string(entry_dir.join(MANIFEST)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Finishes trashing whatever a previous run left staged
+pub fn init(app: &AppHandle) {
+    let Ok(entries) = staging_dir(app).and_then(|dir| fs::read_dir(dir).map_err(|e| e.to_string()))
+    else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match read_manifest(&path) {
+            Some(files) => {
+                finish(UndoAction::Trash(files));
+                let _ = fs::remove_dir_all(&path);
+            }
+            // Whatever it holds, we can't tell where it came from
+            None => {
+                if let Err(e) = trash::delete(&path) {
+                    tracing::error!(error = %e, path = %path.display(), "Failed to trash staging directory");
+                }
+            }
+        }
+    }
+}
+
+/// Reverts the most recent action still in its undo window
+#[tauri::command]
+pub async fn undo_last_action(app: AppHandle) -> Result<String, String> {
+    let entry = STACK.lock().unwrap().pop().ok_or("Nothing to undo")?;
+    let title = entry.title.clone();
+    revert(entry.action)?;
+    hud::show(
+        &app,
+        HudMessage::new(format!("Undone: {}", title)).style(HudStyle::Success),
+    )?;
+    Ok(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revert_moves_staged_files_back() {
+        let dir = std::env::temp_dir().join(format!("flare-undo-{}", rand::random::<u32>()));
+        let entry_dir = dir.join("staging").join("1");
+        fs::create_dir_all(&entry_dir).unwrap();
+        let original = dir.join("notes").join("todo.txt");
+        let staged = entry_dir.join("0");
+        fs::write(&staged, "milk").unwrap();
+
+        let file = StagedFile {
+            original: original.clone(),
+            staged,
+        };
+        assert_eq!(
+            trash_title(std::slice::from_ref(&file)),
+            "Moved “todo.txt” to Trash"
+        );
+        revert(UndoAction::Trash(vec![file.clone()])).unwrap();
+        assert_eq!(fs::read_to_string(&original).unwrap(), "milk");
+        assert!(!entry_dir.exists());
+
+        // Listed in the manifest but never staged: left where it is
+        trash_staged(&file);
+        assert!(original.exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,552 @@
+//! Rich previews for links: Open Graph and Twitter Card metadata, falling
+//! back to the page title, meta description and favicon.
+//!
+//! Links come from the clipboard or from quicklinks, i.e. from anywhere, so
+//! fetching refuses hosts that resolve to loopback, private or link-local
+//! addresses. Each redirect hop is checked the same way and the connection is
+//! pinned to the checked address so DNS can't be rebound in between.
+
+use crate::quicklinks::QuicklinkManager;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use url::Url;
+
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+const CACHE_CAPACITY: usize = 300;
+const MAX_REDIRECTS: usize = 5;
+/// Metadata lives in `<head>`, so there's no point downloading whole pages
+const MAX_BODY_BYTES: usize = 512 * 1024;
+const MAX_DESCRIPTION_CHARS: usize = 300;
+
+static META_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<meta\s[^>]*>").unwrap());
+static LINK_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<link\s[^>]*>").unwrap());
+static TITLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title\s*>").unwrap());
+static ATTRIBUTE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)([a-zA-Z_:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+});
+static ENTITY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|amp|lt|gt|quot|apos|nbsp);").unwrap());
+
+#[derive(Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkPreview {
+    /// The URL that was asked for
+    pub url: String,
+    /// Where redirects ended up, or the page's canonical URL
+    pub final_url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub favicon: Option<String>,
+    pub site_name: Option<String>,
+}
+
+#[derive(Default)]
+pub struct UnfurlService {
+    cache: Mutex<HashMap<String, (Instant, LinkPreview)>>,
+}
+
+impl UnfurlService {
+    fn cached(&self, key: &str, now: Instant) -> Option<LinkPreview> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(key)
+            .filter(|(stored_at, _)| now.duration_since(*stored_at) < CACHE_TTL)
+            .map(|(_, preview)| preview.clone())
+    }
+
+    fn store(&self, key: String, preview: LinkPreview, now: Instant) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, (stored_at, _)| now.duration_since(*stored_at) < CACHE_TTL);
+        if cache.len() >= CACHE_CAPACITY {
+            if let Some(oldest) = cache
+                .iter()
+                .min_by_key(|(_, (stored_at, _))| *stored_at)
+                .map(|(key, _)| key.clone())
+            {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(key, (now, preview));
+    }
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                || a >= 240
+                // Carrier-grade NAT and benchmarking ranges
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (b == 18 || b == 19)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local fc00::/7 and link-local fe80::/10
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+fn check_url(url: &Url) -> Result<(), String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Can't preview {} links", url.scheme()));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err("Links with credentials aren't previewed".to_string());
+    }
+    Ok(())
+}
+
+/// Resolves the host and returns its first address, refusing non-public ones
+async fn resolve_public(url: &Url) -> Result<SocketAddr, String> {
+    let host = url.host_str().ok_or("Link has no host")?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+        .collect();
+    if addresses.is_empty() {
+        return Err(format!("{} has no addresses", host));
+    }
+    // All addresses must be public, otherwise a record could point inside
+    // the network next to a public one
+    if let Some(blocked) = addresses.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!(
+            "{} resolves to a private address ({})",
+            host,
+            blocked.ip()
+        ));
+    }
+    Ok(addresses[0])
+}
+
+pub(crate) fn decode_entities(text: &str) -> String {
+    ENTITY_REGEX
+        .replace_all(text, |caps: &regex::Captures| {
+            let entity = &caps[1];
+            let decoded = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => {
+                    let number = &entity[1..];
+                    match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => number.parse().ok(),
+                    }
+                    .and_then(char::from_u32)
+                }
+            };
+            decoded
+                .map(String::from)
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+fn clean_text(text: &str) -> Option<String> {
+    let text = decode_entities(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn attributes(tag: &str) -> HashMap<String, String> {
+    ATTRIBUTE_REGEX
+        .captures_iter(tag)
+        .map(|caps| {
+            let value = caps
+                .get(2)
+                .or_else(|| caps.get(3))
+                .or_else(|| caps.get(4))
+                .map_or("", |m| m.as_str());
+            (caps[1].to_lowercase(), decode_entities(value))
+        })
+        .collect()
+}
+
+pub(crate) fn truncate(text: String, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let mut truncated: String = text.chars().take(max_chars).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Extracts the preview from a page's HTML; relative links are resolved
+/// against `page_url`
+fn parse_metadata(html: &str, page_url: &Url) -> LinkPreview {
+    // Only the head matters, and bodies may contain unrelated example markup
+    let head = html
+        .find("</head>")
+        .or_else(|| html.find("</HEAD>"))
+        .map_or(html, |end| &html[..end]);
+
+    let mut meta: HashMap<String, String> = HashMap::new();
+    for tag in META_TAG_REGEX.find_iter(head) {
+        let attrs = attributes(tag.as_str());
+        let Some(key) = attrs.get("property").or_else(|| attrs.get("name")) else {
+            continue;
+        };
+        if let Some(content) = attrs.get("content").filter(|c| !c.trim().is_empty()) {
+            // The first occurrence wins, like in most unfurlers
+            meta.entry(key.to_lowercase())
+                .or_insert_with(|| content.clone());
+        }
+    }
+    let first = |keys: &[&str]| keys.iter().find_map(|key| meta.get(*key).cloned());
+    let resolve = |link: String| {
+        page_url
+            .join(link.trim())
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .map(String::from)
+    };
+
+    let mut favicon = None;
+    let mut touch_icon = None;
+    for tag in LINK_TAG_REGEX.find_iter(head) {
+        let attrs = attributes(tag.as_str());
+        let (Some(rel), Some(href)) = (attrs.get("rel"), attrs.get("href")) else {
+            continue;
+        };
+        let rel = rel.to_lowercase();
+        let rels: Vec<&str> = rel.split_whitespace().collect();
+        if favicon.is_none() && rels.contains(&"icon") {
+            favicon = Some(href.clone());
+        } else if touch_icon.is_none() && rels.contains(&"apple-touch-icon") {
+            touch_icon = Some(href.clone());
+        }
+    }
+
+    let title = first(&["og:title", "twitter:title"])
+        .or_else(|| TITLE_REGEX.captures(head).map(|caps| caps[1].to_string()))
+        .and_then(|title| clean_text(&title));
+    let description = first(&["og:description", "twitter:description", "description"])
+        .and_then(|description| clean_text(&description))
+        .map(|description| truncate(description, MAX_DESCRIPTION_CHARS));
+    let image = first(&[
+        "og:image:secure_url",
+        "og:image",
+        "og:image:url",
+        "twitter:image",
+        "twitter:image:src",
+    ])
+    .and_then(resolve);
+    let favicon = favicon
+        .or(touch_icon)
+        .unwrap_or_else(|| "/favicon.ico".to_string());
+
+    LinkPreview {
+        url: page_url.to_string(),
+        final_url: first(&["og:url"])
+            .and_then(resolve)
+            .unwrap_or_else(|| page_url.to_string()),
+        title,
+        description,
+        image,
+        favicon: resolve(favicon),
+        site_name: first(&["og:site_name", "application-name"])
+            .and_then(|name| clean_text(&name))
+            .or_else(|| page_url.host_str().map(String::from)),
+    }
+}
+
+/// Reads at most `max_bytes` of the body and drops the rest
+pub(crate) async fn read_limited(
+    mut response: reqwest::Response,
+    max_bytes: usize,
+) -> Result<String, String> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= max_bytes {
+            body.truncate(max_bytes);
+            break;
+        }
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+pub(crate) fn content_type(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// GETs a URL from anywhere, following redirects only to public addresses.
+/// Returns the URL the redirects ended at with its successful response.
+pub(crate) async fn fetch_public(
+    url: Url,
+    accept: &str,
+    user_agent: &str,
+) -> Result<(Url, reqwest::Response), String> {
+    let mut current = url;
+
+    for _ in 0..=MAX_REDIRECTS {
+        check_url(&current)?;
+        let address = resolve_public(&current).await?;
+        let mut builder = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(Duration::from_secs(8))
+            .user_agent(user_agent);
+        if let Some(domain) = current.domain() {
+            builder = builder.resolve(domain, address);
+        }
+        let client = builder.build().map_err(|e| e.to_string())?;
+
+        let response = client
+            .get(current.clone())
+            .header(reqwest::header::ACCEPT, accept)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let status = response.status();
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .ok_or("Redirect without a location")?;
+            current = current
+                .join(location)
+                .map_err(|e| format!("Invalid redirect: {}", e))?;
+            continue;
+        }
+        if !status.is_success() {
+            return Err(format!("Link returned {}", status));
+        }
+        return Ok((current, response));
+    }
+    Err("Too many redirects".to_string())
+}
+
+async fn fetch_preview(url: Url) -> Result<LinkPreview, String> {
+    let requested = url.to_string();
+    let (current, response) = fetch_public(
+        url,
+        "text/html,application/xhtml+xml",
+        "Mozilla/5.0 (compatible; Flare link preview)",
+    )
+    .await?;
+
+    let content_type = content_type(&response);
+    let mut preview = if content_type.starts_with("image/") {
+        LinkPreview {
+            url: current.to_string(),
+            final_url: current.to_string(),
+            image: Some(current.to_string()),
+            site_name: current.host_str().map(String::from),
+            ..LinkPreview::default()
+        }
+    } else if content_type.is_empty() || content_type.contains("html") {
+        parse_metadata(&read_limited(response, MAX_BODY_BYTES).await?, &current)
+    } else {
+        return Err(format!("Can't preview {} content", content_type));
+    };
+    preview.url = requested;
+    Ok(preview)
+}
+
+async fn unfurl(service: &UnfurlService, url: &str) -> Result<LinkPreview, String> {
+    let url = Url::parse(url.trim()).map_err(|e| format!("Invalid link: {}", e))?;
+    check_url(&url)?;
+    let key = url.to_string();
+    if let Some(preview) = service.cached(&key, Instant::now()) {
+        return Ok(preview);
+    }
+    let preview = fetch_preview(url).await?;
+    service.store(key, preview.clone(), Instant::now());
+    Ok(preview)
+}
+
+/// A single http(s) URL, as opposed to text that merely contains one
+fn as_link(text: &str) -> Option<Url> {
+    let text = text.trim();
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+    Url::parse(text)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Quicklinks with `{argument}` placeholders are previewed by their site
+fn quicklink_preview_url(link: &str) -> Option<Url> {
+    if !link.contains('{') {
+        return as_link(link);
+    }
+    let origin_end = link
+        .match_indices('/')
+        .nth(2)
+        .map_or(link.len(), |(index, _)| index);
+    as_link(&link[..origin_end]).filter(|url| !url.as_str().contains('{'))
+}
+
+#[tauri::command]
+pub async fn unfurl_url(
+    service: State<'_, UnfurlService>,
+    url: String,
+) -> Result<LinkPreview, String> {
+    unfurl(&service, &url).await
+}
+
+/// Preview of the link on the clipboard, or `None` when it doesn't hold one
+#[tauri::command]
+pub async fn unfurl_clipboard(
+    app: AppHandle,
+    service: State<'_, UnfurlService>,
+) -> Result<Option<LinkPreview>, String> {
+    let text = app.clipboard().read_text().unwrap_or_default();
+    match as_link(&text) {
+        Some(url) => unfurl(&service, url.as_str()).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+pub async fn unfurl_quicklink(
+    app: AppHandle,
+    service: State<'_, UnfurlService>,
+    id: i64,
+) -> Result<Option<LinkPreview>, String> {
+    let link = app
+        .state::<QuicklinkManager>()
+        .get_quicklink_link(id)
+        .map_err(|e| e.to_string())?;
+    match quicklink_preview_url(&link) {
+        Some(url) => unfurl(&service, url.as_str()).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_open_graph() {
+        let html = r#"<!doctype html><html><head>
+            <title>Fallback title</title>
+            <meta property="og:title" content="Flare &amp; friends">
+            <meta property='og:description' content='A   launcher
+                for Linux'>
+            <meta property="og:image" content="/img/card.png">
+            <meta property="og:site_name" content="Flare">
+            <link rel="apple-touch-icon" href="/touch.png">
+            <link rel="shortcut icon" href="https://cdn.example.com/favicon.svg">
+            </head><body><meta property="og:title" content="Not this"></body></html>"#;
+        let url = Url::parse("https://example.com/blog/post").unwrap();
+        let preview = parse_metadata(html, &url);
+        assert_eq!(preview.title.as_deref(), Some("Flare & friends"));
+        assert_eq!(preview.description.as_deref(), Some("A launcher for Linux"));
+        assert_eq!(
+            preview.image.as_deref(),
+            Some("https://example.com/img/card.png")
+        );
+        assert_eq!(
+            preview.favicon.as_deref(),
+            Some("https://cdn.example.com/favicon.svg")
+        );
+        assert_eq!(preview.site_name.as_deref(), Some("Flare"));
+        assert_eq!(preview.final_url, "https://example.com/blog/post");
+    }
+
+    #[test]
+    fn test_parse_fallbacks() {
+        let html = "<HTML><HEAD><TITLE> Plain &#8211; page </TITLE>\
+                    <META NAME=description CONTENT=\"Just a page\">\
+                    <meta name=\"twitter:image\" content=\"javascript:alert(1)\"></HEAD></HTML>";
+        let url = Url::parse("http://example.org/").unwrap();
+        let preview = parse_metadata(html, &url);
+        assert_eq!(preview.title.as_deref(), Some("Plain – page"));
+        assert_eq!(preview.description.as_deref(), Some("Just a page"));
+        assert_eq!(preview.image, None);
+        assert_eq!(
+            preview.favicon.as_deref(),
+            Some("http://example.org/favicon.ico")
+        );
+        assert_eq!(preview.site_name.as_deref(), Some("example.org"));
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for blocked in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(blocked.parse().unwrap()), "{}", blocked);
+        }
+        for allowed in ["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(allowed.parse().unwrap()), "{}", allowed);
+        }
+    }
+
+    #[test]
+    fn test_links() {
+        assert!(as_link("https://example.com/a?b=c").is_some());
+        assert!(as_link("see https://example.com").is_none());
+        assert!(as_link("file:///etc/passwd").is_none());
+        assert!(check_url(&Url::parse("https://user:pw@example.com").unwrap()).is_err());
+
+        assert_eq!(
+            quicklink_preview_url("https://github.com/search?q={query}")
+                .map(String::from)
+                .as_deref(),
+            Some("https://github.com/")
+        );
+        assert!(quicklink_preview_url("{url}").is_none());
+    }
+
+    #[test]
+    fn test_cache_expiry() {
+        let service = UnfurlService::default();
+        let now = Instant::now();
+        service.store("a".to_string(), LinkPreview::default(), now);
+        assert!(service.cached("a", now).is_some());
+        assert!(service.cached("a", now + CACHE_TTL).is_none());
+    }
+}
@@ -0,0 +1,773 @@
+//! Desktop wallpaper: setting and reading it on GNOME, Cinnamon, MATE, KDE
+//! Plasma, XFCE, Sway, Hyprland (hyprpaper) and other wlroots compositors
+//! (swaybg), plus optional rotation from a folder or from Unsplash.
+//!
+//! Settings and the rotation state live in `wallpaper_settings.json`;
+//! downloaded Unsplash photos are kept in `wallpapers/` in the cache dir.
+
+use crate::error::AppError;
+use crate::power_awareness;
+use crate::secrets;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const UNSPLASH_RANDOM_URL: &str = "https://api.unsplash.com/photos/random";
+const UNSPLASH_KEY: &str = "unsplash_access_key";
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "bmp"];
+/// Downloaded photos kept around, newest first
+const CACHED_PHOTOS: usize = 20;
+/// Under the app cache directory; the current wallpaper may be one of these
+pub const PHOTO_CACHE_DIR: &str = "wallpapers";
+const ROTATION_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// The swaybg we started, replaced when the wallpaper changes
+static SWAYBG: Lazy<Mutex<Option<Child>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Backend {
+    Gnome,
+    Cinnamon,
+    Mate,
+    Kde,
+    Xfce,
+    Sway,
+    Hyprland,
+    Swaybg,
+}
+
+fn backend_for(desktop: &str, hyprland: bool, sway: bool, wayland: bool) -> Option<Backend> {
+    let desktop = desktop.to_lowercase();
+    if hyprland || desktop.contains("hyprland") {
+        Some(Backend::Hyprland)
+    } else if sway || desktop.contains("sway") {
+        Some(Backend::Sway)
+    } else if desktop.contains("cinnamon") {
+        Some(Backend::Cinnamon)
+    } else if desktop.contains("mate") {
+        Some(Backend::Mate)
+    } else if desktop.contains("kde") || desktop.contains("plasma") {
+        Some(Backend::Kde)
+    } else if desktop.contains("xfce") {
+        Some(Backend::Xfce)
+    } else if ["gnome", "ubuntu", "unity", "budgie", "pop"]
+        .iter()
+        .any(|name| desktop.contains(name))
+    {
+        Some(Backend::Gnome)
+    } else if wayland {
+        Some(Backend::Swaybg)
+    } else {
+        None
+    }
+}
+
+fn detect_backend() -> Result<Backend, String> {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP")
+        .or_else(|_| std::env::var("DESKTOP_SESSION"))
+        .unwrap_or_default();
+    backend_for(
+        &desktop,
+        std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok(),
+        std::env::var("SWAYSOCK").is_ok(),
+        std::env::var("WAYLAND_DISPLAY").is_ok(),
+    )
+    .ok_or_else(|| format!("Setting the wallpaper isn't supported on '{}'", desktop))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum RotationSource {
+    Folder { path: String },
+    Unsplash { query: Option<String> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Rotation {
+    pub source: RotationSource,
+    #[serde(default = "default_interval_hours")]
+    pub interval_hours: u32,
+    pub monitor: Option<String>,
+}
+
+fn default_interval_hours() -> u32 {
+    24
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoCredit {
+    pub photographer: String,
+    pub profile_url: Option<String>,
+    pub photo_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WallpaperSettings {
+    /// Last wallpaper set through Flare; compositors without a settings
+    /// store can only be asked through this
+    pub current: Option<String>,
+    pub rotation: Option<Rotation>,
+    pub last_rotated_at: Option<DateTime<Utc>>,
+    /// Attribution for the current Unsplash photo
+    pub credit: Option<PhotoCredit>,
+}
+
+impl WallpaperSettings {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("wallpaper_settings.json"))
+    }
+
+    pub fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+
+    fn rotation_due(&self, now: DateTime<Utc>) -> bool {
+        match (&self.rotation, self.last_rotated_at) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(rotation), Some(last)) => {
+                now - last >= chrono::Duration::hours(rotation.interval_hours.max(1) as i64)
+            }
+        }
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {} (is it installed?): {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn file_uri(path: &Path) -> String {
+    url::Url::from_file_path(path)
+        .map(String::from)
+        .unwrap_or_else(|_| format!("file://{}", path.display()))
+}
+
+/// `'file:///a%20b.jpg'` as printed by gsettings -> `/a b.jpg`
+fn path_from_gsettings(value: &str) -> Option<String> {
+    let value = value.trim().trim_matches('\'');
+    if value.is_empty() {
+        return None;
+    }
+    match url::Url::parse(value) {
+        Ok(url) if url.scheme() == "file" => url
+            .to_file_path()
+            .ok()
+            .map(|path| path.to_string_lossy().to_string()),
+        _ => Some(value.to_string()),
+    }
+}
+
+/// Plasma desktop script that sets the image on every desktop, or only on
+/// screen `screen`
+fn plasma_script(uri: &str, screen: Option<i32>) -> String {
+    format!(
+        r#"const uri = {};
+const screen = {};
+desktops().forEach(function (desktop) {{
+    if (screen >= 0 && desktop.screen !== screen) return;
+    desktop.wallpaperPlugin = "org.kde.image";
+    desktop.currentConfigGroup = ["Wallpaper", "org.kde.image", "General"];
+    desktop.writeConfig("Image", uri);
+}});"#,
+        serde_json::to_string(uri).unwrap_or_default(),
+        screen.unwrap_or(-1)
+    )
+}
+
+async fn set_plasma_wallpaper(path: &Path, monitor: Option<&str>) -> Result<(), String> {
+    let screen = monitor
+        .map(|monitor| {
+            monitor
+                .parse::<i32>()
+                .map_err(|_| "On KDE the monitor is the screen number, e.g. 0".to_string())
+        })
+        .transpose()?;
+    let connection = zbus::Connection::session()
+        .await
+        .map_err(|e| e.to_string())?;
+    connection
+        .call_method(
+            Some("org.kde.plasmashell"),
+            "/PlasmaShell",
+            Some("org.kde.PlasmaShell"),
+            "evaluateScript",
+            &(plasma_script(&file_uri(path), screen),),
+        )
+        .await
+        .map_err(|e| format!("Failed to set the Plasma wallpaper: {}", e))?;
+    Ok(())
+}
+
+/// Backdrop properties like `/backdrop/screen0/monitorHDMI-1/workspace0/last-image`
+fn xfce_image_properties(listing: &str, monitor: Option<&str>) -> Vec<String> {
+    listing
+        .lines()
+        .map(str::trim)
+        .filter(|property| property.ends_with("/last-image"))
+        .filter(|property| match monitor {
+            Some(monitor) => property.contains(&format!("/monitor{}/", monitor)),
+            None => true,
+        })
+        .map(String::from)
+        .collect()
+}
+
+fn set_xfce_wallpaper(path: &Path, monitor: Option<&str>) -> Result<(), String> {
+    let listing = run("xfconf-query", &["-c", "xfce4-desktop", "-l"])?;
+    let properties = xfce_image_properties(&listing, monitor);
+    if properties.is_empty() {
+        return Err(match monitor {
+            Some(monitor) => format!("No XFCE backdrop found for monitor {}", monitor),
+            None => "No XFCE backdrop found".to_string(),
+        });
+    }
+    let path = path.to_string_lossy();
+    for property in properties {
+        run(
+            "xfconf-query",
+            &["-c", "xfce4-desktop", "-p", &property, "-s", &path],
+        )?;
+    }
+    Ok(())
+}
+
+fn set_swaybg_wallpaper(path: &Path, monitor: Option<&str>) -> Result<(), String> {
+    let mut command = Command::new("swaybg");
+    if let Some(monitor) = monitor {
+        command.args(["-o", monitor]);
+    }
+    let child = command
+        .arg("-i")
+        .arg(path)
+        .args(["-m", "fill"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start swaybg (is it installed?): {}", e))?;
+
+    // The new instance draws over the old one, which can then go
+    let mut running = SWAYBG.lock().unwrap();
+    if let Some(mut previous) = running.replace(child) {
+        let _ = previous.kill();
+        let _ = previous.wait();
+    }
+    Ok(())
+}
+
+async fn apply_wallpaper(path: &Path, monitor: Option<&str>) -> Result<(), String> {
+    let path_str = path.to_string_lossy();
+    let uri = file_uri(path);
+    match detect_backend()? {
+        Backend::Gnome => {
+            run(
+                "gsettings",
+                &["set", "org.gnome.desktop.background", "picture-uri", &uri],
+            )?;
+            // Only exists since GNOME 42
+            let _ = run(
+                "gsettings",
+                &[
+                    "set",
+                    "org.gnome.desktop.background",
+                    "picture-uri-dark",
+                    &uri,
+                ],
+            );
+        }
+        Backend::Cinnamon => {
+            run(
+                "gsettings",
+                &[
+                    "set",
+                    "org.cinnamon.desktop.background",
+                    "picture-uri",
+                    &uri,
+                ],
+            )?;
+        }
+        Backend::Mate => {
+            run(
+                "gsettings",
+                &["set", "org.mate.background", "picture-filename", &path_str],
+            )?;
+        }
+        Backend::Kde => set_plasma_wallpaper(path, monitor).await?,
+        Backend::Xfce => set_xfce_wallpaper(path, monitor)?,
+        Backend::Sway => {
+            run(
+                "swaymsg",
+                &["output", monitor.unwrap_or("*"), "bg", &path_str, "fill"],
+            )?;
+        }
+        Backend::Hyprland => {
+            run("hyprctl", &["hyprpaper", "preload", &path_str])?;
+            let target = format!("{},{}", monitor.unwrap_or_default(), path_str);
+            run("hyprctl", &["hyprpaper", "wallpaper", &target])?;
+        }
+        Backend::Swaybg => set_swaybg_wallpaper(path, monitor)?,
+    }
+    Ok(())
+}
+
+/// Reads the wallpaper from the desktop's own settings where there are any
+fn read_desktop_wallpaper() -> Option<String> {
+    match detect_backend().ok()? {
+        Backend::Gnome => run(
+            "gsettings",
+            &["get", "org.gnome.desktop.background", "picture-uri"],
+        )
+        .ok()
+        .and_then(|value| path_from_gsettings(&value)),
+        Backend::Cinnamon => run(
+            "gsettings",
+            &["get", "org.cinnamon.desktop.background", "picture-uri"],
+        )
+        .ok()
+        .and_then(|value| path_from_gsettings(&value)),
+        Backend::Mate => run(
+            "gsettings",
+            &["get", "org.mate.background", "picture-filename"],
+        )
+        .ok()
+        .and_then(|value| path_from_gsettings(&value)),
+        Backend::Kde => {
+            let config = dirs::config_dir()?.join("plasma-org.kde.plasma.desktop-appletsrc");
+            fs::read_to_string(config)
+                .ok()?
+                .lines()
+                .find_map(|line| line.strip_prefix("Image="))
+                .and_then(path_from_gsettings)
+        }
+        Backend::Xfce => {
+            let listing = run("xfconf-query", &["-c", "xfce4-desktop", "-l"]).ok()?;
+            let property = xfce_image_properties(&listing, None).into_iter().next()?;
+            run("xfconf-query", &["-c", "xfce4-desktop", "-p", &property])
+                .ok()
+                .filter(|path| !path.is_empty())
+        }
+        Backend::Hyprland => run("hyprctl", &["hyprpaper", "listactive"])
+            .ok()?
+            .lines()
+            .find_map(|line| {
+                line.split_once(" = ")
+                    .map(|(_, path)| path.trim().to_string())
+            }),
+        Backend::Sway | Backend::Swaybg => None,
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// A random image from `folder`, avoiding the current one when there's a choice
+fn pick_from_folder(folder: &Path, current: Option<&str>) -> Result<PathBuf, String> {
+    let images: Vec<PathBuf> = fs::read_dir(folder)
+        .map_err(|e| format!("Failed to read {}: {}", folder.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_image(path))
+        .collect();
+    let candidates: Vec<&PathBuf> = images
+        .iter()
+        .filter(|path| images.len() == 1 || current.map(Path::new) != Some(path.as_path()))
+        .collect();
+    candidates
+        .choose(&mut rand::rng())
+        .map(|path| path.to_path_buf())
+        .ok_or_else(|| format!("No images found in {}", folder.display()))
+}
+
+struct UnsplashPhoto {
+    id: String,
+    download_url: String,
+    track_url: Option<String>,
+    credit: PhotoCredit,
+}
+
+fn parse_unsplash_photo(body: &Value) -> Option<UnsplashPhoto> {
+    let str_at = |pointer: &str| {
+        body.pointer(pointer)
+            .and_then(Value::as_str)
+            .map(String::from)
+    };
+    Some(UnsplashPhoto {
+        id: str_at("/id")?,
+        download_url: str_at("/urls/full").or_else(|| str_at("/urls/raw"))?,
+        track_url: str_at("/links/download_location"),
+        credit: PhotoCredit {
+            photographer: str_at("/user/name").unwrap_or_else(|| "Unknown".to_string()),
+            profile_url: str_at("/user/links/html"),
+            photo_url: str_at("/links/html"),
+        },
+    })
+}
+
+fn photo_cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|_| "Failed to get app cache dir".to_string())?
+        .join(PHOTO_CACHE_DIR);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+fn prune_photo_cache(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut photos: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    photos.sort_by_key(|(modified, _)| Reverse(*modified));
+    for (_, path) in photos.into_iter().skip(CACHED_PHOTOS) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+async fn fetch_unsplash(
+    app: &AppHandle,
+    query: Option<&str>,
+) -> Result<(PathBuf, PhotoCredit), String> {
+    let key = secrets::get(secrets::WALLPAPER, UNSPLASH_KEY)
+        .map_err(|e| e.to_string())?
+        .ok_or("Add an Unsplash access key to rotate wallpapers from Unsplash")?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(60))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let auth = format!("Client-ID {}", key);
+
+    let mut request = client
+        .get(UNSPLASH_RANDOM_URL)
+        .header(reqwest::header::AUTHORIZATION, &auth)
+        .query(&[("orientation", "landscape"), ("content_filter", "high")]);
+    if let Some(query) = query.filter(|query| !query.trim().is_empty()) {
+        request = request.query(&[("query", query)]);
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Unsplash returned {}", response.status()));
+    }
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    let photo = parse_unsplash_photo(&body).ok_or("Unexpected Unsplash response")?;
+
+    let dir = photo_cache_dir(app)?;
+    let path = dir.join(format!("unsplash-{}.jpg", photo.id));
+    if !path.exists() {
+        let bytes = client
+            .get(&photo.download_url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?
+            .bytes()
+            .await
+            .map_err(|e| e.to_string())?;
+        fs::write(&path, bytes).map_err(|e| e.to_string())?;
+        prune_photo_cache(&dir);
+    }
+    // Unsplash's API terms ask for downloads to be reported
+    if let Some(track_url) = photo.track_url {
+        let _ = client
+            .get(track_url)
+            .header(reqwest::header::AUTHORIZATION, &auth)
+            .send()
+            .await;
+    }
+    Ok((path, photo.credit))
+}
+
+async fn set_and_remember(
+    app: &AppHandle,
+    path: &Path,
+    monitor: Option<&str>,
+    credit: Option<PhotoCredit>,
+) -> Result<(), String> {
+    apply_wallpaper(path, monitor).await?;
+    let mut settings = WallpaperSettings::load(app).map_err(|e| e.to_string())?;
+    settings.current = Some(path.to_string_lossy().to_string());
+    settings.credit = credit;
+    settings.save(app).map_err(|e| e.to_string())?;
+    let _ = app.emit("wallpaper-changed", &settings.current);
+    Ok(())
+}
+
+async fn rotate(app: &AppHandle) -> Result<String, String> {
+    let settings = WallpaperSettings::load(app).map_err(|e| e.to_string())?;
+    let rotation = settings
+        .rotation
+        .clone()
+        .ok_or("Wallpaper rotation is off")?;
+    let (path, credit) = match &rotation.source {
+        RotationSource::Folder { path } => (
+            pick_from_folder(Path::new(path), settings.current.as_deref())?,
+            None,
+        ),
+        RotationSource::Unsplash { query } => {
+            let (path, credit) = fetch_unsplash(app, query.as_deref()).await?;
+            (path, Some(credit))
+        }
+    };
+    set_and_remember(app, &path, rotation.monitor.as_deref(), credit).await?;
+
+    let mut settings = WallpaperSettings::load(app).map_err(|e| e.to_string())?;
+    settings.last_rotated_at = Some(Utc::now());
+    settings.save(app).map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Checks periodically whether the rotation is due
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let due = WallpaperSettings::load(&app)
+                .map(|settings| settings.rotation_due(Utc::now()))
+                .unwrap_or(false);
+            if due {
+                if let Err(e) = rotate(&app).await {
+                    tracing::warn!(error = %e, "Wallpaper rotation failed");
+                }
+            }
+            power_awareness::sleep(ROTATION_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// `monitor` is an output name (e.g. `DP-1`), or the screen number on KDE.
+/// GNOME, Cinnamon and MATE always set every monitor.
+#[tauri::command]
+pub async fn set_wallpaper(
+    app: AppHandle,
+    path: String,
+    monitor: Option<String>,
+) -> Result<(), String> {
+    let path = fs::canonicalize(&path).map_err(|e| format!("Can't use {}: {}", path, e))?;
+    if !is_image(&path) {
+        return Err(format!("{} is not an image", path.display()));
+    }
+    set_and_remember(&app, &path, monitor.as_deref(), None).await
+}
+
+#[tauri::command]
+pub fn get_current_wallpaper(app: AppHandle) -> Result<Option<String>, String> {
+    if let Some(path) = read_desktop_wallpaper() {
+        return Ok(Some(path));
+    }
+    Ok(WallpaperSettings::load(&app)
+        .map_err(|e| e.to_string())?
+        .current)
+}
+
+#[tauri::command]
+pub fn wallpaper_get_settings(app: AppHandle) -> Result<WallpaperSettings, String> {
+    WallpaperSettings::load(&app).map_err(|e| e.to_string())
+}
+
+/// Turns rotation on (or off with `None`); a new rotation starts right away
+#[tauri::command]
+pub async fn wallpaper_set_rotation(
+    app: AppHandle,
+    rotation: Option<Rotation>,
+) -> Result<(), String> {
+    if let Some(RotationSource::Folder { path }) = rotation.as_ref().map(|r| &r.source) {
+        pick_from_folder(Path::new(path), None)?;
+    }
+    let mut settings = WallpaperSettings::load(&app).map_err(|e| e.to_string())?;
+    let changed = settings.rotation != rotation;
+    settings.rotation = rotation;
+    if changed {
+        settings.last_rotated_at = None;
+    }
+    settings.save(&app).map_err(|e| e.to_string())?;
+
+    if changed && settings.rotation.is_some() {
+        rotate(&app).await?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn wallpaper_rotate_now(app: AppHandle) -> Result<String, String> {
+    rotate(&app).await
+}
+
+#[tauri::command]
+pub fn wallpaper_set_unsplash_key(key: Option<String>) -> Result<(), String> {
+    match key
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+    {
+        Some(key) => secrets::set(secrets::WALLPAPER, UNSPLASH_KEY, &key),
+        None => secrets::delete(secrets::WALLPAPER, UNSPLASH_KEY),
+    }
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_backend_for() {
+        assert_eq!(
+            backend_for("ubuntu:GNOME", false, false, true),
+            Some(Backend::Gnome)
+        );
+        assert_eq!(
+            backend_for("X-Cinnamon", false, false, false),
+            Some(Backend::Cinnamon)
+        );
+        assert_eq!(backend_for("KDE", false, false, true), Some(Backend::Kde));
+        assert_eq!(
+            backend_for("XFCE", false, false, false),
+            Some(Backend::Xfce)
+        );
+        assert_eq!(backend_for("", true, false, true), Some(Backend::Hyprland));
+        assert_eq!(backend_for("sway", false, true, true), Some(Backend::Sway));
+        assert_eq!(
+            backend_for("river", false, false, true),
+            Some(Backend::Swaybg)
+        );
+        assert_eq!(backend_for("i3", false, false, false), None);
+    }
+
+    #[test]
+    fn test_path_from_gsettings() {
+        assert_eq!(
+            path_from_gsettings("'file:///home/me/My%20Pictures/a.jpg'").as_deref(),
+            Some("/home/me/My Pictures/a.jpg")
+        );
+        assert_eq!(
+            path_from_gsettings("/usr/share/backgrounds/b.png").as_deref(),
+            Some("/usr/share/backgrounds/b.png")
+        );
+        assert_eq!(path_from_gsettings("''"), None);
+    }
+
+    #[test]
+    fn test_xfce_image_properties() {
+        let listing = "/backdrop/screen0/monitorHDMI-1/workspace0/color-style\n\
+                       /backdrop/screen0/monitorHDMI-1/workspace0/last-image\n\
+                       /backdrop/screen0/monitoreDP-1/workspace0/last-image\n";
+        assert_eq!(xfce_image_properties(listing, None).len(), 2);
+        assert_eq!(
+            xfce_image_properties(listing, Some("eDP-1")),
+            vec!["/backdrop/screen0/monitoreDP-1/workspace0/last-image"]
+        );
+    }
+
+    #[test]
+    fn test_plasma_script_escapes_uri() {
+        let script = plasma_script("file:///tmp/it\"s.jpg", Some(1));
+        assert!(script.contains(r#"const uri = "file:///tmp/it\"s.jpg";"#));
+        assert!(script.contains("const screen = 1;"));
+    }
+
+    #[test]
+    fn test_parse_unsplash_photo() {
+        let body = json!({
+            "id": "abc123",
+            "urls": { "full": "https://images.unsplash.com/photo-1" },
+            "links": {
+                "html": "https://unsplash.com/photos/abc123",
+                "download_location": "https://api.unsplash.com/photos/abc123/download"
+            },
+            "user": { "name": "Jane Doe", "links": { "html": "https://unsplash.com/@jane" } }
+        });
+        let photo = parse_unsplash_photo(&body).unwrap();
+        assert_eq!(photo.id, "abc123");
+        assert_eq!(photo.download_url, "https://images.unsplash.com/photo-1");
+        assert_eq!(photo.credit.photographer, "Jane Doe");
+        assert!(photo.track_url.is_some());
+        assert!(parse_unsplash_photo(&json!({ "id": "x" })).is_none());
+    }
+
+    #[test]
+    fn test_rotation_due() {
+        let now = Utc::now();
+        let mut settings = WallpaperSettings::default();
+        assert!(!settings.rotation_due(now));
+
+        settings.rotation = Some(Rotation {
+            source: RotationSource::Unsplash { query: None },
+            interval_hours: 24,
+            monitor: None,
+        });
+        assert!(settings.rotation_due(now));
+        settings.last_rotated_at = Some(now - chrono::Duration::hours(2));
+        assert!(!settings.rotation_due(now));
+        settings.last_rotated_at = Some(now - chrono::Duration::hours(25));
+        assert!(settings.rotation_due(now));
+    }
+
+    #[test]
+    fn test_pick_from_folder() {
+        let dir = std::env::temp_dir().join(format!("flare_wallpapers_{}", rand::random::<u32>()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.jpg"), b"").unwrap();
+        fs::write(dir.join("b.PNG"), b"").unwrap();
+        fs::write(dir.join("notes.txt"), b"").unwrap();
+
+        let current = dir.join("a.jpg").to_string_lossy().to_string();
+        for _ in 0..10 {
+            assert_eq!(
+                pick_from_folder(&dir, Some(&current)).unwrap(),
+                dir.join("b.PNG")
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(pick_from_folder(&dir, None).is_err());
+    }
+}
@@ -0,0 +1,621 @@
+//! Weather from Open-Meteo, which needs no API key: place search through its
+//! geocoding API, and current conditions plus hourly and daily forecasts.
+//!
+//! The chosen location and units are saved in `weather_settings.json`. With
+//! no saved location the approximate location of the current IP address is
+//! used, so the weather command works before any setup. Forecasts are cached
+//! in memory for a few minutes.
+
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+const IP_LOCATION_URL: &str = "https://ipapi.co/json/";
+
+const CACHE_TTL: Duration = Duration::from_secs(15 * 60);
+const FORECAST_DAYS: usize = 7;
+const DEFAULT_HOURS: usize = 24;
+const MAX_LOCATION_RESULTS: &str = "8";
+
+const CURRENT_FIELDS: &str = "temperature_2m,apparent_temperature,relative_humidity_2m,weather_code,wind_speed_10m,wind_direction_10m,is_day,precipitation";
+const HOURLY_FIELDS: &str = "temperature_2m,weather_code,precipitation_probability,is_day";
+const DAILY_FIELDS: &str = "weather_code,temperature_2m_max,temperature_2m_min,precipitation_probability_max,sunrise,sunset";
+
+static FORECAST_CACHE: Lazy<Mutex<HashMap<String, (Instant, Forecast)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static IP_LOCATION: Lazy<Mutex<Option<Location>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Units {
+    /// °C, km/h, mm
+    #[default]
+    Metric,
+    /// °F, mph, inch
+    Imperial,
+}
+
+impl Units {
+    fn query(&self) -> [(&'static str, &'static str); 3] {
+        match self {
+            Units::Metric => [
+                ("temperature_unit", "celsius"),
+                ("wind_speed_unit", "kmh"),
+                ("precipitation_unit", "mm"),
+            ],
+            Units::Imperial => [
+                ("temperature_unit", "fahrenheit"),
+                ("wind_speed_unit", "mph"),
+                ("precipitation_unit", "inch"),
+            ],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    pub name: String,
+    /// State or region, when known
+    pub region: Option<String>,
+    pub country: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub timezone: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherSettings {
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub units: Units,
+}
+
+impl WeatherSettings {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("weather_settings.json"))
+    }
+
+    pub fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrentWeather {
+    /// Local time at the location, e.g. `2025-01-15T14:15`
+    pub time: String,
+    pub temperature: f64,
+    pub apparent_temperature: f64,
+    pub humidity: f64,
+    pub precipitation: f64,
+    pub wind_speed: f64,
+    pub wind_direction: f64,
+    pub weather_code: u8,
+    pub description: String,
+    /// Freedesktop icon name, e.g. `weather-few-clouds-night`
+    pub icon: String,
+    pub is_day: bool,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HourlyForecast {
+    pub time: String,
+    pub temperature: f64,
+    pub precipitation_probability: Option<f64>,
+    pub weather_code: u8,
+    pub description: String,
+    pub icon: String,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyForecast {
+    pub date: String,
+    pub temperature_max: f64,
+    pub temperature_min: f64,
+    pub precipitation_probability: Option<f64>,
+    pub weather_code: u8,
+    pub description: String,
+    pub icon: String,
+    pub sunrise: Option<String>,
+    pub sunset: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Forecast {
+    pub location: Location,
+    pub units: Units,
+    pub current: CurrentWeather,
+    /// From the current hour on
+    pub hourly: Vec<HourlyForecast>,
+    pub daily: Vec<DailyForecast>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// WMO weather interpretation codes, as used by Open-Meteo
+pub fn describe(code: u8) -> &'static str {
+    match code {
+        0 => "Clear sky",
+        1 => "Mainly clear",
+        2 => "Partly cloudy",
+        3 => "Overcast",
+        45 | 48 => "Fog",
+        51 | 53 | 55 => "Drizzle",
+        56 | 57 => "Freezing drizzle",
+        61 => "Light rain",
+        63 => "Rain",
+        65 => "Heavy rain",
+        66 | 67 => "Freezing rain",
+        71 => "Light snow",
+        73 => "Snow",
+        75 => "Heavy snow",
+        77 => "Snow grains",
+        80..=82 => "Rain showers",
+        85 | 86 => "Snow showers",
+        95 => "Thunderstorm",
+        96 | 99 => "Thunderstorm with hail",
+        _ => "Unknown",
+    }
+}
+
+pub fn icon_name(code: u8, is_day: bool) -> String {
+    let (base, has_night) = match code {
+        0 | 1 => ("weather-clear", true),
+        2 => ("weather-few-clouds", true),
+        3 => ("weather-overcast", false),
+        45 | 48 => ("weather-fog", false),
+        51..=57 | 61 | 80 => ("weather-showers-scattered", false),
+        63..=67 | 81 | 82 => ("weather-showers", false),
+        71..=77 | 85 | 86 => ("weather-snow", false),
+        95..=99 => ("weather-storm", false),
+        _ => ("weather-severe-alert", false),
+    };
+    if has_night && !is_day {
+        format!("{}-night", base)
+    } else {
+        base.to_string()
+    }
+}
+
+fn cache_key(location: &Location, units: Units) -> String {
+    format!(
+        "{:.3},{:.3},{:?}",
+        location.latitude, location.longitude, units
+    )
+}
+
+fn cached(key: &str, now: Instant) -> Option<Forecast> {
+    FORECAST_CACHE
+        .lock()
+        .unwrap()
+        .get(key)
+        .filter(|(stored_at, _)| now.duration_since(*stored_at) < CACHE_TTL)
+        .map(|(_, forecast)| forecast.clone())
+}
+
+fn store(key: String, forecast: Forecast, now: Instant) {
+    let mut cache = FORECAST_CACHE.lock().unwrap();
+    cache.retain(|_, (stored_at, _)| now.duration_since(*stored_at) < CACHE_TTL);
+    cache.insert(key, (now, forecast));
+}
+
+fn parse_location(result: &Value) -> Option<Location> {
+    let text = |key: &str| {
+        result
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+    };
+    Some(Location {
+        name: text("name")?,
+        region: text("admin1"),
+        country: text("country"),
+        latitude: result.get("latitude")?.as_f64()?,
+        longitude: result.get("longitude")?.as_f64()?,
+        timezone: text("timezone"),
+    })
+}
+
+fn parse_ip_location(body: &Value) -> Option<Location> {
+    let text = |key: &str| body.get(key).and_then(Value::as_str).map(String::from);
+    Some(Location {
+        name: text("city")?,
+        region: text("region"),
+        country: text("country_name"),
+        latitude: body.get("latitude")?.as_f64()?,
+        longitude: body.get("longitude")?.as_f64()?,
+        timezone: text("timezone"),
+    })
+}
+
+/// Open-Meteo returns each series as parallel arrays under `hourly`/`daily`
+struct Series<'a> {
+    block: &'a Value,
+}
+
+impl<'a> Series<'a> {
+    fn new(body: &'a Value, name: &str) -> Result<Self, String> {
+        body.get(name)
+            .map(|block| Series { block })
+            .ok_or_else(|| format!("Forecast has no {} data", name))
+    }
+
+    fn len(&self) -> usize {
+        self.block
+            .get("time")
+            .and_then(Value::as_array)
+            .map_or(0, Vec::len)
+    }
+
+    fn at(&self, key: &str, i: usize) -> Option<&'a Value> {
+        self.block.get(key)?.as_array()?.get(i)
+    }
+
+    fn f64(&self, key: &str, i: usize) -> Option<f64> {
+        self.at(key, i)?.as_f64()
+    }
+
+    fn str(&self, key: &str, i: usize) -> Option<String> {
+        self.at(key, i)?.as_str().map(String::from)
+    }
+
+    fn code(&self, i: usize) -> u8 {
+        self.at("weather_code", i)
+            .and_then(Value::as_u64)
+            .unwrap_or(u8::MAX as u64) as u8
+    }
+}
+
+fn parse_forecast(body: &Value, location: Location, units: Units) -> Result<Forecast, String> {
+    let current = body.get("current").ok_or("Forecast has no current data")?;
+    let number = |key: &str| current.get(key).and_then(Value::as_f64).unwrap_or_default();
+    let code = current
+        .get("weather_code")
+        .and_then(Value::as_u64)
+        .unwrap_or(u8::MAX as u64) as u8;
+    let is_day = current.get("is_day").and_then(Value::as_i64) != Some(0);
+    let time = current
+        .get("time")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    // Hourly data starts at midnight; skip the hours already past
+    let hourly = Series::new(body, "hourly")?;
+    let current_hour = time.get(..13).unwrap_or(&time).to_string();
+    let hourly: Vec<HourlyForecast> = (0..hourly.len())
+        .filter_map(|i| {
+            let time = hourly.str("time", i)?;
+            if time.get(..13).unwrap_or(&time) < current_hour.as_str() {
+                return None;
+            }
+            let code = hourly.code(i);
+            let is_day = hourly.f64("is_day", i) != Some(0.0);
+            Some(HourlyForecast {
+                time,
+                temperature: hourly.f64("temperature_2m", i)?,
+                precipitation_probability: hourly.f64("precipitation_probability", i),
+                weather_code: code,
+                description: describe(code).to_string(),
+                icon: icon_name(code, is_day),
+            })
+        })
+        .collect();
+
+    let daily = Series::new(body, "daily")?;
+    let daily: Vec<DailyForecast> = (0..daily.len())
+        .filter_map(|i| {
+            let code = daily.code(i);
+            Some(DailyForecast {
+                date: daily.str("time", i)?,
+                temperature_max: daily.f64("temperature_2m_max", i)?,
+                temperature_min: daily.f64("temperature_2m_min", i)?,
+                precipitation_probability: daily.f64("precipitation_probability_max", i),
+                weather_code: code,
+                description: describe(code).to_string(),
+                icon: icon_name(code, true),
+                sunrise: daily.str("sunrise", i),
+                sunset: daily.str("sunset", i),
+            })
+        })
+        .collect();
+
+    Ok(Forecast {
+        location,
+        units,
+        current: CurrentWeather {
+            time,
+            temperature: number("temperature_2m"),
+            apparent_temperature: number("apparent_temperature"),
+            humidity: number("relative_humidity_2m"),
+            precipitation: number("precipitation"),
+            wind_speed: number("wind_speed_10m"),
+            wind_direction: number("wind_direction_10m"),
+            weather_code: code,
+            description: describe(code).to_string(),
+            icon: icon_name(code, is_day),
+            is_day,
+        },
+        hourly,
+        daily,
+        fetched_at: Utc::now(),
+    })
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent("Flare launcher")
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+async fn get_json(request: reqwest::RequestBuilder) -> Result<Value, String> {
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Weather service error: {}", response.status()));
+    }
+    response.json().await.map_err(|e| e.to_string())
+}
+
+async fn fetch_forecast(location: Location, units: Units) -> Result<Forecast, String> {
+    let days = FORECAST_DAYS.to_string();
+    let latitude = location.latitude.to_string();
+    let longitude = location.longitude.to_string();
+    let request = http_client()?
+        .get(FORECAST_URL)
+        .query(&[
+            ("latitude", latitude.as_str()),
+            ("longitude", longitude.as_str()),
+            ("current", CURRENT_FIELDS),
+            ("hourly", HOURLY_FIELDS),
+            ("daily", DAILY_FIELDS),
+            ("timezone", "auto"),
+            ("forecast_days", days.as_str()),
+        ])
+        .query(&units.query());
+    let body = get_json(request).await?;
+    if let Some(reason) = body.get("reason").and_then(Value::as_str) {
+        return Err(format!("Open-Meteo error: {}", reason));
+    }
+    parse_forecast(&body, location, units)
+}
+
+async fn ip_location() -> Result<Location, String> {
+    if let Some(location) = IP_LOCATION.lock().unwrap().clone() {
+        return Ok(location);
+    }
+    let body = get_json(http_client()?.get(IP_LOCATION_URL)).await?;
+    let location = parse_ip_location(&body)
+        .ok_or("Couldn't detect your location; set one in the weather settings")?;
+    *IP_LOCATION.lock().unwrap() = Some(location.clone());
+    Ok(location)
+}
+
+async fn forecast(app: &AppHandle, location: Option<Location>) -> Result<Forecast, String> {
+    let settings = WeatherSettings::load(app).map_err(|e| e.to_string())?;
+    let location = match location.or(settings.location) {
+        Some(location) => location,
+        None => ip_location().await?,
+    };
+
+    let key = cache_key(&location, settings.units);
+    if let Some(forecast) = cached(&key, Instant::now()) {
+        return Ok(forecast);
+    }
+    let forecast = fetch_forecast(location, settings.units).await?;
+    store(key, forecast.clone(), Instant::now());
+    Ok(forecast)
+}
+
+#[tauri::command]
+pub async fn weather_search_locations(query: String) -> Result<Vec<Location>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let request = http_client()?.get(GEOCODING_URL).query(&[
+        ("name", query),
+        ("count", MAX_LOCATION_RESULTS),
+        ("language", "en"),
+        ("format", "json"),
+    ]);
+    let body = get_json(request).await?;
+    Ok(body
+        .get("results")
+        .and_then(Value::as_array)
+        .map(|results| results.iter().filter_map(parse_location).collect())
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn weather_get_settings(app: AppHandle) -> Result<WeatherSettings, String> {
+    WeatherSettings::load(&app).map_err(|e| e.to_string())
+}
+
+/// `None` goes back to detecting the location from the IP address
+#[tauri::command]
+pub fn weather_set_location(app: AppHandle, location: Option<Location>) -> Result<(), String> {
+    let mut settings = WeatherSettings::load(&app).map_err(|e| e.to_string())?;
+    settings.location = location;
+    settings.save(&app).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn weather_set_units(app: AppHandle, units: Units) -> Result<(), String> {
+    let mut settings = WeatherSettings::load(&app).map_err(|e| e.to_string())?;
+    settings.units = units;
+    settings.save(&app).map_err(|e| e.to_string())
+}
+
+/// Full forecast for `location`, or the saved location when omitted
+#[tauri::command]
+pub async fn weather_forecast(
+    app: AppHandle,
+    location: Option<Location>,
+) -> Result<Forecast, String> {
+    forecast(&app, location).await
+}
+
+#[tauri::command]
+pub async fn weather_current(
+    app: AppHandle,
+    location: Option<Location>,
+) -> Result<CurrentWeather, String> {
+    Ok(forecast(&app, location).await?.current)
+}
+
+#[tauri::command]
+pub async fn weather_hourly(
+    app: AppHandle,
+    location: Option<Location>,
+    hours: Option<usize>,
+) -> Result<Vec<HourlyForecast>, String> {
+    let mut hourly = forecast(&app, location).await?.hourly;
+    hourly.truncate(hours.unwrap_or(DEFAULT_HOURS));
+    Ok(hourly)
+}
+
+#[tauri::command]
+pub async fn weather_daily(
+    app: AppHandle,
+    location: Option<Location>,
+    days: Option<usize>,
+) -> Result<Vec<DailyForecast>, String> {
+    let mut daily = forecast(&app, location).await?.daily;
+    daily.truncate(days.unwrap_or(FORECAST_DAYS));
+    Ok(daily)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn szeged() -> Location {
+        Location {
+            name: "Szeged".to_string(),
+            region: Some("Csongrád".to_string()),
+            country: Some("Hungary".to_string()),
+            latitude: 46.253,
+            longitude: 20.14824,
+            timezone: Some("Europe/Budapest".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_parse_forecast() {
+        let body = json!({
+            "current": {
+                "time": "2025-01-15T14:15",
+                "temperature_2m": 3.4,
+                "apparent_temperature": 0.1,
+                "relative_humidity_2m": 81,
+                "precipitation": 0.0,
+                "weather_code": 2,
+                "wind_speed_10m": 12.5,
+                "wind_direction_10m": 270,
+                "is_day": 0
+            },
+            "hourly": {
+                "time": ["2025-01-15T13:00", "2025-01-15T14:00", "2025-01-15T15:00"],
+                "temperature_2m": [3.0, 3.4, 3.1],
+                "precipitation_probability": [0, 10, null],
+                "weather_code": [1, 2, 61],
+                "is_day": [1, 1, 0]
+            },
+            "daily": {
+                "time": ["2025-01-15"],
+                "weather_code": [3],
+                "temperature_2m_max": [4.2],
+                "temperature_2m_min": [-1.5],
+                "precipitation_probability_max": [20],
+                "sunrise": ["2025-01-15T07:21"],
+                "sunset": ["2025-01-15T16:27"]
+            }
+        });
+        let forecast = parse_forecast(&body, szeged(), Units::Metric).unwrap();
+        assert_eq!(forecast.current.description, "Partly cloudy");
+        assert_eq!(forecast.current.icon, "weather-few-clouds-night");
+        assert_eq!(forecast.current.humidity, 81.0);
+
+        assert_eq!(forecast.hourly.len(), 2);
+        assert_eq!(forecast.hourly[0].time, "2025-01-15T14:00");
+        assert_eq!(forecast.hourly[1].precipitation_probability, None);
+        assert_eq!(forecast.hourly[1].icon, "weather-showers-scattered");
+
+        assert_eq!(forecast.daily[0].temperature_min, -1.5);
+        assert_eq!(
+            forecast.daily[0].sunset.as_deref(),
+            Some("2025-01-15T16:27")
+        );
+    }
+
+    #[test]
+    fn test_parse_location() {
+        let result = json!({
+            "name": "Szeged",
+            "latitude": 46.253,
+            "longitude": 20.14824,
+            "country": "Hungary",
+            "admin1": "Csongrád",
+            "timezone": "Europe/Budapest"
+        });
+        assert_eq!(parse_location(&result), Some(szeged()));
+        assert_eq!(parse_location(&json!({ "name": "Nowhere" })), None);
+    }
+
+    #[test]
+    fn test_forecast_cache_expiry() {
+        let body = json!({
+            "current": { "time": "2025-01-15T14:15", "weather_code": 0 },
+            "hourly": { "time": [] },
+            "daily": { "time": [] }
+        });
+        let forecast = parse_forecast(&body, szeged(), Units::Imperial).unwrap();
+        let key = cache_key(&forecast.location, Units::Imperial);
+        let now = Instant::now();
+        store(key.clone(), forecast, now);
+        assert!(cached(&key, now).is_some());
+        assert!(cached(&key, now + CACHE_TTL).is_none());
+        assert_ne!(key, cache_key(&szeged(), Units::Metric));
+    }
+}
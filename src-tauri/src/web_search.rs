@@ -0,0 +1,509 @@
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_opener::open_url;
+
+const SEARCH_ENGINES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS search_engines (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    keyword TEXT NOT NULL UNIQUE,
+    url_template TEXT NOT NULL,
+    suggestions_url TEXT,
+    is_default INTEGER NOT NULL DEFAULT 0,
+    use_count INTEGER NOT NULL DEFAULT 0,
+    last_used_at INTEGER,
+    created_at INTEGER NOT NULL
+)";
+
+const QUERY_PLACEHOLDER: &str = "{query}";
+const MAX_SUGGESTIONS: usize = 8;
+/// Same decay the command palette applies to frecency
+const FRECENCY_GRAVITY: f64 = 1.8;
+
+/// (name, keyword, url template, suggestions endpoint)
+const DEFAULT_ENGINES: &[(&str, &str, &str, Option<&str>)] = &[
+    (
+        "Google",
+        "g",
+        "https://www.google.com/search?q={query}",
+        Some("https://suggestqueries.google.com/complete/search?client=firefox&q={query}"),
+    ),
+    (
+        "DuckDuckGo",
+        "ddg",
+        "https://duckduckgo.com/?q={query}",
+        Some("https://duckduckgo.com/ac/?q={query}&type=list"),
+    ),
+    (
+        "Bing",
+        "b",
+        "https://www.bing.com/search?q={query}",
+        Some("https://api.bing.com/osjson.aspx?query={query}"),
+    ),
+    (
+        "Wikipedia",
+        "w",
+        "https://en.wikipedia.org/wiki/Special:Search?search={query}",
+        Some("https://en.wikipedia.org/w/api.php?action=opensearch&format=json&search={query}"),
+    ),
+    (
+        "YouTube",
+        "yt",
+        "https://www.youtube.com/results?search_query={query}",
+        Some("https://suggestqueries.google.com/complete/search?client=firefox&ds=yt&q={query}"),
+    ),
+    ("GitHub", "gh", "https://github.com/search?q={query}", None),
+];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchEngine {
+    pub id: i64,
+    pub name: String,
+    /// Typing `<keyword> <query>` searches this engine directly
+    pub keyword: String,
+    /// URL with a `{query}` placeholder
+    pub url_template: String,
+    /// OpenSearch suggestions endpoint with a `{query}` placeholder
+    pub suggestions_url: Option<String>,
+    pub is_default: bool,
+    pub use_count: i64,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Storable for SearchEngine {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let last_used_at: Option<i64> = row.get(7)?;
+        let created_at: i64 = row.get(8)?;
+        Ok(SearchEngine {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            keyword: row.get(2)?,
+            url_template: row.get(3)?,
+            suggestions_url: row.get(4)?,
+            is_default: row.get(5)?,
+            use_count: row.get(6)?,
+            last_used_at: last_used_at.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_default(),
+        })
+    }
+}
+
+impl SearchEngine {
+    pub fn search_url(&self, query: &str) -> String {
+        fill_template(&self.url_template, query)
+    }
+
+    fn frecency(&self, now: DateTime<Utc>) -> f64 {
+        let Some(last_used_at) = self.last_used_at else {
+            return 0.0;
+        };
+        let hours = (now - last_used_at).num_seconds().max(0) as f64 / 3600.0;
+        self.use_count as f64 / (hours + 2.0).powf(FRECENCY_GRAVITY)
+    }
+}
+
+/// A query routed to an engine, either by keyword or as the fallback
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedSearch {
+    pub engine: SearchEngine,
+    pub query: String,
+    pub url: String,
+}
+
+fn fill_template(template: &str, query: &str) -> String {
+    template.replace(QUERY_PLACEHOLDER, &urlencoding::encode(query))
+}
+
+fn validate_engine(keyword: &str, url_template: &str) -> Result<(), String> {
+    if keyword.trim().is_empty() || keyword.contains(char::is_whitespace) {
+        return Err("Keyword must be a single word".to_string());
+    }
+    if !url_template.contains(QUERY_PLACEHOLDER) {
+        return Err(format!("URL template must contain {}", QUERY_PLACEHOLDER));
+    }
+    Ok(())
+}
+
+/// OpenSearch suggestions are `[query, [suggestion, ...], ...]`; DuckDuckGo's
+/// `type=list` uses the same shape
+fn parse_suggestions(body: &Value) -> Vec<String> {
+    body.get(1)
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(String::from))
+                .take(MAX_SUGGESTIONS)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Default engine first, then by how often and how recently each was used
+fn sort_by_fallback_priority(engines: &mut [SearchEngine], now: DateTime<Utc>) {
+    engines.sort_by(|a, b| {
+        b.is_default
+            .cmp(&a.is_default)
+            .then_with(|| b.frecency(now).total_cmp(&a.frecency(now)))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+pub struct WebSearchManager {
+    store: Store,
+}
+
+impl WebSearchManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "web_search.sqlite")?;
+        store.init_table(SEARCH_ENGINES_SCHEMA)?;
+        let manager = Self { store };
+        manager.seed_defaults()?;
+        Ok(manager)
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(SEARCH_ENGINES_SCHEMA)?;
+        let manager = Self { store };
+        manager.seed_defaults()?;
+        Ok(manager)
+    }
+
+    fn seed_defaults(&self) -> Result<(), AppError> {
+        let count: i64 =
+            self.store
+                .conn()
+                .query_row("SELECT COUNT(*) FROM search_engines", [], |row| row.get(0))?;
+        if count > 0 {
+            return Ok(());
+        }
+        let now = Utc::now().timestamp();
+        for (i, (name, keyword, url_template, suggestions_url)) in
+            DEFAULT_ENGINES.iter().enumerate()
+        {
+            self.store.execute(
+                "INSERT INTO search_engines (name, keyword, url_template, suggestions_url, is_default, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                params![name, keyword, url_template, suggestions_url, i == 0, now],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn list_engines(&self) -> Result<Vec<SearchEngine>, AppError> {
+        let mut engines: Vec<SearchEngine> = self.store.query(
+            "SELECT id, name, keyword, url_template, suggestions_url, is_default, use_count, last_used_at, created_at
+             FROM search_engines",
+            [],
+        )?;
+        sort_by_fallback_priority(&mut engines, Utc::now());
+        Ok(engines)
+    }
+
+    /// Where a query goes without a keyword
+    pub(crate) fn fallback_engine(&self) -> Result<Option<SearchEngine>, AppError> {
+        Ok(self.list_engines()?.into_iter().next())
+    }
+
+    fn get_engine(&self, id: i64) -> Result<Option<SearchEngine>, AppError> {
+        let mut engines: Vec<SearchEngine> = self.store.query(
+            "SELECT id, name, keyword, url_template, suggestions_url, is_default, use_count, last_used_at, created_at
+             FROM search_engines WHERE id = ?",
+            params![id],
+        )?;
+        Ok(engines.pop())
+    }
+
+    fn create_engine(
+        &self,
+        name: String,
+        keyword: String,
+        url_template: String,
+        suggestions_url: Option<String>,
+    ) -> Result<i64, AppError> {
+        self.store.execute(
+            "INSERT INTO search_engines (name, keyword, url_template, suggestions_url, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+            params![
+                name,
+                keyword,
+                url_template,
+                suggestions_url,
+                Utc::now().timestamp()
+            ],
+        )?;
+        Ok(self.store.last_insert_rowid())
+    }
+
+    fn update_engine(
+        &self,
+        id: i64,
+        name: String,
+        keyword: String,
+        url_template: String,
+        suggestions_url: Option<String>,
+    ) -> Result<(), AppError> {
+        self.store.execute(
+            "UPDATE search_engines SET name = ?, keyword = ?, url_template = ?, suggestions_url = ?
+             WHERE id = ?",
+            params![name, keyword, url_template, suggestions_url, id],
+        )?;
+        Ok(())
+    }
+
+    fn delete_engine(&self, id: i64) -> Result<(), AppError> {
+        self.store
+            .execute("DELETE FROM search_engines WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    fn set_default(&self, id: i64) -> Result<(), AppError> {
+        self.store.execute(
+            "UPDATE search_engines SET is_default = (id = ?)",
+            params![id],
+        )?;
+        Ok(())
+    }
+
+    fn record_usage(&self, id: i64) -> Result<(), AppError> {
+        self.store.execute(
+            "UPDATE search_engines SET use_count = use_count + 1, last_used_at = ? WHERE id = ?",
+            params![Utc::now().timestamp(), id],
+        )?;
+        Ok(())
+    }
+
+    /// `g rust lifetimes` goes to the engine with keyword `g`; anything else
+    /// falls through to the highest-priority engine
+    fn resolve(&self, input: &str) -> Result<Option<ResolvedSearch>, AppError> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+        let engines = self.list_engines()?;
+
+        let keyword_match = input
+            .split_once(char::is_whitespace)
+            .and_then(|(word, rest)| {
+                let rest = rest.trim();
+                engines
+                    .iter()
+                    .find(|e| e.keyword.eq_ignore_ascii_case(word) && !rest.is_empty())
+                    .map(|engine| (engine.clone(), rest.to_string()))
+            });
+        let resolved = keyword_match.or_else(|| {
+            engines
+                .into_iter()
+                .next()
+                .map(|engine| (engine, input.to_string()))
+        });
+
+        Ok(resolved.map(|(engine, query)| ResolvedSearch {
+            url: engine.search_url(&query),
+            engine,
+            query,
+        }))
+    }
+}
+
+#[tauri::command]
+pub fn list_search_engines(app: AppHandle) -> Result<Vec<SearchEngine>, String> {
+    app.state::<WebSearchManager>()
+        .list_engines()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_search_engine(
+    app: AppHandle,
+    name: String,
+    keyword: String,
+    url_template: String,
+    suggestions_url: Option<String>,
+) -> Result<i64, String> {
+    validate_engine(&keyword, &url_template)?;
+    app.state::<WebSearchManager>()
+        .create_engine(name, keyword, url_template, suggestions_url)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_search_engine(
+    app: AppHandle,
+    id: i64,
+    name: String,
+    keyword: String,
+    url_template: String,
+    suggestions_url: Option<String>,
+) -> Result<(), String> {
+    validate_engine(&keyword, &url_template)?;
+    app.state::<WebSearchManager>()
+        .update_engine(id, name, keyword, url_template, suggestions_url)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_search_engine(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<WebSearchManager>()
+        .delete_engine(id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_default_search_engine(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<WebSearchManager>()
+        .set_default(id)
+        .map_err(|e| e.to_string())
+}
+
+/// Works out which engine a palette query should fall through to
+#[tauri::command]
+pub fn resolve_web_search(app: AppHandle, query: String) -> Result<Option<ResolvedSearch>, String> {
+    app.state::<WebSearchManager>()
+        .resolve(&query)
+        .map_err(|e| e.to_string())
+}
+
+/// Opens the search in the browser and counts it towards the engine's ranking.
+/// Without `engine_id` the query is resolved by keyword or fallback order.
+#[tauri::command]
+pub fn web_search(app: AppHandle, engine_id: Option<i64>, query: String) -> Result<(), String> {
+    let manager = app.state::<WebSearchManager>();
+    let (engine, query) = match engine_id {
+        Some(id) => {
+            let engine = manager
+                .get_engine(id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Search engine {} not found", id))?;
+            (engine, query)
+        }
+        None => {
+            let resolved = manager
+                .resolve(&query)
+                .map_err(|e| e.to_string())?
+                .ok_or("No search engine configured")?;
+            (resolved.engine, resolved.query)
+        }
+    };
+
+    open_url(engine.search_url(&query), None::<String>).map_err(|e| e.to_string())?;
+    manager.record_usage(engine.id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_search_suggestions(
+    app: AppHandle,
+    engine: i64,
+    query: String,
+) -> Result<Vec<String>, String> {
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let engine = app
+        .state::<WebSearchManager>()
+        .get_engine(engine)
+        .map_err(|e| e.to_string())?
+        .ok_or("Search engine not found")?;
+    let Some(suggestions_url) = engine.suggestions_url else {
+        return Ok(Vec::new());
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .get(fill_template(&suggestions_url, &query))
+        .header("User-Agent", "Flare launcher")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Suggestions request failed: {}", response.status()));
+    }
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    Ok(parse_suggestions(&body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_are_seeded_once() {
+        let manager = WebSearchManager::new_for_test().unwrap();
+        manager.seed_defaults().unwrap();
+        let engines = manager.list_engines().unwrap();
+        assert_eq!(engines.len(), DEFAULT_ENGINES.len());
+        assert_eq!(engines[0].keyword, "g");
+        assert!(engines[0].is_default);
+    }
+
+    #[test]
+    fn test_resolve_by_keyword_and_fallback() {
+        let manager = WebSearchManager::new_for_test().unwrap();
+
+        let resolved = manager.resolve("w rust  lang").unwrap().unwrap();
+        assert_eq!(resolved.engine.keyword, "w");
+        assert_eq!(resolved.query, "rust  lang");
+        assert_eq!(
+            resolved.url,
+            "https://en.wikipedia.org/wiki/Special:Search?search=rust%20%20lang"
+        );
+
+        let resolved = manager.resolve("rust lang").unwrap().unwrap();
+        assert_eq!(resolved.engine.keyword, "g");
+        assert_eq!(resolved.query, "rust lang");
+
+        // A bare keyword is a query, not an engine switch
+        let resolved = manager.resolve("w").unwrap().unwrap();
+        assert_eq!(resolved.engine.keyword, "g");
+        assert!(manager.resolve("   ").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_usage_ranks_non_default_engines() {
+        let manager = WebSearchManager::new_for_test().unwrap();
+        let gh = manager
+            .list_engines()
+            .unwrap()
+            .into_iter()
+            .find(|e| e.keyword == "gh")
+            .unwrap();
+        manager.record_usage(gh.id).unwrap();
+
+        let engines = manager.list_engines().unwrap();
+        assert_eq!(engines[0].keyword, "g");
+        assert_eq!(engines[1].keyword, "gh");
+        assert_eq!(engines[1].use_count, 1);
+
+        manager.set_default(gh.id).unwrap();
+        let engines = manager.list_engines().unwrap();
+        assert_eq!(engines[0].keyword, "gh");
+        assert_eq!(engines.iter().filter(|e| e.is_default).count(), 1);
+    }
+
+    #[test]
+    fn test_parse_suggestions() {
+        let body = serde_json::json!(["rust", ["rust lang", "rustup", 3], [], []]);
+        assert_eq!(parse_suggestions(&body), vec!["rust lang", "rustup"]);
+        assert!(parse_suggestions(&serde_json::json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_validate_engine() {
+        assert!(validate_engine("sp", "https://startpage.com/do/search?q={query}").is_ok());
+        assert!(validate_engine("two words", "https://x.com/?q={query}").is_err());
+        assert!(validate_engine("x", "https://x.com/").is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use super::geometry::{Monitor, Rect, WindowInfo};
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+const WINDOW_ARRANGEMENTS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS window_arrangements (
+    name TEXT PRIMARY KEY,
+    windows TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+)";
+
+/// Where a window was when the arrangement was saved. The position is stored
+/// relative to its monitor so it survives monitors being rearranged.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedWindow {
+    pub wm_class: String,
+    pub title: String,
+    pub monitor: String,
+    pub rect: Rect,
+    pub desktop: Option<u32>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowArrangement {
+    pub name: String,
+    pub windows: Vec<SavedWindow>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Storable for WindowArrangement {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let windows_json: String = row.get(1)?;
+        let created_at_ts: i64 = row.get(2)?;
+        Ok(WindowArrangement {
+            name: row.get(0)?,
+            windows: serde_json::from_str(&windows_json).unwrap_or_default(),
+            created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
+        })
+    }
+}
+
+/// A move to perform when restoring: window id plus absolute target geometry
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedMove {
+    pub window: u32,
+    pub rect: Rect,
+    pub desktop: Option<u32>,
+}
+
+fn monitor_for<'a>(monitors: &'a [Monitor], rect: &Rect) -> Option<&'a Monitor> {
+    monitors
+        .iter()
+        .find(|m| m.rect.contains(rect.center()))
+        .or_else(|| monitors.iter().find(|m| m.primary))
+        .or_else(|| monitors.first())
+}
+
+pub fn capture(windows: &[WindowInfo], monitors: &[Monitor]) -> Vec<SavedWindow> {
+    windows
+        .iter()
+        .filter_map(|window| {
+            let monitor = monitor_for(monitors, &window.rect)?;
+            Some(SavedWindow {
+                wm_class: window.wm_class.clone(),
+                title: window.title.clone(),
+                monitor: monitor.name.clone(),
+                rect: Rect::new(
+                    window.rect.x - monitor.rect.x,
+                    window.rect.y - monitor.rect.y,
+                    window.rect.width,
+                    window.rect.height,
+                ),
+                desktop: window.desktop,
+            })
+        })
+        .collect()
+}
+
+/// Pairs saved windows with currently open ones by WM_CLASS, preferring an
+/// exact title match. Windows on a monitor that is gone land on the primary one.
+pub fn plan_restore(
+    saved: &[SavedWindow],
+    windows: &[WindowInfo],
+    monitors: &[Monitor],
+) -> Vec<PlannedMove> {
+    let fallback = monitors
+        .iter()
+        .find(|m| m.primary)
+        .or_else(|| monitors.first());
+    let mut used = vec![false; windows.len()];
+    let mut moves = Vec::new();
+
+    for entry in saved {
+        let candidates = || {
+            windows
+                .iter()
+                .enumerate()
+                .filter(|(i, w)| !used[*i] && w.wm_class == entry.wm_class)
+        };
+        let matched = candidates()
+            .find(|(_, w)| w.title == entry.title)
+            .or_else(|| candidates().next())
+            .map(|(i, _)| i);
+
+        let Some(index) = matched else {
+            continue;
+        };
+        let Some(monitor) = monitors
+            .iter()
+            .find(|m| m.name == entry.monitor)
+            .or(fallback)
+        else {
+            continue;
+        };
+        used[index] = true;
+
+        let width = entry.rect.width.min(monitor.rect.width);
+        let height = entry.rect.height.min(monitor.rect.height);
+        let max_x = (monitor.rect.width - width) as i32;
+        let max_y = (monitor.rect.height - height) as i32;
+        moves.push(PlannedMove {
+            window: windows[index].id,
+            rect: Rect::new(
+                monitor.rect.x + entry.rect.x.clamp(0, max_x),
+                monitor.rect.y + entry.rect.y.clamp(0, max_y),
+                width,
+                height,
+            ),
+            desktop: entry.desktop,
+        });
+    }
+
+    moves
+}
+
+pub struct WindowArrangementManager {
+    store: Store,
+}
+
+impl WindowArrangementManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "window_arrangements.sqlite")?;
+        store.init_table(WINDOW_ARRANGEMENTS_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(WINDOW_ARRANGEMENTS_SCHEMA)?;
+        Ok(Self { store })
+    }
+
+    pub fn save(&self, name: &str, windows: &[SavedWindow]) -> Result<(), AppError> {
+        let windows_json =
+            serde_json::to_string(windows).map_err(|e| AppError::Serialization(e.to_string()))?;
+        self.store.execute(
+            "INSERT OR REPLACE INTO window_arrangements (name, windows, created_at) VALUES (?, ?, ?)",
+            params![name, windows_json, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<WindowArrangement>, AppError> {
+        self.store.query_row(
+            "SELECT name, windows, created_at FROM window_arrangements WHERE name = ?",
+            params![name],
+        )
+    }
+
+    pub fn list(&self) -> Result<Vec<WindowArrangement>, AppError> {
+        self.store.query(
+            "SELECT name, windows, created_at FROM window_arrangements ORDER BY name ASC",
+            [],
+        )
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), AppError> {
+        self.store.execute(
+            "DELETE FROM window_arrangements WHERE name = ?",
+            params![name],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, x: i32, primary: bool) -> Monitor {
+        Monitor {
+            name: name.to_string(),
+            primary,
+            rect: Rect::new(x, 0, 1920, 1080),
+        }
+    }
+
+    fn window(id: u32, class: &str, title: &str, rect: Rect) -> WindowInfo {
+        WindowInfo {
+            id,
+            wm_class: class.to_string(),
+            title: title.to_string(),
+            rect,
+            desktop: Some(0),
+        }
+    }
+
+    #[test]
+    fn test_capture_is_monitor_relative() {
+        let monitors = vec![monitor("eDP-1", 0, true), monitor("HDMI-1", 1920, false)];
+        let windows = vec![window(1, "firefox", "Docs", Rect::new(2020, 50, 800, 600))];
+
+        let saved = capture(&windows, &monitors);
+        assert_eq!(saved[0].monitor, "HDMI-1");
+        assert_eq!(saved[0].rect, Rect::new(100, 50, 800, 600));
+    }
+
+    #[test]
+    fn test_plan_restore_matches_title_then_class() {
+        let monitors = vec![monitor("eDP-1", 0, true), monitor("HDMI-1", 1920, false)];
+        let saved = vec![SavedWindow {
+            wm_class: "kitty".to_string(),
+            title: "server".to_string(),
+            monitor: "HDMI-1".to_string(),
+            rect: Rect::new(0, 0, 960, 1080),
+            desktop: Some(1),
+        }];
+        let windows = vec![
+            window(1, "kitty", "editor", Rect::new(0, 0, 100, 100)),
+            window(2, "kitty", "server", Rect::new(0, 0, 100, 100)),
+        ];
+
+        let moves = plan_restore(&saved, &windows, &monitors);
+        assert_eq!(
+            moves,
+            vec![PlannedMove {
+                window: 2,
+                rect: Rect::new(1920, 0, 960, 1080),
+                desktop: Some(1),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_plan_restore_falls_back_to_primary() {
+        // Undocked: the external monitor is gone
+        let monitors = vec![monitor("eDP-1", 0, true)];
+        let saved = vec![SavedWindow {
+            wm_class: "firefox".to_string(),
+            title: "Docs".to_string(),
+            monitor: "HDMI-1".to_string(),
+            rect: Rect::new(1500, 0, 2560, 1440),
+            desktop: None,
+        }];
+        let windows = vec![window(7, "firefox", "Other", Rect::new(0, 0, 100, 100))];
+
+        let moves = plan_restore(&saved, &windows, &monitors);
+        assert_eq!(moves[0].rect, Rect::new(0, 0, 1920, 1080));
+    }
+
+    #[test]
+    fn test_arrangement_storage() {
+        let manager = WindowArrangementManager::new_for_test().unwrap();
+        let saved = vec![SavedWindow {
+            wm_class: "firefox".to_string(),
+            title: "Docs".to_string(),
+            monitor: "eDP-1".to_string(),
+            rect: Rect::new(0, 0, 800, 600),
+            desktop: Some(0),
+        }];
+        manager.save("docked", &saved).unwrap();
+        manager.save("docked", &saved).unwrap();
+
+        assert_eq!(manager.list().unwrap().len(), 1);
+        assert_eq!(manager.get("docked").unwrap().unwrap().windows, saved);
+
+        manager.delete("docked").unwrap();
+        assert!(manager.get("docked").unwrap().is_none());
+    }
+}
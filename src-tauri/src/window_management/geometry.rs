@@ -0,0 +1,372 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn center(&self) -> (i32, i32) {
+        (
+            self.x + self.width as i32 / 2,
+            self.y + self.height as i32 / 2,
+        )
+    }
+
+    pub fn contains(&self, (x, y): (i32, i32)) -> bool {
+        x >= self.x
+            && y >= self.y
+            && x < self.x + self.width as i32
+            && y < self.y + self.height as i32
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    /// Sub-rectangle given as fractions (0.0-1.0) of this one
+    pub fn fraction(&self, x: f64, y: f64, width: f64, height: f64) -> Rect {
+        let w = self.width as f64;
+        let h = self.height as f64;
+        Rect {
+            x: self.x + (w * x).round() as i32,
+            y: self.y + (h * y).round() as i32,
+            width: (w * width).round() as u32,
+            height: (h * height).round() as u32,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Monitor {
+    pub name: String,
+    pub primary: bool,
+    pub rect: Rect,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StrutEdge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// Screen space reserved by a panel or dock, in root window coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Strut {
+    pub edge: StrutEdge,
+    pub rect: Rect,
+}
+
+impl Strut {
+    /// Decodes `_NET_WM_STRUT_PARTIAL` (12 values) or the older `_NET_WM_STRUT` (4 values,
+    /// spanning the whole edge)
+    pub fn from_property(values: &[u32], screen_width: u32, screen_height: u32) -> Vec<Strut> {
+        if values.len() < 4 {
+            return Vec::new();
+        }
+        let full = |i: usize, max: u32| -> (u32, u32) {
+            if values.len() >= 12 {
+                (values[i], values[i + 1])
+            } else {
+                (0, max.saturating_sub(1))
+            }
+        };
+        let span = |(start, end): (u32, u32)| end.saturating_sub(start) + 1;
+
+        let mut struts = Vec::new();
+        let (left, right, top, bottom) = (values[0], values[1], values[2], values[3]);
+        if left > 0 {
+            let range = full(4, screen_height);
+            struts.push(Strut {
+                edge: StrutEdge::Left,
+                rect: Rect::new(0, range.0 as i32, left, span(range)),
+            });
+        }
+        if right > 0 {
+            let range = full(6, screen_height);
+            struts.push(Strut {
+                edge: StrutEdge::Right,
+                rect: Rect::new(
+                    screen_width.saturating_sub(right) as i32,
+                    range.0 as i32,
+                    right,
+                    span(range),
+                ),
+            });
+        }
+        if top > 0 {
+            let range = full(8, screen_width);
+            struts.push(Strut {
+                edge: StrutEdge::Top,
+                rect: Rect::new(range.0 as i32, 0, span(range), top),
+            });
+        }
+        if bottom > 0 {
+            let range = full(10, screen_width);
+            struts.push(Strut {
+                edge: StrutEdge::Bottom,
+                rect: Rect::new(
+                    range.0 as i32,
+                    screen_height.saturating_sub(bottom) as i32,
+                    span(range),
+                    bottom,
+                ),
+            });
+        }
+        struts
+    }
+}
+
+/// Shrinks `monitor` by every strut that overlaps it
+pub fn work_area(monitor: &Rect, struts: &[Strut]) -> Rect {
+    let (mut left, mut top, mut right, mut bottom) =
+        (monitor.x, monitor.y, monitor.right(), monitor.bottom());
+
+    for strut in struts.iter().filter(|s| s.rect.intersects(monitor)) {
+        match strut.edge {
+            StrutEdge::Left => left = left.max(strut.rect.right()),
+            StrutEdge::Right => right = right.min(strut.rect.x),
+            StrutEdge::Top => top = top.max(strut.rect.bottom()),
+            StrutEdge::Bottom => bottom = bottom.min(strut.rect.y),
+        }
+    }
+
+    Rect::new(
+        left,
+        top,
+        (right - left).max(0) as u32,
+        (bottom - top).max(0) as u32,
+    )
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowInfo {
+    pub id: u32,
+    pub wm_class: String,
+    pub title: String,
+    pub rect: Rect,
+    pub desktop: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SnapPosition {
+    LeftHalf,
+    RightHalf,
+    TopHalf,
+    BottomHalf,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    LeftThird,
+    CenterThird,
+    RightThird,
+    LeftTwoThirds,
+    RightTwoThirds,
+    Maximize,
+    Center,
+}
+
+impl SnapPosition {
+    /// Target rectangle for this position inside `area`
+    pub fn rect(&self, area: &Rect) -> Rect {
+        let third = 1.0 / 3.0;
+        match self {
+            SnapPosition::LeftHalf => area.fraction(0.0, 0.0, 0.5, 1.0),
+            SnapPosition::RightHalf => area.fraction(0.5, 0.0, 0.5, 1.0),
+            SnapPosition::TopHalf => area.fraction(0.0, 0.0, 1.0, 0.5),
+            SnapPosition::BottomHalf => area.fraction(0.0, 0.5, 1.0, 0.5),
+            SnapPosition::TopLeft => area.fraction(0.0, 0.0, 0.5, 0.5),
+            SnapPosition::TopRight => area.fraction(0.5, 0.0, 0.5, 0.5),
+            SnapPosition::BottomLeft => area.fraction(0.0, 0.5, 0.5, 0.5),
+            SnapPosition::BottomRight => area.fraction(0.5, 0.5, 0.5, 0.5),
+            SnapPosition::LeftThird => area.fraction(0.0, 0.0, third, 1.0),
+            SnapPosition::CenterThird => area.fraction(third, 0.0, third, 1.0),
+            SnapPosition::RightThird => area.fraction(2.0 * third, 0.0, third, 1.0),
+            SnapPosition::LeftTwoThirds => area.fraction(0.0, 0.0, 2.0 * third, 1.0),
+            SnapPosition::RightTwoThirds => area.fraction(third, 0.0, 2.0 * third, 1.0),
+            SnapPosition::Maximize => *area,
+            SnapPosition::Center => area.fraction(0.15, 0.1, 0.7, 0.8),
+        }
+    }
+}
+
+/// One zone of a custom layout, in percent of the work area
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutZone {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl LayoutZone {
+    pub fn validate(&self) -> Result<(), String> {
+        let in_range = |v: f64| (0.0..=100.0).contains(&v);
+        if !in_range(self.x) || !in_range(self.y) {
+            return Err("Zone origin must be between 0 and 100 percent".to_string());
+        }
+        if self.width <= 0.0 || self.height <= 0.0 {
+            return Err("Zone size must be positive".to_string());
+        }
+        if self.x + self.width > 100.0 + 1e-6 || self.y + self.height > 100.0 + 1e-6 {
+            return Err("Zone must fit inside the work area".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn rect(&self, area: &Rect) -> Rect {
+        area.fraction(
+            self.x / 100.0,
+            self.y / 100.0,
+            self.width / 100.0,
+            self.height / 100.0,
+        )
+    }
+}
+
+/// A change to a window frame in the terms AppleScript uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameChange {
+    /// Left, top, right, bottom
+    Bounds(i32, i32, i32, i32),
+    Position(i32, i32),
+    Size(u32, u32),
+}
+
+impl FrameChange {
+    pub fn apply(self, rect: &Rect) -> Rect {
+        match self {
+            FrameChange::Bounds(left, top, right, bottom) => Rect::new(
+                left,
+                top,
+                (right - left).max(1) as u32,
+                (bottom - top).max(1) as u32,
+            ),
+            FrameChange::Position(x, y) => Rect::new(x, y, rect.width, rect.height),
+            FrameChange::Size(width, height) => Rect::new(rect.x, rect.y, width, height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snap_positions() {
+        let area = Rect::new(0, 30, 1920, 1050);
+        assert_eq!(
+            SnapPosition::LeftHalf.rect(&area),
+            Rect::new(0, 30, 960, 1050)
+        );
+        assert_eq!(
+            SnapPosition::BottomRight.rect(&area),
+            Rect::new(960, 555, 960, 525)
+        );
+        assert_eq!(
+            SnapPosition::RightThird.rect(&area),
+            Rect::new(1280, 30, 640, 1050)
+        );
+        assert_eq!(SnapPosition::Maximize.rect(&area), area);
+    }
+
+    #[test]
+    fn test_work_area_multi_panel() {
+        // Two 1920x1080 monitors side by side, GNOME-style top bar on the left one
+        // and a KDE-style side panel on the right edge of the right one
+        let screen = (3840, 1080);
+        let mut struts = Strut::from_property(
+            &[0, 0, 32, 0, 0, 0, 0, 0, 0, 1919, 0, 0],
+            screen.0,
+            screen.1,
+        );
+        struts.extend(Strut::from_property(
+            &[0, 48, 0, 0, 0, 0, 0, 1079, 0, 0, 0, 0],
+            screen.0,
+            screen.1,
+        ));
+
+        let left = Rect::new(0, 0, 1920, 1080);
+        let right = Rect::new(1920, 0, 1920, 1080);
+        assert_eq!(work_area(&left, &struts), Rect::new(0, 32, 1920, 1048));
+        assert_eq!(work_area(&right, &struts), Rect::new(1920, 0, 1872, 1080));
+    }
+
+    #[test]
+    fn test_legacy_strut_spans_whole_edge() {
+        let struts = Strut::from_property(&[0, 0, 0, 30], 1920, 1080);
+        assert_eq!(
+            work_area(&Rect::new(0, 0, 1920, 1080), &struts),
+            Rect::new(0, 0, 1920, 1050)
+        );
+    }
+
+    #[test]
+    fn test_layout_zone() {
+        let area = Rect::new(100, 0, 3000, 1000);
+        let two_thirds = LayoutZone {
+            x: 0.0,
+            y: 0.0,
+            width: 66.0,
+            height: 100.0,
+        };
+        assert_eq!(two_thirds.rect(&area), Rect::new(100, 0, 1980, 1000));
+        assert!(two_thirds.validate().is_ok());
+
+        let overflowing = LayoutZone {
+            x: 50.0,
+            y: 0.0,
+            width: 60.0,
+            height: 100.0,
+        };
+        assert!(overflowing.validate().is_err());
+    }
+
+    #[test]
+    fn test_frame_change() {
+        let rect = Rect::new(10, 20, 300, 200);
+        assert_eq!(
+            FrameChange::Bounds(0, 25, 800, 625).apply(&rect),
+            Rect::new(0, 25, 800, 600)
+        );
+        assert_eq!(
+            FrameChange::Position(-1920, 0).apply(&rect),
+            Rect::new(-1920, 0, 300, 200)
+        );
+        assert_eq!(
+            FrameChange::Size(640, 480).apply(&rect),
+            Rect::new(10, 20, 640, 480)
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use super::geometry::LayoutZone;
+use crate::error::AppError;
+use crate::store::{Storable, Store};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Result as RusqliteResult};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+const SNAP_LAYOUTS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS snap_layouts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    zones TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+)";
+
+/// Presses of the layout hotkey closer together than this advance to the next zone
+const CYCLE_WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapLayout {
+    pub id: i64,
+    pub name: String,
+    pub zones: Vec<LayoutZone>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Storable for SnapLayout {
+    fn from_row(row: &rusqlite::Row) -> RusqliteResult<Self> {
+        let zones_json: String = row.get(2)?;
+        let created_at_ts: i64 = row.get(3)?;
+        let updated_at_ts: i64 = row.get(4)?;
+        Ok(SnapLayout {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            zones: serde_json::from_str(&zones_json).unwrap_or_default(),
+            created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
+            updated_at: DateTime::from_timestamp(updated_at_ts, 0).unwrap_or_default(),
+        })
+    }
+}
+
+struct CycleState {
+    window: String,
+    layout_id: i64,
+    zone: usize,
+    at: Instant,
+}
+
+pub struct SnapLayoutManager {
+    store: Store,
+    last_snap: Mutex<Option<CycleState>>,
+}
+
+impl SnapLayoutManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let store = Store::new(app_handle, "snap_layouts.sqlite")?;
+        store.init_table(SNAP_LAYOUTS_SCHEMA)?;
+        Ok(Self {
+            store,
+            last_snap: Mutex::new(None),
+        })
+    }
+
+    #[cfg(test)]
+    fn new_for_test() -> Result<Self, AppError> {
+        let store = Store::new_in_memory()?;
+        store.init_table(SNAP_LAYOUTS_SCHEMA)?;
+        Ok(Self {
+            store,
+            last_snap: Mutex::new(None),
+        })
+    }
+
+    fn validate(zones: &[LayoutZone]) -> Result<String, AppError> {
+        if zones.is_empty() {
+            return Err(AppError::WindowManagement(
+                "A layout needs at least one zone".to_string(),
+            ));
+        }
+        for zone in zones {
+            zone.validate().map_err(AppError::WindowManagement)?;
+        }
+        serde_json::to_string(zones).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    pub fn create_layout(&self, name: String, zones: Vec<LayoutZone>) -> Result<i64, AppError> {
+        let zones_json = Self::validate(&zones)?;
+        let now = Utc::now().timestamp();
+        self.store.execute(
+            "INSERT INTO snap_layouts (name, zones, created_at, updated_at) VALUES (?, ?, ?, ?)",
+            params![name, zones_json, now, now],
+        )?;
+        Ok(self.store.last_insert_rowid())
+    }
+
+    pub fn list_layouts(&self) -> Result<Vec<SnapLayout>, AppError> {
+        self.store.query(
+            "SELECT id, name, zones, created_at, updated_at FROM snap_layouts ORDER BY name ASC",
+            [],
+        )
+    }
+
+    pub fn get_layout(&self, id: i64) -> Result<Option<SnapLayout>, AppError> {
+        self.store.query_row(
+            "SELECT id, name, zones, created_at, updated_at FROM snap_layouts WHERE id = ?",
+            params![id],
+        )
+    }
+
+    pub fn update_layout(
+        &self,
+        id: i64,
+        name: String,
+        zones: Vec<LayoutZone>,
+    ) -> Result<(), AppError> {
+        let zones_json = Self::validate(&zones)?;
+        let now = Utc::now().timestamp();
+        self.store.execute(
+            "UPDATE snap_layouts SET name = ?, zones = ?, updated_at = ? WHERE id = ?",
+            params![name, zones_json, now, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_layout(&self, id: i64) -> Result<(), AppError> {
+        self.store
+            .execute("DELETE FROM snap_layouts WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    /// Picks the zone to snap `window` into. An explicit zone wins; otherwise
+    /// repeated presses on the same window and layout step through the zones.
+    pub fn resolve_zone(
+        &self,
+        layout: &SnapLayout,
+        window: &str,
+        zone: Option<usize>,
+        now: Instant,
+    ) -> Result<usize, AppError> {
+        let mut last_snap = self.last_snap.lock().unwrap();
+
+        let index = match zone {
+            Some(index) => index,
+            None => match last_snap.as_ref() {
+                Some(last)
+                    if last.window == window
+                        && last.layout_id == layout.id
+                        && now.duration_since(last.at) <= CYCLE_WINDOW =>
+                {
+                    (last.zone + 1) % layout.zones.len()
+                }
+                _ => 0,
+            },
+        };
+
+        if index >= layout.zones.len() {
+            return Err(AppError::WindowManagement(format!(
+                "Layout '{}' has no zone {}",
+                layout.name, index
+            )));
+        }
+
+        *last_snap = Some(CycleState {
+            window: window.to_string(),
+            layout_id: layout.id,
+            zone: index,
+            at: now,
+        });
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(x: f64, width: f64) -> LayoutZone {
+        LayoutZone {
+            x,
+            y: 0.0,
+            width,
+            height: 100.0,
+        }
+    }
+
+    #[test]
+    fn test_layout_crud() {
+        let manager = SnapLayoutManager::new_for_test().unwrap();
+        let id = manager
+            .create_layout(
+                "Two thirds".to_string(),
+                vec![zone(0.0, 66.6), zone(66.6, 33.4)],
+            )
+            .unwrap();
+
+        let layout = manager.get_layout(id).unwrap().unwrap();
+        assert_eq!(layout.zones.len(), 2);
+
+        manager
+            .update_layout(
+                id,
+                "Halves".to_string(),
+                vec![zone(0.0, 50.0), zone(50.0, 50.0)],
+            )
+            .unwrap();
+        assert_eq!(manager.list_layouts().unwrap()[0].name, "Halves");
+
+        assert!(manager
+            .create_layout("Broken".to_string(), vec![zone(80.0, 40.0)])
+            .is_err());
+
+        manager.delete_layout(id).unwrap();
+        assert!(manager.list_layouts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_zone_cycling() {
+        let manager = SnapLayoutManager::new_for_test().unwrap();
+        let id = manager
+            .create_layout(
+                "Thirds".to_string(),
+                vec![zone(0.0, 33.3), zone(33.3, 33.3), zone(66.6, 33.4)],
+            )
+            .unwrap();
+        let layout = manager.get_layout(id).unwrap().unwrap();
+        let now = Instant::now();
+
+        assert_eq!(manager.resolve_zone(&layout, "1", None, now).unwrap(), 0);
+        assert_eq!(manager.resolve_zone(&layout, "1", None, now).unwrap(), 1);
+        assert_eq!(manager.resolve_zone(&layout, "1", None, now).unwrap(), 2);
+        assert_eq!(manager.resolve_zone(&layout, "1", None, now).unwrap(), 0);
+
+        // A different window starts over
+        assert_eq!(manager.resolve_zone(&layout, "2", None, now).unwrap(), 0);
+
+        // So does a press after the cycle window
+        let later = now + CYCLE_WINDOW * 2;
+        assert_eq!(manager.resolve_zone(&layout, "2", None, later).unwrap(), 0);
+
+        assert_eq!(manager.resolve_zone(&layout, "2", Some(2), now).unwrap(), 2);
+        assert!(manager.resolve_zone(&layout, "2", Some(5), now).is_err());
+    }
+}
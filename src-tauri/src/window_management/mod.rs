@@ -0,0 +1,201 @@
+pub mod arrangements;
+pub mod geometry;
+pub mod layouts;
+pub mod x11;
+
+use crate::compositor::{self, Compositor, CompositorWindow};
+use arrangements::{WindowArrangement, WindowArrangementManager};
+use geometry::{FrameChange, LayoutZone, SnapPosition};
+use layouts::{SnapLayout, SnapLayoutManager};
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+use x11::X11Session;
+
+#[tauri::command]
+pub fn snap_active_window(position: SnapPosition) -> Result<(), String> {
+    let compositor = compositor::detect();
+    let window = compositor.active_window()?;
+    let area = compositor.work_area(&window)?;
+    compositor.move_resize(&window.id, &position.rect(&area))
+}
+
+#[tauri::command]
+pub fn create_snap_layout(
+    app: AppHandle,
+    name: String,
+    zones: Vec<LayoutZone>,
+) -> Result<i64, String> {
+    app.state::<SnapLayoutManager>()
+        .create_layout(name, zones)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_snap_layouts(app: AppHandle) -> Result<Vec<SnapLayout>, String> {
+    app.state::<SnapLayoutManager>()
+        .list_layouts()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_snap_layout(
+    app: AppHandle,
+    id: i64,
+    name: String,
+    zones: Vec<LayoutZone>,
+) -> Result<(), String> {
+    app.state::<SnapLayoutManager>()
+        .update_layout(id, name, zones)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_snap_layout(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<SnapLayoutManager>()
+        .delete_layout(id)
+        .map_err(|e| e.to_string())
+}
+
+/// Snaps the active window into a zone of a custom layout. Without a zone,
+/// repeated calls cycle the window through the layout's zones.
+#[tauri::command]
+pub fn snap_active_window_to_layout(
+    app: AppHandle,
+    layout_id: i64,
+    zone: Option<usize>,
+) -> Result<usize, String> {
+    snap_to_layout(&app, layout_id, zone)
+}
+
+pub fn snap_to_layout(
+    app: &AppHandle,
+    layout_id: i64,
+    zone: Option<usize>,
+) -> Result<usize, String> {
+    let manager = app.state::<SnapLayoutManager>();
+    let layout = manager
+        .get_layout(layout_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Layout {} not found", layout_id))?;
+
+    let compositor = compositor::detect();
+    let window = compositor.active_window()?;
+    let index = manager
+        .resolve_zone(&layout, &window.id, zone, Instant::now())
+        .map_err(|e| e.to_string())?;
+
+    let area = compositor.work_area(&window)?;
+    compositor.move_resize(&window.id, &layout.zones[index].rect(&area))?;
+    Ok(index)
+}
+
+/// Monitors shrunk to their work areas, so saved positions are relative to the
+/// usable space and restored windows don't end up under a panel
+fn usable_monitors(session: &X11Session) -> Result<Vec<geometry::Monitor>, String> {
+    Ok(session
+        .work_areas()?
+        .into_iter()
+        .map(|(monitor, area)| geometry::Monitor {
+            rect: area,
+            ..monitor
+        })
+        .collect())
+}
+
+/// Records geometry, monitor and workspace of every visible window under `name`
+#[tauri::command]
+pub fn save_window_layout(app: AppHandle, name: String) -> Result<usize, String> {
+    let session = X11Session::connect()?;
+    let windows = session.list_windows()?;
+    let monitors = usable_monitors(&session)?;
+    let saved = arrangements::capture(&windows, &monitors);
+
+    app.state::<WindowArrangementManager>()
+        .save(&name, &saved)
+        .map_err(|e| e.to_string())?;
+    Ok(saved.len())
+}
+
+/// Moves open windows back to where they were when `name` was saved.
+/// Returns how many windows were moved.
+#[tauri::command]
+pub fn restore_window_layout(app: AppHandle, name: String) -> Result<usize, String> {
+    let arrangement = app
+        .state::<WindowArrangementManager>()
+        .get(&name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Window layout '{}' not found", name))?;
+
+    let session = X11Session::connect()?;
+    let windows = session.list_windows()?;
+    let monitors = usable_monitors(&session)?;
+    let moves = arrangements::plan_restore(&arrangement.windows, &windows, &monitors);
+
+    for planned in &moves {
+        if let Some(desktop) = planned.desktop {
+            session.move_to_desktop(planned.window, desktop)?;
+        }
+        session.move_resize(planned.window, &planned.rect)?;
+    }
+    Ok(moves.len())
+}
+
+#[tauri::command]
+pub fn list_window_layouts(app: AppHandle) -> Result<Vec<WindowArrangement>, String> {
+    app.state::<WindowArrangementManager>()
+        .list()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_window_layout(app: AppHandle, name: String) -> Result<(), String> {
+    app.state::<WindowArrangementManager>()
+        .delete(&name)
+        .map_err(|e| e.to_string())
+}
+
+/// The top window of an application, matched on app_id or WM_CLASS, or
+/// the active window when no application is named
+fn app_window(
+    compositor: &dyn Compositor,
+    app_name: Option<&str>,
+) -> Result<CompositorWindow, String> {
+    let Some(app_name) = app_name else {
+        return compositor.active_window();
+    };
+    compositor
+        .windows()?
+        .into_iter()
+        .rev()
+        .find(|window| window.app_id.eq_ignore_ascii_case(app_name))
+        .ok_or_else(|| format!("No window found for {}", app_name))
+}
+
+pub(crate) fn change_window_frame(
+    app_name: Option<&str>,
+    change: FrameChange,
+) -> Result<(), String> {
+    let compositor = compositor::detect();
+    let window = app_window(compositor.as_ref(), app_name)?;
+    compositor.move_resize(&window.id, &change.apply(&window.rect))
+}
+
+/// App id of the active window, the closest thing to the frontmost process
+pub(crate) fn frontmost_app_name() -> Result<String, String> {
+    crate::context::active_window_info()
+        .map(|window| window.app_id)
+        .ok_or_else(|| "No active window".to_string())
+}
+
+/// Applications with at least one window, by app id
+pub(crate) fn window_app_names() -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = compositor::detect()
+        .windows()?
+        .into_iter()
+        .map(|window| window.app_id)
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.dedup();
+    Ok(names)
+}
@@ -0,0 +1,470 @@
+use super::geometry::{self, Monitor, Rect, Strut, WindowInfo};
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::xproto::{
+    AtomEnum, ClientMessageEvent, ConnectionExt, CreateWindowAux, EventMask, PropMode, Window,
+    WindowClass,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+const NET_WM_STATE_REMOVE: u32 = 0;
+/// `_NET_ACTIVE_WINDOW` source indication for a regular application
+const SOURCE_APPLICATION: u32 = 1;
+
+fn x11_err<E: std::fmt::Display>(e: E) -> String {
+    format!("X11 error: {}", e)
+}
+
+/// A connection to the X server plus the EWMH atoms we need
+pub struct X11Session {
+    conn: RustConnection,
+    root: Window,
+    screen_width: u32,
+    screen_height: u32,
+}
+
+impl X11Session {
+    pub fn connect() -> Result<Self, String> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(x11_err)?;
+        let screen = &conn.setup().roots[screen_num];
+        let root = screen.root;
+        let screen_width = screen.width_in_pixels as u32;
+        let screen_height = screen.height_in_pixels as u32;
+        Ok(Self {
+            conn,
+            root,
+            screen_width,
+            screen_height,
+        })
+    }
+
+    pub fn atom(&self, name: &str) -> Result<u32, String> {
+        Ok(self
+            .conn
+            .intern_atom(false, name.as_bytes())
+            .map_err(x11_err)?
+            .reply()
+            .map_err(x11_err)?
+            .atom)
+    }
+
+    pub fn active_window(&self) -> Result<Window, String> {
+        let atom = self.atom("_NET_ACTIVE_WINDOW")?;
+        let reply = self
+            .conn
+            .get_property(false, self.root, atom, AtomEnum::WINDOW, 0, 1)
+            .map_err(x11_err)?
+            .reply()
+            .map_err(x11_err)?;
+        reply
+            .value32()
+            .and_then(|mut values| values.next())
+            .filter(|&w| w != x11rb::NONE)
+            .ok_or_else(|| "No active window".to_string())
+    }
+
+    fn property32(&self, window: Window, name: &str, kind: AtomEnum) -> Result<Vec<u32>, String> {
+        let atom = self.atom(name)?;
+        let reply = self
+            .conn
+            .get_property(false, window, atom, kind, 0, 1024)
+            .map_err(x11_err)?
+            .reply()
+            .map_err(x11_err)?;
+        Ok(reply
+            .value32()
+            .map(|values| values.collect())
+            .unwrap_or_default())
+    }
+
+    fn property_string(&self, window: Window, name: &str, kind: u32) -> Result<String, String> {
+        let atom = self.atom(name)?;
+        let reply = self
+            .conn
+            .get_property(false, window, atom, kind, 0, 1024)
+            .map_err(x11_err)?
+            .reply()
+            .map_err(x11_err)?;
+        Ok(String::from_utf8_lossy(&reply.value).into_owned())
+    }
+
+    /// Class name part of WM_CLASS, e.g. `firefox`
+    pub fn wm_class(&self, window: Window) -> Result<String, String> {
+        // WM_CLASS is two null-terminated strings: instance name and class name
+        Ok(self
+            .property_string(window, "WM_CLASS", AtomEnum::STRING.into())?
+            .split('\0')
+            .rfind(|part| !part.is_empty())
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    /// `_NET_WM_NAME`, or the legacy `WM_NAME` for clients that don't set it
+    pub fn title(&self, window: Window) -> Result<String, String> {
+        let title = self.property_string(window, "_NET_WM_NAME", self.atom("UTF8_STRING")?)?;
+        if !title.is_empty() {
+            return Ok(title);
+        }
+        self.property_string(window, "WM_NAME", AtomEnum::STRING.into())
+    }
+
+    /// Managed client windows in stacking order, skipping docks, desktops and minimized windows
+    pub fn list_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        let clients = self.property32(self.root, "_NET_CLIENT_LIST", AtomEnum::WINDOW)?;
+        let skipped_types = [
+            self.atom("_NET_WM_WINDOW_TYPE_DOCK")?,
+            self.atom("_NET_WM_WINDOW_TYPE_DESKTOP")?,
+        ];
+        let hidden = self.atom("_NET_WM_STATE_HIDDEN")?;
+
+        let mut windows = Vec::new();
+        for window in clients {
+            // A window closing mid-scan fails its reads with BadWindow; it no
+            // longer needs saving, the others still do
+            match self.window_info(window, &skipped_types, hidden) {
+                Ok(Some(info)) => windows.push(info),
+                Ok(None) => {}
+                Err(e) => tracing::debug!(window, error = %e, "Skipping unreadable window"),
+            }
+        }
+        Ok(windows)
+    }
+
+    /// None for docks, desktops and minimized windows
+    fn window_info(
+        &self,
+        window: Window,
+        skipped_types: &[u32],
+        hidden: u32,
+    ) -> Result<Option<WindowInfo>, String> {
+        let types = self.property32(window, "_NET_WM_WINDOW_TYPE", AtomEnum::ATOM)?;
+        if types.iter().any(|t| skipped_types.contains(t)) {
+            return Ok(None);
+        }
+        let state = self.property32(window, "_NET_WM_STATE", AtomEnum::ATOM)?;
+        if state.contains(&hidden) {
+            return Ok(None);
+        }
+
+        Ok(Some(WindowInfo {
+            id: window,
+            wm_class: self.wm_class(window)?,
+            title: self.title(window)?,
+            rect: self.frame_rect(window)?,
+            desktop: self
+                .property32(window, "_NET_WM_DESKTOP", AtomEnum::CARDINAL)?
+                .first()
+                .copied(),
+        }))
+    }
+
+    /// Outer position of the window frame with the client size, which is what
+    /// `_NET_MOVERESIZE_WINDOW` with NorthWest gravity expects back
+    pub fn frame_rect(&self, window: Window) -> Result<Rect, String> {
+        let geometry = self
+            .conn
+            .get_geometry(window)
+            .map_err(x11_err)?
+            .reply()
+            .map_err(x11_err)?;
+        let origin = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)
+            .map_err(x11_err)?
+            .reply()
+            .map_err(x11_err)?;
+        let extents = self.property32(window, "_NET_FRAME_EXTENTS", AtomEnum::CARDINAL)?;
+        let left = extents.first().copied().unwrap_or(0) as i32;
+        let top = extents.get(2).copied().unwrap_or(0) as i32;
+
+        Ok(Rect::new(
+            origin.dst_x as i32 - left,
+            origin.dst_y as i32 - top,
+            geometry.width as u32,
+            geometry.height as u32,
+        ))
+    }
+
+    pub fn monitors(&self) -> Result<Vec<Monitor>, String> {
+        let reply = self
+            .conn
+            .randr_get_monitors(self.root, true)
+            .map_err(x11_err)?
+            .reply()
+            .map_err(x11_err)?;
+
+        let mut monitors = Vec::new();
+        for info in reply.monitors {
+            let name = self
+                .conn
+                .get_atom_name(info.name)
+                .map_err(x11_err)?
+                .reply()
+                .map_err(x11_err)?
+                .name;
+            monitors.push(Monitor {
+                name: String::from_utf8_lossy(&name).into_owned(),
+                primary: info.primary,
+                rect: Rect::new(
+                    info.x as i32,
+                    info.y as i32,
+                    info.width as u32,
+                    info.height as u32,
+                ),
+            });
+        }
+
+        if monitors.is_empty() {
+            monitors.push(Monitor {
+                name: "default".to_string(),
+                primary: true,
+                rect: Rect::new(0, 0, self.screen_width, self.screen_height),
+            });
+        }
+        Ok(monitors)
+    }
+
+    pub fn move_to_desktop(&self, window: Window, desktop: u32) -> Result<(), String> {
+        let wm_desktop = self.atom("_NET_WM_DESKTOP")?;
+        self.send_root_message(window, wm_desktop, [desktop, 2, 0, 0, 0])
+    }
+
+    /// `_NET_WM_PID`, for clients that set it
+    pub fn pid(&self, window: Window) -> Result<Option<u32>, String> {
+        Ok(self
+            .property32(window, "_NET_WM_PID", AtomEnum::CARDINAL)?
+            .first()
+            .copied())
+    }
+
+    /// Names of the virtual desktops, numbering unnamed ones, and the index of
+    /// the current one
+    pub fn desktops(&self) -> Result<(Vec<String>, Option<u32>), String> {
+        let count = self
+            .property32(self.root, "_NET_NUMBER_OF_DESKTOPS", AtomEnum::CARDINAL)?
+            .first()
+            .copied()
+            .unwrap_or(0);
+        let utf8 = self.atom("UTF8_STRING")?;
+        let names = self.property_string(self.root, "_NET_DESKTOP_NAMES", utf8)?;
+        let mut names = names.split('\0');
+        let desktops = (0..count)
+            .map(|index| {
+                names
+                    .next()
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("Desktop {}", index + 1))
+            })
+            .collect();
+        let current = self
+            .property32(self.root, "_NET_CURRENT_DESKTOP", AtomEnum::CARDINAL)?
+            .first()
+            .copied();
+        Ok((desktops, current))
+    }
+
+    /// Space reserved by panels and docks, read from every client's struts
+    pub fn struts(&self) -> Result<Vec<Strut>, String> {
+        let clients = self.property32(self.root, "_NET_CLIENT_LIST", AtomEnum::WINDOW)?;
+        let mut struts = Vec::new();
+        for window in clients {
+            let mut values =
+                self.property32(window, "_NET_WM_STRUT_PARTIAL", AtomEnum::CARDINAL)?;
+            if values.is_empty() {
+                values = self.property32(window, "_NET_WM_STRUT", AtomEnum::CARDINAL)?;
+            }
+            struts.extend(Strut::from_property(
+                &values,
+                self.screen_width,
+                self.screen_height,
+            ));
+        }
+        Ok(struts)
+    }
+
+    /// `_NET_WORKAREA` for the current desktop. Most WMs publish one rectangle
+    /// spanning all monitors, so it's only a fallback when no struts are visible
+    /// (GNOME's top bar and KDE panels under XWayland don't always expose them).
+    fn net_workarea(&self) -> Result<Option<Rect>, String> {
+        let areas = self.property32(self.root, "_NET_WORKAREA", AtomEnum::CARDINAL)?;
+        let desktop = self
+            .property32(self.root, "_NET_CURRENT_DESKTOP", AtomEnum::CARDINAL)?
+            .first()
+            .copied()
+            .unwrap_or(0) as usize;
+        Ok(areas
+            .chunks_exact(4)
+            .nth(desktop)
+            .or_else(|| areas.chunks_exact(4).next())
+            .map(|a| Rect::new(a[0] as i32, a[1] as i32, a[2], a[3])))
+    }
+
+    /// Usable area of each monitor after panels, docks and bars
+    pub fn work_areas(&self) -> Result<Vec<(Monitor, Rect)>, String> {
+        let monitors = self.monitors()?;
+        let struts = self.struts()?;
+
+        if struts.is_empty() {
+            if let Some(workarea) = self.net_workarea()? {
+                return Ok(monitors
+                    .into_iter()
+                    .map(|monitor| {
+                        let area = intersect(&monitor.rect, &workarea).unwrap_or(monitor.rect);
+                        (monitor, area)
+                    })
+                    .collect());
+            }
+        }
+
+        Ok(monitors
+            .into_iter()
+            .map(|monitor| {
+                let area = geometry::work_area(&monitor.rect, &struts);
+                (monitor, area)
+            })
+            .collect())
+    }
+
+    /// Work area of the monitor that holds most of `window`, or the primary
+    /// one when it's off-screen. X11 only; on Wayland the compositor backends
+    /// ask the compositor for the window's output instead.
+    pub fn work_area_for(&self, window: Window) -> Result<Rect, String> {
+        let frame = self.frame_rect(window)?;
+        let areas = self.work_areas()?;
+        areas
+            .iter()
+            .filter_map(|entry| {
+                let overlap = intersect(&entry.0.rect, &frame)?;
+                Some((overlap.width as u64 * overlap.height as u64, entry))
+            })
+            .max_by_key(|(overlap, _)| *overlap)
+            .map(|(_, entry)| entry)
+            .or_else(|| areas.iter().find(|(monitor, _)| monitor.primary))
+            .or_else(|| areas.first())
+            .map(|(_, area)| *area)
+            .ok_or_else(|| "No monitors found".to_string())
+    }
+
+    /// Moves and resizes a window using `_NET_MOVERESIZE_WINDOW`, un-maximizing it first
+    pub fn move_resize(&self, window: Window, rect: &Rect) -> Result<(), String> {
+        let wm_state = self.atom("_NET_WM_STATE")?;
+        let max_vert = self.atom("_NET_WM_STATE_MAXIMIZED_VERT")?;
+        let max_horz = self.atom("_NET_WM_STATE_MAXIMIZED_HORZ")?;
+        self.send_root_message(
+            window,
+            wm_state,
+            [NET_WM_STATE_REMOVE, max_vert, max_horz, 2, 0],
+        )?;
+
+        let moveresize = self.atom("_NET_MOVERESIZE_WINDOW")?;
+        // NorthWest gravity, x/y/width/height present, source indication = pager
+        let flags = 1 | (0b1111 << 8) | (2 << 12);
+        self.send_root_message(
+            window,
+            moveresize,
+            [flags, rect.x as u32, rect.y as u32, rect.width, rect.height],
+        )?;
+        self.conn.flush().map_err(x11_err)
+    }
+
+    /// Current X server time, read from the PropertyNotify a zero-length
+    /// property append on a throwaway window produces
+    pub fn server_time(&self) -> Result<u32, String> {
+        let window = self.conn.generate_id().map_err(x11_err)?;
+        self.conn
+            .create_window(
+                0,
+                window,
+                self.root,
+                -1,
+                -1,
+                1,
+                1,
+                0,
+                WindowClass::INPUT_ONLY,
+                0,
+                &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+            )
+            .map_err(x11_err)?;
+        let atom = self.atom("_FLARE_TIMESTAMP")?;
+        self.conn
+            .change_property32(PropMode::APPEND, window, atom, AtomEnum::CARDINAL, &[])
+            .map_err(x11_err)?;
+        self.conn.flush().map_err(x11_err)?;
+
+        let time = loop {
+            match self.conn.wait_for_event().map_err(x11_err)? {
+                Event::PropertyNotify(event) if event.window == window => break event.time,
+                _ => continue,
+            }
+        };
+        self.conn.destroy_window(window).map_err(x11_err)?;
+        self.conn.flush().map_err(x11_err)?;
+        Ok(time)
+    }
+
+    /// Asks the window manager to raise and focus `window`. `timestamp` should
+    /// be the time of the user action, or focus stealing prevention may
+    /// refuse it.
+    pub fn activate(&self, window: Window, timestamp: u32) -> Result<(), String> {
+        let user_time = self.atom("_NET_WM_USER_TIME")?;
+        self.conn
+            .change_property32(
+                PropMode::REPLACE,
+                window,
+                user_time,
+                AtomEnum::CARDINAL,
+                &[timestamp],
+            )
+            .map_err(x11_err)?;
+        let active = self.atom("_NET_ACTIVE_WINDOW")?;
+        self.send_root_message(window, active, [SOURCE_APPLICATION, timestamp, 0, 0, 0])?;
+        self.conn.flush().map_err(x11_err)
+    }
+
+    /// Asks KWin to blur what's behind `window`; other window managers
+    /// ignore the property
+    pub fn set_blur_behind(&self, window: Window, enabled: bool) -> Result<(), String> {
+        let atom = self.atom("_KDE_NET_WM_BLUR_BEHIND_REGION")?;
+        if enabled {
+            // An empty region blurs behind the whole window
+            self.conn
+                .change_property32(PropMode::REPLACE, window, atom, AtomEnum::CARDINAL, &[])
+                .map_err(x11_err)?;
+        } else {
+            self.conn.delete_property(window, atom).map_err(x11_err)?;
+        }
+        self.conn.flush().map_err(x11_err)?;
+        Ok(())
+    }
+
+    fn send_root_message(&self, window: Window, kind: u32, data: [u32; 5]) -> Result<(), String> {
+        let event = ClientMessageEvent::new(32, window, kind, data);
+        self.conn
+            .send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )
+            .map_err(x11_err)?;
+        Ok(())
+    }
+}
+
+fn intersect(a: &Rect, b: &Rect) -> Option<Rect> {
+    if !a.intersects(b) {
+        return None;
+    }
+    let x = a.x.max(b.x);
+    let y = a.y.max(b.y);
+    Some(Rect::new(
+        x,
+        y,
+        (a.right().min(b.right()) - x) as u32,
+        (a.bottom().min(b.bottom()) - y) as u32,
+    ))
+}
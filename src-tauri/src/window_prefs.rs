@@ -0,0 +1,264 @@
+//! Size, placement and look of the launcher window. The values in
+//! `tauri.conf.json` are only the first-run defaults; these are applied on
+//! startup and whenever they change.
+//!
+//! Opacity is applied to the whole GTK window. Blur behind the window only
+//! exists as KWin's X11 property; elsewhere it's up to the compositor. No
+//! Linux compositor has vibrancy, so it's only forwarded to the frontend,
+//! which can tint its translucent background instead.
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, LogicalSize, Manager, PhysicalPosition, WebviewWindow};
+
+const MIN_WIDTH: u32 = 500;
+const MIN_HEIGHT: u32 = 300;
+const MIN_OPACITY: f64 = 0.3;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum WindowPlacement {
+    #[default]
+    Center,
+    /// Horizontally centred with the top edge a fifth of the way down, so
+    /// results growing downwards don't shift the search field
+    UpperThird,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WindowPrefs {
+    pub width: u32,
+    pub height: u32,
+    pub placement: WindowPlacement,
+    pub opacity: f64,
+    pub blur: bool,
+    pub vibrancy: bool,
+}
+
+impl Default for WindowPrefs {
+    fn default() -> Self {
+        Self {
+            width: 774,
+            height: 474,
+            placement: WindowPlacement::Center,
+            opacity: 1.0,
+            blur: false,
+            vibrancy: false,
+        }
+    }
+}
+
+impl WindowPrefs {
+    fn get_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+        let data_dir = app
+            .path()
+            .app_local_data_dir()
+            .map_err(|_| AppError::DirectoryNotFound)?;
+
+        fs::create_dir_all(&data_dir)?;
+        Ok(data_dir.join("window_prefs.json"))
+    }
+
+    pub fn load(app: &AppHandle) -> Result<Self, AppError> {
+        let path = Self::get_path(app)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str::<Self>(&content)
+            .map(Self::normalized)
+            .map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(Self::get_path(app)?, content)?;
+        Ok(())
+    }
+
+    /// Keeps the window usable whatever was saved
+    fn normalized(self) -> Self {
+        Self {
+            width: self.width.max(MIN_WIDTH),
+            height: self.height.max(MIN_HEIGHT),
+            opacity: if self.opacity.is_finite() {
+                self.opacity.clamp(MIN_OPACITY, 1.0)
+            } else {
+                1.0
+            },
+            ..self
+        }
+    }
+}
+
+/// Top-left corner of a window on a monitor, all in physical pixels
+fn placement_origin(
+    placement: WindowPlacement,
+    monitor_position: (i32, i32),
+    monitor_size: (u32, u32),
+    window_size: (u32, u32),
+) -> (i32, i32) {
+    let (x, y) = monitor_position;
+    let (monitor_width, monitor_height) = monitor_size;
+    let (width, height) = window_size;
+    let left = x + (monitor_width.saturating_sub(width) / 2) as i32;
+    let top = match placement {
+        WindowPlacement::Center => y + (monitor_height.saturating_sub(height) / 2) as i32,
+        WindowPlacement::UpperThird => y + (monitor_height / 5) as i32,
+    };
+    (left, top)
+}
+
+fn place(window: &WebviewWindow, prefs: &WindowPrefs) -> Result<(), String> {
+    let monitor = match window.current_monitor().map_err(|e| e.to_string())? {
+        Some(monitor) => monitor,
+        None => window
+            .primary_monitor()
+            .map_err(|e| e.to_string())?
+            .ok_or("No monitor found")?,
+    };
+    // The new size may not have reached the window yet
+    let scale = monitor.scale_factor();
+    let size = (
+        (prefs.width as f64 * scale).round() as u32,
+        (prefs.height as f64 * scale).round() as u32,
+    );
+    let (x, y) = placement_origin(
+        prefs.placement,
+        (monitor.position().x, monitor.position().y),
+        (monitor.size().width, monitor.size().height),
+        size,
+    );
+    window
+        .set_position(PhysicalPosition::new(x, y))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn set_opacity(window: &WebviewWindow, opacity: f64) -> Result<(), String> {
+    let target = window.clone();
+    window
+        .run_on_main_thread(move || {
+            use gtk::prelude::WidgetExt;
+            if let Ok(gtk_window) = target.gtk_window() {
+                gtk_window.set_opacity(opacity);
+            }
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_opacity(_window: &WebviewWindow, _opacity: f64) -> Result<(), String> {
+    Ok(())
+}
+
+fn set_blur(window: &WebviewWindow, blur: bool) -> Result<(), String> {
+    if std::env::var("WAYLAND_DISPLAY").is_ok() {
+        return Ok(());
+    }
+    let window_id = crate::activation::x11_window_id(window)?;
+    crate::window_management::x11::X11Session::connect()?.set_blur_behind(window_id, blur)
+}
+
+fn apply(app: &AppHandle, prefs: &WindowPrefs) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+    window
+        .set_size(LogicalSize::new(prefs.width, prefs.height))
+        .map_err(|e| e.to_string())?;
+    // A layer surface gets centred by the compositor and can't be moved
+    if !crate::layer_shell::is_active() {
+        place(&window, prefs)?;
+    }
+    set_opacity(&window, prefs.opacity)?;
+    if let Err(e) = set_blur(&window, prefs.blur) {
+        tracing::debug!(error = %e, "Failed to request blur behind the window");
+    }
+    let _ = app.emit("window-prefs-changed", prefs);
+    Ok(())
+}
+
+/// Applies the saved preferences to the main window during setup
+pub fn init(app: &AppHandle) {
+    let prefs = WindowPrefs::load(app).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Failed to read window preferences");
+        WindowPrefs::default()
+    });
+    if let Err(e) = apply(app, &prefs) {
+        tracing::error!(error = %e, "Failed to apply window preferences");
+    }
+}
+
+#[tauri::command]
+pub fn get_window_prefs(app: AppHandle) -> Result<WindowPrefs, String> {
+    WindowPrefs::load(&app).map_err(|e| e.to_string())
+}
+
+/// Saves and applies `prefs`, returning them as stored after clamping
+#[tauri::command]
+pub fn set_window_prefs(app: AppHandle, prefs: WindowPrefs) -> Result<WindowPrefs, String> {
+    let prefs = prefs.normalized();
+    prefs.save(&app).map_err(|e| e.to_string())?;
+    apply(&app, &prefs)?;
+    Ok(prefs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized_keeps_window_usable() {
+        let prefs = WindowPrefs {
+            width: 100,
+            height: 2000,
+            opacity: 0.0,
+            ..WindowPrefs::default()
+        }
+        .normalized();
+        assert_eq!((prefs.width, prefs.height), (MIN_WIDTH, 2000));
+        assert_eq!(prefs.opacity, MIN_OPACITY);
+
+        let prefs = WindowPrefs {
+            opacity: f64::NAN,
+            ..WindowPrefs::default()
+        }
+        .normalized();
+        assert_eq!(prefs.opacity, 1.0);
+
+        let partial: WindowPrefs = serde_json::from_str(r#"{"width": 900}"#).unwrap();
+        assert_eq!(partial.width, 900);
+        assert_eq!(partial.height, WindowPrefs::default().height);
+    }
+
+    #[test]
+    fn test_placement_origin() {
+        let monitor = ((1920, 0), (2560, 1440));
+        assert_eq!(
+            placement_origin(WindowPlacement::Center, monitor.0, monitor.1, (800, 480)),
+            (1920 + 880, 480)
+        );
+        assert_eq!(
+            placement_origin(
+                WindowPlacement::UpperThird,
+                monitor.0,
+                monitor.1,
+                (800, 480)
+            ),
+            (1920 + 880, 288)
+        );
+        // Larger than the monitor: pinned to its corner
+        assert_eq!(
+            placement_origin(WindowPlacement::Center, (0, 0), (640, 480), (800, 600)),
+            (0, 0)
+        );
+    }
+}
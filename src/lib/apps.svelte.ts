@@ -1,7 +1,17 @@
 import { invoke } from '@tauri-apps/api/core';
 import { frecencyStore } from './frecency.svelte';
 
-export type App = { name: string; comment?: string; exec: string; icon_path?: string };
+export type AppAction = { id: string; name: string; exec: string };
+
+export type App = {
+	id?: string;
+	name: string;
+	comment?: string;
+	exec: string;
+	icon_path?: string;
+	source?: 'native' | 'flatpak' | 'snap' | 'appimage';
+	actions?: AppAction[];
+};
 
 class AppsStore {
 	rawApps = $state<App[]>([]);
@@ -0,0 +1,13 @@
+/**
+ * Bumped when caches are cleared from settings, so icon and asset URLs change
+ * and the webview fetches them again instead of showing what it held on to.
+ */
+class AssetCache {
+	version = $state(0);
+
+	invalidate() {
+		this.version++;
+	}
+}
+
+export const assetCache = new AssetCache();
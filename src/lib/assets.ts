@@ -1,5 +1,6 @@
 import { type ImageLike } from '@flare/protocol';
 import { convertFileSrc } from '@tauri-apps/api/core';
+import { assetCache } from './assetCache.svelte';
 import { mode } from 'mode-watcher';
 import path from 'path';
 import type { ColorLike } from './props';
@@ -18,6 +19,11 @@ const iconIsEmoji = (icon: string) => {
 	return Array.from(graphemes).length === 1 && EMOJI_REGEX.test(icon);
 };
 
+const fileSrc = (filePath: string) => {
+	const src = convertFileSrc(filePath);
+	return assetCache.version > 0 ? `${src}?v=${assetCache.version}` : src;
+};
+
 type ImageMask = 'circle' | 'roundedRectangle';
 
 export type ResolvedIcon =
@@ -51,10 +57,10 @@ export function resolveIcon(
 		}
 
 		if (icon.startsWith('/')) {
-			return { type: 'image', src: convertFileSrc(icon) };
+			return { type: 'image', src: fileSrc(icon) };
 		}
 
-		return { type: 'image', src: convertFileSrc(path.join(assetsBasePath, icon)) };
+		return { type: 'image', src: fileSrc(path.join(assetsBasePath, icon)) };
 	}
 
 	if (typeof icon === 'object' && 'source' in icon) {
@@ -82,9 +88,9 @@ export function resolveIcon(
 		if (source.startsWith('http') || source.startsWith('data:') || source.startsWith('blob:')) {
 			src = source;
 		} else if (source.startsWith('/')) {
-			src = convertFileSrc(source);
+			src = fileSrc(source);
 		} else {
-			src = convertFileSrc(path.join(assetsBasePath, source));
+			src = fileSrc(path.join(assetsBasePath, source));
 		}
 
 		return {
@@ -99,7 +105,7 @@ export function resolveIcon(
 	}
 
 	if (typeof icon === 'object' && 'fileIcon' in icon) {
-		return { type: 'image', src: convertFileSrc(icon.fileIcon) };
+		return { type: 'image', src: fileSrc(icon.fileIcon) };
 	}
 
 	return null;
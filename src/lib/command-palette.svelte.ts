@@ -8,7 +8,7 @@ import { viewManager } from './viewManager.svelte';
 import type { App } from './apps.svelte';
 
 export type UnifiedItem = {
-	type: 'calculator' | 'plugin' | 'app' | 'quicklink';
+	type: 'calculator' | 'plugin' | 'app' | 'quicklink' | 'web-search';
 	id: string;
 	data: any;
 	score: number;
@@ -155,6 +155,16 @@ export function useCommandPaletteItems({
 			});
 		}
 
+		// Unmatched queries fall through to the default (or most used) search engine
+		if (term.trim() && items.length === 0) {
+			items.push({
+				type: 'web-search',
+				id: 'web-search',
+				data: { query: term.trim() },
+				score: 0
+			});
+		}
+
 		return [...new Map(items.map((item) => [item.id, item])).values()];
 	});
 
@@ -208,6 +218,11 @@ export function useCommandPaletteActions({
 				}
 				break;
 			}
+			case 'web-search': {
+				invoke('web_search', { engineId: null, query: item.data.query }).catch(console.error);
+				resetState();
+				break;
+			}
 			case 'quicklink': {
 				const quicklink = item.data as Quicklink;
 				if (quicklink.link.includes('{argument}')) {
@@ -35,7 +35,7 @@ class QuicklinksStore {
 
 	async create(data: { name: string; link: string; application?: string; icon?: string }) {
 		try {
-			await invoke('create_quicklink', data);
+			await invoke('create_quicklink', { input: data });
 			await this.fetchQuicklinks();
 		} catch (e) {
 			console.error('Failed to create quicklink:', e);
@@ -48,7 +48,7 @@ class QuicklinksStore {
 		data: { name: string; link: string; application?: string; icon?: string }
 	) {
 		try {
-			await invoke('update_quicklink', { id, ...data });
+			await invoke('update_quicklink', { id, input: data });
 			await this.fetchQuicklinks();
 		} catch (e) {
 			console.error('Failed to update quicklink:', e);
@@ -7,6 +7,53 @@ import { appCacheDir, appLocalDataDir } from '@tauri-apps/api/path';
 import { listen } from '@tauri-apps/api/event';
 import { imperativeBus } from './imperative.svelte';
 import { inflate } from 'pako';
+import { ask } from '@tauri-apps/plugin-dialog';
+
+type ShimPermissionRequest = {
+	requestId: string;
+	extension: string | null;
+	capability: 'appleScript' | 'shell';
+	detail: string;
+};
+
+const CAPABILITY_LABELS: Record<ShimPermissionRequest['capability'], string> = {
+	appleScript: 'run AppleScript',
+	shell: 'run shell commands'
+};
+
+/** Commands that act as, or on behalf of, the calling extension */
+const GATED_COMMANDS = new Set([
+	'extension_secret_set',
+	'extension_secret_get',
+	'extension_secret_delete',
+	'extension_secret_list',
+	'extension_storage_get',
+	'extension_storage_set',
+	'extension_storage_remove',
+	'extension_storage_all_items',
+	'extension_storage_clear'
+]);
+
+/** Everything the sidecar may invoke on an extension's behalf */
+const SIDECAR_COMMANDS = new Set([
+	...GATED_COMMANDS,
+	'ai_extension_tool_result',
+	'clipboard_clear',
+	'clipboard_copy',
+	'clipboard_paste',
+	'clipboard_read',
+	'clipboard_read_text',
+	'get_applications',
+	'get_default_application',
+	'get_frontmost_application',
+	'get_selected_finder_items',
+	'get_selected_text',
+	'shim_get_system_info',
+	'shim_run_applescript',
+	'shim_translate_path',
+	'show_in_finder',
+	'trash'
+]);
 
 type OauthState = {
 	url: string;
@@ -102,7 +149,46 @@ class SidecarService {
 				this.dispatchEvent('ai-stream-error', event.payload as object);
 			});
 
-			this.#aiEventUnlisten.push(chunkUnlisten, endUnlisten, errorUnlisten);
+			const toolRunUnlisten = await listen('ai-extension-tool-run', (event) => {
+				this.dispatchEvent('run-tool', event.payload as object);
+			});
+
+			const shimPermissionUnlisten = await listen<ShimPermissionRequest>(
+				'shim-permission-request',
+				async (event) => {
+					const request = event.payload;
+					const name =
+						uiStore.currentRunningPlugin?.pluginName === request.extension
+							? uiStore.currentRunningPlugin.pluginTitle
+							: (request.extension ?? 'An unknown caller');
+					const allow = await ask(request.detail, {
+						title: `Allow ${name} to ${CAPABILITY_LABELS[request.capability]}?`,
+						kind: request.capability === 'shell' ? 'warning' : 'info',
+						okLabel: 'Allow',
+						cancelLabel: 'Deny'
+					});
+					const remember =
+						request.extension !== null &&
+						(await ask(`Remember this choice for ${name}?`, {
+							title: 'Remember Choice',
+							okLabel: 'Always',
+							cancelLabel: 'Ask Every Time'
+						}));
+					invoke('shim_permission_respond', {
+						requestId: request.requestId,
+						allow,
+						remember
+					}).catch((error) => this.#log(`ERROR answering shim permission: ${error}`));
+				}
+			);
+
+			this.#aiEventUnlisten.push(
+				chunkUnlisten,
+				endUnlisten,
+				errorUnlisten,
+				toolRunUnlisten,
+				shimPermissionUnlisten
+			);
 		} catch (error) {
 			this.#log(`Error setting up AI event listeners: ${error}`);
 		}
@@ -215,8 +301,21 @@ class SidecarService {
 		if (typedMessage.type === 'invoke_command') {
 			const { requestId, command, params } = typedMessage.payload;
 			const responseType = `invoke_command-response`;
+			if (!SIDECAR_COMMANDS.has(command)) {
+				this.#log(`ERROR: sidecar tried to invoke ${command}`);
+				this.dispatchEvent(responseType, {
+					requestId,
+					error: `Command '${command}' is not available to extensions`
+				});
+				return;
+			}
 			try {
-				const result = await invoke(command, params);
+				// Attributed here rather than by the sidecar, which runs the
+				// extension's own code
+				const args = GATED_COMMANDS.has(command)
+					? { ...params, extension: uiStore.currentRunningPlugin?.pluginName ?? null }
+					: params;
+				const result = await invoke(command, args);
 				this.dispatchEvent(responseType, { requestId, result });
 			} catch (error) {
 				const errorMessage = error instanceof Error ? error.message : String(error);
@@ -3,6 +3,7 @@ import type { Command } from '@flare/protocol';
 import type { PluginInfo } from '@flare/protocol';
 import type { KeyboardShortcut } from '$lib/props/actions';
 import { SvelteMap } from 'svelte/reactivity';
+import { invoke } from '@tauri-apps/api/core';
 
 export type Toast = {
 	id: number;
@@ -278,6 +279,10 @@ function createUiStore() {
 		setCurrentPreferences,
 		setCurrentRunningPlugin(plugin: PluginInfo | null) {
 			currentRunningPlugin = plugin;
+			// Shim calls are attributed to this extension by the backend
+			invoke('shim_set_running_extension', { extension: plugin?.pluginName ?? null }).catch(
+				(error) => console.error('Failed to record the running extension:', error)
+			);
 		},
 		resetForNewPlugin
 	};
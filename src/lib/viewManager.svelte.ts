@@ -7,6 +7,13 @@ import { extensionsStore } from './components/extensions/store.svelte';
 import { fetch } from '@tauri-apps/plugin-http';
 import { ExtensionSchema, type Extension } from '$lib/store';
 
+const POWER_ACTIONS: Record<string, string> = {
+	'builtin:shut-down': 'shutDown',
+	'builtin:restart': 'restart',
+	'builtin:sleep': 'sleep',
+	'builtin:log-out': 'logOut'
+};
+
 export type Snippet = {
 	id: number;
 	name: string;
@@ -125,6 +132,15 @@ class ViewManager {
 			case 'builtin:ai-chat':
 				this.showAiChat();
 				return;
+			case 'builtin:shut-down':
+			case 'builtin:restart':
+			case 'builtin:sleep':
+			case 'builtin:log-out':
+				// The backend asks for confirmation first
+				invoke('system_power', { action: POWER_ACTIONS[plugin.pluginPath] }).catch((e) =>
+					console.error('Power command failed:', e)
+				);
+				return;
 		}
 
 		uiStore.setCurrentRunningPlugin(plugin);